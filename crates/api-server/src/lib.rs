@@ -4,6 +4,7 @@
 //! - RESTful endpoints for tab management
 //! - IP rotation and validation endpoints
 //! - Proxy configuration management
+//! - Background proxy validation controls and throughput benchmarks
 //! - Health check and monitoring endpoints
 
 use anyhow::Result;
@@ -13,18 +14,26 @@ use axum::{
     routing::{get, post},
     Json, Router,
 };
-use browser_core::TabIPManager;
+use browser_core::local_proxy::LocalProxyServer;
+use browser_core::{
+    ChromiumDnsProbe, ChromiumEngine, ChromiumIceSource, TabIPManager, ThroughputResult, ValidationScheduler,
+    ValidationSchedulerStatus,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, RwLock};
 use tracing::{info, error};
-use virtual_ip::{Country, IPGenerator, IPValidator, VirtualIP};
+use virtual_ip::{Country, GeoIpDatabase, IPGenerator, IPValidator, LeakCheckConfig, VirtualIP};
 
 #[derive(Clone)]
 /// Represents a ApiServer.
 pub struct ApiServer {
     tab_manager: Arc<Mutex<TabIPManager>>,
     ip_generator: Arc<IPGenerator>,
+    geoip: Option<Arc<GeoIpDatabase>>,
+    leak_config: LeakCheckConfig,
+    leak_browser: Arc<RwLock<Option<ChromiumEngine>>>,
+    validation_scheduler: Option<Arc<ValidationScheduler>>,
 }
 
 impl ApiServer {
@@ -33,9 +42,39 @@ impl ApiServer {
         Self {
             tab_manager,
             ip_generator,
+            geoip: None,
+            leak_config: LeakCheckConfig::default(),
+            leak_browser: Arc::new(RwLock::new(None)),
+            validation_scheduler: None,
         }
     }
 
+    /// Use a local GeoIP database for validation lookups.
+    pub fn with_geoip(mut self, geoip: Arc<GeoIpDatabase>) -> Self {
+        self.geoip = Some(geoip);
+        self
+    }
+
+    /// STUN servers and DNS probe zone used by the WebRTC and DNS leak checks.
+    pub fn with_leak_config(mut self, config: LeakCheckConfig) -> Self {
+        self.leak_config = config;
+        self
+    }
+
+    /// Browser whose pages gather WebRTC candidates through each tab's exit.
+    ///
+    /// Until the engine is running, WebRTC checks report that they could not run.
+    pub fn with_leak_browser(mut self, engine: Arc<RwLock<Option<ChromiumEngine>>>) -> Self {
+        self.leak_browser = engine;
+        self
+    }
+
+    /// Expose start/stop/status controls for a background validation scheduler.
+    pub fn with_validation_scheduler(mut self, scheduler: Arc<ValidationScheduler>) -> Self {
+        self.validation_scheduler = Some(scheduler);
+        self
+    }
+
     /// Performs router operation.
     pub async fn router(self: Arc<Self>) -> Router {
         Router::new()
@@ -49,6 +88,11 @@ impl ApiServer {
             .route("/api/tabs/:id/validate", get(validate_ip_handler))
             // Country endpoints
             .route("/api/countries", get(list_countries_handler))
+            // Validation scheduler endpoints
+            .route("/api/validation/status", get(validation_status_handler))
+            .route("/api/validation/start", post(start_validation_handler))
+            .route("/api/validation/stop", post(stop_validation_handler))
+            .route("/api/validation/benchmark", post(benchmark_proxy_handler))
             .with_state(self)
     }
 
@@ -146,17 +190,53 @@ async fn validate_ip_handler(
     State(state): State<Arc<ApiServer>>,
     Path(id): Path<String>,
 ) -> Result<Json<ValidationResponse>, StatusCode> {
-    let manager = state.tab_manager.lock().await;
-    let tab = manager.get_tab(&id).await.ok_or(StatusCode::NOT_FOUND)?;
+    // Validation drives a browser and waits on the network, so don't hold up other tab requests
+    let (tab, proxy) = {
+        let manager = state.tab_manager.lock().await;
+        let tab = manager.get_tab(&id).await.ok_or(StatusCode::NOT_FOUND)?;
+        let proxy = manager.exit_proxy(&id).await.map(|p| p.to_proxy_settings());
+        (tab, proxy)
+    };
 
-    let validator = IPValidator::new();
-    let report = validator
-        .validate_comprehensive(&tab.virtual_ip)
-        .await
-        .map_err(|e| {
-            error!("Failed to validate IP for tab '{}': {}", id, e);
+    // The public IP is detected through the tab's exit; reqwest needs a relay for SSH and Shadowsocks
+    let relay = match &proxy {
+        Some(settings) if settings.is_tunnel_protocol() => Some(
+            LocalProxyServer::start_relay(settings.clone()).await.map_err(|e| {
+                error!("Failed to start relay for tab '{}': {}", id, e);
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?,
+        ),
+        _ => None,
+    };
+    let proxy_url = match &relay {
+        Some((_, addr)) => Some(format!("http://{}", addr)),
+        None => proxy.as_ref().and_then(|p| p.to_url()),
+    };
+
+    // Candidates and DNS lookups come from a Chromium page on the tab's exit, not this process
+    let ice_source = ChromiumIceSource::for_profile(state.leak_browser.clone(), proxy.clone(), tab.fingerprint.clone());
+    let dns_source = ChromiumDnsProbe::for_profile(state.leak_browser.clone(), proxy, tab.fingerprint.clone());
+    let mut validator = IPValidator::new()
+        .with_leak_config(state.leak_config.clone())
+        .with_ice_source(Arc::new(ice_source))
+        .with_dns_source(Arc::new(dns_source));
+    if let Some(url) = &proxy_url {
+        validator = validator.with_proxy(url).map_err(|e| {
+            error!("Invalid exit proxy for tab '{}': {}", id, e);
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
+    }
+    if let Some(geoip) = &state.geoip {
+        validator = validator.with_geoip(geoip.clone());
+    }
+    let report = validator.validate_comprehensive(&tab.virtual_ip).await;
+    if let Some((server, _)) = relay {
+        let _ = server.stop().await;
+    }
+    let report = report.map_err(|e| {
+        error!("Failed to validate IP for tab '{}': {}", id, e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
 
     Ok(Json(ValidationResponse::from(report)))
 }
@@ -173,6 +253,56 @@ async fn list_countries_handler(
     ))
 }
 
+fn validation_scheduler(state: &ApiServer) -> Result<&Arc<ValidationScheduler>, StatusCode> {
+    state
+        .validation_scheduler
+        .as_ref()
+        .ok_or(StatusCode::SERVICE_UNAVAILABLE)
+}
+
+async fn validation_status_handler(
+    State(state): State<Arc<ApiServer>>,
+) -> Result<Json<ValidationSchedulerStatus>, StatusCode> {
+    let scheduler = validation_scheduler(&state)?;
+    Ok(Json(scheduler.status().await))
+}
+
+async fn start_validation_handler(
+    State(state): State<Arc<ApiServer>>,
+) -> Result<Json<ValidationSchedulerStatus>, StatusCode> {
+    let scheduler = validation_scheduler(&state)?;
+    if !scheduler.start() {
+        info!("Validation scheduler already running");
+    }
+    Ok(Json(scheduler.status().await))
+}
+
+async fn stop_validation_handler(
+    State(state): State<Arc<ApiServer>>,
+) -> Result<Json<ValidationSchedulerStatus>, StatusCode> {
+    let scheduler = validation_scheduler(&state)?;
+    scheduler.stop();
+    Ok(Json(scheduler.status().await))
+}
+
+#[derive(Deserialize)]
+struct BenchmarkRequest {
+    ip: String,
+    port: u16,
+}
+
+async fn benchmark_proxy_handler(
+    State(state): State<Arc<ApiServer>>,
+    Json(payload): Json<BenchmarkRequest>,
+) -> Result<Json<ThroughputResult>, StatusCode> {
+    let scheduler = validation_scheduler(&state)?;
+    let result = scheduler.benchmark(&payload.ip, payload.port).await.map_err(|e| {
+        error!("Failed to benchmark proxy {}:{}: {}", payload.ip, payload.port, e);
+        StatusCode::BAD_GATEWAY
+    })?;
+    Ok(Json(result))
+}
+
 // ========= DTOs =========
 
 #[derive(Serialize, Deserialize)]
@@ -265,16 +395,24 @@ pub struct ValidationResponse {
     pub ip_matches: bool,
     pub webrtc_secure: bool,
     pub dns_secure: bool,
+    pub country_matches: Option<bool>,
     pub overall_pass: bool,
+    pub webrtc_leaked_ips: Vec<String>,
+    pub dns_leaked_resolvers: Vec<String>,
+    pub notes: Vec<String>,
 }
 
 impl From<virtual_ip::ValidationReport> for ValidationResponse {
     fn from(r: virtual_ip::ValidationReport) -> Self {
         Self {
             ip_matches: r.ip_matches,
-            webrtc_secure: !r.webrtc_leaks,
+            webrtc_secure: r.webrtc_secure,
             dns_secure: r.dns_secure,
+            country_matches: r.country_matches,
             overall_pass: r.overall_pass,
+            webrtc_leaked_ips: r.webrtc_leaked_ips,
+            dns_leaked_resolvers: r.dns_leaked_resolvers,
+            notes: r.notes,
         }
     }
 }
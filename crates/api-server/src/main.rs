@@ -4,20 +4,44 @@
 //! - RESTful API endpoints for proxy management
 //! - Tab and IP management integration
 //! - Virtual IP generation and rotation
+//! - Background proxy validation
 
 use std::env;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
 
 use api_server::ApiServer;
-use browser_core::TabIPManager;
+use browser_core::{
+    AppConfig,
+    ChromiumEngine,
+    ChromiumEngineConfig,
+    ConfigManager,
+    ExitBindingConfig,
+    FreeIpProviderManager,
+    GeoVerificationConfig,
+    GeoVerifier,
+    ProxyManager,
+    ProxyQuarantineManager,
+    ProxyValidator,
+    ProxyValidatorConfig,
+    PublicIpExitVerifier,
+    TabIPManager,
+    TamperCheckConfig,
+    ValidationScheduler,
+    ValidationSchedulerConfig,
+};
 use virtual_ip::{
     demo_generator,
     load_countries_from_file,
     load_ip_ranges,
-    load_ip_ranges_from_file,
+    load_range_index,
     CountryDatabase,
+    GeoIpDatabase,
     IPGenerator,
+    IpRangeIndex,
+    LeakCheckConfig,
 };
 
 #[tokio::main]
@@ -32,22 +56,100 @@ async fn main() -> anyhow::Result<()> {
         .map(load_countries_from_file)
         .unwrap_or_else(CountryDatabase::load_all_countries);
 
-    let ranges = ip_ranges_path
-        .as_deref()
-        .map(std::path::Path::new)
-        .map(load_ip_ranges_from_file)
-        .unwrap_or_else(load_ip_ranges);
-    let ip_generator: IPGenerator = if countries.is_empty() || ranges.is_empty() {
+    // Only real range data is indexed to correct provider countries; the
+    // generator alone falls back to the placeholder ranges
+    let loaded = ip_ranges_path.as_deref().and_then(|path| {
+        load_range_index(std::path::Path::new(path), &countries)
+            .map_err(|e| warn!("IP ranges in {} not loaded: {:#}", path, e))
+            .ok()
+    });
+    let (ranges, range_index) = match loaded {
+        Some((ranges, index)) => (ranges, Some(Arc::new(index))),
+        None => (load_ip_ranges(), None),
+    };
+    // Optional offline GeoIP databases (a directory of .mmdb/.csv files).
+    let mut geoip = None;
+    if let Ok(geoip_dir) = env::var("GEOIP_DB_DIR") {
+        match GeoIpDatabase::open_dir(std::path::Path::new(&geoip_dir)) {
+            Ok(db) => {
+                let db = Arc::new(db);
+                db.spawn_refresh_task(Duration::from_secs(3600));
+                geoip = Some(db);
+            }
+            Err(e) => warn!("GeoIP databases in {} not loaded: {}", geoip_dir, e),
+        }
+    }
+
+    let mut ip_generator: IPGenerator = if countries.is_empty() || ranges.is_empty() {
         demo_generator()
     } else {
         IPGenerator::new(countries, ranges)
     };
+    // Generated IPs take their city, region and timezone from the GeoIP record
+    if let Some(db) = &geoip {
+        ip_generator = ip_generator.with_geoip_database(db.clone());
+    }
+
+    // Optional app config (TOML or JSON), e.g. for user-defined providers
+    let config = load_app_config().await;
+    let mut free_ip_manager = FreeIpProviderManager::from_config(&config.proxy)?;
+    if let Some(index) = &range_index {
+        free_ip_manager = free_ip_manager.with_range_index(index.clone());
+    }
+    let provider_manager = Arc::new(RwLock::new(free_ip_manager));
+
+    // Tabs are backed by verified exits from the proxy pool when one matches;
+    // FETCH_PROXIES_ON_STARTUP=1 fills the pool from the providers at startup
+    let proxy_manager = Arc::new(ProxyManager::new());
+    if env_flag("FETCH_PROXIES_ON_STARTUP") {
+        spawn_proxy_fetch_task(proxy_manager.clone(), provider_manager);
+    }
+    let tab_manager = Arc::new(Mutex::new(TabIPManager::new(ip_generator.clone()).with_exit_verifier(
+        proxy_manager.proxy_pool(),
+        Arc::new(exit_verifier(geoip.clone(), range_index.clone())),
+        ExitBindingConfig::default(),
+    )));
+    let mut server = ApiServer::new(tab_manager, Arc::new(ip_generator));
 
-    // Create TabIPManager with in-memory storage (no database)
-    let tab_manager = Arc::new(Mutex::new(
-        TabIPManager::new(ip_generator.clone())
+    if let Some(db) = &geoip {
+        server = server.with_geoip(db.clone());
+    }
+
+    // Leak checks: comma-separated STUN servers, a "whoami" DNS probe zone and trusted resolvers.
+    server = server.with_leak_config(leak_config_from_env());
+    // Proxies caught tampering or rejecting credentials are set aside
+    let quarantine = Arc::new(ProxyQuarantineManager::new(
+        3,
+        Duration::from_secs(300),
+        Duration::from_secs(24 * 3600),
     ));
-    let server = ApiServer::new(tab_manager, Arc::new(ip_generator));
+
+    // WebRTC candidates are gathered in a headless browser once it has started;
+    // LEAK_BROWSER=1 launches it, otherwise WebRTC checks report they could not run
+    let leak_browser = Arc::new(RwLock::new(None));
+    if env_flag("LEAK_BROWSER") {
+        spawn_leak_browser(leak_browser.clone(), quarantine.clone());
+    }
+    server = server.with_leak_browser(leak_browser);
+
+    // Background validation of the proxy pool; idle until started via /api/validation/start.
+    // Working proxies are also checked for content injection and TLS interception.
+    let mut scheduler = ValidationScheduler::new(
+        ProxyValidator::new(ProxyValidatorConfig::default()).with_tamper_checks(TamperCheckConfig::default()),
+        quarantine,
+        proxy_manager.proxy_pool(),
+        ValidationSchedulerConfig::default(),
+    );
+    // Exits are located offline; the GeoIP APIs are only asked when configured
+    let mut geo_verifier = GeoVerifier::new(GeoVerificationConfig::from_env())?;
+    if let Some(db) = geoip {
+        geo_verifier = geo_verifier.with_geoip_database(db);
+    }
+    if let Some(index) = range_index {
+        geo_verifier = geo_verifier.with_range_index(index);
+    }
+    scheduler = scheduler.with_geo_verifier(geo_verifier);
+    server = server.with_validation_scheduler(Arc::new(scheduler));
 
     let port: u16 = env::var("PORT")
         .ok()
@@ -56,3 +158,80 @@ async fn main() -> anyhow::Result<()> {
 
     server.run(port).await
 }
+
+/// Load the config file named by `CONFIG_PATH`, or the defaults
+async fn load_app_config() -> AppConfig {
+    let manager = match env::var("CONFIG_PATH") {
+        Ok(path) => ConfigManager::with_path(path),
+        Err(_) => ConfigManager::new(),
+    };
+    if let Err(e) = manager.load().await {
+        warn!("Config not loaded, using defaults: {}", e);
+    }
+    manager.get().await
+}
+
+/// Observe tab exits through the proxy, locating them offline where possible
+fn exit_verifier(geoip: Option<Arc<GeoIpDatabase>>, range_index: Option<Arc<IpRangeIndex>>) -> PublicIpExitVerifier {
+    let mut verifier = PublicIpExitVerifier::new();
+    if let Some(db) = geoip {
+        verifier = verifier.with_geoip_database(db);
+    }
+    if let Some(index) = range_index {
+        verifier = verifier.with_range_index(index);
+    }
+    verifier
+}
+
+/// Fill the shared proxy pool from the providers in the background
+fn spawn_proxy_fetch_task(proxy_manager: Arc<ProxyManager>, provider_manager: Arc<RwLock<FreeIpProviderManager>>) {
+    tokio::spawn(async move {
+        info!("Fetching free proxies on startup...");
+        let proxies = FreeIpProviderManager::fetch_shared(&provider_manager).await;
+        info!("Fetched {} proxies", proxies.len());
+        proxy_manager.add_free_proxies(proxies.clone()).await;
+        // Tabs only bind to proxies the pool marks as working
+        let checked = FreeIpProviderManager::validate_shared(&provider_manager, proxies).await;
+        proxy_manager.record_checks(&checked).await;
+    });
+}
+
+/// Launch the headless Chromium used for WebRTC leak checks in the background
+fn spawn_leak_browser(slot: Arc<RwLock<Option<ChromiumEngine>>>, quarantine: Arc<ProxyQuarantineManager>) {
+    tokio::spawn(async move {
+        let mut engine = ChromiumEngine::new(ChromiumEngineConfig {
+            headless: true,
+            ..ChromiumEngineConfig::default()
+        })
+        .with_quarantine_manager(quarantine);
+        match engine.launch().await {
+            Ok(()) => *slot.write().await = Some(engine),
+            Err(e) => warn!("Chromium not started, WebRTC leak checks disabled: {}", e),
+        }
+    });
+}
+
+fn leak_config_from_env() -> LeakCheckConfig {
+    let list = |name: &str| -> Vec<String> {
+        env::var(name)
+            .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+            .unwrap_or_default()
+    };
+
+    let mut config = LeakCheckConfig::default();
+    let stun_servers = list("LEAK_STUN_SERVERS");
+    if !stun_servers.is_empty() {
+        config.stun_servers = stun_servers;
+    }
+    config.dns_probe_domain = env::var("LEAK_DNS_PROBE_DOMAIN").ok();
+    config.dns_probe_report_url = env::var("LEAK_DNS_PROBE_REPORT_URL").ok();
+    config.dns_nameservers = list("LEAK_DNS_NAMESERVERS").iter().filter_map(|s| s.parse().ok()).collect();
+    config.trusted_resolvers = list("LEAK_TRUSTED_RESOLVERS").iter().filter_map(|s| s.parse().ok()).collect();
+    config.report_local_candidates = env_flag("LEAK_REPORT_LOCAL_CANDIDATES");
+    config
+}
+
+/// Whether an opt-in environment flag is set to `1` or `true`
+fn env_flag(name: &str) -> bool {
+    env::var(name).is_ok_and(|v| v == "1" || v == "true")
+}
@@ -0,0 +1,356 @@
+//! Backconnect Gateway Module
+//!
+//! Rotating residential gateways that take targeting in the proxy username:
+//! - Username templates with `{country}`, `{COUNTRY}`, `{city}` and `{session}` placeholders
+//! - Optional `[...]` sections, dropped when a placeholder inside them has no value
+//! - Rotation by issuing a new session token instead of switching hosts
+//! - Exit IP checks after each rotation
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::free_ip_providers::ProxyProvider;
+use crate::http_client::HttpClient;
+use crate::proxy::{FreeProxy, ProxySettings, ProxyType};
+
+/// New sessions tried per rotation before accepting an unchanged exit IP
+const MAX_ROTATION_ATTEMPTS: usize = 3;
+
+fn default_protocol() -> ProxyType {
+    ProxyType::Http
+}
+
+fn default_session_length() -> usize {
+    8
+}
+
+fn default_ip_check_url() -> String {
+    "https://api.ipify.org?format=json".to_string()
+}
+
+/// Configuration for a backconnect gateway
+///
+/// ```toml
+/// [[proxy.backconnect_gateways]]
+/// name = "residential"
+/// host = "gw.example.com"
+/// port = 7000
+/// username_template = "customer-acme[-country-{country}][-city-{city}]-session-{session}"
+/// password = "secret"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BackconnectGateway {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    #[serde(default = "default_protocol")]
+    pub protocol: ProxyType,
+    pub username_template: String,
+    #[serde(default)]
+    pub password: String,
+    /// Length of generated session tokens
+    #[serde(default = "default_session_length")]
+    pub session_length: usize,
+    /// Endpoint returning the caller's IP as `{"ip": ...}` or plain text
+    #[serde(default = "default_ip_check_url")]
+    pub ip_check_url: String,
+    /// Country used when a request carries no targeting
+    #[serde(default)]
+    pub default_country: Option<String>,
+}
+
+/// Geographic targeting for a gateway session
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GatewayTarget {
+    /// ISO 3166-1 alpha-2 country code
+    pub country: Option<String>,
+    pub city: Option<String>,
+}
+
+impl GatewayTarget {
+    /// Target a country
+    pub fn country(code: impl Into<String>) -> Self {
+        Self {
+            country: Some(code.into()),
+            city: None,
+        }
+    }
+
+    /// Narrow the target to a city
+    pub fn with_city(mut self, city: impl Into<String>) -> Self {
+        self.city = Some(city.into());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Country,
+    CountryUpper,
+    City,
+    Session,
+}
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Text(String),
+    Field(Field),
+    Optional(Vec<Segment>),
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>> {
+    let mut stack: Vec<Vec<Segment>> = vec![Vec::new()];
+    let mut text = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let name: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                let field = match name.as_str() {
+                    "country" => Field::Country,
+                    "COUNTRY" => Field::CountryUpper,
+                    "city" => Field::City,
+                    "session" => Field::Session,
+                    other => return Err(anyhow!("Unknown placeholder '{{{}}}' in username template", other)),
+                };
+                let current = stack.last_mut().expect("template stack is never empty");
+                if !text.is_empty() {
+                    current.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                current.push(Segment::Field(field));
+            }
+            '[' => {
+                if stack.len() > 1 {
+                    return Err(anyhow!("Nested optional sections are not supported"));
+                }
+                if !text.is_empty() {
+                    stack[0].push(Segment::Text(std::mem::take(&mut text)));
+                }
+                stack.push(Vec::new());
+            }
+            ']' => {
+                if stack.len() == 1 {
+                    return Err(anyhow!("Unmatched ']' in username template"));
+                }
+                let mut section = stack.pop().expect("checked above");
+                if !text.is_empty() {
+                    section.push(Segment::Text(std::mem::take(&mut text)));
+                }
+                stack[0].push(Segment::Optional(section));
+            }
+            c => text.push(c),
+        }
+    }
+
+    if stack.len() > 1 {
+        return Err(anyhow!("Unclosed '[' in username template"));
+    }
+    let mut segments = stack.pop().expect("template stack is never empty");
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+fn contains_field(segments: &[Segment], field: Field) -> bool {
+    segments.iter().any(|s| match s {
+        Segment::Field(f) => *f == field,
+        Segment::Optional(inner) => contains_field(inner, field),
+        Segment::Text(_) => false,
+    })
+}
+
+/// Generates session-scoped proxies for a backconnect gateway
+pub struct BackconnectProvider {
+    gateway: BackconnectGateway,
+    template: Vec<Segment>,
+}
+
+impl BackconnectProvider {
+    /// Creates a provider, checking the gateway and its username template
+    pub fn new(gateway: BackconnectGateway) -> Result<Self> {
+        if gateway.name.trim().is_empty() {
+            return Err(anyhow!("Gateway name cannot be empty"));
+        }
+        if gateway.host.trim().is_empty() || gateway.port == 0 {
+            return Err(anyhow!("Gateway '{}' needs a host and port", gateway.name));
+        }
+        if gateway.protocol == ProxyType::Direct {
+            return Err(anyhow!("Gateway '{}' cannot use a direct connection", gateway.name));
+        }
+        if gateway.session_length == 0 {
+            return Err(anyhow!("Gateway '{}' session length must be positive", gateway.name));
+        }
+        let template = parse_template(&gateway.username_template)
+            .map_err(|e| anyhow!("Gateway '{}': {}", gateway.name, e))?;
+        if !contains_field(&template, Field::Session) {
+            return Err(anyhow!("Gateway '{}' username template needs a {{session}} placeholder", gateway.name));
+        }
+        Ok(Self { gateway, template })
+    }
+
+    /// The gateway this provider was created from
+    pub fn gateway(&self) -> &BackconnectGateway {
+        &self.gateway
+    }
+
+    /// Generates a fresh session token
+    pub fn new_session(&self) -> String {
+        rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(self.gateway.session_length)
+            .map(|b| (b as char).to_ascii_lowercase())
+            .collect()
+    }
+
+    fn resolve_country(&self, target: &GatewayTarget) -> Option<String> {
+        target
+            .country
+            .clone()
+            .or_else(|| self.gateway.default_country.clone())
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty())
+    }
+
+    /// Renders `segments` into `out`; with `strict`, a missing value fails the whole run
+    fn render(&self, segments: &[Segment], target: &GatewayTarget, session: &str, strict: bool, out: &mut String) -> bool {
+        let country = self.resolve_country(target);
+        for segment in segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Field(field) => {
+                    let value = match field {
+                        Field::Country => country.as_ref().map(|c| c.to_ascii_lowercase()),
+                        Field::CountryUpper => country.as_ref().map(|c| c.to_ascii_uppercase()),
+                        Field::City => target
+                            .city
+                            .as_ref()
+                            .map(|c| c.trim().to_lowercase().replace(char::is_whitespace, "_"))
+                            .filter(|c| !c.is_empty()),
+                        Field::Session => Some(session.to_string()),
+                    };
+                    match value {
+                        Some(value) => out.push_str(&value),
+                        None if strict => return false,
+                        None => {}
+                    }
+                }
+                Segment::Optional(inner) => {
+                    let mut section = String::new();
+                    if self.render(inner, target, session, true, &mut section) {
+                        out.push_str(&section);
+                    }
+                }
+            }
+        }
+        true
+    }
+
+    /// Renders the username for a target and session
+    ///
+    /// Placeholders without a value outside an optional section render as empty.
+    pub fn username_for(&self, target: &GatewayTarget, session: &str) -> String {
+        let mut username = String::new();
+        self.render(&self.template, target, session, false, &mut username);
+        username
+    }
+
+    /// Proxy settings for a target and session
+    pub fn settings_for(&self, target: &GatewayTarget, session: &str) -> ProxySettings {
+        self.proxy_for(target, session).to_proxy_settings()
+    }
+
+    /// A pool entry for a target and session
+    pub fn proxy_for(&self, target: &GatewayTarget, session: &str) -> FreeProxy {
+        let country = self.resolve_country(target).map(|c| c.to_ascii_uppercase());
+        FreeProxy {
+            ip: self.gateway.host.clone(),
+            port: self.gateway.port,
+            protocol: self.gateway.protocol.clone(),
+            country: country.clone().unwrap_or_else(|| "Unknown".to_string()),
+            country_code: country.unwrap_or_else(|| "XX".to_string()),
+            anonymity: "elite".to_string(),
+            speed: 0,
+            uptime: 0.0,
+            last_checked: chrono::Utc::now().to_rfc3339(),
+            provider: self.gateway.name.clone(),
+            is_working: true,
+            username: Some(self.username_for(target, session)),
+            password: Some(self.gateway.password.clone()).filter(|p| !p.is_empty()),
+            ssh: None,
+        }
+    }
+
+    /// Requests the exit IP through `proxy`
+    pub async fn check_exit_ip(&self, proxy: &FreeProxy) -> Result<String> {
+        let client = HttpClient::with_proxy(&proxy.to_proxy_settings())?;
+        let body = client.get(&self.gateway.ip_check_url).await?;
+
+        #[derive(Deserialize)]
+        struct IpResponse {
+            ip: String,
+        }
+
+        let ip = match serde_json::from_str::<IpResponse>(&body) {
+            Ok(response) => response.ip,
+            Err(_) => body.trim().to_string(),
+        };
+        ip.parse::<std::net::IpAddr>()
+            .map_err(|_| anyhow!("Exit IP check returned '{}'", ip))?;
+        Ok(ip)
+    }
+
+    /// Starts a new session and verifies its exit IP
+    ///
+    /// Retries with fresh sessions while the exit IP matches `previous_exit_ip`,
+    /// then settles for the last session if the gateway keeps returning it.
+    pub async fn rotate(&self, target: &GatewayTarget, previous_exit_ip: Option<&str>) -> Result<(FreeProxy, String)> {
+        let mut last = None;
+        let mut last_error = None;
+
+        for attempt in 1..=MAX_ROTATION_ATTEMPTS {
+            let proxy = self.proxy_for(target, &self.new_session());
+            match self.check_exit_ip(&proxy).await {
+                Ok(ip) if Some(ip.as_str()) != previous_exit_ip => return Ok((proxy, ip)),
+                Ok(ip) => {
+                    debug!("Gateway {} session {} kept exit IP {}", self.gateway.name, attempt, ip);
+                    last = Some((proxy, ip));
+                }
+                Err(e) => {
+                    debug!("Exit IP check through gateway {} failed: {}", self.gateway.name, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        match last {
+            Some(result) => {
+                warn!("Gateway {} did not change exit IP after {} sessions", self.gateway.name, MAX_ROTATION_ATTEMPTS);
+                Ok(result)
+            }
+            None => Err(last_error.unwrap_or_else(|| anyhow!("Exit IP check failed"))),
+        }
+    }
+}
+
+#[async_trait]
+impl ProxyProvider for BackconnectProvider {
+    fn name(&self) -> &str {
+        &self.gateway.name
+    }
+
+    fn rate_limit(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// A single entry for the gateway's default target with a fresh session
+    async fn fetch_proxies(&self) -> Result<Vec<FreeProxy>> {
+        Ok(vec![self.proxy_for(&GatewayTarget::default(), &self.new_session())])
+    }
+}
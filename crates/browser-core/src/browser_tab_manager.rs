@@ -18,6 +18,8 @@ use serde::{Serialize, Deserialize};
 use chrono::{DateTime, Utc};
 use tauri::State;
 
+use crate::chromium_engine::ChromiumEngineConfig;
+use crate::fingerprint_lint::{FingerprintLinter, LintReport, LintSeverity, ENGINE_RULE, VIEWPORT_RULE};
 use crate::tab_manager::TabIPManager;
 use crate::webview_manager::{WebviewManager, WebviewTab};
 use crate::tab_isolation::TabProfile;
@@ -39,6 +41,9 @@ pub struct BrowserTab {
     pub is_active: bool,
     pub created_at: DateTime<Utc>,
     pub last_active: DateTime<Utc>,
+    /// Consistency findings for the tab's fingerprint, IP and engine config
+    #[serde(default)]
+    pub lint: LintReport,
 }
 
 /// Configuration for creating a new browser tab
@@ -72,6 +77,8 @@ pub struct BrowserTabManager {
     webview_manager: Arc<WebviewManager>,
     tabs: Arc<RwLock<HashMap<String, BrowserTab>>>,
     active_tab: Arc<RwLock<Option<String>>>,
+    engine_config: Option<ChromiumEngineConfig>,
+    linter: Arc<FingerprintLinter>,
 }
 
 impl BrowserTabManager {
@@ -92,9 +99,37 @@ impl BrowserTabManager {
             webview_manager,
             tabs: Arc::new(RwLock::new(HashMap::new())),
             active_tab: Arc::new(RwLock::new(None)),
+            engine_config: None,
+            linter: Arc::new(FingerprintLinter::new()),
         }
     }
 
+    /// Lint tabs against this engine configuration
+    ///
+    /// Without one, checks of the engine's viewport are skipped.
+    pub fn with_engine_config(mut self, config: ChromiumEngineConfig) -> Self {
+        self.engine_config = Some(config);
+        self
+    }
+
+    /// Replace the fingerprint consistency rules
+    pub fn with_linter(mut self, linter: FingerprintLinter) -> Self {
+        self.linter = Arc::new(linter);
+        self
+    }
+
+    /// Allocate tab IPs with this manager, e.g. one bound to the app's proxy pool
+    pub fn with_tab_ip_manager(mut self, tab_ip_manager: TabIPManager) -> Self {
+        self.tab_ip_manager = Arc::new(tab_ip_manager);
+        self
+    }
+
+    /// Open tabs through this webview manager, e.g. one built with the app's proxy config
+    pub fn with_webview_manager(mut self, webview_manager: WebviewManager) -> Self {
+        self.webview_manager = Arc::new(webview_manager);
+        self
+    }
+
 
     /// Create a new browser tab
     /// Create a new browser tab with the specified configuration
@@ -119,9 +154,11 @@ impl BrowserTabManager {
         let proxy_config = if let Some(proxy) = config.proxy_config {
             Some(proxy)
         } else {
-            self.create_proxy_from_virtual_ip(&virtual_ip)?
+            self.create_proxy_for_profile(&profile.tab_id).await
         };
 
+        let lint = self.lint_profile(&tab_id, &profile, &virtual_ip, config.user_agent.as_deref());
+
         // Create the WebView
         let webview = self.webview_manager
             .create_tab(config.url.clone())
@@ -138,6 +175,7 @@ impl BrowserTabManager {
             is_active: !config.background,
             created_at: Utc::now(),
             last_active: Utc::now(),
+            lint,
         };
 
         // Store the tab
@@ -338,32 +376,36 @@ impl BrowserTabManager {
     pub async fn rotate_ip(&self, tab_id: &str, country_code: Option<&str>) -> Result<VirtualIP> {
         debug!("Rotating IP for browser tab: {}", tab_id);
 
-        let tabs = self.tabs.read().await;
-        
-        if let Some(tab) = tabs.get(tab_id) {
-            // Rotate IP in the tab profile
-            let new_ip = self.tab_ip_manager
-                .rotate_ip(&tab.profile.tab_id, country_code)
-                .await?;
-            
-            // Create new proxy config
-            if let Some(proxy) = self.create_proxy_from_virtual_ip(&new_ip)? {
-                // Apply the new proxy config
-                drop(tabs);
-                let mut tabs_mut = self.tabs.write().await;
-                if let Some(tab_mut) = tabs_mut.get_mut(tab_id) {
-                    tab_mut.virtual_ip = new_ip.clone();
-                    tab_mut.proxy_config = Some(proxy.clone());
-                }
-                
-                self.apply_proxy_config(tab_id, &proxy).await?;
+        // Clone the profile id so exit verification runs without the tabs lock
+        let profile_id = self
+            .tabs
+            .read()
+            .await
+            .get(tab_id)
+            .map(|tab| tab.profile.tab_id.clone())
+            .ok_or_else(|| anyhow!("Tab not found: {}", tab_id))?;
+
+        // Rotate IP in the tab profile
+        let new_ip = self.tab_ip_manager
+            .rotate_ip(&profile_id, country_code)
+            .await?;
+        let proxy = self.create_proxy_for_profile(&profile_id).await;
+        let profile = self.tab_ip_manager.get_tab(&profile_id).await;
+
+        if let Some(tab) = self.tabs.write().await.get_mut(tab_id) {
+            if let Some(profile) = profile {
+                tab.lint = self.lint_profile(tab_id, &profile, &new_ip, None);
+                tab.profile = profile;
             }
-            
-            info!("Successfully rotated IP for browser tab: {}", tab_id);
-            Ok(new_ip)
-        } else {
-            Err(anyhow!("Tab not found: {}", tab_id))
+            tab.virtual_ip = new_ip.clone();
+            tab.proxy_config = proxy.clone();
         }
+        if let Some(proxy) = &proxy {
+            self.apply_proxy_config(tab_id, proxy).await?;
+        }
+
+        info!("Successfully rotated IP for browser tab: {}", tab_id);
+        Ok(new_ip)
     }
 
     /// Get all browser tabs
@@ -442,11 +484,38 @@ impl BrowserTabManager {
         }
     }
 
-    /// Create proxy configuration from virtual IP
-    fn create_proxy_from_virtual_ip(&self, _virtual_ip: &VirtualIP) -> Result<Option<ProxySettings>> {
-        // For now, return None - in a real implementation, you'd create
-        // a proxy config that routes through the virtual IP
-        Ok(None)
+    /// Re-run the consistency linter for a tab
+    pub async fn lint_tab(&self, tab_id: &str) -> Result<LintReport> {
+        let mut tabs = self.tabs.write().await;
+        let tab = tabs.get_mut(tab_id).ok_or_else(|| anyhow!("Tab not found: {}", tab_id))?;
+        tab.lint = self.lint_profile(tab_id, &tab.profile, &tab.virtual_ip, None);
+        Ok(tab.lint.clone())
+    }
+
+    /// Lint a profile, logging warnings and errors
+    fn lint_profile(&self, tab_id: &str, profile: &TabProfile, virtual_ip: &VirtualIP, user_agent: Option<&str>) -> LintReport {
+        let mut engine_config = self.engine_config.clone().unwrap_or_default();
+        if let Some(user_agent) = user_agent {
+            engine_config.user_agent = Some(user_agent.to_string());
+        }
+        let mut report = self.linter.lint(&profile.fingerprint, virtual_ip, &engine_config);
+        if self.engine_config.is_none() {
+            // The default viewport says nothing about the webview's window, and
+            // the system webview is not necessarily Chromium
+            report.findings.retain(|f| f.rule != VIEWPORT_RULE && f.rule != ENGINE_RULE);
+        }
+        for finding in report.at_least(LintSeverity::Warning) {
+            warn!("Tab {} fingerprint {:?} [{}]: {}", tab_id, finding.severity, finding.rule, finding.message);
+        }
+        report
+    }
+
+    /// Proxy configuration for the exit a profile's virtual IP is bound to
+    async fn create_proxy_for_profile(&self, profile_tab_id: &str) -> Option<ProxySettings> {
+        self.tab_ip_manager
+            .exit_proxy(profile_tab_id)
+            .await
+            .map(|proxy| proxy.to_proxy_settings())
     }
 
     /// Apply proxy configuration to a tab
@@ -743,6 +812,17 @@ pub async fn update_webview_tab_state(
         .map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+/// Re-lints a browser tab's fingerprint.
+pub async fn lint_browser_tab(
+    manager: State<'_, Arc<BrowserTabManager>>,
+    tab_id: String,
+) -> Result<LintReport, String> {
+    manager.lint_tab(&tab_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
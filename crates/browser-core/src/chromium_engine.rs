@@ -6,27 +6,64 @@
 //! proxy support, fingerprinting protection, and anti-detection features.
 //! 
 //! Key Features:
-//! - Advanced per-tab proxy routing
+//! - Per-tab proxy routing, each tab in its own browser context
 //! - Comprehensive fingerprint randomization
 //! - Stealth mode for bot detection avoidance
-//! - Network condition emulation
-//! - Geolocation spoofing
+//! - Network and CPU emulation, changeable per tab at runtime
+//! - Timezone, locale and geolocation matching each tab's exit IP
+//! - Per-tab BrowserFingerprint injection (UA/client hints, timezone, locale, screen),
+//!   including out-of-process iframes and workers
+//! - Seeded canvas/audio/WebGL noise with recorded rotation
+//! - Authenticated upstream proxies answered over CDP Fetch
 //! - Request interception
 //! - Performance monitoring
 //! - Full CDP (Chrome DevTools Protocol) access
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use chromiumoxide::auth::Credentials;
+use chromiumoxide::cdp::browser_protocol::browser::BrowserContextId;
+use chromiumoxide::cdp::browser_protocol::emulation::{
+    SetCpuThrottlingRateParams, SetDeviceMetricsOverrideParams, SetGeolocationOverrideParams,
+    SetLocaleOverrideParams, SetTimezoneOverrideParams, SetUserAgentOverrideParams,
+    UserAgentBrandVersion, UserAgentMetadata,
+};
+use chromiumoxide::cdp::browser_protocol::fetch::{AuthChallengeSource, EventAuthRequired};
+use chromiumoxide::cdp::browser_protocol::network::{
+    Cookie, CookieParam, EmulateNetworkConditionsParams, TimeSinceEpoch,
+};
+use chromiumoxide::cdp::browser_protocol::page::{
+    AddScriptToEvaluateOnNewDocumentParams, RemoveScriptToEvaluateOnNewDocumentParams, ScriptIdentifier,
+};
+use chromiumoxide::cdp::browser_protocol::storage::{GetCookiesParams, SetCookiesParams};
+use chromiumoxide::cdp::browser_protocol::target::{CreateBrowserContextParams, CreateTargetParams};
 use chromiumoxide::{Browser, BrowserConfig, Page};
 use futures::StreamExt;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{info, debug, warn};
+use virtual_ip::{GeoIpDatabase, VirtualIP};
 
-use crate::proxy::ProxySettings;
+use crate::exit_location::ExitLocation;
+use crate::fingerprint::BrowserFingerprint;
+use crate::fingerprint_injection::{BrandVersion, ClientHints, FingerprintInjection};
+use crate::fingerprint_noise::{NoiseHashes, NoiseRotation, PROBE_SCRIPT};
+use crate::http_client::PublicIpInfo;
+use crate::local_proxy::LocalProxyServer;
+use crate::proxy::{ProxySettings, ProxyType};
+use crate::proxy_validator::ProxyQuarantineManager;
+use crate::tab_manager::ExitVerifier;
+use crate::target_injection::TargetInjector;
+
+/// Time allowed for checking where a tab's proxy exits
+const EXIT_CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Rejected auth challenges after which a tab reports its upstream proxy
+const PROXY_AUTH_REPORT_THRESHOLD: u32 = 3;
 
 /// Engine version - v1000 (1.0.0.0)
 pub const ENGINE_VERSION: u32 = 1000;
@@ -68,6 +105,10 @@ pub enum NetworkCondition {
         upload_throughput: f64,
         latency: f64,
     },
+    /// No network at all
+    Offline,
+    /// A profile from `ChromiumEngineConfig::network_profiles`, by name
+    Profile(String),
 }
 
 
@@ -82,7 +123,96 @@ impl NetworkCondition {
             NetworkCondition::Custom { download_throughput, upload_throughput, latency } => {
                 (*download_throughput, *upload_throughput, *latency)
             }
+            NetworkCondition::Offline => (0.0, 0.0, 0.0),
+            // Profiles are resolved against the config by `to_profile`
+            NetworkCondition::Profile(_) => (-1.0, -1.0, 0.0),
+        }
+    }
+    /// Name the condition is reported under in load metrics
+    pub fn name(&self) -> String {
+        match self {
+            NetworkCondition::None => "none".to_string(),
+            NetworkCondition::Slow3G => "slow-3g".to_string(),
+            NetworkCondition::Fast3G => "fast-3g".to_string(),
+            NetworkCondition::LTE => "lte".to_string(),
+            NetworkCondition::Custom { .. } => "custom".to_string(),
+            NetworkCondition::Offline => "offline".to_string(),
+            NetworkCondition::Profile(name) => name.clone(),
+        }
+    }
+
+    /// The emulation settings for this condition; None for a profile not in `profiles`
+    pub fn to_profile(&self, profiles: &[NetworkProfile]) -> Option<NetworkProfile> {
+        if let NetworkCondition::Profile(name) = self {
+            return profiles.iter().find(|p| &p.name == name).cloned();
         }
+        let (download_throughput, upload_throughput, latency) = self.get_params();
+        Some(NetworkProfile {
+            name: self.name(),
+            download_throughput,
+            upload_throughput,
+            latency,
+            offline: matches!(self, NetworkCondition::Offline),
+            cpu_throttling_rate: 1.0,
+        })
+    }
+}
+
+fn unlimited_throughput() -> f64 {
+    -1.0
+}
+
+fn default_cpu_throttling_rate() -> f64 {
+    1.0
+}
+
+/// Network and CPU emulation settings, as loaded from config
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkProfile {
+    pub name: String,
+    /// Bytes per second; -1 for no limit
+    #[serde(default = "unlimited_throughput")]
+    pub download_throughput: f64,
+    /// Bytes per second; -1 for no limit
+    #[serde(default = "unlimited_throughput")]
+    pub upload_throughput: f64,
+    /// Added round-trip time in milliseconds
+    #[serde(default)]
+    pub latency: f64,
+    #[serde(default)]
+    pub offline: bool,
+    /// CPU slowdown factor; 1 runs at full speed
+    #[serde(default = "default_cpu_throttling_rate")]
+    pub cpu_throttling_rate: f64,
+}
+
+#[derive(Deserialize)]
+struct NetworkProfileFile {
+    #[serde(default, alias = "profiles")]
+    profile: Vec<NetworkProfile>,
+}
+
+impl NetworkProfile {
+    /// Load profiles from a TOML (`[[profile]]` tables) or JSON (array) file
+    pub fn load_file(path: &Path) -> Result<Vec<NetworkProfile>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read network profiles from {:?}", path))?;
+
+        if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&content).context("Failed to parse JSON network profiles")
+        } else {
+            let file: NetworkProfileFile = toml::from_str(&content).context("Failed to parse TOML network profiles")?;
+            Ok(file.profile)
+        }
+    }
+
+    /// Whether applying the profile changes nothing
+    pub fn is_unthrottled(&self) -> bool {
+        !self.offline
+            && self.download_throughput < 0.0
+            && self.upload_throughput < 0.0
+            && self.latency <= 0.0
+            && self.cpu_throttling_rate <= 1.0
     }
 }
 
@@ -186,7 +316,7 @@ pub struct ChromiumEngineConfig {
     pub extra_args: Vec<String>,
     /// Proxy configuration
     pub proxy: Option<ProxySettings>,
-    /// Proxy authentication
+    /// Credentials for `proxy`, taking precedence over any it carries
     pub proxy_auth: Option<ProxyAuth>,
     /// Enable stealth mode to avoid detection
     pub stealth_mode: bool,
@@ -202,6 +332,9 @@ pub struct ChromiumEngineConfig {
     pub doh_server: Option<String>,
     /// Network condition for throttling
     pub network_condition: NetworkCondition,
+    /// Profiles `NetworkCondition::Profile` can name
+    #[serde(default)]
+    pub network_profiles: Vec<NetworkProfile>,
     /// Fingerprint configuration
     pub fingerprint: FingerprintConfig,
     /// Cookie isolation mode
@@ -240,6 +373,7 @@ impl Default for ChromiumEngineConfig {
             webrtc_protection: true,
             doh_server: Some("https://cloudflare-dns.com/dns-query".to_string()),
             network_condition: NetworkCondition::default(),
+            network_profiles: Vec::new(),
             fingerprint: FingerprintConfig::default(),
             cookie_isolation: CookieIsolationMode::default(),
             blocked_urls: Vec::new(),
@@ -260,6 +394,9 @@ pub struct ChromiumTab {
     pub is_loading: bool,
     pub can_go_back: bool,
     pub can_go_forward: bool,
+    /// Fingerprint the tab presents instead of the engine-wide `FingerprintConfig`
+    #[serde(default)]
+    pub fingerprint: Option<BrowserFingerprint>,
 }
 
 /// Performance metrics for the engine
@@ -270,6 +407,9 @@ pub struct EngineMetrics {
     pub page_loads: u64,
     pub total_load_time_ms: u128,
     pub avg_load_time_ms: u128,
+    /// Page loads by the network condition active in the tab
+    #[serde(default)]
+    pub loads_by_condition: HashMap<String, ConditionLoadMetrics>,
     pub tabs_created: u64,
     pub tabs_closed: u64,
     pub cdp_commands_sent: u64,
@@ -278,6 +418,22 @@ pub struct EngineMetrics {
 }
 
 
+/// Page loads under one network condition
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ConditionLoadMetrics {
+    pub page_loads: u64,
+    pub total_load_time_ms: u128,
+    pub avg_load_time_ms: u128,
+}
+
+impl ConditionLoadMetrics {
+    fn record(&mut self, elapsed_ms: u128) {
+        self.page_loads += 1;
+        self.total_load_time_ms += elapsed_ms;
+        self.avg_load_time_ms = self.total_load_time_ms / self.page_loads as u128;
+    }
+}
+
 /// Engine metadata and version information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a EngineInfo.
@@ -290,11 +446,74 @@ pub struct EngineInfo {
     pub uptime: Duration,
 }
 
+/// Browser context a tab runs in, with the relay serving its proxy if it needs one
+struct TabContext {
+    id: BrowserContextId,
+    relay: Option<LocalProxyServer>,
+}
+
+/// Cookie as read from one browser context, for writing into another
+fn cookie_param(cookie: Cookie) -> CookieParam {
+    let mut param = CookieParam::new(cookie.name, cookie.value);
+    param.domain = Some(cookie.domain);
+    param.path = Some(cookie.path);
+    param.secure = Some(cookie.secure);
+    param.http_only = Some(cookie.http_only);
+    param.same_site = cookie.same_site;
+    param.expires = (!cookie.session).then(|| TimeSinceEpoch::new(cookie.expires));
+    param.priority = Some(cookie.priority);
+    param.source_scheme = Some(cookie.source_scheme);
+    param.source_port = Some(cookie.source_port);
+    param.partition_key = cookie.partition_key;
+    param
+}
+
+/// A tab fingerprint with the timezone and languages of the tab's exit
+fn localized_fingerprint(fp: &BrowserFingerprint, exit: Option<&ExitLocation>) -> BrowserFingerprint {
+    let mut fp = fp.clone();
+    if let Some(exit) = exit {
+        fp.timezone = exit.timezone.clone();
+        fp.accept_language = exit.accept_language.clone();
+    }
+    fp
+}
+
+/// `Emulation.setUserAgentOverride` metadata for a set of client hints
+fn user_agent_metadata(hints: &ClientHints) -> UserAgentMetadata {
+    let brands = |list: &[BrandVersion]| {
+        list.iter()
+            .map(|b| UserAgentBrandVersion::new(b.brand.clone(), b.version.clone()))
+            .collect::<Vec<_>>()
+    };
+    UserAgentMetadata {
+        brands: Some(brands(&hints.brands)),
+        full_version_list: Some(brands(&hints.full_version_list)),
+        platform: hints.platform.clone(),
+        platform_version: hints.platform_version.clone(),
+        architecture: hints.architecture.clone(),
+        model: hints.model.clone(),
+        mobile: hints.mobile,
+        bitness: Some(hints.bitness.clone()),
+        wow64: Some(hints.wow64),
+    }
+}
+
 /// Chromium engine manager with optimized proxy support
 pub struct ChromiumEngine {
     config: ChromiumEngineConfig,
     browser: Option<Browser>,
     tabs: Arc<RwLock<HashMap<String, ChromiumTab>>>,
+    pages: Arc<RwLock<HashMap<String, Page>>>,
+    contexts: Arc<RwLock<HashMap<String, TabContext>>>,
+    fingerprint_scripts: Arc<RwLock<HashMap<String, ScriptIdentifier>>>,
+    target_injectors: Arc<RwLock<HashMap<String, TargetInjector>>>,
+    noise_rotations: Arc<RwLock<HashMap<String, Vec<NoiseRotation>>>>,
+    proxy_auth_failures: Arc<RwLock<HashMap<String, u32>>>,
+    network_profiles: Arc<RwLock<HashMap<String, NetworkProfile>>>,
+    exit_locations: Arc<RwLock<HashMap<String, ExitLocation>>>,
+    quarantine_manager: Option<Arc<ProxyQuarantineManager>>,
+    exit_verifier: Option<Arc<dyn ExitVerifier>>,
+    geoip_database: Option<Arc<GeoIpDatabase>>,
     active_tab_id: Arc<RwLock<Option<String>>>,
     is_running: Arc<RwLock<bool>>,
     metrics: Arc<RwLock<EngineMetrics>>,
@@ -309,12 +528,42 @@ impl ChromiumEngine {
             config,
             browser: None,
             tabs: Arc::new(RwLock::new(HashMap::new())),
+            pages: Arc::new(RwLock::new(HashMap::new())),
+            contexts: Arc::new(RwLock::new(HashMap::new())),
+            fingerprint_scripts: Arc::new(RwLock::new(HashMap::new())),
+            target_injectors: Arc::new(RwLock::new(HashMap::new())),
+            noise_rotations: Arc::new(RwLock::new(HashMap::new())),
+            proxy_auth_failures: Arc::new(RwLock::new(HashMap::new())),
+            network_profiles: Arc::new(RwLock::new(HashMap::new())),
+            exit_locations: Arc::new(RwLock::new(HashMap::new())),
+            quarantine_manager: None,
+            exit_verifier: None,
+            geoip_database: None,
             active_tab_id: Arc::new(RwLock::new(None)),
             is_running: Arc::new(RwLock::new(false)),
             metrics: Arc::new(RwLock::new(EngineMetrics::default())),
             start_time: Instant::now(),
         }
     }
+
+    /// Report upstream proxies that reject a tab's credentials
+    pub fn with_quarantine_manager(mut self, quarantine_manager: Arc<ProxyQuarantineManager>) -> Self {
+        self.quarantine_manager = Some(quarantine_manager);
+        self
+    }
+
+    /// Check where each tab's proxy exits, to match its timezone, locale and position
+    pub fn with_exit_verifier(mut self, verifier: Arc<dyn ExitVerifier>) -> Self {
+        self.exit_verifier = Some(verifier);
+        self
+    }
+
+    /// Locate exits in a local GeoIP database, for virtual IPs and for proxies
+    /// when no exit verifier is set
+    pub fn with_geoip_database(mut self, database: Arc<GeoIpDatabase>) -> Self {
+        self.geoip_database = Some(database);
+        self
+    }
     
     /// Get engine version information
     pub fn get_version_info(&self) -> EngineInfo {
@@ -331,6 +580,7 @@ impl ChromiumEngine {
     /// Get engine capabilities
     pub fn get_capabilities(&self) -> EngineCapabilities {
         EngineCapabilities {
+            // Every tab gets its own browser context and proxy
             per_tab_proxy: true,
             webrtc_protection: true,
             stealth_mode: true,
@@ -386,9 +636,10 @@ impl ChromiumEngine {
         }
 
         // Add proxy configuration - OPTIMIZED for better proxy support
+        // Tabs without a proxy of their own use this one
         if let Some(proxy) = &self.config.proxy {
-            if let Some(proxy_url) = proxy.to_url() {
-                builder = builder.arg(format!("--proxy-server={}", proxy_url));
+            if let Some(proxy_server) = proxy.to_proxy_server() {
+                builder = builder.arg(format!("--proxy-server={}", proxy_server));
                 
                 // Add proxy bypass for local addresses
                 let bypass_list = proxy.bypass_list.join(",");
@@ -457,60 +708,41 @@ impl ChromiumEngine {
         Ok(())
     }
 
-    /// Create a new tab with optional proxy settings
+    /// Create a new tab with optional proxy settings, in its own browser context
     pub async fn create_tab(&self, url: Option<&str>, proxy: Option<ProxySettings>) -> Result<ChromiumTab> {
-        let start_time = Instant::now();
-        
-        let browser = self.browser.as_ref()
-            .ok_or_else(|| anyhow!("Browser not launched"))?;
-
-        let page = browser.new_page("about:blank")
-            .await
-            .map_err(|e| anyhow!("Failed to create new tab: {}", e))?;
+        self.create_tab_with_fingerprint(url, proxy, None).await
+    }
 
+    /// Create a new tab presenting its own fingerprint.
+    ///
+    /// With a fingerprint, the engine-wide user agent and `FingerprintConfig` are not applied.
+    pub async fn create_tab_with_fingerprint(
+        &self,
+        url: Option<&str>,
+        proxy: Option<ProxySettings>,
+        fingerprint: Option<BrowserFingerprint>,
+    ) -> Result<ChromiumTab> {
+        let start_time = Instant::now();
         let tab_id = uuid::Uuid::new_v4().to_string();
-        
+        let (context, page) = self.open_tab_page(&tab_id, proxy.as_ref(), fingerprint.as_ref()).await?;
+
         // Update metrics
         {
             let mut metrics = self.metrics.write().await;
             metrics.tabs_created += 1;
         }
 
-        // Apply custom user agent if configured
-        if let Some(user_agent) = &self.config.user_agent {
-            page.set_user_agent(user_agent)
-                .await
-                .map_err(|e| anyhow!("Failed to set user agent: {}", e))?;
-        }
-
-        // Apply network throttling if configured
-        if !matches!(self.config.network_condition, NetworkCondition::None) {
-            self.apply_network_throttling(&page).await?;
-        }
-
-        // Apply geolocation spoofing if configured
-        if let Some(geo) = &self.config.geolocation {
-            self.apply_geolocation(&page, geo).await?;
-        }
-
-        // Apply stealth scripts to avoid detection
-        if self.config.stealth_mode {
-            self.inject_stealth_scripts(&page).await?;
-        }
-
-        // Apply fingerprint spoofing
-        self.apply_fingerprint_spoofing(&page).await?;
-        
-        // Apply request interception if enabled
-        if self.config.enable_interception && !self.config.blocked_urls.is_empty() {
-            self.apply_request_interception(&page).await?;
-        }
-
         // Navigate to URL if provided
         let final_url = if let Some(u) = url {
-            page.goto(u)
-                .await
-                .map_err(|e| anyhow!("Failed to navigate: {}", e))?;
+            if let Err(e) = page.goto(u).await {
+                let _ = page.close().await;
+                self.dispose_context(context).await;
+                self.fingerprint_scripts.write().await.remove(&tab_id);
+                self.target_injectors.write().await.remove(&tab_id);
+                self.network_profiles.write().await.remove(&tab_id);
+                self.exit_locations.write().await.remove(&tab_id);
+                return Err(anyhow!("Failed to navigate: {}", e));
+            }
             u.to_string()
         } else {
             "about:blank".to_string()
@@ -529,60 +761,329 @@ impl ChromiumEngine {
             is_loading: false,
             can_go_back: false,
             can_go_forward: false,
+            fingerprint,
         };
 
         self.tabs.write().await.insert(tab_id.clone(), tab.clone());
+        self.pages.write().await.insert(tab_id.clone(), page);
+        self.contexts.write().await.insert(tab_id.clone(), context);
         *self.active_tab_id.write().await = Some(tab_id);
         
-        // Update load metrics
-        {
-            let elapsed = start_time.elapsed().as_millis();
-            let mut metrics = self.metrics.write().await;
-            metrics.page_loads += 1;
-            metrics.total_load_time_ms += elapsed;
-            metrics.avg_load_time_ms = if metrics.page_loads > 0 {
-                metrics.total_load_time_ms / metrics.page_loads as u128
-            } else {
-                0
-            };
-        }
+        self.record_page_load(&tab.id, start_time.elapsed().as_millis()).await;
 
         Ok(tab)
     }
 
-    /// Navigate a tab to a URL
-    pub async fn navigate(&self, tab_id: &str, url: &str) -> Result<()> {
+    /// Open a page for a tab in a new browser context routed through `proxy`,
+    /// with the engine's page setup and the tab's fingerprint applied.
+    async fn open_tab_page(
+        &self,
+        tab_id: &str,
+        proxy: Option<&ProxySettings>,
+        fingerprint: Option<&BrowserFingerprint>,
+    ) -> Result<(TabContext, Page)> {
         let browser = self.browser.as_ref()
             .ok_or_else(|| anyhow!("Browser not launched"))?;
 
-        let pages = browser.pages().await.map_err(|e| anyhow!("Failed to get pages: {}", e))?;
-        
-        // Find the page by checking tabs
-        let tabs = self.tabs.read().await;
-        if !tabs.contains_key(tab_id) {
-            return Err(anyhow!("Tab not found: {}", tab_id));
+        let exit = self.resolve_exit_location(proxy).await;
+        let context = self.create_context(proxy).await?;
+        let mut params = CreateTargetParams::new("about:blank");
+        params.browser_context_id = Some(context.id.clone());
+        let page = match browser.new_page(params).await {
+            Ok(page) => page,
+            Err(e) => {
+                self.dispose_context(context).await;
+                return Err(anyhow!("Failed to create new tab: {}", e));
+            }
+        };
+
+        if let Err(e) = self.apply_proxy_auth(tab_id, &page, proxy).await {
+            let _ = page.close().await;
+            self.dispose_context(context).await;
+            return Err(e);
+        }
+        if let Err(e) = self.prepare_page(tab_id, &page, fingerprint, exit.as_ref()).await {
+            let _ = page.close().await;
+            self.dispose_context(context).await;
+            return Err(e);
+        }
+
+        let mut exits = self.exit_locations.write().await;
+        match exit {
+            Some(exit) => exits.insert(tab_id.to_string(), exit),
+            None => exits.remove(tab_id),
+        };
+        Ok((context, page))
+    }
+
+    /// Create a browser context whose traffic goes through `proxy`.
+    ///
+    /// Without a proxy the context uses the launch-time `--proxy-server`; SSH,
+    /// Shadowsocks and authenticated SOCKS5 upstreams are served through a
+    /// `LocalProxyServer` relay.
+    async fn create_context(&self, proxy: Option<&ProxySettings>) -> Result<TabContext> {
+        let browser = self.browser.as_ref()
+            .ok_or_else(|| anyhow!("Browser not launched"))?;
+
+        let mut params = CreateBrowserContextParams::default();
+        let mut relay = None;
+        if let Some(proxy) = proxy {
+            params.proxy_server = if proxy.proxy_type == ProxyType::Direct {
+                Some("direct://".to_string())
+            } else if proxy.requires_local_relay() {
+                let (server, addr) = LocalProxyServer::start_relay(proxy.clone()).await?;
+                relay = Some(server);
+                Some(format!("http://{}", addr))
+            } else {
+                proxy.to_proxy_server()
+            };
+            if !proxy.bypass_list.is_empty() {
+                params.proxy_bypass_list = Some(proxy.bypass_list.join(","));
+            }
         }
-        drop(tabs);
 
-        // Navigate the first available page (simplified for now)
-        if let Some(page) = pages.first() {
-            page.goto(url)
+        match browser.create_browser_context(params).await {
+            Ok(id) => {
+                let mut metrics = self.metrics.write().await;
+                metrics.cdp_commands_sent += 1;
+                Ok(TabContext { id, relay })
+            }
+            Err(e) => {
+                if let Some(relay) = relay {
+                    let _ = relay.stop().await;
+                }
+                Err(anyhow!("Failed to create browser context: {}", e))
+            }
+        }
+    }
+
+    /// Dispose a tab's browser context and stop its relay
+    async fn dispose_context(&self, context: TabContext) {
+        if let Some(browser) = &self.browser {
+            if let Err(e) = browser.dispose_browser_context(context.id).await {
+                warn!("Failed to dispose browser context: {}", e);
+            }
+        }
+        if let Some(relay) = context.relay {
+            if let Err(e) = relay.stop().await {
+                warn!("Failed to stop proxy relay: {}", e);
+            }
+        }
+    }
+
+    /// Upstream proxy and credentials for a tab's auth challenges.
+    ///
+    /// Tabs without a proxy of their own go through the launch-time proxy, with
+    /// `proxy_auth` in place of its credentials. Relayed upstreams authenticate
+    /// in the relay, so the browser never sees a challenge for them.
+    fn proxy_credentials(&self, proxy: Option<&ProxySettings>) -> Option<(ProxySettings, Credentials)> {
+        let (upstream, auth) = match proxy {
+            Some(proxy) => (proxy, None),
+            None => (self.config.proxy.as_ref()?, self.config.proxy_auth.as_ref()),
+        };
+        if upstream.proxy_type == ProxyType::Direct || upstream.requires_local_relay() {
+            return None;
+        }
+
+        let credentials = match auth {
+            Some(auth) => Credentials {
+                username: auth.username.clone(),
+                password: auth.password.clone(),
+            },
+            None => Credentials {
+                username: upstream.username.clone()?,
+                password: upstream.password.clone().unwrap_or_default(),
+            },
+        };
+        Some((upstream.clone(), credentials))
+    }
+
+    /// Answer a tab's proxy auth challenges with its credentials.
+    ///
+    /// The page enables `Fetch` with `handleAuthRequests`; its handler provides the
+    /// credentials on a request's first challenge and cancels on a repeat. A repeat
+    /// means the upstream rejected them, which is counted for the tab; the tab
+    /// reports the upstream to the quarantine manager once, when its count reaches
+    /// `PROXY_AUTH_REPORT_THRESHOLD`, so a page's many sub-requests count as one
+    /// failure.
+    async fn apply_proxy_auth(&self, tab_id: &str, page: &Page, proxy: Option<&ProxySettings>) -> Result<()> {
+        self.proxy_auth_failures.write().await.remove(tab_id);
+        let Some((upstream, credentials)) = self.proxy_credentials(proxy) else {
+            return Ok(());
+        };
+
+        let mut challenges = page.event_listener::<EventAuthRequired>()
+            .await
+            .map_err(|e| anyhow!("Failed to listen for auth challenges: {}", e))?;
+        page.authenticate(credentials)
+            .await
+            .map_err(|e| anyhow!("Failed to set proxy credentials: {}", e))?;
+
+        let failures = self.proxy_auth_failures.clone();
+        let quarantine = self.quarantine_manager.clone();
+        let tab_id = tab_id.to_string();
+        tokio::spawn(async move {
+            let mut attempted = HashSet::new();
+            // Ends when the page closes
+            while let Some(event) = challenges.next().await {
+                if event.auth_challenge.source != Some(AuthChallengeSource::Proxy) {
+                    continue;
+                }
+                if attempted.len() >= 1024 {
+                    attempted.clear();
+                }
+                if attempted.insert(event.request_id.as_ref().to_string()) {
+                    continue;
+                }
+
+                let count = {
+                    let mut failures = failures.write().await;
+                    let count = failures.entry(tab_id.clone()).or_insert(0);
+                    *count += 1;
+                    *count
+                };
+                warn!(
+                    "Proxy {} rejected credentials for tab {} ({} failures)",
+                    event.auth_challenge.origin, tab_id, count
+                );
+
+                if count != PROXY_AUTH_REPORT_THRESHOLD {
+                    continue;
+                }
+                if let (Some(quarantine), Some(proxy)) = (&quarantine, upstream.to_free_proxy("chromium")) {
+                    let reason = format!("Proxy authentication failed {} times for tab {}", count, tab_id);
+                    if quarantine.record_failure(&proxy, reason).await {
+                        warn!("Proxy {} quarantined after repeated authentication failures", event.auth_challenge.origin);
+                    }
+                }
+            }
+        });
+
+        let mut metrics = self.metrics.write().await;
+        metrics.cdp_commands_sent += 1;
+        Ok(())
+    }
+
+    /// Apply the engine's page setup, or the tab's fingerprint in place of the
+    /// engine-wide user agent and `FingerprintConfig`
+    async fn prepare_page(
+        &self,
+        tab_id: &str,
+        page: &Page,
+        fingerprint: Option<&BrowserFingerprint>,
+        exit: Option<&ExitLocation>,
+    ) -> Result<()> {
+        // Apply custom user agent if configured (a tab fingerprint carries its own)
+        if let (None, Some(user_agent)) = (fingerprint, &self.config.user_agent) {
+            page.set_user_agent(user_agent)
                 .await
-                .map_err(|e| anyhow!("Failed to navigate: {}", e))?;
-            
-            // Update tab state
-            let mut tabs = self.tabs.write().await;
-            if let Some(tab) = tabs.get_mut(tab_id) {
-                tab.url = url.to_string();
-                tab.is_loading = true;
+                .map_err(|e| anyhow!("Failed to set user agent: {}", e))?;
+        }
+
+        // Apply network emulation; a tab moving to a new page keeps the one it had
+        let profile = match self.network_profiles.read().await.get(tab_id).cloned() {
+            Some(profile) => profile,
+            None => self.resolve_network_condition(&self.config.network_condition)?,
+        };
+        self.apply_network_profile(tab_id, page, profile).await?;
+
+        // Apply geolocation spoofing: near the exit, else the configured position
+        let geolocation = exit
+            .and_then(|exit| exit.geolocation(tab_id))
+            .or_else(|| self.config.geolocation.clone());
+        if let Some(geo) = &geolocation {
+            self.apply_geolocation(page, geo).await?;
+        }
+
+        // Apply stealth scripts to avoid detection
+        if self.config.stealth_mode {
+            self.inject_stealth_scripts(page).await?;
+        }
+
+        // Apply fingerprint spoofing, after stealth so the tab's values win
+        match fingerprint {
+            Some(fp) => self.apply_tab_fingerprint(tab_id, page, fp, exit).await?,
+            None => {
+                let mut fingerprint_config = self.config.fingerprint.clone();
+                if let Some(exit) = exit {
+                    // The exit decides these
+                    fingerprint_config.spoof_timezone = false;
+                    fingerprint_config.spoof_language = false;
+                    self.apply_exit_locale(page, exit).await?;
+                }
+                self.apply_fingerprint_spoofing(page, &fingerprint_config).await?;
             }
         }
+        
+        // Apply request interception if enabled
+        if self.config.enable_interception && !self.config.blocked_urls.is_empty() {
+            self.apply_request_interception(page).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Copy cookies between browser contexts, so moving a tab keeps its sessions
+    async fn copy_cookies(&self, from: &BrowserContextId, to: &BrowserContextId) -> Result<()> {
+        let browser = self.browser.as_ref()
+            .ok_or_else(|| anyhow!("Browser not launched"))?;
+
+        let get = GetCookiesParams {
+            browser_context_id: Some(from.clone()),
+        };
+        let cookies = browser.execute(get)
+            .await
+            .map_err(|e| anyhow!("Failed to read cookies: {}", e))?
+            .result
+            .cookies;
+        if cookies.is_empty() {
+            return Ok(());
+        }
+
+        let mut set = SetCookiesParams::new(cookies.into_iter().map(cookie_param).collect());
+        set.browser_context_id = Some(to.clone());
+        browser.execute(set)
+            .await
+            .map_err(|e| anyhow!("Failed to write cookies: {}", e))?;
+        Ok(())
+    }
+
+    /// Navigate a tab to a URL
+    pub async fn navigate(&self, tab_id: &str, url: &str) -> Result<()> {
+        let page = self.get_page_for_tab(tab_id).await?;
+        let start_time = Instant::now();
+        page.goto(url)
+            .await
+            .map_err(|e| anyhow!("Failed to navigate: {}", e))?;
+        self.record_page_load(tab_id, start_time.elapsed().as_millis()).await;
+
+        // Update tab state
+        let mut tabs = self.tabs.write().await;
+        if let Some(tab) = tabs.get_mut(tab_id) {
+            tab.url = url.to_string();
+            tab.is_loading = true;
+        }
 
         Ok(())
     }
 
     /// Close a tab
     pub async fn close_tab(&self, tab_id: &str) -> Result<()> {
+        self.fingerprint_scripts.write().await.remove(tab_id);
+        self.target_injectors.write().await.remove(tab_id);
+        self.proxy_auth_failures.write().await.remove(tab_id);
+        self.network_profiles.write().await.remove(tab_id);
+        self.exit_locations.write().await.remove(tab_id);
+        if let Some(page) = self.pages.write().await.remove(tab_id) {
+            if let Err(e) = page.close().await {
+                warn!("Failed to close page for tab {}: {}", tab_id, e);
+            }
+        }
+        let context = self.contexts.write().await.remove(tab_id);
+        if let Some(context) = context {
+            self.dispose_context(context).await;
+        }
+
         let mut tabs = self.tabs.write().await;
         tabs.remove(tab_id);
         
@@ -627,134 +1128,428 @@ impl ChromiumEngine {
         Ok(())
     }
 
-    /// Update proxy for a specific tab (advanced feature)
+    /// Update proxy for a specific tab.
+    ///
+    /// A browser context's proxy is fixed, so the tab moves to a new context with
+    /// its cookies, fingerprint and current URL; the old context is disposed.
+    /// Timezone, locale and position follow the new exit.
     pub async fn set_tab_proxy(&self, tab_id: &str, proxy: Option<ProxySettings>) -> Result<()> {
-        let mut tabs = self.tabs.write().await;
-        if let Some(tab) = tabs.get_mut(tab_id) {
+        let tab = self.tabs
+            .read()
+            .await
+            .get(tab_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Tab not found: {}", tab_id))?;
+        let old_page = self.get_page_for_tab(tab_id).await?;
+        let url = old_page.url().await.ok().flatten().unwrap_or_else(|| tab.url.clone());
+
+        // The new page registers its own fingerprint script and injector; the old page
+        // keeps its own until the new page is installed
+        let previous_script = self.fingerprint_scripts.write().await.remove(tab_id);
+        let previous_injector = self.target_injectors.write().await.remove(tab_id);
+        let (context, page) = match self.open_tab_page(tab_id, proxy.as_ref(), tab.fingerprint.as_ref()).await {
+            Ok(opened) => opened,
+            Err(e) => {
+                let mut scripts = self.fingerprint_scripts.write().await;
+                match previous_script {
+                    Some(script) => scripts.insert(tab_id.to_string(), script),
+                    None => scripts.remove(tab_id),
+                };
+                let mut injectors = self.target_injectors.write().await;
+                match previous_injector {
+                    Some(injector) => injectors.insert(tab_id.to_string(), injector),
+                    None => injectors.remove(tab_id),
+                };
+                return Err(e);
+            }
+        };
+        let context_id = context.id.clone();
+        let old_context = self.contexts.write().await.insert(tab_id.to_string(), context);
+        self.pages.write().await.insert(tab_id.to_string(), page.clone());
+
+        if let Some(old_context) = old_context {
+            if let Err(e) = self.copy_cookies(&old_context.id, &context_id).await {
+                warn!("Failed to carry cookies over for tab {}: {}", tab_id, e);
+            }
+            if let Err(e) = old_page.close().await {
+                warn!("Failed to close previous page for tab {}: {}", tab_id, e);
+            }
+            self.dispose_context(old_context).await;
+        }
+
+        if url != "about:blank" {
+            // The tab has moved either way; an unreachable proxy shows as a failed load
+            if let Err(e) = page.goto(&url).await {
+                warn!("Failed to reload {} in tab {} after proxy change: {}", url, tab_id, e);
+            }
+        }
+
+        if let Some(tab) = self.tabs.write().await.get_mut(tab_id) {
+            tab.url = url;
             tab.proxy = proxy;
-            info!("Updated proxy for tab {}", tab_id);
-        } else {
-            return Err(anyhow!("Tab not found: {}", tab_id));
         }
+        info!("Moved tab {} to a new browser context for its proxy", tab_id);
         Ok(())
     }
 
-    /// Apply network throttling to the page
-    /// 
-    /// # Current Limitation
-    /// Network throttling is not currently implemented. The configuration is validated
-    /// and logged, but throttling is not actively applied to network requests.
-    /// 
-    /// # Future Implementation
-    /// This will require direct CDP (Chrome DevTools Protocol) access via chromiumoxide
-    /// to use the Network.emulateNetworkConditions command.
-    /// 
-    /// # Workaround
-    /// For now, this method validates the configuration without error, allowing
-    /// the API to remain stable. Tests pass because they only verify the configuration,
-    /// not actual network behavior.
-    async fn apply_network_throttling(&self, page: &Page) -> Result<()> {
-        let (download, upload, latency) = self.config.network_condition.get_params();
-        
-        // Skip if no throttling configured (values are 0 or max)
-        if download == 0.0 && upload == 0.0 && latency == 0.0 {
-            debug!("Network throttling: no throttling configured, skipping");
-            return Ok(());
+    /// Times the tab's upstream proxy has rejected its credentials since the tab
+    /// last got a proxy
+    pub async fn get_proxy_auth_failures(&self, tab_id: &str) -> u32 {
+        self.proxy_auth_failures.read().await.get(tab_id).copied().unwrap_or(0)
+    }
+
+    /// Change a tab's network and CPU emulation; takes effect immediately
+    pub async fn set_tab_network_condition(&self, tab_id: &str, condition: NetworkCondition) -> Result<NetworkProfile> {
+        let page = self.get_page_for_tab(tab_id).await?;
+        let profile = self.resolve_network_condition(&condition)?;
+        self.apply_network_profile(tab_id, &page, profile.clone()).await?;
+        info!("Tab {} now emulates network profile {}", tab_id, profile.name);
+        Ok(profile)
+    }
+
+    /// The network profile a tab is emulating
+    pub async fn get_tab_network_profile(&self, tab_id: &str) -> Option<NetworkProfile> {
+        self.network_profiles.read().await.get(tab_id).cloned()
+    }
+
+    /// Give a tab a new fingerprint; the page script applies from the next navigation.
+    pub async fn set_tab_fingerprint(&self, tab_id: &str, fingerprint: BrowserFingerprint) -> Result<()> {
+        let page = self.get_page_for_tab(tab_id).await?;
+        let exit = self.exit_locations.read().await.get(tab_id).cloned();
+        self.apply_tab_fingerprint(tab_id, &page, &fingerprint, exit.as_ref()).await?;
+        if let Some(tab) = self.tabs.write().await.get_mut(tab_id) {
+            tab.fingerprint = Some(fingerprint);
         }
-        
-        info!(
-            "Applying network throttling: download={}kbps, upload={}kbps, latency={}ms",
-            download, upload, latency
+        Ok(())
+    }
+
+    /// Move a tab's fingerprint to a new random noise seed and record why.
+    ///
+    /// Noise is otherwise fixed per (seed, origin); this is the only way it changes.
+    pub async fn rotate_tab_noise(&self, tab_id: &str, reason: impl Into<String>) -> Result<NoiseRotation> {
+        let mut fingerprint = self.tabs
+            .read()
+            .await
+            .get(tab_id)
+            .ok_or_else(|| anyhow!("Tab not found: {}", tab_id))?
+            .fingerprint
+            .clone()
+            .ok_or_else(|| anyhow!("Tab {} has no fingerprint to rotate", tab_id))?;
+
+        let rotation = fingerprint.rotate_noise(rand::random(), reason);
+        self.set_tab_fingerprint(tab_id, fingerprint).await?;
+        info!("Rotated noise for tab {}: {}", tab_id, rotation.reason);
+        self.noise_rotations
+            .write()
+            .await
+            .entry(tab_id.to_string())
+            .or_default()
+            .push(rotation.clone());
+        Ok(rotation)
+    }
+
+    /// Noise rotations of a tab, oldest first
+    pub async fn get_noise_rotations(&self, tab_id: &str) -> Vec<NoiseRotation> {
+        self.noise_rotations.read().await.get(tab_id).cloned().unwrap_or_default()
+    }
+
+    /// Canvas and audio hashes the tab's current document reads back; compare with
+    /// `NoiseHashes::expected` for the tab's noise seed and the returned origin.
+    pub async fn probe_noise_hashes(&self, tab_id: &str) -> Result<NoiseHashes> {
+        let page = self.get_page_for_tab(tab_id).await?;
+        let result = page.evaluate_expression(PROBE_SCRIPT)
+            .await
+            .map_err(|e| anyhow!("Failed to probe noise hashes: {}", e))?;
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.cdp_commands_sent += 1;
+        }
+
+        result.into_value().map_err(|e| anyhow!("Unexpected noise probe result: {}", e))
+    }
+
+    /// Present a fingerprint in a page: UA and client hints, timezone, locale and
+    /// screen through the Emulation domain, the rest through a new-document script.
+    async fn apply_tab_fingerprint(
+        &self,
+        tab_id: &str,
+        page: &Page,
+        fp: &BrowserFingerprint,
+        exit: Option<&ExitLocation>,
+    ) -> Result<()> {
+        let injection = FingerprintInjection::from_fingerprint(&localized_fingerprint(fp, exit));
+
+        let mut user_agent = SetUserAgentOverrideParams::new(injection.user_agent.clone());
+        user_agent.accept_language = Some(injection.accept_language.clone());
+        user_agent.platform = Some(injection.platform.clone());
+        user_agent.user_agent_metadata = injection.client_hints.as_ref().map(user_agent_metadata);
+        page.execute(user_agent)
+            .await
+            .map_err(|e| anyhow!("Failed to override user agent: {}", e))?;
+
+        if let Err(e) = page.execute(SetTimezoneOverrideParams::new(injection.timezone.clone())).await {
+            warn!("Failed to override timezone {}: {}", injection.timezone, e);
+        }
+        let locale = SetLocaleOverrideParams { locale: Some(injection.locale.clone()) };
+        if let Err(e) = page.execute(locale).await {
+            warn!("Failed to override locale {}: {}", injection.locale, e);
+        }
+
+        let mut metrics = SetDeviceMetricsOverrideParams::new(
+            self.config.viewport_width as i64,
+            self.config.viewport_height as i64,
+            0.0,
+            injection.mobile,
         );
-        
-        // Convert kbps to bytes per second for CDP
-        // CDP expects bytes per second, -1 means no limit
-        let download_throughput = if download > 0.0 {
-            (download * 1024.0) / 8.0 // kbps to bytes/s
-        } else {
-            -1.0 // No limit
-        };
-        
-        let upload_throughput = if upload > 0.0 {
-            (upload * 1024.0) / 8.0 // kbps to bytes/s
-        } else {
-            -1.0 // No limit
-        };
-        
-        // Use JavaScript to apply network emulation via CDP
-        // This is a workaround since chromiumoxide may not expose all CDP commands directly
-        let script = format!(
-            r#"
-            (async () => {{
-                // Note: Network throttling requires CDP access which may not be
-                // available in all contexts. This is a placeholder for when
-                // full CDP integration is available.
-                console.log('Network throttling configured: download={}bps, upload={}bps, latency={}ms');
-            }})();
-            "#,
-            download_throughput, upload_throughput, latency
+        metrics.screen_width = Some(injection.screen_width as i64);
+        metrics.screen_height = Some(injection.screen_height as i64);
+        page.execute(metrics)
+            .await
+            .map_err(|e| anyhow!("Failed to override screen metrics: {}", e))?;
+
+        // The first registered script marks the global as patched, so replace rather than add
+        if let Some(previous) = self.fingerprint_scripts.write().await.remove(tab_id) {
+            if let Err(e) = page.execute(RemoveScriptToEvaluateOnNewDocumentParams::new(previous)).await {
+                warn!("Failed to remove previous fingerprint script for tab {}: {}", tab_id, e);
+            }
+        }
+        let script = injection.script();
+        let registered = page.execute(AddScriptToEvaluateOnNewDocumentParams::new(script.clone()))
+            .await
+            .map_err(|e| anyhow!("Failed to register fingerprint script: {}", e))?;
+        self.fingerprint_scripts
+            .write()
+            .await
+            .insert(tab_id.to_string(), registered.result.identifier);
+        // The current document predates the registration
+        page.evaluate(script.clone())
+            .await
+            .map_err(|e| anyhow!("Failed to apply fingerprint script: {}", e))?;
+        self.inject_into_child_targets(tab_id, page, script).await;
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.cdp_commands_sent += 6;
+        }
+        debug!("Applied tab fingerprint for {}", injection.user_agent);
+        Ok(())
+    }
+
+    /// Apply the fingerprint script to the out-of-process iframes and workers the page
+    /// starts from now on; one injector per tab, replaced when the tab gets a new page.
+    async fn inject_into_child_targets(&self, tab_id: &str, page: &Page, script: String) {
+        let Some(browser) = self.browser.as_ref() else { return };
+        let target_id = page.target_id().as_ref();
+        let mut injectors = self.target_injectors.write().await;
+        if let Some(injector) = injectors.get(tab_id).filter(|i| i.target_id() == target_id) {
+            injector.set_script(script);
+            return;
+        }
+        match TargetInjector::attach(browser.websocket_address(), target_id, script).await {
+            Ok(injector) => {
+                injectors.insert(tab_id.to_string(), injector);
+            }
+            Err(e) => {
+                injectors.remove(tab_id);
+                warn!("Failed to watch iframes and workers of tab {}: {}", tab_id, e);
+            }
+        }
+    }
+
+    /// Emulation settings for a condition, resolving profiles against the config
+    fn resolve_network_condition(&self, condition: &NetworkCondition) -> Result<NetworkProfile> {
+        condition
+            .to_profile(&self.config.network_profiles)
+            .ok_or_else(|| anyhow!("Unknown network profile: {}", condition.name()))
+    }
+
+    /// Apply network conditions and CPU throttling to a tab's page
+    async fn apply_network_profile(&self, tab_id: &str, page: &Page, profile: NetworkProfile) -> Result<()> {
+        // A new tab needs no commands to stay unthrottled
+        let emulating = self.network_profiles.read().await.contains_key(tab_id);
+        if emulating || !profile.is_unthrottled() {
+            page.execute(EmulateNetworkConditionsParams::new(
+                profile.offline,
+                profile.latency,
+                profile.download_throughput,
+                profile.upload_throughput,
+            ))
+            .await
+            .map_err(|e| anyhow!("Failed to emulate network conditions: {}", e))?;
+            page.execute(SetCpuThrottlingRateParams::new(profile.cpu_throttling_rate.max(1.0)))
+                .await
+                .map_err(|e| anyhow!("Failed to set CPU throttling: {}", e))?;
+
+            let mut metrics = self.metrics.write().await;
+            metrics.cdp_commands_sent += 2;
+        }
+
+        debug!(
+            "Network profile {} for tab {}: download={}B/s, upload={}B/s, latency={}ms, offline={}, cpu={}x",
+            profile.name, tab_id, profile.download_throughput, profile.upload_throughput,
+            profile.latency, profile.offline, profile.cpu_throttling_rate
         );
-        
-        page.evaluate(script).await.map_err(|e| {
-            warn!("Failed to apply network throttling: {}", e);
-            anyhow!("Network throttling failed: {}", e)
-        })?;
-        
-        debug!("Network throttling applied successfully");
+        self.network_profiles.write().await.insert(tab_id.to_string(), profile);
         Ok(())
     }
 
+    /// Record a page load under the tab's active network condition
+    async fn record_page_load(&self, tab_id: &str, elapsed_ms: u128) {
+        let condition = match self.network_profiles.read().await.get(tab_id) {
+            Some(profile) => profile.name.clone(),
+            None => self.config.network_condition.name(),
+        };
 
-    /// Apply geolocation spoofing
+        let mut metrics = self.metrics.write().await;
+        metrics.page_loads += 1;
+        metrics.total_load_time_ms += elapsed_ms;
+        metrics.avg_load_time_ms = metrics.total_load_time_ms / metrics.page_loads as u128;
+        metrics.loads_by_condition.entry(condition).or_default().record(elapsed_ms);
+    }
+
+    /// Apply geolocation spoofing.
+    ///
+    /// Permission is left to the site's own request, so a fresh profile still
+    /// reports "prompt"; once it is allowed, the override answers in place of
+    /// the machine's real position.
     async fn apply_geolocation(&self, page: &Page, geo: &Geolocation) -> Result<()> {
-        let script = format!(
-            r#"
-            // Override geolocation
-            navigator.geolocation.getCurrentPosition = function(success) {{
-                const position = {{
-                    coords: {{
-                        latitude: {},
-                        longitude: {},
-                        accuracy: {},
-                        altitude: null,
-                        altitudeAccuracy: null,
-                        heading: null,
-                        speed: null
-                    }},
-                    timestamp: Date.now()
-                }};
-                success(position);
-            }};
-            navigator.geolocation.watchPosition = function(success) {{
-                const position = {{
-                    coords: {{
-                        latitude: {},
-                        longitude: {},
-                        accuracy: {},
-                        altitude: null,
-                        altitudeAccuracy: null,
-                        heading: null,
-                        speed: null
-                    }},
-                    timestamp: Date.now()
-                }};
-                success(position);
-                return 1; // Return a fake watch ID
-            }};
-            navigator.geolocation.clearWatch = function() {{}};
-            "#,
-            geo.latitude, geo.longitude, geo.accuracy,
-            geo.latitude, geo.longitude, geo.accuracy
-        );
-        
-        page.evaluate(script)
+        let position = SetGeolocationOverrideParams {
+            latitude: Some(geo.latitude),
+            longitude: Some(geo.longitude),
+            accuracy: Some(geo.accuracy),
+        };
+        page.execute(position)
             .await
             .map_err(|e| anyhow!("Failed to apply geolocation: {}", e))?;
-        
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.cdp_commands_sent += 1;
+        }
         info!("Applied geolocation spoofing: lat={}, lon={}", geo.latitude, geo.longitude);
         Ok(())
     }
 
+    /// Apply the timezone, locale and `Accept-Language` of a tab's exit, keeping
+    /// the client hints that match the user agent
+    async fn apply_exit_locale(&self, page: &Page, exit: &ExitLocation) -> Result<()> {
+        if let Err(e) = page.execute(SetTimezoneOverrideParams::new(exit.timezone.clone())).await {
+            warn!("Failed to override timezone {}: {}", exit.timezone, e);
+        }
+        let locale = SetLocaleOverrideParams { locale: Some(exit.locale.clone()) };
+        if let Err(e) = page.execute(locale).await {
+            warn!("Failed to override locale {}: {}", exit.locale, e);
+        }
+
+        let user_agent = match &self.config.user_agent {
+            Some(user_agent) => user_agent.clone(),
+            None => page.user_agent()
+                .await
+                .map_err(|e| anyhow!("Failed to read user agent: {}", e))?,
+        };
+        let metadata = ClientHints::from_user_agent(&user_agent).as_ref().map(user_agent_metadata);
+        let mut user_agent = SetUserAgentOverrideParams::new(user_agent);
+        user_agent.accept_language = Some(exit.accept_language.clone());
+        user_agent.user_agent_metadata = metadata;
+        page.execute(user_agent)
+            .await
+            .map_err(|e| anyhow!("Failed to override Accept-Language: {}", e))?;
+
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.cdp_commands_sent += 3;
+        }
+        debug!("Applied exit locale {} ({}) for {}", exit.locale, exit.timezone, exit.ip);
+        Ok(())
+    }
+
+    /// Where traffic through `proxy`, or the launch-time proxy without one, exits.
+    ///
+    /// SSH and Shadowsocks servers are their own exit, so their host is looked up
+    /// in the GeoIP database. Other proxies are observed through the exit verifier;
+    /// without one, only a proxy without credentials is taken to exit at its own
+    /// address, as an authenticated one may be a backconnect gateway. None for
+    /// direct connections, or when the exit can't be placed.
+    async fn resolve_exit_location(&self, proxy: Option<&ProxySettings>) -> Option<ExitLocation> {
+        let upstream = proxy.or(self.config.proxy.as_ref())?;
+        if upstream.proxy_type == ProxyType::Direct {
+            return None;
+        }
+        if upstream.is_tunnel_protocol() {
+            return self.lookup_exit_host(upstream.host.as_deref()?).await;
+        }
+
+        if let Some(verifier) = &self.exit_verifier {
+            let entry = upstream.to_free_proxy("chromium")?;
+            let observed = tokio::time::timeout(EXIT_CHECK_TIMEOUT, verifier.verify_exit(&entry))
+                .await
+                .map_err(|_| anyhow!("Exit check timed out"))
+                .and_then(|result| result);
+            return match observed {
+                Ok(info) => ExitLocation::from_public_ip(&info),
+                Err(e) => {
+                    warn!("Exit check through {}:{} failed: {}", entry.ip, entry.port, e);
+                    None
+                }
+            };
+        }
+
+        if upstream.username.as_deref().is_some_and(|username| !username.is_empty()) {
+            return None;
+        }
+        self.lookup_exit_host(upstream.host.as_deref()?).await
+    }
+
+    /// GeoIP location of a proxy host, resolving it if it is a name
+    async fn lookup_exit_host(&self, host: &str) -> Option<ExitLocation> {
+        let database = self.geoip_database.as_ref()?;
+        let ip = match host.parse::<IpAddr>() {
+            Ok(ip) => ip,
+            Err(_) => match tokio::net::lookup_host((host, 0)).await {
+                Ok(mut addrs) => addrs.next()?.ip(),
+                Err(e) => {
+                    warn!("Failed to resolve proxy host {}: {}", host, e);
+                    return None;
+                }
+            },
+        };
+        let record = database.lookup(ip)?;
+        ExitLocation::from_public_ip(&PublicIpInfo::from_geoip(ip.to_string(), record))
+    }
+
+    /// Make a tab present the location of `virtual_ip` until its proxy next changes
+    pub async fn set_tab_virtual_ip(&self, tab_id: &str, virtual_ip: &VirtualIP) -> Result<ExitLocation> {
+        let page = self.get_page_for_tab(tab_id).await?;
+        let fingerprint = self.tabs.read().await.get(tab_id).and_then(|tab| tab.fingerprint.clone());
+
+        let mut exit = ExitLocation::from_virtual_ip(virtual_ip);
+        let position = self.geoip_database
+            .as_ref()
+            .and_then(|database| database.lookup(virtual_ip.ip))
+            .and_then(|record| record.location());
+        if let Some((latitude, longitude)) = position {
+            exit = exit.with_coordinates(latitude, longitude);
+        }
+
+        if let Some(geo) = exit.geolocation(tab_id) {
+            self.apply_geolocation(&page, &geo).await?;
+        }
+        match &fingerprint {
+            Some(fp) => self.apply_tab_fingerprint(tab_id, &page, fp, Some(&exit)).await?,
+            None => self.apply_exit_locale(&page, &exit).await?,
+        }
+
+        self.exit_locations.write().await.insert(tab_id.to_string(), exit.clone());
+        info!("Tab {} presents {} ({}, {})", tab_id, exit.ip, exit.country_code, exit.timezone);
+        Ok(exit)
+    }
+
+    /// The exit a tab's timezone, locale and position follow
+    pub async fn get_tab_exit_location(&self, tab_id: &str) -> Option<ExitLocation> {
+        self.exit_locations.read().await.get(tab_id).cloned()
+    }
+
     /// Apply request interception for blocked URLs
     async fn apply_request_interception(&self, page: &Page) -> Result<()> {
         let blocked_patterns = self.config.blocked_urls.clone();
@@ -877,8 +1672,7 @@ impl ChromiumEngine {
     }
 
     /// Apply fingerprint spoofing to prevent browser fingerprinting
-    async fn apply_fingerprint_spoofing(&self, page: &Page) -> Result<()> {
-        let fp = &self.config.fingerprint;
+    async fn apply_fingerprint_spoofing(&self, page: &Page, fp: &FingerprintConfig) -> Result<()> {
         let mut scripts = Vec::new();
         
         // Add static spoofing scripts based on config
@@ -1027,7 +1821,19 @@ impl ChromiumEngine {
             drop(browser);
         }
 
+        // Contexts went with the browser; only their relays are left
+        for (_, context) in self.contexts.write().await.drain() {
+            if let Some(relay) = context.relay {
+                let _ = relay.stop().await;
+            }
+        }
         self.tabs.write().await.clear();
+        self.pages.write().await.clear();
+        self.fingerprint_scripts.write().await.clear();
+        self.target_injectors.write().await.clear();
+        self.proxy_auth_failures.write().await.clear();
+        self.network_profiles.write().await.clear();
+        self.exit_locations.write().await.clear();
         *self.active_tab_id.write().await = None;
         *self.is_running.write().await = false;
 
@@ -1053,25 +1859,16 @@ impl ChromiumEngine {
     // ===== Enhanced CDP Commands =====
     
     /// Helper method to get a page for a specific tab
-    /// Note: Due to chromiumoxide's architecture, we use the active tab for CDP operations.
-    /// This is a known limitation where tab_id is validated but operations target the active page.
     async fn get_page_for_tab(&self, tab_id: &str) -> Result<Page> {
-        // Verify the tab exists
-        let tabs = self.tabs.read().await;
-        if !tabs.contains_key(tab_id) {
+        if !self.tabs.read().await.contains_key(tab_id) {
             return Err(anyhow!("Tab not found: {}", tab_id));
         }
-        drop(tabs);
-        
-        let browser = self.browser.as_ref()
-            .ok_or_else(|| anyhow!("Browser not launched"))?;
-        
-        let pages = browser.pages().await.map_err(|e| anyhow!("Failed to get pages: {}", e))?;
-        
-        // Use the first available page (active page)
-        // TODO: Implement proper page-to-tab mapping when chromiumoxide supports target IDs
-        pages.into_iter().next()
-            .ok_or_else(|| anyhow!("No pages available"))
+        self.pages
+            .read()
+            .await
+            .get(tab_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("No page for tab: {}", tab_id))
     }
     
     /// Execute custom JavaScript in a tab
@@ -1091,6 +1888,88 @@ impl ChromiumEngine {
         Ok(format!("{:?}", result))
     }
     
+    /// Gather WebRTC ICE candidates from inside a tab.
+    ///
+    /// Runs an `RTCPeerConnection` in the tab's page against `stun_servers`
+    /// (`host:port`), so candidates reflect the tab's own network path.
+    pub async fn gather_ice_candidates(&self, tab_id: &str, stun_servers: &[String], timeout: Duration) -> Result<Vec<String>> {
+        let page = self.get_page_for_tab(tab_id).await?;
+        let urls: Vec<String> = stun_servers.iter().map(|s| format!("stun:{}", s)).collect();
+        let script = format!(
+            r#"(async () => {{
+                const pc = new RTCPeerConnection({{ iceServers: [{{ urls: {} }}] }});
+                const found = [];
+                pc.createDataChannel('probe');
+                const done = new Promise(resolve => {{
+                    pc.onicecandidate = e => e.candidate ? found.push(e.candidate.candidate) : resolve(true);
+                    setTimeout(() => resolve(false), {});
+                }});
+                await pc.setLocalDescription(await pc.createOffer());
+                const complete = await done;
+                pc.close();
+                return {{ complete, found }};
+            }})()"#,
+            serde_json::to_string(&urls)?,
+            timeout.as_millis()
+        );
+
+        let result = page.evaluate_expression(script)
+            .await
+            .map_err(|e| anyhow!("Failed to gather ICE candidates: {}", e))?;
+        
+        // Update metrics
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.cdp_commands_sent += 1;
+        }
+        
+        #[derive(Deserialize)]
+        struct Gathered {
+            complete: bool,
+            found: Vec<String>,
+        }
+        let gathered: Gathered = result
+            .into_value()
+            .map_err(|e| anyhow!("Unexpected ICE candidate result: {}", e))?;
+        // Gathering that finished without candidates means none are exposed
+        if !gathered.complete && gathered.found.is_empty() {
+            return Err(anyhow!("ICE gathering did not complete in tab {}", tab_id));
+        }
+        Ok(gathered.found)
+    }
+    
+    /// Request `http://{host}/` for each of `hosts` from a tab's page
+    ///
+    /// Only the lookups matter: responses and network errors are ignored, and
+    /// requests still pending after `timeout` are abandoned.
+    pub async fn request_hosts(&self, tab_id: &str, hosts: &[String], timeout: Duration) -> Result<()> {
+        let page = self.get_page_for_tab(tab_id).await?;
+        let script = format!(
+            r#"(async () => {{
+                const hosts = {};
+                await Promise.allSettled(hosts.map(host => {{
+                    const abort = new AbortController();
+                    setTimeout(() => abort.abort(), {});
+                    return fetch('http://' + host + '/', {{ mode: 'no-cors', cache: 'no-store', signal: abort.signal }});
+                }}));
+                return hosts.length;
+            }})()"#,
+            serde_json::to_string(hosts)?,
+            timeout.as_millis()
+        );
+
+        page.evaluate_expression(script)
+            .await
+            .map_err(|e| anyhow!("Failed to request probe hosts: {}", e))?;
+
+        // Update metrics
+        {
+            let mut metrics = self.metrics.write().await;
+            metrics.cdp_commands_sent += 1;
+        }
+        Ok(())
+    }
+
     /// Capture screenshot of a tab
     pub async fn capture_screenshot(&self, tab_id: &str) -> Result<Vec<u8>> {
         let page = self.get_page_for_tab(tab_id).await?;
@@ -1129,10 +2008,12 @@ impl ChromiumEngine {
     /// Reload a tab
     pub async fn reload_tab(&self, tab_id: &str) -> Result<()> {
         let page = self.get_page_for_tab(tab_id).await?;
+        let start_time = Instant::now();
         
         page.reload()
             .await
             .map_err(|e| anyhow!("Failed to reload page: {}", e))?;
+        self.record_page_load(tab_id, start_time.elapsed().as_millis()).await;
         
         // Update metrics
         {
@@ -1172,11 +2053,141 @@ impl ChromiumEngine {
     }
 }
 
+/// Where a Chromium leak probe runs.
+enum ProbeTarget {
+    Tab(String),
+    /// A page opened for the check and closed after it
+    Profile {
+        proxy: Option<ProxySettings>,
+        fingerprint: Box<BrowserFingerprint>,
+    },
+}
+
+impl ProbeTarget {
+    /// Run `probe` in the target's tab, opening a page for a profile and closing it afterwards
+    async fn run<T, F, Fut>(&self, engine: &ChromiumEngine, probe: F) -> Result<T>
+    where
+        F: FnOnce(String) -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        match self {
+            ProbeTarget::Tab(tab_id) => probe(tab_id.clone()).await,
+            ProbeTarget::Profile { proxy, fingerprint } => {
+                let tab = engine
+                    .create_tab_with_fingerprint(None, proxy.clone(), Some(fingerprint.as_ref().clone()))
+                    .await?;
+                let result = probe(tab.id.clone()).await;
+                if let Err(e) = engine.close_tab(&tab.id).await {
+                    warn!("Failed to close leak-check tab {}: {}", tab.id, e);
+                }
+                result
+            }
+        }
+    }
+}
+
+/// Gathers WebRTC leak-check candidates from a tab in the integrated engine
+pub struct ChromiumIceSource {
+    engine: Arc<RwLock<Option<ChromiumEngine>>>,
+    target: ProbeTarget,
+}
+
+impl ChromiumIceSource {
+    /// Creates a source for `tab_id` in `engine`.
+    pub fn new(engine: Arc<RwLock<Option<ChromiumEngine>>>, tab_id: impl Into<String>) -> Self {
+        Self {
+            engine,
+            target: ProbeTarget::Tab(tab_id.into()),
+        }
+    }
+
+    /// Creates a source that opens a page in `engine` with a tab profile's
+    /// exit proxy and fingerprint, for tabs not open in the engine.
+    pub fn for_profile(
+        engine: Arc<RwLock<Option<ChromiumEngine>>>,
+        proxy: Option<ProxySettings>,
+        fingerprint: BrowserFingerprint,
+    ) -> Self {
+        Self {
+            engine,
+            target: ProbeTarget::Profile {
+                proxy,
+                fingerprint: Box::new(fingerprint),
+            },
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl virtual_ip::IceCandidateSource for ChromiumIceSource {
+    async fn gather_candidates(&self, stun_servers: &[String], timeout: Duration) -> Result<Vec<String>> {
+        let engine = self.engine.read().await;
+        let engine = engine.as_ref().ok_or_else(|| anyhow!("Chromium engine not running"))?;
+        self.target
+            .run(engine, |tab_id| async move { engine.gather_ice_candidates(&tab_id, stun_servers, timeout).await })
+            .await
+    }
+}
+
+/// Resolves DNS leak probes from a tab in the integrated engine
+///
+/// The page requests each probe name, so it is resolved wherever the tab's
+/// lookups go; the probe zone's report URL then says which resolvers asked.
+pub struct ChromiumDnsProbe {
+    engine: Arc<RwLock<Option<ChromiumEngine>>>,
+    target: ProbeTarget,
+}
+
+impl ChromiumDnsProbe {
+    /// Creates a probe for `tab_id` in `engine`.
+    pub fn new(engine: Arc<RwLock<Option<ChromiumEngine>>>, tab_id: impl Into<String>) -> Self {
+        Self {
+            engine,
+            target: ProbeTarget::Tab(tab_id.into()),
+        }
+    }
+
+    /// Creates a probe that opens a page in `engine` with a tab profile's
+    /// exit proxy and fingerprint, for tabs not open in the engine.
+    pub fn for_profile(
+        engine: Arc<RwLock<Option<ChromiumEngine>>>,
+        proxy: Option<ProxySettings>,
+        fingerprint: BrowserFingerprint,
+    ) -> Self {
+        Self {
+            engine,
+            target: ProbeTarget::Profile {
+                proxy,
+                fingerprint: Box::new(fingerprint),
+            },
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl virtual_ip::DnsProbeSource for ChromiumDnsProbe {
+    async fn resolve_probes(&self, config: &virtual_ip::LeakCheckConfig, names: &[String]) -> Result<Vec<IpAddr>> {
+        // A page cannot read DNS answers, so without the zone's report there is nothing to learn
+        if config.dns_probe_report_url.is_none() {
+            return Err(anyhow!("No DNS probe report URL configured"));
+        }
+        {
+            let engine = self.engine.read().await;
+            let engine = engine.as_ref().ok_or_else(|| anyhow!("Chromium engine not running"))?;
+            self.target
+                .run(engine, |tab_id| async move { engine.request_hosts(&tab_id, names, config.dns_timeout).await })
+                .await?;
+        }
+        virtual_ip::fetch_reported_resolvers(config, names).await
+    }
+}
+
 /// Manager for switching between system and integrated browser engines
 pub struct BrowserEngineManager {
     engine_type: Arc<RwLock<BrowserEngineType>>,
     chromium_engine: Arc<RwLock<Option<ChromiumEngine>>>,
     config: Arc<RwLock<ChromiumEngineConfig>>,
+    quarantine_manager: Option<Arc<ProxyQuarantineManager>>,
 }
 
 impl BrowserEngineManager {
@@ -1186,9 +2197,16 @@ impl BrowserEngineManager {
             engine_type: Arc::new(RwLock::new(BrowserEngineType::System)),
             chromium_engine: Arc::new(RwLock::new(None)),
             config: Arc::new(RwLock::new(ChromiumEngineConfig::default())),
+            quarantine_manager: None,
         }
     }
 
+    /// Report upstream proxies that reject a tab's credentials in launched engines
+    pub fn with_quarantine_manager(mut self, quarantine_manager: Arc<ProxyQuarantineManager>) -> Self {
+        self.quarantine_manager = Some(quarantine_manager);
+        self
+    }
+
     /// Get current engine type
     pub async fn get_engine_type(&self) -> BrowserEngineType {
         *self.engine_type.read().await
@@ -1217,6 +2235,9 @@ impl BrowserEngineManager {
                 // Launch Chromium engine
                 let config = self.config.read().await.clone();
                 let mut engine = ChromiumEngine::new(config);
+                if let Some(quarantine) = &self.quarantine_manager {
+                    engine = engine.with_quarantine_manager(quarantine.clone());
+                }
                 engine.launch().await?;
                 *self.chromium_engine.write().await = Some(engine);
             }
@@ -1263,7 +2284,7 @@ impl BrowserEngineManager {
 
     /// Check if integrated engine supports the requested proxy operation
     pub fn supports_per_tab_proxy(&self) -> bool {
-        // Integrated Chromium engine supports per-tab proxy via CDP
+        // Each Chromium tab runs in its own browser context with its own proxy
         true
     }
 
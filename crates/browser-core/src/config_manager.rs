@@ -14,6 +14,10 @@ use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info};
 
+use crate::backconnect::BackconnectGateway;
+use crate::custom_providers::ProviderDefinition;
+use crate::tor::TorConfig;
+
 /// Main application configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a AppConfig.
@@ -95,6 +99,15 @@ pub struct ProxyConfig {
     pub use_free_providers: bool,
     /// Preferred countries for proxy selection
     pub preferred_countries: Vec<String>,
+    /// User-defined proxy providers fetched alongside the built-in ones
+    #[serde(default)]
+    pub custom_providers: Vec<ProviderDefinition>,
+    /// Rotating gateways that take country/city/session targeting in the username
+    #[serde(default)]
+    pub backconnect_gateways: Vec<BackconnectGateway>,
+    /// Local tor daemon used as a proxy source
+    #[serde(default)]
+    pub tor: Option<TorConfig>,
 }
 
 impl Default for ProxyConfig {
@@ -111,6 +124,9 @@ impl Default for ProxyConfig {
             validation_timeout_ms: 5000,
             use_free_providers: true,
             preferred_countries: vec![],
+            custom_providers: vec![],
+            backconnect_gateways: vec![],
+            tor: None,
         }
     }
 }
@@ -596,6 +612,21 @@ impl ConfigManager {
                 warnings.push("Proxy enabled but no port configured".to_string());
             }
         }
+        for definition in &config.proxy.custom_providers {
+            if let Err(e) = crate::custom_providers::DeclarativeProvider::new(definition.clone()) {
+                warnings.push(format!("Invalid custom provider: {}", e));
+            }
+        }
+        for gateway in &config.proxy.backconnect_gateways {
+            if let Err(e) = crate::backconnect::BackconnectProvider::new(gateway.clone()) {
+                warnings.push(format!("Invalid backconnect gateway: {}", e));
+            }
+        }
+        if let Some(tor) = &config.proxy.tor {
+            if let Err(e) = crate::tor::TorProvider::new(tor.clone()) {
+                warnings.push(format!("Invalid Tor configuration: {}", e));
+            }
+        }
 
         // Validate performance settings
         if config.performance.max_tabs == 0 {
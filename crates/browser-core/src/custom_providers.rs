@@ -0,0 +1,505 @@
+//! Custom Proxy Providers
+//!
+//! Declarative, user-defined proxy sources loaded from configuration:
+//! - URL templates with optional pagination
+//! - Plain text, CSV, JSON (with a field path) and HTML table responses
+//! - Field mapping onto `FreeProxy`
+//! - Auth headers with `${ENV_VAR}` expansion
+//! - Per-provider rate limiting
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use scraper::{Html, Selector};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::free_ip_providers::ProxyProvider;
+use crate::http_client::HttpClient;
+use crate::proxy::{FreeProxy, ProxyType};
+use virtual_ip::CountryDatabase;
+
+fn default_pages() -> u32 {
+    1
+}
+
+fn default_rate_limit_ms() -> u64 {
+    1000
+}
+
+fn default_timeout_secs() -> u64 {
+    30
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_cell_selector() -> String {
+    "td".to_string()
+}
+
+fn default_protocol() -> String {
+    "http".to_string()
+}
+
+/// How a provider's response body is laid out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// One proxy per line: `ip:port` or `protocol://ip:port`
+    PlainText,
+    /// Delimited rows; fields are column indexes, or header names when `has_header` is set
+    Csv {
+        #[serde(default)]
+        delimiter: Option<char>,
+        #[serde(default)]
+        has_header: bool,
+    },
+    /// JSON array found at a dotted `path` (empty for the root); fields are dotted paths within each item
+    Json {
+        #[serde(default)]
+        path: String,
+    },
+    /// HTML table; fields are cell indexes within each row
+    HtmlTable {
+        row_selector: String,
+        #[serde(default = "default_cell_selector")]
+        cell_selector: String,
+    },
+}
+
+/// Where each `FreeProxy` field comes from in a parsed record
+///
+/// An `ip` value of the form `ip:port` is split when no `port` field is mapped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldMapping {
+    pub ip: String,
+    #[serde(default)]
+    pub port: Option<String>,
+    #[serde(default)]
+    pub protocol: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+    #[serde(default)]
+    pub country_code: Option<String>,
+    #[serde(default)]
+    pub anonymity: Option<String>,
+    /// Protocol used when no protocol field is mapped or it is unrecognized
+    #[serde(default = "default_protocol")]
+    pub default_protocol: String,
+}
+
+impl FieldMapping {
+    /// Mapping used when a definition does not specify one
+    pub fn default_for(format: &ResponseFormat) -> Self {
+        let (ip, port) = match format {
+            ResponseFormat::Json { .. } => ("ip", "port"),
+            _ => ("0", "1"),
+        };
+        Self {
+            ip: ip.to_string(),
+            port: Some(port.to_string()),
+            protocol: None,
+            country: None,
+            country_code: None,
+            anonymity: None,
+            default_protocol: default_protocol(),
+        }
+    }
+
+    fn references(&self) -> impl Iterator<Item = &String> {
+        std::iter::once(&self.ip)
+            .chain(self.port.iter())
+            .chain(self.protocol.iter())
+            .chain(self.country.iter())
+            .chain(self.country_code.iter())
+            .chain(self.anonymity.iter())
+    }
+}
+
+/// A user-defined proxy source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ProviderDefinition {
+    pub name: String,
+    /// URL to fetch; `{page}` is replaced with the page number
+    pub url: String,
+    /// Number of pages to fetch when the URL contains `{page}`
+    #[serde(default = "default_pages")]
+    pub pages: u32,
+    pub format: ResponseFormat,
+    #[serde(default)]
+    pub fields: Option<FieldMapping>,
+    /// Extra request headers; values may reference `${ENV_VAR}`
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Minimum delay between requests to this provider
+    #[serde(default = "default_rate_limit_ms")]
+    pub rate_limit_ms: u64,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+struct ProviderFile {
+    #[serde(default, alias = "providers")]
+    provider: Vec<ProviderDefinition>,
+}
+
+impl ProviderDefinition {
+    /// Load provider definitions from a TOML (`[[provider]]` tables) or JSON (array) file
+    pub fn load_file(path: &Path) -> Result<Vec<ProviderDefinition>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read provider definitions from {:?}", path))?;
+
+        if path.extension().is_some_and(|e| e == "json") {
+            serde_json::from_str(&content).context("Failed to parse JSON provider definitions")
+        } else {
+            let file: ProviderFile = toml::from_str(&content).context("Failed to parse TOML provider definitions")?;
+            Ok(file.provider)
+        }
+    }
+
+    /// The field mapping in effect for this definition
+    pub fn field_mapping(&self) -> FieldMapping {
+        self.fields.clone().unwrap_or_else(|| FieldMapping::default_for(&self.format))
+    }
+
+    /// URLs to fetch, one per page
+    pub fn page_urls(&self) -> Vec<String> {
+        if self.url.contains("{page}") {
+            (1..=self.pages.max(1)).map(|page| self.url.replace("{page}", &page.to_string())).collect()
+        } else {
+            vec![self.url.clone()]
+        }
+    }
+}
+
+/// Replace `${NAME}` with the value of environment variable NAME
+fn expand_env(value: &str) -> Result<String> {
+    let mut expanded = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unterminated variable in '{}'", value))?;
+        let name = &rest[start + 2..start + end];
+        let var = std::env::var(name).map_err(|_| anyhow!("Environment variable {} is not set", name))?;
+        expanded.push_str(&rest[..start]);
+        expanded.push_str(&var);
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn proxy_address(proxy: &FreeProxy) -> String {
+    format!("{}:{}", proxy.ip, proxy.port)
+}
+
+/// Alpha-2 code for an alpha-2 or alpha-3 code or an English country name
+fn iso_country_code(value: &str) -> Option<String> {
+    let value = value.trim();
+    CountryDatabase::find(value)
+        .or_else(|| CountryDatabase::find_by_name(value))
+        .map(|country| country.code.to_ascii_uppercase())
+}
+
+pub(crate) fn parse_protocol(value: &str) -> Option<ProxyType> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "http" => Some(ProxyType::Http),
+        "https" => Some(ProxyType::Https),
+        "socks4" | "socks4a" => Some(ProxyType::Socks4),
+        "socks5" | "socks5h" | "socks" => Some(ProxyType::Socks5),
+        "ss" | "shadowsocks" => Some(ProxyType::Shadowsocks),
+        _ => None,
+    }
+}
+
+/// A parsed record that fields can be looked up in
+enum Record<'a> {
+    Cells(Vec<String>, Option<&'a [String]>),
+    Json(&'a serde_json::Value),
+}
+
+impl Record<'_> {
+    fn get(&self, reference: &str) -> Option<String> {
+        let value = match self {
+            Record::Cells(cells, header) => {
+                let index = reference.parse::<usize>().ok().or_else(|| {
+                    header.and_then(|h| h.iter().position(|name| name.eq_ignore_ascii_case(reference)))
+                })?;
+                cells.get(index).cloned()
+            }
+            Record::Json(item) => json_path(item, reference).and_then(json_to_string),
+        }?;
+        let value = value.trim().to_string();
+        (!value.is_empty()).then_some(value)
+    }
+}
+
+fn json_path<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+    path.split('.').filter(|s| !s.is_empty()).try_fold(value, |current, segment| match current {
+        serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+        _ => current.get(segment),
+    })
+}
+
+fn json_to_string(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => Some(s.clone()),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        serde_json::Value::Bool(b) => Some(b.to_string()),
+        // Lists of protocols: prefer the most capable one
+        serde_json::Value::Array(items) => {
+            let values: Vec<String> = items.iter().filter_map(json_to_string).collect();
+            ["socks5", "socks4", "https", "http"]
+                .iter()
+                .find(|p| values.iter().any(|v| v.eq_ignore_ascii_case(p)))
+                .map(|p| p.to_string())
+                .or_else(|| values.into_iter().next())
+        }
+        _ => None,
+    }
+}
+
+pub(crate) fn split_delimited(line: &str, delimiter: char) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            c if c == delimiter && !in_quotes => fields.push(std::mem::take(&mut current)),
+            c => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+/// A proxy provider driven entirely by a [`ProviderDefinition`]
+pub struct DeclarativeProvider {
+    definition: ProviderDefinition,
+    fields: FieldMapping,
+    default_protocol: ProxyType,
+    http_client: HttpClient,
+}
+
+impl DeclarativeProvider {
+    /// Creates a new provider, checking the definition up front
+    pub fn new(definition: ProviderDefinition) -> Result<Self> {
+        if definition.name.trim().is_empty() {
+            return Err(anyhow!("Provider definition has no name"));
+        }
+        for url in definition.page_urls() {
+            url::Url::parse(&url).with_context(|| format!("Provider {}: invalid URL {}", definition.name, url))?;
+        }
+
+        let fields = definition.field_mapping();
+        let default_protocol = parse_protocol(&fields.default_protocol)
+            .ok_or_else(|| anyhow!("Provider {}: unknown default protocol {}", definition.name, fields.default_protocol))?;
+
+        match &definition.format {
+            ResponseFormat::HtmlTable { row_selector, cell_selector } => {
+                for selector in [row_selector, cell_selector] {
+                    Selector::parse(selector)
+                        .map_err(|e| anyhow!("Provider {}: invalid selector '{}': {:?}", definition.name, selector, e))?;
+                }
+                if let Some(field) = fields.references().find(|r| r.parse::<usize>().is_err()) {
+                    return Err(anyhow!("Provider {}: HTML table field '{}' must be a cell index", definition.name, field));
+                }
+            }
+            ResponseFormat::Csv { has_header: false, .. } => {
+                if let Some(field) = fields.references().find(|r| r.parse::<usize>().is_err()) {
+                    return Err(anyhow!(
+                        "Provider {}: CSV field '{}' must be a column index when there is no header",
+                        definition.name,
+                        field
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        let http_client = HttpClient::new()?;
+        Ok(Self {
+            definition,
+            fields,
+            default_protocol,
+            http_client,
+        })
+    }
+
+    /// The definition this provider was built from
+    pub fn definition(&self) -> &ProviderDefinition {
+        &self.definition
+    }
+
+    /// Parse one response body into proxies; malformed records and repeated
+    /// addresses are skipped
+    pub fn parse(&self, body: &str) -> Result<Vec<FreeProxy>> {
+        let proxies: Vec<FreeProxy> = match &self.definition.format {
+            ResponseFormat::PlainText => body
+                .lines()
+                .filter_map(|line| self.parse_plain_line(line))
+                .collect(),
+            ResponseFormat::Csv { delimiter, has_header } => {
+                let delimiter = delimiter.unwrap_or(',');
+                let mut lines = body.lines().filter(|l| !l.trim().is_empty());
+                let header: Option<Vec<String>> = if *has_header {
+                    lines.next().map(|l| split_delimited(l, delimiter).into_iter().map(|h| h.trim().to_string()).collect())
+                } else {
+                    None
+                };
+                lines
+                    .filter_map(|line| {
+                        self.build_proxy(&Record::Cells(split_delimited(line, delimiter), header.as_deref()))
+                    })
+                    .collect()
+            }
+            ResponseFormat::Json { path } => {
+                let root: serde_json::Value = serde_json::from_str(body)
+                    .with_context(|| format!("Provider {}: response is not JSON", self.definition.name))?;
+                let items = json_path(&root, path)
+                    .and_then(|v| v.as_array())
+                    .ok_or_else(|| anyhow!("Provider {}: no array at path '{}'", self.definition.name, path))?;
+                items.iter().filter_map(|item| self.build_proxy(&Record::Json(item))).collect()
+            }
+            ResponseFormat::HtmlTable { row_selector, cell_selector } => {
+                let document = Html::parse_document(body);
+                let rows = Selector::parse(row_selector).map_err(|e| anyhow!("Invalid row selector: {:?}", e))?;
+                let cells = Selector::parse(cell_selector).map_err(|e| anyhow!("Invalid cell selector: {:?}", e))?;
+                document
+                    .select(&rows)
+                    .filter_map(|row| {
+                        let values = row.select(&cells).map(|cell| cell.text().collect::<String>()).collect();
+                        self.build_proxy(&Record::Cells(values, None))
+                    })
+                    .collect()
+            }
+        };
+        let mut seen = HashSet::new();
+        Ok(proxies.into_iter().filter(|p| seen.insert(proxy_address(p))).collect())
+    }
+
+    fn parse_plain_line(&self, line: &str) -> Option<FreeProxy> {
+        let line = line.split_whitespace().next()?;
+        let (protocol, address) = match line.split_once("://") {
+            Some((scheme, rest)) => (parse_protocol(scheme), rest),
+            None => (None, line),
+        };
+        let (ip, port) = address.rsplit_once(':')?;
+        let port = port.parse::<u16>().ok()?;
+        Some(self.make_proxy(ip.to_string(), port, protocol.unwrap_or_else(|| self.default_protocol.clone())))
+    }
+
+    fn build_proxy(&self, record: &Record) -> Option<FreeProxy> {
+        let ip_value = record.get(&self.fields.ip)?;
+        let (ip, port) = match self.fields.port.as_ref().and_then(|field| record.get(field)) {
+            Some(port) => (ip_value, port.parse::<u16>().ok()?),
+            None => {
+                let (ip, port) = ip_value.rsplit_once(':')?;
+                (ip.to_string(), port.parse::<u16>().ok()?)
+            }
+        };
+        let protocol = self
+            .fields
+            .protocol
+            .as_ref()
+            .and_then(|field| record.get(field))
+            .and_then(|value| parse_protocol(&value))
+            .unwrap_or_else(|| self.default_protocol.clone());
+
+        let mut proxy = self.make_proxy(ip, port, protocol);
+        let country = self.fields.country.as_ref().and_then(|f| record.get(f));
+        let country_code = self.fields.country_code.as_ref().and_then(|f| record.get(f));
+        // Only ISO codes go into country_code, whichever field names the country
+        if let Some(code) = country_code.iter().chain(country.iter()).find_map(|value| iso_country_code(value)) {
+            proxy.country_code = code;
+        }
+        if let Some(name) = country.or(country_code) {
+            proxy.country = name;
+        }
+        if let Some(anonymity) = self.fields.anonymity.as_ref().and_then(|f| record.get(f)) {
+            proxy.anonymity = anonymity.to_lowercase();
+        }
+        Some(proxy)
+    }
+
+    fn make_proxy(&self, ip: String, port: u16, protocol: ProxyType) -> FreeProxy {
+        FreeProxy {
+            ip,
+            port,
+            protocol,
+            country: "Unknown".to_string(),
+            country_code: "XX".to_string(),
+            anonymity: "unknown".to_string(),
+            speed: 0,
+            uptime: 0.0,
+            last_checked: chrono::Utc::now().to_rfc3339(),
+            provider: self.definition.name.clone(),
+            is_working: false,
+            username: None,
+            password: None,
+            ssh: None,
+        }
+    }
+
+    async fn fetch_page(&self, url: &str) -> Result<String> {
+        let mut request = self
+            .http_client
+            .client()
+            .get(url)
+            .timeout(Duration::from_secs(self.definition.timeout_secs));
+        for (name, value) in &self.definition.headers {
+            request = request.header(name.as_str(), expand_env(value)?);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("Provider {}: request to {} failed: {}", self.definition.name, url, e))?;
+        if !response.status().is_success() {
+            return Err(anyhow!("Provider {}: {} returned {}", self.definition.name, url, response.status()));
+        }
+        Ok(response.text().await?)
+    }
+}
+
+#[async_trait]
+impl ProxyProvider for DeclarativeProvider {
+    fn name(&self) -> &str {
+        &self.definition.name
+    }
+
+    fn rate_limit(&self) -> Duration {
+        Duration::from_millis(self.definition.rate_limit_ms)
+    }
+
+    async fn fetch_proxies(&self) -> Result<Vec<FreeProxy>> {
+        let mut proxies = Vec::new();
+        let mut seen = HashSet::new();
+        for (i, url) in self.definition.page_urls().iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(self.rate_limit()).await;
+            }
+            let body = self.fetch_page(url).await?;
+            let page = self.parse(&body)?;
+            if page.is_empty() && i > 0 {
+                // Past the last page
+                break;
+            }
+            proxies.extend(page.into_iter().filter(|p| seen.insert(proxy_address(p))));
+        }
+        Ok(proxies)
+    }
+}
@@ -0,0 +1,132 @@
+//! Exit Location Module
+//!
+//! Timezone, locale, `Accept-Language` and position for a tab, derived from
+//! where its traffic leaves the proxy, so pages see a visitor consistent with
+//! the IP they are served to.
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use virtual_ip::{Country, CountryDatabase, VirtualIP};
+
+use crate::chromium_engine::Geolocation;
+use crate::http_client::PublicIpInfo;
+
+/// Farthest a reported position strays from the city centre, in kilometres
+const MAX_JITTER_KM: f64 = 8.0;
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// Where a tab's traffic exits and the locale a visitor from there presents
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExitLocation {
+    pub ip: String,
+    pub country_code: String,
+    pub city: Option<String>,
+    /// IANA timezone
+    pub timezone: String,
+    /// BCP-47 locale, e.g. `de-DE`
+    pub locale: String,
+    pub accept_language: String,
+    /// City centre, or the capital's when the city's position is unknown
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+impl ExitLocation {
+    /// From a tab's virtual IP
+    pub fn from_virtual_ip(ip: &VirtualIP) -> Self {
+        let country = CountryDatabase::find(&ip.country_code);
+        let locale = non_empty(&ip.language)
+            .or_else(|| country.as_ref().and_then(|c| non_empty(&c.language)))
+            .unwrap_or_else(|| "en-US".to_string());
+        let accept_language = non_empty(&ip.accept_language).unwrap_or_else(|| match &country {
+            Some(country) => country.accept_language(&locale),
+            None => locale.clone(),
+        });
+        let timezone = non_empty(&ip.timezone)
+            .or_else(|| country.as_ref().and_then(|c| non_empty(&c.timezone)))
+            .unwrap_or_else(|| "UTC".to_string());
+        let (latitude, longitude) = capital(country.as_ref());
+
+        Self {
+            ip: ip.ip.to_string(),
+            country_code: ip.country_code.to_ascii_uppercase(),
+            city: non_empty(&ip.city).filter(|c| c != "Unknown"),
+            timezone,
+            locale,
+            accept_language,
+            latitude,
+            longitude,
+        }
+    }
+
+    /// From an exit observed through the tab's proxy; None when its country is unknown
+    pub fn from_public_ip(info: &PublicIpInfo) -> Option<Self> {
+        let country = CountryDatabase::find(info.country_code.as_deref()?)?;
+        let locale = non_empty(&country.language).unwrap_or_else(|| "en-US".to_string());
+        let (latitude, longitude) = match (info.lat, info.lon) {
+            (Some(lat), Some(lon)) => (Some(lat), Some(lon)),
+            _ => capital(Some(&country)),
+        };
+
+        Some(Self {
+            ip: info.ip.trim().to_string(),
+            country_code: country.code.clone(),
+            city: info.city.as_deref().and_then(non_empty),
+            timezone: info
+                .timezone
+                .as_deref()
+                .and_then(non_empty)
+                .unwrap_or_else(|| country.timezone.clone()),
+            accept_language: country.accept_language(&locale),
+            locale,
+            latitude,
+            longitude,
+        })
+    }
+
+    /// Use a more precise city position, e.g. from a GeoIP record
+    pub fn with_coordinates(mut self, latitude: f64, longitude: f64) -> Self {
+        self.latitude = Some(latitude);
+        self.longitude = Some(longitude);
+        self
+    }
+
+    /// Position to report, within `MAX_JITTER_KM` of the centre.
+    ///
+    /// The same seed on the same exit gives the same position, so a tab does
+    /// not wander between page loads.
+    pub fn geolocation(&self, seed: &str) -> Option<Geolocation> {
+        let (latitude, longitude) = (self.latitude?, self.longitude?);
+        // FNV-1a and ChaCha8, so positions stay stable across builds, platforms and rand versions
+        let seed = format!("{}|{}", seed, self.ip)
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
+        // Uniform over the disc rather than bunched at the centre
+        let distance = MAX_JITTER_KM * rng.gen::<f64>().sqrt();
+        let bearing = rng.gen_range(0.0..std::f64::consts::TAU);
+        let lat_offset = distance * bearing.cos() / KM_PER_DEGREE;
+        let lon_offset = distance * bearing.sin() / (KM_PER_DEGREE * latitude.to_radians().cos().max(0.01));
+
+        Some(Geolocation {
+            latitude: (latitude + lat_offset).clamp(-90.0, 90.0),
+            longitude: (longitude + lon_offset + 180.0).rem_euclid(360.0) - 180.0,
+            // Wi-Fi positioning accuracy
+            accuracy: rng.gen_range(20.0..150.0),
+        })
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+fn capital(country: Option<&Country>) -> (Option<f64>, Option<f64>) {
+    match country.and_then(|c| c.capital.as_ref()) {
+        Some(capital) => (Some(capital.latitude), Some(capital.longitude)),
+        None => (None, None),
+    }
+}
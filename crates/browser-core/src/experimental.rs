@@ -241,6 +241,8 @@ impl Default for MeshProxyNetwork {
 }
 
 /// EXP-2004: Onion Routing Integration
+///
+/// Circuit bookkeeping only; routing through a real tor daemon is done by [`crate::tor::TorProvider`].
 pub struct OnionRouter {
     circuit_hops: u8,
     active_circuits: u32,
@@ -5,8 +5,19 @@
 //! - WebGL fingerprint randomization
 //! - Audio context fingerprint spoofing
 //! - User-Agent rotation
+//! - Seeded generation from real-world attribute distributions
+//! - Per-profile readback noise seeds with the hashes they produce
 
+use anyhow::{anyhow, Result};
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock};
+use virtual_ip::VirtualIP;
+
+use crate::fingerprint_noise::{self, NoiseRotation, OPAQUE_ORIGIN};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a BrowserFingerprint.
@@ -21,6 +32,357 @@ pub struct BrowserFingerprint {
     pub platform: String,
     pub webgl_vendor: String,
     pub webgl_renderer: String,
+    /// Canvas hash the noise seed produces for `PROBE_SCRIPT`'s fixed drawing on a
+    /// new tab's opaque origin. Sites draw their own content on their own origin, so
+    /// their canvas hashes differ; `ChromiumEngine::probe_noise_hashes` reads a page's.
     pub canvas_hash: String,
+    /// Audio hash the noise seed produces for `PROBE_SCRIPT`'s fixed buffer, like
+    /// `canvas_hash`
     pub audio_hash: String,
+    /// Seed for canvas, audio and WebGL readback noise
+    #[serde(default)]
+    pub noise_seed: u64,
+}
+
+impl BrowserFingerprint {
+    /// Use `seed` for readback noise and record the hashes it produces.
+    pub fn with_noise_seed(mut self, seed: u64) -> Self {
+        self.set_noise_seed(seed);
+        self
+    }
+
+    /// Switch to a new noise seed; noise never changes except through this.
+    pub fn rotate_noise(&mut self, seed: u64, reason: impl Into<String>) -> NoiseRotation {
+        let rotation = NoiseRotation::new(self.noise_seed, seed, reason);
+        self.set_noise_seed(seed);
+        rotation
+    }
+
+    fn set_noise_seed(&mut self, seed: u64) {
+        self.noise_seed = seed;
+        self.canvas_hash = fingerprint_noise::canvas_probe_hash(seed, OPAQUE_ORIGIN);
+        self.audio_hash = fingerprint_noise::audio_probe_hash(seed, OPAQUE_ORIGIN);
+    }
+}
+
+/// Browser attribute distributions bundled with the crate.
+const BUNDLED_FINGERPRINTS: &str = include_str!("../data/fingerprints.json");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A value with its relative frequency.
+pub struct Weighted<T> {
+    pub value: T,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A GPU as reported through `WEBGL_debug_renderer_info`.
+///
+/// Chromium browsers report the ANGLE renderer under `Google Inc. (<vendor>)`;
+/// Firefox reports a bucketed vendor and renderer.
+pub struct GpuProfile {
+    pub weight: f64,
+    pub vendor: String,
+    pub angle_renderer: String,
+    pub firefox_vendor: String,
+    pub firefox_renderer: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A class of hardware (such as "entry laptop") whose attributes are sampled together.
+pub struct DeviceClass {
+    pub name: String,
+    pub weight: f64,
+    pub screens: Vec<Weighted<(u32, u32)>>,
+    pub hardware_concurrency: Vec<Weighted<u8>>,
+    pub device_memory: Vec<Weighted<u8>>,
+    pub color_depth: Vec<Weighted<u8>>,
+    pub gpus: Vec<GpuProfile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Attribute distributions for one operating system.
+pub struct OsProfile {
+    pub name: String,
+    pub platform: String,
+    /// User-Agent OS token by browser engine
+    pub ua_tokens: HashMap<String, String>,
+    pub browsers: Vec<Weighted<String>>,
+    pub devices: Vec<DeviceClass>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A released browser version.
+pub struct BrowserVersion {
+    pub value: String,
+    /// Underlying Chromium version for Blink-based browsers
+    #[serde(default)]
+    pub chromium: Option<String>,
+    pub weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A browser and its User-Agent template.
+pub struct BrowserProfile {
+    pub name: String,
+    /// `blink`, `gecko` or `webkit`
+    pub engine: String,
+    /// Template with `{os}`, `{version}` and `{chromium}` placeholders
+    pub user_agent: String,
+    /// Fixed WebGL vendor and renderer, for browsers that hide the GPU
+    #[serde(default)]
+    pub webgl: Option<(String, String)>,
+    pub versions: Vec<BrowserVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Real-world distributions of browser attributes.
+pub struct FingerprintDataset {
+    /// When the distributions were collected
+    pub collected: String,
+    pub os: Vec<OsProfile>,
+    pub browsers: Vec<BrowserProfile>,
+    /// OS share by country code, with a `default` entry
+    pub country_os: HashMap<String, HashMap<String, f64>>,
+    /// Per-country multipliers applied to an OS's browser shares
+    #[serde(default)]
+    pub country_browsers: HashMap<String, HashMap<String, f64>>,
+}
+
+impl FingerprintDataset {
+    /// The dataset bundled with the crate.
+    pub fn bundled() -> Arc<Self> {
+        static DATASET: OnceLock<Arc<FingerprintDataset>> = OnceLock::new();
+        DATASET
+            .get_or_init(|| Arc::new(serde_json::from_str(BUNDLED_FINGERPRINTS).expect("Bundled fingerprint data is valid")))
+            .clone()
+    }
+
+    /// Look up an OS by name, ignoring case.
+    pub fn os(&self, name: &str) -> Option<&OsProfile> {
+        self.os.iter().find(|o| o.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Look up a browser by name, ignoring case.
+    pub fn browser(&self, name: &str) -> Option<&BrowserProfile> {
+        self.browsers.iter().find(|b| b.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+/// What to generate a fingerprint for. Unset fields are sampled.
+pub struct FingerprintRequest {
+    pub seed: u64,
+    pub os: Option<String>,
+    pub browser: Option<String>,
+    /// Only browsers on this engine (`blink`, `gecko` or `webkit`)
+    pub engine: Option<String>,
+    pub country_code: Option<String>,
+    pub accept_language: Option<String>,
+    pub timezone: Option<String>,
+}
+
+impl FingerprintRequest {
+    /// Creates a request; the same seed and request always give the same fingerprint.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Default::default()
+        }
+    }
+
+    /// Seed derived from a stable profile identifier such as a tab ID.
+    pub fn for_profile(profile_id: &str) -> Self {
+        // FNV-1a, so seeds stay stable across builds and platforms
+        let seed = profile_id
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+        Self::new(seed)
+    }
+
+    /// Fix the operating system.
+    pub fn with_os(mut self, os: impl Into<String>) -> Self {
+        self.os = Some(os.into());
+        self
+    }
+
+    /// Fix the browser.
+    pub fn with_browser(mut self, browser: impl Into<String>) -> Self {
+        self.browser = Some(browser.into());
+        self
+    }
+
+    /// Restrict to browsers on `engine`, such as `blink` for pages rendered by Chromium.
+    pub fn with_engine(mut self, engine: impl Into<String>) -> Self {
+        self.engine = Some(engine.into());
+        self
+    }
+
+    /// Condition on a virtual IP's country and take its language and timezone.
+    pub fn with_virtual_ip(mut self, ip: &VirtualIP) -> Self {
+        self.country_code = Some(ip.country_code.clone());
+        self.accept_language = Some(if ip.accept_language.is_empty() {
+            ip.language.clone()
+        } else {
+            ip.accept_language.clone()
+        });
+        self.timezone = Some(ip.timezone.clone());
+        self
+    }
+}
+
+/// Samples coherent fingerprints from a `FingerprintDataset`.
+///
+/// OS is drawn by country, then browser by OS, then one device class whose
+/// screen, cores, memory and GPU are drawn together.
+#[derive(Debug, Clone)]
+pub struct FingerprintGenerator {
+    dataset: Arc<FingerprintDataset>,
+}
+
+impl Default for FingerprintGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FingerprintGenerator {
+    /// Creates a generator over the bundled dataset.
+    pub fn new() -> Self {
+        Self::with_dataset(FingerprintDataset::bundled())
+    }
+
+    /// Creates a generator over a custom dataset.
+    pub fn with_dataset(dataset: Arc<FingerprintDataset>) -> Self {
+        Self { dataset }
+    }
+
+    /// Returns the dataset.
+    pub fn dataset(&self) -> &FingerprintDataset {
+        &self.dataset
+    }
+
+    /// Generate a fingerprint for `request`.
+    pub fn generate(&self, request: &FingerprintRequest) -> Result<BrowserFingerprint> {
+        let mut rng = ChaCha8Rng::seed_from_u64(request.seed);
+        let country = request.country_code.as_deref().map(str::to_ascii_uppercase);
+
+        let os = self.pick_os(&mut rng, request, country.as_deref())?;
+        let browser = self.pick_browser(&mut rng, request, os, country.as_deref())?;
+        let version = pick(&mut rng, &browser.versions, |v| v.weight)?;
+        let device = pick(&mut rng, &os.devices, |d| d.weight)?;
+        let screen = pick(&mut rng, &device.screens, |s| s.weight)?.value;
+        let hardware_concurrency = pick(&mut rng, &device.hardware_concurrency, |h| h.weight)?.value;
+        let device_memory = pick(&mut rng, &device.device_memory, |m| m.weight)?.value;
+        let color_depth = pick(&mut rng, &device.color_depth, |c| c.weight)?.value;
+        let gpu = pick(&mut rng, &device.gpus, |g| g.weight)?;
+
+        let (webgl_vendor, webgl_renderer) = match (&browser.webgl, browser.engine.as_str()) {
+            (Some(fixed), _) => fixed.clone(),
+            (None, "gecko") => (gpu.firefox_vendor.clone(), gpu.firefox_renderer.clone()),
+            (None, _) => (format!("Google Inc. ({})", gpu.vendor), gpu.angle_renderer.clone()),
+        };
+        let ua_token = os
+            .ua_tokens
+            .get(&browser.engine)
+            .ok_or_else(|| anyhow!("{} has no {} User-Agent token", os.name, browser.engine))?;
+        let user_agent = browser
+            .user_agent
+            .replace("{os}", ua_token)
+            .replace("{chromium}", version.chromium.as_deref().unwrap_or(&version.value))
+            .replace("{version}", &version.value);
+
+        Ok(BrowserFingerprint {
+            user_agent,
+            accept_language: request.accept_language.clone().unwrap_or_else(|| "en-US,en;q=0.9".to_string()),
+            timezone: request.timezone.clone().unwrap_or_else(|| "UTC".to_string()),
+            screen_resolution: screen,
+            color_depth,
+            hardware_concurrency,
+            device_memory,
+            platform: os.platform.clone(),
+            webgl_vendor,
+            webgl_renderer,
+            canvas_hash: String::new(),
+            audio_hash: String::new(),
+            noise_seed: 0,
+        }
+        .with_noise_seed(rng.gen()))
+    }
+
+    /// Generate a fingerprint matching a virtual IP's country, language and timezone.
+    pub fn generate_for_ip(&self, ip: &VirtualIP, seed: u64) -> Result<BrowserFingerprint> {
+        self.generate(&FingerprintRequest::new(seed).with_virtual_ip(ip))
+    }
+
+    fn pick_os<'a, R: Rng>(&'a self, rng: &mut R, request: &FingerprintRequest, country: Option<&str>) -> Result<&'a OsProfile> {
+        if let Some(name) = &request.os {
+            return self.dataset.os(name).ok_or_else(|| anyhow!("Unknown OS: {}", name));
+        }
+        let shares = country
+            .and_then(|c| self.dataset.country_os.get(c))
+            .or_else(|| self.dataset.country_os.get("default"));
+        // Only OSes the requested browser, or a browser on the requested engine, runs on
+        let candidates: Vec<&OsProfile> = self
+            .dataset
+            .os
+            .iter()
+            .filter(|o| match &request.browser {
+                Some(b) => o.browsers.iter().any(|w| w.value.eq_ignore_ascii_case(b)),
+                None => o.browsers.iter().any(|w| self.on_engine(&w.value, request)),
+            })
+            .collect();
+        let os = pick(rng, &candidates, |o| shares.and_then(|s| s.get(&o.name).copied()).unwrap_or(1.0))
+            .map_err(|_| match &request.browser {
+                Some(b) => anyhow!("No OS supports browser {}", b),
+                None => anyhow!("No OS has a {} browser", request.engine.as_deref().unwrap_or("")),
+            })?;
+        Ok(*os)
+    }
+
+    fn pick_browser<'a, R: Rng>(
+        &'a self,
+        rng: &mut R,
+        request: &FingerprintRequest,
+        os: &OsProfile,
+        country: Option<&str>,
+    ) -> Result<&'a BrowserProfile> {
+        let name = match &request.browser {
+            Some(name) => {
+                if !os.browsers.iter().any(|b| b.value.eq_ignore_ascii_case(name)) {
+                    return Err(anyhow!("{} is not available on {}", name, os.name));
+                }
+                if !self.on_engine(name, request) {
+                    return Err(anyhow!("{} does not run on {}", name, request.engine.as_deref().unwrap_or("")));
+                }
+                name.clone()
+            }
+            None => {
+                let multipliers = country.and_then(|c| self.dataset.country_browsers.get(c));
+                let browsers: Vec<&Weighted<String>> =
+                    os.browsers.iter().filter(|b| self.on_engine(&b.value, request)).collect();
+                pick(rng, &browsers, |b| {
+                    b.weight * multipliers.and_then(|m| m.get(&b.value).copied()).unwrap_or(1.0)
+                })?
+                .value
+                .clone()
+            }
+        };
+        self.dataset.browser(&name).ok_or_else(|| anyhow!("Unknown browser: {}", name))
+    }
+
+    /// Whether `browser` satisfies the request's engine, if it names one
+    fn on_engine(&self, browser: &str, request: &FingerprintRequest) -> bool {
+        match &request.engine {
+            Some(engine) => self.dataset.browser(browser).is_some_and(|b| b.engine.eq_ignore_ascii_case(engine)),
+            None => true,
+        }
+    }
+}
+
+/// Draw one item by weight, failing on empty or all-zero distributions.
+fn pick<'a, T, R: Rng>(rng: &mut R, items: &'a [T], weight: impl Fn(&T) -> f64) -> Result<&'a T> {
+    let index = WeightedIndex::new(items.iter().map(|i| weight(i).max(0.0)))
+        .map_err(|e| anyhow!("Invalid fingerprint distribution: {}", e))?;
+    Ok(&items[index.sample(rng)])
 }
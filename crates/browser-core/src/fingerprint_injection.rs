@@ -0,0 +1,320 @@
+//! Fingerprint Injection Module
+//!
+//! Turns a tab's `BrowserFingerprint` into what Chromium needs to present it:
+//! - User-Agent override with client hints (`Sec-CH-UA*` and `navigator.userAgentData`)
+//! - Timezone, locale and screen values for the CDP Emulation domain
+//! - A new-document script for navigator, screen and WebGL values and seeded
+//!   canvas/audio/WebGL readback noise, applied to same-origin iframes as well;
+//!   `target_injection` evaluates it in out-of-process iframes and workers
+
+use serde::Serialize;
+
+use crate::fingerprint::BrowserFingerprint;
+use crate::fingerprint_noise::NOISE_SCRIPT;
+
+/// A brand and version as listed in `Sec-CH-UA`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BrandVersion {
+    pub brand: String,
+    pub version: String,
+}
+
+impl BrandVersion {
+    fn new(brand: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            brand: brand.into(),
+            version: version.into(),
+        }
+    }
+}
+
+/// User-Agent client hints; field names match `NavigatorUAData.getHighEntropyValues`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientHints {
+    /// Major versions, including Chromium's GREASE brand
+    pub brands: Vec<BrandVersion>,
+    pub full_version_list: Vec<BrandVersion>,
+    pub ua_full_version: String,
+    pub platform: String,
+    pub platform_version: String,
+    pub architecture: String,
+    pub model: String,
+    pub mobile: bool,
+    pub bitness: String,
+    pub wow64: bool,
+}
+
+/// Version token following `marker`, e.g. "140.0.0.0" for "Chrome/".
+fn version_after<'a>(ua: &'a str, marker: &str) -> Option<&'a str> {
+    let start = ua.find(marker)? + marker.len();
+    let version = ua[start..].split([' ', ';', ')']).next()?;
+    (!version.is_empty()).then_some(version)
+}
+
+/// Chromium's brand list: a GREASE brand, "Chromium" and the product, in the order
+/// Chromium derives from its major version. `version` formats each version.
+fn brand_list(major: u32, chromium: &str, product: (&str, &str), version: impl Fn(&str) -> String) -> Vec<BrandVersion> {
+    const GREASE_CHARS: [char; 11] = [' ', '(', ':', '-', '.', '/', ')', ';', '=', '?', '_'];
+    const GREASE_VERSIONS: [&str; 3] = ["8", "99", "24"];
+    const ORDERS: [[usize; 3]; 6] = [[0, 1, 2], [0, 2, 1], [1, 0, 2], [1, 2, 0], [2, 0, 1], [2, 1, 0]];
+
+    let seed = major as usize;
+    let grease = format!(
+        "Not{}A{}Brand",
+        GREASE_CHARS[seed % GREASE_CHARS.len()],
+        GREASE_CHARS[(seed + 1) % GREASE_CHARS.len()]
+    );
+    let entries = [
+        BrandVersion::new(grease, version(GREASE_VERSIONS[seed % GREASE_VERSIONS.len()])),
+        BrandVersion::new("Chromium", version(chromium)),
+        BrandVersion::new(product.0, version(product.1)),
+    ];
+    let mut list = vec![BrandVersion::new("", ""); 3];
+    for (entry, slot) in entries.into_iter().zip(ORDERS[seed % ORDERS.len()]) {
+        list[slot] = entry;
+    }
+    list
+}
+
+impl ClientHints {
+    /// Client hints a Chromium-based browser with this User-Agent sends; `None` for
+    /// Firefox and Safari, which have no `navigator.userAgentData`.
+    pub fn from_user_agent(ua: &str) -> Option<Self> {
+        // Chrome on iOS (CriOS) is WebKit underneath
+        let chrome_version = version_after(ua, "Chrome/")?;
+        let (product, product_version) = if let Some(v) = version_after(ua, "Edg/") {
+            ("Microsoft Edge", v)
+        } else if let Some(v) = version_after(ua, "OPR/") {
+            ("Opera", v)
+        } else {
+            ("Google Chrome", chrome_version)
+        };
+        let major = |v: &str| v.split('.').next().unwrap_or(v).to_string();
+        let full = |v: &str| if v.contains('.') { v.to_string() } else { format!("{}.0.0.0", v) };
+        let chrome_major: u32 = major(chrome_version).parse().ok()?;
+        let brands = brand_list(chrome_major, chrome_version, (product, product_version), major);
+        let full_version_list = brand_list(chrome_major, chrome_version, (product, product_version), full);
+
+        let mobile = ua.contains("Mobile");
+        let (platform, platform_version, architecture, model) = if let Some(rest) = ua.split("Android ").nth(1) {
+            let version = rest.split([';', ')']).next().unwrap_or("").trim();
+            let model = rest.split(';').nth(1).map(|m| m.split(')').next().unwrap_or("").trim()).unwrap_or("");
+            let version = match version.matches('.').count() {
+                0 => format!("{}.0.0", version),
+                1 => format!("{}.0", version),
+                _ => version.to_string(),
+            };
+            ("Android", version, "", model)
+        } else if ua.contains("Windows") {
+            ("Windows", "10.0.0".to_string(), "x86", "")
+        } else if ua.contains("CrOS") {
+            ("Chrome OS", "".to_string(), "x86", "")
+        } else if let Some(version) = version_after(ua, "Mac OS X ") {
+            ("macOS", version.replace('_', "."), "arm", "")
+        } else {
+            ("Linux", "".to_string(), "x86", "")
+        };
+
+        Some(Self {
+            brands,
+            full_version_list,
+            ua_full_version: full(product_version),
+            platform: platform.to_string(),
+            platform_version,
+            architecture: architecture.to_string(),
+            model: model.to_string(),
+            mobile,
+            bitness: if architecture.is_empty() { String::new() } else { "64".to_string() },
+            wow64: false,
+        })
+    }
+}
+
+/// Everything applied to a tab so it presents one `BrowserFingerprint`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FingerprintInjection {
+    pub user_agent: String,
+    /// Sent as Accept-Language
+    pub accept_language: String,
+    /// `navigator.languages`, from Accept-Language without q-values
+    pub languages: Vec<String>,
+    /// ICU locale for `Emulation.setLocaleOverride`
+    pub locale: String,
+    pub timezone: String,
+    /// `navigator.platform`
+    pub platform: String,
+    pub screen_width: u32,
+    pub screen_height: u32,
+    pub avail_width: u32,
+    pub avail_height: u32,
+    pub color_depth: u8,
+    pub hardware_concurrency: u8,
+    pub device_memory: u8,
+    pub webgl_vendor: String,
+    pub webgl_renderer: String,
+    pub mobile: bool,
+    /// Readback noise seed as 16 hex digits, see `fingerprint_noise`
+    pub noise_seed: String,
+    /// `None` removes `navigator.userAgentData`, as in Firefox and Safari
+    pub client_hints: Option<ClientHints>,
+}
+
+impl FingerprintInjection {
+    /// Values for a fingerprint; screen work area leaves room for the OS taskbar or menu bar.
+    pub fn from_fingerprint(fp: &BrowserFingerprint) -> Self {
+        let languages: Vec<String> = fp
+            .accept_language
+            .split(',')
+            .map(|part| part.split(';').next().unwrap_or("").trim().to_string())
+            .filter(|lang| !lang.is_empty())
+            .collect();
+        let languages = if languages.is_empty() { vec!["en-US".to_string()] } else { languages };
+        let locale = languages[0].clone();
+        let (width, height) = fp.screen_resolution;
+        let reserved = if fp.user_agent.contains("Windows") {
+            48
+        } else if fp.user_agent.contains("Macintosh") {
+            25
+        } else {
+            0
+        };
+
+        Self {
+            user_agent: fp.user_agent.clone(),
+            accept_language: fp.accept_language.clone(),
+            languages,
+            locale,
+            timezone: fp.timezone.clone(),
+            platform: fp.platform.clone(),
+            screen_width: width,
+            screen_height: height,
+            avail_width: width,
+            avail_height: height.saturating_sub(reserved),
+            color_depth: fp.color_depth,
+            hardware_concurrency: fp.hardware_concurrency,
+            device_memory: fp.device_memory,
+            webgl_vendor: fp.webgl_vendor.clone(),
+            webgl_renderer: fp.webgl_renderer.clone(),
+            mobile: fp.user_agent.contains("Mobile") || fp.user_agent.contains("Android"),
+            noise_seed: format!("{:016x}", fp.noise_seed),
+            client_hints: ClientHints::from_user_agent(&fp.user_agent),
+        }
+    }
+
+    /// Script for `Page.addScriptToEvaluateOnNewDocument`.
+    ///
+    /// Values are embedded as JSON, so fingerprint strings cannot break out of the script.
+    pub fn script(&self) -> String {
+        let values = serde_json::to_string(self).unwrap_or_else(|_| "{}".to_string());
+        INJECTION_SCRIPT
+            .replace("__NOISE__", NOISE_SCRIPT)
+            .replace("__FINGERPRINT__", &values)
+    }
+}
+
+/// Patches a window or worker global, and same-origin iframe windows as they are read.
+const INJECTION_SCRIPT: &str = r#"(() => {
+    const patch = function patch(scope, fp) {
+        const marker = Symbol.for('__fingerprint_applied');
+        if (!scope || scope[marker]) return;
+        Object.defineProperty(scope, marker, { value: true });
+
+        // Keep patched functions looking native to Function.prototype.toString
+        const natives = new WeakMap();
+        const originalToString = scope.Function.prototype.toString;
+        const patchedToString = function toString() {
+            return natives.has(this) ? natives.get(this) : originalToString.call(this);
+        };
+        natives.set(patchedToString, 'function toString() { [native code] }');
+        scope.Function.prototype.toString = patchedToString;
+        const native = (fn, name) => { natives.set(fn, `function ${name}() { [native code] }`); return fn; };
+
+        const getter = (proto, name, value) => {
+            if (!proto) return;
+            try {
+                const desc = Object.getOwnPropertyDescriptor(proto, name);
+                Object.defineProperty(proto, name, {
+                    get: native(function () { return value; }, `get ${name}`),
+                    enumerable: desc ? desc.enumerable : true,
+                    configurable: true,
+                });
+            } catch (e) {}
+        };
+
+        const nav = (scope.Navigator || scope.WorkerNavigator || {}).prototype;
+        getter(nav, 'userAgent', fp.userAgent);
+        getter(nav, 'appVersion', fp.userAgent.replace(/^Mozilla\//, ''));
+        getter(nav, 'platform', fp.platform);
+        getter(nav, 'language', fp.languages[0]);
+        getter(nav, 'languages', Object.freeze(fp.languages.slice()));
+        getter(nav, 'hardwareConcurrency', fp.hardwareConcurrency);
+        // deviceMemory and userAgentData only exist in Chromium
+        if (fp.clientHints) {
+            getter(nav, 'deviceMemory', fp.deviceMemory);
+        } else if (nav) {
+            delete nav.deviceMemory;
+        }
+
+        const hints = fp.clientHints;
+        if (hints && scope.NavigatorUAData) {
+            const uaData = scope.NavigatorUAData.prototype;
+            const low = () => ({ brands: hints.brands, mobile: hints.mobile, platform: hints.platform });
+            getter(uaData, 'brands', Object.freeze(hints.brands.map((b) => Object.freeze({ ...b }))));
+            getter(uaData, 'mobile', hints.mobile);
+            getter(uaData, 'platform', hints.platform);
+            uaData.getHighEntropyValues = native(function getHighEntropyValues(keys) {
+                const values = low();
+                for (const key of keys || []) {
+                    if (key in hints) values[key] = hints[key];
+                }
+                return Promise.resolve(values);
+            }, 'getHighEntropyValues');
+            uaData.toJSON = native(function toJSON() { return low(); }, 'toJSON');
+        } else if (nav) {
+            delete nav.userAgentData;
+        }
+
+        if (scope.Screen) {
+            const screen = scope.Screen.prototype;
+            getter(screen, 'width', fp.screenWidth);
+            getter(screen, 'height', fp.screenHeight);
+            getter(screen, 'availWidth', fp.availWidth);
+            getter(screen, 'availHeight', fp.availHeight);
+            getter(screen, 'colorDepth', fp.colorDepth);
+            getter(screen, 'pixelDepth', fp.colorDepth);
+        }
+
+        for (const name of ['WebGLRenderingContext', 'WebGL2RenderingContext']) {
+            const ctx = scope[name];
+            if (!ctx) continue;
+            const originalGetParameter = ctx.prototype.getParameter;
+            ctx.prototype.getParameter = native(function getParameter(parameter) {
+                if (parameter === 37445) return fp.webglVendor;
+                if (parameter === 37446) return fp.webglRenderer;
+                return originalGetParameter.apply(this, arguments);
+            }, 'getParameter');
+        }
+
+        (__NOISE__)(scope, fp.noiseSeed, native);
+
+        // Same-origin iframes, including ones read before their document's script runs
+        if (scope.HTMLIFrameElement) {
+            const iframe = scope.HTMLIFrameElement.prototype;
+            const desc = Object.getOwnPropertyDescriptor(iframe, 'contentWindow');
+            if (desc && desc.get) {
+                Object.defineProperty(iframe, 'contentWindow', {
+                    get: native(function () {
+                        const win = desc.get.call(this);
+                        try { patch(win, fp); } catch (e) {}
+                        return win;
+                    }, 'get contentWindow'),
+                    enumerable: desc.enumerable,
+                    configurable: true,
+                });
+            }
+        }
+    };
+    patch(self, __FINGERPRINT__);
+})();"#;
@@ -0,0 +1,665 @@
+//! Fingerprint Consistency Linter
+//!
+//! Checks a `BrowserFingerprint`, the tab's `VirtualIP` and the
+//! `ChromiumEngineConfig` against rules for contradictions detection vendors
+//! look for:
+//! - User-Agent vs `navigator.platform`, WebGL renderer and screen size
+//! - User-Agent browser family vs the Chromium (Blink) engine
+//! - Timezone and language vs the exit IP's country
+//! - Engine overrides (UA, timezone, viewport, geolocation) vs the fingerprint
+//! - Hardware values browsers can never report
+
+use crate::chromium_engine::ChromiumEngineConfig;
+use crate::fingerprint::BrowserFingerprint;
+use serde::{Deserialize, Serialize};
+use virtual_ip::{Country, CountryDatabase, VirtualIP};
+
+/// ID of the rule comparing the engine viewport with the fingerprint's screen
+pub const VIEWPORT_RULE: &str = "viewport-screen";
+
+/// ID of the rule comparing the User-Agent's browser family with the Chromium engine
+pub const ENGINE_RULE: &str = "ua-engine";
+
+/// Severity of a lint finding
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum LintSeverity {
+    /// Unusual but seen in real traffic
+    Info,
+    /// Rare combination that raises a risk score
+    Warning,
+    /// Impossible combination that identifies the profile as spoofed
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A suggested replacement value for one field.
+///
+/// `field` is prefixed with `fingerprint.`, `config.` or `virtual_ip.`.
+pub struct LintFix {
+    pub field: String,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A single contradiction found by a rule.
+pub struct LintFinding {
+    pub rule: String,
+    pub severity: LintSeverity,
+    pub message: String,
+    pub fix: Option<LintFix>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Findings for one fingerprint, most severe first.
+pub struct LintReport {
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    /// Highest severity found, if any.
+    pub fn max_severity(&self) -> Option<LintSeverity> {
+        self.findings.iter().map(|f| f.severity).max()
+    }
+
+    /// Returns true if any finding is an error.
+    pub fn has_errors(&self) -> bool {
+        self.max_severity() == Some(LintSeverity::Error)
+    }
+
+    /// Findings at or above `severity`.
+    pub fn at_least(&self, severity: LintSeverity) -> impl Iterator<Item = &LintFinding> {
+        self.findings.iter().filter(move |f| f.severity >= severity)
+    }
+
+    /// Apply suggested fixes to the fingerprint and engine config.
+    ///
+    /// Returns the number of fixes applied; fixes for other fields are skipped.
+    pub fn apply_fixes(&self, fingerprint: &mut BrowserFingerprint, config: &mut ChromiumEngineConfig) -> usize {
+        self.findings
+            .iter()
+            .filter_map(|f| f.fix.as_ref())
+            .filter(|fix| apply_fix(fix, fingerprint, config))
+            .count()
+    }
+}
+
+/// Everything a rule can inspect.
+pub struct LintContext<'a> {
+    pub fingerprint: &'a BrowserFingerprint,
+    pub virtual_ip: &'a VirtualIP,
+    pub config: &'a ChromiumEngineConfig,
+    /// The virtual IP's country, if known
+    pub country: Option<Country>,
+}
+
+/// A consistency rule.
+pub trait LintRule: Send + Sync {
+    /// Stable identifier reported with findings.
+    fn id(&self) -> &str;
+    /// Push findings for contradictions in `ctx`.
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<LintFinding>);
+}
+
+/// A rule backed by a plain function.
+type CheckFn = fn(&LintContext<'_>, &mut Findings);
+
+struct FnRule {
+    id: &'static str,
+    check: CheckFn,
+}
+
+impl LintRule for FnRule {
+    fn id(&self) -> &str {
+        self.id
+    }
+
+    fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<LintFinding>) {
+        let mut out = Findings { rule: self.id, findings };
+        (self.check)(ctx, &mut out);
+    }
+}
+
+/// Collects findings for the rule being run.
+struct Findings<'a> {
+    rule: &'static str,
+    findings: &'a mut Vec<LintFinding>,
+}
+
+impl Findings<'_> {
+    fn push(&mut self, severity: LintSeverity, message: String, fix: Option<(&str, String)>) {
+        self.findings.push(LintFinding {
+            rule: self.rule.to_string(),
+            severity,
+            message,
+            fix: fix.map(|(field, value)| LintFix {
+                field: field.to_string(),
+                value,
+            }),
+        });
+    }
+}
+
+/// Runs a rule set over fingerprint, virtual IP and engine config combinations.
+pub struct FingerprintLinter {
+    rules: Vec<Box<dyn LintRule>>,
+}
+
+impl Default for FingerprintLinter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FingerprintLinter {
+    /// Creates a linter with the built-in rules.
+    pub fn new() -> Self {
+        let builtin: [(&'static str, CheckFn); 10] = [
+            ("ua-platform", check_ua_platform),
+            (ENGINE_RULE, check_ua_engine),
+            ("ua-screen", check_ua_screen),
+            ("webgl-platform", check_webgl),
+            ("hardware-values", check_hardware),
+            ("timezone-country", check_timezone),
+            ("language-country", check_language),
+            ("engine-overrides", check_engine_overrides),
+            (VIEWPORT_RULE, check_viewport),
+            ("geolocation-country", check_geolocation),
+        ];
+        Self {
+            rules: builtin
+                .into_iter()
+                .map(|(id, check)| Box::new(FnRule { id, check }) as Box<dyn LintRule>)
+                .collect(),
+        }
+    }
+
+    /// Creates a linter with no rules.
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Adds a rule.
+    pub fn with_rule(mut self, rule: Box<dyn LintRule>) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// IDs of the rules that will run.
+    pub fn rule_ids(&self) -> Vec<&str> {
+        self.rules.iter().map(|r| r.id()).collect()
+    }
+
+    /// Check one combination.
+    pub fn lint(&self, fingerprint: &BrowserFingerprint, virtual_ip: &VirtualIP, config: &ChromiumEngineConfig) -> LintReport {
+        let ctx = LintContext {
+            fingerprint,
+            virtual_ip,
+            config,
+            country: CountryDatabase::find(&virtual_ip.country_code),
+        };
+        let mut findings = Vec::new();
+        for rule in &self.rules {
+            rule.check(&ctx, &mut findings);
+        }
+        // Stable sort keeps rule order within a severity
+        findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        LintReport { findings }
+    }
+}
+
+// ============================================================================
+// User-Agent parsing
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UaOs {
+    Windows,
+    MacOs,
+    Linux,
+    Android,
+    Ios,
+    ChromeOs,
+}
+
+fn ua_os(ua: &str) -> Option<UaOs> {
+    // Order matters: Android UAs contain "Linux", iOS UAs contain "Mac OS X"
+    if ua.contains("Android") {
+        Some(UaOs::Android)
+    } else if ua.contains("iPhone") || ua.contains("iPad") {
+        Some(UaOs::Ios)
+    } else if ua.contains("CrOS") {
+        Some(UaOs::ChromeOs)
+    } else if ua.contains("Windows") {
+        Some(UaOs::Windows)
+    } else if ua.contains("Macintosh") || ua.contains("Mac OS X") {
+        Some(UaOs::MacOs)
+    } else if ua.contains("Linux") || ua.contains("X11") {
+        Some(UaOs::Linux)
+    } else {
+        None
+    }
+}
+
+fn is_mobile(ua: &str) -> bool {
+    matches!(ua_os(ua), Some(UaOs::Android) | Some(UaOs::Ios)) || ua.contains("Mobile")
+}
+
+fn is_firefox(ua: &str) -> bool {
+    ua.contains("Firefox/")
+}
+
+fn is_safari(ua: &str) -> bool {
+    ua.contains("Version/") && ua.contains("Safari/") && !ua.contains("Chrome/")
+}
+
+/// Platforms `navigator.platform` reports for each OS.
+fn expected_platforms(os: UaOs) -> &'static [&'static str] {
+    match os {
+        UaOs::Windows => &["Win32"],
+        UaOs::MacOs => &["MacIntel"],
+        UaOs::Linux => &["Linux x86_64", "Linux aarch64", "Linux i686"],
+        UaOs::Android => &["Linux armv8l", "Linux aarch64", "Linux armv7l"],
+        UaOs::Ios => &["iPhone", "iPad"],
+        UaOs::ChromeOs => &["Linux x86_64", "Linux aarch64"],
+    }
+}
+
+// ============================================================================
+// Rules
+// ============================================================================
+
+fn check_ua_platform(ctx: &LintContext<'_>, out: &mut Findings) {
+    let fp = ctx.fingerprint;
+    let Some(os) = ua_os(&fp.user_agent) else {
+        out.push(LintSeverity::Warning, "User-Agent names no known OS".to_string(), None);
+        return;
+    };
+    let expected = expected_platforms(os);
+    if !expected.contains(&fp.platform.as_str()) {
+        out.push(
+            LintSeverity::Error,
+            format!("{:?} User-Agent with platform \"{}\"", os, fp.platform),
+            Some(("fingerprint.platform", expected[0].to_string())),
+        );
+    }
+    if is_safari(&fp.user_agent) && !matches!(os, UaOs::MacOs | UaOs::Ios) {
+        out.push(LintSeverity::Error, format!("Safari User-Agent on {:?}", os), None);
+    }
+}
+
+fn check_ua_engine(ctx: &LintContext<'_>, out: &mut Findings) {
+    let ua = ctx.fingerprint.user_agent.as_str();
+    // Pages render in Chromium, whose Blink APIs (userAgentData, chrome.*) give a Gecko or WebKit UA away
+    let family = if is_firefox(ua) {
+        "Firefox (Gecko)"
+    } else if is_safari(ua) {
+        "Safari (WebKit)"
+    } else if ua.contains("Chrome/") {
+        return;
+    } else {
+        out.push(LintSeverity::Warning, "User-Agent names no Chromium version".to_string(), None);
+        return;
+    };
+    out.push(LintSeverity::Error, format!("{} User-Agent on a Chromium engine", family), None);
+}
+
+fn check_ua_screen(ctx: &LintContext<'_>, out: &mut Findings) {
+    let fp = ctx.fingerprint;
+    let (width, height) = fp.screen_resolution;
+    let long_side = width.max(height);
+    if is_mobile(&fp.user_agent) {
+        if long_side > 1400 {
+            out.push(
+                LintSeverity::Error,
+                format!("Mobile User-Agent with a {}x{} screen", width, height),
+                Some(("fingerprint.screen_resolution", "412x915".to_string())),
+            );
+        }
+    } else if width < 1024 || height < 600 {
+        out.push(
+            LintSeverity::Warning,
+            format!("Desktop User-Agent with a {}x{} screen", width, height),
+            Some(("fingerprint.screen_resolution", "1920x1080".to_string())),
+        );
+    }
+    if height > width && !is_mobile(&fp.user_agent) {
+        out.push(LintSeverity::Info, "Portrait screen on a desktop User-Agent".to_string(), None);
+    }
+}
+
+fn check_webgl(ctx: &LintContext<'_>, out: &mut Findings) {
+    let fp = ctx.fingerprint;
+    let ua = fp.user_agent.as_str();
+    let renderer = fp.webgl_renderer.as_str();
+    let os = ua_os(ua);
+
+    if renderer.contains("Direct3D") && os != Some(UaOs::Windows) {
+        out.push(LintSeverity::Error, "Direct3D WebGL renderer outside Windows".to_string(), None);
+    }
+    if (renderer.contains("Apple M") || renderer.contains("Metal")) && !matches!(os, Some(UaOs::MacOs) | Some(UaOs::Ios)) {
+        out.push(LintSeverity::Error, "Apple GPU renderer outside macOS/iOS".to_string(), None);
+    }
+    if renderer.contains("Mesa") && !matches!(os, Some(UaOs::Linux) | Some(UaOs::ChromeOs) | Some(UaOs::Android)) {
+        out.push(LintSeverity::Error, "Mesa WebGL renderer outside Linux".to_string(), None);
+    }
+
+    if is_safari(ua) {
+        if fp.webgl_vendor != "Apple Inc." || fp.webgl_renderer != "Apple GPU" {
+            out.push(
+                LintSeverity::Error,
+                "Safari reports its GPU as \"Apple GPU\"".to_string(),
+                Some(("fingerprint.webgl_renderer", "Apple GPU".to_string())),
+            );
+        }
+    } else if is_firefox(ua) {
+        if fp.webgl_vendor.starts_with("Google Inc.") || renderer.starts_with("ANGLE (") {
+            out.push(LintSeverity::Error, "Firefox User-Agent with a Chromium ANGLE renderer".to_string(), None);
+        }
+    } else if ua.contains("Chrome/") && !fp.webgl_vendor.starts_with("Google Inc.") {
+        out.push(
+            LintSeverity::Warning,
+            format!("Chromium User-Agent with WebGL vendor \"{}\"", fp.webgl_vendor),
+            None,
+        );
+    }
+}
+
+fn check_hardware(ctx: &LintContext<'_>, out: &mut Findings) {
+    let fp = ctx.fingerprint;
+    // navigator.deviceMemory is bucketed to a power of two and capped at 8
+    if !fp.device_memory.is_power_of_two() || fp.device_memory > 8 {
+        let fixed = fp.device_memory.clamp(1, 8).next_power_of_two().min(8);
+        out.push(
+            LintSeverity::Error,
+            format!("deviceMemory {} is never reported by browsers", fp.device_memory),
+            Some(("fingerprint.device_memory", fixed.to_string())),
+        );
+    }
+    if fp.hardware_concurrency == 0 {
+        out.push(
+            LintSeverity::Error,
+            "hardwareConcurrency of 0".to_string(),
+            Some(("fingerprint.hardware_concurrency", "4".to_string())),
+        );
+    } else if fp.hardware_concurrency > 1 && fp.hardware_concurrency % 2 == 1 {
+        out.push(
+            LintSeverity::Info,
+            format!("Odd hardwareConcurrency {}", fp.hardware_concurrency),
+            None,
+        );
+    }
+    if is_mobile(&fp.user_agent) && fp.hardware_concurrency > 12 {
+        out.push(
+            LintSeverity::Warning,
+            format!("Mobile device with {} cores", fp.hardware_concurrency),
+            Some(("fingerprint.hardware_concurrency", "8".to_string())),
+        );
+    }
+    if ![24, 30, 32, 48].contains(&fp.color_depth) {
+        out.push(
+            LintSeverity::Warning,
+            format!("Unusual color depth {}", fp.color_depth),
+            Some(("fingerprint.color_depth", "24".to_string())),
+        );
+    }
+}
+
+fn check_timezone(ctx: &LintContext<'_>, out: &mut Findings) {
+    let fp = ctx.fingerprint;
+    let ip = ctx.virtual_ip;
+    let Some(country) = &ctx.country else {
+        return;
+    };
+    if country.timezones.is_empty() && country.timezone.is_empty() {
+        return;
+    }
+    let in_country = country.has_timezone(&fp.timezone) || fp.timezone == country.timezone;
+    if !in_country {
+        let fix = if country.has_timezone(&ip.timezone) { ip.timezone.clone() } else { country.timezone.clone() };
+        out.push(
+            LintSeverity::Error,
+            format!("Timezone {} is not used in {} ({})", fp.timezone, country.name, country.code),
+            Some(("fingerprint.timezone", fix)),
+        );
+    } else if fp.timezone != ip.timezone && country.has_timezone(&ip.timezone) {
+        out.push(
+            LintSeverity::Info,
+            format!("Timezone {} differs from the exit IP's {}", fp.timezone, ip.timezone),
+            Some(("fingerprint.timezone", ip.timezone.clone())),
+        );
+    }
+}
+
+fn check_language(ctx: &LintContext<'_>, out: &mut Findings) {
+    let fp = ctx.fingerprint;
+    let Some(country) = &ctx.country else {
+        return;
+    };
+    let primary = fp
+        .accept_language
+        .split(',')
+        .next()
+        .and_then(|l| l.split(';').next())
+        .unwrap_or("")
+        .trim();
+    if primary.is_empty() {
+        out.push(
+            LintSeverity::Error,
+            "Empty Accept-Language".to_string(),
+            Some(("fingerprint.accept_language", country.accept_language(&country.language))),
+        );
+        return;
+    }
+    let base = primary.split('-').next().unwrap_or(primary).to_ascii_lowercase();
+    let spoken = country.languages.iter().any(|l| {
+        l.tag.split('-').next().unwrap_or(&l.tag).eq_ignore_ascii_case(&base)
+    }) || country.language.split('-').next().is_some_and(|l| l.eq_ignore_ascii_case(&base));
+
+    // English is common enough everywhere to be only informational
+    if !spoken {
+        let severity = if base == "en" { LintSeverity::Info } else { LintSeverity::Warning };
+        let fix = if ctx.virtual_ip.accept_language.is_empty() {
+            country.accept_language(&country.language)
+        } else {
+            ctx.virtual_ip.accept_language.clone()
+        };
+        out.push(
+            severity,
+            format!("Language {} is not spoken in {} ({})", primary, country.name, country.code),
+            Some(("fingerprint.accept_language", fix)),
+        );
+    }
+}
+
+fn check_engine_overrides(ctx: &LintContext<'_>, out: &mut Findings) {
+    let fp = ctx.fingerprint;
+    let config = ctx.config;
+    if let Some(ua) = &config.user_agent {
+        if ua != &fp.user_agent {
+            out.push(
+                LintSeverity::Error,
+                "Engine User-Agent override differs from the fingerprint".to_string(),
+                Some(("config.user_agent", fp.user_agent.clone())),
+            );
+        }
+    }
+    let spoof = &config.fingerprint;
+    if spoof.spoof_platform && spoof.platform != fp.platform {
+        out.push(
+            LintSeverity::Error,
+            format!("Engine reports platform {} but the fingerprint has {}", spoof.platform, fp.platform),
+            Some(("config.fingerprint.platform", fp.platform.clone())),
+        );
+    }
+    if spoof.spoof_timezone && spoof.timezone != fp.timezone {
+        out.push(
+            LintSeverity::Error,
+            format!("Engine reports timezone {} but the fingerprint has {}", spoof.timezone, fp.timezone),
+            Some(("config.fingerprint.timezone", fp.timezone.clone())),
+        );
+    }
+    if spoof.spoof_language && !fp.accept_language.starts_with(&spoof.language) {
+        out.push(
+            LintSeverity::Warning,
+            format!("Engine reports language {} but Accept-Language is {}", spoof.language, fp.accept_language),
+            fp.accept_language
+                .split([',', ';'])
+                .next()
+                .map(|l| ("config.fingerprint.language", l.to_string())),
+        );
+    }
+    if spoof.spoof_screen && (spoof.screen_width, spoof.screen_height) != fp.screen_resolution {
+        out.push(
+            LintSeverity::Error,
+            "Engine screen override differs from the fingerprint".to_string(),
+            Some(("config.fingerprint.screen", format_size(fp.screen_resolution))),
+        );
+    }
+    if spoof.spoof_hardware_concurrency && spoof.hardware_concurrency != fp.hardware_concurrency as u32 {
+        out.push(
+            LintSeverity::Error,
+            "Engine hardwareConcurrency override differs from the fingerprint".to_string(),
+            Some(("config.fingerprint.hardware_concurrency", fp.hardware_concurrency.to_string())),
+        );
+    }
+    if spoof.spoof_device_memory && spoof.device_memory != fp.device_memory as u32 {
+        out.push(
+            LintSeverity::Error,
+            "Engine deviceMemory override differs from the fingerprint".to_string(),
+            Some(("config.fingerprint.device_memory", fp.device_memory.to_string())),
+        );
+    }
+    if ctx.virtual_ip.proxy_url.is_some() && !config.webrtc_protection {
+        out.push(
+            LintSeverity::Error,
+            "WebRTC protection is off while traffic goes through a proxy".to_string(),
+            Some(("config.webrtc_protection", "true".to_string())),
+        );
+    }
+}
+
+fn check_viewport(ctx: &LintContext<'_>, out: &mut Findings) {
+    let (width, height) = ctx.fingerprint.screen_resolution;
+    let config = ctx.config;
+    if config.viewport_width > width || config.viewport_height > height {
+        out.push(
+            LintSeverity::Error,
+            format!(
+                "Viewport {}x{} is larger than the {}x{} screen",
+                config.viewport_width, config.viewport_height, width, height
+            ),
+            // Leave room for the browser's tab strip and toolbar
+            Some(("config.viewport", format_size((width, height.saturating_sub(140))))),
+        );
+    }
+}
+
+fn check_geolocation(ctx: &LintContext<'_>, out: &mut Findings) {
+    let (Some(geo), Some(country)) = (&ctx.config.geolocation, &ctx.country) else {
+        return;
+    };
+    let Some(capital) = &country.capital else {
+        return;
+    };
+    let distance = haversine_km(geo.latitude, geo.longitude, capital.latitude, capital.longitude);
+    // Countries spanning several timezones are too large to judge from the capital
+    let limit = if country.timezones.len() > 1 { 5000.0 } else { 1500.0 };
+    if distance > limit {
+        out.push(
+            LintSeverity::Error,
+            format!("Geolocation is {:.0} km from {}, outside {}", distance, capital.name, country.name),
+            Some(("config.geolocation", format!("{},{}", capital.latitude, capital.longitude))),
+        );
+    }
+}
+
+fn haversine_km(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (dlat, dlon) = ((lat2 - lat1).to_radians(), (lon2 - lon1).to_radians());
+    let a = (dlat / 2.0).sin().powi(2) + lat1.to_radians().cos() * lat2.to_radians().cos() * (dlon / 2.0).sin().powi(2);
+    6371.0 * 2.0 * a.sqrt().asin()
+}
+
+// ============================================================================
+// Fixes
+// ============================================================================
+
+fn format_size((width, height): (u32, u32)) -> String {
+    format!("{}x{}", width, height)
+}
+
+fn parse_size(value: &str) -> Option<(u32, u32)> {
+    let (width, height) = value.split_once('x')?;
+    Some((width.parse().ok()?, height.parse().ok()?))
+}
+
+fn apply_fix(fix: &LintFix, fp: &mut BrowserFingerprint, config: &mut ChromiumEngineConfig) -> bool {
+    let value = fix.value.clone();
+    match fix.field.as_str() {
+        "fingerprint.platform" => fp.platform = value,
+        "fingerprint.timezone" => fp.timezone = value,
+        "fingerprint.accept_language" => fp.accept_language = value,
+        "fingerprint.webgl_renderer" => {
+            fp.webgl_renderer = value;
+            if fp.webgl_renderer == "Apple GPU" {
+                fp.webgl_vendor = "Apple Inc.".to_string();
+            }
+        }
+        "fingerprint.screen_resolution" => match parse_size(&value) {
+            Some(size) => fp.screen_resolution = size,
+            None => return false,
+        },
+        "fingerprint.device_memory" => match value.parse() {
+            Ok(v) => fp.device_memory = v,
+            Err(_) => return false,
+        },
+        "fingerprint.hardware_concurrency" => match value.parse() {
+            Ok(v) => fp.hardware_concurrency = v,
+            Err(_) => return false,
+        },
+        "fingerprint.color_depth" => match value.parse() {
+            Ok(v) => fp.color_depth = v,
+            Err(_) => return false,
+        },
+        "config.user_agent" => config.user_agent = Some(value),
+        "config.webrtc_protection" => config.webrtc_protection = value == "true",
+        "config.fingerprint.platform" => config.fingerprint.platform = value,
+        "config.fingerprint.timezone" => config.fingerprint.timezone = value,
+        "config.fingerprint.language" => config.fingerprint.language = value,
+        "config.fingerprint.screen" => match parse_size(&value) {
+            Some((w, h)) => {
+                config.fingerprint.screen_width = w;
+                config.fingerprint.screen_height = h;
+            }
+            None => return false,
+        },
+        "config.fingerprint.hardware_concurrency" => match value.parse() {
+            Ok(v) => config.fingerprint.hardware_concurrency = v,
+            Err(_) => return false,
+        },
+        "config.fingerprint.device_memory" => match value.parse() {
+            Ok(v) => config.fingerprint.device_memory = v,
+            Err(_) => return false,
+        },
+        "config.viewport" => match parse_size(&value) {
+            Some((w, h)) => {
+                config.viewport_width = w;
+                config.viewport_height = h;
+            }
+            None => return false,
+        },
+        "config.geolocation" => {
+            let parsed = value
+                .split_once(',')
+                .and_then(|(lat, lon)| Some((lat.parse().ok()?, lon.parse().ok()?)));
+            match (parsed, config.geolocation.as_mut()) {
+                (Some((lat, lon)), Some(geo)) => {
+                    geo.latitude = lat;
+                    geo.longitude = lon;
+                }
+                _ => return false,
+            }
+        }
+        _ => return false,
+    }
+    true
+}
@@ -0,0 +1,273 @@
+//! Fingerprint Noise Module
+//!
+//! Deterministic canvas, WebGL and audio readback noise. Noise is keyed by a
+//! profile's noise seed and the page origin, so a profile reads back the same
+//! values on every visit to a site and different values on different sites.
+//!
+//! The functions here are mirrored by `NOISE_SCRIPT`, so the hashes computed in
+//! Rust are the ones `PROBE_SCRIPT` reads back in a tab.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Origin of documents without one, such as a new tab's `about:blank`.
+pub const OPAQUE_ORIGIN: &str = "null";
+
+const PROBE_WIDTH: u32 = 32;
+const PROBE_HEIGHT: u32 = 8;
+const PROBE_SAMPLES: u32 = 512;
+
+/// Noise key for an origin.
+pub fn origin_key(seed: u64, origin: &str) -> u32 {
+    // FNV-1a over UTF-8, as `TextEncoder` produces in the script
+    format!("{:016x}|{}", seed, origin)
+        .bytes()
+        .fold(0x811c_9dc5u32, |hash, b| (hash ^ b as u32).wrapping_mul(0x0100_0193))
+}
+
+fn mix(key: u32, index: u32) -> u32 {
+    let mut h = key ^ index.wrapping_mul(0x9e37_79b9);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+/// Perturb one RGBA pixel; about one pixel in sixteen changes the low bit of one
+/// color channel. Transparent pixels are left alone.
+pub fn canvas_noise(key: u32, pixel: u32, rgba: &mut [u8]) {
+    if rgba[3] == 0 {
+        return;
+    }
+    let h = mix(key, pixel);
+    if h & 0xf == 0 {
+        rgba[((h >> 4) % 3) as usize] ^= 1;
+    }
+}
+
+/// Sample `index` of `channel` after noise of at most 5e-8.
+pub fn audio_noise(key: u32, channel: u32, index: u32, sample: f32) -> f32 {
+    let h = mix(key.wrapping_add(channel), index);
+    (sample as f64 + (h as f64 / 4_294_967_296.0 - 0.5) * 1e-7) as f32
+}
+
+fn fnv64(bytes: impl IntoIterator<Item = u8>) -> String {
+    let hash = bytes
+        .into_iter()
+        .fold(0xcbf2_9ce4_8422_2325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", hash)
+}
+
+/// Hash of the canvas `PROBE_SCRIPT` draws and reads back for this seed on `origin`.
+pub fn canvas_probe_hash(seed: u64, origin: &str) -> String {
+    let key = origin_key(seed, origin);
+    let mut pixels = Vec::with_capacity((PROBE_WIDTH * PROBE_HEIGHT * 4) as usize);
+    for y in 0..PROBE_HEIGHT {
+        for x in 0..PROBE_WIDTH {
+            // 4x4 blocks filled with opaque colors rasterize exactly
+            let (bx, by) = (x / 4 * 4, y / 4 * 4);
+            let mut rgba = [
+                ((bx * 7 + by * 31) & 255) as u8,
+                ((bx * 13 + 64) & 255) as u8,
+                ((by * 29 + 128) & 255) as u8,
+                255,
+            ];
+            canvas_noise(key, y * PROBE_WIDTH + x, &mut rgba);
+            pixels.extend_from_slice(&rgba);
+        }
+    }
+    fnv64(pixels)
+}
+
+/// Hash of the audio buffer `PROBE_SCRIPT` reads back for this seed on `origin`.
+pub fn audio_probe_hash(seed: u64, origin: &str) -> String {
+    let key = origin_key(seed, origin);
+    fnv64((0..PROBE_SAMPLES).flat_map(|i| {
+        let sample = ((i % 64) as f32 - 32.0) / 64.0;
+        audio_noise(key, 0, i, sample).to_le_bytes()
+    }))
+}
+
+/// Hashes read back in a tab by `PROBE_SCRIPT`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NoiseHashes {
+    pub origin: String,
+    pub canvas: String,
+    pub audio: String,
+}
+
+impl NoiseHashes {
+    /// Hashes a tab presenting `seed` should produce on `origin`.
+    pub fn expected(seed: u64, origin: &str) -> Self {
+        Self {
+            origin: origin.to_string(),
+            canvas: canvas_probe_hash(seed, origin),
+            audio: audio_probe_hash(seed, origin),
+        }
+    }
+}
+
+/// A deliberate change of noise seed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoiseRotation {
+    pub previous_seed: u64,
+    pub seed: u64,
+    pub reason: String,
+    pub rotated_at: DateTime<Utc>,
+}
+
+impl NoiseRotation {
+    /// Record a rotation happening now.
+    pub fn new(previous_seed: u64, seed: u64, reason: impl Into<String>) -> Self {
+        Self {
+            previous_seed,
+            seed,
+            reason: reason.into(),
+            rotated_at: Utc::now(),
+        }
+    }
+}
+
+/// `function installNoise(scope, seed, native)`: hooks canvas, WebGL and audio
+/// readback in a window or worker global. `seed` is the noise seed as 16 hex
+/// digits; `native` registers a replacement function under its native name.
+pub const NOISE_SCRIPT: &str = r#"function installNoise(scope, seed, native) {
+    const fnv32 = (text) => {
+        let h = 0x811c9dc5;
+        for (const b of new TextEncoder().encode(text)) h = Math.imul(h ^ b, 0x01000193);
+        return h >>> 0;
+    };
+    const mix = (key, index) => {
+        let h = (key ^ Math.imul(index, 0x9e3779b9)) >>> 0;
+        h ^= h >>> 16;
+        h = Math.imul(h, 0x85ebca6b);
+        h ^= h >>> 13;
+        h = Math.imul(h, 0xc2b2ae35);
+        return (h ^ (h >>> 16)) >>> 0;
+    };
+    const key = () => {
+        let origin = 'null';
+        try { origin = String(scope.location.origin); } catch (e) {}
+        return fnv32(`${seed}|${origin}`);
+    };
+    // Pixel indices are canvas coordinates, so every readback of a region agrees
+    const noisePixels = (data, count, canvasWidth, x0, y0, width) => {
+        const k = key();
+        for (let i = 0; i + 3 < count; i += 4) {
+            if (data[i + 3] === 0) continue;
+            const p = i >> 2;
+            const h = mix(k, (y0 + Math.floor(p / width)) * canvasWidth + x0 + (p % width));
+            if ((h & 0xf) === 0) data[i + ((h >>> 4) % 3)] ^= 1;
+        }
+    };
+
+    for (const name of ['CanvasRenderingContext2D', 'OffscreenCanvasRenderingContext2D']) {
+        const ctx = scope[name];
+        if (!ctx) continue;
+        const originalGetImageData = ctx.prototype.getImageData;
+        ctx.prototype.getImageData = native(function getImageData(sx, sy) {
+            const image = originalGetImageData.apply(this, arguments);
+            noisePixels(image.data, image.data.length, this.canvas.width, sx | 0, sy | 0, image.width);
+            return image;
+        }, 'getImageData');
+    }
+
+    // Encoders read a noised copy; the page's canvas keeps its pixels
+    const noisyCopy = (canvas, copy) => {
+        try {
+            copy.width = canvas.width;
+            copy.height = canvas.height;
+            const ctx = copy.getContext('2d');
+            ctx.drawImage(canvas, 0, 0);
+            ctx.putImageData(ctx.getImageData(0, 0, copy.width, copy.height), 0, 0);
+            return copy;
+        } catch (e) {
+            return canvas;
+        }
+    };
+    if (scope.HTMLCanvasElement && scope.document) {
+        for (const name of ['toDataURL', 'toBlob']) {
+            const original = scope.HTMLCanvasElement.prototype[name];
+            scope.HTMLCanvasElement.prototype[name] = native(function () {
+                return original.apply(noisyCopy(this, scope.document.createElement('canvas')), arguments);
+            }, name);
+        }
+    }
+    if (scope.OffscreenCanvas) {
+        const originalConvertToBlob = scope.OffscreenCanvas.prototype.convertToBlob;
+        scope.OffscreenCanvas.prototype.convertToBlob = native(function convertToBlob() {
+            return originalConvertToBlob.apply(noisyCopy(this, new scope.OffscreenCanvas(1, 1)), arguments);
+        }, 'convertToBlob');
+    }
+
+    for (const name of ['WebGLRenderingContext', 'WebGL2RenderingContext']) {
+        const gl = scope[name];
+        if (!gl) continue;
+        const originalReadPixels = gl.prototype.readPixels;
+        gl.prototype.readPixels = native(function readPixels(x, y, width, height, format, type, pixels) {
+            const result = originalReadPixels.apply(this, arguments);
+            if (arguments.length === 7 && pixels instanceof Uint8Array
+                && format === this.RGBA && type === this.UNSIGNED_BYTE) {
+                noisePixels(pixels, Math.min(pixels.length, width * height * 4), this.drawingBufferWidth, x | 0, y | 0, width);
+            }
+            return result;
+        }, 'readPixels');
+    }
+
+    if (scope.AudioBuffer) {
+        const proto = scope.AudioBuffer.prototype;
+        const originalGetChannelData = proto.getChannelData;
+        const originalCopyFromChannel = proto.copyFromChannel;
+        // Noise is added once per channel, in place, since getChannelData returns a live view
+        const noised = new WeakMap();
+        const noiseChannel = (buffer, channel) => {
+            const data = originalGetChannelData.call(buffer, channel);
+            const done = noised.get(buffer) || new Set();
+            noised.set(buffer, done);
+            if (done.has(channel >>> 0)) return data;
+            done.add(channel >>> 0);
+            const k = (key() + (channel >>> 0)) >>> 0;
+            for (let i = 0; i < data.length; i++) data[i] = data[i] + (mix(k, i) / 4294967296 - 0.5) * 1e-7;
+            return data;
+        };
+        proto.getChannelData = native(function getChannelData(channel) {
+            return noiseChannel(this, channel);
+        }, 'getChannelData');
+        if (originalCopyFromChannel) {
+            proto.copyFromChannel = native(function copyFromChannel(destination, channel) {
+                noiseChannel(this, channel);
+                return originalCopyFromChannel.apply(this, arguments);
+            }, 'copyFromChannel');
+        }
+    }
+}"#;
+
+/// Expression evaluating to `NoiseHashes` for the current document.
+pub const PROBE_SCRIPT: &str = r#"(() => {
+    const fnv64 = (bytes) => {
+        let h = 0xcbf29ce484222325n;
+        for (const b of bytes) h = BigInt.asUintN(64, (h ^ BigInt(b)) * 0x100000001b3n);
+        return h.toString(16).padStart(16, '0');
+    };
+    const canvas = document.createElement('canvas');
+    canvas.width = 32;
+    canvas.height = 8;
+    const ctx = canvas.getContext('2d');
+    for (let y = 0; y < 8; y += 4) {
+        for (let x = 0; x < 32; x += 4) {
+            ctx.fillStyle = `rgb(${(x * 7 + y * 31) & 255}, ${(x * 13 + 64) & 255}, ${(y * 29 + 128) & 255})`;
+            ctx.fillRect(x, y, 4, 4);
+        }
+    }
+    const pixels = ctx.getImageData(0, 0, 32, 8).data;
+
+    const buffer = new AudioBuffer({ length: 512, sampleRate: 44100 });
+    const samples = new Float32Array(512);
+    for (let i = 0; i < 512; i++) samples[i] = ((i % 64) - 32) / 64;
+    buffer.copyToChannel(samples, 0);
+    const channel = buffer.getChannelData(0);
+    const audio = new Uint8Array(channel.buffer, channel.byteOffset, channel.byteLength);
+
+    return { origin: String(location.origin), canvas: fnv64(pixels), audio: fnv64(audio) };
+})()"#;
@@ -5,13 +5,23 @@
 //! - Automatic proxy fetching and validation
 //! - Provider health monitoring
 //! - Rate limiting per provider
+//! - User-defined providers registered next to the built-ins
+//! - Cross-provider deduplication with provenance and provider stats
+//! - Correcting provider-supplied countries from a local IP range index
 
 use anyhow::{Result, anyhow};
-use serde::Deserialize;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
 use async_trait::async_trait;
 use std::time::Duration;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::RwLock;
+use virtual_ip::IpRangeIndex;
 
+use crate::config_manager::ProxyConfig;
+use crate::custom_providers::{DeclarativeProvider, ProviderDefinition};
 use crate::http_client::HttpClient;
 use crate::proxy::{FreeProxy, ProxyType};
 use crate::scraper_util;
@@ -115,13 +125,138 @@ impl FreeIpProvider {
     }
 }
 
+/// How long an endpoint's provenance is kept after it was last seen
+const PROVENANCE_RETENTION_DAYS: i64 = 7;
+
+/// Per-provider result of a fetch round: fetched and duplicate counts, or the error
+type FetchOutcome = Result<(usize, usize), String>;
+
+/// Where a pooled proxy endpoint has been seen
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyProvenance {
+    /// Providers that have returned this endpoint, in first-seen order
+    pub sources: Vec<String>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Thresholds for switching off providers that don't pull their weight
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderHealthPolicy {
+    pub auto_disable: bool,
+    /// Tested (or fetched) proxies needed before ratios are acted on
+    pub min_samples: u64,
+    pub min_working_ratio: f64,
+    pub max_duplicate_ratio: f64,
+    pub max_consecutive_failures: u32,
+}
+
+impl Default for ProviderHealthPolicy {
+    fn default() -> Self {
+        Self {
+            auto_disable: true,
+            min_samples: 20,
+            min_working_ratio: 0.02,
+            max_duplicate_ratio: 0.98,
+            max_consecutive_failures: 5,
+        }
+    }
+}
+
+/// Reliability figures for a single provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderStats {
+    pub name: String,
+    pub enabled: bool,
+    pub disabled_reason: Option<String>,
+    pub last_fetch_at: Option<DateTime<Utc>>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// Seconds since the last successful fetch
+    pub freshness_secs: Option<i64>,
+    pub consecutive_failures: u32,
+    pub last_error: Option<String>,
+    /// Proxies returned by the most recent fetch
+    pub last_fetched: usize,
+    /// Of those, how many an earlier provider had already returned (or were repeated)
+    pub last_duplicates: usize,
+    pub total_fetched: u64,
+    pub total_duplicates: u64,
+    pub tested: u64,
+    pub working: u64,
+    /// Share of tested proxies that worked
+    pub working_ratio: f64,
+    /// Share of fetched proxies that were duplicates
+    pub duplicate_ratio: f64,
+}
+
+impl ProviderStats {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            enabled: true,
+            disabled_reason: None,
+            last_fetch_at: None,
+            last_success_at: None,
+            freshness_secs: None,
+            consecutive_failures: 0,
+            last_error: None,
+            last_fetched: 0,
+            last_duplicates: 0,
+            total_fetched: 0,
+            total_duplicates: 0,
+            tested: 0,
+            working: 0,
+            working_ratio: 0.0,
+            duplicate_ratio: 0.0,
+        }
+    }
+
+    fn refresh_ratios(&mut self) {
+        self.working_ratio = if self.tested > 0 { self.working as f64 / self.tested as f64 } else { 0.0 };
+        self.duplicate_ratio = if self.total_fetched > 0 {
+            self.total_duplicates as f64 / self.total_fetched as f64
+        } else {
+            0.0
+        };
+    }
+}
+
+fn endpoint_key(proxy: &FreeProxy) -> (String, u16) {
+    (proxy.ip.to_ascii_lowercase(), proxy.port)
+}
+
+/// Replace a proxy's country with the owner of its IP when the two disagree
+///
+/// Returns true if the proxy was changed. Hostnames and IPs the index does
+/// not cover are left as the provider reported them.
+pub fn correct_proxy_country(index: &IpRangeIndex, proxy: &mut FreeProxy) -> bool {
+    let Some((country, _)) = index.lookup_str(&proxy.ip) else {
+        return false;
+    };
+    if proxy.country_code.eq_ignore_ascii_case(&country.code) {
+        return false;
+    }
+    tracing::debug!(
+        "Correcting country of {}:{} from {:?} to {}",
+        proxy.ip, proxy.port, proxy.country_code, country.code
+    );
+    proxy.country_code = country.code.clone();
+    proxy.country = country.name.clone();
+    true
+}
+
 /// Represents a FreeIpProviderManager.
 pub struct FreeIpProviderManager {
     http_client: HttpClient,
     proxy_pool: Vec<FreeProxy>,
     last_update: HashMap<String, chrono::DateTime<chrono::Utc>>,
     update_interval: Duration,
-    rate_limiters: HashMap<String, std::time::Instant>,
+    rate_limiters: HashMap<String, Instant>,
+    custom_providers: Vec<Arc<dyn ProxyProvider>>,
+    provenance: HashMap<(String, u16), ProxyProvenance>,
+    provider_stats: HashMap<String, ProviderStats>,
+    health_policy: ProviderHealthPolicy,
+    range_index: Option<Arc<IpRangeIndex>>,
 }
 
 impl FreeIpProviderManager {
@@ -133,9 +268,294 @@ impl FreeIpProviderManager {
             last_update: HashMap::new(),
             update_interval: Duration::from_secs(300), // 5 minutes default
             rate_limiters: HashMap::new(),
+            custom_providers: Vec::new(),
+            provenance: HashMap::new(),
+            provider_stats: HashMap::new(),
+            health_policy: ProviderHealthPolicy::default(),
+            range_index: None,
         })
     }
 
+    /// Configures when weak providers are switched off automatically
+    pub fn with_health_policy(mut self, policy: ProviderHealthPolicy) -> Self {
+        self.health_policy = policy;
+        self
+    }
+
+    /// Checks provider-supplied countries against a local IP range index
+    pub fn with_range_index(mut self, index: Arc<IpRangeIndex>) -> Self {
+        self.range_index = Some(index);
+        self
+    }
+
+    /// The IP range index countries are checked against, if configured
+    pub fn range_index(&self) -> Option<Arc<IpRangeIndex>> {
+        self.range_index.clone()
+    }
+
+    /// Names of all providers, built-in first
+    pub fn provider_names(&self) -> Vec<String> {
+        FreeIpProvider::all()
+            .iter()
+            .map(|p| p.name().to_string())
+            .chain(self.custom_provider_names())
+            .collect()
+    }
+
+    /// Whether a provider takes part in `fetch_all`
+    pub fn is_provider_enabled(&self, name: &str) -> bool {
+        self.provider_stats.get(name).map(|s| s.enabled).unwrap_or(true)
+    }
+
+    /// Turn a provider on or off
+    ///
+    /// Re-enabling clears its failure streak and validation counts so it is
+    /// judged afresh.
+    pub fn set_provider_enabled(&mut self, name: &str, enabled: bool) -> Result<()> {
+        if !self.provider_names().iter().any(|n| n == name) {
+            return Err(anyhow!("No provider named {}", name));
+        }
+        let stats = self.provider_stats.entry(name.to_string()).or_insert_with(|| ProviderStats::new(name));
+        stats.enabled = enabled;
+        if enabled {
+            stats.disabled_reason = None;
+            stats.consecutive_failures = 0;
+            stats.tested = 0;
+            stats.working = 0;
+            stats.total_fetched = 0;
+            stats.total_duplicates = 0;
+            stats.refresh_ratios();
+        } else {
+            stats.disabled_reason = Some("Disabled manually".to_string());
+        }
+        Ok(())
+    }
+
+    /// Stats for every provider, in the same order as `provider_names`
+    pub fn provider_stats(&self) -> Vec<ProviderStats> {
+        let now = Utc::now();
+        self.provider_names()
+            .iter()
+            .map(|name| {
+                let mut stats = self.provider_stats.get(name).cloned().unwrap_or_else(|| ProviderStats::new(name));
+                stats.freshness_secs = stats.last_success_at.map(|t| (now - t).num_seconds());
+                stats
+            })
+            .collect()
+    }
+
+    /// Providers and timestamps recorded for an endpoint
+    pub fn provenance(&self, ip: &str, port: u16) -> Option<&ProxyProvenance> {
+        self.provenance.get(&(ip.to_ascii_lowercase(), port))
+    }
+
+    /// Merge per-provider fetch results into one record per `ip:port`
+    ///
+    /// The first provider to return an endpoint supplies its record and later
+    /// sources count it as a duplicate, only filling in a missing country. Fetch
+    /// outcomes, duplicate counts and provenance are recorded along the way.
+    pub fn merge_results(&mut self, results: Vec<(String, Result<Vec<FreeProxy>>)>) -> Vec<FreeProxy> {
+        let now = Utc::now();
+
+        let mut merged: Vec<FreeProxy> = Vec::new();
+        let mut index: HashMap<(String, u16), usize> = HashMap::new();
+        let mut round_stats: Vec<(String, FetchOutcome)> = Vec::new();
+
+        for (name, result) in &results {
+            let proxies = match result {
+                Ok(proxies) => proxies,
+                Err(e) => {
+                    round_stats.push((name.clone(), Err(e.to_string())));
+                    continue;
+                }
+            };
+
+            let mut own: HashSet<(String, u16)> = HashSet::new();
+            let mut duplicates = 0;
+            for proxy in proxies {
+                let key = endpoint_key(proxy);
+                if !own.insert(key.clone()) {
+                    duplicates += 1;
+                    continue;
+                }
+
+                match index.get(&key) {
+                    Some(&i) => {
+                        duplicates += 1;
+                        let canonical = &mut merged[i];
+                        if canonical.country_code == "XX" || canonical.country_code.is_empty() {
+                            canonical.country = proxy.country.clone();
+                            canonical.country_code = proxy.country_code.clone();
+                        }
+                    }
+                    None => {
+                        index.insert(key.clone(), merged.len());
+                        merged.push(proxy.clone());
+                    }
+                }
+
+                let provenance = self.provenance.entry(key).or_insert_with(|| ProxyProvenance {
+                    sources: Vec::new(),
+                    first_seen: now,
+                    last_seen: now,
+                });
+                provenance.last_seen = now;
+                if !provenance.sources.iter().any(|s| s == name) {
+                    provenance.sources.push(name.clone());
+                }
+            }
+            round_stats.push((name.clone(), Ok((proxies.len(), duplicates))));
+        }
+
+        for (name, outcome) in round_stats {
+            let stats = self.provider_stats.entry(name.clone()).or_insert_with(|| ProviderStats::new(&name));
+            stats.last_fetch_at = Some(now);
+            match outcome {
+                Ok((fetched, duplicates)) => {
+                    stats.last_success_at = Some(now);
+                    stats.consecutive_failures = 0;
+                    stats.last_error = None;
+                    stats.last_fetched = fetched;
+                    stats.last_duplicates = duplicates;
+                    stats.total_fetched += fetched as u64;
+                    stats.total_duplicates += duplicates as u64;
+                }
+                Err(e) => {
+                    stats.consecutive_failures += 1;
+                    stats.last_error = Some(e);
+                    stats.last_fetched = 0;
+                    stats.last_duplicates = 0;
+                }
+            }
+            stats.refresh_ratios();
+            self.apply_health_policy(&name);
+        }
+
+        let cutoff = now - chrono::Duration::days(PROVENANCE_RETENTION_DAYS);
+        self.provenance.retain(|_, p| p.last_seen > cutoff);
+
+        if let Some(index) = &self.range_index {
+            let mut corrected = 0;
+            for proxy in merged.iter_mut() {
+                if correct_proxy_country(index, proxy) {
+                    corrected += 1;
+                }
+            }
+            if corrected > 0 {
+                tracing::info!("Corrected the country of {} proxies from the IP range index", corrected);
+            }
+        }
+
+        merged
+    }
+
+    /// Credit validation results to every provider that supplied each proxy
+    pub fn record_validation(&mut self, tested: &[FreeProxy]) {
+        let mut touched = HashSet::new();
+        for proxy in tested {
+            let Some(provenance) = self.provenance.get(&endpoint_key(proxy)) else {
+                continue;
+            };
+            for source in &provenance.sources {
+                let stats = self.provider_stats.entry(source.clone()).or_insert_with(|| ProviderStats::new(source));
+                stats.tested += 1;
+                if proxy.is_working {
+                    stats.working += 1;
+                }
+                touched.insert(source.clone());
+            }
+        }
+        for name in touched {
+            if let Some(stats) = self.provider_stats.get_mut(&name) {
+                stats.refresh_ratios();
+            }
+            self.apply_health_policy(&name);
+        }
+    }
+
+    fn apply_health_policy(&mut self, name: &str) {
+        let policy = &self.health_policy;
+        let Some(stats) = self.provider_stats.get_mut(name) else {
+            return;
+        };
+        if !policy.auto_disable || !stats.enabled {
+            return;
+        }
+
+        let reason = if stats.consecutive_failures >= policy.max_consecutive_failures {
+            Some(format!("{} consecutive fetch failures", stats.consecutive_failures))
+        } else if stats.tested >= policy.min_samples && stats.working_ratio < policy.min_working_ratio {
+            Some(format!("Only {:.1}% of tested proxies worked", stats.working_ratio * 100.0))
+        } else if stats.total_fetched >= policy.min_samples && stats.duplicate_ratio > policy.max_duplicate_ratio {
+            Some(format!("{:.1}% of proxies duplicated other providers", stats.duplicate_ratio * 100.0))
+        } else {
+            None
+        };
+
+        if let Some(reason) = reason {
+            tracing::warn!("Disabling provider {}: {}", name, reason);
+            stats.enabled = false;
+            stats.disabled_reason = Some(reason);
+        }
+    }
+
+    /// Register providers from declarative definitions; disabled definitions are skipped
+    pub fn with_provider_definitions(mut self, definitions: Vec<ProviderDefinition>) -> Result<Self> {
+        for definition in definitions.into_iter().filter(|d| d.enabled) {
+            self.register_provider(Arc::new(DeclarativeProvider::new(definition)?))?;
+        }
+        Ok(self)
+    }
+
+    /// Manager with the built-ins plus the custom providers from `config`
+    ///
+    /// Invalid definitions are logged and skipped so one typo does not drop
+    /// the other providers.
+    pub fn from_config(config: &ProxyConfig) -> Result<Self> {
+        let mut manager = Self::new()?;
+        for definition in config.custom_providers.iter().filter(|d| d.enabled) {
+            let registered = DeclarativeProvider::new(definition.clone())
+                .and_then(|provider| manager.register_provider(Arc::new(provider)));
+            if let Err(e) = registered {
+                tracing::warn!("Skipping custom provider {}: {}", definition.name, e);
+            }
+        }
+        Ok(manager)
+    }
+
+    /// Register an additional provider to run alongside the built-ins
+    pub fn register_provider(&mut self, provider: Arc<dyn ProxyProvider>) -> Result<()> {
+        let name = provider.name().to_string();
+        let taken = FreeIpProvider::all().iter().any(|p| p.name().eq_ignore_ascii_case(&name))
+            || self.custom_providers.iter().any(|p| p.name().eq_ignore_ascii_case(&name));
+        if taken {
+            return Err(anyhow!("A provider named {} is already registered", name));
+        }
+        self.custom_providers.push(provider);
+        Ok(())
+    }
+
+    /// Names of registered user-defined providers
+    pub fn custom_provider_names(&self) -> Vec<String> {
+        self.custom_providers.iter().map(|p| p.name().to_string()).collect()
+    }
+
+    /// Fetches from a user-defined provider by name.
+    pub async fn fetch_from_custom(&mut self, name: &str) -> Result<Vec<FreeProxy>> {
+        let provider = self
+            .custom_providers
+            .iter()
+            .find(|p| p.name() == name)
+            .cloned()
+            .ok_or_else(|| anyhow!("No custom provider named {}", name))?;
+
+        wait_for_rate_limit(self.rate_limiters.get(name), provider.rate_limit()).await;
+        let result = provider.fetch_proxies().await;
+        self.rate_limiters.insert(name.to_string(), Instant::now());
+
+        result
+    }
+
     /// Configures with update interval.
     pub fn with_update_interval(mut self, interval: Duration) -> Self {
         self.update_interval = interval;
@@ -145,17 +565,21 @@ impl FreeIpProviderManager {
     /// Fetches from provider.
     pub async fn fetch_from_provider(&mut self, provider: &FreeIpProvider) -> Result<Vec<FreeProxy>> {
         // Check rate limiting
-        if let Some(last_fetch) = self.rate_limiters.get(provider.name()) {
-            let elapsed = last_fetch.elapsed();
-            if elapsed < provider.rate_limit() {
-                tokio::time::sleep(provider.rate_limit() - elapsed).await;
-            }
-        }
+        wait_for_rate_limit(self.rate_limiters.get(provider.name()), provider.rate_limit()).await;
+
+        let result = Self::fetch_builtin(&self.http_client, provider).await;
+
+        // Update rate limiter
+        self.rate_limiters.insert(provider.name().to_string(), Instant::now());
+
+        result
+    }
 
-        let result = match provider {
-            FreeIpProvider::ProxyScrape => self.fetch_proxyscrape().await,
-            FreeIpProvider::GeoNode => self.fetch_geonode().await,
-            FreeIpProvider::PubProxy => self.fetch_pubproxy().await,
+    async fn fetch_builtin(client: &HttpClient, provider: &FreeIpProvider) -> Result<Vec<FreeProxy>> {
+        match provider {
+            FreeIpProvider::ProxyScrape => Self::fetch_proxyscrape(client).await,
+            FreeIpProvider::GeoNode => Self::fetch_geonode(client).await,
+            FreeIpProvider::PubProxy => Self::fetch_pubproxy(client).await,
             FreeIpProvider::FreeProxyList => {
                 let scraper = scraper_util::ProxyScraper::new(HttpClient::new()?);
                 scraper.scrape_free_proxy_list().await
@@ -171,36 +595,69 @@ impl FreeIpProviderManager {
                 scraper.scrape_spys_one().await
                     .map_err(|e| anyhow!("Failed to scrape spys.one: {}", e))
             }
+        }
+    }
+
+    /// Snapshot of the enabled providers, to fetch without holding the manager's lock
+    pub fn prepare_fetch(&self) -> ProviderFetch {
+        let enabled = |name: &str| {
+            let enabled = self.is_provider_enabled(name);
+            if !enabled {
+                tracing::debug!("Skipping disabled provider {}", name);
+            }
+            enabled
         };
+        let builtin: Vec<FreeIpProvider> = FreeIpProvider::all()
+            .into_iter()
+            .filter(|p| enabled(p.name()))
+            .collect();
+        let custom: Vec<Arc<dyn ProxyProvider>> = self
+            .custom_providers
+            .iter()
+            .filter(|p| enabled(p.name()))
+            .cloned()
+            .collect();
 
-        // Update rate limiter
-        self.rate_limiters.insert(provider.name().to_string(), std::time::Instant::now());
+        ProviderFetch {
+            http_client: self.http_client.clone(),
+            builtin,
+            custom,
+            last_fetches: self.rate_limiters.clone(),
+        }
+    }
 
-        result
+    /// Records rate limits and merges a detached fetch into provenance and stats
+    pub fn complete_fetch(&mut self, fetched: ProviderFetchResults) -> Vec<FreeProxy> {
+        self.rate_limiters.extend(fetched.fetched_at);
+
+        // One record per IP:port, with provenance tracked per endpoint
+        let all_proxies = self.merge_results(fetched.results);
+
+        tracing::info!("Total unique proxies fetched: {}", all_proxies.len());
+        all_proxies
     }
 
     /// Fetches all.
     pub async fn fetch_all(&mut self) -> Vec<FreeProxy> {
-        let mut all_proxies = Vec::new();
-        
-        for provider in FreeIpProvider::all() {
-            match self.fetch_from_provider(&provider).await {
-                Ok(proxies) => {
-                    tracing::info!("Fetched {} proxies from {}", proxies.len(), provider.name());
-                    all_proxies.extend(proxies);
-                }
-                Err(e) => {
-                    tracing::error!("Failed to fetch from {}: {}", provider.name(), e);
-                }
-            }
-        }
-        
-        // Remove duplicates based on IP:port
-        all_proxies.sort_by(|a, b| a.ip.cmp(&b.ip).then(a.port.cmp(&b.port)));
-        all_proxies.dedup_by(|a, b| a.ip == b.ip && a.port == b.port);
-        
-        tracing::info!("Total unique proxies fetched: {}", all_proxies.len());
-        all_proxies
+        let fetched = self.prepare_fetch().run().await;
+        self.complete_fetch(fetched)
+    }
+
+    /// Fetches into a shared manager, holding its lock only to snapshot and merge
+    pub async fn fetch_shared(manager: &RwLock<Self>) -> Vec<FreeProxy> {
+        let fetch = manager.read().await.prepare_fetch();
+        let fetched = fetch.run().await;
+        manager.write().await.complete_fetch(fetched)
+    }
+
+    /// Validates a sample for a shared manager and stores the result as its pool
+    ///
+    /// The checks run without the lock; provider stats (and auto-disable) are
+    /// updated afterwards.
+    pub async fn validate_shared(manager: &RwLock<Self>, proxies: Vec<FreeProxy>) -> Vec<FreeProxy> {
+        let (pool, sampled) = Self::test_proxy_sample(proxies).await;
+        manager.write().await.apply_validation(pool.clone(), &sampled);
+        pool
     }
 
     /// Updates the proxy pool.
@@ -209,26 +666,35 @@ impl FreeIpProviderManager {
         let count = proxies.len();
         
         // Test a sample of proxies to mark working ones
-        let working_proxies = self.test_proxy_sample(proxies).await;
-        
-        self.proxy_pool = working_proxies;
+        let (working_proxies, sampled) = Self::test_proxy_sample(proxies).await;
+        self.apply_validation(working_proxies, &sampled);
+        Ok(count)
+    }
+
+    /// Stores a validated pool and credits the sampled results to their providers
+    pub fn apply_validation(&mut self, pool: Vec<FreeProxy>, sampled: &[FreeProxy]) {
+        self.record_validation(sampled);
+        self.proxy_pool = pool;
         
         // Update last fetch time for all providers
         let now = chrono::Utc::now();
         for provider in FreeIpProvider::all() {
             self.last_update.insert(provider.name().to_string(), now);
         }
+        for name in self.custom_provider_names() {
+            self.last_update.insert(name, now);
+        }
         
         tracing::info!("Updated proxy pool with {} working proxies", self.proxy_pool.len());
-        Ok(count)
     }
 
-    async fn test_proxy_sample(&self, proxies: Vec<FreeProxy>) -> Vec<FreeProxy> {
+    /// Returns every proxy with `is_working` set, plus the sampled subset that was actually tested
+    pub async fn test_proxy_sample(proxies: Vec<FreeProxy>) -> (Vec<FreeProxy>, Vec<FreeProxy>) {
         const SAMPLE_SIZE: usize = 50;
         let sample_size = proxies.len().min(SAMPLE_SIZE);
         
         if proxies.is_empty() {
-            return proxies;
+            return (proxies, Vec::new());
         }
 
         // Generate random sample indices in a separate scope to ensure rng is dropped before await
@@ -242,20 +708,17 @@ impl FreeIpProviderManager {
         };
         
         // Now we can safely use async operations
-        let mut working_proxies = Vec::new();
+        let mut sampled = Vec::new();
         let mut tested_proxies = Vec::new();
         
         for &idx in &sample_indices {
             let proxy = &proxies[idx];
-            let test_result = self.test_proxy(proxy).await;
+            let test_result = Self::check_proxy(proxy).await;
             
             let mut proxy = proxy.clone();
             proxy.is_working = test_result.is_working;
             tested_proxies.push(proxy.clone());
-            
-            if test_result.is_working {
-                working_proxies.push(proxy);
-            }
+            sampled.push(proxy);
         }
         
         // Include untested proxies as non-working
@@ -267,7 +730,7 @@ impl FreeIpProviderManager {
             }
         }
         
-        tested_proxies
+        (tested_proxies, sampled)
     }
 
     /// Gets the proxy pool.
@@ -320,18 +783,16 @@ impl FreeIpProviderManager {
                 };
                 
                 if needs_update {
-                    let mut mgr = manager.write().await;
-                    if let Err(e) = mgr.update_proxy_pool().await {
-                        tracing::error!("Failed to auto-update proxy pool: {}", e);
-                    }
+                    let proxies = Self::fetch_shared(&manager).await;
+                    Self::validate_shared(&manager, proxies).await;
                 }
             }
         })
     }
 
-    async fn fetch_proxyscrape(&self) -> Result<Vec<FreeProxy>> {
+    async fn fetch_proxyscrape(client: &HttpClient) -> Result<Vec<FreeProxy>> {
         let url = "https://api.proxyscrape.com/v2/?request=displayproxies&protocol=http&timeout=10000&country=all&ssl=all&anonymity=all";
-        let response = self.http_client.get(url).await?;
+        let response = client.get(url).await?;
         
         let mut proxies = Vec::new();
         for line in response.lines() {
@@ -350,6 +811,9 @@ impl FreeIpProviderManager {
                         last_checked: chrono::Utc::now().to_rfc3339(),
                         provider: "ProxyScrape".to_string(),
                         is_working: false,
+                        username: None,
+                        password: None,
+                        ssh: None,
                     });
                 }
             }
@@ -358,7 +822,7 @@ impl FreeIpProviderManager {
         Ok(proxies)
     }
 
-    async fn fetch_geonode(&self) -> Result<Vec<FreeProxy>> {
+    async fn fetch_geonode(client: &HttpClient) -> Result<Vec<FreeProxy>> {
         #[derive(Deserialize)]
         struct GeoNodeResponse {
             data: Vec<GeoNodeProxy>,
@@ -380,7 +844,7 @@ impl FreeIpProviderManager {
         }
 
         let url = "https://proxylist.geonode.com/api/proxy-list?limit=100&page=1&sort_by=lastChecked&sort_type=desc";
-        let response: GeoNodeResponse = self.http_client.get_json(url).await?;
+        let response: GeoNodeResponse = client.get_json(url).await?;
 
         let proxies = response.data.into_iter().filter_map(|p| {
             let port = p.port.parse::<u16>().ok()?;
@@ -404,13 +868,16 @@ impl FreeIpProviderManager {
                 last_checked: chrono::Utc::now().to_rfc3339(),
                 provider: "GeoNode".to_string(),
                 is_working: false,
+                username: None,
+                password: None,
+                ssh: None,
             })
         }).collect();
 
         Ok(proxies)
     }
 
-    async fn fetch_pubproxy(&self) -> Result<Vec<FreeProxy>> {
+    async fn fetch_pubproxy(client: &HttpClient) -> Result<Vec<FreeProxy>> {
         #[derive(Deserialize)]
         struct PubProxyResponse {
             data: Vec<PubProxyProxy>,
@@ -429,7 +896,7 @@ impl FreeIpProviderManager {
         }
 
         let url = "http://pubproxy.com/api/proxy?limit=20&format=json&type=http";
-        let response: PubProxyResponse = self.http_client.get_json(url).await?;
+        let response: PubProxyResponse = client.get_json(url).await?;
 
         let proxies = response.data.into_iter().filter_map(|p| {
             let port = p.port.parse::<u16>().ok()?;
@@ -451,6 +918,9 @@ impl FreeIpProviderManager {
                 last_checked: chrono::Utc::now().to_rfc3339(),
                 provider: "PubProxy".to_string(),
                 is_working: false,
+                username: None,
+                password: None,
+                ssh: None,
             })
         }).collect();
 
@@ -459,6 +929,10 @@ impl FreeIpProviderManager {
 
     /// Tests proxy.
     pub async fn test_proxy(&self, proxy: &FreeProxy) -> crate::proxy::ProxyTestResult {
+        Self::check_proxy(proxy).await
+    }
+
+    async fn check_proxy(proxy: &FreeProxy) -> crate::proxy::ProxyTestResult {
         let settings = proxy.to_proxy_settings();
         
         let start = std::time::Instant::now();
@@ -509,3 +983,65 @@ impl Default for FreeIpProviderManager {
         Self::new().expect("Failed to create FreeIpProviderManager")
     }
 }
+
+/// Wait until a provider's rate limit allows another request
+async fn wait_for_rate_limit(last_fetch: Option<&Instant>, rate_limit: Duration) {
+    if let Some(last_fetch) = last_fetch {
+        let elapsed = last_fetch.elapsed();
+        if elapsed < rate_limit {
+            tokio::time::sleep(rate_limit - elapsed).await;
+        }
+    }
+}
+
+/// A fetch round detached from its manager
+///
+/// Taken with `FreeIpProviderManager::prepare_fetch`, run without holding the
+/// manager's lock, and merged back with `complete_fetch`.
+pub struct ProviderFetch {
+    http_client: HttpClient,
+    builtin: Vec<FreeIpProvider>,
+    custom: Vec<Arc<dyn ProxyProvider>>,
+    last_fetches: HashMap<String, Instant>,
+}
+
+/// Per-provider results of a detached fetch round
+pub struct ProviderFetchResults {
+    results: Vec<(String, Result<Vec<FreeProxy>>)>,
+    fetched_at: Vec<(String, Instant)>,
+}
+
+impl ProviderFetch {
+    /// Fetches from every provider in the snapshot, honouring rate limits
+    pub async fn run(self) -> ProviderFetchResults {
+        let mut results = Vec::new();
+        let mut fetched_at = Vec::new();
+
+        for provider in &self.builtin {
+            let name = provider.name().to_string();
+            wait_for_rate_limit(self.last_fetches.get(&name), provider.rate_limit()).await;
+            let result = FreeIpProviderManager::fetch_builtin(&self.http_client, provider).await;
+            fetched_at.push((name.clone(), Instant::now()));
+            Self::log_result(&name, &result);
+            results.push((name, result));
+        }
+
+        for provider in &self.custom {
+            let name = provider.name().to_string();
+            wait_for_rate_limit(self.last_fetches.get(&name), provider.rate_limit()).await;
+            let result = provider.fetch_proxies().await;
+            fetched_at.push((name.clone(), Instant::now()));
+            Self::log_result(&name, &result);
+            results.push((name, result));
+        }
+
+        ProviderFetchResults { results, fetched_at }
+    }
+
+    fn log_result(name: &str, result: &Result<Vec<FreeProxy>>) {
+        match result {
+            Ok(proxies) => tracing::info!("Fetched {} proxies from {}", proxies.len(), name),
+            Err(e) => tracing::error!("Failed to fetch from {}: {}", name, e),
+        }
+    }
+}
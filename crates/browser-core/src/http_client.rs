@@ -4,6 +4,7 @@
 //! - Retry logic with exponential backoff
 //! - Rate limiting support
 //! - Proxy integration
+//! - Tab TLS/HTTP2 fingerprints via `ImpersonatedClient`
 //! - Timeout and connection management
 
 use anyhow::{anyhow, Result};
@@ -17,13 +18,21 @@ use tracing::debug;
 use governor::{Quota, RateLimiter, state::{NotKeyed, InMemoryState}, clock::DefaultClock};
 use std::num::NonZeroU32;
 
+#[cfg(feature = "impersonation")]
+use crate::impersonation::{ImpersonatedClient, ImpersonatedRequest};
 use crate::proxy::ProxySettings;
+use crate::tab_isolation::{NetworkConfig, TabProfile};
+use virtual_ip::{GeoIpDatabase, GeoIpRecord};
 
 /// Represents a HttpClient.
+#[derive(Clone)]
 pub struct HttpClient {
     client: Client,
     enhanced_client: Option<ClientWithMiddleware>,
     rate_limiter: Option<Arc<RateLimiter<NotKeyed, InMemoryState, DefaultClock>>>,
+    /// When set, requests carry this client's TLS and HTTP/2 fingerprint instead of reqwest's
+    #[cfg(feature = "impersonation")]
+    impersonated: Option<Arc<ImpersonatedClient>>,
 }
 
 impl HttpClient {
@@ -51,6 +60,8 @@ impl HttpClient {
             client,
             enhanced_client: Some(enhanced_client),
             rate_limiter: Some(rate_limiter),
+            #[cfg(feature = "impersonation")]
+            impersonated: None,
         })
     }
 
@@ -59,6 +70,12 @@ impl HttpClient {
         let mut builder = Client::builder()
             .timeout(Duration::from_secs(30));
 
+        if proxy_settings.is_tunnel_protocol() {
+            return Err(anyhow!(
+                "{:?} upstreams are used through LocalProxyServer, not directly by HttpClient",
+                proxy_settings.proxy_type
+            ));
+        }
         if let Some(proxy_url) = proxy_settings.to_url() {
             let proxy = Proxy::all(&proxy_url)?;
             builder = builder.proxy(proxy);
@@ -84,11 +101,84 @@ impl HttpClient {
             client,
             enhanced_client: Some(enhanced_client),
             rate_limiter: Some(rate_limiter),
+            #[cfg(feature = "impersonation")]
+            impersonated: None,
         })
     }
 
+    /// Client that sends the TLS and HTTP/2 fingerprint of `config`, optionally through a proxy.
+    ///
+    /// Unlike `with_proxy`, SSH and Shadowsocks upstreams work directly.
+    #[cfg(feature = "impersonation")]
+    pub fn with_network_config(config: &NetworkConfig, proxy_settings: Option<&ProxySettings>) -> Result<Self> {
+        let mut impersonated = ImpersonatedClient::new(config)?;
+        if let Some(proxy) = proxy_settings.filter(|p| p.is_configured()) {
+            impersonated = impersonated.with_proxy(proxy.clone());
+        }
+        let mut client = Self::new()?;
+        client.impersonated = Some(Arc::new(impersonated));
+        Ok(client)
+    }
+
+    /// Client that looks like the tab's browser: its network fingerprint plus
+    /// User-Agent and Accept-Language headers.
+    #[cfg(feature = "impersonation")]
+    pub fn for_tab(tab: &TabProfile, proxy_settings: Option<&ProxySettings>) -> Result<Self> {
+        let headers = vec![
+            ("user-agent".to_string(), tab.fingerprint.user_agent.clone()),
+            ("accept".to_string(), "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8".to_string()),
+            ("accept-language".to_string(), tab.fingerprint.accept_language.clone()),
+        ];
+        let mut impersonated = ImpersonatedClient::new(&tab.network_config)?.with_default_headers(headers);
+        if let Some(proxy) = proxy_settings.filter(|p| p.is_configured()) {
+            impersonated = impersonated.with_proxy(proxy.clone());
+        }
+        let mut client = Self::new()?;
+        client.impersonated = Some(Arc::new(impersonated));
+        Ok(client)
+    }
+
+    /// Without the `impersonation` feature a tab's fingerprint can't be sent, so this fails
+    /// rather than falling back to reqwest's.
+    #[cfg(not(feature = "impersonation"))]
+    pub fn with_network_config(_config: &NetworkConfig, _proxy_settings: Option<&ProxySettings>) -> Result<Self> {
+        Err(anyhow!("browser-core was built without the `impersonation` feature"))
+    }
+
+    /// Without the `impersonation` feature a tab's fingerprint can't be sent, so this fails
+    /// rather than falling back to reqwest's.
+    #[cfg(not(feature = "impersonation"))]
+    pub fn for_tab(_tab: &TabProfile, _proxy_settings: Option<&ProxySettings>) -> Result<Self> {
+        Err(anyhow!("browser-core was built without the `impersonation` feature"))
+    }
+
+    /// The fingerprinting client, if this client impersonates a tab
+    #[cfg(feature = "impersonation")]
+    pub fn impersonated(&self) -> Option<&Arc<ImpersonatedClient>> {
+        self.impersonated.as_ref()
+    }
+
+    /// Sends an impersonated request; non-2xx statuses are errors.
+    #[cfg(feature = "impersonation")]
+    async fn send_impersonated(&self, client: &ImpersonatedClient, request: ImpersonatedRequest) -> Result<Vec<u8>> {
+        let url = request.url.clone();
+        let response = client
+            .send(&request)
+            .await
+            .map_err(|e| anyhow!("HTTP request failed for {}: {}", url, e))?;
+        if !(200..300).contains(&response.status) {
+            return Err(anyhow!("HTTP request failed for {}: status {}", response.url, response.status));
+        }
+        Ok(response.body)
+    }
+
     /// Performs get operation with graceful DNS error handling.
     pub async fn get(&self, url: &str) -> Result<String> {
+        #[cfg(feature = "impersonation")]
+        if let Some(client) = &self.impersonated {
+            let body = self.send_impersonated(client, ImpersonatedRequest::get(url)).await?;
+            return Ok(String::from_utf8_lossy(&body).into_owned());
+        }
         let response = self.client.get(url).send().await
             .map_err(|e| {
                 let error_str = e.to_string().to_lowercase();
@@ -112,6 +202,12 @@ impl HttpClient {
 
     /// Enhanced GET with retry and rate limiting
     pub async fn get_enhanced(&self, url: &str) -> Result<String> {
+        #[cfg(feature = "impersonation")]
+        if let (Some(client), Some(rate_limiter)) = (&self.impersonated, &self.rate_limiter) {
+            rate_limiter.until_ready().await;
+            let body = self.send_impersonated(client, ImpersonatedRequest::get(url)).await?;
+            return Ok(String::from_utf8_lossy(&body).into_owned());
+        }
         if let (Some(enhanced_client), Some(rate_limiter)) = (&self.enhanced_client, &self.rate_limiter) {
             // Wait for rate limit
             rate_limiter.until_ready().await;
@@ -144,6 +240,12 @@ impl HttpClient {
 
     /// Gets the json.
     pub async fn get_json<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
+        #[cfg(feature = "impersonation")]
+        if let Some(client) = &self.impersonated {
+            let request = ImpersonatedRequest::get(url).header("accept", "application/json");
+            let body = self.send_impersonated(client, request).await?;
+            return serde_json::from_slice(&body).map_err(|e| anyhow!("Failed to parse JSON: {}", e));
+        }
         let response = self.client.get(url).send().await
             .map_err(|e| {
                 let error_str = e.to_string().to_lowercase();
@@ -166,6 +268,11 @@ impl HttpClient {
 
     /// Enhanced GET with retry and rate limiting, returns JSON
     pub async fn get_json_enhanced<T: for<'de> Deserialize<'de>>(&self, url: &str) -> Result<T> {
+        #[cfg(feature = "impersonation")]
+        if let (Some(_), Some(rate_limiter)) = (&self.impersonated, &self.rate_limiter) {
+            rate_limiter.until_ready().await;
+            return self.get_json(url).await;
+        }
         if let (Some(enhanced_client), Some(rate_limiter)) = (&self.enhanced_client, &self.rate_limiter) {
             // Wait for rate limit
             rate_limiter.until_ready().await;
@@ -197,6 +304,17 @@ impl HttpClient {
     }
     /// Enhanced POST with retry and rate limiting
     pub async fn post_enhanced(&self, url: &str, body: String) -> Result<String> {
+        #[cfg(feature = "impersonation")]
+        if let Some(client) = &self.impersonated {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.until_ready().await;
+            }
+            let request = ImpersonatedRequest::new("POST", url)
+                .header("content-type", "application/json")
+                .body(body);
+            let body = self.send_impersonated(client, request).await?;
+            return Ok(String::from_utf8_lossy(&body).into_owned());
+        }
         if let (Some(enhanced_client), Some(rate_limiter)) = (&self.enhanced_client, &self.rate_limiter) {
             // Wait for rate limit
             rate_limiter.until_ready().await;
@@ -272,9 +390,40 @@ pub struct PublicIpInfo {
     pub lon: Option<f64>,
 }
 
+impl PublicIpInfo {
+    /// Builds IP info from a local GeoIP record.
+    pub fn from_geoip(ip: impl Into<String>, record: GeoIpRecord) -> Self {
+        Self {
+            ip: ip.into(),
+            country: record.country,
+            country_code: record.country_code,
+            city: record.city,
+            region: record.region,
+            isp: record.organization,
+            timezone: record.timezone,
+            lat: record.latitude,
+            lon: record.longitude,
+        }
+    }
+
+    /// Fills fields the detection service left empty from a local GeoIP record.
+    fn fill_from_geoip(&mut self, record: GeoIpRecord) {
+        let local = Self::from_geoip(self.ip.clone(), record);
+        self.country = self.country.take().or(local.country);
+        self.country_code = self.country_code.take().or(local.country_code);
+        self.city = self.city.take().or(local.city);
+        self.region = self.region.take().or(local.region);
+        self.isp = self.isp.take().or(local.isp);
+        self.timezone = self.timezone.take().or(local.timezone);
+        self.lat = self.lat.or(local.lat);
+        self.lon = self.lon.or(local.lon);
+    }
+}
+
 /// Represents a PublicIpDetector.
 pub struct PublicIpDetector {
     http_client: HttpClient,
+    geoip_database: Option<Arc<GeoIpDatabase>>,
 }
 
 impl PublicIpDetector {
@@ -282,6 +431,7 @@ impl PublicIpDetector {
     pub fn new() -> Result<Self> {
         Ok(Self {
             http_client: HttpClient::new()?,
+            geoip_database: None,
         })
     }
 
@@ -289,23 +439,45 @@ impl PublicIpDetector {
     pub fn with_proxy(proxy_settings: &ProxySettings) -> Result<Self> {
         Ok(Self {
             http_client: HttpClient::with_proxy(proxy_settings)?,
+            geoip_database: None,
         })
     }
 
+    /// Resolve location details from a local GeoIP database.
+    ///
+    /// With a database configured, only the bare IP is requested remotely.
+    pub fn with_geoip_database(mut self, database: Arc<GeoIpDatabase>) -> Self {
+        self.geoip_database = Some(database);
+        self
+    }
+
+    /// Looks up an arbitrary IP in the local GeoIP database.
+    pub fn lookup_local(&self, ip: &str) -> Option<PublicIpInfo> {
+        let record = self.geoip_database.as_ref()?.lookup_str(ip)?;
+        Some(PublicIpInfo::from_geoip(ip, record))
+    }
+
     #[allow(clippy::type_complexity)]
     /// Performs detect ip operation.
     pub async fn detect_ip(&self) -> Result<PublicIpInfo> {
         // Try multiple IP detection services
-        let services: Vec<(&str, fn(&str) -> Result<PublicIpInfo>)> = vec![
+        let mut services: Vec<(&str, fn(&str) -> Result<PublicIpInfo>)> = vec![
             ("https://ipapi.co/json/", Self::parse_ipapi_co as fn(&str) -> Result<PublicIpInfo>),
             ("https://api.ipify.org?format=json", Self::parse_ipify as fn(&str) -> Result<PublicIpInfo>),
             ("https://ipinfo.io/json", Self::parse_ipinfo as fn(&str) -> Result<PublicIpInfo>),
         ];
+        if self.geoip_database.is_some() {
+            // The bare-IP service is enough when location comes from the local database
+            services.swap(0, 1);
+        }
 
         for (url, parser) in services {
             match self.http_client.get(url).await {
                 Ok(response) => {
-                    if let Ok(info) = parser(&response) {
+                    if let Ok(mut info) = parser(&response) {
+                        if let Some(record) = self.geoip_database.as_ref().and_then(|db| db.lookup_str(&info.ip)) {
+                            info.fill_from_geoip(record);
+                        }
                         return Ok(info);
                     }
                 }
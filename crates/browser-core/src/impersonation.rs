@@ -0,0 +1,755 @@
+//! Impersonating HTTP Client
+//!
+//! Sends requests with the TLS and HTTP/2 fingerprint of a tab's
+//! `NetworkConfig` instead of reqwest's rustls defaults:
+//! - ClientHello cipher, extension, group, signature algorithm and ALPN order (BoringSSL)
+//! - HTTP/2 SETTINGS order, connection WINDOW_UPDATE, PRIORITY frames and pseudo-header order
+//! - HTTP CONNECT, SOCKS5, SSH and Shadowsocks upstreams
+//!
+//! BoringSSL decides the placement of GREASE, padding and pre_shared_key, and
+//! always offers TLS 1.3 suites in its own order. One connection is opened per request.
+
+use anyhow::{anyhow, Result};
+use boring::ssl::{
+    CertCompressionAlgorithm, ExtensionType, SslConnector, SslMethod, SslOptions, SslVerifyMode, SslVersion,
+};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tracing::{debug, warn};
+use url::{Host, Position, Url};
+
+use crate::local_proxy::establish_proxy_tunnel;
+use crate::proxy::ProxySettings;
+use crate::tab_isolation::{HTTP2Settings, NetworkConfig, TLSProfile};
+use crate::tls_fingerprint::*;
+
+/// Headers HTTP/2 forbids and HTTP/1.1 clients add themselves
+const CONNECTION_HEADERS: &[&str] = &["connection", "keep-alive", "proxy-connection", "transfer-encoding", "upgrade", "host"];
+
+/// Headers dropped when a redirect leaves the request's origin
+const CREDENTIAL_HEADERS: &[&str] = &["authorization", "cookie", "proxy-authorization"];
+
+/// Largest response body read, whatever length the server announces
+const MAX_BODY_SIZE: usize = 32 * 1024 * 1024;
+
+/// A request sent by `ImpersonatedClient`; headers go out in the order given.
+#[derive(Debug, Clone)]
+pub struct ImpersonatedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl ImpersonatedRequest {
+    /// Create a request with the specified method
+    pub fn new(method: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            method: method.into().to_ascii_uppercase(),
+            url: url.into(),
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Create a GET request
+    pub fn get(url: impl Into<String>) -> Self {
+        Self::new("GET", url)
+    }
+
+    /// Append a header
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Set the body
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    fn has_header(&self, name: &str) -> bool {
+        self.headers.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Response of an `ImpersonatedClient` request.
+#[derive(Debug, Clone)]
+pub struct ImpersonatedResponse {
+    pub status: u16,
+    /// Header names are lowercase
+    pub headers: Vec<(String, String)>,
+    /// Body as received; not decompressed
+    pub body: Vec<u8>,
+    /// "h2" or "http/1.1"
+    pub protocol: String,
+    /// URL after redirects
+    pub url: String,
+}
+
+impl ImpersonatedResponse {
+    /// First value of a header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Body as UTF-8, lossily
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+/// HTTP client whose connections carry a configured TLS and HTTP/2 fingerprint.
+pub struct ImpersonatedClient {
+    spec: ClientHelloSpec,
+    connector: SslConnector,
+    http2: HTTP2Settings,
+    proxy: Option<ProxySettings>,
+    default_headers: Vec<(String, String)>,
+    timeout: Duration,
+    max_redirects: usize,
+    http2_prior_knowledge: bool,
+}
+
+impl ImpersonatedClient {
+    /// Client for a tab's network configuration.
+    pub fn new(config: &NetworkConfig) -> Result<Self> {
+        Self::from_profiles(&config.tls_profile, &config.http2_settings)
+    }
+
+    /// Client for explicit TLS and HTTP/2 profiles.
+    pub fn from_profiles(tls: &TLSProfile, http2: &HTTP2Settings) -> Result<Self> {
+        let spec = ClientHelloSpec::from_profile(tls)?;
+        Ok(Self {
+            connector: build_connector(&spec, true)?,
+            spec,
+            http2: http2.clone(),
+            proxy: None,
+            default_headers: Vec::new(),
+            timeout: Duration::from_secs(30),
+            max_redirects: 10,
+            http2_prior_knowledge: false,
+        })
+    }
+
+    /// Route connections through an upstream proxy.
+    pub fn with_proxy(mut self, proxy: ProxySettings) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Headers sent before a request's own, unless the request sets them.
+    pub fn with_default_headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.default_headers = headers;
+        self
+    }
+
+    /// Overall timeout per request, redirects included.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Maximum redirects to follow; 0 returns redirects as-is.
+    pub fn with_max_redirects(mut self, max_redirects: usize) -> Self {
+        self.max_redirects = max_redirects;
+        self
+    }
+
+    /// Speak HTTP/2 without TLS to `http://` URLs (h2c with prior knowledge).
+    pub fn http2_prior_knowledge(mut self) -> Self {
+        self.http2_prior_knowledge = true;
+        self
+    }
+
+    /// Skip certificate verification, for local test servers.
+    pub fn danger_accept_invalid_certs(mut self) -> Result<Self> {
+        self.connector = build_connector(&self.spec, false)?;
+        Ok(self)
+    }
+
+    /// The resolved ClientHello parameters.
+    pub fn spec(&self) -> &ClientHelloSpec {
+        &self.spec
+    }
+
+    /// Send a request, following redirects.
+    pub async fn send(&self, request: &ImpersonatedRequest) -> Result<ImpersonatedResponse> {
+        tokio::time::timeout(self.timeout, self.send_following(request.clone()))
+            .await
+            .map_err(|_| anyhow!("Request to {} timed out", request.url))?
+    }
+
+    async fn send_following(&self, mut request: ImpersonatedRequest) -> Result<ImpersonatedResponse> {
+        for _ in 0..=self.max_redirects {
+            let response = self.exchange(&request).await?;
+            let location = match (response.status, response.header("location")) {
+                (301 | 302 | 303 | 307 | 308, Some(location)) if self.max_redirects > 0 => location.to_string(),
+                _ => return Ok(response),
+            };
+            let current = Url::parse(&request.url)?;
+            let next = current.join(&location)?;
+            debug!("Following {} redirect to {}", response.status, next);
+            if current.origin() != next.origin() {
+                request.headers.retain(|(n, _)| !CREDENTIAL_HEADERS.iter().any(|c| n.eq_ignore_ascii_case(c)));
+            }
+            if response.status == 303 || (matches!(response.status, 301 | 302) && request.method == "POST") {
+                request.method = "GET".to_string();
+                request.body.clear();
+                request
+                    .headers
+                    .retain(|(n, _)| !n.eq_ignore_ascii_case("content-type") && !n.eq_ignore_ascii_case("content-length"));
+            }
+            request.url = next.to_string();
+        }
+        Err(anyhow!("Too many redirects"))
+    }
+
+    /// One request on a fresh connection.
+    async fn exchange(&self, request: &ImpersonatedRequest) -> Result<ImpersonatedResponse> {
+        let url = Url::parse(&request.url).map_err(|e| anyhow!("Invalid URL {}: {}", request.url, e))?;
+        let host = match url.host() {
+            Some(Host::Ipv6(addr)) => addr.to_string(),
+            Some(host) => host.to_string(),
+            None => return Err(anyhow!("URL {} has no host", request.url)),
+        };
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| anyhow!("URL {} has no port", request.url))?;
+        let target = Target {
+            scheme: url.scheme().to_string(),
+            authority: url[Position::BeforeHost..Position::AfterPort].to_string(),
+            path: url[Position::BeforePath..Position::AfterQuery].to_string(),
+            url: request.url.clone(),
+        };
+        let request = self.with_defaults(request);
+
+        let stream = match &self.proxy {
+            Some(proxy) if proxy.is_configured() => establish_proxy_tunnel(proxy, &host, port).await?,
+            _ => TcpStream::connect((host.as_str(), port))
+                .await
+                .map_err(|e| anyhow!("Connection to {}:{} failed: {}", host, port, e))?,
+        };
+
+        match target.scheme.as_str() {
+            "https" => {
+                let mut config = self.connector.configure()?;
+                if self.spec.has_extension(65037) {
+                    config.set_enable_ech_grease(true);
+                }
+                let alps_new = self.spec.has_extension(17613);
+                if (alps_new || self.spec.has_extension(17513)) && self.spec.alpn.iter().any(|p| p == "h2") {
+                    config.add_application_settings(b"h2")?;
+                    config.set_alps_use_new_codepoint(alps_new);
+                }
+                let tls = tokio_boring::connect(config, &host, stream)
+                    .await
+                    .map_err(|e| anyhow!("TLS handshake with {} failed: {}", host, e))?;
+                if tls.ssl().selected_alpn_protocol() == Some(b"h2") {
+                    http2_exchange(tls, &self.http2, &request, &target).await
+                } else {
+                    http1_exchange(tls, &request, &target).await
+                }
+            }
+            "http" if self.http2_prior_knowledge => http2_exchange(stream, &self.http2, &request, &target).await,
+            "http" => http1_exchange(stream, &request, &target).await,
+            other => Err(anyhow!("Unsupported URL scheme '{}'", other)),
+        }
+    }
+
+    fn with_defaults(&self, request: &ImpersonatedRequest) -> ImpersonatedRequest {
+        let mut merged = request.clone();
+        merged.headers = self
+            .default_headers
+            .iter()
+            .filter(|(name, _)| !request.has_header(name))
+            .cloned()
+            .chain(request.headers.iter().cloned())
+            .collect();
+        merged
+    }
+}
+
+/// Where a request goes, split the way HTTP/1.1 and HTTP/2 need it.
+struct Target {
+    scheme: String,
+    authority: String,
+    path: String,
+    url: String,
+}
+
+fn build_connector(spec: &ClientHelloSpec, verify: bool) -> Result<SslConnector> {
+    let version = |v: u16| if v >= 0x0304 { SslVersion::TLS1_3 } else { SslVersion::TLS1_2 };
+    let mut builder = SslConnector::builder(SslMethod::tls_client())?;
+    builder.set_min_proto_version(Some(version(spec.min_version)))?;
+    builder.set_max_proto_version(Some(version(spec.max_version)))?;
+
+    let cipher_list = spec.cipher_list();
+    if !cipher_list.is_empty() {
+        builder.set_cipher_list(&cipher_list)?;
+    }
+    let tls13: Vec<u16> = spec.cipher_suites.iter().copied().filter(|c| (0x1301..=0x1303).contains(c)).collect();
+    if !tls13.is_empty() && tls13 != [0x1301, 0x1302, 0x1303] {
+        warn!("BoringSSL sends TLS 1.3 suites as 1301, 1302, 1303, not {:04x?}", tls13);
+    }
+    // BoringSSL sends these whenever TLS 1.2 (and, for the last three, TLS 1.3) is enabled
+    let mut implied = vec![0, 10, 11, 13, 23, 65281];
+    if spec.max_version >= 0x0304 {
+        implied.extend([43, 45, 51]);
+    }
+    let unlisted: Vec<u16> = implied.into_iter().filter(|id| !spec.has_extension(*id)).collect();
+    if !unlisted.is_empty() {
+        warn!("BoringSSL adds extensions {:?} that the profile does not list", unlisted);
+    }
+    let unplaced: Vec<u16> = spec
+        .extensions
+        .iter()
+        .copied()
+        .filter(|&id| ExtensionType::index_of(ExtensionType::from(id)).is_none())
+        .collect();
+    if !unplaced.is_empty() {
+        warn!("BoringSSL decides whether and where to send extensions {:?}", unplaced);
+    }
+    let curves = spec.curves_list();
+    if !curves.is_empty() {
+        builder.set_curves_list(&curves)?;
+    }
+    let sigalgs = spec.sigalgs_list();
+    if !sigalgs.is_empty() {
+        builder.set_sigalgs_list(&sigalgs)?;
+    }
+    if !spec.alpn.is_empty() {
+        builder.set_alpn_protos(&spec.alpn_wire())?;
+    }
+
+    builder.set_grease_enabled(spec.grease);
+    if spec.permute_extensions {
+        builder.set_permute_extensions(true);
+    } else {
+        // Configured order first, then whatever BoringSSL may add on its own
+        let mut order: Vec<ExtensionType> = spec
+            .extensions
+            .iter()
+            .map(|&id| ExtensionType::from(id))
+            .filter(|ext| ExtensionType::index_of(*ext).is_some())
+            .collect();
+        for ext in ExtensionType::BORING_SSLEXTENSION_PERMUTATION {
+            if !order.contains(ext) {
+                order.push(*ext);
+            }
+        }
+        builder.set_extension_permutation(&order)?;
+    }
+
+    if spec.has_extension(5) {
+        builder.enable_ocsp_stapling();
+    }
+    if spec.has_extension(18) {
+        builder.enable_signed_cert_timestamps();
+    }
+    if spec.has_extension(27) {
+        for alg in &spec.cert_compression {
+            builder.add_cert_compression_alg(match alg {
+                1 => CertCompressionAlgorithm::Zlib,
+                3 => CertCompressionAlgorithm::Zstd,
+                _ => CertCompressionAlgorithm::Brotli,
+            })?;
+        }
+    }
+    if spec.has_extension(28) {
+        builder.set_record_size_limit(0x4001);
+    }
+    if spec.has_extension(34) {
+        builder.set_delegated_credentials("ecdsa_secp256r1_sha256:ecdsa_secp384r1_sha384:ecdsa_secp521r1_sha512:ecdsa_sha1")?;
+    }
+    if !spec.has_extension(35) {
+        builder.set_options(SslOptions::NO_TICKET);
+    }
+    if !verify {
+        builder.set_verify(SslVerifyMode::NONE);
+    }
+    Ok(builder.build())
+}
+
+// ============================================================================
+// HTTP/1.1
+// ============================================================================
+
+async fn http1_exchange<S>(stream: S, request: &ImpersonatedRequest, target: &Target) -> Result<ImpersonatedResponse>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", request.method, target.path, target.authority);
+    for (name, value) in &request.headers {
+        if !CONNECTION_HEADERS.contains(&name.to_ascii_lowercase().as_str()) {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+    }
+    if !request.body.is_empty() && !request.has_header("content-length") {
+        head.push_str(&format!("Content-Length: {}\r\n", request.body.len()));
+    }
+    head.push_str("\r\n");
+
+    let mut stream = BufReader::new(stream);
+    stream.get_mut().write_all(head.as_bytes()).await?;
+    stream.get_mut().write_all(&request.body).await?;
+    stream.get_mut().flush().await?;
+
+    // Skip interim 1xx responses
+    let (status, headers) = loop {
+        let status_line = read_line(&mut stream).await?;
+        let status: u16 = status_line
+            .split_whitespace()
+            .nth(1)
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| anyhow!("Invalid status line '{}'", status_line))?;
+        let mut headers = Vec::new();
+        loop {
+            let line = read_line(&mut stream).await?;
+            if line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+            }
+        }
+        if !(100..200).contains(&status) {
+            break (status, headers);
+        }
+    };
+
+    let header = |name: &str| headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str());
+    let mut body = Vec::new();
+    if request.method == "HEAD" || status == 204 || status == 304 {
+        // No body
+    } else if header("transfer-encoding").is_some_and(|te| te.to_ascii_lowercase().contains("chunked")) {
+        loop {
+            let size_line = read_line(&mut stream).await?;
+            let size = usize::from_str_radix(size_line.split(';').next().unwrap_or("").trim(), 16)
+                .map_err(|_| anyhow!("Invalid chunk size '{}'", size_line))?;
+            if size == 0 {
+                // Trailers end with an empty line
+                while !read_line(&mut stream).await?.is_empty() {}
+                break;
+            }
+            let total = body.len().checked_add(size).filter(|total| *total <= MAX_BODY_SIZE);
+            let total = total.ok_or_else(|| anyhow!("Response body exceeds {} bytes", MAX_BODY_SIZE))?;
+            read_body(&mut stream, &mut body, total).await?;
+            read_line(&mut stream).await?;
+        }
+    } else if let Some(length) = header("content-length") {
+        let length: usize = length.parse().map_err(|_| anyhow!("Invalid Content-Length '{}'", length))?;
+        if length > MAX_BODY_SIZE {
+            return Err(anyhow!("Response body exceeds {} bytes", MAX_BODY_SIZE));
+        }
+        read_body(&mut stream, &mut body, length).await?;
+    } else {
+        (&mut stream).take(MAX_BODY_SIZE as u64 + 1).read_to_end(&mut body).await?;
+        if body.len() > MAX_BODY_SIZE {
+            return Err(anyhow!("Response body exceeds {} bytes", MAX_BODY_SIZE));
+        }
+    }
+
+    Ok(ImpersonatedResponse {
+        status,
+        headers,
+        body,
+        protocol: "http/1.1".to_string(),
+        url: target.url.clone(),
+    })
+}
+
+/// Read until `body` holds `total` bytes, growing it only as data arrives
+async fn read_body<R: AsyncRead + Unpin>(reader: &mut R, body: &mut Vec<u8>, total: usize) -> Result<()> {
+    let missing = total.saturating_sub(body.len());
+    reader.take(missing as u64).read_to_end(body).await?;
+    if body.len() < total {
+        return Err(anyhow!("Connection closed before the response was complete"));
+    }
+    Ok(())
+}
+
+async fn read_line<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> Result<String> {
+    let mut line = Vec::new();
+    if reader.read_until(b'\n', &mut line).await? == 0 {
+        return Err(anyhow!("Connection closed before the response was complete"));
+    }
+    Ok(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']).to_string())
+}
+
+// ============================================================================
+// HTTP/2
+// ============================================================================
+
+const DEFAULT_WINDOW: i64 = 65_535;
+const MAX_WINDOW: u32 = 0x7fff_ffff;
+const DEFAULT_MAX_FRAME: usize = 16_384;
+
+/// Frame-level I/O over one connection.
+struct Http2Connection<S> {
+    stream: S,
+    buffer: Vec<u8>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> Http2Connection<S> {
+    async fn read_frame(&mut self) -> Result<Frame> {
+        loop {
+            if let Some((frame, used)) = Frame::decode(&self.buffer) {
+                self.buffer.drain(..used);
+                return Ok(frame);
+            }
+            let mut chunk = [0u8; 16_384];
+            let n = self.stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Err(anyhow!("HTTP/2 connection closed before the response was complete"));
+            }
+            self.buffer.extend_from_slice(&chunk[..n]);
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        self.stream.write_all(bytes).await?;
+        self.stream.flush().await?;
+        Ok(())
+    }
+}
+
+/// Split a header block into HEADERS and CONTINUATION frames.
+fn header_frames(stream_id: u32, block: &[u8], priority: Option<Http2Priority>, end_stream: bool) -> Vec<u8> {
+    let mut first = Vec::new();
+    let mut flags = if end_stream { FLAG_END_STREAM } else { 0 };
+    if let Some(priority) = priority {
+        first.extend_from_slice(&priority_payload(&priority));
+        flags |= FLAG_PRIORITY;
+    }
+    let first_len = block.len().min(DEFAULT_MAX_FRAME - first.len());
+    first.extend_from_slice(&block[..first_len]);
+    let mut chunks: Vec<&[u8]> = block[first_len..].chunks(DEFAULT_MAX_FRAME).collect();
+    if chunks.is_empty() {
+        flags |= FLAG_END_HEADERS;
+    }
+
+    let mut out = Frame::new(FRAME_HEADERS, flags, stream_id, first).encode();
+    let last = chunks.len().saturating_sub(1);
+    for (i, chunk) in chunks.drain(..).enumerate() {
+        let flags = if i == last { FLAG_END_HEADERS } else { 0 };
+        out.extend(Frame::new(FRAME_CONTINUATION, flags, stream_id, chunk.to_vec()).encode());
+    }
+    out
+}
+
+async fn http2_exchange<S>(
+    stream: S,
+    settings: &HTTP2Settings,
+    request: &ImpersonatedRequest,
+    target: &Target,
+) -> Result<ImpersonatedResponse>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let fingerprint = Http2Fingerprint::from_settings(settings);
+    let mut conn = Http2Connection { stream, buffer: Vec::new() };
+
+    // Preface, SETTINGS, WINDOW_UPDATE and PRIORITY frames exactly as configured
+    let mut out = HTTP2_PREFACE.to_vec();
+    let mut payload = Vec::new();
+    for (id, value) in &fingerprint.settings {
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.extend_from_slice(&value.to_be_bytes());
+    }
+    out.extend(Frame::new(FRAME_SETTINGS, 0, 0, payload).encode());
+    if fingerprint.window_update > 0 {
+        out.extend(Frame::new(FRAME_WINDOW_UPDATE, 0, 0, fingerprint.window_update.to_be_bytes().to_vec()).encode());
+    }
+    for priority in &fingerprint.priority_frames {
+        out.extend(Frame::new(FRAME_PRIORITY, 0, priority.stream_id, priority_payload(priority)).encode());
+    }
+
+    // The request takes the first odd stream after any used for PRIORITY
+    let stream_id = fingerprint
+        .priority_frames
+        .iter()
+        .map(|p| p.stream_id)
+        .max()
+        .map_or(1, |max| (max + 1) | 1);
+
+    let mut pseudo = fingerprint.pseudo_headers.clone();
+    for letter in ['m', 'a', 's', 'p'] {
+        if !pseudo.contains(&letter) {
+            pseudo.push(letter);
+        }
+    }
+    let mut headers: Vec<(Vec<u8>, Vec<u8>)> = pseudo
+        .iter()
+        .map(|letter| match letter {
+            'm' => (b":method".to_vec(), request.method.as_bytes().to_vec()),
+            'a' => (b":authority".to_vec(), target.authority.as_bytes().to_vec()),
+            's' => (b":scheme".to_vec(), target.scheme.as_bytes().to_vec()),
+            _ => (b":path".to_vec(), target.path.as_bytes().to_vec()),
+        })
+        .collect();
+    for (name, value) in &request.headers {
+        let name = name.to_ascii_lowercase();
+        if !CONNECTION_HEADERS.contains(&name.as_str()) && (name != "te" || value == "trailers") {
+            headers.push((name.into_bytes(), value.as_bytes().to_vec()));
+        }
+    }
+    if !request.body.is_empty() && !request.has_header("content-length") {
+        headers.push((b"content-length".to_vec(), request.body.len().to_string().into_bytes()));
+    }
+    let block = loona_hpack::Encoder::new().encode(headers.iter().map(|(n, v)| (&n[..], &v[..])));
+    let headers_priority = fingerprint.headers_priority.map(|p| Http2Priority { stream_id, ..p });
+    out.extend(header_frames(stream_id, &block, headers_priority, request.body.is_empty()));
+    conn.write(&out).await?;
+
+    let our_header_table = fingerprint.settings.iter().find(|(id, _)| *id == 1).map(|(_, v)| *v as usize);
+    let mut decoder = loona_hpack::Decoder::new();
+    if let Some(size) = our_header_table {
+        decoder.set_max_allowed_table_size(size);
+    }
+
+    let mut conn_window = DEFAULT_WINDOW;
+    let mut stream_window = DEFAULT_WINDOW;
+    // SETTINGS_INITIAL_WINDOW_SIZE changes apply to open streams as a delta
+    let mut peer_initial_window = DEFAULT_WINDOW;
+    let mut peer_max_frame = DEFAULT_MAX_FRAME;
+    let mut body_sent = 0;
+    let mut pending_block: Option<(u8, u32, u8, Vec<u8>)> = None;
+    let mut status = None;
+    let mut response_headers = Vec::new();
+    let mut body = Vec::new();
+
+    loop {
+        // Send as much of the request body as flow control allows
+        while body_sent < request.body.len() && conn_window > 0 && stream_window > 0 {
+            let len = (request.body.len() - body_sent)
+                .min(peer_max_frame)
+                .min(conn_window.min(stream_window) as usize);
+            let end = body_sent + len == request.body.len();
+            let chunk = request.body[body_sent..body_sent + len].to_vec();
+            conn.write(&Frame::new(FRAME_DATA, if end { FLAG_END_STREAM } else { 0 }, stream_id, chunk).encode())
+                .await?;
+            body_sent += len;
+            conn_window -= len as i64;
+            stream_window -= len as i64;
+        }
+
+        let frame = conn.read_frame().await?;
+        match frame.kind {
+            FRAME_SETTINGS if frame.flags & FLAG_ACK == 0 => {
+                for pair in frame.payload.chunks_exact(6) {
+                    let value = u32::from_be_bytes([pair[2], pair[3], pair[4], pair[5]]);
+                    match u16::from_be_bytes([pair[0], pair[1]]) {
+                        4 => {
+                            if value > MAX_WINDOW {
+                                return Err(anyhow!("Server sent an initial window size of {}", value));
+                            }
+                            stream_window += value as i64 - peer_initial_window;
+                            peer_initial_window = value as i64;
+                        }
+                        5 => peer_max_frame = value as usize,
+                        _ => {}
+                    }
+                }
+                conn.write(&Frame::new(FRAME_SETTINGS, FLAG_ACK, 0, Vec::new()).encode()).await?;
+            }
+            FRAME_PING if frame.flags & FLAG_ACK == 0 => {
+                conn.write(&Frame::new(FRAME_PING, FLAG_ACK, 0, frame.payload).encode()).await?;
+            }
+            FRAME_WINDOW_UPDATE if frame.payload.len() == 4 => {
+                let increment =
+                    (u32::from_be_bytes([frame.payload[0], frame.payload[1], frame.payload[2], frame.payload[3]]) & 0x7fff_ffff) as i64;
+                if frame.stream_id == 0 {
+                    conn_window += increment;
+                } else if frame.stream_id == stream_id {
+                    stream_window += increment;
+                }
+            }
+            FRAME_RST_STREAM if frame.stream_id == stream_id => {
+                let code = frame.payload.get(..4).map_or(0, |c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]));
+                return Err(anyhow!("Server reset the request stream (error code {})", code));
+            }
+            FRAME_GOAWAY => {
+                let last = frame.payload.get(..4).map_or(0, |c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]) & 0x7fff_ffff);
+                if last < stream_id {
+                    return Err(anyhow!("Server refused the request with GOAWAY"));
+                }
+            }
+            FRAME_HEADERS | FRAME_PUSH_PROMISE => {
+                let mut fragment = frame.header_block()?.to_vec();
+                if frame.kind == FRAME_PUSH_PROMISE {
+                    // Promised stream ID precedes the block
+                    fragment.drain(..4.min(fragment.len()));
+                }
+                pending_block = Some((frame.kind, frame.stream_id, frame.flags, fragment));
+            }
+            FRAME_CONTINUATION => {
+                let (_, _, flags, block) = pending_block
+                    .as_mut()
+                    .ok_or_else(|| anyhow!("CONTINUATION without HEADERS"))?;
+                block.extend_from_slice(&frame.payload);
+                *flags |= frame.flags & FLAG_END_HEADERS;
+            }
+            FRAME_DATA if frame.stream_id == stream_id => {
+                let data = frame.header_block()?;
+                if body.len() + data.len() > MAX_BODY_SIZE {
+                    return Err(anyhow!("Response body exceeds {} bytes", MAX_BODY_SIZE));
+                }
+                body.extend_from_slice(data);
+                let consumed = frame.payload.len() as u32;
+                if frame.flags & FLAG_END_STREAM != 0 {
+                    break;
+                }
+                if consumed > 0 {
+                    let increment = consumed.to_be_bytes().to_vec();
+                    let mut update = Frame::new(FRAME_WINDOW_UPDATE, 0, 0, increment.clone()).encode();
+                    update.extend(Frame::new(FRAME_WINDOW_UPDATE, 0, stream_id, increment).encode());
+                    conn.write(&update).await?;
+                }
+            }
+            _ => {}
+        }
+
+        // Decode a complete header block; HPACK state must see every block, pushed ones included
+        if pending_block.as_ref().is_some_and(|(_, _, flags, _)| flags & FLAG_END_HEADERS != 0) {
+            let (kind, block_stream, flags, block) = pending_block.take().unwrap_or_default();
+            let decoded = decoder
+                .decode(&block)
+                .map_err(|e| anyhow!("Invalid response header block: {:?}", e))?;
+            if kind != FRAME_HEADERS || block_stream != stream_id {
+                continue;
+            }
+            if status.is_none() {
+                let code: u16 = decoded
+                    .iter()
+                    .find(|(n, _)| n == b":status")
+                    .and_then(|(_, v)| std::str::from_utf8(v).ok()?.parse().ok())
+                    .ok_or_else(|| anyhow!("Response without :status"))?;
+                if !(100..200).contains(&code) {
+                    status = Some(code);
+                    response_headers = decoded
+                        .into_iter()
+                        .filter(|(n, _)| !n.starts_with(b":"))
+                        .map(|(n, v)| (String::from_utf8_lossy(&n).into_owned(), String::from_utf8_lossy(&v).into_owned()))
+                        .collect();
+                }
+            }
+            if flags & FLAG_END_STREAM != 0 && status.is_some() {
+                break;
+            }
+        }
+    }
+
+    Ok(ImpersonatedResponse {
+        status: status.ok_or_else(|| anyhow!("Stream ended without response headers"))?,
+        headers: response_headers,
+        body,
+        protocol: "h2".to_string(),
+        url: target.url.clone(),
+    })
+}
@@ -17,7 +17,16 @@ pub mod config_manager;
 
 pub mod tab_isolation;
 pub mod fingerprint;
+pub mod fingerprint_lint;
+pub mod fingerprint_injection;
+pub mod fingerprint_noise;
+pub mod target_injection;
+pub mod exit_location;
+pub mod tls_fingerprint;
+#[cfg(feature = "impersonation")]
+pub mod impersonation;
 pub mod proxy;
+pub mod proxy_import;
 pub mod http_client;
 pub mod request;
 pub mod scraper_util;
@@ -25,6 +34,11 @@ pub mod security;
 pub mod webview_manager;
 pub mod browser_tab_manager;
 pub mod free_ip_providers;
+pub mod custom_providers;
+pub mod backconnect;
+pub mod tor;
+pub mod ssh_tunnel;
+pub mod shadowsocks;
 pub mod storage;
 pub mod backup;
 pub mod browser_controls;
@@ -32,6 +46,7 @@ pub mod local_proxy;
 pub mod pac_server;
 pub mod proxy_rotation;
 pub mod proxy_validator;
+pub mod validation_scheduler;
 pub mod chromium_engine;
 pub mod ad_verification;
 
@@ -63,17 +78,39 @@ pub use config_manager::{
     NetworkConfig as AppNetworkConfig, StorageConfig, LoggingConfig, FeatureFlags
 };
 
-pub use tab_manager::TabIPManager;
+pub use tab_manager::{TabIPManager, ExitVerifier, PublicIpExitVerifier, ExitBindingConfig};
 pub use tab_isolation::{TabProfile, NetworkConfig, TabStatus, TLSProfile, HTTP2Settings, TCPFingerprint};
-pub use fingerprint::BrowserFingerprint;
+pub use fingerprint::{
+    BrowserFingerprint, FingerprintGenerator, FingerprintRequest, FingerprintDataset,
+    OsProfile, BrowserVersion, DeviceClass, GpuProfile, Weighted
+};
 pub use proxy::{ProxyManager, ProxySettings, ProxyType, FreeProxy, ProxyTestResult};
+pub use proxy_import::{ProxyImporter, ProxyListFormat, ImportReport, ImportError, export_proxies};
 pub use http_client::{HttpClient, PublicIpDetector, PublicIpInfo};
+pub use tls_fingerprint::{ClientHelloSpec, ClientHelloInfo, Http2Fingerprint, Http2Priority, HTTP2_PREFACE, is_grease, parse_client_hello, tls_record_len};
+#[cfg(feature = "impersonation")]
+pub use impersonation::{ImpersonatedClient, ImpersonatedRequest, ImpersonatedResponse};
+pub use fingerprint_injection::{FingerprintInjection, ClientHints, BrandVersion};
+pub use fingerprint_noise::{NoiseHashes, NoiseRotation};
+pub use exit_location::ExitLocation;
 pub use request::{RequestBuilder, RequestManager, RequestConfig, RequestResponse, RequestError, RequestErrorKind, HttpMethod, RequestBody};
 pub use scraper_util::ProxyScraper;
 pub use security::{SecurityManager, BookmarkInput, ProxyInput};
 pub use webview_manager::{WebviewManager, WebviewTab};
 pub use browser_tab_manager::{BrowserTabManager, BrowserTab, CreateTabConfig, TabStats};
-pub use free_ip_providers::{FreeIpProvider, FreeIpProviderManager, ProxyFilter};
+pub use fingerprint_lint::{
+    FingerprintLinter, LintContext, LintFinding, LintFix, LintReport, LintRule, LintSeverity
+};
+pub use free_ip_providers::{
+    FreeIpProvider, FreeIpProviderManager, ProxyFilter, ProxyProvider,
+    ProxyProvenance, ProviderStats, ProviderHealthPolicy, ProviderFetch, ProviderFetchResults,
+    correct_proxy_country
+};
+pub use custom_providers::{DeclarativeProvider, ProviderDefinition, ResponseFormat, FieldMapping};
+pub use backconnect::{BackconnectGateway, BackconnectProvider, GatewayTarget};
+pub use ssh_tunnel::{SshOptions, SshTunnelPool};
+pub use shadowsocks::{ShadowsocksCipher, ShadowsocksConfig, ShadowsocksServer, Sip008Document, Sip008Server};
+pub use tor::{TorAuth, TorCircuit, TorConfig, TorControl, TorExitInfo, TorProvider, TorRelay};
 pub use storage::{
     SessionManager, BrowserSession, SessionTab, SessionSettings, SessionProxyConfig,
     WindowState, ScrollPosition, TabHistoryEntry, SessionStatistics,
@@ -104,9 +141,14 @@ pub use proxy_rotation::{
 };
 pub use proxy_validator::{
     ProxyValidator, ProxyValidatorConfig, ValidationResult, ProxyHealthChecker,
-    ProxyQuarantineManager, QuarantinedProxy, QuarantineStats,
+    ProxyQuarantineManager, QuarantinedProxy, QuarantineStats, QuarantineReason,
+    TamperCheckConfig, TamperDetection,
     GeoVerifier, GeoVerificationConfig, GeoVerificationResult,
-    EnhancedProxyHealthChecker
+    EnhancedProxyHealthChecker,
+    ThroughputBenchmarkConfig, ThroughputResult, ThroughputSample
+};
+pub use validation_scheduler::{
+    ValidationScheduler, ValidationSchedulerConfig, ValidationSchedulerStatus, ValidationPriority
 };
 pub use chromium_engine::{
     ChromiumEngine,
@@ -114,6 +156,8 @@ pub use chromium_engine::{
     BrowserEngineManager,
     BrowserEngineType,
     ChromiumTab,
+    ChromiumIceSource,
+    ChromiumDnsProbe,
     EngineCapabilities
 };
 pub use ad_verification::{
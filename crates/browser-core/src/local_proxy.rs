@@ -6,10 +6,13 @@ use std::sync::Arc;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
-use crate::proxy::ProxySettings;
+use crate::proxy::{ProxySettings, ProxyType};
+use crate::shadowsocks::ShadowsocksConfig;
+use crate::ssh_tunnel::SshTunnelPool;
 
 // ============================================================================
 // Shared Utility Functions
@@ -56,6 +59,118 @@ async fn send_connect_request(
     Ok(())
 }
 
+/// Perform a SOCKS5 CONNECT (RFC 1928), with username/password auth (RFC 1929) when configured
+async fn send_socks5_connect(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    proxy: &ProxySettings,
+) -> Result<()> {
+    let credentials = proxy
+        .username
+        .as_deref()
+        .map(|user| (user, proxy.password.as_deref().unwrap_or("")));
+    // RFC 1929 and the domain address type carry one-byte lengths
+    if let Some((user, pass)) = credentials {
+        if user.len() > 255 || pass.len() > 255 {
+            return Err(anyhow!("SOCKS5 username and password must be at most 255 bytes each"));
+        }
+    }
+    if host.len() > 255 {
+        return Err(anyhow!("SOCKS5 target host '{}' is longer than 255 bytes", host));
+    }
+    let greeting: &[u8] = if credentials.is_some() { &[0x05, 0x02, 0x00, 0x02] } else { &[0x05, 0x01, 0x00] };
+    stream.write_all(greeting).await?;
+
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    match (choice[1], credentials) {
+        (0x00, _) => {}
+        (0x02, Some((user, pass))) => {
+            let mut auth = vec![0x01, user.len() as u8];
+            auth.extend_from_slice(user.as_bytes());
+            auth.push(pass.len() as u8);
+            auth.extend_from_slice(pass.as_bytes());
+            stream.write_all(&auth).await?;
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0x00 {
+                return Err(anyhow!("SOCKS5 authentication rejected"));
+            }
+        }
+        _ => return Err(anyhow!("SOCKS5 proxy accepted none of the offered auth methods")),
+    }
+
+    let mut request = vec![0x05, 0x01, 0x00];
+    match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(std::net::IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(anyhow!("SOCKS5 CONNECT to {}:{} failed with code {}", host, port, reply[1]));
+    }
+    // Skip the bound address
+    let addr_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        other => return Err(anyhow!("Unknown SOCKS5 address type {}", other)),
+    };
+    let mut bound = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound).await?;
+    Ok(())
+}
+
+/// Perform a SOCKS4 CONNECT, with the SOCKS4a extension for hostnames
+///
+/// The username, if any, goes in the USERID field; SOCKS4 has no passwords.
+async fn send_socks4_connect(
+    stream: &mut TcpStream,
+    host: &str,
+    port: u16,
+    proxy: &ProxySettings,
+) -> Result<()> {
+    let mut request = vec![0x04, 0x01];
+    request.extend_from_slice(&port.to_be_bytes());
+    let ip = match host.parse::<std::net::IpAddr>() {
+        Ok(std::net::IpAddr::V4(ip)) => Some(ip),
+        Ok(std::net::IpAddr::V6(_)) => return Err(anyhow!("SOCKS4 cannot connect to IPv6 address {}", host)),
+        Err(_) => None,
+    };
+    // 0.0.0.1 asks a SOCKS4a proxy to resolve the hostname that follows the USERID
+    request.extend_from_slice(&ip.map(|ip| ip.octets()).unwrap_or([0, 0, 0, 1]));
+    request.extend_from_slice(proxy.username.as_deref().unwrap_or("").as_bytes());
+    request.push(0x00);
+    if ip.is_none() {
+        request.extend_from_slice(host.as_bytes());
+        request.push(0x00);
+    }
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 8];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x5A {
+        return Err(anyhow!("SOCKS4 CONNECT to {}:{} failed with code {}", host, port, reply[1]));
+    }
+    Ok(())
+}
+
 /// Extract proxy address from ProxySettings
 fn get_proxy_address(proxy: &ProxySettings) -> Result<String> {
     let host = proxy.host.as_ref()
@@ -73,15 +188,46 @@ async fn connect_to_proxy(proxy: &ProxySettings) -> Result<TcpStream> {
         .map_err(|e| anyhow!("Failed to connect to proxy {} - {}", proxy_addr, e))
 }
 
+/// Connected loopback socket pair
+///
+/// Lets tunnels that are not plain TCP (SSH channels, encrypted streams) be
+/// handed out as a `TcpStream`: the caller keeps the first socket while a
+/// relay task services the second.
+pub(crate) async fn loopback_pair() -> Result<(TcpStream, TcpStream)> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    let (client, accepted) = tokio::join!(TcpStream::connect(addr), listener.accept());
+    let client = client?;
+    let (server, peer) = accepted?;
+    if peer != client.local_addr()? {
+        return Err(anyhow!("Unexpected connection on loopback bridge from {}", peer));
+    }
+    Ok((client, server))
+}
+
 /// Establish a CONNECT tunnel through an upstream proxy (shared implementation)
 /// This consolidates the duplicate tunnel establishment logic
-async fn establish_proxy_tunnel(
+///
+/// SOCKS upstreams get a SOCKS CONNECT, SSH upstreams open a `direct-tcpip` channel on
+/// the server's shared session instead, and Shadowsocks upstreams an encrypted stream to the server.
+pub(crate) async fn establish_proxy_tunnel(
     proxy: &ProxySettings,
     target_host: &str,
     target_port: u16,
 ) -> Result<TcpStream> {
+    match proxy.proxy_type {
+        ProxyType::Ssh => return SshTunnelPool::global().open_stream(proxy, target_host, target_port).await,
+        ProxyType::Shadowsocks => {
+            return ShadowsocksConfig::from_settings(proxy)?.connect(target_host, target_port).await;
+        }
+        _ => {}
+    }
     let mut proxy_stream = connect_to_proxy(proxy).await?;
-    send_connect_request(&mut proxy_stream, target_host, target_port, proxy).await?;
+    match proxy.proxy_type {
+        ProxyType::Socks5 => send_socks5_connect(&mut proxy_stream, target_host, target_port, proxy).await?,
+        ProxyType::Socks4 => send_socks4_connect(&mut proxy_stream, target_host, target_port, proxy).await?,
+        _ => send_connect_request(&mut proxy_stream, target_host, target_port, proxy).await?,
+    }
     Ok(proxy_stream)
 }
 
@@ -120,12 +266,27 @@ async fn forward_bidirectional(client_stream: TcpStream, target_stream: TcpStrea
 // Local Proxy Server
 // ============================================================================
 
+/// Largest request head the relay will buffer
+const MAX_REQUEST_HEAD: usize = 64 * 1024;
+
+/// Headers that apply to the client's hop and are not forwarded
+const HOP_BY_HOP_HEADERS: [&str; 4] = ["connection", "keep-alive", "proxy-connection", "proxy-authorization"];
+
+/// A parsed client request
+enum RelayRequest {
+    /// `CONNECT host:port`: answered with 200, then tunnelled
+    Connect { host: String, port: u16 },
+    /// Absolute-form request rewritten to origin form, ready to send to the target
+    Forward { host: String, port: u16, head: Vec<u8> },
+}
+
 /// Local proxy server for routing tab traffic through upstream proxies
 pub struct LocalProxyServer {
     bind_addr: SocketAddr,
     upstream_proxy: Option<ProxySettings>,
     connections: Arc<RwLock<HashMap<String, ProxyConnection>>>,
     is_running: Arc<RwLock<bool>>,
+    accept_task: std::sync::Mutex<Option<JoinHandle<()>>>,
 }
 
 /// Represents an active proxy connection
@@ -152,9 +313,24 @@ impl LocalProxyServer {
             upstream_proxy,
             connections: Arc::new(RwLock::new(HashMap::new())),
             is_running: Arc::new(RwLock::new(false)),
+            accept_task: std::sync::Mutex::new(None),
         })
     }
 
+    /// Start a relay to `upstream` on an ephemeral loopback port
+    ///
+    /// Lets clients that only speak HTTP proxies (reqwest, the validator) use
+    /// upstreams that need a local relay, such as SSH or Shadowsocks.
+    pub async fn start_relay(upstream: ProxySettings) -> Result<(Self, SocketAddr)> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = Self::new(addr.port(), Some(upstream))?;
+        *server.is_running.write().await = true;
+        server.serve(listener);
+        debug!("Relay listening on {}", addr);
+        Ok((server, addr))
+    }
+
     /// Start the local proxy server
     pub async fn start(&self) -> Result<()> {
         let mut is_running = self.is_running.write().await;
@@ -170,15 +346,19 @@ impl LocalProxyServer {
         *is_running = true;
         drop(is_running);
 
+        self.serve(listener);
+        Ok(())
+    }
+
+    fn serve(&self, listener: TcpListener) {
         let connections = self.connections.clone();
         let upstream_proxy = self.upstream_proxy.clone();
         let is_running = self.is_running.clone();
 
-        tokio::spawn(async move {
+        let task = tokio::spawn(async move {
             Self::accept_connections(listener, connections, upstream_proxy, is_running).await;
         });
-
-        Ok(())
+        *self.accept_task.lock().unwrap_or_else(|e| e.into_inner()) = Some(task);
     }
 
     /// Accept incoming connections loop (extracted for reduced complexity)
@@ -223,6 +403,9 @@ impl LocalProxyServer {
     pub async fn stop(&self) -> Result<()> {
         let mut is_running = self.is_running.write().await;
         *is_running = false;
+        if let Some(task) = self.accept_task.lock().unwrap_or_else(|e| e.into_inner()).take() {
+            task.abort();
+        }
 
         let mut connections = self.connections.write().await;
         connections.clear();
@@ -237,6 +420,9 @@ impl LocalProxyServer {
     }
 
     /// Handle an incoming proxy connection (refactored for lower complexity)
+    ///
+    /// CONNECT requests are answered and tunnelled; absolute-form requests for plain
+    /// `http://` URLs are rewritten to origin form and forwarded on a fresh tunnel.
     async fn handle_connection(
         mut client_stream: TcpStream,
         client_addr: String,
@@ -244,7 +430,10 @@ impl LocalProxyServer {
         upstream_proxy: Option<ProxySettings>,
         connections: Arc<RwLock<HashMap<String, ProxyConnection>>>,
     ) -> Result<()> {
-        let (target_host, target_port) = Self::read_and_parse_connect_request(&mut client_stream).await?;
+        let request = Self::read_and_parse_request(&mut client_stream).await?;
+        let (target_host, target_port) = match &request {
+            RelayRequest::Connect { host, port } | RelayRequest::Forward { host, port, .. } => (host.clone(), *port),
+        };
 
         Self::record_connection(
             &connections,
@@ -255,10 +444,28 @@ impl LocalProxyServer {
             &upstream_proxy,
         ).await;
 
-        // Send 200 Connection established response
-        client_stream.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await?;
-
-        let target_stream = Self::connect_to_target(&upstream_proxy, &target_host, target_port).await?;
+        let target_stream = match request {
+            RelayRequest::Connect { .. } => {
+                // Send 200 Connection established response
+                client_stream.write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n").await?;
+                Self::connect_to_target(&upstream_proxy, &target_host, target_port).await?
+            }
+            RelayRequest::Forward { head, .. } => {
+                match Self::connect_to_target(&upstream_proxy, &target_host, target_port).await {
+                    Ok(mut stream) => {
+                        stream.write_all(&head).await?;
+                        stream
+                    }
+                    Err(e) => {
+                        Self::remove_connection(&connections, &conn_id).await;
+                        let _ = client_stream
+                            .write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\nConnection: close\r\n\r\n")
+                            .await;
+                        return Err(e);
+                    }
+                }
+            }
+        };
 
         forward_bidirectional(client_stream, target_stream).await;
 
@@ -268,12 +475,28 @@ impl LocalProxyServer {
         Ok(())
     }
 
-    /// Read and parse the CONNECT request from client
-    async fn read_and_parse_connect_request(client_stream: &mut TcpStream) -> Result<(String, u16)> {
-        let mut buffer = vec![0u8; 4096];
-        let n = client_stream.read(&mut buffer).await?;
-        let request = String::from_utf8_lossy(&buffer[..n]);
-        Self::parse_connect_request(&request)
+    /// Read the request head from the client and parse it
+    ///
+    /// Anything the client sent after the head (the start of a request body) is
+    /// kept in a forwarded request so it reaches the target.
+    async fn read_and_parse_request(client_stream: &mut TcpStream) -> Result<RelayRequest> {
+        let mut buffer = Vec::with_capacity(4096);
+        let mut chunk = [0u8; 4096];
+        let head_len = loop {
+            let n = client_stream.read(&mut chunk).await?;
+            if n == 0 {
+                return Err(anyhow!("Client closed the connection before sending a request"));
+            }
+            buffer.extend_from_slice(&chunk[..n]);
+            if let Some(pos) = buffer.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+            if buffer.len() > MAX_REQUEST_HEAD {
+                return Err(anyhow!("Request head exceeds {} bytes", MAX_REQUEST_HEAD));
+            }
+        };
+        let head = String::from_utf8_lossy(&buffer[..head_len]).into_owned();
+        Self::parse_request(&head, &buffer[head_len..])
     }
 
     /// Record a new connection
@@ -334,6 +557,48 @@ impl LocalProxyServer {
         Self::parse_host_port(parts[1])
     }
 
+    /// Parse a request head: CONNECT, or an absolute-form `http://` request
+    fn parse_request(head: &str, body: &[u8]) -> Result<RelayRequest> {
+        let first_line = head.lines().next()
+            .ok_or_else(|| anyhow!("Empty request"))?;
+        if first_line.starts_with("CONNECT ") {
+            let (host, port) = Self::parse_connect_request(head)?;
+            return Ok(RelayRequest::Connect { host, port });
+        }
+
+        let parts: Vec<&str> = first_line.split_whitespace().collect();
+        if parts.len() != 3 {
+            return Err(anyhow!("Invalid request line: {}", first_line));
+        }
+        let (method, target, version) = (parts[0], parts[1], parts[2]);
+        let url = url::Url::parse(target)
+            .map_err(|_| anyhow!("Expected CONNECT or an absolute http:// URL, got {}", target))?;
+        if url.scheme() != "http" {
+            return Err(anyhow!("Unsupported scheme in proxy request: {}", url.scheme()));
+        }
+        let host = url.host_str()
+            .ok_or_else(|| anyhow!("No host in {}", target))?
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_string();
+        let port = url.port_or_known_default().unwrap_or(80);
+
+        // Origin form, and one request per tunnel: the next request may be for another host
+        let mut rewritten = format!("{} {} {}\r\n", method, &url[url::Position::BeforePath..url::Position::AfterQuery], version);
+        for line in head.lines().skip(1).take_while(|line| !line.is_empty()) {
+            let name = line.split(':').next().unwrap_or("").trim().to_ascii_lowercase();
+            if !HOP_BY_HOP_HEADERS.contains(&name.as_str()) {
+                rewritten.push_str(line);
+                rewritten.push_str("\r\n");
+            }
+        }
+        rewritten.push_str("Connection: close\r\n\r\n");
+
+        let mut forwarded = rewritten.into_bytes();
+        forwarded.extend_from_slice(body);
+        Ok(RelayRequest::Forward { host, port, head: forwarded })
+    }
+
     /// Parse host:port string
     fn parse_host_port(target: &str) -> Result<(String, u16)> {
         let target_parts: Vec<&str> = target.split(':').collect();
@@ -3,7 +3,8 @@
 //! Part of the V1000 Upgrade Deep Plan - Phase 2 Feature Expansion  
 //! Provides advanced anti-tracking, fingerprint randomization, and privacy scoring.
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
@@ -11,6 +12,8 @@ use std::time::Instant;
 use tokio::sync::RwLock;
 use tracing::{debug, info};
 
+use crate::fingerprint_noise::{NoiseRotation, NOISE_SCRIPT};
+
 /// Privacy fortress manager
 pub struct PrivacyFortress {
     tracker_blocker: Arc<RwLock<TrackerBlocker>>,
@@ -52,6 +55,9 @@ pub struct PrivacyConfig {
     pub cookie_isolation: CookieIsolationLevel,
     /// Privacy level (1-5)
     pub privacy_level: u8,
+    /// Noise seed of a persistent profile; a new random seed when unset
+    #[serde(default)]
+    pub noise_seed: Option<u64>,
 }
 
 impl Default for PrivacyConfig {
@@ -70,6 +76,7 @@ impl Default for PrivacyConfig {
             block_third_party_cookies: true,
             cookie_isolation: CookieIsolationLevel::Domain,
             privacy_level: 3,
+            noise_seed: None,
         }
     }
 }
@@ -235,21 +242,19 @@ pub struct TrackerStats {
 pub struct FingerprintProtector {
     randomized_values: FingerprintValues,
     protection_level: u8,
-    rotations: u64,
+    rotations: Vec<NoiseRotation>,
 }
 
 /// Randomized fingerprint values
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a FingerprintValues.
 pub struct FingerprintValues {
-    /// Canvas noise seed
+    /// Canvas, audio and WebGL readback noise seed
     pub canvas_noise_seed: u64,
     /// WebGL vendor
     pub webgl_vendor: String,
     /// WebGL renderer
     pub webgl_renderer: String,
-    /// Audio context offset
-    pub audio_offset: f64,
     /// Screen resolution
     pub screen_resolution: (u32, u32),
     /// Color depth
@@ -270,13 +275,19 @@ pub struct FingerprintValues {
 
 impl Default for FingerprintValues {
     fn default() -> Self {
-        let mut rng = rand::thread_rng();
-        
+        Self::from_seed(rand::thread_rng().gen())
+    }
+}
+
+impl FingerprintValues {
+    /// Values for a noise seed; the same seed always gives the same values.
+    pub fn from_seed(seed: u64) -> Self {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
         Self {
-            canvas_noise_seed: rng.gen(),
+            canvas_noise_seed: seed,
             webgl_vendor: "Intel Inc.".to_string(),
             webgl_renderer: "Intel Iris OpenGL Engine".to_string(),
-            audio_offset: rng.gen_range(-0.0001..0.0001),
             screen_resolution: (1920, 1080),
             color_depth: 24,
             hardware_concurrency: rng.gen_range(4..16),
@@ -297,18 +308,35 @@ impl Default for FingerprintValues {
 impl FingerprintProtector {
     /// Create a new fingerprint protector
     pub fn new(protection_level: u8) -> Self {
+        Self::with_seed(protection_level, rand::thread_rng().gen())
+    }
+
+    /// Create a fingerprint protector whose values and noise follow `seed`
+    pub fn with_seed(protection_level: u8, seed: u64) -> Self {
         Self {
-            randomized_values: FingerprintValues::default(),
+            randomized_values: FingerprintValues::from_seed(seed),
             protection_level,
-            rotations: 0,
+            rotations: Vec::new(),
         }
     }
 
-    /// Rotate fingerprint values
-    pub fn rotate(&mut self) {
-        self.randomized_values = FingerprintValues::default();
-        self.rotations += 1;
-        info!("Rotated fingerprint values (rotation #{})", self.rotations);
+    /// Rotate fingerprint values to a new seed and record why
+    pub fn rotate(&mut self, reason: impl Into<String>) -> NoiseRotation {
+        let seed = rand::thread_rng().gen();
+        let rotation = NoiseRotation::new(self.randomized_values.canvas_noise_seed, seed, reason);
+        self.randomized_values = FingerprintValues::from_seed(seed);
+        info!(
+            "Rotated fingerprint values (rotation #{}): {}",
+            self.rotations.len() + 1,
+            rotation.reason
+        );
+        self.rotations.push(rotation.clone());
+        rotation
+    }
+
+    /// Rotations so far, oldest first
+    pub fn rotations(&self) -> &[NoiseRotation] {
+        &self.rotations
     }
 
     /// Get JavaScript to inject for fingerprint protection
@@ -317,23 +345,8 @@ impl FingerprintProtector {
         
         format!(r#"
 (function() {{
-    // Canvas fingerprint protection
-    const originalToDataURL = HTMLCanvasElement.prototype.toDataURL;
-    HTMLCanvasElement.prototype.toDataURL = function(type) {{
-        const ctx = this.getContext('2d');
-        if (ctx) {{
-            try {{
-                const imageData = ctx.getImageData(0, 0, this.width, this.height);
-                const seed = {canvas_seed};
-                for (let i = 0; i < imageData.data.length; i += 4) {{
-                    const noise = ((seed * i) % 10) - 5;
-                    imageData.data[i] = Math.min(255, Math.max(0, imageData.data[i] + noise));
-                }}
-                ctx.putImageData(imageData, 0, 0);
-            }} catch(e) {{}}
-        }}
-        return originalToDataURL.apply(this, arguments);
-    }};
+    // Canvas, WebGL and audio readback noise, stable per seed and origin
+    ({noise})(window, '{noise_seed:016x}', (fn) => fn);
 
     // WebGL fingerprint protection
     const getParameter = WebGLRenderingContext.prototype.getParameter;
@@ -373,25 +386,11 @@ impl FingerprintProtector {
     Object.defineProperty(screen, 'availHeight', {{ get: () => {screen_height} }});
     Object.defineProperty(screen, 'colorDepth', {{ get: () => {color_depth} }});
 
-    // Audio context fingerprint protection
-    const audioConstructor = window.AudioContext || window.webkitAudioContext;
-    if (audioConstructor) {{
-        const originalCreateOscillator = audioConstructor.prototype.createOscillator;
-        audioConstructor.prototype.createOscillator = function() {{
-            const osc = originalCreateOscillator.apply(this, arguments);
-            const originalStart = osc.start;
-            osc.start = function() {{
-                arguments[0] = (arguments[0] || 0) + {audio_offset};
-                return originalStart.apply(this, arguments);
-            }};
-            return osc;
-        }};
-    }}
-
     console.log('[PrivacyFortress] Fingerprint protection active');
 }})();
 "#,
-            canvas_seed = fp.canvas_noise_seed,
+            noise = NOISE_SCRIPT,
+            noise_seed = fp.canvas_noise_seed,
             webgl_vendor = fp.webgl_vendor,
             webgl_renderer = fp.webgl_renderer,
             hardware_concurrency = fp.hardware_concurrency,
@@ -401,7 +400,6 @@ impl FingerprintProtector {
             screen_width = fp.screen_resolution.0,
             screen_height = fp.screen_resolution.1,
             color_depth = fp.color_depth,
-            audio_offset = fp.audio_offset,
         )
     }
 
@@ -674,7 +672,10 @@ impl PrivacyFortress {
         
         Self {
             tracker_blocker: Arc::new(RwLock::new(TrackerBlocker::new())),
-            fingerprint_protector: Arc::new(RwLock::new(FingerprintProtector::new(config.privacy_level))),
+            fingerprint_protector: Arc::new(RwLock::new(match config.noise_seed {
+                Some(seed) => FingerprintProtector::with_seed(config.privacy_level, seed),
+                None => FingerprintProtector::new(config.privacy_level),
+            })),
             cookie_manager: Arc::new(RwLock::new(PrivacyCookieManager::new(config.cookie_isolation))),
             privacy_score: Arc::new(RwLock::new(PrivacyScore::default())),
             leak_prevention: Arc::new(RwLock::new(LeakPrevention::new())),
@@ -709,10 +710,15 @@ impl PrivacyFortress {
         scripts
     }
 
-    /// Rotate fingerprint values
-    pub async fn rotate_fingerprint(&self) {
+    /// Rotate fingerprint values; noise only changes through this
+    pub async fn rotate_fingerprint(&self, reason: impl Into<String>) -> NoiseRotation {
         let mut protector = self.fingerprint_protector.write().await;
-        protector.rotate();
+        protector.rotate(reason)
+    }
+
+    /// Fingerprint rotations so far, oldest first
+    pub async fn get_rotations(&self) -> Vec<NoiseRotation> {
+        self.fingerprint_protector.read().await.rotations().to_vec()
     }
 
     /// Get current privacy score
@@ -886,4 +892,30 @@ mod tests {
         assert!(fp1.canvas_noise_seed != fp2.canvas_noise_seed || 
                 fp1.hardware_concurrency != fp2.hardware_concurrency);
     }
+
+    #[test]
+    fn test_seeded_protection_is_stable() {
+        let first = FingerprintProtector::with_seed(3, 42);
+        let second = FingerprintProtector::with_seed(3, 42);
+
+        assert_eq!(first.get_protection_script(), second.get_protection_script());
+        assert!(first.get_protection_script().contains("'000000000000002a'"));
+    }
+
+    #[tokio::test]
+    async fn test_rotation_is_recorded() {
+        let fortress = PrivacyFortress::with_config(PrivacyConfig {
+            noise_seed: Some(7),
+            ..Default::default()
+        });
+        let before = fortress.get_protection_scripts().await;
+        assert_eq!(before, fortress.get_protection_scripts().await);
+        assert!(fortress.get_rotations().await.is_empty());
+
+        let rotation = fortress.rotate_fingerprint("detected on example.com").await;
+        assert_eq!(rotation.previous_seed, 7);
+        assert_eq!(rotation.reason, "detected on example.com");
+        assert_eq!(fortress.get_rotations().await, vec![rotation]);
+        assert_ne!(before, fortress.get_protection_scripts().await);
+    }
 }
@@ -1,17 +1,45 @@
 //! Proxy Module
 //!
 //! Provides proxy configuration and management including:
-//! - Proxy settings (HTTP, HTTPS, SOCKS4, SOCKS5)
+//! - Proxy settings (HTTP, HTTPS, SOCKS4, SOCKS5, SSH, Shadowsocks)
 //! - Free proxy management and rotation
 //! - Proxy URL generation and validation
 //! - Proxy fetching from public sources
 
 use anyhow::Result;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::warn;
 
+use crate::proxy_import::{export_proxies, ImportReport, ProxyImporter, ProxyListFormat};
+use crate::shadowsocks::ShadowsocksConfig;
+use crate::ssh_tunnel::SshOptions;
+
+/// Characters escaped in URL credentials, so `@`, `:`, `/` or `%` survive a round trip
+const USERINFO: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'/')
+    .add(b':')
+    .add(b';')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'@')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 /// Enumeration of ProxyType variants.
 #[derive(Default)]
@@ -22,6 +50,39 @@ pub enum ProxyType {
     Https,
     Socks4,
     Socks5,
+    /// SSH server tunnelling through `direct-tcpip` channels
+    Ssh,
+    /// Shadowsocks server; the method goes in `username` and the key in `password`
+    Shadowsocks,
+}
+
+impl ProxyType {
+    /// Protocol name the UI sends and displays
+    pub fn name(&self) -> &'static str {
+        match self {
+            ProxyType::Direct => "direct",
+            ProxyType::Http => "http",
+            ProxyType::Https => "https",
+            ProxyType::Socks4 => "socks4",
+            ProxyType::Socks5 => "socks5",
+            ProxyType::Ssh => "ssh",
+            ProxyType::Shadowsocks => "shadowsocks",
+        }
+    }
+
+    /// Parse a protocol name from the UI; unknown names are `None`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "direct" => Some(ProxyType::Direct),
+            "http" => Some(ProxyType::Http),
+            "https" => Some(ProxyType::Https),
+            "socks4" => Some(ProxyType::Socks4),
+            "socks5" => Some(ProxyType::Socks5),
+            "ssh" => Some(ProxyType::Ssh),
+            "shadowsocks" | "ss" => Some(ProxyType::Shadowsocks),
+            _ => None,
+        }
+    }
 }
 
 
@@ -35,6 +96,9 @@ pub struct ProxySettings {
     pub password: Option<String>,
     pub dns_servers: Vec<String>,
     pub bypass_list: Vec<String>,
+    /// Key and known_hosts settings for `ProxyType::Ssh`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<SshOptions>,
 }
 
 impl Default for ProxySettings {
@@ -47,6 +111,7 @@ impl Default for ProxySettings {
             password: None,
             dns_servers: vec!["1.1.1.1".to_string(), "8.8.8.8".to_string()],
             bypass_list: vec!["localhost".to_string(), "127.0.0.1".to_string()],
+            ssh: None,
         }
     }
 }
@@ -60,27 +125,87 @@ impl ProxySettings {
         if self.proxy_type == ProxyType::Direct {
             return None;
         }
+        if self.proxy_type == ProxyType::Shadowsocks {
+            return ShadowsocksConfig::from_settings(self).ok().map(|c| c.to_uri());
+        }
 
         let scheme = match self.proxy_type {
             ProxyType::Http => "http",
             ProxyType::Https => "https",
             ProxyType::Socks4 => "socks4",
             ProxyType::Socks5 => "socks5",
+            ProxyType::Ssh => "ssh",
+            ProxyType::Shadowsocks => "ss",
             ProxyType::Direct => return None,
         };
 
         let host = self.host.as_ref()?;
         let port = self.port?;
+        let host = if host.contains(':') && !host.starts_with('[') {
+            format!("[{}]", host)
+        } else {
+            host.clone()
+        };
 
+        let encode = |value: &str| utf8_percent_encode(value, USERINFO).to_string();
         let auth = match (&self.username, &self.password) {
-            (Some(user), Some(pass)) => format!("{}:{}@", user, pass),
-            (Some(user), None) => format!("{}@", user),
+            (Some(user), Some(pass)) => format!("{}:{}@", encode(user), encode(pass)),
+            (Some(user), None) => format!("{}@", encode(user)),
             _ => String::new(),
         };
 
         Some(format!("{}://{}{}:{}", scheme, auth, host, port))
     }
 
+    /// Proxy server in Chromium's `--proxy-server` form, without credentials
+    ///
+    /// Chromium rejects credentials in the proxy URL and asks for them on an auth
+    /// challenge instead. None for direct connections and upstreams that need a
+    /// local relay.
+    pub fn to_proxy_server(&self) -> Option<String> {
+        let scheme = match self.proxy_type {
+            ProxyType::Http => "http",
+            ProxyType::Https => "https",
+            ProxyType::Socks4 => "socks4",
+            ProxyType::Socks5 => "socks5",
+            ProxyType::Ssh | ProxyType::Shadowsocks | ProxyType::Direct => return None,
+        };
+        Some(format!("{}://{}:{}", scheme, self.host.as_ref()?, self.port?))
+    }
+
+    /// Pool entry for this upstream, so failures seen elsewhere can be reported
+    /// to `ProxyQuarantineManager`. None without a host and port.
+    pub fn to_free_proxy(&self, provider: &str) -> Option<FreeProxy> {
+        Some(FreeProxy {
+            ip: self.host.clone()?,
+            port: self.port?,
+            protocol: self.proxy_type.clone(),
+            country: "Unknown".to_string(),
+            country_code: "XX".to_string(),
+            anonymity: "unknown".to_string(),
+            speed: 0,
+            uptime: 0.0,
+            last_checked: chrono::Utc::now().to_rfc3339(),
+            provider: provider.to_string(),
+            is_working: true,
+            username: self.username.clone(),
+            password: self.password.clone(),
+            ssh: self.ssh.clone(),
+        })
+    }
+
+    /// Whether the upstream speaks a protocol only `establish_proxy_tunnel` can,
+    /// so reqwest clients can't use it directly
+    pub fn is_tunnel_protocol(&self) -> bool {
+        matches!(self.proxy_type, ProxyType::Ssh | ProxyType::Shadowsocks)
+    }
+
+    /// Whether Chromium must reach the upstream through `LocalProxyServer`: tunnel
+    /// protocols, and SOCKS5 with credentials, which Chromium can't send
+    pub fn requires_local_relay(&self) -> bool {
+        self.is_tunnel_protocol() || (self.proxy_type == ProxyType::Socks5 && self.username.as_deref().is_some_and(|u| !u.is_empty()))
+    }
+
     /// Checks if configured.
     /// Check if a proxy is configured (not direct connection)
     pub fn is_configured(&self) -> bool {
@@ -102,19 +227,39 @@ pub struct FreeProxy {
     pub last_checked: String,
     pub provider: String,
     pub is_working: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// Key and known_hosts settings for `ProxyType::Ssh`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssh: Option<SshOptions>,
 }
 
 impl FreeProxy {
+    /// Copy for frontend responses, without the password or key passphrase
+    pub fn redacted(&self) -> Self {
+        Self {
+            password: None,
+            ssh: self.ssh.as_ref().map(|ssh| SshOptions {
+                private_key_passphrase: None,
+                ..ssh.clone()
+            }),
+            ..self.clone()
+        }
+    }
+
     /// Converts to proxy settings.
     pub fn to_proxy_settings(&self) -> ProxySettings {
         ProxySettings {
             proxy_type: self.protocol.clone(),
             host: Some(self.ip.clone()),
             port: Some(self.port),
-            username: None,
-            password: None,
+            username: self.username.clone(),
+            password: self.password.clone(),
             dns_servers: vec!["1.1.1.1".to_string()],
             bypass_list: vec!["localhost".to_string()],
+            ssh: self.ssh.clone(),
         }
     }
 }
@@ -167,6 +312,11 @@ impl ProxyManager {
         self.free_proxies.read().await.clone()
     }
 
+    /// Shared handle to the free proxy pool, for background tasks that update it in place
+    pub fn proxy_pool(&self) -> Arc<RwLock<Vec<FreeProxy>>> {
+        self.free_proxies.clone()
+    }
+
     /// Adds a free proxies.
     /// Add free proxies to the available pool
     ///
@@ -175,12 +325,80 @@ impl ProxyManager {
     pub async fn add_free_proxies(&self, proxies: Vec<FreeProxy>) {
         let mut list = self.free_proxies.write().await;
         for proxy in proxies {
-            if !list.iter().any(|p| p.ip == proxy.ip && p.port == proxy.port) {
+            if !list.iter().any(|p| p.ip == proxy.ip && p.port == proxy.port && p.username == proxy.username) {
                 list.push(proxy);
             }
         }
     }
 
+    /// Copy check results onto the matching proxies in the pool
+    ///
+    /// Entries the pool does not hold are ignored.
+    pub async fn record_checks(&self, checked: &[FreeProxy]) {
+        let mut list = self.free_proxies.write().await;
+        for result in checked {
+            if let Some(entry) = list
+                .iter_mut()
+                .find(|p| p.ip == result.ip && p.port == result.port && p.username == result.username)
+            {
+                entry.is_working = result.is_working;
+                entry.speed = result.speed;
+                entry.last_checked = result.last_checked.clone();
+            }
+        }
+    }
+
+    /// Import a vendor proxy list into the pool
+    ///
+    /// The format is detected from the content unless given. Entries already in
+    /// the pool are counted as duplicates and skipped.
+    pub async fn import_proxies(&self, content: &str, format: Option<ProxyListFormat>) -> Result<ImportReport> {
+        let importer = ProxyImporter::new();
+        let mut report = match format {
+            Some(format) => importer.parse_as(content, format)?,
+            None => importer.parse(content)?,
+        };
+
+        let mut list = self.free_proxies.write().await;
+        report.dedupe_against(&list);
+        list.extend(report.imported.iter().cloned());
+        Ok(report)
+    }
+
+    /// Export the pool in the given list format
+    ///
+    /// # Arguments
+    /// * `working_only` - Only include proxies that passed validation
+    pub async fn export_proxies(&self, format: ProxyListFormat, working_only: bool) -> String {
+        let list = self.free_proxies.read().await;
+        let proxies: Vec<FreeProxy> = list.iter().filter(|p| !working_only || p.is_working).cloned().collect();
+        export_proxies(&proxies, format)
+    }
+
+    /// Restore the password and SSH options of a proxy the frontend sent back
+    ///
+    /// Responses to the UI are redacted, so a proxy it picks arrives without its
+    /// password or key passphrase; the matching pool entry (same host, port and
+    /// username) supplies them.
+    pub async fn with_pool_credentials(&self, mut proxy: FreeProxy) -> FreeProxy {
+        let needs_ssh = proxy.ssh.as_ref().is_none_or(|ssh| ssh.private_key_passphrase.is_none());
+        if proxy.password.is_none() || needs_ssh {
+            let list = self.free_proxies.read().await;
+            if let Some(pooled) = list
+                .iter()
+                .find(|p| p.ip == proxy.ip && p.port == proxy.port && p.username == proxy.username)
+            {
+                if proxy.password.is_none() {
+                    proxy.password = pooled.password.clone();
+                }
+                if needs_ssh && pooled.ssh.is_some() {
+                    proxy.ssh = pooled.ssh.clone();
+                }
+            }
+        }
+        proxy
+    }
+
     /// Sets the active proxy.
     /// Set the currently active proxy
     ///
@@ -251,6 +469,9 @@ impl ProxyManager {
                         last_checked: "2024-01-01T00:00:00Z".to_string(),
                         provider: "test".to_string(),
                         is_working: true,
+                        username: None,
+                        password: None,
+                        ssh: None,
                     },
                     FreeProxy {
                         ip: "10.0.0.1".to_string(),
@@ -264,6 +485,9 @@ impl ProxyManager {
                         last_checked: "2024-01-01T00:00:00Z".to_string(),
                         provider: "test".to_string(),
                         is_working: true,
+                        username: None,
+                        password: None,
+                        ssh: None,
                     },
                 ];
                 let count = test_proxies.len();
@@ -0,0 +1,749 @@
+//! Proxy List Import/Export
+//!
+//! Bulk loading and saving of vendor proxy lists:
+//! - `host:port[:user:pass]` and `[scheme://][user:pass@]host:port` lines
+//! - CSV with or without a header row
+//! - Clash (`proxies:` YAML) and Surge (`[Proxy]` section) configs
+//! - Shadowsocks `ss://` URIs and SIP008 subscription documents
+//! - Format auto-detection, credential retention, dedupe and line-level errors
+
+use anyhow::{anyhow, Result};
+use percent_encoding::percent_decode_str;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::custom_providers::{parse_protocol, split_delimited};
+use crate::proxy::{FreeProxy, ProxyType};
+use crate::shadowsocks::{parse_cipher, ShadowsocksConfig, Sip008Document};
+
+/// Surge proxy types, used to recognise a bare list of Surge proxy lines
+const SURGE_TYPES: &[&str] = &["http", "https", "socks5", "socks5-tls", "ss", "vmess", "trojan", "snell"];
+
+/// Supported proxy list formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxyListFormat {
+    /// `host:port` or `host:port:user:pass`, one per line
+    HostPort,
+    /// `[scheme://][user:pass@]host:port`, one per line
+    Url,
+    /// Comma-separated values, optionally with a header row
+    Csv,
+    /// Clash config with a `proxies:` list
+    Clash,
+    /// Surge config with a `[Proxy]` section
+    Surge,
+    /// SIP008 Shadowsocks subscription JSON
+    Sip008,
+}
+
+impl ProxyListFormat {
+    /// Guesses the format of a proxy list from its content
+    pub fn detect(content: &str) -> Option<Self> {
+        let lines: Vec<&str> = content
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty() && !is_comment(l))
+            .collect();
+        let first = lines.first()?;
+
+        if first.starts_with('{') && content.contains("\"servers\"") {
+            return Some(Self::Sip008);
+        }
+        if lines.iter().any(|l| l.starts_with("proxies:")) {
+            return Some(Self::Clash);
+        }
+        let surge_entry = |l: &&str| {
+            !l.contains("://") && matches!(parse_surge_line(l), Some((kind, params)) if params.len() >= 2 && SURGE_TYPES.contains(&kind))
+        };
+        if lines.iter().any(|l| l.eq_ignore_ascii_case("[proxy]")) || lines.iter().any(surge_entry) {
+            return Some(Self::Surge);
+        }
+        if first.contains(',') && !first.contains("://") {
+            return Some(Self::Csv);
+        }
+        if lines.iter().any(|l| is_url_line(l)) {
+            return Some(Self::Url);
+        }
+        Some(Self::HostPort)
+    }
+}
+
+/// A line that could not be imported
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImportError {
+    /// 1-based line number in the input, or 0 when it could not be located
+    pub line: usize,
+    pub content: String,
+    pub message: String,
+}
+
+/// Outcome of a bulk import
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportReport {
+    pub format: ProxyListFormat,
+    pub imported: Vec<FreeProxy>,
+    pub duplicates: usize,
+    pub errors: Vec<ImportError>,
+}
+
+impl ImportReport {
+    /// Drops proxies that are already in `existing` or repeated within the import
+    pub fn dedupe_against(&mut self, existing: &[FreeProxy]) {
+        let mut seen: HashSet<(String, u16, Option<String>)> = existing.iter().map(dedupe_key).collect();
+        let before = self.imported.len();
+        self.imported.retain(|p| seen.insert(dedupe_key(p)));
+        self.duplicates += before - self.imported.len();
+    }
+}
+
+fn dedupe_key(proxy: &FreeProxy) -> (String, u16, Option<String>) {
+    (proxy.ip.to_ascii_lowercase(), proxy.port, proxy.username.clone())
+}
+
+/// Whether a line is `[scheme://][user:pass@]host:port` rather than
+/// `host:port[:user:pass]`, whose password may itself contain `@`
+fn is_url_line(line: &str) -> bool {
+    if line.contains("://") {
+        return true;
+    }
+    let host_port_auth = matches!(
+        split_host_fields(line),
+        Some((_, fields)) if matches!(fields.as_slice(), [port, _, _] if port.trim().parse::<u16>().is_ok())
+    );
+    line.contains('@') && !host_port_auth
+}
+
+/// Splits `host:port[:user:pass]` into the host and the fields after it; IPv6
+/// hosts are written in brackets
+fn split_host_fields(line: &str) -> Option<(&str, Vec<&str>)> {
+    let (host, rest) = match line.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, rest) = bracketed.split_once(']')?;
+            (host, rest.strip_prefix(':')?)
+        }
+        None => line.split_once(':')?,
+    };
+    Some((host, rest.splitn(3, ':').collect()))
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with('#') || line.starts_with(';') || line.starts_with("//")
+}
+
+/// Parses proxy lists into `FreeProxy` entries
+#[derive(Debug, Clone)]
+pub struct ProxyImporter {
+    default_protocol: ProxyType,
+    provider: String,
+}
+
+impl Default for ProxyImporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProxyImporter {
+    /// Creates an importer that assumes HTTP for entries without a scheme
+    pub fn new() -> Self {
+        Self {
+            default_protocol: ProxyType::Http,
+            provider: "Import".to_string(),
+        }
+    }
+
+    /// Protocol for entries that don't specify one
+    pub fn with_default_protocol(mut self, protocol: ProxyType) -> Self {
+        self.default_protocol = protocol;
+        self
+    }
+
+    /// Provider label recorded on imported proxies
+    pub fn with_provider_label(mut self, provider: impl Into<String>) -> Self {
+        self.provider = provider.into();
+        self
+    }
+
+    /// Parses `content`, detecting the format automatically
+    pub fn parse(&self, content: &str) -> Result<ImportReport> {
+        let format = ProxyListFormat::detect(content).ok_or_else(|| anyhow!("Proxy list is empty"))?;
+        self.parse_as(content, format)
+    }
+
+    /// Parses `content` in the given format
+    ///
+    /// Malformed entries are reported in `errors`; only a config that cannot be
+    /// read at all is an error.
+    pub fn parse_as(&self, content: &str, format: ProxyListFormat) -> Result<ImportReport> {
+        let mut report = ImportReport {
+            format,
+            imported: Vec::new(),
+            duplicates: 0,
+            errors: Vec::new(),
+        };
+
+        match format {
+            ProxyListFormat::HostPort | ProxyListFormat::Url => self.parse_lines(content, &mut report),
+            ProxyListFormat::Csv => self.parse_csv(content, &mut report),
+            ProxyListFormat::Clash => self.parse_clash(content, &mut report)?,
+            ProxyListFormat::Surge => self.parse_surge(content, &mut report),
+            ProxyListFormat::Sip008 => self.parse_sip008(content, &mut report)?,
+        }
+
+        report.dedupe_against(&[]);
+        Ok(report)
+    }
+
+    fn make_proxy(&self, host: &str, port: u16, protocol: ProxyType) -> FreeProxy {
+        FreeProxy {
+            ip: host.to_string(),
+            port,
+            protocol,
+            country: "Unknown".to_string(),
+            country_code: "XX".to_string(),
+            anonymity: "unknown".to_string(),
+            speed: 0,
+            uptime: 0.0,
+            last_checked: chrono::Utc::now().to_rfc3339(),
+            provider: self.provider.clone(),
+            is_working: false,
+            username: None,
+            password: None,
+            ssh: None,
+        }
+    }
+
+    fn record(&self, report: &mut ImportReport, line: usize, content: &str, result: Result<FreeProxy>) {
+        match result {
+            Ok(proxy) => report.imported.push(proxy),
+            Err(e) => report.errors.push(ImportError {
+                line,
+                content: content.to_string(),
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    fn parse_lines(&self, content: &str, report: &mut ImportReport) {
+        for (index, raw) in content.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || is_comment(line) {
+                continue;
+            }
+            let result = if is_url_line(line) {
+                self.parse_url_line(line)
+            } else {
+                self.parse_host_port_line(line)
+            };
+            self.record(report, index + 1, line, result);
+        }
+    }
+
+    /// `host:port` or `host:port:user:pass`, with IPv6 hosts in brackets
+    fn parse_host_port_line(&self, line: &str) -> Result<FreeProxy> {
+        let expected = || anyhow!("Expected host:port or host:port:user:pass");
+        let (host, fields) = split_host_fields(line).ok_or_else(expected)?;
+        let port = match fields.as_slice() {
+            [port] | [port, _, _] => parse_port(port)?,
+            _ => return Err(expected()),
+        };
+        let mut proxy = self.make_proxy(parse_host(host)?, port, self.default_protocol.clone());
+        if let [_, user, pass] = fields.as_slice() {
+            proxy.username = Some(user.to_string());
+            // `host:port:user:` carries a username without a password
+            proxy.password = (!pass.is_empty()).then(|| pass.to_string());
+        }
+        Ok(proxy)
+    }
+
+    /// `[scheme://][user[:pass]@]host:port`, or an `ss://` URI
+    fn parse_url_line(&self, line: &str) -> Result<FreeProxy> {
+        if line.get(..5).is_some_and(|s| s.eq_ignore_ascii_case("ss://")) {
+            return ShadowsocksConfig::parse_uri(line).map(|c| c.to_free_proxy(&self.provider));
+        }
+        let (protocol, rest) = match line.split_once("://") {
+            Some((scheme, rest)) => {
+                (parse_protocol(scheme).ok_or_else(|| anyhow!("Unsupported scheme '{}'", scheme))?, rest)
+            }
+            None => (self.default_protocol.clone(), line),
+        };
+        let rest = rest.trim_end_matches('/');
+        let (auth, address) = match rest.rsplit_once('@') {
+            Some((auth, address)) => (Some(auth), address),
+            None => (None, rest),
+        };
+        let (host, port) = address
+            .rsplit_once(':')
+            .ok_or_else(|| anyhow!("Missing port in '{}'", address))?;
+
+        let mut proxy = self.make_proxy(parse_host(host)?, parse_port(port)?, protocol);
+        if let Some(auth) = auth {
+            // Credentials in URLs are percent-encoded
+            let decode = |value: &str| percent_decode_str(value).decode_utf8_lossy().into_owned();
+            let (user, pass) = match auth.split_once(':') {
+                Some((user, pass)) => (user, Some(decode(pass))),
+                None => (auth, None),
+            };
+            if user.is_empty() {
+                return Err(anyhow!("Empty username"));
+            }
+            proxy.username = Some(decode(user));
+            proxy.password = pass;
+        }
+        Ok(proxy)
+    }
+
+    /// CSV with a header row naming its columns, or positional `host,port[,user,pass]`
+    fn parse_csv(&self, content: &str, report: &mut ImportReport) {
+        let mut rows = content
+            .lines()
+            .enumerate()
+            .map(|(i, l)| (i + 1, l.trim()))
+            .filter(|(_, l)| !l.is_empty() && !is_comment(l))
+            .peekable();
+
+        let header = rows.peek().and_then(|(_, first)| CsvColumns::from_header(&split_delimited(first, ',')));
+        let columns = match header {
+            Some(columns) => {
+                rows.next();
+                columns
+            }
+            None => CsvColumns::positional(),
+        };
+
+        for (line, row) in rows {
+            let cells: Vec<String> = split_delimited(row, ',').into_iter().map(|c| c.trim().to_string()).collect();
+            let result = self.parse_csv_row(&columns, &cells);
+            self.record(report, line, row, result);
+        }
+    }
+
+    fn parse_csv_row(&self, columns: &CsvColumns, cells: &[String]) -> Result<FreeProxy> {
+        let cell = |index: Option<usize>| index.and_then(|i| cells.get(i)).map(String::as_str).filter(|c| !c.is_empty());
+
+        let host = cell(Some(columns.host)).ok_or_else(|| anyhow!("Missing host"))?;
+        let port = parse_port(cell(Some(columns.port)).ok_or_else(|| anyhow!("Missing port"))?)?;
+        let protocol = match cell(columns.protocol) {
+            Some(value) => parse_protocol(value).ok_or_else(|| anyhow!("Unsupported protocol '{}'", value))?,
+            None => self.default_protocol.clone(),
+        };
+
+        let mut proxy = self.make_proxy(parse_host(host)?, port, protocol);
+        proxy.username = cell(columns.username).map(str::to_string);
+        proxy.password = cell(columns.password).map(str::to_string);
+        if let Some(code) = cell(columns.country_code) {
+            proxy.country_code = code.to_ascii_uppercase();
+        }
+        if let Some(country) = cell(columns.country) {
+            proxy.country = country.to_string();
+        }
+        Ok(proxy)
+    }
+
+    fn parse_clash(&self, content: &str, report: &mut ImportReport) -> Result<()> {
+        let docs = YamlLoader::load_from_str(content).map_err(|e| anyhow!("Invalid Clash config: {}", e))?;
+        let entries = docs
+            .first()
+            .and_then(|doc| doc["proxies"].as_vec())
+            .ok_or_else(|| anyhow!("Clash config has no 'proxies' list"))?;
+
+        for entry in entries {
+            let name = yaml_string(&entry["name"]).unwrap_or_default();
+            let line = locate_line(content, &name);
+            let result = self.parse_clash_entry(entry);
+            self.record(report, line, &name, result);
+        }
+        Ok(())
+    }
+
+    fn parse_clash_entry(&self, entry: &Yaml) -> Result<FreeProxy> {
+        let kind = yaml_string(&entry["type"]).ok_or_else(|| anyhow!("Missing type"))?;
+        if kind == "ss" {
+            return self.parse_clash_shadowsocks(entry);
+        }
+        let tls = entry["tls"].as_bool().unwrap_or(false);
+        let protocol = match kind.as_str() {
+            "http" if tls => ProxyType::Https,
+            "http" => ProxyType::Http,
+            "socks5" if tls => return Err(anyhow!("SOCKS5 over TLS is not supported")),
+            "socks5" => ProxyType::Socks5,
+            other => return Err(anyhow!("Unsupported Clash proxy type '{}'", other)),
+        };
+        let server = yaml_string(&entry["server"]).ok_or_else(|| anyhow!("Missing server"))?;
+        let port = parse_port(&yaml_string(&entry["port"]).ok_or_else(|| anyhow!("Missing port"))?)?;
+
+        let mut proxy = self.make_proxy(parse_host(&server)?, port, protocol);
+        proxy.username = yaml_string(&entry["username"]);
+        proxy.password = yaml_string(&entry["password"]);
+        Ok(proxy)
+    }
+
+    fn parse_clash_shadowsocks(&self, entry: &Yaml) -> Result<FreeProxy> {
+        if !entry["plugin"].is_badvalue() {
+            return Err(anyhow!("Shadowsocks plugins are not supported"));
+        }
+        let server = yaml_string(&entry["server"]).ok_or_else(|| anyhow!("Missing server"))?;
+        let port = parse_port(&yaml_string(&entry["port"]).ok_or_else(|| anyhow!("Missing port"))?)?;
+        let cipher = yaml_string(&entry["cipher"]).ok_or_else(|| anyhow!("Missing cipher"))?;
+        let password = yaml_string(&entry["password"]).ok_or_else(|| anyhow!("Missing password"))?;
+        let config = ShadowsocksConfig::new(parse_host(&server)?, port, parse_cipher(&cipher)?, password)?;
+        Ok(config.to_free_proxy(&self.provider))
+    }
+
+    fn parse_sip008(&self, content: &str, report: &mut ImportReport) -> Result<()> {
+        let document: Sip008Document =
+            serde_json::from_str(content).map_err(|e| anyhow!("Invalid SIP008 document: {}", e))?;
+        for server in &document.servers {
+            let line = locate_line(content, &server.server);
+            let result = server.to_config().map(|c| c.to_free_proxy(&self.provider));
+            self.record(report, line, &server.server, result);
+        }
+        Ok(())
+    }
+
+    fn parse_surge(&self, content: &str, report: &mut ImportReport) {
+        let has_sections = content.lines().any(|l| l.trim().starts_with('['));
+        let mut in_proxy_section = !has_sections;
+
+        for (index, raw) in content.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || is_comment(line) {
+                continue;
+            }
+            if line.starts_with('[') {
+                in_proxy_section = line.eq_ignore_ascii_case("[proxy]");
+                continue;
+            }
+            if !in_proxy_section {
+                continue;
+            }
+            let result = match parse_surge_line(line) {
+                // Built-in policies such as `DIRECT = direct` carry no server
+                Some(("direct" | "reject", _)) => continue,
+                Some((kind, params)) => self.parse_surge_entry(kind, &params),
+                None => Err(anyhow!("Expected 'name = type, server, port, ...'")),
+            };
+            self.record(report, index + 1, line, result);
+        }
+    }
+
+    fn parse_surge_entry(&self, kind: &str, params: &[String]) -> Result<FreeProxy> {
+        if kind == "ss" {
+            return self.parse_surge_shadowsocks(params);
+        }
+        let protocol = match kind {
+            "http" => ProxyType::Http,
+            "https" => ProxyType::Https,
+            "socks5" => ProxyType::Socks5,
+            "socks5-tls" => return Err(anyhow!("SOCKS5 over TLS is not supported")),
+            other => return Err(anyhow!("Unsupported Surge proxy type '{}'", other)),
+        };
+        let (server, port) = match params {
+            [server, port, ..] => (server.as_str(), parse_port(port)?),
+            _ => return Err(anyhow!("Missing server or port")),
+        };
+
+        let mut proxy = self.make_proxy(parse_host(server)?, port, protocol);
+        let mut positional = Vec::new();
+        for param in &params[2..] {
+            match param.split_once('=') {
+                Some((key, value)) => match key.trim() {
+                    "username" => proxy.username = Some(value.trim().to_string()),
+                    "password" => proxy.password = Some(value.trim().to_string()),
+                    _ => {}
+                },
+                None => positional.push(param),
+            }
+        }
+        // Older Surge syntax: `name = http, server, port, user, pass`
+        if let [user, pass, ..] = positional.as_slice() {
+            proxy.username.get_or_insert_with(|| user.to_string());
+            proxy.password.get_or_insert_with(|| pass.to_string());
+        }
+        Ok(proxy)
+    }
+
+    /// `name = ss, server, port, encrypt-method=..., password=...`
+    fn parse_surge_shadowsocks(&self, params: &[String]) -> Result<FreeProxy> {
+        let (server, port) = match params {
+            [server, port, ..] => (server.as_str(), parse_port(port)?),
+            _ => return Err(anyhow!("Missing server or port")),
+        };
+        let option = |name: &str| {
+            params[2..]
+                .iter()
+                .filter_map(|p| p.split_once('='))
+                .find(|(key, _)| key.trim() == name)
+                .map(|(_, value)| value.trim().trim_matches('"').to_string())
+        };
+        let method = option("encrypt-method").ok_or_else(|| anyhow!("Missing encrypt-method"))?;
+        let password = option("password").ok_or_else(|| anyhow!("Missing password"))?;
+        let config = ShadowsocksConfig::new(parse_host(server)?, port, parse_cipher(&method)?, password)?;
+        Ok(config.to_free_proxy(&self.provider))
+    }
+}
+
+/// Column positions for CSV imports
+struct CsvColumns {
+    host: usize,
+    port: usize,
+    protocol: Option<usize>,
+    username: Option<usize>,
+    password: Option<usize>,
+    country: Option<usize>,
+    country_code: Option<usize>,
+}
+
+impl CsvColumns {
+    fn positional() -> Self {
+        Self {
+            host: 0,
+            port: 1,
+            protocol: None,
+            username: Some(2),
+            password: Some(3),
+            country: None,
+            country_code: None,
+        }
+    }
+
+    /// Recognises a header row by its column names; `None` if the row is data
+    fn from_header(cells: &[String]) -> Option<Self> {
+        let find = |names: &[&str]| {
+            cells
+                .iter()
+                .position(|c| names.iter().any(|n| c.trim().eq_ignore_ascii_case(n)))
+        };
+        Some(Self {
+            host: find(&["host", "ip", "server", "address", "hostname"])?,
+            port: find(&["port"])?,
+            protocol: find(&["protocol", "type", "scheme"]),
+            username: find(&["username", "user", "login"]),
+            password: find(&["password", "pass"]),
+            country: find(&["country"]),
+            country_code: find(&["country_code", "countrycode", "cc"]),
+        })
+    }
+}
+
+/// Splits a Surge proxy line into its type and remaining parameters
+///
+/// Values may be double-quoted to carry commas, e.g. `password="a,b"`.
+fn parse_surge_line(line: &str) -> Option<(&str, Vec<String>)> {
+    let (_, value) = line.split_once('=')?;
+    let (kind, rest) = value.split_once(',').unwrap_or((value, ""));
+    let kind = kind.trim();
+    if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+    let params = if rest.trim().is_empty() {
+        Vec::new()
+    } else {
+        split_delimited(rest, ',').into_iter().map(|p| p.trim().to_string()).collect()
+    };
+    Some((kind, params))
+}
+
+fn parse_port(value: &str) -> Result<u16> {
+    match value.trim().parse::<u16>() {
+        Ok(port) if port > 0 => Ok(port),
+        _ => Err(anyhow!("Invalid port '{}'", value.trim())),
+    }
+}
+
+fn parse_host(value: &str) -> Result<&str> {
+    let host = value.trim().trim_start_matches('[').trim_end_matches(']');
+    if host.is_empty() || host.contains(char::is_whitespace) || host.contains('/') {
+        return Err(anyhow!("Invalid host '{}'", value.trim()));
+    }
+    Ok(host)
+}
+
+fn yaml_string(value: &Yaml) -> Option<String> {
+    match value {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        _ => None,
+    }
+}
+
+fn locate_line(content: &str, needle: &str) -> usize {
+    if needle.is_empty() {
+        return 0;
+    }
+    content
+        .lines()
+        .position(|l| l.contains(needle))
+        .map(|i| i + 1)
+        .unwrap_or(0)
+}
+
+// ============================================================================
+// Export
+// ============================================================================
+
+/// Writes proxies out in one of the supported list formats
+///
+/// Clash and Surge have no SOCKS4 support, so SOCKS4 proxies are left out of
+/// those formats. Shadowsocks proxies need their method, so `host:port` lists
+/// leave them out, and SIP008 holds only Shadowsocks proxies. Direct entries
+/// are never exported.
+pub fn export_proxies(proxies: &[FreeProxy], format: ProxyListFormat) -> String {
+    let proxies = proxies.iter().filter(|p| p.protocol != ProxyType::Direct);
+    let mut out = String::new();
+
+    match format {
+        ProxyListFormat::HostPort => {
+            for p in proxies.filter(|p| p.protocol != ProxyType::Shadowsocks) {
+                let host = if p.ip.contains(':') { format!("[{}]", p.ip) } else { p.ip.clone() };
+                match (&p.username, &p.password) {
+                    (Some(user), Some(pass)) => out.push_str(&format!("{}:{}:{}:{}\n", host, p.port, user, pass)),
+                    (Some(user), None) => out.push_str(&format!("{}:{}:{}:\n", host, p.port, user)),
+                    _ => out.push_str(&format!("{}:{}\n", host, p.port)),
+                }
+            }
+        }
+        ProxyListFormat::Url => {
+            for p in proxies {
+                if let Some(url) = p.to_proxy_settings().to_url() {
+                    out.push_str(&url);
+                    out.push('\n');
+                }
+            }
+        }
+        ProxyListFormat::Csv => {
+            out.push_str("protocol,host,port,username,password,country_code\n");
+            for p in proxies {
+                let row = [
+                    scheme(&p.protocol).to_string(),
+                    p.ip.clone(),
+                    p.port.to_string(),
+                    p.username.clone().unwrap_or_default(),
+                    p.password.clone().unwrap_or_default(),
+                    p.country_code.clone(),
+                ];
+                let row: Vec<String> = row.iter().map(|c| csv_escape(c)).collect();
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+        }
+        ProxyListFormat::Clash => {
+            out.push_str("proxies:\n");
+            for p in proxies {
+                let kind = match p.protocol {
+                    ProxyType::Http | ProxyType::Https => "http",
+                    ProxyType::Socks5 => "socks5",
+                    ProxyType::Shadowsocks => {
+                        if let Some(config) = shadowsocks_config(p) {
+                            out.push_str(&format!(
+                                "  - {{name: {}, type: ss, server: {}, port: {}, cipher: {}, password: {}}}\n",
+                                yaml_quote(&export_name(p)),
+                                yaml_quote(&config.server),
+                                config.port,
+                                config.cipher.name(),
+                                yaml_quote(&config.password)
+                            ));
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+                out.push_str(&format!(
+                    "  - {{name: {}, type: {}, server: {}, port: {}",
+                    yaml_quote(&export_name(p)),
+                    kind,
+                    yaml_quote(&p.ip),
+                    p.port
+                ));
+                if p.protocol == ProxyType::Https {
+                    out.push_str(", tls: true");
+                }
+                if let Some(user) = &p.username {
+                    out.push_str(&format!(", username: {}", yaml_quote(user)));
+                }
+                if let Some(pass) = &p.password {
+                    out.push_str(&format!(", password: {}", yaml_quote(pass)));
+                }
+                out.push_str("}\n");
+            }
+        }
+        ProxyListFormat::Surge => {
+            out.push_str("[Proxy]\n");
+            for p in proxies {
+                let kind = match p.protocol {
+                    ProxyType::Http => "http",
+                    ProxyType::Https => "https",
+                    ProxyType::Socks5 => "socks5",
+                    ProxyType::Shadowsocks => {
+                        if let Some(config) = shadowsocks_config(p) {
+                            out.push_str(&format!(
+                                "{} = ss, {}, {}, encrypt-method={}, password={}\n",
+                                export_name(p).replace(['=', ','], "_"),
+                                config.server,
+                                config.port,
+                                config.cipher.name(),
+                                csv_escape(&config.password)
+                            ));
+                        }
+                        continue;
+                    }
+                    _ => continue,
+                };
+                out.push_str(&format!("{} = {}, {}, {}", export_name(p).replace(['=', ','], "_"), kind, p.ip, p.port));
+                if let Some(user) = &p.username {
+                    out.push_str(&format!(", username={}", csv_escape(user)));
+                }
+                if let Some(pass) = &p.password {
+                    out.push_str(&format!(", password={}", csv_escape(pass)));
+                }
+                out.push('\n');
+            }
+        }
+        ProxyListFormat::Sip008 => {
+            let configs: Vec<ShadowsocksConfig> = proxies.filter_map(shadowsocks_config).collect();
+            let document = Sip008Document::from_configs(&configs);
+            out = serde_json::to_string_pretty(&document).unwrap_or_default();
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+/// Shadowsocks settings of a pool entry, if it is a valid Shadowsocks proxy
+fn shadowsocks_config(proxy: &FreeProxy) -> Option<ShadowsocksConfig> {
+    ShadowsocksConfig::from_settings(&proxy.to_proxy_settings()).ok()
+}
+
+fn scheme(protocol: &ProxyType) -> &'static str {
+    match protocol {
+        ProxyType::Http => "http",
+        ProxyType::Https => "https",
+        ProxyType::Socks4 => "socks4",
+        ProxyType::Socks5 => "socks5",
+        ProxyType::Ssh => "ssh",
+        ProxyType::Shadowsocks => "ss",
+        ProxyType::Direct => "direct",
+    }
+}
+
+fn export_name(proxy: &FreeProxy) -> String {
+    match &proxy.username {
+        Some(user) => format!("{}-{}:{}-{}", proxy.country_code, proxy.ip, proxy.port, user),
+        None => format!("{}-{}:{}", proxy.country_code, proxy.ip, proxy.port),
+    }
+}
+
+fn csv_escape(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn yaml_quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
@@ -10,13 +10,21 @@ use anyhow::{anyhow, Result};
 use chrono::{DateTime, Duration, Utc};
 use rand::Rng;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::{debug, info, warn};
 use serde::{Deserialize, Serialize};
 
+use crate::backconnect::{BackconnectProvider, GatewayTarget};
+use crate::config_manager::ProxyConfig;
 use crate::proxy::FreeProxy;
 use crate::free_ip_providers::FreeIpProviderManager;
+use crate::proxy_validator::ThroughputResult;
+use crate::tor::TorProvider;
+
+/// Gateway sessions tried per rotation before giving up on an exit in the tab's country
+const EXIT_COUNTRY_ATTEMPTS: usize = 3;
 
 /// Manages proxy rotation strategies for browser tabs.
 pub struct ProxyRotationManager {
@@ -24,6 +32,10 @@ pub struct ProxyRotationManager {
     active_proxies: Arc<RwLock<HashMap<String, ProxySession>>>,
     strategy: ProxyRotationStrategy,
     performance_metrics: Arc<RwLock<HashMap<String, ProxyMetrics>>>,
+    gateways: Vec<Arc<BackconnectProvider>>,
+    gateway_targets: Arc<RwLock<HashMap<String, GatewayTarget>>>,
+    exit_ips: Arc<RwLock<HashMap<String, String>>>,
+    tor: Option<Arc<TorProvider>>,
 }
 
 #[derive(Clone)]
@@ -62,7 +74,7 @@ pub enum ProxyRotationStrategy {
     Manual,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 /// Represents a ProxyMetrics.
 pub struct ProxyMetrics {
     pub response_time_ms: f64,
@@ -71,6 +83,31 @@ pub struct ProxyMetrics {
     pub consecutive_failures: u32,
     pub total_requests: u32,
     pub failed_requests: u32,
+    /// Sustained download throughput from the last benchmark (Mbit/s)
+    #[serde(default)]
+    pub download_mbps: Option<f64>,
+    /// Upload throughput from the last benchmark (Mbit/s)
+    #[serde(default)]
+    pub upload_mbps: Option<f64>,
+    /// Time-to-first-byte from the last benchmark
+    #[serde(default)]
+    pub ttfb_ms: Option<f64>,
+    /// TTFB jitter across benchmark samples
+    #[serde(default)]
+    pub jitter_ms: Option<f64>,
+    #[serde(default)]
+    pub last_benchmark: Option<DateTime<Utc>>,
+}
+
+impl ProxyMetrics {
+    /// Store the results of a throughput benchmark
+    pub fn apply_throughput(&mut self, result: &ThroughputResult) {
+        self.download_mbps = Some(result.download_mbps);
+        self.upload_mbps = result.upload_mbps;
+        self.ttfb_ms = Some(result.ttfb_ms);
+        self.jitter_ms = Some(result.jitter_ms);
+        self.last_benchmark = Some(result.measured_at);
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -95,99 +132,152 @@ impl ProxyRotationManager {
             active_proxies: Arc::new(RwLock::new(HashMap::new())),
             strategy,
             performance_metrics: Arc::new(RwLock::new(HashMap::new())),
+            gateways: Vec::new(),
+            gateway_targets: Arc::new(RwLock::new(HashMap::new())),
+            exit_ips: Arc::new(RwLock::new(HashMap::new())),
+            tor: None,
+        }
+    }
+
+    /// Creates a manager with the backconnect gateways and Tor daemon from the app config
+    ///
+    /// Gateways take precedence over Tor. Invalid entries are skipped with a
+    /// warning, as the config validator reports them.
+    pub fn from_config(
+        provider_manager: Arc<RwLock<FreeIpProviderManager>>,
+        strategy: ProxyRotationStrategy,
+        config: &ProxyConfig,
+    ) -> Self {
+        let mut manager = Self::new(provider_manager, strategy);
+        for gateway in &config.backconnect_gateways {
+            match BackconnectProvider::new(gateway.clone()) {
+                Ok(provider) => manager = manager.with_gateway(Arc::new(provider)),
+                Err(e) => warn!("Skipping backconnect gateway '{}': {}", gateway.name, e),
+            }
+        }
+        if let Some(tor) = &config.tor {
+            match TorProvider::new(tor.clone()) {
+                Ok(provider) => manager = manager.with_tor(Arc::new(provider)),
+                Err(e) => warn!("Skipping Tor provider '{}': {}", tor.name, e),
+            }
         }
+        manager
+    }
+
+    /// Rotate through a backconnect gateway instead of the provider pool
+    ///
+    /// Each rotation starts a new gateway session and checks its exit IP.
+    /// With several gateways, each tab sticks to one of them.
+    pub fn with_gateway(mut self, gateway: Arc<BackconnectProvider>) -> Self {
+        self.gateways.push(gateway);
+        self
+    }
+
+    /// Route tabs through Tor instead of the provider pool
+    ///
+    /// Each tab gets its own circuit; rotation sends `NEWNYM` and re-isolates the tab.
+    pub fn with_tor(mut self, tor: Arc<TorProvider>) -> Self {
+        self.tor = Some(tor);
+        self
+    }
+
+    /// Set the country/city a tab's gateway sessions should target
+    ///
+    /// Takes effect on the tab's next rotation.
+    pub async fn set_gateway_target(&self, tab_id: &str, target: GatewayTarget) {
+        self.gateway_targets.write().await.insert(tab_id.to_string(), target);
+    }
+
+    /// Exit IP observed after the tab's last gateway rotation
+    pub async fn get_exit_ip(&self, tab_id: &str) -> Option<String> {
+        self.exit_ips.read().await.get(tab_id).cloned()
     }
 
     /// Get or rotate proxy for tab
     pub async fn get_proxy_for_tab(&self, tab_id: &str, domain: Option<&str>) -> Result<FreeProxy> {
-        let mut sessions = self.active_proxies.write().await;
+        let domain = domain.filter(|_| matches!(self.strategy, ProxyRotationStrategy::DomainBased));
 
-        if let Some(session) = sessions.get_mut(tab_id) {
-            // Check if we need domain-based proxy
-            if let (Some(domain), ProxyRotationStrategy::DomainBased) = (domain, &self.strategy) {
-                let _proxy_key = format!("{}:{}", tab_id, domain);
-                if let Some(proxy_id) = session.domain_proxy_map.get(domain) {
-                    // Use the same proxy for this domain
-                    if let Some(proxy) = self.get_proxy_by_id(proxy_id).await? {
-                        session.last_used = Utc::now();
-                        session.request_count += 1;
-                        return Ok(proxy);
-                    }
-                }
-                // Get a new proxy for this domain
-                let new_proxy = self.get_next_proxy(session).await?;
-                session.domain_proxy_map.insert(domain.to_string(), new_proxy.ip.clone());
-                session.last_used = Utc::now();
-                session.request_count += 1;
-                return Ok(new_proxy);
-            }
+        // Decide from a snapshot; lookups and new proxies (exit checks, Tor) are awaited without the lock
+        let session = self.active_proxies.read().await.get(tab_id).cloned();
+        let Some(session) = session else {
+            // Create new session with new proxy
+            let proxy = self.select_proxy(tab_id, None).await?;
+            let mut sessions = self.active_proxies.write().await;
+            // A concurrent call may have assigned one meanwhile
+            let session = sessions
+                .entry(tab_id.to_string())
+                .or_insert_with(|| Self::new_session(tab_id, proxy));
+            info!("Assigned initial proxy {} to tab {}", session.proxy.ip, tab_id);
+            return Ok(session.proxy.clone());
+        };
 
-            if self.should_rotate(session).await {
-                let new_proxy = self.get_next_proxy(session).await?;
-                session.proxy = new_proxy.clone();
-                session.assigned_at = Utc::now();
-                session.last_used = Utc::now();
-                session.request_count = 1;
-                
-                // Clear domain map when rotating
-                if matches!(self.strategy, ProxyRotationStrategy::DomainBased) {
-                    session.domain_proxy_map.clear();
-                }
-                
-                debug!("Rotated proxy for tab {}: {} -> {}", tab_id, session.proxy.ip, new_proxy.ip);
-                return Ok(new_proxy);
-            } else {
+        let reuse = match domain {
+            // Use the same proxy for this domain
+            Some(domain) => match session.domain_proxy_map.get(domain) {
+                Some(proxy_id) => self.get_proxy_by_id(proxy_id).await?,
+                None => None,
+            },
+            None if self.should_rotate(&session).await => None,
+            None => Some(session.proxy.clone()),
+        };
+        if let Some(proxy) = reuse {
+            if let Some(session) = self.active_proxies.write().await.get_mut(tab_id) {
                 session.last_used = Utc::now();
                 session.request_count += 1;
-                return Ok(session.proxy.clone());
             }
+            return Ok(proxy);
         }
 
-        // Create new session with new proxy
-        let proxy = self.get_initial_proxy().await?;
-        let session = ProxySession {
-            proxy: proxy.clone(),
-            assigned_at: Utc::now(),
-            last_used: Utc::now(),
-            request_count: 1,
-            tab_id: tab_id.to_string(),
-            domain_proxy_map: HashMap::new(),
-        };
+        let new_proxy = self.select_proxy(tab_id, Some(&session.proxy)).await?;
+        let mut sessions = self.active_proxies.write().await;
+        let session = sessions
+            .entry(tab_id.to_string())
+            .or_insert_with(|| Self::new_session(tab_id, new_proxy.clone()));
+        session.last_used = Utc::now();
+
+        if let Some(domain) = domain {
+            // Get a new proxy for this domain
+            session.domain_proxy_map.insert(domain.to_string(), new_proxy.ip.clone());
+            session.request_count += 1;
+            return Ok(new_proxy);
+        }
+
+        debug!("Rotated proxy for tab {}: {} -> {}", tab_id, session.proxy.ip, new_proxy.ip);
+        session.proxy = new_proxy.clone();
+        session.assigned_at = Utc::now();
+        session.request_count = 1;
 
-        sessions.insert(tab_id.to_string(), session);
-        info!("Assigned initial proxy {} to tab {}", proxy.ip, tab_id);
-        Ok(proxy)
+        // Clear domain map when rotating
+        if matches!(self.strategy, ProxyRotationStrategy::DomainBased) {
+            session.domain_proxy_map.clear();
+        }
+        Ok(new_proxy)
     }
 
     /// Manually rotate proxy for tab
     pub async fn force_rotate(&self, tab_id: &str) -> Result<FreeProxy> {
+        let current = self.get_current_proxy(tab_id)
+            .await
+            .ok_or_else(|| anyhow!("Tab session not found"))?;
+
+        let new_proxy = self.select_proxy(tab_id, Some(&current)).await?;
         let mut sessions = self.active_proxies.write().await;
-        if let Some(session) = sessions.get_mut(tab_id) {
-            let new_proxy = self.get_next_proxy(session).await?;
-            session.proxy = new_proxy.clone();
-            session.assigned_at = Utc::now();
-            session.last_used = Utc::now();
-            session.request_count = 0;
-            session.domain_proxy_map.clear();
-            
-            info!("Force rotated proxy for tab {}: {} -> {}", tab_id, session.proxy.ip, new_proxy.ip);
-            Ok(new_proxy)
-        } else {
-            Err(anyhow!("Tab session not found"))
-        }
+        let session = sessions
+            .get_mut(tab_id)
+            .ok_or_else(|| anyhow!("Tab session not found"))?;
+        info!("Force rotated proxy for tab {}: {} -> {}", tab_id, session.proxy.ip, new_proxy.ip);
+        session.proxy = new_proxy.clone();
+        session.assigned_at = Utc::now();
+        session.last_used = Utc::now();
+        session.request_count = 0;
+        session.domain_proxy_map.clear();
+        Ok(new_proxy)
     }
 
     /// Record proxy performance metrics
     pub async fn record_performance(&self, proxy_id: &str, success: bool, response_time_ms: Option<f64>) {
         let mut metrics = self.performance_metrics.write().await;
-        let metric = metrics.entry(proxy_id.to_string()).or_insert(ProxyMetrics {
-            response_time_ms: 0.0,
-            success_rate: 0.0,
-            last_success: None,
-            consecutive_failures: 0,
-            total_requests: 0,
-            failed_requests: 0,
-        });
+        let metric = metrics.entry(proxy_id.to_string()).or_default();
 
         metric.total_requests += 1;
         if success {
@@ -205,6 +295,17 @@ impl ProxyRotationManager {
         metric.success_rate = (metric.total_requests - metric.failed_requests) as f64 / metric.total_requests as f64;
     }
 
+    /// Record the results of a throughput benchmark
+    pub async fn record_throughput(&self, proxy_id: &str, result: &ThroughputResult) {
+        let mut metrics = self.performance_metrics.write().await;
+        metrics.entry(proxy_id.to_string()).or_default().apply_throughput(result);
+    }
+
+    /// Get performance metrics for a proxy
+    pub async fn get_metrics(&self, proxy_id: &str) -> Option<ProxyMetrics> {
+        self.performance_metrics.read().await.get(proxy_id).cloned()
+    }
+
     /// Get current proxy for tab
     pub async fn get_current_proxy(&self, tab_id: &str) -> Option<FreeProxy> {
         let sessions = self.active_proxies.read().await;
@@ -256,7 +357,7 @@ impl ProxyRotationManager {
                 let elapsed = Utc::now() - session.last_used;
                 elapsed > *duration
             }
-            ProxyRotationStrategy::Geographic { .. } => false, // Handled in get_next_proxy
+            ProxyRotationStrategy::Geographic { .. } => false, // Handled in select_proxy
             ProxyRotationStrategy::PerformanceBased => {
                 // Check if current proxy is underperforming
                 if let Some(metric) = self.performance_metrics.read().await.get(&session.proxy.ip) {
@@ -331,8 +432,93 @@ impl ProxyRotationManager {
         }
     }
 
-    async fn get_next_proxy(&self, _current_session: &ProxySession) -> Result<FreeProxy> {
-        self.get_initial_proxy().await
+    /// Pick a new proxy for a tab; `current` is its proxy when rotating
+    ///
+    /// A gateway session whose exit is located outside the tab's country is
+    /// replaced with a fresh one, up to `EXIT_COUNTRY_ATTEMPTS` times. Its exit IP
+    /// is only recorded for the tab once it passes.
+    async fn select_proxy(&self, tab_id: &str, current: Option<&FreeProxy>) -> Result<FreeProxy> {
+        let gateway = match (self.gateway_for(tab_id), &self.tor) {
+            (Some(gateway), _) => gateway,
+            (None, Some(tor)) if current.is_some() => return tor.rotate(tab_id).await,
+            (None, Some(tor)) => return tor.assign(tab_id).await,
+            (None, None) => return self.get_initial_proxy().await,
+        };
+
+        let target = self.gateway_target(tab_id, current).await;
+        let mut previous = self.exit_ips.read().await.get(tab_id).cloned();
+        let mut misplaced = None;
+        for _ in 0..EXIT_COUNTRY_ATTEMPTS {
+            let (proxy, exit_ip) = gateway.rotate(&target, previous.as_deref()).await?;
+            match self.misplaced_exit(&proxy, &exit_ip).await {
+                None => {
+                    debug!("Gateway session for tab {} exits via {}", tab_id, exit_ip);
+                    self.exit_ips.write().await.insert(tab_id.to_string(), exit_ip);
+                    return Ok(proxy);
+                }
+                Some(reason) => {
+                    warn!("Discarding gateway session for tab {}: {}", tab_id, reason);
+                    misplaced = Some(reason);
+                    previous = Some(exit_ip);
+                }
+            }
+        }
+        Err(anyhow!(misplaced.unwrap_or_else(|| "No gateway session in the tab's country".to_string())))
+    }
+
+    /// Describe a gateway exit located outside the proxy's targeted country
+    ///
+    /// Untargeted sessions and exits the range index cannot locate pass.
+    async fn misplaced_exit(&self, proxy: &FreeProxy, exit_ip: &str) -> Option<String> {
+        if proxy.country_code == "XX" {
+            return None;
+        }
+        let index = self.provider_manager.read().await.range_index()?;
+        let info = index.lookup_info(exit_ip.parse().ok()?)?;
+        if info.country_code.eq_ignore_ascii_case(&proxy.country_code) {
+            return None;
+        }
+        Some(format!("Exit {} is in {}, not {}", exit_ip, info.country_code, proxy.country_code))
+    }
+
+    fn new_session(tab_id: &str, proxy: FreeProxy) -> ProxySession {
+        ProxySession {
+            proxy,
+            assigned_at: Utc::now(),
+            last_used: Utc::now(),
+            request_count: 1,
+            tab_id: tab_id.to_string(),
+            domain_proxy_map: HashMap::new(),
+        }
+    }
+
+    /// Gateway serving a tab, stable for the tab's lifetime
+    fn gateway_for(&self, tab_id: &str) -> Option<&BackconnectProvider> {
+        if self.gateways.is_empty() {
+            return None;
+        }
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        tab_id.hash(&mut hasher);
+        let index = (hasher.finish() % self.gateways.len() as u64) as usize;
+        Some(self.gateways[index].as_ref())
+    }
+
+    /// Targeting for a tab: its explicit target, else the country of its current
+    /// session, else a country from the Geographic strategy
+    async fn gateway_target(&self, tab_id: &str, current: Option<&FreeProxy>) -> GatewayTarget {
+        if let Some(target) = self.gateway_targets.read().await.get(tab_id) {
+            return target.clone();
+        }
+        if let Some(current) = current.filter(|p| p.country_code != "XX") {
+            return GatewayTarget::country(current.country_code.clone());
+        }
+        match &self.strategy {
+            ProxyRotationStrategy::Geographic { country_codes } if !country_codes.is_empty() => {
+                let index = rand::thread_rng().gen_range(0..country_codes.len());
+                GatewayTarget::country(country_codes[index].clone())
+            }
+            _ => GatewayTarget::default(),
+        }
     }
 
     async fn get_proxy_by_id(&self, proxy_id: &str) -> Result<Option<FreeProxy>> {
@@ -368,6 +554,12 @@ pub struct SmartProxySelector {
     pub max_response_time_ms: f64,
     /// Minimum acceptable success rate
     pub min_success_rate: f64,
+    /// Weight for benchmarked download bandwidth (0.0 - 1.0)
+    pub bandwidth_weight: f64,
+    /// Download bandwidth that earns a full bandwidth score, in Mbit/s
+    pub target_download_mbps: f64,
+    /// Reject proxies without a benchmark at or above this bandwidth, in Mbit/s
+    pub min_download_mbps: Option<f64>,
 }
 
 impl Default for SmartProxySelector {
@@ -381,6 +573,9 @@ impl Default for SmartProxySelector {
             preferred_countries: vec![],
             max_response_time_ms: 5000.0,
             min_success_rate: 0.7,
+            bandwidth_weight: 0.0,
+            target_download_mbps: 10.0,
+            min_download_mbps: None,
         }
     }
 }
@@ -404,6 +599,23 @@ impl SmartProxySelector {
         }
     }
 
+    /// Require a minimum benchmarked download bandwidth and score on it
+    pub fn with_bandwidth_requirement(mut self, min_download_mbps: f64, bandwidth_weight: f64) -> Self {
+        self.min_download_mbps = Some(min_download_mbps);
+        self.bandwidth_weight = bandwidth_weight;
+        self
+    }
+
+    /// Check a proxy against the minimum bandwidth requirement
+    pub fn meets_bandwidth(&self, metrics: Option<&ProxyMetrics>) -> bool {
+        match self.min_download_mbps {
+            None => true,
+            Some(min) => metrics
+                .and_then(|m| m.download_mbps)
+                .is_some_and(|mbps| mbps >= min),
+        }
+    }
+
     /// Calculate composite score for a proxy
     pub fn calculate_score(&self, proxy: &FreeProxy, metrics: Option<&ProxyMetrics>) -> f64 {
         let mut score = 0.0;
@@ -449,6 +661,15 @@ impl SmartProxySelector {
         };
         score += anonymity_score * self.anonymity_weight;
         
+        // Bandwidth score (normalized against the target)
+        if self.bandwidth_weight > 0.0 {
+            let bandwidth_score = metrics
+                .and_then(|m| m.download_mbps)
+                .map(|mbps| (mbps / self.target_download_mbps.max(f64::EPSILON)).min(1.0))
+                .unwrap_or(0.0);
+            score += bandwidth_score * self.bandwidth_weight;
+        }
+        
         score
     }
 
@@ -473,6 +694,10 @@ impl SmartProxySelector {
                 }
             }
             
+            if !self.meets_bandwidth(metrics.get(&proxy.ip)) {
+                continue;
+            }
+            
             let score = self.calculate_score(proxy, metrics.get(&proxy.ip));
             if score > best_score {
                 best_score = score;
@@ -487,7 +712,7 @@ impl SmartProxySelector {
     pub fn select_top_n(&self, proxies: &[FreeProxy], metrics: &HashMap<String, ProxyMetrics>, n: usize) -> Vec<FreeProxy> {
         let mut scored_proxies: Vec<(f64, &FreeProxy)> = proxies
             .iter()
-            .filter(|p| p.is_working)
+            .filter(|p| p.is_working && self.meets_bandwidth(metrics.get(&p.ip)))
             .map(|p| (self.calculate_score(p, metrics.get(&p.ip)), p))
             .collect();
         
@@ -538,6 +763,16 @@ pub struct BandwidthStats {
     pub requests_count: u64,
     pub start_time: Option<DateTime<Utc>>,
     pub last_updated: Option<DateTime<Utc>>,
+    /// Sustained download rate from the last benchmark, in bytes per second
+    #[serde(default)]
+    pub sustained_download_bps: Option<f64>,
+    /// Upload rate from the last benchmark, in bytes per second
+    #[serde(default)]
+    pub sustained_upload_bps: Option<f64>,
+    #[serde(default)]
+    pub ttfb_ms: Option<f64>,
+    #[serde(default)]
+    pub jitter_ms: Option<f64>,
 }
 
 impl BandwidthStats {
@@ -594,14 +829,7 @@ impl ProxyHealthMonitor {
         
         // Update bandwidth stats
         let mut bandwidth = self.bandwidth_tracker.write().await;
-        let stats = bandwidth.entry(proxy_id.to_string()).or_insert(BandwidthStats {
-            proxy_id: proxy_id.to_string(),
-            bytes_sent: 0,
-            bytes_received: 0,
-            requests_count: 0,
-            start_time: Some(Utc::now()),
-            last_updated: None,
-        });
+        let stats = bandwidth.entry(proxy_id.to_string()).or_insert_with(|| Self::new_bandwidth_stats(proxy_id));
         
         stats.bytes_sent += bytes_sent;
         stats.bytes_received += bytes_received;
@@ -609,6 +837,29 @@ impl ProxyHealthMonitor {
         stats.last_updated = Some(Utc::now());
     }
 
+    /// Record the results of a throughput benchmark
+    pub async fn record_throughput(&self, proxy_id: &str, result: &ThroughputResult) {
+        let mut bandwidth = self.bandwidth_tracker.write().await;
+        let stats = bandwidth.entry(proxy_id.to_string()).or_insert_with(|| Self::new_bandwidth_stats(proxy_id));
+        
+        stats.bytes_received += result.download_samples.iter().map(|s| s.bytes).sum::<u64>();
+        stats.bytes_sent += result.upload_samples.iter().map(|s| s.bytes).sum::<u64>();
+        stats.requests_count += (result.download_samples.len() + result.upload_samples.len()) as u64;
+        stats.sustained_download_bps = Some(result.download_bytes_per_sec());
+        stats.sustained_upload_bps = result.upload_mbps.map(|mbps| mbps * 1_000_000.0 / 8.0);
+        stats.ttfb_ms = Some(result.ttfb_ms);
+        stats.jitter_ms = Some(result.jitter_ms);
+        stats.last_updated = Some(result.measured_at);
+    }
+
+    fn new_bandwidth_stats(proxy_id: &str) -> BandwidthStats {
+        BandwidthStats {
+            proxy_id: proxy_id.to_string(),
+            start_time: Some(Utc::now()),
+            ..Default::default()
+        }
+    }
+
     /// Record a failed request
     pub async fn record_failure(&self, proxy_id: &str, error: &str) {
         let mut status = self.health_status.write().await;
@@ -808,6 +1059,9 @@ mod enhanced_tests {
             last_checked: "2024-01-01".to_string(),
             provider: "test".to_string(),
             is_working: true,
+            username: None,
+            password: None,
+            ssh: None,
         };
         
         let metrics = ProxyMetrics {
@@ -817,6 +1071,7 @@ mod enhanced_tests {
             consecutive_failures: 0,
             total_requests: 100,
             failed_requests: 5,
+            ..Default::default()
         };
         
         let score = selector.calculate_score(&proxy, Some(&metrics));
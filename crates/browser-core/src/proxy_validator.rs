@@ -7,17 +7,22 @@
 //! - IP leak detection
 //! - Health monitoring with automatic quarantine
 //! - Batch validation with concurrency control
+//! - Throughput benchmarking (sustained bandwidth, jitter, time-to-first-byte)
+//! - Tamper detection (content injection, forged TLS certificates)
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
 use tokio::sync::Semaphore;
 use tracing::{debug, info, warn, error};
 use std::sync::Arc;
 
-use crate::proxy::{FreeProxy, ProxySettings};
+use crate::proxy::{FreeProxy, ProxySettings, ProxyType};
 use crate::http_client::HttpClient;
+use crate::local_proxy::LocalProxyServer;
+use virtual_ip::{GeoIpDatabase, GeoIpRecord, IpRangeIndex};
 
 // Internal struct for test results
 #[derive(Debug, Clone)]
@@ -41,6 +46,16 @@ pub struct ValidationResult {
     pub has_ip_leak: bool,
     pub error: Option<String>,
     pub validated_at: DateTime<Utc>,
+    /// Set when the proxy altered a known payload or certificate
+    #[serde(default)]
+    pub tampering: Option<TamperDetection>,
+}
+
+impl ValidationResult {
+    /// Returns true if the proxy was caught tampering with traffic
+    pub fn is_tampering(&self) -> bool {
+        self.tampering.is_some()
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -68,9 +83,12 @@ impl Default for ProxyValidatorConfig {
 }
 
 /// Represents a ProxyValidator.
+#[derive(Clone)]
 pub struct ProxyValidator {
     config: ProxyValidatorConfig,
     semaphore: Arc<Semaphore>,
+    tamper_checks: Option<TamperCheckConfig>,
+    quarantine_manager: Option<Arc<ProxyQuarantineManager>>,
 }
 
 impl ProxyValidator {
@@ -79,9 +97,23 @@ impl ProxyValidator {
         Self {
             semaphore: Arc::new(Semaphore::new(config.concurrent_checks)),
             config,
+            tamper_checks: None,
+            quarantine_manager: None,
         }
     }
 
+    /// Run tamper checks on every proxy that passes connectivity validation
+    pub fn with_tamper_checks(mut self, tamper_checks: TamperCheckConfig) -> Self {
+        self.tamper_checks = Some(tamper_checks);
+        self
+    }
+
+    /// Quarantine proxies caught tampering as soon as they are detected
+    pub fn with_quarantine_manager(mut self, quarantine_manager: Arc<ProxyQuarantineManager>) -> Self {
+        self.quarantine_manager = Some(quarantine_manager);
+        self
+    }
+
     /// Validates the proxy.
     /// Validate a single proxy
     ///
@@ -94,7 +126,22 @@ impl ProxyValidator {
         let _permit = self.semaphore.acquire().await
             .map_err(|e| anyhow!("Failed to acquire semaphore: {}", e))?;
 
-        let settings = proxy.to_proxy_settings();
+        let upstream = proxy.to_proxy_settings();
+        // reqwest can't speak SSH or Shadowsocks, so those are checked through a local relay
+        let relay = if upstream.is_tunnel_protocol() {
+            Some(LocalProxyServer::start_relay(upstream.clone()).await?)
+        } else {
+            None
+        };
+        let settings = match &relay {
+            Some((_, addr)) => ProxySettings {
+                proxy_type: ProxyType::Http,
+                host: Some(addr.ip().to_string()),
+                port: Some(addr.port()),
+                ..Default::default()
+            },
+            None => upstream,
+        };
         
         info!("Validating proxy {}:{}", proxy.ip, proxy.port);
         
@@ -126,7 +173,7 @@ impl ProxyValidator {
             }
         }
         
-        let result = best_result.unwrap_or_else(|| ValidationResult {
+        let mut result = best_result.unwrap_or_else(|| ValidationResult {
             is_working: false,
             response_time_ms: u64::MAX,
             detected_country: None,
@@ -135,8 +182,27 @@ impl ProxyValidator {
             has_ip_leak: false,
             error: last_error,
             validated_at: Utc::now(),
+            tampering: None,
         });
         
+        if result.is_working {
+            if let Some(tamper_checks) = &self.tamper_checks {
+                if let Some(detection) = Self::check_tampering(&settings, tamper_checks).await {
+                    error!("Proxy {}:{} is tampering with traffic: {}", proxy.ip, proxy.port, detection);
+                    if let Some(quarantine_manager) = &self.quarantine_manager {
+                        quarantine_manager.quarantine_for_tampering(proxy, &detection).await;
+                    }
+                    result.is_working = false;
+                    result.error = Some(detection.to_string());
+                    result.tampering = Some(detection);
+                }
+            }
+        }
+        
+        if let Some((relay, _)) = relay {
+            relay.stop().await?;
+        }
+        
         if result.is_working {
             info!("Proxy {}:{} is working ({}ms)", proxy.ip, proxy.port, result.response_time_ms);
         } else {
@@ -166,6 +232,7 @@ impl ProxyValidator {
             has_ip_leak: test_result.has_ip_leak,
             error: test_result.error,
             validated_at: Utc::now(),
+            tampering: None,
         })
     }
 
@@ -249,22 +316,22 @@ impl ProxyValidator {
         let mut results = Vec::new();
         
         // Process proxies in parallel with semaphore limiting
-        let mut tasks = Vec::new();
+        let mut tasks = BatchTasks(Vec::new());
         
         for proxy in proxies {
             let proxy = proxy.clone();
-            let validator = ProxyValidator::new(self.config.clone());
+            let validator = self.clone();
             
             let task = tokio::spawn(async move {
                 let result = validator.validate_proxy(&proxy).await;
                 (proxy, result)
             });
             
-            tasks.push(task);
+            tasks.0.push(task);
         }
         
         // Wait for all tasks to complete
-        for task in tasks {
+        for task in tasks.0.iter_mut() {
             match task.await {
                 Ok((proxy, Ok(result))) => {
                     results.push((proxy, result));
@@ -280,6 +347,7 @@ impl ProxyValidator {
                         has_ip_leak: false,
                         error: Some(e.to_string()),
                         validated_at: Utc::now(),
+                        tampering: None,
                     }));
                 }
                 Err(e) => {
@@ -292,6 +360,399 @@ impl ProxyValidator {
     }
 }
 
+/// Validation tasks of a batch, aborted when the batch is dropped unfinished
+/// (e.g. a stopped scheduler) so no checks outlive it
+struct BatchTasks<T>(Vec<tokio::task::JoinHandle<T>>);
+
+impl<T> Drop for BatchTasks<T> {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+// ============================================================================
+// Tamper Detection
+// ============================================================================
+
+/// Known-good payloads used to detect content-injecting or TLS-intercepting proxies
+#[derive(Debug, Clone)]
+pub struct TamperCheckConfig {
+    /// Plain-HTTP URL serving a fixed payload
+    pub http_url: Option<String>,
+    /// Expected SHA-256 of the HTTP payload (hex)
+    pub http_sha256: String,
+    /// HTTPS URL whose leaf certificate is pinned
+    pub https_url: Option<String>,
+    /// Accepted SHA-256 fingerprints of the leaf certificate (hex, colons optional)
+    pub https_cert_sha256: Vec<String>,
+    /// Timeout for each check
+    pub timeout: Duration,
+}
+
+impl Default for TamperCheckConfig {
+    fn default() -> Self {
+        Self {
+            // Firefox captive-portal probe: always "success\n"
+            http_url: Some("http://detectportal.firefox.com/success.txt".to_string()),
+            http_sha256: "81b2bd4ea98c8db66554fbc8d7637a1a69a130f331feb732b75caab4c4868fd5".to_string(),
+            // Certificates rotate, so HTTPS pinning is opt-in
+            https_url: None,
+            https_cert_sha256: vec![],
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+/// Evidence that a proxy modified traffic passing through it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TamperDetection {
+    /// The HTTP payload did not match its known hash
+    ContentModified {
+        url: String,
+        expected_sha256: String,
+        actual_sha256: String,
+    },
+    /// The HTTPS leaf certificate did not match any pinned fingerprint
+    CertificateMismatch {
+        url: String,
+        actual_sha256: Option<String>,
+    },
+}
+
+impl std::fmt::Display for TamperDetection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TamperDetection::ContentModified { url, actual_sha256, .. } => {
+                write!(f, "Tampering: content of {} modified (sha256 {})", url, actual_sha256)
+            }
+            TamperDetection::CertificateMismatch { url, actual_sha256 } => write!(
+                f,
+                "Tampering: certificate for {} does not match pin (sha256 {})",
+                url,
+                actual_sha256.as_deref().unwrap_or("none")
+            ),
+        }
+    }
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+fn normalize_fingerprint(fingerprint: &str) -> String {
+    fingerprint.replace(':', "").to_ascii_lowercase()
+}
+
+impl ProxyValidator {
+    /// Check a proxy for content injection and TLS interception
+    ///
+    /// Checks that cannot complete (e.g. the proxy refuses plain HTTP or
+    /// answers with an error status) are inconclusive and do not count as
+    /// tampering.
+    ///
+    /// # Returns
+    /// The first detected tampering, or None if the proxy looks clean
+    pub async fn check_tampering(settings: &ProxySettings, config: &TamperCheckConfig) -> Option<TamperDetection> {
+        if let Some(url) = &config.http_url {
+            match Self::check_http_payload(settings, url, &config.http_sha256, config.timeout).await {
+                Ok(Some(detection)) => return Some(detection),
+                Ok(None) => {}
+                Err(e) => debug!("HTTP tamper check inconclusive: {}", e),
+            }
+        }
+
+        if let Some(url) = &config.https_url {
+            if !config.https_cert_sha256.is_empty() {
+                match Self::check_certificate_pin(settings, url, &config.https_cert_sha256, config.timeout).await {
+                    Ok(Some(detection)) => return Some(detection),
+                    Ok(None) => {}
+                    Err(e) => debug!("HTTPS tamper check inconclusive: {}", e),
+                }
+            }
+        }
+
+        None
+    }
+
+    fn tamper_check_client(settings: &ProxySettings, timeout: Duration) -> Result<reqwest::Client> {
+        // A redirect is the proxy's answer, so it is hashed rather than followed
+        let mut builder = reqwest::Client::builder()
+            .timeout(timeout)
+            .redirect(reqwest::redirect::Policy::none());
+        if let Some(proxy_url) = settings.to_url() {
+            builder = builder.proxy(reqwest::Proxy::all(&proxy_url)?);
+        }
+        Ok(builder.build()?)
+    }
+
+    async fn check_http_payload(
+        settings: &ProxySettings,
+        url: &str,
+        expected_sha256: &str,
+        timeout: Duration,
+    ) -> Result<Option<TamperDetection>> {
+        let client = Self::tamper_check_client(settings, timeout)?;
+        // Error pages from a broken proxy are inconclusive, not tampering
+        let body = client.get(url).send().await?.error_for_status()?.bytes().await?;
+        let actual_sha256 = sha256_hex(&body);
+
+        if actual_sha256 == normalize_fingerprint(expected_sha256) {
+            Ok(None)
+        } else {
+            Ok(Some(TamperDetection::ContentModified {
+                url: url.to_string(),
+                expected_sha256: expected_sha256.to_string(),
+                actual_sha256,
+            }))
+        }
+    }
+
+    async fn check_certificate_pin(
+        settings: &ProxySettings,
+        url: &str,
+        pins: &[String],
+        timeout: Duration,
+    ) -> Result<Option<TamperDetection>> {
+        // reqwest does not expose TLS details for tunnelled connections, so the
+        // tunnel (CONNECT, SOCKS, SSH or Shadowsocks) and handshake are done by hand
+        if settings.proxy_type == ProxyType::Direct {
+            return Err(anyhow!("Certificate pinning requires a proxy"));
+        }
+        let parsed = url::Url::parse(url)?;
+        let host = parsed.host_str().ok_or_else(|| anyhow!("No host in URL: {}", url))?;
+        let port = parsed.port_or_known_default().unwrap_or(443);
+
+        let connector = tokio_native_tls::TlsConnector::from(
+            native_tls::TlsConnector::builder()
+                // Forged certificates must reach the pin comparison instead of failing the handshake
+                .danger_accept_invalid_certs(true)
+                .danger_accept_invalid_hostnames(true)
+                .build()?,
+        );
+        let tls = tokio::time::timeout(timeout, async {
+            let tunnel = crate::local_proxy::establish_proxy_tunnel(settings, host, port).await?;
+            Ok::<_, anyhow::Error>(connector.connect(host, tunnel).await?)
+        })
+        .await
+        .map_err(|_| anyhow!("Timed out connecting to {}", url))??;
+
+        let actual_sha256 = tls
+            .get_ref()
+            .peer_certificate()?
+            .map(|cert| cert.to_der())
+            .transpose()?
+            .map(|der| sha256_hex(&der));
+
+        let pinned = actual_sha256
+            .as_ref()
+            .is_some_and(|actual| pins.iter().any(|pin| normalize_fingerprint(pin) == *actual));
+        if pinned {
+            Ok(None)
+        } else {
+            Ok(Some(TamperDetection::CertificateMismatch {
+                url: url.to_string(),
+                actual_sha256,
+            }))
+        }
+    }
+}
+
+// ============================================================================
+// Throughput Benchmarking
+// ============================================================================
+
+/// Configuration for proxy throughput benchmarks
+#[derive(Debug, Clone)]
+pub struct ThroughputBenchmarkConfig {
+    /// URL serving the download payload; `{bytes}` is replaced with `download_bytes`
+    pub download_url: String,
+    /// Size of the download payload in bytes
+    pub download_bytes: u64,
+    /// URL accepting a POSTed payload; uploads are skipped when `None`
+    pub upload_url: Option<String>,
+    /// Size of the upload payload in bytes
+    pub upload_bytes: u64,
+    /// Number of samples to take (jitter needs at least two)
+    pub samples: u32,
+    /// Timeout for a single sample
+    pub sample_timeout: Duration,
+}
+
+impl Default for ThroughputBenchmarkConfig {
+    fn default() -> Self {
+        Self {
+            download_url: "https://speed.cloudflare.com/__down?bytes={bytes}".to_string(),
+            download_bytes: 5_000_000,
+            upload_url: None,
+            upload_bytes: 1_000_000,
+            samples: 3,
+            sample_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// A single download or upload measurement
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThroughputSample {
+    /// Time until the first payload byte arrived (downloads) or the response started (uploads)
+    pub ttfb_ms: f64,
+    pub bytes: u64,
+    /// Time spent transferring the payload, from the response headers for downloads
+    pub transfer_ms: f64,
+    pub mbps: f64,
+}
+
+/// Aggregated result of a throughput benchmark
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThroughputResult {
+    /// Mean sustained download throughput in Mbit/s
+    pub download_mbps: f64,
+    /// Mean upload throughput in Mbit/s, if uploads were measured
+    pub upload_mbps: Option<f64>,
+    /// Mean download time-to-first-byte
+    pub ttfb_ms: f64,
+    /// Mean absolute difference between consecutive download TTFBs
+    pub jitter_ms: f64,
+    pub download_samples: Vec<ThroughputSample>,
+    pub upload_samples: Vec<ThroughputSample>,
+    pub measured_at: DateTime<Utc>,
+}
+
+impl ThroughputResult {
+    /// Build a result from raw samples
+    pub fn from_samples(download_samples: Vec<ThroughputSample>, upload_samples: Vec<ThroughputSample>) -> Self {
+        let mean = |values: Vec<f64>| {
+            if values.is_empty() {
+                0.0
+            } else {
+                values.iter().sum::<f64>() / values.len() as f64
+            }
+        };
+
+        let jitter_ms = mean(
+            download_samples
+                .windows(2)
+                .map(|w| (w[1].ttfb_ms - w[0].ttfb_ms).abs())
+                .collect(),
+        );
+        let upload_mbps = (!upload_samples.is_empty())
+            .then(|| mean(upload_samples.iter().map(|s| s.mbps).collect()));
+
+        Self {
+            download_mbps: mean(download_samples.iter().map(|s| s.mbps).collect()),
+            upload_mbps,
+            ttfb_ms: mean(download_samples.iter().map(|s| s.ttfb_ms).collect()),
+            jitter_ms,
+            download_samples,
+            upload_samples,
+            measured_at: Utc::now(),
+        }
+    }
+
+    /// Sustained download throughput in bytes per second
+    pub fn download_bytes_per_sec(&self) -> f64 {
+        self.download_mbps * 1_000_000.0 / 8.0
+    }
+}
+
+fn to_mbps(bytes: u64, elapsed: Duration) -> f64 {
+    // Clamp to 1ms so a payload delivered in a single read doesn't report infinite bandwidth
+    let secs = elapsed.as_secs_f64().max(0.001);
+    (bytes as f64 * 8.0) / secs / 1_000_000.0
+}
+
+impl ProxyValidator {
+    /// Benchmark sustained throughput through a proxy
+    ///
+    /// # Arguments
+    /// * `proxy` - The proxy to benchmark
+    /// * `config` - Payload sizes, URLs and sample count
+    ///
+    /// # Returns
+    /// ThroughputResult with per-sample measurements and aggregates
+    pub async fn benchmark_throughput(&self, proxy: &FreeProxy, config: &ThroughputBenchmarkConfig) -> Result<ThroughputResult> {
+        let _permit = self.semaphore.acquire().await
+            .map_err(|e| anyhow!("Failed to acquire semaphore: {}", e))?;
+
+        let client = HttpClient::with_proxy(&proxy.to_proxy_settings())?;
+        let samples = config.samples.max(1);
+        let download_url = config.download_url.replace("{bytes}", &config.download_bytes.to_string());
+
+        info!("Benchmarking throughput of proxy {}:{} ({} samples)", proxy.ip, proxy.port, samples);
+
+        let mut download_samples = Vec::with_capacity(samples as usize);
+        for _ in 0..samples {
+            download_samples.push(Self::download_sample(client.client(), &download_url, config.sample_timeout).await?);
+        }
+
+        let mut upload_samples = Vec::new();
+        if let Some(upload_url) = &config.upload_url {
+            for _ in 0..samples {
+                upload_samples.push(
+                    Self::upload_sample(client.client(), upload_url, config.upload_bytes, config.sample_timeout).await?,
+                );
+            }
+        }
+
+        let result = ThroughputResult::from_samples(download_samples, upload_samples);
+        info!(
+            "Proxy {}:{} throughput: {:.2} Mbit/s down, TTFB {:.0}ms, jitter {:.0}ms",
+            proxy.ip, proxy.port, result.download_mbps, result.ttfb_ms, result.jitter_ms
+        );
+        Ok(result)
+    }
+
+    async fn download_sample(client: &reqwest::Client, url: &str, timeout: Duration) -> Result<ThroughputSample> {
+        let start = Instant::now();
+        let mut response = client.get(url).timeout(timeout).send().await
+            .map_err(|e| anyhow!("Download request failed: {}", e))?
+            .error_for_status()
+            .map_err(|e| anyhow!("Download request failed: {}", e))?;
+        // Every body byte is counted, so the clock starts before the first one arrives
+        let headers_received = Instant::now();
+
+        let mut bytes = 0u64;
+        let mut first_byte: Option<Instant> = None;
+        while let Some(chunk) = response.chunk().await
+            .map_err(|e| anyhow!("Download interrupted after {} bytes: {}", bytes, e))?
+        {
+            first_byte.get_or_insert_with(Instant::now);
+            bytes += chunk.len() as u64;
+        }
+
+        let first_byte = first_byte.ok_or_else(|| anyhow!("Download returned an empty body"))?;
+        let transfer = headers_received.elapsed();
+        Ok(ThroughputSample {
+            ttfb_ms: (first_byte - start).as_secs_f64() * 1000.0,
+            bytes,
+            transfer_ms: transfer.as_secs_f64() * 1000.0,
+            mbps: to_mbps(bytes, transfer),
+        })
+    }
+
+    async fn upload_sample(client: &reqwest::Client, url: &str, bytes: u64, timeout: Duration) -> Result<ThroughputSample> {
+        let payload = vec![0u8; bytes as usize];
+        let start = Instant::now();
+        let response = client.post(url).timeout(timeout).body(payload).send().await
+            .map_err(|e| anyhow!("Upload request failed: {}", e))?
+            .error_for_status()
+            .map_err(|e| anyhow!("Upload request failed: {}", e))?;
+        let elapsed = start.elapsed();
+        // Drain the (usually empty) response so the connection can be reused
+        let _ = response.bytes().await;
+
+        Ok(ThroughputSample {
+            ttfb_ms: elapsed.as_secs_f64() * 1000.0,
+            bytes,
+            transfer_ms: elapsed.as_secs_f64() * 1000.0,
+            mbps: to_mbps(bytes, elapsed),
+        })
+    }
+}
+
 #[allow(dead_code)]
 /// Represents a ProxyHealthChecker.
 pub struct ProxyHealthChecker {
@@ -367,6 +828,16 @@ impl ProxyHealthChecker {
 use std::collections::HashMap;
 use tokio::sync::RwLock;
 
+/// Why a proxy was quarantined
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuarantineReason {
+    /// Repeated connectivity or validation failures
+    #[default]
+    Failures,
+    /// The proxy was caught modifying traffic
+    Tampering,
+}
+
 /// Represents a quarantined proxy with failure tracking
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a QuarantinedProxy.
@@ -376,11 +847,17 @@ pub struct QuarantinedProxy {
     pub quarantined_at: DateTime<Utc>,
     pub release_at: DateTime<Utc>,
     pub failure_reasons: Vec<String>,
+    #[serde(default)]
+    pub reason: QuarantineReason,
 }
 
 /// Manages quarantined proxies and their release
+///
+/// Each quarantine term a proxy serves without an intervening success doubles
+/// the length of its next term, up to `max_quarantine_duration`.
 pub struct ProxyQuarantineManager {
     quarantined: Arc<RwLock<HashMap<String, QuarantinedProxy>>>,
+    served_terms: Arc<RwLock<HashMap<String, u32>>>,
     max_consecutive_failures: u32,
     quarantine_duration: Duration,
     max_quarantine_duration: Duration,
@@ -395,6 +872,7 @@ impl ProxyQuarantineManager {
     ) -> Self {
         Self {
             quarantined: Arc::new(RwLock::new(HashMap::new())),
+            served_terms: Arc::new(RwLock::new(HashMap::new())),
             max_consecutive_failures,
             quarantine_duration,
             max_quarantine_duration,
@@ -406,6 +884,19 @@ impl ProxyQuarantineManager {
         format!("{}:{}", proxy.ip, proxy.port)
     }
 
+    /// Quarantine length after `terms` earlier terms: base * 2^terms, capped at the maximum
+    pub fn backoff_duration(&self, terms: u32) -> Duration {
+        let multiplier = 2u32.saturating_pow(terms.min(16));
+        self.quarantine_duration
+            .saturating_mul(multiplier)
+            .min(self.max_quarantine_duration)
+    }
+
+    /// Whether an entry is an actual quarantine rather than a failure tracker below the threshold
+    fn is_active(&self, entry: &QuarantinedProxy) -> bool {
+        entry.reason == QuarantineReason::Tampering || entry.consecutive_failures >= self.max_consecutive_failures
+    }
+
     /// Record a failure for a proxy, potentially quarantining it
     /// Record a proxy failure
     ///
@@ -418,59 +909,99 @@ impl ProxyQuarantineManager {
     pub async fn record_failure(&self, proxy: &FreeProxy, reason: String) -> bool {
         let key = Self::proxy_key(proxy);
         let mut quarantined = self.quarantined.write().await;
+        let terms = self.served_terms.read().await.get(&key).copied().unwrap_or(0);
+        let now = Utc::now();
 
-        if let Some(entry) = quarantined.get_mut(&key) {
-            // Proxy already in quarantine, increment failures
-            entry.consecutive_failures += 1;
-            entry.failure_reasons.push(reason);
-            
-            // Extend quarantine with exponential backoff, up to max
-            let multiplier = 2u32.pow(entry.consecutive_failures.min(5));
-            let extended_duration = self.quarantine_duration * multiplier;
-            let actual_duration = extended_duration.min(self.max_quarantine_duration);
-            entry.release_at = Utc::now() + chrono::Duration::from_std(actual_duration).unwrap_or(chrono::Duration::hours(24));
-            
+        let entry = quarantined.entry(key.clone()).or_insert_with(|| QuarantinedProxy {
+            proxy: proxy.clone(),
+            consecutive_failures: 0,
+            quarantined_at: now,
+            release_at: now,
+            failure_reasons: vec![],
+            reason: QuarantineReason::Failures,
+        });
+        entry.consecutive_failures += 1;
+        entry.failure_reasons.push(reason);
+
+        if entry.consecutive_failures < self.max_consecutive_failures {
+            // Track failure but don't quarantine yet; the count lapses after one base period
+            entry.release_at = now + chrono::Duration::from_std(self.quarantine_duration).unwrap_or(chrono::Duration::minutes(5));
+            return false;
+        }
+
+        // Extend quarantine with exponential backoff over earlier terms and extra failures, up to max
+        let extra_failures = entry.consecutive_failures - self.max_consecutive_failures;
+        let duration = self.backoff_duration(terms + extra_failures);
+        let release_at = now + chrono::Duration::from_std(duration).unwrap_or(chrono::Duration::hours(24));
+        entry.release_at = entry.release_at.max(release_at);
+
+        if extra_failures == 0 {
+            info!(
+                "Quarantining proxy {} after {} failures (term #{}, {:?})",
+                key, entry.consecutive_failures, terms + 1, duration
+            );
+        } else {
             warn!(
                 "Proxy {} failure #{}: {}. Quarantine extended to {:?}",
                 key, entry.consecutive_failures, entry.failure_reasons.last().unwrap_or(&String::new()), entry.release_at
             );
-            true
-        } else {
-            // First failure, check if we should quarantine
-            let entry = QuarantinedProxy {
-                proxy: proxy.clone(),
-                consecutive_failures: 1,
-                quarantined_at: Utc::now(),
-                release_at: Utc::now() + chrono::Duration::from_std(self.quarantine_duration).unwrap_or(chrono::Duration::minutes(5)),
-                failure_reasons: vec![reason.clone()],
-            };
-            
-            if entry.consecutive_failures >= self.max_consecutive_failures {
-                info!("Quarantining proxy {} after {} failures", key, entry.consecutive_failures);
-                quarantined.insert(key, entry);
-                true
-            } else {
-                // Track failure but don't quarantine yet
-                quarantined.insert(key, entry);
-                false
-            }
         }
+        true
+    }
+
+    /// Quarantine a proxy caught tampering, skipping the failure threshold
+    ///
+    /// # Arguments
+    /// * `proxy` - The tampering proxy
+    /// * `detection` - What the tamper check found
+    pub async fn quarantine_for_tampering(&self, proxy: &FreeProxy, detection: &TamperDetection) {
+        let key = Self::proxy_key(proxy);
+        let now = Utc::now();
+        let release_at = now + chrono::Duration::from_std(self.max_quarantine_duration).unwrap_or(chrono::Duration::hours(24));
+        let mut quarantined = self.quarantined.write().await;
+
+        let entry = quarantined.entry(key.clone()).or_insert_with(|| QuarantinedProxy {
+            proxy: proxy.clone(),
+            consecutive_failures: 0,
+            quarantined_at: now,
+            release_at,
+            failure_reasons: vec![],
+            reason: QuarantineReason::Tampering,
+        });
+        entry.consecutive_failures = entry.consecutive_failures.max(self.max_consecutive_failures).max(1);
+        entry.failure_reasons.push(detection.to_string());
+        entry.release_at = entry.release_at.max(release_at);
+        entry.reason = QuarantineReason::Tampering;
+
+        warn!("Proxy {} quarantined for tampering until {:?}", key, entry.release_at);
     }
 
     /// Record a success for a proxy, potentially releasing it from quarantine
     /// Record a successful proxy operation
     ///
+    /// Proxies quarantined for tampering stay quarantined until their time is served.
+    ///
     /// # Arguments
     /// * `proxy` - The successful proxy
     pub async fn record_success(&self, proxy: &FreeProxy) {
         let key = Self::proxy_key(proxy);
         let mut quarantined = self.quarantined.write().await;
         
+        if quarantined.get(&key).is_some_and(|e| e.reason == QuarantineReason::Tampering) {
+            return;
+        }
+        self.served_terms.write().await.remove(&key);
         if quarantined.remove(&key).is_some() {
             info!("Proxy {} released from quarantine after successful validation", key);
         }
     }
 
+    /// Number of quarantine terms a proxy has served since its last success
+    pub async fn served_terms(&self, proxy: &FreeProxy) -> u32 {
+        let key = Self::proxy_key(proxy);
+        self.served_terms.read().await.get(&key).copied().unwrap_or(0)
+    }
+
     /// Check if a proxy is currently quarantined
     /// Check if a proxy is currently quarantined
     ///
@@ -481,7 +1012,7 @@ impl ProxyQuarantineManager {
         let quarantined = self.quarantined.read().await;
         
         if let Some(entry) = quarantined.get(&key) {
-            entry.release_at > Utc::now()
+            self.is_active(entry) && entry.release_at > Utc::now()
         } else {
             false
         }
@@ -496,8 +1027,11 @@ impl ProxyQuarantineManager {
 
     /// Release proxies that have served their quarantine time
     /// Release proxies whose quarantine period has expired
+    ///
+    /// Failure trackers below the threshold lapse silently and are not returned.
     pub async fn release_expired(&self) -> Vec<FreeProxy> {
         let mut quarantined = self.quarantined.write().await;
+        let mut served_terms = self.served_terms.write().await;
         let now = Utc::now();
         
         let expired_keys: Vec<String> = quarantined
@@ -509,7 +1043,11 @@ impl ProxyQuarantineManager {
         let mut released = Vec::new();
         for key in expired_keys {
             if let Some(entry) = quarantined.remove(&key) {
+                if !self.is_active(&entry) {
+                    continue;
+                }
                 info!("Releasing proxy {} from quarantine (served time)", key);
+                *served_terms.entry(key).or_insert(0) += 1;
                 released.push(entry.proxy);
             }
         }
@@ -524,7 +1062,7 @@ impl ProxyQuarantineManager {
         let now = Utc::now();
         
         let total = quarantined.len();
-        let active = quarantined.values().filter(|e| e.release_at > now).count();
+        let active = quarantined.values().filter(|e| self.is_active(e) && e.release_at > now).count();
         let pending_release = total - active;
         let avg_failures = if total > 0 {
             quarantined.values().map(|e| e.consecutive_failures as f64).sum::<f64>() / total as f64
@@ -560,6 +1098,9 @@ pub struct QuarantineStats {
 pub struct GeoVerificationConfig {
     pub enabled: bool,
     pub tolerance_km: f64,
+    /// Remote GeoIP API templates (`{ip}` is replaced), used only when the local
+    /// database has no answer. Empty by default, so proxy IPs never reach third
+    /// parties unless a deployment opts in.
     pub geoip_api_urls: Vec<String>,
 }
 
@@ -568,10 +1109,20 @@ impl Default for GeoVerificationConfig {
         Self {
             enabled: true,
             tolerance_km: 500.0, // Allow 500km tolerance for geo verification
-            geoip_api_urls: vec![
-                "https://ipapi.co/{ip}/json/".to_string(),
-                "https://ip-api.com/json/{ip}".to_string(),
-            ],
+            geoip_api_urls: Vec::new(),
+        }
+    }
+}
+
+impl GeoVerificationConfig {
+    /// Default config with remote GeoIP lookups from `GEOIP_API_URLS`, a
+    /// comma-separated list of URL templates such as `https://ipapi.co/{ip}/json/`
+    pub fn from_env() -> Self {
+        Self {
+            geoip_api_urls: std::env::var("GEOIP_API_URLS")
+                .map(|v| v.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect())
+                .unwrap_or_default(),
+            ..Self::default()
         }
     }
 }
@@ -593,6 +1144,8 @@ pub struct GeoVerificationResult {
 pub struct GeoVerifier {
     config: GeoVerificationConfig,
     http_client: HttpClient,
+    geoip_database: Option<Arc<GeoIpDatabase>>,
+    range_index: Option<Arc<IpRangeIndex>>,
 }
 
 impl GeoVerifier {
@@ -601,9 +1154,31 @@ impl GeoVerifier {
         Ok(Self {
             config,
             http_client: HttpClient::new()?,
+            geoip_database: None,
+            range_index: None,
         })
     }
 
+    /// Resolve locations from a local GeoIP database before trying the HTTP APIs.
+    pub fn with_geoip_database(mut self, database: Arc<GeoIpDatabase>) -> Self {
+        self.geoip_database = Some(database);
+        self
+    }
+
+    /// Resolve countries from an IP range index when the GeoIP database has no answer.
+    pub fn with_range_index(mut self, index: Arc<IpRangeIndex>) -> Self {
+        self.range_index = Some(index);
+        self
+    }
+
+    /// Replace the proxy's provider-supplied country with the one its IP is
+    /// registered to. Returns true if the proxy was changed.
+    pub fn correct_country(&self, proxy: &mut FreeProxy) -> bool {
+        self.range_index
+            .as_ref()
+            .is_some_and(|index| crate::free_ip_providers::correct_proxy_country(index, proxy))
+    }
+
     /// Verify that a proxy's detected IP matches its claimed geographic location
     /// Verify a proxy's claimed location
     ///
@@ -627,16 +1202,18 @@ impl GeoVerifier {
         let geo_result = self.lookup_ip_location(detected_ip).await;
         
         match geo_result {
-            Ok((country, location)) => {
-                let is_country_match = country.to_lowercase() == proxy.country.to_lowercase()
-                    || country.to_lowercase() == proxy.country_code.to_lowercase();
+            Ok(record) => {
+                let is_country_match = [&record.country, &record.country_code]
+                    .into_iter()
+                    .flatten()
+                    .any(|c| c.eq_ignore_ascii_case(&proxy.country) || c.eq_ignore_ascii_case(&proxy.country_code));
                 
                 GeoVerificationResult {
                     is_verified: is_country_match,
                     expected_country: proxy.country.clone(),
-                    detected_country: Some(country),
+                    detected_country: record.country.clone().or_else(|| record.country_code.clone()),
                     expected_location: None, // We don't have expected coordinates
-                    detected_location: location,
+                    detected_location: record.location(),
                     distance_km: None,
                     error: None,
                 }
@@ -655,8 +1232,25 @@ impl GeoVerifier {
         }
     }
 
-    /// Lookup IP location using geo IP APIs
-    async fn lookup_ip_location(&self, ip: &str) -> Result<(String, Option<(f64, f64)>)> {
+    /// Lookup IP location in the local database or range index, falling back to any configured geo IP APIs
+    pub async fn lookup_ip_location(&self, ip: &str) -> Result<GeoIpRecord> {
+        if let Some(record) = self.geoip_database.as_ref().and_then(|db| db.lookup_str(ip)) {
+            if record.country_code.is_some() || record.country.is_some() {
+                return Ok(record);
+            }
+        }
+
+        let indexed = self.range_index.as_ref().zip(ip.trim().parse::<std::net::IpAddr>().ok());
+        if let Some(info) = indexed.and_then(|(index, ip)| index.lookup_info(ip)) {
+            return Ok(GeoIpRecord {
+                country_code: Some(info.country_code),
+                country: Some(info.country),
+                asn: info.asn,
+                organization: Some(info.isp),
+                ..Default::default()
+            });
+        }
+
         for api_url_template in &self.config.geoip_api_urls {
             let url = api_url_template.replace("{ip}", ip);
             
@@ -674,11 +1268,11 @@ impl GeoVerifier {
             }
         }
         
-        Err(anyhow!("All geo IP APIs failed"))
+        Err(anyhow!("No location found for {}", ip))
     }
 
     /// Parse various geo IP API response formats
-    fn parse_geo_response(&self, response: &str) -> Result<(String, Option<(f64, f64)>)> {
+    fn parse_geo_response(&self, response: &str) -> Result<GeoIpRecord> {
         // Try ipapi.co format
         #[derive(Deserialize)]
         struct IpApiCoResponse {
@@ -692,11 +1286,12 @@ impl GeoVerifier {
             let country = parsed.country_name
                 .or(parsed.country)
                 .ok_or_else(|| anyhow!("No country in response"))?;
-            let location = match (parsed.latitude, parsed.longitude) {
-                (Some(lat), Some(lon)) => Some((lat, lon)),
-                _ => None,
-            };
-            return Ok((country, location));
+            return Ok(GeoIpRecord {
+                country: Some(country),
+                latitude: parsed.latitude,
+                longitude: parsed.longitude,
+                ..Default::default()
+            });
         }
         
         // Try ip-api.com format
@@ -709,11 +1304,12 @@ impl GeoVerifier {
         
         if let Ok(parsed) = serde_json::from_str::<IpApiResponse>(response) {
             let country = parsed.country.ok_or_else(|| anyhow!("No country in response"))?;
-            let location = match (parsed.lat, parsed.lon) {
-                (Some(lat), Some(lon)) => Some((lat, lon)),
-                _ => None,
-            };
-            return Ok((country, location));
+            return Ok(GeoIpRecord {
+                country: Some(country),
+                latitude: parsed.lat,
+                longitude: parsed.lon,
+                ..Default::default()
+            });
         }
         
         Err(anyhow!("Failed to parse geo response"))
@@ -807,6 +1403,12 @@ impl EnhancedProxyHealthChecker {
         result: &ValidationResult,
         proxies_lock: &mut tokio::sync::RwLockWriteGuard<'_, Vec<FreeProxy>>,
     ) {
+        if let Some(detection) = &result.tampering {
+            self.quarantine_manager.quarantine_for_tampering(proxy, detection).await;
+            proxies_lock.retain(|p| !(p.ip == proxy.ip && p.port == proxy.port));
+            return;
+        }
+        
         let reason = result.error.clone().unwrap_or_else(|| "Connection failed".to_string());
         let quarantined = self.quarantine_manager.record_failure(proxy, reason).await;
         
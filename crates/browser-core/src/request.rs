@@ -8,6 +8,7 @@
 //! - Body handling (JSON, form data, raw bytes)
 //! - Response parsing
 //! - Error handling with detailed error types
+//! - Optional TLS/HTTP2 fingerprint of a tab's `NetworkConfig`
 
 use anyhow::{anyhow, Result};
 use reqwest::{Client, Method};
@@ -17,7 +18,10 @@ use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, info};
 
+#[cfg(feature = "impersonation")]
+use crate::impersonation::{ImpersonatedClient, ImpersonatedRequest};
 use crate::proxy::ProxySettings;
+use crate::tab_isolation::NetworkConfig;
 
 /// Request error types
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -202,6 +206,8 @@ pub struct RequestBuilder {
     pub config: RequestConfig,
     /// Optional proxy settings
     pub proxy: Option<ProxySettings>,
+    /// Send with this TLS and HTTP/2 fingerprint instead of reqwest's
+    pub network_config: Option<NetworkConfig>,
 }
 
 impl RequestBuilder {
@@ -249,6 +255,7 @@ impl RequestBuilder {
             body: RequestBody::None,
             config: RequestConfig::default(),
             proxy: None,
+            network_config: None,
         }
     }
 
@@ -316,12 +323,22 @@ impl RequestBuilder {
         self
     }
 
+    /// Send with the TLS and HTTP/2 fingerprint of a tab's network config
+    pub fn network_config(mut self, config: NetworkConfig) -> Self {
+        self.network_config = Some(config);
+        self
+    }
+
     /// Send the request
-    pub async fn send(self) -> Result<RequestResponse> {
+    pub async fn send(mut self) -> Result<RequestResponse> {
         let start_time = std::time::Instant::now();
         
         debug!("Sending {} request to: {}", format!("{:?}", self.method), self.url);
 
+        if let Some(config) = self.network_config.take() {
+            return self.send_impersonated(&config, start_time).await;
+        }
+
         // Build the client
         let mut client_builder = Client::builder()
             .timeout(self.config.timeout)
@@ -418,6 +435,74 @@ impl RequestBuilder {
             final_url,
         })
     }
+
+    #[cfg(not(feature = "impersonation"))]
+    async fn send_impersonated(self, _config: &NetworkConfig, _start_time: std::time::Instant) -> Result<RequestResponse> {
+        Err(anyhow!("browser-core was built without the `impersonation` feature"))
+    }
+
+    #[cfg(feature = "impersonation")]
+    async fn send_impersonated(self, config: &NetworkConfig, start_time: std::time::Instant) -> Result<RequestResponse> {
+        let mut client = ImpersonatedClient::new(config)?
+            .with_timeout(self.config.timeout)
+            .with_max_redirects(if self.config.follow_redirects { self.config.max_redirects as usize } else { 0 });
+        if let Some(proxy) = self.proxy.clone().filter(|p| p.is_configured()) {
+            client = client.with_proxy(proxy);
+        }
+        if !self.config.verify_ssl {
+            client = client.danger_accept_invalid_certs()?;
+        }
+
+        let method: Method = self.method.into();
+        let mut request = ImpersonatedRequest::new(method.as_str(), &self.url);
+        if let Some(ua) = &self.config.user_agent {
+            if !self.headers.keys().any(|name| name.eq_ignore_ascii_case("user-agent")) {
+                request = request.header("user-agent", ua);
+            }
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request = match self.body {
+            RequestBody::None => request,
+            RequestBody::Json(json) => request.body(serde_json::to_vec(&json)?),
+            RequestBody::Form(form) => request.body(url::form_urlencoded::Serializer::new(String::new()).extend_pairs(&form).finish()),
+            RequestBody::Bytes(bytes) => request.body(bytes),
+            RequestBody::Text(text) => request.body(text),
+        };
+
+        let response = client.send(&request).await.map_err(|e| {
+            let message = e.to_string();
+            let kind = if message.contains("timed out") {
+                RequestErrorKind::Timeout
+            } else if message.contains("TLS handshake") {
+                RequestErrorKind::TlsError
+            } else if message.contains("Connection to") {
+                RequestErrorKind::Network
+            } else {
+                RequestErrorKind::Other
+            };
+            anyhow!(RequestError::new(kind, message).with_url(&self.url))
+        })?;
+
+        let status_text = reqwest::StatusCode::from_u16(response.status)
+            .ok()
+            .and_then(|s| s.canonical_reason())
+            .unwrap_or("Unknown")
+            .to_string();
+        let response_time_ms = start_time.elapsed().as_millis() as u64;
+        info!("Request completed: {:?} {} - {} over {} in {}ms",
+            self.method, self.url, response.status, response.protocol, response_time_ms);
+
+        Ok(RequestResponse {
+            status: response.status,
+            status_text,
+            body: response.text(),
+            headers: response.headers.into_iter().collect(),
+            response_time_ms,
+            final_url: response.url,
+        })
+    }
 }
 
 /// Request manager for handling multiple requests
@@ -68,6 +68,9 @@ impl ProxyScraper {
                         last_checked: last_checked.to_string(),
                         provider: "free-proxy-list.net".to_string(),
                         is_working: false,
+                        username: None,
+                        password: None,
+                        ssh: None,
                     };
                     
                     proxies.push(proxy);
@@ -139,6 +142,9 @@ impl ProxyScraper {
                         last_checked: Utc::now().to_rfc3339(),
                         provider: "proxy-nova.com".to_string(),
                         is_working: false,
+                        username: None,
+                        password: None,
+                        ssh: None,
                     };
                     
                     proxies.push(proxy);
@@ -203,6 +209,9 @@ impl ProxyScraper {
                             last_checked: Utc::now().to_rfc3339(),
                             provider: "spys.one".to_string(),
                             is_working: false,
+                            username: None,
+                            password: None,
+                            ssh: None,
                         };
                         
                         proxies.push(proxy);
@@ -0,0 +1,917 @@
+//! Shadowsocks Module
+//!
+//! Native Shadowsocks client for use as a proxy upstream:
+//! - AEAD ciphers (aes-128-gcm, aes-256-gcm, chacha20-ietf-poly1305)
+//! - Shadowsocks 2022 ciphers (2022-blake3-*) with timestamped headers and replay protection
+//! - `ss://` URIs (SIP002 and the legacy base64 form) and SIP008 subscription documents
+//! - A minimal in-process server, so clients can be verified without external services
+
+use aes_gcm::aead::generic_array::GenericArray;
+use aes_gcm::aead::{AeadInPlace, KeyInit};
+use aes_gcm::{Aes128Gcm, Aes256Gcm};
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chacha20poly1305::ChaCha20Poly1305;
+use hkdf::Hkdf;
+use md5::{Digest, Md5};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::local_proxy::loopback_pair;
+use crate::proxy::{FreeProxy, ProxySettings, ProxyType};
+
+/// AEAD authentication tag length, the same for every supported cipher
+const TAG_LEN: usize = 16;
+/// Largest payload chunk for the original AEAD protocol
+const MAX_CHUNK: usize = 0x3FFF;
+/// Largest payload chunk for Shadowsocks 2022
+const MAX_CHUNK_2022: usize = 0xFFFF;
+/// Allowed clock difference for Shadowsocks 2022 request and response headers
+const MAX_TIME_DIFF_SECS: u64 = 30;
+/// Upper bound of the random padding sent with a Shadowsocks 2022 request
+const MAX_PADDING: usize = 900;
+/// Time allowed for the TCP connect to the server
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Shadowsocks encryption methods
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ShadowsocksCipher {
+    #[serde(rename = "aes-128-gcm")]
+    Aes128Gcm,
+    #[serde(rename = "aes-256-gcm")]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-ietf-poly1305")]
+    Chacha20IetfPoly1305,
+    #[serde(rename = "2022-blake3-aes-128-gcm")]
+    Blake3Aes128Gcm,
+    #[serde(rename = "2022-blake3-aes-256-gcm")]
+    Blake3Aes256Gcm,
+    #[serde(rename = "2022-blake3-chacha20-poly1305")]
+    Blake3Chacha20Poly1305,
+}
+
+impl ShadowsocksCipher {
+    /// Every supported method
+    pub const ALL: [ShadowsocksCipher; 6] = [
+        Self::Aes128Gcm,
+        Self::Aes256Gcm,
+        Self::Chacha20IetfPoly1305,
+        Self::Blake3Aes128Gcm,
+        Self::Blake3Aes256Gcm,
+        Self::Blake3Chacha20Poly1305,
+    ];
+
+    /// Method name as used in URIs and server configs
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Aes128Gcm => "aes-128-gcm",
+            Self::Aes256Gcm => "aes-256-gcm",
+            Self::Chacha20IetfPoly1305 => "chacha20-ietf-poly1305",
+            Self::Blake3Aes128Gcm => "2022-blake3-aes-128-gcm",
+            Self::Blake3Aes256Gcm => "2022-blake3-aes-256-gcm",
+            Self::Blake3Chacha20Poly1305 => "2022-blake3-chacha20-poly1305",
+        }
+    }
+
+    /// Looks a method up by name, case-insensitively
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.trim().to_ascii_lowercase();
+        if name == "chacha20-poly1305" {
+            return Some(Self::Chacha20IetfPoly1305);
+        }
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// Key length in bytes, which is also the salt length
+    pub fn key_len(&self) -> usize {
+        match self {
+            Self::Aes128Gcm | Self::Blake3Aes128Gcm => 16,
+            _ => 32,
+        }
+    }
+
+    /// Whether this is a Shadowsocks 2022 method
+    ///
+    /// 2022 methods take a base64-encoded key of exactly `key_len` bytes as the password.
+    pub fn is_2022(&self) -> bool {
+        matches!(self, Self::Blake3Aes128Gcm | Self::Blake3Aes256Gcm | Self::Blake3Chacha20Poly1305)
+    }
+
+    fn max_chunk(&self) -> usize {
+        if self.is_2022() {
+            MAX_CHUNK_2022
+        } else {
+            MAX_CHUNK
+        }
+    }
+
+    /// Long-term key derived from the password
+    fn master_key(&self, password: &str) -> Result<Vec<u8>> {
+        if self.is_2022() {
+            let key = STANDARD
+                .decode(password.trim())
+                .map_err(|_| anyhow!("{} needs a base64-encoded key as the password", self.name()))?;
+            if key.len() != self.key_len() {
+                return Err(anyhow!(
+                    "{} needs a {}-byte key, got {} bytes",
+                    self.name(),
+                    self.key_len(),
+                    key.len()
+                ));
+            }
+            return Ok(key);
+        }
+        if password.is_empty() {
+            return Err(anyhow!("Shadowsocks password is empty"));
+        }
+        Ok(evp_bytes_to_key(password.as_bytes(), self.key_len()))
+    }
+
+    /// Per-connection key derived from the master key and the connection's salt
+    fn session_key(&self, master_key: &[u8], salt: &[u8]) -> Vec<u8> {
+        let mut key = vec![0u8; self.key_len()];
+        if self.is_2022() {
+            let mut material = master_key.to_vec();
+            material.extend_from_slice(salt);
+            let derived = blake3::derive_key("shadowsocks 2022 session subkey", &material);
+            key.copy_from_slice(&derived[..self.key_len()]);
+        } else {
+            Hkdf::<Sha1>::new(Some(salt), master_key)
+                .expand(b"ss-subkey", &mut key)
+                .expect("Subkey length is valid for HKDF-SHA1");
+        }
+        key
+    }
+}
+
+/// OpenSSL `EVP_BytesToKey` with MD5, used to turn legacy passwords into keys
+fn evp_bytes_to_key(password: &[u8], key_len: usize) -> Vec<u8> {
+    let mut key = Vec::with_capacity(key_len + 16);
+    let mut previous: Vec<u8> = Vec::new();
+    while key.len() < key_len {
+        let mut hasher = Md5::new();
+        hasher.update(&previous);
+        hasher.update(password);
+        previous = hasher.finalize().to_vec();
+        key.extend_from_slice(&previous);
+    }
+    key.truncate(key_len);
+    key
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn check_timestamp(timestamp: u64) -> Result<()> {
+    if unix_time().abs_diff(timestamp) > MAX_TIME_DIFF_SECS {
+        return Err(anyhow!("Shadowsocks header timestamp is outside the allowed window"));
+    }
+    Ok(())
+}
+
+fn random_salt(cipher: ShadowsocksCipher) -> Vec<u8> {
+    let mut salt = vec![0u8; cipher.key_len()];
+    rand::thread_rng().fill(&mut salt[..]);
+    salt
+}
+
+// ============================================================================
+// Framing
+// ============================================================================
+
+enum AeadKey {
+    Aes128(Box<Aes128Gcm>),
+    Aes256(Box<Aes256Gcm>),
+    Chacha(Box<ChaCha20Poly1305>),
+}
+
+/// One direction of an encrypted stream, with its nonce counter
+struct CipherState {
+    key: AeadKey,
+    counter: u64,
+}
+
+impl CipherState {
+    fn new(cipher: ShadowsocksCipher, session_key: &[u8]) -> Self {
+        let invalid = "Session keys always match the cipher's key length";
+        let key = match cipher {
+            ShadowsocksCipher::Aes128Gcm | ShadowsocksCipher::Blake3Aes128Gcm => {
+                AeadKey::Aes128(Box::new(Aes128Gcm::new_from_slice(session_key).expect(invalid)))
+            }
+            ShadowsocksCipher::Aes256Gcm | ShadowsocksCipher::Blake3Aes256Gcm => {
+                AeadKey::Aes256(Box::new(Aes256Gcm::new_from_slice(session_key).expect(invalid)))
+            }
+            ShadowsocksCipher::Chacha20IetfPoly1305 | ShadowsocksCipher::Blake3Chacha20Poly1305 => {
+                AeadKey::Chacha(Box::new(ChaCha20Poly1305::new_from_slice(session_key).expect(invalid)))
+            }
+        };
+        Self { key, counter: 0 }
+    }
+
+    /// Little-endian counter nonce, incremented after every seal or open
+    fn next_nonce(&mut self) -> [u8; 12] {
+        let mut nonce = [0u8; 12];
+        nonce[..8].copy_from_slice(&self.counter.to_le_bytes());
+        self.counter += 1;
+        nonce
+    }
+
+    fn seal(&mut self, data: &[u8]) -> Vec<u8> {
+        let nonce = self.next_nonce();
+        let nonce = GenericArray::from_slice(&nonce);
+        let mut buffer = Vec::with_capacity(data.len() + TAG_LEN);
+        buffer.extend_from_slice(data);
+        let result = match &self.key {
+            AeadKey::Aes128(key) => key.encrypt_in_place(nonce, b"", &mut buffer),
+            AeadKey::Aes256(key) => key.encrypt_in_place(nonce, b"", &mut buffer),
+            AeadKey::Chacha(key) => key.encrypt_in_place(nonce, b"", &mut buffer),
+        };
+        result.expect("Vec buffers always have room for the tag");
+        buffer
+    }
+
+    fn open(&mut self, mut buffer: Vec<u8>) -> Result<Vec<u8>> {
+        let nonce = self.next_nonce();
+        let nonce = GenericArray::from_slice(&nonce);
+        let result = match &self.key {
+            AeadKey::Aes128(key) => key.decrypt_in_place(nonce, b"", &mut buffer),
+            AeadKey::Aes256(key) => key.decrypt_in_place(nonce, b"", &mut buffer),
+            AeadKey::Chacha(key) => key.decrypt_in_place(nonce, b"", &mut buffer),
+        };
+        result.map_err(|_| anyhow!("Shadowsocks decryption failed (wrong password or cipher?)"))?;
+        Ok(buffer)
+    }
+}
+
+/// Encrypts outgoing data as length-prefixed chunks
+struct Encryptor {
+    state: CipherState,
+    max_chunk: usize,
+}
+
+impl Encryptor {
+    fn new(cipher: ShadowsocksCipher, session_key: &[u8]) -> Self {
+        Self {
+            state: CipherState::new(cipher, session_key),
+            max_chunk: cipher.max_chunk(),
+        }
+    }
+
+    fn seal(&mut self, data: &[u8]) -> Vec<u8> {
+        self.state.seal(data)
+    }
+
+    fn chunks(&mut self, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(payload.len() + 64);
+        for piece in payload.chunks(self.max_chunk) {
+            out.extend(self.state.seal(&(piece.len() as u16).to_be_bytes()));
+            out.extend(self.state.seal(piece));
+        }
+        out
+    }
+}
+
+/// Decrypts incoming length-prefixed chunks
+struct Decryptor {
+    state: CipherState,
+    max_chunk: usize,
+}
+
+impl Decryptor {
+    fn new(cipher: ShadowsocksCipher, session_key: &[u8]) -> Self {
+        Self {
+            state: CipherState::new(cipher, session_key),
+            max_chunk: cipher.max_chunk(),
+        }
+    }
+
+    /// Reads and decrypts one sealed block of `len` plaintext bytes
+    async fn open_exact<R: AsyncRead + Unpin>(&mut self, reader: &mut R, len: usize) -> Result<Vec<u8>> {
+        let mut buffer = vec![0u8; len + TAG_LEN];
+        reader.read_exact(&mut buffer).await?;
+        self.state.open(buffer)
+    }
+
+    /// Next payload chunk, or `None` at a clean end of stream
+    async fn read_chunk<R: AsyncRead + Unpin>(&mut self, reader: &mut R) -> Result<Option<Vec<u8>>> {
+        let length = match self.open_exact(reader, 2).await {
+            Ok(length) => length,
+            Err(e) if is_eof(&e) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+        if length == 0 || length > self.max_chunk {
+            return Err(anyhow!("Invalid Shadowsocks chunk length {}", length));
+        }
+        self.open_exact(reader, length).await.map(Some)
+    }
+}
+
+fn is_eof(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<std::io::Error>()
+        .is_some_and(|e| e.kind() == std::io::ErrorKind::UnexpectedEof)
+}
+
+async fn read_salt<R: AsyncRead + Unpin>(reader: &mut R, cipher: ShadowsocksCipher) -> Result<Vec<u8>> {
+    let mut salt = vec![0u8; cipher.key_len()];
+    reader.read_exact(&mut salt).await?;
+    Ok(salt)
+}
+
+/// Encrypts everything read from `plain` onto `out` until EOF
+async fn pump_encrypt<R, W>(mut plain: R, mut encryptor: Encryptor, mut out: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buffer = vec![0u8; 16 * 1024];
+    loop {
+        let n = plain.read(&mut buffer).await?;
+        if n == 0 {
+            break;
+        }
+        out.write_all(&encryptor.chunks(&buffer[..n])).await?;
+    }
+    out.shutdown().await?;
+    Ok(())
+}
+
+/// Decrypts chunks from `input` onto `plain` until EOF
+async fn pump_decrypt<R, W>(mut input: R, mut decryptor: Decryptor, mut plain: W) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    while let Some(chunk) = decryptor.read_chunk(&mut input).await? {
+        plain.write_all(&chunk).await?;
+    }
+    plain.shutdown().await?;
+    Ok(())
+}
+
+// ============================================================================
+// Addresses
+// ============================================================================
+
+/// SOCKS5-style target address: ATYP, address, big-endian port
+fn encode_address(host: &str, port: u16) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(host.len() + 4);
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            out.push(1);
+            out.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            out.push(4);
+            out.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            let len = u8::try_from(host.len()).map_err(|_| anyhow!("Host name too long: {}", host))?;
+            out.push(3);
+            out.push(len);
+            out.extend_from_slice(host.as_bytes());
+        }
+    }
+    out.extend_from_slice(&port.to_be_bytes());
+    Ok(out)
+}
+
+/// Parses a target address, returning it with the number of bytes consumed
+fn decode_address(data: &[u8]) -> Result<((String, u16), usize)> {
+    let short = || anyhow!("Truncated Shadowsocks address");
+    let (host, offset) = match data.first().ok_or_else(short)? {
+        1 => {
+            let octets: [u8; 4] = data.get(1..5).ok_or_else(short)?.try_into()?;
+            (IpAddr::from(octets).to_string(), 5)
+        }
+        3 => {
+            let len = *data.get(1).ok_or_else(short)? as usize;
+            let name = data.get(2..2 + len).ok_or_else(short)?;
+            (String::from_utf8(name.to_vec()).context("Host name is not UTF-8")?, 2 + len)
+        }
+        4 => {
+            let octets: [u8; 16] = data.get(1..17).ok_or_else(short)?.try_into()?;
+            (IpAddr::from(octets).to_string(), 17)
+        }
+        other => return Err(anyhow!("Unknown Shadowsocks address type {}", other)),
+    };
+    let port = data.get(offset..offset + 2).ok_or_else(short)?;
+    Ok(((host, u16::from_be_bytes([port[0], port[1]])), offset + 2))
+}
+
+// ============================================================================
+// Configuration
+// ============================================================================
+
+/// One Shadowsocks server
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShadowsocksConfig {
+    /// Display name, from the URI fragment or SIP008 `remarks`
+    #[serde(default)]
+    pub name: Option<String>,
+    pub server: String,
+    pub port: u16,
+    pub cipher: ShadowsocksCipher,
+    pub password: String,
+}
+
+impl ShadowsocksConfig {
+    /// Creates a config, checking the password against the cipher
+    pub fn new(server: impl Into<String>, port: u16, cipher: ShadowsocksCipher, password: impl Into<String>) -> Result<Self> {
+        let config = Self {
+            name: None,
+            server: server.into(),
+            port,
+            cipher,
+            password: password.into(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Sets the display name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.server.trim().is_empty() {
+            return Err(anyhow!("Shadowsocks server is empty"));
+        }
+        if self.port == 0 {
+            return Err(anyhow!("Shadowsocks port must be non-zero"));
+        }
+        self.cipher.master_key(&self.password)?;
+        Ok(())
+    }
+
+    /// Parses an `ss://` URI
+    ///
+    /// Accepts SIP002 (`ss://base64(method:password)@host:port#name`, or
+    /// percent-encoded `method:password` for 2022 methods) and the legacy
+    /// `ss://base64(method:password@host:port)#name` form. Plugins are not supported.
+    pub fn parse_uri(uri: &str) -> Result<Self> {
+        let uri = uri.trim();
+        let rest = uri
+            .get(..5)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("ss://"))
+            .map(|_| &uri[5..])
+            .ok_or_else(|| anyhow!("Not an ss:// URI"))?;
+        let (rest, name) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(percent_decode(fragment)).filter(|n| !n.is_empty())),
+            None => (rest, None),
+        };
+
+        let mut config = if rest.contains('@') {
+            Self::parse_sip002(rest)?
+        } else {
+            let decoded = decode_base64(rest.trim_end_matches('/')).ok_or_else(|| anyhow!("Invalid base64 in ss:// URI"))?;
+            let (credentials, address) = decoded
+                .rsplit_once('@')
+                .ok_or_else(|| anyhow!("Missing server in ss:// URI"))?;
+            let (method, password) = credentials
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Missing password in ss:// URI"))?;
+            let (host, port) = address
+                .rsplit_once(':')
+                .ok_or_else(|| anyhow!("Missing port in ss:// URI"))?;
+            let port = port.parse().map_err(|_| anyhow!("Invalid port '{}'", port))?;
+            Self::new(host.trim_start_matches('[').trim_end_matches(']'), port, parse_cipher(method)?, password)?
+        };
+        config.name = name;
+        Ok(config)
+    }
+
+    fn parse_sip002(rest: &str) -> Result<Self> {
+        let url = url::Url::parse(&format!("ss://{}", rest)).context("Invalid ss:// URI")?;
+        if url.query_pairs().any(|(key, _)| key == "plugin") {
+            return Err(anyhow!("Shadowsocks plugins are not supported"));
+        }
+        let host = url.host_str().ok_or_else(|| anyhow!("Missing server in ss:// URI"))?;
+        let port = url.port().ok_or_else(|| anyhow!("Missing port in ss:// URI"))?;
+        let (method, password) = match url.password() {
+            Some(password) => (percent_decode(url.username()), percent_decode(password)),
+            None => {
+                let decoded = decode_base64(&percent_decode(url.username()))
+                    .ok_or_else(|| anyhow!("Invalid base64 user info in ss:// URI"))?;
+                let (method, password) = decoded
+                    .split_once(':')
+                    .ok_or_else(|| anyhow!("Missing password in ss:// URI"))?;
+                (method.to_string(), password.to_string())
+            }
+        };
+        Self::new(host.trim_start_matches('[').trim_end_matches(']'), port, parse_cipher(&method)?, password)
+    }
+
+    /// Formats the config as a SIP002 `ss://` URI
+    pub fn to_uri(&self) -> String {
+        let userinfo = if self.cipher.is_2022() {
+            format!("{}:{}", self.cipher.name(), percent_encode(&self.password))
+        } else {
+            URL_SAFE_NO_PAD.encode(format!("{}:{}", self.cipher.name(), self.password))
+        };
+        let host = if self.server.contains(':') {
+            format!("[{}]", self.server)
+        } else {
+            self.server.clone()
+        };
+        let mut uri = format!("ss://{}@{}:{}", userinfo, host, self.port);
+        if let Some(name) = &self.name {
+            uri.push('#');
+            uri.push_str(&percent_encode(name));
+        }
+        uri
+    }
+
+    /// Parses a SIP008 subscription document
+    ///
+    /// Servers that need a plugin or use an unsupported method are skipped.
+    pub fn parse_sip008(json: &str) -> Result<Vec<Self>> {
+        let document: Sip008Document = serde_json::from_str(json).context("Invalid SIP008 document")?;
+        let mut configs = Vec::new();
+        for server in document.servers {
+            match server.to_config() {
+                Ok(config) => configs.push(config),
+                Err(e) => warn!("Skipping SIP008 server {}: {}", server.server, e),
+            }
+        }
+        Ok(configs)
+    }
+
+    /// Reads the config back from proxy settings
+    ///
+    /// `ProxySettings::username` carries the method and `password` the key.
+    pub fn from_settings(proxy: &ProxySettings) -> Result<Self> {
+        if proxy.proxy_type != ProxyType::Shadowsocks {
+            return Err(anyhow!("Not a Shadowsocks upstream"));
+        }
+        let server = proxy.host.clone().ok_or_else(|| anyhow!("Shadowsocks server not set"))?;
+        let port = proxy.port.ok_or_else(|| anyhow!("Shadowsocks port not set"))?;
+        let method = proxy.username.as_deref().ok_or_else(|| anyhow!("Shadowsocks method not set"))?;
+        let password = proxy.password.clone().ok_or_else(|| anyhow!("Shadowsocks password not set"))?;
+        Self::new(server, port, parse_cipher(method)?, password)
+    }
+
+    /// Proxy settings routing through this server
+    pub fn to_proxy_settings(&self) -> ProxySettings {
+        ProxySettings {
+            proxy_type: ProxyType::Shadowsocks,
+            host: Some(self.server.clone()),
+            port: Some(self.port),
+            username: Some(self.cipher.name().to_string()),
+            password: Some(self.password.clone()),
+            ..Default::default()
+        }
+    }
+
+    /// Pool entry for this server
+    pub fn to_free_proxy(&self, provider: &str) -> FreeProxy {
+        FreeProxy {
+            ip: self.server.clone(),
+            port: self.port,
+            protocol: ProxyType::Shadowsocks,
+            country: "Unknown".to_string(),
+            country_code: "XX".to_string(),
+            anonymity: "unknown".to_string(),
+            speed: 0,
+            uptime: 0.0,
+            last_checked: chrono::Utc::now().to_rfc3339(),
+            provider: provider.to_string(),
+            is_working: false,
+            username: Some(self.cipher.name().to_string()),
+            password: Some(self.password.clone()),
+            ssh: None,
+        }
+    }
+
+    /// Opens a tunnel to `target_host:target_port` through this server
+    ///
+    /// The encrypted stream is bridged through a loopback socket so callers can
+    /// treat it like any other proxy tunnel.
+    pub async fn connect(&self, target_host: &str, target_port: u16) -> Result<TcpStream> {
+        let master_key = self.cipher.master_key(&self.password)?;
+        let address = encode_address(target_host, target_port)?;
+        let mut upstream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((self.server.as_str(), self.port)))
+            .await
+            .map_err(|_| anyhow!("Connection to Shadowsocks server {}:{} timed out", self.server, self.port))?
+            .with_context(|| format!("Failed to connect to Shadowsocks server {}:{}", self.server, self.port))?;
+        upstream.set_nodelay(true)?;
+
+        let cipher = self.cipher;
+        let salt = random_salt(cipher);
+        let mut encryptor = Encryptor::new(cipher, &cipher.session_key(&master_key, &salt));
+        let mut request = salt.clone();
+        if cipher.is_2022() {
+            let padding_len = rand::thread_rng().gen_range(1..=MAX_PADDING);
+            let mut variable = address;
+            variable.extend_from_slice(&(padding_len as u16).to_be_bytes());
+            variable.resize(variable.len() + padding_len, 0);
+
+            let mut fixed = vec![0u8];
+            fixed.extend_from_slice(&unix_time().to_be_bytes());
+            fixed.extend_from_slice(&(variable.len() as u16).to_be_bytes());
+            request.extend(encryptor.seal(&fixed));
+            request.extend(encryptor.seal(&variable));
+        } else {
+            request.extend(encryptor.chunks(&address));
+        }
+        upstream.write_all(&request).await?;
+
+        let (client, local) = loopback_pair().await?;
+        let (upstream_read, upstream_write) = upstream.into_split();
+        let (local_read, local_write) = local.into_split();
+        let server = format!("{}:{}", self.server, self.port);
+
+        tokio::spawn(async move {
+            let uplink = pump_encrypt(local_read, encryptor, upstream_write);
+            let downlink = receive_response(upstream_read, cipher, master_key, salt, local_write);
+            let (up, down) = tokio::join!(uplink, downlink);
+            if let Err(e) = up.and(down) {
+                debug!("Shadowsocks tunnel via {} closed: {}", server, e);
+            }
+        });
+        Ok(client)
+    }
+}
+
+/// Reads the server's salt (and 2022 response header), then decrypts the rest of the stream
+async fn receive_response<R, W>(
+    mut upstream: R,
+    cipher: ShadowsocksCipher,
+    master_key: Vec<u8>,
+    request_salt: Vec<u8>,
+    mut local: W,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let salt = match read_salt(&mut upstream, cipher).await {
+        Ok(salt) => salt,
+        Err(e) if is_eof(&e) => return Err(anyhow!("Shadowsocks server closed the connection")),
+        Err(e) => return Err(e),
+    };
+    let mut decryptor = Decryptor::new(cipher, &cipher.session_key(&master_key, &salt));
+    if cipher.is_2022() {
+        let header = decryptor.open_exact(&mut upstream, 1 + 8 + request_salt.len() + 2).await?;
+        if header[0] != 1 {
+            return Err(anyhow!("Unexpected Shadowsocks response header type {}", header[0]));
+        }
+        check_timestamp(u64::from_be_bytes(header[1..9].try_into()?))?;
+        if header[9..9 + request_salt.len()] != request_salt[..] {
+            return Err(anyhow!("Shadowsocks response does not match the request"));
+        }
+        let length = u16::from_be_bytes([header[header.len() - 2], header[header.len() - 1]]) as usize;
+        if length > 0 {
+            let first = decryptor.open_exact(&mut upstream, length).await?;
+            local.write_all(&first).await?;
+        }
+    }
+    pump_decrypt(upstream, decryptor, local).await
+}
+
+/// SIP008 online configuration document
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Sip008Document {
+    #[serde(default = "sip008_version")]
+    pub version: u32,
+    pub servers: Vec<Sip008Server>,
+}
+
+fn sip008_version() -> u32 {
+    1
+}
+
+impl Sip008Document {
+    /// Document listing `configs`
+    pub fn from_configs(configs: &[ShadowsocksConfig]) -> Self {
+        Self {
+            version: sip008_version(),
+            servers: configs
+                .iter()
+                .map(|c| Sip008Server {
+                    id: None,
+                    remarks: c.name.clone(),
+                    server: c.server.clone(),
+                    server_port: c.port,
+                    password: c.password.clone(),
+                    method: c.cipher.name().to_string(),
+                    plugin: None,
+                    plugin_opts: None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// One server entry of a SIP008 document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sip008Server {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remarks: Option<String>,
+    pub server: String,
+    pub server_port: u16,
+    pub password: String,
+    pub method: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin_opts: Option<String>,
+}
+
+impl Sip008Server {
+    /// Converts to a config, rejecting plugin servers
+    pub fn to_config(&self) -> Result<ShadowsocksConfig> {
+        if self.plugin.as_deref().is_some_and(|p| !p.is_empty()) {
+            return Err(anyhow!("Shadowsocks plugins are not supported"));
+        }
+        let mut config = ShadowsocksConfig::new(&self.server, self.server_port, parse_cipher(&self.method)?, &self.password)?;
+        config.name = self.remarks.clone().filter(|r| !r.is_empty());
+        Ok(config)
+    }
+}
+
+pub(crate) fn parse_cipher(method: &str) -> Result<ShadowsocksCipher> {
+    ShadowsocksCipher::from_name(method).ok_or_else(|| anyhow!("Unsupported Shadowsocks method '{}'", method))
+}
+
+fn decode_base64(value: &str) -> Option<String> {
+    let value = value.trim().trim_end_matches('=');
+    let bytes = URL_SAFE_NO_PAD
+        .decode(value)
+        .or_else(|_| base64::engine::general_purpose::STANDARD_NO_PAD.decode(value))
+        .ok()?;
+    String::from_utf8(bytes).ok()
+}
+
+fn percent_decode(value: &str) -> String {
+    url::form_urlencoded::parse(format!("v={}", value.replace('+', "%2B")).as_bytes())
+        .next()
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_default()
+}
+
+fn percent_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes())
+        .collect::<String>()
+        .replace('+', "%20")
+}
+
+// ============================================================================
+// Test Server
+// ============================================================================
+
+/// Minimal Shadowsocks server relaying to any TCP target
+///
+/// Meant for verifying clients locally; it has no access control beyond the password.
+pub struct ShadowsocksServer {
+    local_addr: SocketAddr,
+    task: JoinHandle<()>,
+}
+
+impl ShadowsocksServer {
+    /// Starts serving on `bind` (use port 0 for an ephemeral port)
+    pub async fn start(cipher: ShadowsocksCipher, password: &str, bind: SocketAddr) -> Result<Self> {
+        let master_key = Arc::new(cipher.master_key(password)?);
+        let listener = TcpListener::bind(bind).await?;
+        let local_addr = listener.local_addr()?;
+        let seen_salts = Arc::new(Mutex::new(HashMap::new()));
+
+        let task = tokio::spawn(async move {
+            while let Ok((socket, peer)) = listener.accept().await {
+                let master_key = master_key.clone();
+                let seen_salts = seen_salts.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = serve_connection(socket, cipher, &master_key, &seen_salts).await {
+                        debug!("Shadowsocks connection from {} ended: {}", peer, e);
+                    }
+                });
+            }
+        });
+        Ok(Self { local_addr, task })
+    }
+
+    /// Address the server listens on
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// Stops accepting connections
+    pub fn stop(&self) {
+        self.task.abort();
+    }
+}
+
+impl Drop for ShadowsocksServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Records a request salt, false if it was already seen
+///
+/// Salts are kept with their header timestamp and dropped once it falls outside
+/// the allowed window, since a replay of that header fails `check_timestamp`.
+fn remember_salt(seen_salts: &Mutex<HashMap<Vec<u8>, u64>>, salt: &[u8], timestamp: u64) -> bool {
+    let mut seen = seen_salts.lock().unwrap_or_else(|e| e.into_inner());
+    let oldest = unix_time().saturating_sub(MAX_TIME_DIFF_SECS);
+    seen.retain(|_, seen_at| *seen_at >= oldest);
+    match seen.entry(salt.to_vec()) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(timestamp);
+            true
+        }
+    }
+}
+
+async fn serve_connection(
+    socket: TcpStream,
+    cipher: ShadowsocksCipher,
+    master_key: &[u8],
+    seen_salts: &Mutex<HashMap<Vec<u8>, u64>>,
+) -> Result<()> {
+    let (mut client_read, mut client_write) = socket.into_split();
+    let request_salt = read_salt(&mut client_read, cipher).await?;
+    let mut decryptor = Decryptor::new(cipher, &cipher.session_key(master_key, &request_salt));
+
+    let ((host, port), initial) = if cipher.is_2022() {
+        let fixed = decryptor.open_exact(&mut client_read, 11).await?;
+        if fixed[0] != 0 {
+            return Err(anyhow!("Unexpected request header type {}", fixed[0]));
+        }
+        let timestamp = u64::from_be_bytes(fixed[1..9].try_into()?);
+        check_timestamp(timestamp)?;
+        if !remember_salt(seen_salts, &request_salt, timestamp) {
+            return Err(anyhow!("Replayed request salt"));
+        }
+        let length = u16::from_be_bytes([fixed[9], fixed[10]]) as usize;
+        let variable = decryptor.open_exact(&mut client_read, length).await?;
+        let (target, offset) = decode_address(&variable)?;
+        let padding = variable
+            .get(offset..offset + 2)
+            .map(|p| u16::from_be_bytes([p[0], p[1]]) as usize)
+            .ok_or_else(|| anyhow!("Truncated request header"))?;
+        let payload_start = offset + 2 + padding;
+        if payload_start > variable.len() {
+            return Err(anyhow!("Truncated request padding"));
+        }
+        (target, variable[payload_start..].to_vec())
+    } else {
+        let first = decryptor
+            .read_chunk(&mut client_read)
+            .await?
+            .ok_or_else(|| anyhow!("Connection closed before the target address"))?;
+        let (target, offset) = decode_address(&first)?;
+        (target, first[offset..].to_vec())
+    };
+
+    let target = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect((host.as_str(), port)))
+        .await
+        .map_err(|_| anyhow!("Connection to {}:{} timed out", host, port))?
+        .with_context(|| format!("Failed to connect to {}:{}", host, port))?;
+    let (mut target_read, mut target_write) = target.into_split();
+    if !initial.is_empty() {
+        target_write.write_all(&initial).await?;
+    }
+
+    let response_salt = random_salt(cipher);
+    let mut encryptor = Encryptor::new(cipher, &cipher.session_key(master_key, &response_salt));
+    let downlink = async move {
+        let mut header = response_salt;
+        if cipher.is_2022() {
+            // The response header travels with the first payload chunk
+            let mut buffer = vec![0u8; MAX_CHUNK_2022];
+            let n = target_read.read(&mut buffer).await?;
+            let mut fixed = vec![1u8];
+            fixed.extend_from_slice(&unix_time().to_be_bytes());
+            fixed.extend_from_slice(&request_salt);
+            fixed.extend_from_slice(&(n as u16).to_be_bytes());
+            header.extend(encryptor.seal(&fixed));
+            if n > 0 {
+                header.extend(encryptor.seal(&buffer[..n]));
+            }
+        }
+        client_write.write_all(&header).await?;
+        pump_encrypt(target_read, encryptor, client_write).await
+    };
+    let uplink = pump_decrypt(client_read, decryptor, target_write);
+    let (up, down) = tokio::join!(uplink, downlink);
+    up.and(down)
+}
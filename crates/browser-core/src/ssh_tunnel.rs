@@ -0,0 +1,301 @@
+//! SSH Tunnel Module
+//!
+//! Uses plain SSH servers as upstream proxies without external `ssh -D` processes:
+//! - Password or private key authentication
+//! - Host key verification against a known_hosts file
+//! - One session per server, multiplexing every connection as a `direct-tcpip` channel
+//! - Automatic reconnection when a session drops, with connect and idle timeouts
+
+use anyhow::{anyhow, Context, Result};
+use russh::client::{self, Handle};
+use russh::keys::{check_known_hosts, check_known_hosts_path, load_secret_key, PrivateKeyWithHashAlg};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::local_proxy::loopback_pair;
+use crate::proxy::{ProxySettings, ProxyType};
+
+/// Default SSH port when `ProxySettings::port` is unset
+pub const DEFAULT_SSH_PORT: u16 = 22;
+
+/// Time allowed for the TCP connect, key exchange and authentication
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// Idle sessions are dropped after this long; the next tunnel reconnects
+const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Time allowed for the server to confirm a `direct-tcpip` channel
+const CHANNEL_OPEN_TIMEOUT: Duration = Duration::from_secs(15);
+
+static GLOBAL_POOL: LazyLock<SshTunnelPool> = LazyLock::new(SshTunnelPool::new);
+
+type SessionSlot = Arc<Mutex<Option<Arc<Handle<HostKeyVerifier>>>>>;
+
+/// SSH-specific settings carried by `ProxySettings`
+///
+/// Without `private_key_path`, `ProxySettings::password` is used for password authentication.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SshOptions {
+    #[serde(default)]
+    pub private_key_path: Option<PathBuf>,
+    #[serde(default)]
+    pub private_key_passphrase: Option<String>,
+    /// known_hosts file used to verify the server; `~/.ssh/known_hosts` when unset
+    #[serde(default)]
+    pub known_hosts_path: Option<PathBuf>,
+}
+
+/// Accepts the server only if its key is recorded in known_hosts
+struct HostKeyVerifier {
+    host: String,
+    port: u16,
+    known_hosts_path: Option<PathBuf>,
+}
+
+impl client::Handler for HostKeyVerifier {
+    type Error = russh::Error;
+
+    async fn check_server_key(&mut self, server_public_key: &russh::keys::PublicKey) -> Result<bool, Self::Error> {
+        let result = match &self.known_hosts_path {
+            Some(path) => check_known_hosts_path(&self.host, self.port, server_public_key, path),
+            None => check_known_hosts(&self.host, self.port, server_public_key),
+        };
+        match result {
+            Ok(true) => Ok(true),
+            Ok(false) => {
+                warn!("Host key for {}:{} is not in known_hosts", self.host, self.port);
+                Ok(false)
+            }
+            Err(e) => {
+                warn!("Host key check for {}:{} failed: {}", self.host, self.port, e);
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Connection details resolved from `ProxySettings`
+struct SshTarget {
+    host: String,
+    port: u16,
+    username: String,
+    password: Option<String>,
+    options: SshOptions,
+}
+
+impl SshTarget {
+    fn from_settings(proxy: &ProxySettings) -> Result<Self> {
+        if proxy.proxy_type != ProxyType::Ssh {
+            return Err(anyhow!("Not an SSH upstream"));
+        }
+        let host = proxy.host.clone().ok_or_else(|| anyhow!("SSH host not set"))?;
+        let username = proxy
+            .username
+            .clone()
+            .filter(|u| !u.is_empty())
+            .ok_or_else(|| anyhow!("SSH username not set"))?;
+        let options = proxy.ssh.clone().unwrap_or_default();
+        if options.private_key_path.is_none() && proxy.password.is_none() {
+            return Err(anyhow!("SSH upstream {} needs a password or private key", host));
+        }
+        Ok(Self {
+            port: proxy.port.unwrap_or(DEFAULT_SSH_PORT),
+            host,
+            username,
+            password: proxy.password.clone(),
+            options,
+        })
+    }
+
+    fn key(&self) -> String {
+        format!("{}@{}:{}", self.username, self.host, self.port)
+    }
+
+    async fn connect(&self) -> Result<Handle<HostKeyVerifier>> {
+        tokio::time::timeout(CONNECT_TIMEOUT, self.handshake())
+            .await
+            .map_err(|_| anyhow!("SSH connection to {} timed out", self.key()))?
+    }
+
+    async fn handshake(&self) -> Result<Handle<HostKeyVerifier>> {
+        let config = Arc::new(client::Config {
+            inactivity_timeout: Some(INACTIVITY_TIMEOUT),
+            keepalive_interval: Some(Duration::from_secs(30)),
+            keepalive_max: 3,
+            ..Default::default()
+        });
+        let verifier = HostKeyVerifier {
+            host: self.host.clone(),
+            port: self.port,
+            known_hosts_path: self.options.known_hosts_path.clone(),
+        };
+        let mut handle = client::connect(config, (self.host.as_str(), self.port), verifier)
+            .await
+            .with_context(|| format!("SSH connection to {}:{} failed", self.host, self.port))?;
+
+        let result = match &self.options.private_key_path {
+            Some(path) => {
+                let key = load_secret_key(path, self.options.private_key_passphrase.as_deref())
+                    .with_context(|| format!("Failed to load SSH key {}", path.display()))?;
+                let hash = handle.best_supported_rsa_hash().await?.flatten();
+                handle
+                    .authenticate_publickey(&self.username, PrivateKeyWithHashAlg::new(Arc::new(key), hash))
+                    .await?
+            }
+            None => {
+                handle
+                    .authenticate_password(&self.username, self.password.clone().unwrap_or_default())
+                    .await?
+            }
+        };
+        if !result.success() {
+            return Err(anyhow!("SSH authentication to {} failed", self.key()));
+        }
+
+        info!("SSH session established to {}", self.key());
+        Ok(handle)
+    }
+}
+
+/// Shared SSH sessions, one per server and user
+///
+/// Each server has its own slot, so a slow handshake only blocks tunnels to that server.
+pub struct SshTunnelPool {
+    sessions: std::sync::Mutex<HashMap<String, SessionSlot>>,
+}
+
+impl SshTunnelPool {
+    /// Create an empty pool
+    pub fn new() -> Self {
+        Self {
+            sessions: std::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Pool used by `establish_proxy_tunnel`
+    pub fn global() -> &'static SshTunnelPool {
+        &GLOBAL_POOL
+    }
+
+    fn slot(&self, key: String) -> SessionSlot {
+        self.sessions.lock().expect("SSH pool lock poisoned").entry(key).or_default().clone()
+    }
+
+    fn slots(&self) -> Vec<SessionSlot> {
+        self.sessions.lock().expect("SSH pool lock poisoned").values().cloned().collect()
+    }
+
+    /// The server's live session, connecting if there is none or it is `stale`
+    async fn session(
+        &self,
+        target: &SshTarget,
+        stale: Option<&Arc<Handle<HostKeyVerifier>>>,
+    ) -> Result<Arc<Handle<HostKeyVerifier>>> {
+        let slot = self.slot(target.key());
+        let mut session = slot.lock().await;
+        if let Some(handle) = session.as_ref().filter(|h| !h.is_closed()) {
+            // Another tunnel may already have replaced the stale session
+            if !stale.is_some_and(|stale| Arc::ptr_eq(stale, handle)) {
+                return Ok(handle.clone());
+            }
+        }
+        let handle = Arc::new(target.connect().await?);
+        *session = Some(handle.clone());
+        Ok(handle)
+    }
+
+    async fn open_direct(
+        handle: &Handle<HostKeyVerifier>,
+        target_host: &str,
+        target_port: u16,
+    ) -> Result<russh::Channel<client::Msg>> {
+        let open = handle.channel_open_direct_tcpip(target_host, target_port.into(), "127.0.0.1", 0);
+        Ok(tokio::time::timeout(CHANNEL_OPEN_TIMEOUT, open)
+            .await
+            .map_err(|_| anyhow!("SSH channel to {}:{} timed out", target_host, target_port))??)
+    }
+
+    /// Open a `direct-tcpip` channel to `target_host:target_port`
+    ///
+    /// Reuses the server's session, reconnecting once if the session has failed.
+    /// A channel the server refuses is an error for that tunnel only.
+    pub async fn open_channel(
+        &self,
+        proxy: &ProxySettings,
+        target_host: &str,
+        target_port: u16,
+    ) -> Result<russh::ChannelStream<client::Msg>> {
+        let target = SshTarget::from_settings(proxy)?;
+        let refused = || format!("SSH server {} refused tunnel to {}:{}", target.key(), target_host, target_port);
+
+        let handle = self.session(&target, None).await?;
+        let channel = match Self::open_direct(&handle, target_host, target_port).await {
+            Ok(channel) => channel,
+            Err(e) if !handle.is_closed() && is_channel_refusal(&e) => return Err(e.context(refused())),
+            Err(e) => {
+                debug!("Session to {} failed ({}), reconnecting", target.key(), e);
+                let handle = self.session(&target, Some(&handle)).await?;
+                Self::open_direct(&handle, target_host, target_port)
+                    .await
+                    .with_context(refused)?
+            }
+        };
+        Ok(channel.into_stream())
+    }
+
+    /// Open a tunnel to `target_host:target_port` as a local TCP stream
+    ///
+    /// The channel is bridged through a loopback socket so callers can treat it
+    /// like any other proxy tunnel.
+    pub async fn open_stream(&self, proxy: &ProxySettings, target_host: &str, target_port: u16) -> Result<TcpStream> {
+        let mut channel = self.open_channel(proxy, target_host, target_port).await?;
+        let (client, mut server) = loopback_pair().await?;
+
+        tokio::spawn(async move {
+            if let Err(e) = tokio::io::copy_bidirectional(&mut server, &mut channel).await {
+                debug!("SSH tunnel closed: {}", e);
+            }
+        });
+        Ok(client)
+    }
+
+    /// Number of live sessions
+    pub async fn session_count(&self) -> usize {
+        let mut count = 0;
+        for slot in self.slots() {
+            if slot.lock().await.as_ref().is_some_and(|h| !h.is_closed()) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Close every session
+    pub async fn disconnect_all(&self) {
+        let slots: Vec<_> = self.sessions.lock().expect("SSH pool lock poisoned").drain().map(|(_, s)| s).collect();
+        for slot in slots {
+            if let Some(handle) = slot.lock().await.take() {
+                let _ = handle
+                    .disconnect(russh::Disconnect::ByApplication, "", "en")
+                    .await;
+            }
+        }
+    }
+}
+
+/// Whether the server declined the channel itself, leaving the session usable
+fn is_channel_refusal(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<russh::Error>(), Some(russh::Error::ChannelOpenFailure(_)))
+}
+
+impl Default for SshTunnelPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -21,19 +21,304 @@ pub struct NetworkConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a TLSProfile.
+///
+/// Names are IANA/RFC names; see `tls_fingerprint` for the accepted set.
 pub struct TLSProfile {
     pub version: String,
     pub cipher_suites: Vec<String>,
+    /// ClientHello extensions in the order they are sent
     pub extensions: Vec<String>,
     pub ja3_hash: String,
+    #[serde(default)]
+    pub supported_groups: Vec<String>,
+    #[serde(default)]
+    pub signature_algorithms: Vec<String>,
+    #[serde(default)]
+    pub alpn: Vec<String>,
+    /// Certificate compression algorithms: "brotli", "zlib", "zstd"
+    #[serde(default)]
+    pub cert_compression: Vec<String>,
+    /// Insert GREASE values (RFC 8701)
+    #[serde(default)]
+    pub grease: bool,
+    /// Shuffle extensions per connection, as Chrome does; `extensions` order is then ignored
+    #[serde(default)]
+    pub permute_extensions: bool,
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+impl TLSProfile {
+    /// Chrome 13x (BoringSSL).
+    pub fn chrome() -> Self {
+        Self {
+            version: "TLS 1.3".to_string(),
+            cipher_suites: strings(&[
+                "TLS_AES_128_GCM_SHA256",
+                "TLS_AES_256_GCM_SHA384",
+                "TLS_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+                "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+                "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+                "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+                "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+                "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+                "TLS_RSA_WITH_AES_128_GCM_SHA256",
+                "TLS_RSA_WITH_AES_256_GCM_SHA384",
+                "TLS_RSA_WITH_AES_128_CBC_SHA",
+                "TLS_RSA_WITH_AES_256_CBC_SHA",
+            ]),
+            extensions: strings(&[
+                "server_name",
+                "extended_master_secret",
+                "renegotiation_info",
+                "supported_groups",
+                "ec_point_formats",
+                "session_ticket",
+                "application_layer_protocol_negotiation",
+                "status_request",
+                "signature_algorithms",
+                "signed_certificate_timestamp",
+                "key_share",
+                "psk_key_exchange_modes",
+                "supported_versions",
+                "compress_certificate",
+                "application_settings_new",
+                "encrypted_client_hello",
+            ]),
+            ja3_hash: String::new(),
+            supported_groups: strings(&["X25519MLKEM768", "X25519", "P-256", "P-384"]),
+            signature_algorithms: strings(&[
+                "ecdsa_secp256r1_sha256",
+                "rsa_pss_rsae_sha256",
+                "rsa_pkcs1_sha256",
+                "ecdsa_secp384r1_sha384",
+                "rsa_pss_rsae_sha384",
+                "rsa_pkcs1_sha384",
+                "rsa_pss_rsae_sha512",
+                "rsa_pkcs1_sha512",
+            ]),
+            alpn: strings(&["h2", "http/1.1"]),
+            cert_compression: strings(&["brotli"]),
+            grease: true,
+            permute_extensions: true,
+        }
+    }
+
+    /// Firefox 13x (NSS), as far as BoringSSL can reproduce it.
+    pub fn firefox() -> Self {
+        Self {
+            version: "TLS 1.3".to_string(),
+            cipher_suites: strings(&[
+                "TLS_AES_128_GCM_SHA256",
+                "TLS_CHACHA20_POLY1305_SHA256",
+                "TLS_AES_256_GCM_SHA384",
+                "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+                "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+                "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+                "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+                "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA",
+                "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA",
+                "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+                "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+                "TLS_RSA_WITH_AES_128_GCM_SHA256",
+                "TLS_RSA_WITH_AES_256_GCM_SHA384",
+                "TLS_RSA_WITH_AES_128_CBC_SHA",
+                "TLS_RSA_WITH_AES_256_CBC_SHA",
+            ]),
+            extensions: strings(&[
+                "server_name",
+                "extended_master_secret",
+                "renegotiation_info",
+                "supported_groups",
+                "ec_point_formats",
+                "session_ticket",
+                "application_layer_protocol_negotiation",
+                "status_request",
+                "delegated_credentials",
+                "key_share",
+                "supported_versions",
+                "signature_algorithms",
+                "psk_key_exchange_modes",
+                "record_size_limit",
+                "compress_certificate",
+                "encrypted_client_hello",
+            ]),
+            ja3_hash: String::new(),
+            supported_groups: strings(&["X25519MLKEM768", "X25519", "P-256", "P-384", "P-521"]),
+            signature_algorithms: strings(&[
+                "ecdsa_secp256r1_sha256",
+                "ecdsa_secp384r1_sha384",
+                "ecdsa_secp521r1_sha512",
+                "rsa_pss_rsae_sha256",
+                "rsa_pss_rsae_sha384",
+                "rsa_pss_rsae_sha512",
+                "rsa_pkcs1_sha256",
+                "rsa_pkcs1_sha384",
+                "rsa_pkcs1_sha512",
+                "ecdsa_sha1",
+                "rsa_pkcs1_sha1",
+            ]),
+            alpn: strings(&["h2", "http/1.1"]),
+            cert_compression: strings(&["zlib", "brotli", "zstd"]),
+            grease: false,
+            permute_extensions: false,
+        }
+    }
+
+    /// Safari 18 (Apple BoringSSL fork).
+    pub fn safari() -> Self {
+        Self {
+            version: "TLS 1.3".to_string(),
+            cipher_suites: strings(&[
+                "TLS_AES_128_GCM_SHA256",
+                "TLS_AES_256_GCM_SHA384",
+                "TLS_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384",
+                "TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256",
+                "TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384",
+                "TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256",
+                "TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256",
+                "TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA",
+                "TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA",
+                "TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA",
+                "TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA",
+                "TLS_RSA_WITH_AES_256_GCM_SHA384",
+                "TLS_RSA_WITH_AES_128_GCM_SHA256",
+                "TLS_RSA_WITH_AES_256_CBC_SHA",
+                "TLS_RSA_WITH_AES_128_CBC_SHA",
+            ]),
+            extensions: strings(&[
+                "server_name",
+                "extended_master_secret",
+                "renegotiation_info",
+                "supported_groups",
+                "ec_point_formats",
+                "application_layer_protocol_negotiation",
+                "status_request",
+                "signature_algorithms",
+                "signed_certificate_timestamp",
+                "key_share",
+                "psk_key_exchange_modes",
+                "supported_versions",
+                "compress_certificate",
+            ]),
+            ja3_hash: String::new(),
+            supported_groups: strings(&["X25519", "P-256", "P-384", "P-521"]),
+            signature_algorithms: strings(&[
+                "ecdsa_secp256r1_sha256",
+                "rsa_pss_rsae_sha256",
+                "rsa_pkcs1_sha256",
+                "ecdsa_secp384r1_sha384",
+                "rsa_pss_rsae_sha384",
+                "rsa_pkcs1_sha384",
+                "rsa_pss_rsae_sha512",
+                "rsa_pkcs1_sha512",
+                "rsa_pkcs1_sha1",
+            ]),
+            alpn: strings(&["h2", "http/1.1"]),
+            cert_compression: strings(&["zlib"]),
+            grease: true,
+            permute_extensions: false,
+        }
+    }
+
+    /// Profile of the browser a user agent claims to be; Chromium-based otherwise.
+    pub fn for_user_agent(user_agent: &str) -> Self {
+        match browser_family(user_agent) {
+            BrowserFamily::Firefox => Self::firefox(),
+            BrowserFamily::Safari => Self::safari(),
+            BrowserFamily::Chromium => Self::chrome(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a HTTP2Settings.
 pub struct HTTP2Settings {
+    /// (SETTINGS id, value) in the order sent
     pub settings_frame: Vec<(u32, u32)>,
+    /// Connection WINDOW_UPDATE increment sent after SETTINGS; 0 sends none
     pub window_update: u32,
+    /// PRIORITY frames sent after the preface, as (stream id, weight 1-256) depending on stream 0
     pub priority: Vec<(u32, u32)>,
+    /// Pseudo-header order as letters, e.g. m, a, s, p
+    #[serde(default = "default_pseudo_header_order")]
+    pub pseudo_header_order: Vec<String>,
+    /// Priority on request HEADERS as (depends on, weight 1-256, exclusive)
+    #[serde(default)]
+    pub headers_priority: Option<(u32, u32, bool)>,
+}
+
+fn default_pseudo_header_order() -> Vec<String> {
+    strings(&["m", "a", "s", "p"])
+}
+
+impl HTTP2Settings {
+    /// Chrome 13x.
+    pub fn chrome() -> Self {
+        Self {
+            settings_frame: vec![(1, 65536), (2, 0), (4, 6291456), (6, 262144)],
+            window_update: 15663105,
+            priority: vec![],
+            pseudo_header_order: strings(&["m", "a", "s", "p"]),
+            headers_priority: Some((0, 256, true)),
+        }
+    }
+
+    /// Firefox 13x.
+    pub fn firefox() -> Self {
+        Self {
+            settings_frame: vec![(1, 65536), (2, 0), (4, 131072), (5, 16384)],
+            window_update: 12517377,
+            priority: vec![],
+            pseudo_header_order: strings(&["m", "p", "a", "s"]),
+            headers_priority: Some((0, 42, false)),
+        }
+    }
+
+    /// Safari 18.
+    pub fn safari() -> Self {
+        Self {
+            settings_frame: vec![(2, 0), (3, 100), (4, 2097152), (9, 1)],
+            window_update: 10420225,
+            priority: vec![],
+            pseudo_header_order: strings(&["m", "s", "a", "p"]),
+            headers_priority: None,
+        }
+    }
+
+    /// Settings of the browser a user agent claims to be; Chromium-based otherwise.
+    pub fn for_user_agent(user_agent: &str) -> Self {
+        match browser_family(user_agent) {
+            BrowserFamily::Firefox => Self::firefox(),
+            BrowserFamily::Safari => Self::safari(),
+            BrowserFamily::Chromium => Self::chrome(),
+        }
+    }
+}
+
+enum BrowserFamily {
+    Chromium,
+    Firefox,
+    Safari,
+}
+
+fn browser_family(user_agent: &str) -> BrowserFamily {
+    if user_agent.contains("Firefox/") {
+        BrowserFamily::Firefox
+    } else if user_agent.contains("Safari/") && !user_agent.contains("Chrome/") && !user_agent.contains("Chromium/") {
+        BrowserFamily::Safari
+    } else {
+        BrowserFamily::Chromium
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
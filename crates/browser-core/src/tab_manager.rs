@@ -5,21 +5,111 @@
 //! - Tab state persistence
 //! - Multi-tab coordination
 //! - Resource management per tab
+//! - Binding virtual IPs to verified exit proxies from the pool
 
-use crate::fingerprint::BrowserFingerprint;
+use crate::fingerprint::{BrowserFingerprint, FingerprintGenerator, FingerprintRequest};
+use crate::http_client::{HttpClient, PublicIpDetector, PublicIpInfo};
+use crate::proxy::FreeProxy;
 use crate::tab_isolation::{NetworkConfig, TabProfile, TabStatus, TLSProfile, HTTP2Settings, TCPFingerprint};
 use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use rand::seq::SliceRandom;
 use std::collections::HashMap;
-use std::time::SystemTime;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::sync::RwLock;
 use uuid::Uuid;
-use virtual_ip::{IPGenerator, VirtualIP};
-use tracing::{info, debug};
+use virtual_ip::{GeoIpDatabase, IPGenerator, IpRangeIndex, VirtualIP};
+use tracing::{info, debug, warn};
+
+#[async_trait]
+/// Trait defining how a proxy's exit is observed.
+pub trait ExitVerifier: Send + Sync {
+    /// Detect the public IP seen by servers when connecting through `proxy`.
+    async fn verify_exit(&self, proxy: &FreeProxy) -> Result<PublicIpInfo>;
+}
+
+/// Observes exits with `PublicIpDetector` routed through the proxy.
+#[derive(Default)]
+pub struct PublicIpExitVerifier {
+    geoip_database: Option<Arc<GeoIpDatabase>>,
+    range_index: Option<Arc<IpRangeIndex>>,
+}
+
+impl PublicIpExitVerifier {
+    /// Creates a new verifier.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resolve exit locations from a local GeoIP database.
+    pub fn with_geoip_database(mut self, database: Arc<GeoIpDatabase>) -> Self {
+        self.geoip_database = Some(database);
+        self
+    }
+
+    /// Resolve exit countries from an IP range index when the detection
+    /// services and GeoIP database report none.
+    pub fn with_range_index(mut self, index: Arc<IpRangeIndex>) -> Self {
+        self.range_index = Some(index);
+        self
+    }
+}
+
+#[async_trait]
+impl ExitVerifier for PublicIpExitVerifier {
+    async fn verify_exit(&self, proxy: &FreeProxy) -> Result<PublicIpInfo> {
+        let mut detector = PublicIpDetector::with_proxy(&proxy.to_proxy_settings())?;
+        if let Some(database) = &self.geoip_database {
+            detector = detector.with_geoip_database(database.clone());
+        }
+        let mut info = detector.detect_ip().await?;
+        if info.country_code.is_none() {
+            if let Some((country, _)) = self.range_index.as_ref().and_then(|index| index.lookup_str(&info.ip)) {
+                info.country = Some(country.name.clone());
+                info.country_code = Some(country.code.clone());
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// Configuration for binding virtual IPs to exit proxies
+#[derive(Debug, Clone)]
+pub struct ExitBindingConfig {
+    /// Fail instead of keeping the generated IP when no proxy verifies
+    pub require_exit: bool,
+    /// Proxies tried per request before giving up
+    pub max_attempts: usize,
+    /// Time allowed for each exit check
+    pub verify_timeout: Duration,
+}
+
+impl Default for ExitBindingConfig {
+    fn default() -> Self {
+        Self {
+            require_exit: false,
+            max_attempts: 3,
+            verify_timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+/// The proxy pool and verifier used to back virtual IPs with real exits.
+struct ExitBinder {
+    pool: Arc<RwLock<Vec<FreeProxy>>>,
+    verifier: Arc<dyn ExitVerifier>,
+    config: ExitBindingConfig,
+}
 
 /// Represents a TabIPManager.
 pub struct TabIPManager {
     tabs: RwLock<HashMap<String, TabProfile>>,
     ip_generator: IPGenerator,
+    fingerprint_generator: FingerprintGenerator,
+    exit_binder: Option<ExitBinder>,
+    exit_proxies: RwLock<HashMap<String, FreeProxy>>,
 }
 
 impl TabIPManager {
@@ -28,15 +118,42 @@ impl TabIPManager {
         Self {
             tabs: RwLock::new(HashMap::new()),
             ip_generator,
+            fingerprint_generator: FingerprintGenerator::new(),
+            exit_binder: None,
+            exit_proxies: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Sample tab fingerprints with `generator` instead of the bundled dataset
+    pub fn with_fingerprint_generator(mut self, generator: FingerprintGenerator) -> Self {
+        self.fingerprint_generator = generator;
+        self
+    }
+
+    /// Route tabs through working proxies from `pool`, checking each exit
+    /// with `PublicIpDetector`
+    pub fn with_proxy_pool(self, pool: Arc<RwLock<Vec<FreeProxy>>>) -> Self {
+        self.with_exit_verifier(pool, Arc::new(PublicIpExitVerifier::new()), ExitBindingConfig::default())
+    }
+
+    /// Route tabs through working proxies from `pool`, checking exits with `verifier`
+    pub fn with_exit_verifier(
+        mut self,
+        pool: Arc<RwLock<Vec<FreeProxy>>>,
+        verifier: Arc<dyn ExitVerifier>,
+        config: ExitBindingConfig,
+    ) -> Self {
+        self.exit_binder = Some(ExitBinder { pool, verifier, config });
+        self
+    }
+
     /// Create new tab with IP from specific country
     pub async fn create_tab(&self, country_code: &str) -> Result<TabProfile> {
         let tab_id = Uuid::new_v4().to_string();
-        let virtual_ip = self.ip_generator.generate_for_country(country_code)?;
-        let fingerprint = self.generate_matching_fingerprint(&virtual_ip)?;
-        let network_config = self.create_network_config(&virtual_ip)?;
+        let requested = self.ip_generator.generate_for_country(country_code)?;
+        let (virtual_ip, exit_proxy) = self.bind_exit(requested).await?;
+        let fingerprint = self.generate_matching_fingerprint(&tab_id, &virtual_ip)?;
+        let network_config = self.create_network_config(&virtual_ip, &fingerprint)?;
 
         let tab_profile = TabProfile {
             tab_id: tab_id.clone(),
@@ -55,6 +172,7 @@ impl TabIPManager {
             .write()
             .await
             .insert(tab_id.clone(), tab_profile.clone());
+        self.set_exit_proxy(&tab_id, exit_proxy).await;
 
         info!("Created tab {} for country {}", tab_id, country_code);
         Ok(tab_profile)
@@ -68,27 +186,110 @@ impl TabIPManager {
 
     /// Rotate IP for existing tab
     pub async fn rotate_ip(&self, tab_id: &str, new_country_code: Option<&str>) -> Result<VirtualIP> {
+        let current_country = self
+            .tabs
+            .read()
+            .await
+            .get(tab_id)
+            .map(|tab| tab.virtual_ip.country_code.clone())
+            .ok_or_else(|| anyhow!("Tab not found"))?;
+
+        // Verify the new exit without holding the tab lock
+        let requested = self
+            .ip_generator
+            .generate_for_country(new_country_code.unwrap_or(&current_country))?;
+        let (new_ip, exit_proxy) = self.bind_exit(requested).await?;
+
         let mut tabs = self.tabs.write().await;
         let tab = tabs
             .get_mut(tab_id)
             .ok_or_else(|| anyhow!("Tab not found"))?;
-
-        let new_ip = if let Some(country) = new_country_code {
-            self.ip_generator.generate_for_country(country)?
-        } else {
-            self.ip_generator
-                .generate_for_country(&tab.virtual_ip.country_code)?
-        };
-
         tab.virtual_ip = new_ip.clone();
-        tab.fingerprint = self.generate_matching_fingerprint(&new_ip)?;
-        tab.network_config = self.create_network_config(&new_ip)?;
+        tab.fingerprint = self.generate_matching_fingerprint(tab_id, &new_ip)?;
+        tab.network_config = self.create_network_config(&new_ip, &tab.fingerprint)?;
         tab.last_active = SystemTime::now();
+        drop(tabs);
+        self.set_exit_proxy(tab_id, exit_proxy).await;
 
         info!("Rotated IP for tab {} to {}", tab_id, new_ip.ip);
         Ok(new_ip)
     }
 
+    /// The proxy carrying a tab's traffic, if its virtual IP is bound to one
+    pub async fn exit_proxy(&self, tab_id: &str) -> Option<FreeProxy> {
+        self.exit_proxies.read().await.get(tab_id).cloned()
+    }
+
+    /// HTTP client that sends the tab's TLS and HTTP/2 fingerprint through its exit proxy
+    pub async fn http_client(&self, tab_id: &str) -> Result<HttpClient> {
+        let tab = self.get_tab(tab_id).await.ok_or_else(|| anyhow!("Tab not found"))?;
+        let proxy = self.exit_proxy(tab_id).await.map(|p| p.to_proxy_settings());
+        HttpClient::for_tab(&tab, proxy.as_ref())
+    }
+
+    async fn set_exit_proxy(&self, tab_id: &str, proxy: Option<FreeProxy>) {
+        let mut exits = self.exit_proxies.write().await;
+        match proxy {
+            Some(proxy) => exits.insert(tab_id.to_string(), proxy),
+            None => exits.remove(tab_id),
+        };
+    }
+
+    /// Back a generated virtual IP with a working proxy in the same country.
+    ///
+    /// The first proxy whose observed exit is in the requested country fills
+    /// the IP, city, region, ISP and timezone. Proxies that fail the check are
+    /// marked as not working. Without a match the generated IP is returned
+    /// unverified, or an error when `require_exit` is set.
+    async fn bind_exit(&self, requested: VirtualIP) -> Result<(VirtualIP, Option<FreeProxy>)> {
+        let Some(binder) = &self.exit_binder else {
+            return Ok((requested, None));
+        };
+
+        let mut candidates: Vec<FreeProxy> = binder
+            .pool
+            .read()
+            .await
+            .iter()
+            .filter(|p| p.is_working && p.country_code.eq_ignore_ascii_case(&requested.country_code))
+            .filter(|p| !p.to_proxy_settings().is_tunnel_protocol())
+            .cloned()
+            .collect();
+        candidates.shuffle(&mut rand::thread_rng());
+
+        for proxy in candidates.into_iter().take(binder.config.max_attempts.max(1)) {
+            let observed = tokio::time::timeout(binder.config.verify_timeout, binder.verifier.verify_exit(&proxy))
+                .await
+                .map_err(|_| anyhow!("Exit check timed out"))
+                .and_then(|result| result);
+            match observed {
+                Ok(info) => match apply_observed_exit(&requested, &proxy, info) {
+                    Ok(bound) => {
+                        info!("Bound {} virtual IP to exit {} via {}:{}", bound.country_code, bound.ip, proxy.ip, proxy.port);
+                        return Ok((bound, Some(proxy)));
+                    }
+                    Err(e) => debug!("Proxy {}:{} rejected: {}", proxy.ip, proxy.port, e),
+                },
+                Err(e) => {
+                    debug!("Exit check through {}:{} failed: {}", proxy.ip, proxy.port, e);
+                    let mut pool = binder.pool.write().await;
+                    if let Some(entry) = pool.iter_mut().find(|p| p.ip == proxy.ip && p.port == proxy.port) {
+                        entry.is_working = false;
+                    }
+                }
+            }
+        }
+
+        if binder.config.require_exit {
+            return Err(anyhow!("No working proxy with a verified exit in {}", requested.country_code));
+        }
+        warn!(
+            "No verified exit proxy in {}; tab uses unverified generated IP {}",
+            requested.country_code, requested.ip
+        );
+        Ok((requested, None))
+    }
+
     /// Get tab by ID
     pub async fn get_tab(&self, tab_id: &str) -> Option<TabProfile> {
         self.tabs.read().await.get(tab_id).cloned()
@@ -103,6 +304,7 @@ impl TabIPManager {
     pub async fn close_tab(&self, tab_id: &str) -> Result<()> {
         // Remove from in-memory cache
         self.tabs.write().await.remove(tab_id);
+        self.exit_proxies.write().await.remove(tab_id);
         
         info!("Tab {} closed successfully", tab_id);
         Ok(())
@@ -120,46 +322,22 @@ impl TabIPManager {
         Ok(())
     }
 
-    fn generate_matching_fingerprint(&self, ip: &VirtualIP) -> Result<BrowserFingerprint> {
-        Ok(BrowserFingerprint {
-            user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36".to_string(),
-            accept_language: ip.language.clone(),
-            timezone: ip.timezone.clone(),
-            screen_resolution: (1920, 1080),
-            color_depth: 24,
-            hardware_concurrency: 8,
-            device_memory: 8,
-            platform: "Win32".to_string(),
-            webgl_vendor: "Google Inc.".to_string(),
-            webgl_renderer: "ANGLE (Intel HD Graphics)".to_string(),
-            canvas_hash: self.generate_canvas_hash(),
-            audio_hash: self.generate_audio_hash(),
-        })
+    /// Fingerprint seeded by the tab ID, so a tab in a given country keeps its device
+    ///
+    /// Tabs render in Chromium, so only Blink browsers are drawn: a Firefox or
+    /// Safari User-Agent on a Blink engine is easy to spot.
+    fn generate_matching_fingerprint(&self, tab_id: &str, ip: &VirtualIP) -> Result<BrowserFingerprint> {
+        self.fingerprint_generator
+            .generate(&FingerprintRequest::for_profile(tab_id).with_engine("blink").with_virtual_ip(ip))
     }
 
-    fn create_network_config(&self, _ip: &VirtualIP) -> Result<NetworkConfig> {
+    /// Network config whose TLS and HTTP/2 fingerprint match the tab's browser
+    fn create_network_config(&self, ip: &VirtualIP, fingerprint: &BrowserFingerprint) -> Result<NetworkConfig> {
         Ok(NetworkConfig {
             dns_servers: vec!["1.1.1.1".to_string(), "8.8.8.8".to_string()],
-            proxy_url: None,
-            tls_profile: TLSProfile {
-                version: "TLS 1.3".to_string(),
-                cipher_suites: vec![
-                    "TLS_AES_128_GCM_SHA256".to_string(),
-                    "TLS_AES_256_GCM_SHA384".to_string(),
-                    "TLS_CHACHA20_POLY1305_SHA256".to_string(),
-                ],
-                extensions: vec![
-                    "server_name".to_string(),
-                    "supported_versions".to_string(),
-                    "key_share".to_string(),
-                ],
-                ja3_hash: "".to_string(),
-            },
-            http2_settings: HTTP2Settings {
-                settings_frame: vec![(1, 65536), (2, 0), (3, 1000), (4, 6291456), (6, 262144)],
-                window_update: 15663105,
-                priority: vec![],
-            },
+            proxy_url: ip.proxy_url.clone(),
+            tls_profile: TLSProfile::for_user_agent(&fingerprint.user_agent),
+            http2_settings: HTTP2Settings::for_user_agent(&fingerprint.user_agent),
             tcp_fingerprint: TCPFingerprint {
                 ttl: 64,
                 window_size: 65535,
@@ -167,16 +345,37 @@ impl TabIPManager {
             },
         })
     }
+}
 
-    fn generate_canvas_hash(&self) -> String {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        format!("{:x}", rng.gen::<u64>())
+/// Fill a requested virtual IP with what was observed through `proxy`.
+///
+/// An exit whose country could not be determined is not verified.
+fn apply_observed_exit(requested: &VirtualIP, proxy: &FreeProxy, info: PublicIpInfo) -> Result<VirtualIP> {
+    let code = info
+        .country_code
+        .as_deref()
+        .ok_or_else(|| anyhow!("exit {} has no known country", info.ip))?;
+    if !code.eq_ignore_ascii_case(&requested.country_code) {
+        return Err(anyhow!("exit is in {}, not {}", code, requested.country_code));
     }
+    let ip: IpAddr = info
+        .ip
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("exit {} is not an IP address", info.ip))?;
 
-    fn generate_audio_hash(&self) -> String {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-        format!("{:x}", rng.gen::<u64>())
+    let mut bound = requested.clone();
+    bound.ip = ip;
+    bound.proxy_url = proxy.to_proxy_settings().to_url();
+    bound.exit_verified = true;
+    // Generated location details would contradict the observed exit
+    bound.city = info.city.unwrap_or_else(|| "Unknown".into());
+    bound.region = info.region.unwrap_or_else(|| "Unknown".into());
+    if let Some(isp) = info.isp {
+        bound.isp = isp;
+    }
+    if let Some(timezone) = info.timezone {
+        bound.timezone = timezone;
     }
+    Ok(bound)
 }
@@ -0,0 +1,152 @@
+//! Fingerprint Injection into Child Targets
+//!
+//! Out-of-process iframes and workers have their own DevTools targets, which a
+//! page's `Page.addScriptToEvaluateOnNewDocument` never reaches. chromiumoxide
+//! resumes auto-attached targets as soon as they attach, so a `TargetInjector`
+//! keeps its own connection to the page: its `Target.setAutoAttach` with
+//! `waitForDebuggerOnStart` holds each new iframe or worker until the tab's
+//! fingerprint script is in place, with site isolation left on.
+
+use anyhow::{anyhow, Result};
+use futures_util::{SinkExt, StreamExt};
+use parking_lot::RwLock;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tracing::{debug, warn};
+
+type DevToolsSocket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Time allowed for attaching to a page
+const ATTACH_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Applies a fingerprint script to the iframes and workers a page starts.
+pub struct TargetInjector {
+    target_id: String,
+    script: Arc<RwLock<String>>,
+    task: JoinHandle<()>,
+}
+
+impl TargetInjector {
+    /// Attach to the page `target_id` through the browser's DevTools endpoint.
+    pub async fn attach(websocket_url: &str, target_id: &str, script: String) -> Result<Self> {
+        let (mut socket, _) = tokio::time::timeout(ATTACH_TIMEOUT, tokio_tungstenite::connect_async(websocket_url))
+            .await
+            .map_err(|_| anyhow!("Timed out connecting to DevTools"))?
+            .map_err(|e| anyhow!("Failed to connect to DevTools: {}", e))?;
+
+        send(&mut socket, 1, None, "Target.attachToTarget", json!({ "targetId": target_id, "flatten": true })).await?;
+        let session = tokio::time::timeout(ATTACH_TIMEOUT, attached_session(&mut socket))
+            .await
+            .map_err(|_| anyhow!("Timed out attaching to target {}", target_id))??;
+        send(&mut socket, 2, Some(&session), "Target.setAutoAttach", auto_attach()).await?;
+
+        let script = Arc::new(RwLock::new(script));
+        let task = tokio::spawn(run(socket, session, script.clone()));
+        Ok(Self {
+            target_id: target_id.to_string(),
+            script,
+            task,
+        })
+    }
+
+    /// The page this injector is attached to
+    pub fn target_id(&self) -> &str {
+        &self.target_id
+    }
+
+    /// Script for targets that attach from now on
+    pub fn set_script(&self, script: String) {
+        *self.script.write() = script;
+    }
+}
+
+impl Drop for TargetInjector {
+    fn drop(&mut self) {
+        // Closing the connection releases any target still waiting on it
+        self.task.abort();
+    }
+}
+
+fn auto_attach() -> Value {
+    json!({ "autoAttach": true, "waitForDebuggerOnStart": true, "flatten": true })
+}
+
+async fn send(socket: &mut DevToolsSocket, id: u64, session: Option<&str>, method: &str, params: Value) -> Result<()> {
+    let mut message = json!({ "id": id, "method": method, "params": params });
+    if let Some(session) = session {
+        message["sessionId"] = json!(session);
+    }
+    socket
+        .send(Message::Text(message.to_string()))
+        .await
+        .map_err(|e| anyhow!("Failed to send {}: {}", method, e))
+}
+
+/// Session id from the reply to `Target.attachToTarget`.
+async fn attached_session(socket: &mut DevToolsSocket) -> Result<String> {
+    while let Some(message) = socket.next().await {
+        let Message::Text(text) = message? else { continue };
+        let reply: Value = serde_json::from_str(&text)?;
+        if reply["id"] != 1 {
+            continue;
+        }
+        if let Some(error) = reply.get("error") {
+            return Err(anyhow!("Failed to attach to target: {}", error["message"]));
+        }
+        return reply["result"]["sessionId"]
+            .as_str()
+            .map(str::to_string)
+            .ok_or_else(|| anyhow!("Attach reply has no session id"));
+    }
+    Err(anyhow!("DevTools connection closed"))
+}
+
+async fn run(mut socket: DevToolsSocket, page_session: String, script: Arc<RwLock<String>>) {
+    let mut next_id = 3;
+    while let Some(Ok(message)) = socket.next().await {
+        let Message::Text(text) = message else { continue };
+        let Ok(event) = serde_json::from_str::<Value>(&text) else { continue };
+        if let Some(error) = event.get("error") {
+            warn!("DevTools command {} failed: {}", event["id"], error["message"]);
+            continue;
+        }
+        let params = &event["params"];
+        match event["method"].as_str() {
+            Some("Target.attachedToTarget") => {
+                let (Some(session), Some(kind)) = (params["sessionId"].as_str(), params["targetInfo"]["type"].as_str()) else {
+                    continue;
+                };
+                let source = script.read().clone();
+                let mut commands = match kind {
+                    // Nested frames and the frame's own workers attach through its session
+                    "iframe" => vec![
+                        ("Page.addScriptToEvaluateOnNewDocument", json!({ "source": source })),
+                        ("Target.setAutoAttach", auto_attach()),
+                    ],
+                    "worker" | "shared_worker" | "service_worker" => {
+                        vec![("Runtime.evaluate", json!({ "expression": source }))]
+                    }
+                    _ => Vec::new(),
+                };
+                if params["waitingForDebugger"].as_bool() == Some(true) {
+                    commands.push(("Runtime.runIfWaitingForDebugger", json!({})));
+                }
+                debug!("Injecting fingerprint into {} target {}", kind, params["targetInfo"]["url"]);
+                // A session runs its commands in order, so the script is in place before the target resumes
+                for (method, params) in commands {
+                    if let Err(e) = send(&mut socket, next_id, Some(session), method, params).await {
+                        warn!("{}", e);
+                    }
+                    next_id += 1;
+                }
+            }
+            Some("Target.detachedFromTarget") if params["sessionId"] == page_session.as_str() => break,
+            _ => {}
+        }
+    }
+}
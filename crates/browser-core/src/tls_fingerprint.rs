@@ -0,0 +1,696 @@
+//! TLS and HTTP/2 Fingerprint Module
+//!
+//! Translates `TLSProfile` and `HTTP2Settings` into wire-level parameters and
+//! computes the fingerprints servers derive from a connection:
+//! - JA3 and JA4 from a raw TLS ClientHello
+//! - The Akamai HTTP/2 fingerprint from a raw client connection preface
+//! - HTTP/2 frame encoding shared with the impersonating client
+
+use anyhow::{anyhow, Result};
+use md5::{Digest as _, Md5};
+use sha2::Sha256;
+
+use crate::tab_isolation::{HTTP2Settings, TLSProfile};
+
+// ============================================================================
+// Code Point Tables
+// ============================================================================
+
+/// Cipher suites as (IANA name, BoringSSL name, code point).
+///
+/// TLS 1.3 suites have no BoringSSL name: BoringSSL always offers them, in
+/// the same order Chrome does.
+const CIPHER_SUITES: &[(&str, &str, u16)] = &[
+    ("TLS_AES_128_GCM_SHA256", "", 0x1301),
+    ("TLS_AES_256_GCM_SHA384", "", 0x1302),
+    ("TLS_CHACHA20_POLY1305_SHA256", "", 0x1303),
+    ("TLS_ECDHE_ECDSA_WITH_AES_128_GCM_SHA256", "ECDHE-ECDSA-AES128-GCM-SHA256", 0xc02b),
+    ("TLS_ECDHE_RSA_WITH_AES_128_GCM_SHA256", "ECDHE-RSA-AES128-GCM-SHA256", 0xc02f),
+    ("TLS_ECDHE_ECDSA_WITH_AES_256_GCM_SHA384", "ECDHE-ECDSA-AES256-GCM-SHA384", 0xc02c),
+    ("TLS_ECDHE_RSA_WITH_AES_256_GCM_SHA384", "ECDHE-RSA-AES256-GCM-SHA384", 0xc030),
+    ("TLS_ECDHE_ECDSA_WITH_CHACHA20_POLY1305_SHA256", "ECDHE-ECDSA-CHACHA20-POLY1305", 0xcca9),
+    ("TLS_ECDHE_RSA_WITH_CHACHA20_POLY1305_SHA256", "ECDHE-RSA-CHACHA20-POLY1305", 0xcca8),
+    ("TLS_ECDHE_ECDSA_WITH_AES_128_CBC_SHA", "ECDHE-ECDSA-AES128-SHA", 0xc009),
+    ("TLS_ECDHE_ECDSA_WITH_AES_256_CBC_SHA", "ECDHE-ECDSA-AES256-SHA", 0xc00a),
+    ("TLS_ECDHE_RSA_WITH_AES_128_CBC_SHA", "ECDHE-RSA-AES128-SHA", 0xc013),
+    ("TLS_ECDHE_RSA_WITH_AES_256_CBC_SHA", "ECDHE-RSA-AES256-SHA", 0xc014),
+    ("TLS_RSA_WITH_AES_128_GCM_SHA256", "AES128-GCM-SHA256", 0x009c),
+    ("TLS_RSA_WITH_AES_256_GCM_SHA384", "AES256-GCM-SHA384", 0x009d),
+    ("TLS_RSA_WITH_AES_128_CBC_SHA", "AES128-SHA", 0x002f),
+    ("TLS_RSA_WITH_AES_256_CBC_SHA", "AES256-SHA", 0x0035),
+];
+
+/// ClientHello extensions as (name, code point), aliases included.
+const EXTENSIONS: &[(&str, u16)] = &[
+    ("server_name", 0),
+    ("status_request", 5),
+    ("supported_groups", 10),
+    ("elliptic_curves", 10),
+    ("ec_point_formats", 11),
+    ("signature_algorithms", 13),
+    ("application_layer_protocol_negotiation", 16),
+    ("alpn", 16),
+    ("signed_certificate_timestamp", 18),
+    ("padding", 21),
+    ("extended_master_secret", 23),
+    ("compress_certificate", 27),
+    ("record_size_limit", 28),
+    ("delegated_credentials", 34),
+    ("session_ticket", 35),
+    ("pre_shared_key", 41),
+    ("early_data", 42),
+    ("supported_versions", 43),
+    ("psk_key_exchange_modes", 45),
+    ("key_share", 51),
+    ("application_settings", 17513),
+    ("application_settings_new", 17613),
+    ("encrypted_client_hello", 65037),
+    ("renegotiation_info", 65281),
+];
+
+/// Named groups as (name, BoringSSL name, code point), aliases included.
+const GROUPS: &[(&str, &str, u16)] = &[
+    ("X25519MLKEM768", "X25519MLKEM768", 0x11ec),
+    ("X25519Kyber768Draft00", "X25519Kyber768Draft00", 0x6399),
+    ("X25519", "X25519", 29),
+    ("x25519", "X25519", 29),
+    ("P-256", "P-256", 23),
+    ("secp256r1", "P-256", 23),
+    ("P-384", "P-384", 24),
+    ("secp384r1", "P-384", 24),
+    ("P-521", "P-521", 25),
+    ("secp521r1", "P-521", 25),
+];
+
+/// Signature schemes as (name, code point).
+const SIGNATURE_ALGORITHMS: &[(&str, u16)] = &[
+    ("rsa_pkcs1_sha1", 0x0201),
+    ("ecdsa_sha1", 0x0203),
+    ("rsa_pkcs1_sha256", 0x0401),
+    ("ecdsa_secp256r1_sha256", 0x0403),
+    ("rsa_pkcs1_sha384", 0x0501),
+    ("ecdsa_secp384r1_sha384", 0x0503),
+    ("rsa_pkcs1_sha512", 0x0601),
+    ("ecdsa_secp521r1_sha512", 0x0603),
+    ("rsa_pss_rsae_sha256", 0x0804),
+    ("rsa_pss_rsae_sha384", 0x0805),
+    ("rsa_pss_rsae_sha512", 0x0806),
+    ("ed25519", 0x0807),
+];
+
+/// Certificate compression algorithms (RFC 8879) as (name, code point).
+const CERT_COMPRESSION: &[(&str, u16)] = &[("zlib", 1), ("brotli", 2), ("zstd", 3)];
+
+/// Extension code point for a profile name.
+pub fn extension_id(name: &str) -> Option<u16> {
+    EXTENSIONS.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|&(_, id)| id)
+}
+
+/// Cipher suite code point for an IANA or BoringSSL name.
+pub fn cipher_suite_id(name: &str) -> Option<u16> {
+    CIPHER_SUITES
+        .iter()
+        .find(|(iana, boring, _)| iana.eq_ignore_ascii_case(name) || (!boring.is_empty() && boring.eq_ignore_ascii_case(name)))
+        .map(|&(_, _, id)| id)
+}
+
+/// GREASE values (RFC 8701) are `0x?a?a` with both bytes equal.
+pub fn is_grease(value: u16) -> bool {
+    value & 0x0f0f == 0x0a0a && value >> 8 == value & 0xff
+}
+
+fn lookup<T: Copy>(kind: &str, names: &[String], find: impl Fn(&str) -> Option<T>) -> Result<Vec<T>> {
+    names
+        .iter()
+        .map(|n| find(n.trim()).ok_or_else(|| anyhow!("Unknown {} '{}'", kind, n)))
+        .collect()
+}
+
+// ============================================================================
+// ClientHello Spec
+// ============================================================================
+
+/// Wire-level ClientHello parameters resolved from a `TLSProfile`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientHelloSpec {
+    pub min_version: u16,
+    pub max_version: u16,
+    pub cipher_suites: Vec<u16>,
+    pub extensions: Vec<u16>,
+    pub supported_groups: Vec<u16>,
+    pub signature_algorithms: Vec<u16>,
+    pub alpn: Vec<String>,
+    pub cert_compression: Vec<u16>,
+    pub grease: bool,
+    pub permute_extensions: bool,
+}
+
+impl ClientHelloSpec {
+    /// Resolve every name in `profile`; unknown names are an error rather than silently dropped.
+    pub fn from_profile(profile: &TLSProfile) -> Result<Self> {
+        let (min_version, max_version) = match profile.version.to_ascii_uppercase().replace(['V', ' '], "").as_str() {
+            "TLS1.3" | "1.3" => (0x0303, 0x0304),
+            "TLS1.2" | "1.2" => (0x0303, 0x0303),
+            other => return Err(anyhow!("Unsupported TLS version '{}'", other)),
+        };
+        Ok(Self {
+            min_version,
+            max_version,
+            cipher_suites: lookup("cipher suite", &profile.cipher_suites, cipher_suite_id)?,
+            extensions: lookup("extension", &profile.extensions, extension_id)?,
+            supported_groups: lookup("group", &profile.supported_groups, |n| {
+                GROUPS.iter().find(|(name, _, _)| *name == n).map(|&(_, _, id)| id)
+            })?,
+            signature_algorithms: lookup("signature algorithm", &profile.signature_algorithms, |n| {
+                SIGNATURE_ALGORITHMS.iter().find(|(name, _)| *name == n).map(|&(_, id)| id)
+            })?,
+            alpn: profile.alpn.clone(),
+            cert_compression: lookup("certificate compression algorithm", &profile.cert_compression, |n| {
+                CERT_COMPRESSION.iter().find(|(name, _)| name.eq_ignore_ascii_case(n)).map(|&(_, id)| id)
+            })?,
+            grease: profile.grease,
+            permute_extensions: profile.permute_extensions,
+        })
+    }
+
+    /// Whether the extension is configured.
+    pub fn has_extension(&self, id: u16) -> bool {
+        self.extensions.contains(&id)
+    }
+
+    /// TLS 1.2 suites in BoringSSL `cipher_list` syntax, order preserved.
+    pub fn cipher_list(&self) -> String {
+        self.cipher_suites
+            .iter()
+            .filter_map(|id| CIPHER_SUITES.iter().find(|(_, _, c)| c == id))
+            .filter(|(_, boring, _)| !boring.is_empty())
+            .map(|(_, boring, _)| *boring)
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// Groups in BoringSSL `curves_list` syntax.
+    pub fn curves_list(&self) -> String {
+        self.supported_groups
+            .iter()
+            .filter_map(|id| GROUPS.iter().find(|(_, _, g)| g == id))
+            .map(|(_, boring, _)| *boring)
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// Signature schemes in BoringSSL `sigalgs_list` syntax.
+    pub fn sigalgs_list(&self) -> String {
+        self.signature_algorithms
+            .iter()
+            .filter_map(|id| SIGNATURE_ALGORITHMS.iter().find(|(_, s)| s == id))
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(":")
+    }
+
+    /// ALPN protocol list in wire format (length-prefixed).
+    pub fn alpn_wire(&self) -> Vec<u8> {
+        let mut wire = Vec::new();
+        for proto in &self.alpn {
+            wire.push(proto.len() as u8);
+            wire.extend_from_slice(proto.as_bytes());
+        }
+        wire
+    }
+}
+
+// ============================================================================
+// ClientHello Parsing
+// ============================================================================
+
+/// Fields of a ClientHello that fingerprints are computed from.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientHelloInfo {
+    pub legacy_version: u16,
+    /// All values in the order sent, GREASE included
+    pub cipher_suites: Vec<u16>,
+    pub extensions: Vec<u16>,
+    pub supported_groups: Vec<u16>,
+    pub point_formats: Vec<u8>,
+    pub signature_algorithms: Vec<u16>,
+    pub supported_versions: Vec<u16>,
+    pub alpn: Vec<String>,
+    pub server_name: Option<String>,
+}
+
+/// Bounds-checked big-endian reader.
+struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < n {
+            return Err(anyhow!("Truncated ClientHello"));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        let b = self.take(2)?;
+        Ok(u16::from_be_bytes([b[0], b[1]]))
+    }
+
+    fn u24(&mut self) -> Result<usize> {
+        let b = self.take(3)?;
+        Ok((b[0] as usize) << 16 | (b[1] as usize) << 8 | b[2] as usize)
+    }
+
+    fn vec8(&mut self) -> Result<Reader<'a>> {
+        let len = self.u8()? as usize;
+        Ok(Reader { buf: self.take(len)? })
+    }
+
+    fn vec16(&mut self) -> Result<Reader<'a>> {
+        let len = self.u16()? as usize;
+        Ok(Reader { buf: self.take(len)? })
+    }
+
+    fn u16s(mut self) -> Result<Vec<u16>> {
+        let mut out = Vec::with_capacity(self.buf.len() / 2);
+        while !self.buf.is_empty() {
+            out.push(self.u16()?);
+        }
+        Ok(out)
+    }
+}
+
+/// Length of the TLS record starting at `buf`, header included, once its header is available.
+pub fn tls_record_len(buf: &[u8]) -> Option<usize> {
+    (buf.len() >= 5).then(|| 5 + u16::from_be_bytes([buf[3], buf[4]]) as usize)
+}
+
+/// Parse the ClientHello from the client's first TLS record(s).
+pub fn parse_client_hello(records: &[u8]) -> Result<ClientHelloInfo> {
+    // Reassemble the handshake message, which may span several records
+    let mut handshake = Vec::new();
+    let mut rest = records;
+    while let Some(len) = tls_record_len(rest) {
+        if rest[0] != 0x16 {
+            return Err(anyhow!("Not a TLS handshake record"));
+        }
+        let end = len.min(rest.len());
+        handshake.extend_from_slice(&rest[5..end]);
+        rest = &rest[end..];
+    }
+    if handshake.is_empty() {
+        return Err(anyhow!("Not a TLS handshake record"));
+    }
+
+    let mut r = Reader { buf: &handshake };
+    if r.u8()? != 0x01 {
+        return Err(anyhow!("Not a ClientHello"));
+    }
+    let len = r.u24()?;
+    let mut body = Reader { buf: r.take(len)? };
+
+    let mut info = ClientHelloInfo {
+        legacy_version: body.u16()?,
+        ..Default::default()
+    };
+    body.take(32)?;
+    body.vec8()?;
+    info.cipher_suites = body.vec16()?.u16s()?;
+    body.vec8()?;
+    if body.buf.is_empty() {
+        return Ok(info);
+    }
+
+    let mut extensions = body.vec16()?;
+    while !extensions.buf.is_empty() {
+        let kind = extensions.u16()?;
+        let mut data = extensions.vec16()?;
+        info.extensions.push(kind);
+        match kind {
+            0 => {
+                let mut list = data.vec16()?;
+                if list.u8()? == 0 {
+                    let name = list.vec16()?.buf;
+                    info.server_name = Some(String::from_utf8_lossy(name).into_owned());
+                }
+            }
+            10 => info.supported_groups = data.vec16()?.u16s()?,
+            11 => info.point_formats = data.vec8()?.buf.to_vec(),
+            13 => info.signature_algorithms = data.vec16()?.u16s()?,
+            16 => {
+                let mut list = data.vec16()?;
+                while !list.buf.is_empty() {
+                    info.alpn.push(String::from_utf8_lossy(list.vec8()?.buf).into_owned());
+                }
+            }
+            43 => info.supported_versions = data.vec8()?.u16s()?,
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn join<T: ToString>(values: impl Iterator<Item = T>, sep: &str) -> String {
+    values.map(|v| v.to_string()).collect::<Vec<_>>().join(sep)
+}
+
+fn truncated_sha256(input: &str) -> String {
+    if input.is_empty() {
+        return "000000000000".to_string();
+    }
+    hex::encode(Sha256::digest(input.as_bytes()))[..12].to_string()
+}
+
+impl ClientHelloInfo {
+    fn without_grease(values: &[u16]) -> impl Iterator<Item = u16> + '_ {
+        values.iter().copied().filter(|v| !is_grease(*v))
+    }
+
+    /// JA3 string: version, ciphers, extensions, groups and point formats, GREASE removed.
+    pub fn ja3_string(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.legacy_version,
+            join(Self::without_grease(&self.cipher_suites), "-"),
+            join(Self::without_grease(&self.extensions), "-"),
+            join(Self::without_grease(&self.supported_groups), "-"),
+            join(self.point_formats.iter(), "-"),
+        )
+    }
+
+    /// MD5 of the JA3 string.
+    pub fn ja3_hash(&self) -> String {
+        hex::encode(Md5::digest(self.ja3_string().as_bytes()))
+    }
+
+    /// JA4 fingerprint (TCP), which is insensitive to extension order.
+    pub fn ja4(&self) -> String {
+        let version = Self::without_grease(&self.supported_versions)
+            .max()
+            .unwrap_or(self.legacy_version);
+        let version = match version {
+            0x0304 => "13",
+            0x0303 => "12",
+            0x0302 => "11",
+            0x0301 => "10",
+            0x0300 => "s3",
+            _ => "00",
+        };
+        let sni = if self.extensions.contains(&0) { 'd' } else { 'i' };
+        let ciphers: Vec<u16> = Self::without_grease(&self.cipher_suites).collect();
+        let extensions: Vec<u16> = Self::without_grease(&self.extensions).collect();
+        let alpn = match self.alpn.first().map(|a| a.as_bytes()) {
+            Some([first, .., last]) | Some([first @ last]) => {
+                if first.is_ascii_alphanumeric() && last.is_ascii_alphanumeric() {
+                    format!("{}{}", *first as char, *last as char)
+                } else {
+                    let hex = hex::encode([*first, *last]);
+                    format!("{}{}", &hex[..1], &hex[3..])
+                }
+            }
+            _ => "00".to_string(),
+        };
+        let a = format!(
+            "t{}{}{:02}{:02}{}",
+            version,
+            sni,
+            ciphers.len().min(99),
+            extensions.len().min(99),
+            alpn
+        );
+
+        let mut sorted_ciphers = ciphers;
+        sorted_ciphers.sort_unstable();
+        let b = truncated_sha256(&join(sorted_ciphers.iter().map(|c| format!("{:04x}", c)), ","));
+
+        let mut sorted_extensions: Vec<u16> = extensions.into_iter().filter(|e| *e != 0 && *e != 16).collect();
+        sorted_extensions.sort_unstable();
+        let mut c_input = join(sorted_extensions.iter().map(|e| format!("{:04x}", e)), ",");
+        let sigalgs: Vec<u16> = Self::without_grease(&self.signature_algorithms).collect();
+        if !c_input.is_empty() && !sigalgs.is_empty() {
+            c_input.push('_');
+            c_input.push_str(&join(sigalgs.iter().map(|s| format!("{:04x}", s)), ","));
+        }
+        let c = truncated_sha256(&c_input);
+
+        format!("{}_{}_{}", a, b, c)
+    }
+}
+
+// ============================================================================
+// HTTP/2 Frames
+// ============================================================================
+
+/// Client connection preface that precedes the first SETTINGS frame.
+pub const HTTP2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+// Frames only the `impersonation` client sends or reads are unused without it
+#[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+pub(crate) const FRAME_DATA: u8 = 0x0;
+pub(crate) const FRAME_HEADERS: u8 = 0x1;
+pub(crate) const FRAME_PRIORITY: u8 = 0x2;
+#[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+pub(crate) const FRAME_RST_STREAM: u8 = 0x3;
+pub(crate) const FRAME_SETTINGS: u8 = 0x4;
+#[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+pub(crate) const FRAME_PUSH_PROMISE: u8 = 0x5;
+#[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+pub(crate) const FRAME_PING: u8 = 0x6;
+#[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+pub(crate) const FRAME_GOAWAY: u8 = 0x7;
+pub(crate) const FRAME_WINDOW_UPDATE: u8 = 0x8;
+pub(crate) const FRAME_CONTINUATION: u8 = 0x9;
+
+#[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+pub(crate) const FLAG_END_STREAM: u8 = 0x1;
+pub(crate) const FLAG_ACK: u8 = 0x1;
+pub(crate) const FLAG_END_HEADERS: u8 = 0x4;
+pub(crate) const FLAG_PADDED: u8 = 0x8;
+pub(crate) const FLAG_PRIORITY: u8 = 0x20;
+
+/// A single HTTP/2 frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Frame {
+    pub kind: u8,
+    pub flags: u8,
+    pub stream_id: u32,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(kind: u8, flags: u8, stream_id: u32, payload: Vec<u8>) -> Self {
+        Self { kind, flags, stream_id, payload }
+    }
+
+    #[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+    pub fn encode(&self) -> Vec<u8> {
+        let len = self.payload.len() as u32;
+        let mut out = Vec::with_capacity(9 + self.payload.len());
+        out.extend_from_slice(&len.to_be_bytes()[1..]);
+        out.push(self.kind);
+        out.push(self.flags);
+        out.extend_from_slice(&(self.stream_id & 0x7fff_ffff).to_be_bytes());
+        out.extend_from_slice(&self.payload);
+        out
+    }
+
+    /// Decode one frame from the front of `buf`, returning it and the bytes consumed.
+    pub fn decode(buf: &[u8]) -> Option<(Self, usize)> {
+        if buf.len() < 9 {
+            return None;
+        }
+        let len = u32::from_be_bytes([0, buf[0], buf[1], buf[2]]) as usize;
+        if buf.len() < 9 + len {
+            return None;
+        }
+        let stream_id = u32::from_be_bytes([buf[5], buf[6], buf[7], buf[8]]) & 0x7fff_ffff;
+        Some((Self::new(buf[3], buf[4], stream_id, buf[9..9 + len].to_vec()), 9 + len))
+    }
+
+    /// Payload of a HEADERS, PUSH_PROMISE or DATA frame without padding and priority fields.
+    pub fn header_block(&self) -> Result<&[u8]> {
+        let mut block = &self.payload[..];
+        let mut pad = 0;
+        if self.flags & FLAG_PADDED != 0 {
+            pad = *block.first().ok_or_else(|| anyhow!("Empty padded frame"))? as usize;
+            block = &block[1..];
+        }
+        if self.kind == FRAME_HEADERS && self.flags & FLAG_PRIORITY != 0 {
+            block = block.get(5..).ok_or_else(|| anyhow!("Truncated HEADERS priority"))?;
+        }
+        if pad > block.len() {
+            return Err(anyhow!("Invalid HEADERS padding"));
+        }
+        Ok(&block[..block.len() - pad])
+    }
+}
+
+/// Payload of a PRIORITY frame or the priority fields of a HEADERS frame.
+#[cfg_attr(not(feature = "impersonation"), allow(dead_code))]
+pub(crate) fn priority_payload(priority: &Http2Priority) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(5);
+    let dependency = priority.depends_on & 0x7fff_ffff | if priority.exclusive { 0x8000_0000 } else { 0 };
+    payload.extend_from_slice(&dependency.to_be_bytes());
+    payload.push(priority.weight.clamp(1, 256).saturating_sub(1) as u8);
+    payload
+}
+
+fn parse_priority(stream_id: u32, payload: &[u8]) -> Result<Http2Priority> {
+    if payload.len() < 5 {
+        return Err(anyhow!("Truncated priority fields"));
+    }
+    let dependency = u32::from_be_bytes([payload[0], payload[1], payload[2], payload[3]]);
+    Ok(Http2Priority {
+        stream_id,
+        exclusive: dependency & 0x8000_0000 != 0,
+        depends_on: dependency & 0x7fff_ffff,
+        weight: payload[4] as u32 + 1,
+    })
+}
+
+// ============================================================================
+// HTTP/2 Fingerprint
+// ============================================================================
+
+/// A PRIORITY frame, or the priority of the request's HEADERS frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Http2Priority {
+    pub stream_id: u32,
+    pub exclusive: bool,
+    pub depends_on: u32,
+    /// 1-256, as in the Akamai fingerprint (wire value + 1)
+    pub weight: u32,
+}
+
+/// What a server sees of a client's HTTP/2 connection setup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Http2Fingerprint {
+    /// SETTINGS in the order sent
+    pub settings: Vec<(u16, u32)>,
+    /// Connection WINDOW_UPDATE increment, 0 when none is sent
+    pub window_update: u32,
+    /// PRIORITY frames sent before the first request
+    pub priority_frames: Vec<Http2Priority>,
+    /// First letters of the request pseudo-headers, in order
+    pub pseudo_headers: Vec<char>,
+    /// Priority carried on the first HEADERS frame
+    pub headers_priority: Option<Http2Priority>,
+}
+
+/// Pseudo-header letter ('m', 'a', 's', 'p') for ":method", "method" or "m".
+pub(crate) fn pseudo_header_letter(name: &str) -> Option<char> {
+    let letter = name.trim_start_matches(':').chars().next()?.to_ascii_lowercase();
+    "masp".contains(letter).then_some(letter)
+}
+
+impl Http2Fingerprint {
+    /// The fingerprint a client configured with `settings` should produce.
+    pub fn from_settings(settings: &HTTP2Settings) -> Self {
+        Self {
+            settings: settings.settings_frame.iter().map(|&(id, value)| (id as u16, value)).collect(),
+            window_update: settings.window_update,
+            priority_frames: settings
+                .priority
+                .iter()
+                .map(|&(stream_id, weight)| Http2Priority {
+                    stream_id,
+                    exclusive: false,
+                    depends_on: 0,
+                    weight,
+                })
+                .collect(),
+            pseudo_headers: settings.pseudo_header_order.iter().filter_map(|p| pseudo_header_letter(p)).collect(),
+            headers_priority: settings.headers_priority.map(|(depends_on, weight, exclusive)| Http2Priority {
+                stream_id: 1,
+                exclusive,
+                depends_on,
+                weight,
+            }),
+        }
+    }
+
+    /// Parse a client connection, starting at the preface, up to its first request headers.
+    pub fn parse_preface(bytes: &[u8]) -> Result<Self> {
+        let mut rest = bytes
+            .strip_prefix(HTTP2_PREFACE)
+            .ok_or_else(|| anyhow!("Missing HTTP/2 connection preface"))?;
+        let mut fingerprint = Self::default();
+        let mut block: Option<Vec<u8>> = None;
+
+        while let Some((frame, used)) = Frame::decode(rest) {
+            rest = &rest[used..];
+            match frame.kind {
+                FRAME_SETTINGS if frame.flags & FLAG_ACK == 0 => {
+                    for pair in frame.payload.chunks_exact(6) {
+                        fingerprint.settings.push((
+                            u16::from_be_bytes([pair[0], pair[1]]),
+                            u32::from_be_bytes([pair[2], pair[3], pair[4], pair[5]]),
+                        ));
+                    }
+                }
+                FRAME_WINDOW_UPDATE if frame.stream_id == 0 && frame.payload.len() == 4 => {
+                    fingerprint.window_update =
+                        u32::from_be_bytes([frame.payload[0], frame.payload[1], frame.payload[2], frame.payload[3]]) & 0x7fff_ffff;
+                }
+                FRAME_PRIORITY => fingerprint.priority_frames.push(parse_priority(frame.stream_id, &frame.payload)?),
+                FRAME_HEADERS => {
+                    if frame.flags & FLAG_PRIORITY != 0 {
+                        let fields = if frame.flags & FLAG_PADDED != 0 { &frame.payload[1..] } else { &frame.payload[..] };
+                        fingerprint.headers_priority = Some(parse_priority(frame.stream_id, fields)?);
+                    }
+                    block = Some(frame.header_block()?.to_vec());
+                    if frame.flags & FLAG_END_HEADERS != 0 {
+                        break;
+                    }
+                }
+                FRAME_CONTINUATION => {
+                    let current = block.as_mut().ok_or_else(|| anyhow!("CONTINUATION without HEADERS"))?;
+                    current.extend_from_slice(&frame.payload);
+                    if frame.flags & FLAG_END_HEADERS != 0 {
+                        break;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let block = block.ok_or_else(|| anyhow!("No HEADERS frame in client preface"))?;
+        let headers = loona_hpack::Decoder::new()
+            .decode(&block)
+            .map_err(|e| anyhow!("Invalid header block: {:?}", e))?;
+        fingerprint.pseudo_headers = headers
+            .iter()
+            .take_while(|(name, _)| name.starts_with(b":"))
+            .filter_map(|(name, _)| pseudo_header_letter(&String::from_utf8_lossy(name)))
+            .collect();
+        Ok(fingerprint)
+    }
+
+    /// Akamai format: `SETTINGS|WINDOW_UPDATE|PRIORITY|PSEUDO_HEADERS`.
+    pub fn akamai(&self) -> String {
+        let settings = join(self.settings.iter().map(|(id, value)| format!("{}:{}", id, value)), ";");
+        let window_update = if self.window_update == 0 {
+            "00".to_string()
+        } else {
+            self.window_update.to_string()
+        };
+        let priority = if self.priority_frames.is_empty() {
+            "0".to_string()
+        } else {
+            join(
+                self.priority_frames
+                    .iter()
+                    .map(|p| format!("{}:{}:{}:{}", p.stream_id, p.exclusive as u8, p.depends_on, p.weight)),
+                ",",
+            )
+        };
+        format!("{}|{}|{}|{}", settings, window_update, priority, join(self.pseudo_headers.iter(), ","))
+    }
+}
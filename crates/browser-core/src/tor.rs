@@ -0,0 +1,599 @@
+//! Tor Module
+//!
+//! Uses a local tor daemon as a proxy source through its control port:
+//! - Cookie, password or no authentication
+//! - `SIGNAL NEWNYM` for rotation
+//! - Per-tab circuit isolation through SOCKS credentials (`IsolateSOCKSAuth`)
+//! - Circuit and exit relay information
+//! - Optional exit country pinning with `ExitNodes`
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+
+use crate::free_ip_providers::ProxyProvider;
+use crate::proxy::{FreeProxy, ProxyType};
+
+fn default_name() -> String {
+    "Tor".to_string()
+}
+
+fn default_host() -> String {
+    "127.0.0.1".to_string()
+}
+
+fn default_control_port() -> u16 {
+    9051
+}
+
+fn default_socks_port() -> u16 {
+    9050
+}
+
+fn default_timeout_secs() -> u64 {
+    10
+}
+
+/// How to authenticate to the control port
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TorAuth {
+    /// No authentication (`CookieAuthentication 0`, no `HashedControlPassword`)
+    #[default]
+    None,
+    /// `HashedControlPassword` set in torrc
+    Password { password: String },
+    /// Auth cookie; the path reported by `PROTOCOLINFO` is used when none is given
+    Cookie {
+        #[serde(default)]
+        path: Option<PathBuf>,
+    },
+}
+
+/// Configuration for a local tor daemon
+///
+/// ```toml
+/// [proxy.tor]
+/// control_port = 9051
+/// socks_port = 9050
+/// auth = { type = "cookie" }
+/// exit_countries = ["de", "nl"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TorConfig {
+    #[serde(default = "default_name")]
+    pub name: String,
+    #[serde(default = "default_host")]
+    pub host: String,
+    #[serde(default = "default_control_port")]
+    pub control_port: u16,
+    #[serde(default = "default_socks_port")]
+    pub socks_port: u16,
+    #[serde(default)]
+    pub auth: TorAuth,
+    /// ISO 3166-1 alpha-2 codes to restrict exits to; empty leaves tor's choice
+    #[serde(default)]
+    pub exit_countries: Vec<String>,
+    /// Fail rather than fall back to other exits when pinned countries are unavailable
+    #[serde(default)]
+    pub strict_exit_nodes: bool,
+    #[serde(default = "default_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for TorConfig {
+    fn default() -> Self {
+        Self {
+            name: default_name(),
+            host: default_host(),
+            control_port: default_control_port(),
+            socks_port: default_socks_port(),
+            auth: TorAuth::default(),
+            exit_countries: Vec::new(),
+            strict_exit_nodes: false,
+            timeout_secs: default_timeout_secs(),
+        }
+    }
+}
+
+/// A relay on a circuit path
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorRelay {
+    /// Hex identity fingerprint without the leading `$`
+    pub fingerprint: String,
+    pub nickname: Option<String>,
+}
+
+/// An entry from `GETINFO circuit-status`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorCircuit {
+    pub id: String,
+    /// LAUNCHED, BUILT, EXTENDED, FAILED or CLOSED
+    pub status: String,
+    pub path: Vec<TorRelay>,
+    pub purpose: Option<String>,
+}
+
+impl TorCircuit {
+    /// Parses one `circuit-status` line
+    pub fn parse(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let id = parts.next()?.to_string();
+        let status = parts.next()?.to_string();
+
+        let mut path = Vec::new();
+        let mut purpose = None;
+        for part in parts {
+            if let Some(value) = part.strip_prefix("PURPOSE=") {
+                purpose = Some(value.to_string());
+            } else if part.starts_with('$') && path.is_empty() {
+                path = part.split(',').filter_map(TorRelay::parse).collect();
+            }
+        }
+
+        Some(Self { id, status, path, purpose })
+    }
+
+    /// Last hop of the circuit
+    pub fn exit(&self) -> Option<&TorRelay> {
+        self.path.last()
+    }
+
+    /// Built circuit usable for user traffic
+    pub fn is_general(&self) -> bool {
+        self.status == "BUILT" && self.purpose.as_deref().is_none_or(|p| p == "GENERAL")
+    }
+}
+
+impl TorRelay {
+    /// Parses `$FINGERPRINT~nickname` or `$FINGERPRINT=nickname`
+    fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.strip_prefix('$')?;
+        let (fingerprint, nickname) = match spec.find(['~', '=']) {
+            Some(i) => (&spec[..i], Some(spec[i + 1..].to_string())),
+            None => (spec, None),
+        };
+        if fingerprint.is_empty() {
+            return None;
+        }
+        Some(Self {
+            fingerprint: fingerprint.to_string(),
+            nickname,
+        })
+    }
+}
+
+/// Where traffic currently leaves the Tor network
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TorExitInfo {
+    pub circuit_id: String,
+    pub fingerprint: String,
+    pub nickname: Option<String>,
+    pub address: Option<String>,
+    pub country_code: Option<String>,
+}
+
+/// An authenticated control port connection
+pub struct TorControl {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    /// Longest wait for a command's reply
+    timeout: Duration,
+}
+
+impl TorControl {
+    /// Connects to the control port and authenticates
+    pub async fn connect(config: &TorConfig) -> Result<Self> {
+        let address = format!("{}:{}", config.host, config.control_port);
+        let timeout = Duration::from_secs(config.timeout_secs);
+        let stream = tokio::time::timeout(timeout, TcpStream::connect(&address))
+            .await
+            .map_err(|_| anyhow!("Timed out connecting to tor control port {}", address))?
+            .with_context(|| format!("Failed to connect to tor control port {}", address))?;
+        let (read, write) = stream.into_split();
+        let mut control = Self {
+            reader: BufReader::new(read),
+            writer: write,
+            timeout,
+        };
+        control.authenticate(&config.auth).await?;
+        debug!("Authenticated to tor control port {}", address);
+        Ok(control)
+    }
+
+    async fn authenticate(&mut self, auth: &TorAuth) -> Result<()> {
+        let command = match auth {
+            TorAuth::None => "AUTHENTICATE".to_string(),
+            TorAuth::Password { password } => {
+                format!("AUTHENTICATE \"{}\"", password.replace('\\', "\\\\").replace('"', "\\\""))
+            }
+            TorAuth::Cookie { path } => {
+                let path = match path {
+                    Some(path) => path.clone(),
+                    None => self.cookie_path().await?,
+                };
+                let cookie = tokio::fs::read(&path)
+                    .await
+                    .with_context(|| format!("Failed to read tor auth cookie {}", path.display()))?;
+                format!("AUTHENTICATE {}", hex::encode(cookie))
+            }
+        };
+        self.command(&command).await.context("Tor control authentication failed")?;
+        Ok(())
+    }
+
+    /// Cookie file location reported by `PROTOCOLINFO`
+    async fn cookie_path(&mut self) -> Result<PathBuf> {
+        let reply = self.command("PROTOCOLINFO 1").await?;
+        reply
+            .iter()
+            .filter_map(|line| line.strip_prefix("AUTH "))
+            .find_map(|line| line.split_once("COOKIEFILE=").map(|(_, rest)| unquote(rest)))
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow!("Tor did not report an auth cookie file"))
+    }
+
+    /// Sends a command and returns its reply lines
+    ///
+    /// Data blocks (`250+key=`) are returned as one entry with embedded newlines.
+    /// Error replies (4xx/5xx) are returned as errors, as is a reply that does
+    /// not arrive within the configured timeout.
+    pub async fn command(&mut self, command: &str) -> Result<Vec<String>> {
+        let timeout = self.timeout;
+        tokio::time::timeout(timeout, self.exchange(command))
+            .await
+            .map_err(|elapsed| {
+                anyhow::Error::new(elapsed)
+                    .context(format!("Tor did not answer '{}' within {:?}", command_name(command), timeout))
+            })?
+    }
+
+    async fn exchange(&mut self, command: &str) -> Result<Vec<String>> {
+        self.writer.write_all(format!("{}\r\n", command).as_bytes()).await?;
+        self.writer.flush().await?;
+
+        let mut entries = Vec::new();
+        loop {
+            let line = self.read_line().await?;
+            if line.len() < 4 {
+                return Err(anyhow!("Malformed tor control reply '{}'", line));
+            }
+            let (code, separator, text) = (&line[..3], &line[3..4], &line[4..]);
+            if !code.starts_with('2') && !code.starts_with('6') {
+                return Err(anyhow!("Tor rejected '{}': {} {}", command_name(command), code, text));
+            }
+            match separator {
+                "+" => {
+                    let mut block = text.to_string();
+                    loop {
+                        let data = self.read_line().await?;
+                        if data == "." {
+                            break;
+                        }
+                        block.push('\n');
+                        block.push_str(data.strip_prefix('.').unwrap_or(&data));
+                    }
+                    entries.push(block);
+                }
+                "-" => entries.push(text.to_string()),
+                " " => {
+                    entries.push(text.to_string());
+                    return Ok(entries);
+                }
+                _ => return Err(anyhow!("Malformed tor control reply '{}'", line)),
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String> {
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).await? == 0 {
+            return Err(anyhow!("Tor control connection closed"));
+        }
+        Ok(line.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    /// Value of a `GETINFO` key
+    pub async fn get_info(&mut self, key: &str) -> Result<String> {
+        let reply = self.command(&format!("GETINFO {}", key)).await?;
+        let prefix = format!("{}=", key);
+        reply
+            .iter()
+            .find_map(|entry| entry.strip_prefix(&prefix))
+            .map(|value| value.trim_start_matches('\n').to_string())
+            .ok_or_else(|| anyhow!("Tor returned no value for '{}'", key))
+    }
+
+    /// Requests new circuits for new streams
+    pub async fn new_identity(&mut self) -> Result<()> {
+        self.command("SIGNAL NEWNYM").await?;
+        Ok(())
+    }
+
+    /// Restricts exits to the given countries, or lifts the restriction when empty
+    ///
+    /// Countries must be ISO 3166-1 alpha-2 codes.
+    pub async fn set_exit_countries(&mut self, countries: &[String], strict: bool) -> Result<()> {
+        let countries = exit_country_codes(countries)?;
+        if countries.is_empty() {
+            self.command("RESETCONF ExitNodes StrictNodes").await?;
+            return Ok(());
+        }
+        let nodes: Vec<String> = countries.iter().map(|c| format!("{{{}}}", c)).collect();
+        self.command(&format!("SETCONF ExitNodes={} StrictNodes={}", nodes.join(","), u8::from(strict)))
+            .await?;
+        Ok(())
+    }
+
+    /// Current circuits
+    pub async fn circuits(&mut self) -> Result<Vec<TorCircuit>> {
+        let status = self.get_info("circuit-status").await?;
+        Ok(status.lines().filter_map(TorCircuit::parse).collect())
+    }
+
+    /// Address and country of a relay from its consensus entry
+    pub async fn relay_address(&mut self, fingerprint: &str) -> Result<(Option<String>, Option<String>)> {
+        let entry = self.get_info(&format!("ns/id/{}", fingerprint)).await?;
+        // r <nickname> <identity> <digest> <date> <time> <ip> <orport> <dirport>
+        let address = entry
+            .lines()
+            .find_map(|line| line.strip_prefix("r "))
+            .and_then(|line| line.split_whitespace().nth(5))
+            .map(str::to_string);
+
+        let country = match &address {
+            Some(address) => self
+                .get_info(&format!("ip-to-country/{}", address))
+                .await
+                .ok()
+                .map(|c| c.trim().to_ascii_uppercase())
+                .filter(|c| c.len() == 2 && c != "??"),
+            None => None,
+        };
+        Ok((address, country))
+    }
+
+    /// Exit of the newest built general-purpose circuit
+    pub async fn current_exit(&mut self) -> Result<Option<TorExitInfo>> {
+        let circuits = self.circuits().await?;
+        let Some(circuit) = circuits.iter().rev().find(|c| c.is_general()) else {
+            return Ok(None);
+        };
+        let Some(exit) = circuit.exit() else {
+            return Ok(None);
+        };
+        let (address, country_code) = self.relay_address(&exit.fingerprint).await?;
+        Ok(Some(TorExitInfo {
+            circuit_id: circuit.id.clone(),
+            fingerprint: exit.fingerprint.clone(),
+            nickname: exit.nickname.clone(),
+            address,
+            country_code,
+        }))
+    }
+}
+
+/// Trimmed, lowercased exit countries, skipping blanks
+///
+/// Only two ASCII letters are accepted, as the codes end up in `SETCONF`.
+fn exit_country_codes(countries: &[String]) -> Result<Vec<String>> {
+    countries
+        .iter()
+        .map(|c| c.trim())
+        .filter(|c| !c.is_empty())
+        .map(|c| {
+            if c.len() == 2 && c.chars().all(|ch| ch.is_ascii_alphabetic()) {
+                Ok(c.to_ascii_lowercase())
+            } else {
+                Err(anyhow!("Invalid exit country '{}'", c.escape_debug()))
+            }
+        })
+        .collect()
+}
+
+fn command_name(command: &str) -> &str {
+    // Keep credentials out of error messages
+    command.split_whitespace().next().unwrap_or(command)
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    match value.strip_prefix('"') {
+        Some(rest) => {
+            let mut out = String::new();
+            let mut chars = rest.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' => out.extend(chars.next()),
+                    '"' => break,
+                    c => out.push(c),
+                }
+            }
+            out
+        }
+        None => value.split_whitespace().next().unwrap_or_default().to_string(),
+    }
+}
+
+/// Tor as a proxy source with per-tab circuit isolation
+///
+/// Each tab gets its own SOCKS credentials, which tor keeps on separate
+/// circuits; rotating a tab sends `NEWNYM` and issues new credentials.
+pub struct TorProvider {
+    config: TorConfig,
+    control: Mutex<Option<TorControl>>,
+    generations: std::sync::Mutex<HashMap<String, u64>>,
+    /// Exit countries and strictness currently pinned, re-applied on reconnect
+    exit_pin: std::sync::Mutex<(Vec<String>, bool)>,
+}
+
+impl TorProvider {
+    /// Creates a provider; the control port is connected on first use
+    pub fn new(config: TorConfig) -> Result<Self> {
+        if config.name.trim().is_empty() {
+            return Err(anyhow!("Tor provider name cannot be empty"));
+        }
+        if config.host.trim().is_empty() || config.control_port == 0 || config.socks_port == 0 {
+            return Err(anyhow!("Tor provider '{}' needs a host, control port and SOCKS port", config.name));
+        }
+        exit_country_codes(&config.exit_countries).with_context(|| format!("Tor provider '{}'", config.name))?;
+        Ok(Self {
+            exit_pin: std::sync::Mutex::new((config.exit_countries.clone(), config.strict_exit_nodes)),
+            config,
+            control: Mutex::new(None),
+            generations: std::sync::Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// The configuration this provider was created from
+    pub fn config(&self) -> &TorConfig {
+        &self.config
+    }
+
+    /// Runs `f` on the control connection, connecting (and reconnecting once) as needed
+    async fn with_control<T, F>(&self, f: F) -> Result<T>
+    where
+        F: for<'a> Fn(&'a mut TorControl) -> futures::future::BoxFuture<'a, Result<T>>,
+    {
+        let mut guard = self.control.lock().await;
+        for attempt in 0..2 {
+            if guard.is_none() {
+                let mut control = TorControl::connect(&self.config).await?;
+                let (countries, strict) = self.exit_pin();
+                control.set_exit_countries(&countries, strict).await?;
+                *guard = Some(control);
+            }
+            let control = guard.as_mut().expect("connected above");
+            match f(control).await {
+                Ok(value) => return Ok(value),
+                Err(e) if e.downcast_ref::<tokio::time::error::Elapsed>().is_some() => {
+                    // A late reply would be read as the next command's
+                    *guard = None;
+                    return Err(e);
+                }
+                Err(e) if attempt == 0 && is_connection_error(&e) => {
+                    warn!("Tor control connection lost, reconnecting: {}", e);
+                    *guard = None;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Err(anyhow!("Tor control connection unavailable"))
+    }
+
+    /// SOCKS username isolating a tab's streams
+    pub fn isolation_token(&self, tab_id: &str) -> String {
+        let generations = self.generations.lock().expect("generation lock poisoned");
+        format!("tab-{}-{}", tab_id, generations.get(tab_id).copied().unwrap_or(0))
+    }
+
+    /// Exit countries and strictness currently pinned
+    pub fn exit_pin(&self) -> (Vec<String>, bool) {
+        self.exit_pin.lock().expect("exit pin lock poisoned").clone()
+    }
+
+    /// Pool entry for a tab, isolated from every other tab
+    pub fn proxy_for_tab(&self, tab_id: &str) -> FreeProxy {
+        let (countries, _) = self.exit_pin();
+        let country = match countries.as_slice() {
+            [single] => single.trim().to_ascii_uppercase(),
+            _ => "XX".to_string(),
+        };
+        FreeProxy {
+            ip: self.config.host.clone(),
+            port: self.config.socks_port,
+            protocol: ProxyType::Socks5,
+            country: if country == "XX" { "Unknown".to_string() } else { country.clone() },
+            country_code: country,
+            anonymity: "elite".to_string(),
+            speed: 0,
+            uptime: 0.0,
+            last_checked: chrono::Utc::now().to_rfc3339(),
+            provider: self.config.name.clone(),
+            is_working: true,
+            username: Some(self.isolation_token(tab_id)),
+            // Tor ignores the password but isolates on it together with the username
+            password: Some("tor".to_string()),
+            ssh: None,
+        }
+    }
+
+    /// Connects to the control port, applying exit country pinning, and returns the tab's entry
+    pub async fn assign(&self, tab_id: &str) -> Result<FreeProxy> {
+        self.with_control(|_| Box::pin(async { Ok(()) })).await?;
+        Ok(self.proxy_for_tab(tab_id))
+    }
+
+    /// Requests new circuits and gives the tab fresh isolation credentials
+    pub async fn rotate(&self, tab_id: &str) -> Result<FreeProxy> {
+        self.new_identity().await?;
+        *self
+            .generations
+            .lock()
+            .expect("generation lock poisoned")
+            .entry(tab_id.to_string())
+            .or_insert(0) += 1;
+        info!("Rotated Tor circuit for tab {}", tab_id);
+        Ok(self.proxy_for_tab(tab_id))
+    }
+
+    /// Sends `SIGNAL NEWNYM`
+    ///
+    /// Tor rate-limits this signal; requests inside its window are deferred by the daemon.
+    pub async fn new_identity(&self) -> Result<()> {
+        self.with_control(|control| Box::pin(control.new_identity())).await
+    }
+
+    /// Pins exits to the given countries, or lifts the pin when empty
+    ///
+    /// The pin outlives control connections, replacing the configured countries.
+    pub async fn set_exit_countries(&self, countries: &[String], strict: bool) -> Result<()> {
+        exit_country_codes(countries)?;
+        let pinned = countries.to_vec();
+        self.with_control(move |control| {
+            let countries = pinned.clone();
+            Box::pin(async move { control.set_exit_countries(&countries, strict).await })
+        })
+        .await?;
+        *self.exit_pin.lock().expect("exit pin lock poisoned") = (countries.to_vec(), strict);
+        Ok(())
+    }
+
+    /// Current circuits
+    pub async fn circuits(&self) -> Result<Vec<TorCircuit>> {
+        self.with_control(|control| Box::pin(control.circuits())).await
+    }
+
+    /// Exit relay of the newest built circuit
+    pub async fn current_exit(&self) -> Result<Option<TorExitInfo>> {
+        self.with_control(|control| Box::pin(control.current_exit())).await
+    }
+}
+
+fn is_connection_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<std::io::Error>().is_some() || error.to_string().contains("connection closed")
+}
+
+#[async_trait]
+impl ProxyProvider for TorProvider {
+    fn name(&self) -> &str {
+        &self.config.name
+    }
+
+    fn rate_limit(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// A single shared entry; tabs get isolated entries through `ProxyRotationManager`
+    async fn fetch_proxies(&self) -> Result<Vec<FreeProxy>> {
+        Ok(vec![self.proxy_for_tab("shared")])
+    }
+}
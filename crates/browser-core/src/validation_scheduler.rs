@@ -0,0 +1,497 @@
+//! Adaptive Validation Scheduler
+//!
+//! Re-validates each proxy in a pool on its own interval instead of
+//! re-checking the whole pool on a fixed timer:
+//! - Critical and leased proxies are checked often
+//! - Healthy proxies are checked at a relaxed pace
+//! - Failing proxies back off exponentially until they are considered dead
+//! - Quarantined proxies are skipped until their (backed-off) term expires
+//! - A global budget caps the number of checks per minute
+//! - Throughput benchmarks on request, recorded in a health monitor
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+use crate::prelude::RateLimiter;
+use crate::proxy::FreeProxy;
+use crate::proxy_rotation::ProxyHealthMonitor;
+use crate::proxy_validator::{
+    GeoVerifier, ProxyQuarantineManager, ProxyValidator, ThroughputBenchmarkConfig, ThroughputResult, ValidationResult,
+};
+
+/// How urgently a proxy needs re-validation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValidationPriority {
+    /// Proxies the application cannot do without
+    Critical,
+    /// Proxies currently handed out to a tab or session
+    Leased,
+    /// Everything else in the pool
+    #[default]
+    Normal,
+}
+
+impl ValidationPriority {
+    fn rank(self) -> u8 {
+        match self {
+            ValidationPriority::Critical => 0,
+            ValidationPriority::Leased => 1,
+            ValidationPriority::Normal => 2,
+        }
+    }
+}
+
+/// Intervals and budget for the validation scheduler
+#[derive(Debug, Clone)]
+pub struct ValidationSchedulerConfig {
+    /// Re-check interval for critical proxies
+    pub critical_interval: Duration,
+    /// Re-check interval for leased proxies
+    pub leased_interval: Duration,
+    /// Re-check interval for healthy proxies
+    pub healthy_interval: Duration,
+    /// First re-check interval after a failure, doubled for each further failure
+    pub failing_interval: Duration,
+    /// Re-check interval for proxies considered dead
+    pub dead_interval: Duration,
+    /// Consecutive failures after which a proxy is considered dead
+    pub dead_after_failures: u32,
+    /// Maximum number of validations started per minute across the whole pool
+    pub max_checks_per_minute: u32,
+    /// How often the scheduler looks for due proxies
+    pub tick_interval: Duration,
+}
+
+impl Default for ValidationSchedulerConfig {
+    fn default() -> Self {
+        Self {
+            critical_interval: Duration::from_secs(30),
+            leased_interval: Duration::from_secs(120),
+            healthy_interval: Duration::from_secs(600),
+            failing_interval: Duration::from_secs(120),
+            dead_interval: Duration::from_secs(6 * 3600),
+            dead_after_failures: 6,
+            max_checks_per_minute: 60,
+            tick_interval: Duration::from_secs(1),
+        }
+    }
+}
+
+impl ValidationSchedulerConfig {
+    /// Time until the next check of a proxy with the given priority and failure streak
+    pub fn interval_for(&self, priority: ValidationPriority, consecutive_failures: u32) -> Duration {
+        match priority {
+            ValidationPriority::Critical => self.critical_interval,
+            ValidationPriority::Leased => self.leased_interval,
+            ValidationPriority::Normal if consecutive_failures == 0 => self.healthy_interval,
+            ValidationPriority::Normal if consecutive_failures >= self.dead_after_failures => self.dead_interval,
+            ValidationPriority::Normal => {
+                let multiplier = 2u32.saturating_pow(consecutive_failures - 1);
+                self.failing_interval.saturating_mul(multiplier).min(self.dead_interval)
+            }
+        }
+    }
+}
+
+/// Snapshot of the scheduler's state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationSchedulerStatus {
+    pub running: bool,
+    pub started_at: Option<DateTime<Utc>>,
+    pub tracked_proxies: usize,
+    pub due_proxies: usize,
+    pub failing_proxies: usize,
+    pub dead_proxies: usize,
+    pub quarantined_proxies: usize,
+    pub next_check_at: Option<DateTime<Utc>>,
+    pub checks_last_minute: u32,
+    pub max_checks_per_minute: u32,
+    pub total_checks: u64,
+    pub total_passed: u64,
+    pub total_failed: u64,
+    /// Due checks postponed because the per-minute budget was spent
+    pub deferred_checks: u64,
+}
+
+#[derive(Debug, Clone)]
+struct ScheduleEntry {
+    proxy: FreeProxy,
+    consecutive_failures: u32,
+    next_check: DateTime<Utc>,
+}
+
+/// Re-validates a proxy pool on per-proxy intervals within a global budget
+pub struct ValidationScheduler {
+    validator: ProxyValidator,
+    quarantine: Arc<ProxyQuarantineManager>,
+    geo_verifier: Option<GeoVerifier>,
+    throughput_config: ThroughputBenchmarkConfig,
+    health_monitor: Arc<ProxyHealthMonitor>,
+    pool: Arc<RwLock<Vec<FreeProxy>>>,
+    config: ValidationSchedulerConfig,
+    budget: RateLimiter,
+    entries: RwLock<HashMap<String, ScheduleEntry>>,
+    priorities: RwLock<HashMap<String, ValidationPriority>>,
+    task: parking_lot::Mutex<Option<JoinHandle<()>>>,
+    started_at: parking_lot::Mutex<Option<DateTime<Utc>>>,
+    total_checks: AtomicU64,
+    total_passed: AtomicU64,
+    total_failed: AtomicU64,
+    deferred_checks: AtomicU64,
+}
+
+impl ValidationScheduler {
+    /// Creates a new scheduler for the given pool.
+    ///
+    /// The validator reports tampering to `quarantine`; other failures are
+    /// recorded there once per check and lengthen the proxy's re-check interval
+    /// until it reaches the dead tier.
+    /// The scheduler does not run until [`ValidationScheduler::start`] is called.
+    pub fn new(
+        validator: ProxyValidator,
+        quarantine: Arc<ProxyQuarantineManager>,
+        pool: Arc<RwLock<Vec<FreeProxy>>>,
+        config: ValidationSchedulerConfig,
+    ) -> Self {
+        Self {
+            validator: validator.with_quarantine_manager(quarantine.clone()),
+            quarantine,
+            geo_verifier: None,
+            throughput_config: ThroughputBenchmarkConfig::default(),
+            health_monitor: Arc::new(ProxyHealthMonitor::new()),
+            pool,
+            budget: RateLimiter::new(config.max_checks_per_minute, 60_000),
+            config,
+            entries: RwLock::new(HashMap::new()),
+            priorities: RwLock::new(HashMap::new()),
+            task: parking_lot::Mutex::new(None),
+            started_at: parking_lot::Mutex::new(None),
+            total_checks: AtomicU64::new(0),
+            total_passed: AtomicU64::new(0),
+            total_failed: AtomicU64::new(0),
+            deferred_checks: AtomicU64::new(0),
+        }
+    }
+
+    /// Correct pool countries and check each working exit against its proxy's country
+    pub fn with_geo_verifier(mut self, verifier: GeoVerifier) -> Self {
+        self.geo_verifier = Some(verifier);
+        self
+    }
+
+    /// Payloads and sample count for [`ValidationScheduler::benchmark`]
+    pub fn with_throughput_benchmark(mut self, config: ThroughputBenchmarkConfig) -> Self {
+        self.throughput_config = config;
+        self
+    }
+
+    /// Monitor holding the bandwidth stats of benchmarked proxies, keyed by `ip:port`
+    pub fn health_monitor(&self) -> Arc<ProxyHealthMonitor> {
+        self.health_monitor.clone()
+    }
+
+    fn proxy_key(proxy: &FreeProxy) -> String {
+        format!("{}:{}", proxy.ip, proxy.port)
+    }
+
+    /// Start the background loop
+    ///
+    /// # Returns
+    /// False if the scheduler was already running
+    pub fn start(self: &Arc<Self>) -> bool {
+        let mut task = self.task.lock();
+        if task.as_ref().is_some_and(|handle| !handle.is_finished()) {
+            return false;
+        }
+
+        let scheduler = Arc::clone(self);
+        *task = Some(tokio::spawn(async move {
+            let mut interval = tokio::time::interval(scheduler.config.tick_interval);
+            loop {
+                interval.tick().await;
+                scheduler.run_once().await;
+            }
+        }));
+        *self.started_at.lock() = Some(Utc::now());
+        info!("Validation scheduler started");
+        true
+    }
+
+    /// Stop the background loop
+    ///
+    /// Aborting the loop drops its in-flight batch, which aborts the batch's
+    /// validation tasks.
+    ///
+    /// # Returns
+    /// False if the scheduler was not running
+    pub fn stop(&self) -> bool {
+        let Some(handle) = self.task.lock().take() else {
+            return false;
+        };
+        handle.abort();
+        *self.started_at.lock() = None;
+        info!("Validation scheduler stopped");
+        true
+    }
+
+    /// Whether the background loop is running
+    pub fn is_running(&self) -> bool {
+        self.task.lock().as_ref().is_some_and(|handle| !handle.is_finished())
+    }
+
+    /// Set how urgently a proxy is re-validated
+    ///
+    /// Raising the priority pulls the proxy's next check forward to fit the new interval.
+    pub async fn set_priority(&self, proxy: &FreeProxy, priority: ValidationPriority) {
+        let key = Self::proxy_key(proxy);
+        if priority == ValidationPriority::Normal {
+            self.priorities.write().await.remove(&key);
+            return;
+        }
+        self.priorities.write().await.insert(key.clone(), priority);
+
+        if let Some(entry) = self.entries.write().await.get_mut(&key) {
+            let interval = self.config.interval_for(priority, entry.consecutive_failures);
+            let latest = Utc::now() + chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::zero());
+            entry.next_check = entry.next_check.min(latest);
+        }
+    }
+
+    /// Current priority of a proxy
+    pub async fn priority(&self, proxy: &FreeProxy) -> ValidationPriority {
+        let key = Self::proxy_key(proxy);
+        self.priorities.read().await.get(&key).copied().unwrap_or_default()
+    }
+
+    /// When a proxy is next due for validation, if it is tracked
+    pub async fn next_check(&self, proxy: &FreeProxy) -> Option<DateTime<Utc>> {
+        let key = Self::proxy_key(proxy);
+        self.entries.read().await.get(&key).map(|entry| entry.next_check)
+    }
+
+    /// Benchmark the throughput of a pool proxy and record it in the health monitor
+    pub async fn benchmark(&self, ip: &str, port: u16) -> Result<ThroughputResult> {
+        let proxy = self
+            .pool
+            .read()
+            .await
+            .iter()
+            .find(|p| p.ip == ip && p.port == port)
+            .cloned()
+            .ok_or_else(|| anyhow!("Proxy {}:{} is not in the pool", ip, port))?;
+        let result = self.validator.benchmark_throughput(&proxy, &self.throughput_config).await?;
+        self.health_monitor.record_throughput(&Self::proxy_key(&proxy), &result).await;
+        Ok(result)
+    }
+
+    /// Run a single scheduling pass: release expired quarantines, then
+    /// validate every due proxy that fits in the budget
+    ///
+    /// # Returns
+    /// Number of proxies validated
+    pub async fn run_once(&self) -> usize {
+        self.release_expired().await;
+        let due = self.collect_due().await;
+        if due.is_empty() {
+            return 0;
+        }
+
+        let mut batch = Vec::with_capacity(due.len());
+        for proxy in &due {
+            if !self.budget.try_acquire().await {
+                break;
+            }
+            batch.push(proxy.clone());
+        }
+        let deferred = due.len() - batch.len();
+        if deferred > 0 {
+            self.deferred_checks.fetch_add(deferred as u64, Ordering::Relaxed);
+            debug!("Check budget spent, deferring {} due proxies", deferred);
+        }
+        if batch.is_empty() {
+            return 0;
+        }
+
+        debug!("Validating {} due proxies", batch.len());
+        let results = self.validator.validate_batch(&batch).await;
+        let count = results.len();
+        for (proxy, result) in results {
+            self.apply_result(&proxy, &result).await;
+        }
+        count
+    }
+
+    /// Put proxies that served their quarantine back in the pool, due immediately
+    async fn release_expired(&self) {
+        let released = self.quarantine.release_expired().await;
+        if released.is_empty() {
+            return;
+        }
+
+        info!("Re-validating {} proxies released from quarantine", released.len());
+        let now = Utc::now();
+        let mut entries = self.entries.write().await;
+        let mut pool = self.pool.write().await;
+        for proxy in released {
+            if let Some(entry) = entries.get_mut(&Self::proxy_key(&proxy)) {
+                entry.next_check = now;
+            }
+            if !pool.iter().any(|p| p.ip == proxy.ip && p.port == proxy.port) {
+                pool.push(proxy);
+            }
+        }
+    }
+
+    /// Sync schedule entries with the pool and return due proxies, most urgent first
+    async fn collect_due(&self) -> Vec<FreeProxy> {
+        let pool = self.pool.read().await.clone();
+        let priorities = self.priorities.read().await.clone();
+        let now = Utc::now();
+
+        let candidates: Vec<(String, DateTime<Utc>, FreeProxy)> = {
+            let mut entries = self.entries.write().await;
+            let keys: std::collections::HashSet<String> = pool.iter().map(Self::proxy_key).collect();
+            entries.retain(|key, _| keys.contains(key));
+            // Entries follow the pool's copy, e.g. after a country correction
+            for proxy in pool {
+                match entries.entry(Self::proxy_key(&proxy)) {
+                    std::collections::hash_map::Entry::Occupied(mut entry) => entry.get_mut().proxy = proxy,
+                    std::collections::hash_map::Entry::Vacant(entry) => {
+                        entry.insert(ScheduleEntry {
+                            proxy,
+                            consecutive_failures: 0,
+                            next_check: now,
+                        });
+                    }
+                }
+            }
+            entries
+                .iter()
+                .filter(|(_, entry)| entry.next_check <= now)
+                .map(|(key, entry)| (key.clone(), entry.next_check, entry.proxy.clone()))
+                .collect()
+        };
+
+        // Quarantine lookups await, so they run without holding the schedule lock
+        let mut due: Vec<(u8, DateTime<Utc>, FreeProxy)> = Vec::new();
+        for (key, next_check, proxy) in candidates {
+            if self.quarantine.is_quarantined(&proxy).await {
+                continue;
+            }
+            let priority = priorities.get(&key).copied().unwrap_or_default();
+            due.push((priority.rank(), next_check, proxy));
+        }
+        due.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.cmp(&b.1)));
+        due.into_iter().map(|(_, _, proxy)| proxy).collect()
+    }
+
+    /// Record a validation result and schedule the proxy's next check
+    async fn apply_result(&self, proxy: &FreeProxy, result: &ValidationResult) {
+        let key = Self::proxy_key(proxy);
+        let priority = self.priorities.read().await.get(&key).copied().unwrap_or_default();
+        self.total_checks.fetch_add(1, Ordering::Relaxed);
+
+        let misplaced = self.misplaced_exit(proxy, result).await;
+        let is_working = result.is_working && misplaced.is_none();
+        if is_working {
+            self.total_passed.fetch_add(1, Ordering::Relaxed);
+            self.quarantine.record_success(proxy).await;
+        } else {
+            self.total_failed.fetch_add(1, Ordering::Relaxed);
+            // The validator has already quarantined a tampering proxy
+            if result.tampering.is_none() {
+                let reason = misplaced
+                    .or_else(|| result.error.clone())
+                    .unwrap_or_else(|| "Connection failed".to_string());
+                if self.quarantine.record_failure(proxy, reason).await {
+                    debug!("Proxy {} quarantined; it stays scheduled for when it is released", key);
+                }
+            }
+        }
+        let quarantined = result.tampering.is_some();
+
+        let mut entries = self.entries.write().await;
+        let mut pool = self.pool.write().await;
+        if quarantined {
+            warn!("Proxy {} quarantined, removing from pool", key);
+            entries.remove(&key);
+            pool.retain(|p| !(p.ip == proxy.ip && p.port == proxy.port));
+            return;
+        }
+
+        if let Some(entry) = entries.get_mut(&key) {
+            entry.consecutive_failures = if is_working { 0 } else { entry.consecutive_failures + 1 };
+            let interval = self.config.interval_for(priority, entry.consecutive_failures);
+            entry.next_check = Utc::now() + chrono::Duration::from_std(interval).unwrap_or(chrono::Duration::hours(1));
+        }
+        if let Some(p) = pool.iter_mut().find(|p| p.ip == proxy.ip && p.port == proxy.port) {
+            p.is_working = is_working;
+            p.last_checked = Utc::now().to_rfc3339();
+            if let Some(verifier) = &self.geo_verifier {
+                verifier.correct_country(p);
+            }
+            if let Some(entry) = entries.get_mut(&key) {
+                entry.proxy = p.clone();
+            }
+        }
+    }
+
+    /// Describe a working proxy whose exit is outside its (corrected) country
+    ///
+    /// Exits that cannot be located are not held against the proxy.
+    async fn misplaced_exit(&self, proxy: &FreeProxy, result: &ValidationResult) -> Option<String> {
+        let (verifier, detected_ip) = self.geo_verifier.as_ref().zip(result.detected_ip.as_ref())?;
+        if !result.is_working {
+            return None;
+        }
+        let mut proxy = proxy.clone();
+        verifier.correct_country(&mut proxy);
+        let geo = verifier.verify_proxy_location(&proxy, detected_ip).await;
+        if geo.is_verified {
+            return None;
+        }
+        geo.detected_country
+            .map(|country| format!("Exit {} is in {}, not {}", detected_ip, country, geo.expected_country))
+    }
+
+    /// Get a snapshot of the scheduler's state
+    pub async fn status(&self) -> ValidationSchedulerStatus {
+        let now = Utc::now();
+        let entries = self.entries.read().await;
+        let quarantine = self.quarantine.get_stats().await;
+        let checks_last_minute = self.budget.current_usage().await;
+        let started_at = *self.started_at.lock();
+
+        ValidationSchedulerStatus {
+            running: self.is_running(),
+            started_at,
+            tracked_proxies: entries.len(),
+            due_proxies: entries.values().filter(|e| e.next_check <= now).count(),
+            failing_proxies: entries
+                .values()
+                .filter(|e| e.consecutive_failures > 0 && e.consecutive_failures < self.config.dead_after_failures)
+                .count(),
+            dead_proxies: entries
+                .values()
+                .filter(|e| e.consecutive_failures >= self.config.dead_after_failures)
+                .count(),
+            quarantined_proxies: quarantine.actively_quarantined,
+            next_check_at: entries.values().map(|e| e.next_check).min(),
+            checks_last_minute,
+            max_checks_per_minute: self.config.max_checks_per_minute,
+            total_checks: self.total_checks.load(Ordering::Relaxed),
+            total_passed: self.total_passed.load(Ordering::Relaxed),
+            total_failed: self.total_failed.load(Ordering::Relaxed),
+            deferred_checks: self.deferred_checks.load(Ordering::Relaxed),
+        }
+    }
+}
@@ -20,6 +20,7 @@ use tracing::{debug, info};
 use crate::proxy::{ProxySettings, FreeProxy};
 use crate::local_proxy::LocalProxyManager;
 use crate::pac_server::PacManager;
+use crate::config_manager::ProxyConfig;
 use crate::free_ip_providers::FreeIpProviderManager;
 use crate::proxy_rotation::{ProxyRotationManager, ProxyRotationStrategy, ProxySessionStats};
 
@@ -94,6 +95,21 @@ impl WebviewManager {
         }
     }
 
+    /// Share a provider pool and rotate through the gateways from the app config
+    pub fn with_proxy_config(
+        mut self,
+        provider_manager: Arc<RwLock<FreeIpProviderManager>>,
+        config: &ProxyConfig,
+    ) -> Self {
+        self.proxy_rotation_manager = Arc::new(RwLock::new(ProxyRotationManager::from_config(
+            provider_manager.clone(),
+            ProxyRotationStrategy::RoundRobin,
+            config,
+        )));
+        self.proxy_provider_manager = provider_manager;
+        self
+    }
+
     /// Start the proxy infrastructure
     /// Start the proxy infrastructure including PAC server
     ///
@@ -107,10 +123,8 @@ impl WebviewManager {
         self.local_proxy_manager.create_proxy_for_tab("default", None).await?;
         
         // Update proxy pool from all providers
-        {
-            let mut provider_manager = self.proxy_provider_manager.write().await;
-            provider_manager.update_proxy_pool().await?;
-        }
+        let proxies = FreeIpProviderManager::fetch_shared(&self.proxy_provider_manager).await;
+        FreeIpProviderManager::validate_shared(&self.proxy_provider_manager, proxies).await;
         
         info!("Proxy infrastructure started");
         Ok(())
@@ -655,6 +669,7 @@ pub async fn fetch_proxies_from_provider(
         .map_err(|e| e.to_string())?;
     
     manager.fetch_from_provider(&provider).await
+        .map(|proxies| proxies.iter().map(FreeProxy::redacted).collect())
         .map_err(|e| e.to_string())
 }
 
@@ -669,7 +684,9 @@ pub async fn test_proxy(
     let manager = FreeIpProviderManager::new()
         .map_err(|e| e.to_string())?;
     
-    Ok(manager.test_proxy(&proxy).await)
+    let mut result = manager.test_proxy(&proxy).await;
+    result.proxy = result.proxy.redacted();
+    Ok(result)
 }
 
 #[tauri::command]
@@ -680,6 +697,7 @@ pub async fn rotate_proxy_for_tab(
 ) -> Result<Option<FreeProxy>, String> {
     let manager = app_handle.state::<WebviewManager>();
     manager.rotate_proxy_for_tab(&tab_id).await
+        .map(|proxy| proxy.as_ref().map(FreeProxy::redacted))
         .map_err(|e| e.to_string())
 }
 
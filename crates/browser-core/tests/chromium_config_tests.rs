@@ -5,7 +5,7 @@
 
 use browser_core::chromium_engine::{
     BrowserEngineType, ChromiumEngineConfig, CookieIsolationMode, FingerprintConfig,
-    Geolocation, NetworkCondition, ProxyAuth,
+    Geolocation, NetworkCondition, NetworkProfile, ProxyAuth,
 };
 
 #[test]
@@ -89,6 +89,76 @@ fn test_network_condition_default() {
     assert_eq!(latency, 0.0);
 }
 
+#[test]
+fn test_network_condition_offline() {
+    let profile = NetworkCondition::Offline.to_profile(&[]).expect("Preset should resolve");
+
+    assert!(profile.offline);
+    assert_eq!(profile.name, "offline");
+    assert!(!profile.is_unthrottled());
+}
+
+#[test]
+fn test_network_condition_preset_profiles() {
+    let none = NetworkCondition::None.to_profile(&[]).expect("Preset should resolve");
+    assert!(none.is_unthrottled());
+    assert_eq!(none.cpu_throttling_rate, 1.0);
+
+    let slow = NetworkCondition::Slow3G.to_profile(&[]).expect("Preset should resolve");
+    assert_eq!(slow.name, "slow-3g");
+    assert_eq!(slow.download_throughput, 500.0 * 1024.0 / 8.0);
+    assert_eq!(slow.latency, 400.0);
+    assert!(!slow.offline);
+}
+
+#[test]
+fn test_network_condition_named_profile() {
+    let profiles = vec![NetworkProfile {
+        name: "low-end-mobile".to_string(),
+        download_throughput: 50_000.0,
+        upload_throughput: 25_000.0,
+        latency: 300.0,
+        offline: false,
+        cpu_throttling_rate: 6.0,
+    }];
+
+    let condition = NetworkCondition::Profile("low-end-mobile".to_string());
+    assert_eq!(condition.to_profile(&profiles), Some(profiles[0].clone()));
+    assert_eq!(condition.name(), "low-end-mobile");
+    assert!(NetworkCondition::Profile("missing".to_string()).to_profile(&profiles).is_none());
+}
+
+#[test]
+fn test_network_profiles_load_file() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+
+    let toml_path = dir.path().join("profiles.toml");
+    std::fs::write(&toml_path, r#"
+[[profile]]
+name = "satellite"
+latency = 600.0
+download_throughput = 125000.0
+
+[[profile]]
+name = "throttled-cpu"
+cpu_throttling_rate = 4.0
+"#).expect("Failed to write profiles");
+    let profiles = NetworkProfile::load_file(&toml_path).expect("Failed to load TOML profiles");
+    assert_eq!(profiles.len(), 2);
+    assert_eq!(profiles[0].latency, 600.0);
+    assert_eq!(profiles[0].upload_throughput, -1.0);
+    assert_eq!(profiles[0].cpu_throttling_rate, 1.0);
+    assert!(profiles[1].download_throughput < 0.0);
+    assert!(!profiles[1].is_unthrottled());
+
+    let json_path = dir.path().join("profiles.json");
+    std::fs::write(&json_path, r#"[{"name": "offline-lab", "offline": true}]"#)
+        .expect("Failed to write profiles");
+    let profiles = NetworkProfile::load_file(&json_path).expect("Failed to load JSON profiles");
+    assert_eq!(profiles.len(), 1);
+    assert!(profiles[0].offline);
+}
+
 #[test]
 fn test_fingerprint_config_default() {
     let config = FingerprintConfig::default();
@@ -222,6 +292,7 @@ fn test_chromium_engine_config_custom() {
         password: Some("pass".to_string()),
         dns_servers: vec!["1.1.1.1".to_string()],
         bypass_list: vec!["localhost".to_string()],
+        ssh: None,
     };
     
     let config = ChromiumEngineConfig {
@@ -242,6 +313,7 @@ fn test_chromium_engine_config_custom() {
         webrtc_protection: false,
         doh_server: None,
         network_condition: NetworkCondition::Fast3G,
+        network_profiles: vec![],
         fingerprint: FingerprintConfig::default(),
         cookie_isolation: CookieIsolationMode::PerTab,
         blocked_urls: vec!["*.ads.com".to_string()],
@@ -297,6 +369,8 @@ fn test_network_condition_serialization() {
             upload_throughput: 500.0,
             latency: 100.0,
         },
+        NetworkCondition::Offline,
+        NetworkCondition::Profile("satellite".to_string()),
     ];
     
     for condition in conditions {
@@ -310,5 +384,6 @@ fn test_network_condition_serialization() {
         assert_eq!(d1, d2);
         assert_eq!(u1, u2);
         assert_eq!(l1, l2);
+        assert_eq!(condition.name(), deserialized.name());
     }
 }
@@ -10,7 +10,10 @@ use browser_core::chromium_engine::{
     ChromiumEngine, ChromiumEngineConfig, EngineCapabilities,
 };
 use browser_core::proxy::{ProxySettings, ProxyType};
+use browser_core::proxy_validator::ProxyQuarantineManager;
 use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 
 #[tokio::test]
 async fn test_engine_new() {
@@ -126,6 +129,7 @@ async fn test_engine_with_proxy() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec!["localhost".to_string()],
+        ssh: None,
     };
     
     let config = ChromiumEngineConfig {
@@ -142,6 +146,70 @@ async fn test_engine_with_proxy() {
     assert_eq!(retrieved_proxy.port, Some(8080));
 }
 
+#[test]
+fn test_proxy_server_flag_omits_credentials() {
+    let proxy = ProxySettings {
+        proxy_type: ProxyType::Socks5,
+        host: Some("proxy.example.com".to_string()),
+        port: Some(1080),
+        username: Some("user".to_string()),
+        password: Some("pass".to_string()),
+        dns_servers: vec![],
+        bypass_list: vec![],
+        ssh: None,
+    };
+
+    // Chromium rejects credentials in a proxy server URL
+    assert_eq!(
+        proxy.to_proxy_server(),
+        Some("socks5://proxy.example.com:1080".to_string())
+    );
+}
+
+#[test]
+fn test_proxy_server_flag_for_relayed_and_direct() {
+    let mut proxy = ProxySettings {
+        host: Some("proxy.example.com".to_string()),
+        port: Some(22),
+        ..Default::default()
+    };
+
+    for proxy_type in [ProxyType::Direct, ProxyType::Ssh, ProxyType::Shadowsocks] {
+        proxy.proxy_type = proxy_type;
+        assert!(proxy.to_proxy_server().is_none());
+    }
+}
+
+#[test]
+fn test_proxy_settings_to_free_proxy() {
+    let proxy = ProxySettings {
+        proxy_type: ProxyType::Http,
+        host: Some("proxy.example.com".to_string()),
+        port: Some(8080),
+        username: Some("user".to_string()),
+        password: Some("pass".to_string()),
+        ..Default::default()
+    };
+
+    let entry = proxy.to_free_proxy("chromium").expect("Configuration error");
+    assert_eq!(entry.ip, "proxy.example.com");
+    assert_eq!(entry.port, 8080);
+    assert_eq!(entry.protocol, ProxyType::Http);
+    assert_eq!(entry.username, Some("user".to_string()));
+    assert_eq!(entry.provider, "chromium");
+
+    assert!(ProxySettings::default().to_free_proxy("chromium").is_none());
+}
+
+#[tokio::test]
+async fn test_engine_proxy_auth_failures_without_tab() {
+    let quarantine = Arc::new(ProxyQuarantineManager::new(3, Duration::from_secs(60), Duration::from_secs(3600)));
+    let engine = ChromiumEngine::new(ChromiumEngineConfig::default())
+        .with_quarantine_manager(quarantine);
+
+    assert_eq!(engine.get_proxy_auth_failures("missing-tab").await, 0);
+}
+
 #[tokio::test]
 async fn test_engine_with_custom_user_agent() {
     let config = ChromiumEngineConfig {
@@ -246,6 +314,7 @@ async fn test_engine_set_tab_proxy_without_browser() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     // Should fail because tab doesn't exist
@@ -123,6 +123,43 @@ async fn test_custom_network_condition() {
     shutdown_browser(engine).await.expect("Operation should succeed");
 }
 
+#[tokio::test]
+#[ignore]
+async fn test_runtime_network_condition() {
+    skip_if_no_chrome!();
+    
+    let config = create_test_config();
+    let engine = launch_browser_with_timeout(config, 30)
+        .await
+        .expect("Failed to launch browser");
+    
+    let tab = engine.create_tab(Some(test_urls::ABOUT_BLANK), None)
+        .await
+        .expect("Failed to create tab");
+    
+    let profile = engine.set_tab_network_condition(&tab.id, NetworkCondition::Offline)
+        .await
+        .expect("Failed to set network condition");
+    assert!(profile.offline);
+    assert_eq!(engine.get_tab_network_profile(&tab.id).await, Some(profile));
+    
+    let online = engine.execute_script(&tab.id, "navigator.onLine")
+        .await
+        .expect("Failed to execute script");
+    assert!(online.contains("false"));
+    
+    engine.set_tab_network_condition(&tab.id, NetworkCondition::Fast3G)
+        .await
+        .expect("Failed to set network condition");
+    engine.reload_tab(&tab.id).await.expect("Failed to reload tab");
+    
+    let metrics = engine.get_metrics().await;
+    assert!(metrics.loads_by_condition.contains_key("none"));
+    assert!(metrics.loads_by_condition.contains_key("fast-3g"));
+    
+    shutdown_browser(engine).await.expect("Operation should succeed");
+}
+
 #[tokio::test]
 #[ignore]
 async fn test_all_features_combined() {
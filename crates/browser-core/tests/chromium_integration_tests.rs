@@ -335,6 +335,7 @@ async fn test_tab_proxy_metadata() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     // Create tab without proxy
@@ -16,6 +16,7 @@ fn test_chromium_tab_creation() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     assert_eq!(tab.id, "tab-1");
@@ -37,6 +38,7 @@ fn test_chromium_tab_with_proxy() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     let tab = ChromiumTab {
@@ -47,6 +49,7 @@ fn test_chromium_tab_with_proxy() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     assert!(tab.proxy.is_some());
@@ -65,6 +68,7 @@ fn test_chromium_tab_clone() {
         is_loading: true,
         can_go_back: true,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     let tab2 = tab1.clone();
@@ -86,6 +90,7 @@ fn test_chromium_tab_serialization() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     let serialized = serde_json::to_string(&tab).expect("Failed to serialize");
@@ -177,6 +182,7 @@ async fn test_tab_state_tracking() {
         is_loading: true,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     // Simulate navigation completion
@@ -197,6 +203,7 @@ async fn test_tab_history_simulation() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     // Navigate to second page
@@ -226,6 +233,7 @@ async fn test_multiple_tabs_with_different_proxies() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     let proxy2 = ProxySettings {
@@ -236,6 +244,7 @@ async fn test_multiple_tabs_with_different_proxies() {
         password: Some("pass".to_string()),
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     let tab1 = ChromiumTab {
@@ -246,6 +255,7 @@ async fn test_multiple_tabs_with_different_proxies() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     let tab2 = ChromiumTab {
@@ -256,6 +266,7 @@ async fn test_multiple_tabs_with_different_proxies() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     let tab3 = ChromiumTab {
@@ -266,6 +277,7 @@ async fn test_multiple_tabs_with_different_proxies() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     // Verify each tab has correct proxy
@@ -289,6 +301,7 @@ async fn test_tab_proxy_update() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     assert!(tab.proxy.is_none());
@@ -302,6 +315,7 @@ async fn test_tab_proxy_update() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     tab.proxy = Some(proxy);
@@ -322,6 +336,7 @@ async fn test_tab_loading_states() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     // Start loading
@@ -356,6 +371,7 @@ fn test_tab_with_special_urls() {
             is_loading: false,
             can_go_back: false,
             can_go_forward: false,
+            fingerprint: None,
         };
         
         assert_eq!(tab.url, url);
@@ -372,6 +388,7 @@ fn test_tab_with_unicode_title() {
         is_loading: false,
         can_go_back: false,
         can_go_forward: false,
+        fingerprint: None,
     };
     
     assert!(tab.title.contains("测试"));
@@ -399,6 +416,7 @@ async fn test_engine_config_with_all_options() {
             password: Some("pass".to_string()),
             dns_servers: vec![],
             bypass_list: vec!["localhost".to_string()],
+            ssh: None,
         }),
         proxy_auth: Some(ProxyAuth {
             username: "user".to_string(),
@@ -411,6 +429,7 @@ async fn test_engine_config_with_all_options() {
         webrtc_protection: true,
         doh_server: Some("https://dns.google/dns-query".to_string()),
         network_condition: NetworkCondition::Fast3G,
+        network_profiles: vec![],
         fingerprint: FingerprintConfig::default(),
         cookie_isolation: CookieIsolationMode::PerTab,
         blocked_urls: vec!["*.ads.com".to_string()],
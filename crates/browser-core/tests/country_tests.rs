@@ -0,0 +1,199 @@
+//! Tests for the bundled country dataset
+//!
+//! This module tests:
+//! - Completeness of the ISO-3166 country list
+//! - Weighted languages and Accept-Language headers
+//! - Regional languages picked by region and city
+//! - Loading country files with the extended fields
+//! - Timezone, language and city selection in IPGenerator
+
+use std::collections::HashSet;
+use std::io::Write;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use virtual_ip::{
+    load_countries_from_file, CountryDatabase, GeoIpDatabase, GeoIpSource, IPGenerator, IPRange,
+};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+const DBIP_CITY_CSV: &str = "\
+ip_start,ip_end,continent,country,stateprov,city,latitude,longitude
+20.0.0.0,20.0.0.255,NA,US,California,Los Angeles,34.0522,-118.2437
+";
+
+fn us_range() -> IPRange {
+    IPRange {
+        start: Ipv4Addr::new(20, 0, 0, 0),
+        end: Ipv4Addr::new(20, 0, 0, 255),
+        country_code: "US".into(),
+        isp: "Example Telecom".into(),
+        asn: Some(64500),
+    }
+}
+
+// ============================================================================
+// Dataset Tests
+// ============================================================================
+
+#[test]
+fn test_bundled_dataset_is_complete() {
+    let countries = CountryDatabase::load_all_countries();
+    assert_eq!(countries.len(), 249);
+
+    let codes: HashSet<&str> = countries.iter().map(|c| c.code.as_str()).collect();
+    assert_eq!(codes.len(), countries.len(), "country codes must be unique");
+
+    for country in &countries {
+        assert_eq!(country.code.len(), 2, "{}", country.code);
+        assert_eq!(country.alpha3.len(), 3, "{}", country.code);
+        assert!(country.calling_code.starts_with('+'), "{}", country.code);
+        assert!(country.capital.is_some(), "{} has no capital", country.code);
+        assert_eq!(country.timezones.first(), Some(&country.timezone), "{}", country.code);
+        assert_eq!(
+            country.languages.first().map(|l| l.tag.as_str()),
+            Some(country.language.as_str()),
+            "{}",
+            country.code
+        );
+        assert!(country.languages.iter().all(|l| l.weight > 0.0), "{}", country.code);
+    }
+}
+
+#[test]
+fn test_find_and_top_countries() {
+    let us = CountryDatabase::find("usa").expect("Alpha-3 lookup");
+    assert_eq!(us.code, "US");
+    assert_eq!(us.calling_code, "+1");
+    assert_eq!(us.timezone, "America/New_York");
+    assert!(us.has_timezone("America/Los_Angeles"));
+    assert!(!us.has_timezone("Europe/London"));
+
+    let au = CountryDatabase::find("AU").expect("Alpha-2 lookup");
+    assert_eq!(au.timezone, "Australia/Sydney");
+    assert_eq!(au.capital.unwrap().name, "Canberra");
+
+    let top = CountryDatabase::load_top_countries();
+    assert!(top.iter().any(|c| c.code == "GB"));
+    assert!(top.len() < 249);
+}
+
+#[test]
+fn test_accept_language() {
+    let de = CountryDatabase::find("DE").unwrap();
+    assert_eq!(de.accept_language("de-DE"), "de-DE,de;q=0.9,en-US;q=0.8,en;q=0.7");
+
+    let ch = CountryDatabase::find("CH").unwrap();
+    let header = ch.accept_language("fr-CH");
+    assert!(header.starts_with("fr-CH,fr;q=0.9,de-CH;q=0.8,de;q=0.7"), "{}", header);
+    assert_eq!(header.matches("en;").count(), 1, "{}", header);
+
+    let us = CountryDatabase::find("US").unwrap();
+    assert_eq!(us.accept_language("en-US"), "en-US,en;q=0.9,es-US;q=0.8,es;q=0.7");
+}
+
+#[test]
+fn test_pick_language_respects_weights() {
+    let mut ca = CountryDatabase::find("CA").unwrap();
+    let mut rng = rand::thread_rng();
+    for _ in 0..50 {
+        let tag = ca.pick_language(&mut rng);
+        assert!(tag == "en-CA" || tag == "fr-CA", "{}", tag);
+    }
+
+    ca.languages.clear();
+    assert_eq!(ca.pick_language(&mut rng), "en-CA");
+}
+
+#[test]
+fn test_pick_language_at_follows_region() {
+    let ca = CountryDatabase::find("CA").unwrap();
+    let mut rng = rand::thread_rng();
+    for _ in 0..50 {
+        assert_eq!(ca.pick_language_at(&["Quebec", "Montreal"], &mut rng), "fr-CA");
+        assert_eq!(ca.pick_language_at(&["Ontario", "Toronto"], &mut rng), "en-CA");
+        // The capital fallback has no region and stays with the national language
+        assert_eq!(ca.pick_language_at(&["Ottawa"], &mut rng), "en-CA");
+    }
+
+    let ch = CountryDatabase::find("CH").unwrap();
+    assert_eq!(ch.pick_language_at(&["Ticino", "Lugano"], &mut rng), "it-CH");
+    assert_eq!(ch.pick_language_at(&["geneva"], &mut rng), "fr-CH");
+
+    // Without a known place the pick stays weighted over every language
+    let be = CountryDatabase::find("BE").unwrap();
+    let picked: HashSet<&str> = (0..200).map(|_| be.pick_language_at(&[], &mut rng)).collect();
+    assert!(picked.len() > 1, "{:?}", picked);
+}
+
+#[test]
+fn test_find_is_case_insensitive() {
+    assert_eq!(CountryDatabase::find("de").unwrap().code, "DE");
+    assert_eq!(CountryDatabase::find("DEU").unwrap().code, "DE");
+    assert!(CountryDatabase::find("zz").is_none());
+    assert!(CountryDatabase::find("").is_none());
+}
+
+#[test]
+fn test_load_countries_from_file() {
+    let dir = TempDir::new().expect("Temp dir");
+
+    // Older files without the extended fields still load
+    let minimal = dir.path().join("countries.json");
+    let mut file = std::fs::File::create(&minimal).unwrap();
+    file.write_all(br#"[{"code":"XK","name":"Kosovo","flag":"","timezone":"Europe/Belgrade","language":"sq-XK","currency":"EUR","is_top":false}]"#)
+        .unwrap();
+    let countries = load_countries_from_file(&minimal);
+    assert_eq!(countries.len(), 1);
+    assert!(countries[0].timezones.is_empty());
+    assert_eq!(countries[0].accept_language("sq-XK"), "sq-XK,sq;q=0.9,en-US;q=0.8,en;q=0.7");
+
+    let full = dir.path().join("full.json");
+    std::fs::write(&full, serde_json::to_string(&CountryDatabase::load_all_countries()).unwrap()).unwrap();
+    assert_eq!(load_countries_from_file(&full).len(), 249);
+
+    assert_eq!(load_countries_from_file(&dir.path().join("missing.json")).len(), 249);
+}
+
+// ============================================================================
+// Generator Tests
+// ============================================================================
+
+#[test]
+fn test_generate_for_country_uses_capital_and_primary_timezone() {
+    let generator = IPGenerator::new(CountryDatabase::load_all_countries(), vec![us_range()]);
+    let us = CountryDatabase::find("US").unwrap();
+
+    for _ in 0..20 {
+        let ip = generator.generate_for_country("US").expect("US should generate");
+        assert_eq!(ip.city, "Washington, D.C.");
+        assert_eq!(ip.timezone, "America/New_York");
+        assert!(us.languages.iter().any(|l| l.tag == ip.language));
+        assert!(ip.accept_language.starts_with(&ip.language));
+        assert_eq!(ip.isp, "Example Telecom");
+    }
+
+    let jp = generator.generate_for_country("JP").expect("Countries without ranges still generate");
+    assert_eq!(jp.timezone, "Asia/Tokyo");
+    assert_eq!(jp.language, "ja-JP");
+    assert_eq!(jp.accept_language, "ja-JP,ja;q=0.9,en-US;q=0.8,en;q=0.7");
+}
+
+#[test]
+fn test_generate_for_country_uses_geoip_city() {
+    let dir = TempDir::new().expect("Temp dir");
+    let path = dir.path().join("dbip-city-lite.csv");
+    std::fs::write(&path, DBIP_CITY_CSV).unwrap();
+    let db = GeoIpDatabase::open(vec![GeoIpSource::detect(path).unwrap()]).expect("Open");
+
+    let generator = IPGenerator::new(CountryDatabase::load_all_countries(), vec![us_range()])
+        .with_geoip_database(Arc::new(db));
+    let ip = generator.generate_for_country("US").unwrap();
+    assert_eq!(ip.city, "Los Angeles");
+    assert_eq!(ip.region, "California");
+    // DB-IP has no timezone column, so the primary one is kept
+    assert_eq!(ip.timezone, "America/New_York");
+}
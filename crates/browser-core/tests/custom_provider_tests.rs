@@ -0,0 +1,363 @@
+//! Tests for user-defined proxy providers
+//!
+//! This module tests:
+//! - Parsing plain text, CSV, JSON and HTML table responses
+//! - Field mapping and protocol/country handling, mapping country names to ISO codes
+//! - Skipping repeated addresses
+//! - Fetching from a local HTTP fixture with auth headers and pagination
+//! - Loading definitions from config files and registering them with the manager
+//! - Building the manager from the app's proxy config
+
+use browser_core::config_manager::ProxyConfig;
+use browser_core::custom_providers::{DeclarativeProvider, FieldMapping, ProviderDefinition, ResponseFormat};
+use browser_core::free_ip_providers::{FreeIpProviderManager, ProxyProvider};
+use browser_core::proxy::ProxyType;
+use std::collections::HashMap;
+use std::io::Write;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+fn definition(name: &str, url: &str, format: ResponseFormat) -> ProviderDefinition {
+    ProviderDefinition {
+        name: name.to_string(),
+        url: url.to_string(),
+        pages: 1,
+        format,
+        fields: None,
+        headers: HashMap::new(),
+        rate_limit_ms: 0,
+        timeout_secs: 5,
+        enabled: true,
+    }
+}
+
+fn mapping(ip: &str, port: Option<&str>) -> FieldMapping {
+    FieldMapping {
+        ip: ip.to_string(),
+        port: port.map(str::to_string),
+        protocol: None,
+        country: None,
+        country_code: None,
+        anonymity: None,
+        default_protocol: "http".to_string(),
+    }
+}
+
+/// Local HTTP server: `/page/N` returns one plain-text proxy for N <= 2 and
+/// nothing after; every request without `X-Api-Key: secret` gets a 401.
+async fn spawn_fixture() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else { break };
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..n]).to_string();
+                let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+                let authorized = request.lines().any(|l| l.eq_ignore_ascii_case("x-api-key: secret"));
+
+                let (status, body) = if !authorized {
+                    ("401 Unauthorized", String::new())
+                } else {
+                    match path.strip_prefix("/page/").and_then(|n| n.parse::<u8>().ok()) {
+                        Some(n) if n <= 2 => ("200 OK", format!("10.0.0.{}:8080\n", n)),
+                        Some(_) => ("200 OK", String::new()),
+                        None => ("404 Not Found", String::new()),
+                    }
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    status,
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    port
+}
+
+// ============================================================================
+// Parsing Tests
+// ============================================================================
+
+#[test]
+fn test_parse_plain_text() {
+    let provider = DeclarativeProvider::new(definition("Plain", "http://example.test/list", ResponseFormat::PlainText))
+        .expect("Valid definition");
+
+    let proxies = provider
+        .parse("1.2.3.4:8080\nsocks5://5.6.7.8:1080 # comment\n\nnot a proxy\n9.9.9.9:99999\n")
+        .expect("Parse");
+    assert_eq!(proxies.len(), 2);
+    assert_eq!(proxies[0].ip, "1.2.3.4");
+    assert_eq!(proxies[0].protocol, ProxyType::Http);
+    assert_eq!(proxies[1].port, 1080);
+    assert_eq!(proxies[1].protocol, ProxyType::Socks5);
+    assert!(proxies.iter().all(|p| p.provider == "Plain"));
+}
+
+#[test]
+fn test_parse_csv_with_header_names() {
+    let mut def = definition(
+        "Csv",
+        "http://example.test/list.csv",
+        ResponseFormat::Csv { delimiter: Some(';'), has_header: true },
+    );
+    def.fields = Some(FieldMapping {
+        protocol: Some("Type".to_string()),
+        country_code: Some("Country".to_string()),
+        ..mapping("Host", Some("Port"))
+    });
+    let provider = DeclarativeProvider::new(def).expect("Valid definition");
+
+    let proxies = provider
+        .parse("Host;Port;Type;Country\n1.1.1.1;3128;HTTPS;DE\n\"2.2.2.2\";bad;http;FR\n")
+        .expect("Parse");
+    assert_eq!(proxies.len(), 1);
+    assert_eq!(proxies[0].protocol, ProxyType::Https);
+    assert_eq!(proxies[0].country_code, "DE");
+    assert_eq!(proxies[0].country, "DE");
+}
+
+#[test]
+fn test_parse_json_with_field_paths() {
+    let mut def = definition(
+        "Json",
+        "http://example.test/api",
+        ResponseFormat::Json { path: "result.proxies".to_string() },
+    );
+    def.fields = Some(FieldMapping {
+        protocol: Some("protocols".to_string()),
+        country: Some("geo.country".to_string()),
+        country_code: Some("geo.code".to_string()),
+        anonymity: Some("level".to_string()),
+        ..mapping("address", Some("port"))
+    });
+    let provider = DeclarativeProvider::new(def).expect("Valid definition");
+
+    let body = r#"{"result": {"proxies": [
+        {"address": "3.3.3.3", "port": 8080, "protocols": ["http", "socks5"],
+         "geo": {"country": "Japan", "code": "JP"}, "level": "Elite"},
+        {"address": "4.4.4.4", "port": "1080"},
+        {"address": "5.5.5.5"}
+    ]}}"#;
+    let proxies = provider.parse(body).expect("Parse");
+    assert_eq!(proxies.len(), 2);
+    assert_eq!(proxies[0].protocol, ProxyType::Socks5);
+    assert_eq!(proxies[0].country, "Japan");
+    assert_eq!(proxies[0].country_code, "JP");
+    assert_eq!(proxies[0].anonymity, "elite");
+    assert_eq!(proxies[1].port, 1080);
+    assert_eq!(proxies[1].country_code, "XX");
+
+    assert!(provider.parse(r#"{"result": {}}"#).is_err());
+}
+
+#[test]
+fn test_parse_json_combined_address() {
+    let mut def = definition("Combined", "http://example.test/api", ResponseFormat::Json { path: String::new() });
+    def.fields = Some(mapping("proxy", None));
+    let provider = DeclarativeProvider::new(def).expect("Valid definition");
+
+    let proxies = provider.parse(r#"[{"proxy": "6.6.6.6:8888"}]"#).expect("Parse");
+    assert_eq!(proxies.len(), 1);
+    assert_eq!(proxies[0].ip, "6.6.6.6");
+    assert_eq!(proxies[0].port, 8888);
+}
+
+#[test]
+fn test_parse_html_table() {
+    let mut def = definition(
+        "Html",
+        "http://example.test/table",
+        ResponseFormat::HtmlTable {
+            row_selector: "table#proxies tbody tr".to_string(),
+            cell_selector: "td".to_string(),
+        },
+    );
+    def.fields = Some(FieldMapping {
+        country_code: Some("2".to_string()),
+        ..mapping("0", Some("1"))
+    });
+    let provider = DeclarativeProvider::new(def).expect("Valid definition");
+
+    let html = r#"<html><body>
+        <table id="proxies"><tbody>
+            <tr><td> 7.7.7.7 </td><td>80</td><td>US</td></tr>
+            <tr><td>8.8.4.4</td><td>n/a</td><td>US</td></tr>
+        </tbody></table>
+        <table><tbody><tr><td>9.9.9.9</td><td>81</td></tr></tbody></table>
+    </body></html>"#;
+    let proxies = provider.parse(html).expect("Parse");
+    assert_eq!(proxies.len(), 1);
+    assert_eq!(proxies[0].ip, "7.7.7.7");
+    assert_eq!(proxies[0].country_code, "US");
+}
+
+#[test]
+fn test_country_names_map_to_codes_and_repeats_are_skipped() {
+    let mut def = definition("Names", "http://example.test/api", ResponseFormat::Json { path: String::new() });
+    def.fields = Some(FieldMapping {
+        country: Some("country".to_string()),
+        ..mapping("ip", Some("port"))
+    });
+    let provider = DeclarativeProvider::new(def).expect("Valid definition");
+
+    let proxies = provider
+        .parse(
+            r#"[{"ip": "1.1.1.1", "port": 80, "country": "germany"},
+                {"ip": "2.2.2.2", "port": 80, "country": "Atlantis"},
+                {"ip": "3.3.3.3", "port": 80, "country": "FRA"},
+                {"ip": "1.1.1.1", "port": 80, "country": "France"}]"#,
+        )
+        .expect("Parse");
+    assert_eq!(proxies.len(), 3);
+    assert_eq!((proxies[0].country.as_str(), proxies[0].country_code.as_str()), ("germany", "DE"));
+    assert_eq!((proxies[1].country.as_str(), proxies[1].country_code.as_str()), ("Atlantis", "XX"));
+    assert_eq!(proxies[2].country_code, "FR");
+}
+
+#[test]
+fn test_invalid_definitions_are_rejected() {
+    let bad_selector = definition(
+        "BadSelector",
+        "http://example.test/",
+        ResponseFormat::HtmlTable { row_selector: "tr[".to_string(), cell_selector: "td".to_string() },
+    );
+    assert!(DeclarativeProvider::new(bad_selector).is_err());
+
+    let mut named_cells = definition(
+        "NamedCells",
+        "http://example.test/",
+        ResponseFormat::Csv { delimiter: None, has_header: false },
+    );
+    named_cells.fields = Some(mapping("host", Some("port")));
+    assert!(DeclarativeProvider::new(named_cells).is_err());
+
+    assert!(DeclarativeProvider::new(definition("BadUrl", "not a url", ResponseFormat::PlainText)).is_err());
+    assert!(DeclarativeProvider::new(definition(" ", "http://example.test/", ResponseFormat::PlainText)).is_err());
+}
+
+// ============================================================================
+// Fetch Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_fetch_with_auth_header_and_pages() {
+    let port = spawn_fixture().await;
+    std::env::set_var("CUSTOM_PROVIDER_TEST_KEY", "secret");
+
+    let mut def = definition(
+        "Paged",
+        &format!("http://127.0.0.1:{}/page/{{page}}", port),
+        ResponseFormat::PlainText,
+    );
+    def.pages = 5;
+    def.headers.insert("X-Api-Key".to_string(), "${CUSTOM_PROVIDER_TEST_KEY}".to_string());
+    let provider = DeclarativeProvider::new(def.clone()).expect("Valid definition");
+
+    // Stops at the first empty page
+    let proxies = provider.fetch_proxies().await.expect("Fetch");
+    let ips: Vec<&str> = proxies.iter().map(|p| p.ip.as_str()).collect();
+    assert_eq!(ips, vec!["10.0.0.1", "10.0.0.2"]);
+
+    // Without the header the fixture refuses the request
+    def.headers.clear();
+    let provider = DeclarativeProvider::new(def).expect("Valid definition");
+    assert!(provider.fetch_proxies().await.is_err());
+}
+
+#[tokio::test]
+async fn test_manager_runs_custom_providers() {
+    let port = spawn_fixture().await;
+    std::env::set_var("CUSTOM_PROVIDER_MANAGER_KEY", "secret");
+
+    let mut def = definition("Local", &format!("http://127.0.0.1:{}/page/1", port), ResponseFormat::PlainText);
+    def.headers.insert("X-Api-Key".to_string(), "${CUSTOM_PROVIDER_MANAGER_KEY}".to_string());
+    let mut disabled = definition("Disabled", "http://127.0.0.1:1/", ResponseFormat::PlainText);
+    disabled.enabled = false;
+
+    let mut manager = FreeIpProviderManager::new()
+        .expect("Manager")
+        .with_provider_definitions(vec![def.clone(), disabled])
+        .expect("Register definitions");
+    assert_eq!(manager.custom_provider_names(), vec!["Local".to_string()]);
+
+    let proxies = manager.fetch_from_custom("Local").await.expect("Fetch");
+    assert_eq!(proxies.len(), 1);
+    assert_eq!(proxies[0].provider, "Local");
+    assert!(manager.fetch_from_custom("Missing").await.is_err());
+
+    // Names must be unique, including against the built-ins
+    assert!(FreeIpProviderManager::new().expect("Manager").with_provider_definitions(vec![def.clone(), def]).is_err());
+    let builtin = definition("GeoNode", "http://example.test/", ResponseFormat::PlainText);
+    assert!(FreeIpProviderManager::new().expect("Manager").with_provider_definitions(vec![builtin]).is_err());
+}
+
+#[test]
+fn test_manager_from_config() {
+    let mut config = ProxyConfig::default();
+    let mut disabled = definition("Disabled", "http://127.0.0.1:1/", ResponseFormat::PlainText);
+    disabled.enabled = false;
+    config.custom_providers = vec![
+        definition("Local", "http://127.0.0.1:1/", ResponseFormat::PlainText),
+        // Invalid: collides with a built-in
+        definition("GeoNode", "http://example.test/", ResponseFormat::PlainText),
+        disabled,
+    ];
+
+    let manager = FreeIpProviderManager::from_config(&config).expect("Manager");
+    assert_eq!(manager.custom_provider_names(), vec!["Local".to_string()]);
+}
+
+#[test]
+fn test_load_definitions_from_toml() {
+    let dir = TempDir::new().expect("Temp dir");
+    let path = dir.path().join("providers.toml");
+    let mut file = std::fs::File::create(&path).expect("Create file");
+    file.write_all(
+        br#"
+[[provider]]
+name = "MyList"
+url = "https://lists.example.com/proxies?page={page}"
+pages = 3
+rate_limit_ms = 2500
+headers = { Authorization = "Bearer ${MY_LIST_TOKEN}" }
+format = { type = "json", path = "data" }
+fields = { ip = "ip", port = "port", country_code = "country" }
+
+[[provider]]
+name = "Table"
+url = "https://table.example.com/"
+format = { type = "html_table", row_selector = "table tr" }
+"#,
+    )
+    .expect("Write file");
+
+    let definitions = ProviderDefinition::load_file(&path).expect("Load definitions");
+    assert_eq!(definitions.len(), 2);
+    assert_eq!(definitions[0].page_urls().len(), 3);
+    assert_eq!(definitions[0].rate_limit_ms, 2500);
+    assert_eq!(definitions[0].format, ResponseFormat::Json { path: "data".to_string() });
+    assert_eq!(
+        definitions[1].format,
+        ResponseFormat::HtmlTable { row_selector: "table tr".to_string(), cell_selector: "td".to_string() }
+    );
+    assert_eq!(definitions[1].field_mapping().ip, "0");
+    assert!(definitions[1].enabled);
+
+    for definition in definitions {
+        assert!(DeclarativeProvider::new(definition).is_ok());
+    }
+}
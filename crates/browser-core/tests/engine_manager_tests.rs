@@ -64,6 +64,7 @@ async fn test_set_proxy() {
         password: Some("pass".to_string()),
         dns_servers: vec![],
         bypass_list: vec!["localhost".to_string()],
+        ssh: None,
     };
     
     let result = manager.set_proxy(Some(proxy.clone())).await;
@@ -90,6 +91,7 @@ async fn test_clear_proxy() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     manager.set_proxy(Some(proxy)).await.expect("Operation should succeed");
@@ -179,6 +181,7 @@ async fn test_proxy_with_authentication() {
         password: Some("secret123".to_string()),
         dns_servers: vec![],
         bypass_list: vec!["*.local".to_string(), "127.0.0.1".to_string()],
+        ssh: None,
     };
     
     manager.set_proxy(Some(proxy)).await.expect("Operation should succeed");
@@ -284,6 +287,7 @@ async fn test_proxy_with_different_types() {
             password: None,
             dns_servers: vec![],
             bypass_list: vec![],
+            ssh: None,
         };
         
         manager.set_proxy(Some(proxy.clone())).await.expect("Operation should succeed");
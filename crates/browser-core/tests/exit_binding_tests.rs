@@ -0,0 +1,277 @@
+//! Tests for binding tab virtual IPs to exit proxies
+//!
+//! This module tests:
+//! - Filling observed IP, city and ISP from a verified exit
+//! - Skipping proxies whose exit is in another country
+//! - Leaving exits without a known country unverified
+//! - Binding IPv6 exits
+//! - Binding to proxies once checks mark them working in the shared pool
+//! - Marking proxies that fail verification as not working
+//! - Fallback when no proxy in the requested country verifies
+//! - Rotating a tab onto a new exit
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use browser_core::{
+    ExitBindingConfig, ExitVerifier, FreeProxy, ProxyManager, ProxyType, PublicIpInfo, TabIPManager,
+};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use virtual_ip::{CountryDatabase, IPGenerator, IPRange};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+/// Answers exit checks from a table keyed by proxy port.
+struct StubVerifier {
+    exits: HashMap<u16, PublicIpInfo>,
+}
+
+#[async_trait]
+impl ExitVerifier for StubVerifier {
+    async fn verify_exit(&self, proxy: &FreeProxy) -> Result<PublicIpInfo> {
+        self.exits
+            .get(&proxy.port)
+            .cloned()
+            .ok_or_else(|| anyhow!("connection refused"))
+    }
+}
+
+fn create_test_proxy(port: u16, country_code: &str) -> FreeProxy {
+    FreeProxy {
+        ip: "10.0.0.1".to_string(),
+        port,
+        protocol: ProxyType::Http,
+        country: country_code.to_string(),
+        country_code: country_code.to_string(),
+        anonymity: "elite".to_string(),
+        speed: 100,
+        uptime: 99.0,
+        last_checked: Utc::now().to_rfc3339(),
+        provider: "TestProvider".to_string(),
+        is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
+    }
+}
+
+fn exit_info(ip: &str, country_code: &str, city: &str) -> PublicIpInfo {
+    PublicIpInfo {
+        ip: ip.to_string(),
+        country: None,
+        country_code: Some(country_code.to_string()),
+        city: Some(city.to_string()),
+        region: Some("Test Region".to_string()),
+        isp: Some("Exit ISP".to_string()),
+        timezone: Some("America/Chicago".to_string()),
+        lat: None,
+        lon: None,
+    }
+}
+
+fn test_generator() -> IPGenerator {
+    let ranges = vec![
+        IPRange {
+            start: Ipv4Addr::new(20, 0, 0, 0),
+            end: Ipv4Addr::new(20, 0, 0, 255),
+            country_code: "US".into(),
+            isp: "Generated Telecom".into(),
+            asn: None,
+        },
+        IPRange {
+            start: Ipv4Addr::new(30, 0, 0, 0),
+            end: Ipv4Addr::new(30, 0, 0, 255),
+            country_code: "DE".into(),
+            isp: "Generated GmbH".into(),
+            asn: None,
+        },
+    ];
+    IPGenerator::new(CountryDatabase::load_all_countries(), ranges)
+}
+
+fn manager(
+    pool: Arc<RwLock<Vec<FreeProxy>>>,
+    exits: HashMap<u16, PublicIpInfo>,
+    require_exit: bool,
+) -> TabIPManager {
+    TabIPManager::new(test_generator()).with_exit_verifier(
+        pool,
+        Arc::new(StubVerifier { exits }),
+        ExitBindingConfig {
+            require_exit,
+            max_attempts: 5,
+            ..Default::default()
+        },
+    )
+}
+
+// ============================================================================
+// Binding Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_create_tab_uses_observed_exit() {
+    let pool = Arc::new(RwLock::new(vec![create_test_proxy(8001, "US")]));
+    let exits = HashMap::from([(8001, exit_info("203.0.113.7", "US", "Dallas"))]);
+    let manager = manager(pool, exits, false);
+
+    let tab = manager.create_tab("US").await.expect("Tab should be created");
+    let ip = &tab.virtual_ip;
+    assert!(ip.exit_verified);
+    assert_eq!(ip.ip, Ipv4Addr::new(203, 0, 113, 7));
+    assert_eq!(ip.city, "Dallas");
+    assert_eq!(ip.isp, "Exit ISP");
+    assert_eq!(ip.timezone, "America/Chicago");
+    assert_eq!(ip.proxy_url.as_deref(), Some("http://10.0.0.1:8001"));
+    assert_eq!(tab.network_config.proxy_url, ip.proxy_url);
+    assert_eq!(tab.fingerprint.timezone, "America/Chicago");
+
+    let exit = manager.exit_proxy(&tab.tab_id).await.expect("Exit proxy recorded");
+    assert_eq!(exit.port, 8001);
+
+    manager.close_tab(&tab.tab_id).await.unwrap();
+    assert!(manager.exit_proxy(&tab.tab_id).await.is_none());
+}
+
+#[tokio::test]
+async fn test_skips_mismatched_and_failing_proxies() {
+    let pool = Arc::new(RwLock::new(vec![
+        create_test_proxy(8001, "US"),
+        create_test_proxy(8002, "US"),
+        create_test_proxy(8003, "US"),
+        create_test_proxy(8004, "DE"),
+    ]));
+    let exits = HashMap::from([
+        // Listed as US but exits in Germany
+        (8001, exit_info("198.51.100.1", "DE", "Berlin")),
+        (8003, exit_info("203.0.113.9", "US", "Austin")),
+        (8004, exit_info("198.51.100.4", "DE", "Munich")),
+    ]);
+    let manager = manager(pool.clone(), exits, true);
+
+    // Candidates are shuffled; enough tabs that the unreachable proxy gets tried
+    for _ in 0..40 {
+        let tab = manager.create_tab("US").await.expect("A US exit verifies");
+        assert_eq!(tab.virtual_ip.city, "Austin");
+        assert_eq!(manager.exit_proxy(&tab.tab_id).await.unwrap().port, 8003);
+    }
+
+    let pool = pool.read().await;
+    let working: Vec<u16> = pool.iter().filter(|p| p.is_working).map(|p| p.port).collect();
+    // Only the proxy that could not be reached is marked dead
+    assert!(!working.contains(&8002));
+    assert!(working.contains(&8001));
+    assert!(working.contains(&8003));
+}
+
+#[tokio::test]
+async fn test_fallback_without_matching_proxy() {
+    let pool = Arc::new(RwLock::new(vec![create_test_proxy(8004, "DE")]));
+    let exits = HashMap::from([(8004, exit_info("198.51.100.4", "DE", "Munich"))]);
+
+    let lenient = manager(pool.clone(), exits.clone(), false);
+    let tab = lenient.create_tab("US").await.expect("Falls back to a generated IP");
+    assert!(!tab.virtual_ip.exit_verified);
+    assert_eq!(tab.virtual_ip.isp, "Generated Telecom");
+    assert!(lenient.exit_proxy(&tab.tab_id).await.is_none());
+
+    let strict = manager(pool, exits, true);
+    let err = strict.create_tab("US").await.expect_err("No US exit exists");
+    assert!(err.to_string().contains("US"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_exit_without_country_is_unverified() {
+    let pool = Arc::new(RwLock::new(vec![create_test_proxy(8001, "US")]));
+    let mut info = exit_info("203.0.113.7", "US", "Dallas");
+    info.country_code = None;
+    let exits = HashMap::from([(8001, info)]);
+
+    let lenient = manager(pool.clone(), exits.clone(), false);
+    let tab = lenient.create_tab("US").await.expect("Falls back to a generated IP");
+    assert!(!tab.virtual_ip.exit_verified);
+    assert_ne!(tab.virtual_ip.city, "Dallas");
+
+    let strict = manager(pool.clone(), exits, true);
+    assert!(strict.create_tab("US").await.is_err());
+    // The proxy answered, so it stays in the pool
+    assert!(pool.read().await[0].is_working);
+}
+
+#[tokio::test]
+async fn test_binds_ipv6_exit() {
+    let pool = Arc::new(RwLock::new(vec![create_test_proxy(8001, "US")]));
+    let exits = HashMap::from([(8001, exit_info("2001:db8::7", "US", "Dallas"))]);
+    let manager = manager(pool, exits, true);
+
+    let tab = manager.create_tab("US").await.expect("IPv6 exit verifies");
+    assert!(tab.virtual_ip.exit_verified);
+    assert_eq!(tab.virtual_ip.ip, "2001:db8::7".parse::<IpAddr>().unwrap());
+}
+
+#[tokio::test]
+async fn test_binds_after_checks_recorded_in_shared_pool() {
+    let proxy_manager = ProxyManager::new();
+    let mut fetched = create_test_proxy(8001, "US");
+    fetched.is_working = false;
+    proxy_manager.add_free_proxies(vec![fetched.clone()]).await;
+
+    let exits = HashMap::from([(8001, exit_info("203.0.113.7", "US", "Dallas"))]);
+    let manager = manager(proxy_manager.proxy_pool(), exits, true);
+    assert!(manager.create_tab("US").await.is_err(), "Unchecked proxies are not used");
+
+    let mut checked = fetched;
+    checked.is_working = true;
+    checked.speed = 42;
+    proxy_manager.record_checks(&[checked, create_test_proxy(9999, "US")]).await;
+    let pool = proxy_manager.get_free_proxies().await;
+    assert_eq!(pool.len(), 1, "Unknown proxies are not added");
+    assert_eq!(pool[0].speed, 42);
+
+    let tab = manager.create_tab("US").await.expect("Checked proxy binds");
+    assert!(tab.virtual_ip.exit_verified);
+}
+
+#[tokio::test]
+async fn test_manager_without_pool_generates_ip() {
+    let manager = TabIPManager::new(test_generator());
+    let tab = manager.create_tab("DE").await.unwrap();
+    assert!(!tab.virtual_ip.exit_verified);
+    assert!(tab.network_config.proxy_url.is_none());
+    assert!(manager.exit_proxy(&tab.tab_id).await.is_none());
+}
+
+// ============================================================================
+// Rotation Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_rotate_ip_moves_to_new_exit() {
+    let pool = Arc::new(RwLock::new(vec![
+        create_test_proxy(8001, "US"),
+        create_test_proxy(8004, "DE"),
+    ]));
+    let exits = HashMap::from([
+        (8001, exit_info("203.0.113.7", "US", "Dallas")),
+        (8004, exit_info("198.51.100.4", "DE", "Munich")),
+    ]);
+    let manager = manager(pool, exits, true);
+
+    let tab = manager.create_tab("US").await.unwrap();
+    let rotated = manager.rotate_ip(&tab.tab_id, Some("DE")).await.expect("Rotates to DE");
+    assert_eq!(rotated.country_code, "DE");
+    assert_eq!(rotated.city, "Munich");
+    assert!(rotated.exit_verified);
+    assert_eq!(manager.exit_proxy(&tab.tab_id).await.unwrap().port, 8004);
+
+    let stored = manager.get_tab(&tab.tab_id).await.unwrap();
+    assert_eq!(stored.virtual_ip.ip, Ipv4Addr::new(198, 51, 100, 4));
+    assert_eq!(stored.network_config.proxy_url.as_deref(), Some("http://10.0.0.1:8004"));
+
+    assert!(manager.rotate_ip("missing", None).await.is_err());
+}
@@ -0,0 +1,192 @@
+//! Tests for exit-derived tab locations
+//!
+//! This module tests:
+//! - Timezone, locale and Accept-Language from a virtual IP
+//! - Locations from observed exits and GeoIP records
+//! - Falling back to the capital without city coordinates
+//! - Seeded, reproducible and bounded geolocation jitter
+
+use browser_core::{ExitLocation, PublicIpInfo};
+use std::net::Ipv4Addr;
+use virtual_ip::VirtualIP;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+fn berlin_ip() -> VirtualIP {
+    VirtualIP {
+        ip: Ipv4Addr::new(85, 214, 132, 117).into(),
+        country_code: "DE".to_string(),
+        country: "Germany".to_string(),
+        city: "Berlin".to_string(),
+        region: "Berlin".to_string(),
+        timezone: "Europe/Berlin".to_string(),
+        language: "de-DE".to_string(),
+        currency: "EUR".to_string(),
+        isp: "Strato AG".to_string(),
+        proxy_url: None,
+        accept_language: "de-DE,de;q=0.9,en-US;q=0.8,en;q=0.7".to_string(),
+        exit_verified: true,
+    }
+}
+
+fn observed_exit() -> PublicIpInfo {
+    PublicIpInfo {
+        ip: "203.0.113.9\n".to_string(),
+        country: Some("France".to_string()),
+        country_code: Some("fr".to_string()),
+        city: Some("Lyon".to_string()),
+        region: None,
+        isp: None,
+        timezone: None,
+        lat: Some(45.764),
+        lon: Some(4.8357),
+    }
+}
+
+/// Great-circle distance in kilometres
+fn distance_km(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (a.0.to_radians(), b.0.to_radians());
+    let dlat = lat2 - lat1;
+    let dlon = (b.1 - a.1).to_radians();
+    let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+    2.0 * 6371.0 * h.sqrt().asin()
+}
+
+// ============================================================================
+// Virtual IP Tests
+// ============================================================================
+
+#[test]
+fn test_from_virtual_ip() {
+    let exit = ExitLocation::from_virtual_ip(&berlin_ip());
+
+    assert_eq!(exit.ip, "85.214.132.117");
+    assert_eq!(exit.country_code, "DE");
+    assert_eq!(exit.city.as_deref(), Some("Berlin"));
+    assert_eq!(exit.timezone, "Europe/Berlin");
+    assert_eq!(exit.locale, "de-DE");
+    assert_eq!(exit.accept_language, "de-DE,de;q=0.9,en-US;q=0.8,en;q=0.7");
+    // Berlin is the capital, so its coordinates are known without GeoIP
+    assert!(exit.latitude.is_some() && exit.longitude.is_some());
+}
+
+#[test]
+fn test_from_virtual_ip_fills_missing_values_from_country() {
+    let mut ip = berlin_ip();
+    ip.accept_language = String::new();
+    ip.timezone = String::new();
+    ip.city = "Unknown".to_string();
+    let exit = ExitLocation::from_virtual_ip(&ip);
+
+    assert!(exit.accept_language.starts_with("de-DE,de;q=0.9"));
+    assert_eq!(exit.timezone, "Europe/Berlin");
+    assert!(exit.city.is_none());
+}
+
+#[test]
+fn test_with_coordinates() {
+    let exit = ExitLocation::from_virtual_ip(&berlin_ip()).with_coordinates(48.1374, 11.5755);
+
+    assert_eq!(exit.latitude, Some(48.1374));
+    assert_eq!(exit.longitude, Some(11.5755));
+}
+
+// ============================================================================
+// Observed Exit Tests
+// ============================================================================
+
+#[test]
+fn test_from_public_ip() {
+    let exit = ExitLocation::from_public_ip(&observed_exit()).expect("Exit should be located");
+
+    assert_eq!(exit.ip, "203.0.113.9");
+    assert_eq!(exit.country_code, "FR");
+    assert_eq!(exit.city.as_deref(), Some("Lyon"));
+    // No timezone observed, so the country's primary one
+    assert_eq!(exit.timezone, "Europe/Paris");
+    assert!(exit.locale.starts_with("fr"));
+    assert!(exit.accept_language.starts_with(&exit.locale));
+    assert_eq!(
+        (exit.latitude, exit.longitude),
+        (Some(45.764), Some(4.8357))
+    );
+}
+
+#[test]
+fn test_from_public_ip_falls_back_to_capital() {
+    let mut info = observed_exit();
+    info.lat = None;
+    info.timezone = Some("Europe/Paris".to_string());
+    let exit = ExitLocation::from_public_ip(&info).expect("Exit should be located");
+
+    let (lat, lon) = (exit.latitude.unwrap(), exit.longitude.unwrap());
+    assert!(distance_km((lat, lon), (48.8566, 2.3522)) < 10.0);
+}
+
+#[test]
+fn test_from_public_ip_without_country() {
+    let mut info = observed_exit();
+    info.country_code = None;
+    assert!(ExitLocation::from_public_ip(&info).is_none());
+
+    info.country_code = Some("ZZ".to_string());
+    assert!(ExitLocation::from_public_ip(&info).is_none());
+}
+
+// ============================================================================
+// Geolocation Tests
+// ============================================================================
+
+#[test]
+fn test_geolocation_is_seeded() {
+    let exit = ExitLocation::from_public_ip(&observed_exit()).unwrap();
+
+    let position = |seed: &str| exit.geolocation(seed).map(|g| (g.latitude, g.longitude));
+
+    assert_eq!(position("tab-1"), position("tab-1"));
+    assert_ne!(position("tab-1"), position("tab-2"));
+}
+
+#[test]
+fn test_geolocation_is_reproducible() {
+    let exit = ExitLocation::from_public_ip(&observed_exit()).unwrap();
+
+    // Pinned, so a dependency bump can't move every tab's position
+    let geo = exit.geolocation("tab-1").unwrap();
+    assert!((geo.latitude - 45.756_567_105).abs() < 1e-6);
+    assert!((geo.longitude - 4.890_641_078).abs() < 1e-6);
+    assert!((geo.accuracy - 55.785_375_680).abs() < 1e-6);
+}
+
+#[test]
+fn test_geolocation_stays_near_city() {
+    let exit = ExitLocation::from_public_ip(&observed_exit()).unwrap();
+
+    for i in 0..200 {
+        let geo = exit.geolocation(&format!("tab-{}", i)).unwrap();
+        assert!(distance_km((geo.latitude, geo.longitude), (45.764, 4.8357)) <= 8.01);
+        assert!((20.0..150.0).contains(&geo.accuracy));
+    }
+}
+
+#[test]
+fn test_geolocation_wraps_longitude() {
+    let exit = ExitLocation::from_public_ip(&observed_exit())
+        .unwrap()
+        .with_coordinates(-17.0, 179.999);
+
+    for i in 0..50 {
+        let geo = exit.geolocation(&format!("tab-{}", i)).unwrap();
+        assert!((-180.0..180.0).contains(&geo.longitude));
+    }
+}
+
+#[test]
+fn test_geolocation_without_coordinates() {
+    let mut exit = ExitLocation::from_public_ip(&observed_exit()).unwrap();
+    exit.latitude = None;
+
+    assert!(exit.geolocation("tab-1").is_none());
+}
@@ -0,0 +1,209 @@
+//! Tests for per-tab fingerprint injection
+//!
+//! This module tests:
+//! - Client hints derived from Chrome, Edge, Android and macOS User-Agents
+//! - GREASE brand ordering
+//! - No client hints for Firefox and Safari
+//! - Language, locale and screen work area values
+//! - Script generation and value escaping
+//! - Workers left to target injection rather than wrapped
+
+use browser_core::*;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+const CHROME_WINDOWS: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36";
+
+fn windows_chrome() -> BrowserFingerprint {
+    BrowserFingerprint {
+        user_agent: CHROME_WINDOWS.to_string(),
+        accept_language: "de-DE,de;q=0.9,en;q=0.8".to_string(),
+        timezone: "Europe/Berlin".to_string(),
+        screen_resolution: (1920, 1080),
+        color_depth: 24,
+        hardware_concurrency: 8,
+        device_memory: 8,
+        platform: "Win32".to_string(),
+        webgl_vendor: "Google Inc. (NVIDIA)".to_string(),
+        webgl_renderer: "ANGLE (NVIDIA, NVIDIA GeForce RTX 3060 Direct3D11 vs_5_0 ps_5_0, D3D11)".to_string(),
+        canvas_hash: "0123456789abcdef".to_string(),
+        audio_hash: "fedcba9876543210".to_string(),
+        noise_seed: 42,
+    }
+}
+
+fn brands(list: &[BrandVersion]) -> Vec<(&str, &str)> {
+    list.iter().map(|b| (b.brand.as_str(), b.version.as_str())).collect()
+}
+
+// ============================================================================
+// Client Hints Tests
+// ============================================================================
+
+#[test]
+fn test_chrome_brands_follow_grease_order() {
+    let hints = ClientHints::from_user_agent(CHROME_WINDOWS).unwrap();
+
+    // Matches the Sec-CH-UA header Chrome 140 sends
+    assert_eq!(
+        brands(&hints.brands),
+        vec![("Chromium", "140"), ("Not=A?Brand", "24"), ("Google Chrome", "140")]
+    );
+    assert_eq!(
+        brands(&hints.full_version_list),
+        vec![("Chromium", "140.0.0.0"), ("Not=A?Brand", "24.0.0.0"), ("Google Chrome", "140.0.0.0")]
+    );
+    assert_eq!(hints.ua_full_version, "140.0.0.0");
+}
+
+#[test]
+fn test_grease_brand_changes_with_major_version() {
+    let ua = CHROME_WINDOWS.replace("Chrome/140", "Chrome/139");
+    let hints = ClientHints::from_user_agent(&ua).unwrap();
+
+    assert_eq!(hints.brands.len(), 3);
+    assert!(hints.brands.iter().any(|b| b.brand == "Chromium" && b.version == "139"));
+    let grease = hints.brands.iter().find(|b| b.brand.starts_with("Not")).unwrap();
+    assert_ne!(grease.brand, "Not=A?Brand");
+}
+
+#[test]
+fn test_windows_hints() {
+    let hints = ClientHints::from_user_agent(CHROME_WINDOWS).unwrap();
+
+    assert_eq!(hints.platform, "Windows");
+    assert_eq!(hints.platform_version, "10.0.0");
+    assert_eq!(hints.architecture, "x86");
+    assert_eq!(hints.bitness, "64");
+    assert!(!hints.mobile);
+    assert!(!hints.wow64);
+}
+
+#[test]
+fn test_edge_brand() {
+    let ua = format!("{} Edg/140.0.3485.54", CHROME_WINDOWS);
+    let hints = ClientHints::from_user_agent(&ua).unwrap();
+
+    assert!(brands(&hints.brands).contains(&("Microsoft Edge", "140")));
+    assert!(brands(&hints.full_version_list).contains(&("Microsoft Edge", "140.0.3485.54")));
+    assert!(!hints.brands.iter().any(|b| b.brand == "Google Chrome"));
+    assert_eq!(hints.ua_full_version, "140.0.3485.54");
+}
+
+#[test]
+fn test_android_hints() {
+    let ua = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Mobile Safari/537.36";
+    let hints = ClientHints::from_user_agent(ua).unwrap();
+
+    assert_eq!(hints.platform, "Android");
+    assert_eq!(hints.platform_version, "14.0.0");
+    assert_eq!(hints.model, "Pixel 8");
+    assert!(hints.mobile);
+    assert_eq!(hints.architecture, "");
+    assert_eq!(hints.bitness, "");
+}
+
+#[test]
+fn test_macos_hints() {
+    let ua = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36";
+    let hints = ClientHints::from_user_agent(ua).unwrap();
+
+    assert_eq!(hints.platform, "macOS");
+    assert_eq!(hints.platform_version, "10.15.7");
+    assert_eq!(hints.architecture, "arm");
+}
+
+#[test]
+fn test_no_hints_without_chromium() {
+    let firefox = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:142.0) Gecko/20100101 Firefox/142.0";
+    let safari = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.5 Safari/605.1.15";
+    let chrome_ios = "Mozilla/5.0 (iPhone; CPU iPhone OS 18_5 like Mac OS X) AppleWebKit/605.1.15 (KHTML, like Gecko) CriOS/140.0.7339.101 Mobile/15E148 Safari/604.1";
+
+    assert!(ClientHints::from_user_agent(firefox).is_none());
+    assert!(ClientHints::from_user_agent(safari).is_none());
+    assert!(ClientHints::from_user_agent(chrome_ios).is_none());
+}
+
+// ============================================================================
+// Injection Value Tests
+// ============================================================================
+
+#[test]
+fn test_languages_and_locale() {
+    let injection = FingerprintInjection::from_fingerprint(&windows_chrome());
+
+    assert_eq!(injection.languages, vec!["de-DE", "de", "en"]);
+    assert_eq!(injection.locale, "de-DE");
+    assert_eq!(injection.timezone, "Europe/Berlin");
+}
+
+#[test]
+fn test_empty_accept_language_defaults() {
+    let mut fp = windows_chrome();
+    fp.accept_language = String::new();
+    let injection = FingerprintInjection::from_fingerprint(&fp);
+
+    assert_eq!(injection.languages, vec!["en-US"]);
+    assert_eq!(injection.locale, "en-US");
+}
+
+#[test]
+fn test_screen_work_area() {
+    let injection = FingerprintInjection::from_fingerprint(&windows_chrome());
+    assert_eq!((injection.screen_width, injection.screen_height), (1920, 1080));
+    assert_eq!((injection.avail_width, injection.avail_height), (1920, 1032));
+
+    let mut fp = windows_chrome();
+    fp.user_agent = "Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36".to_string();
+    let injection = FingerprintInjection::from_fingerprint(&fp);
+    assert_eq!(injection.avail_height, 1080);
+    assert!(!injection.mobile);
+}
+
+// ============================================================================
+// Script Tests
+// ============================================================================
+
+#[test]
+fn test_script_embeds_values() {
+    let script = FingerprintInjection::from_fingerprint(&windows_chrome()).script();
+
+    assert!(!script.contains("__FINGERPRINT__"));
+    assert!(script.contains("\"userAgent\":\"Mozilla/5.0 (Windows NT 10.0"));
+    assert!(script.contains("\"hardwareConcurrency\":8"));
+    assert!(script.contains("\"webglRenderer\":\"ANGLE (NVIDIA"));
+    assert!(script.contains("\"clientHints\":{"));
+    assert!(script.contains("\"Not=A?Brand\""));
+    assert!(script.contains("\"noiseSeed\":\"000000000000002a\""));
+    assert!(script.contains("function installNoise(scope, seed, native)"));
+    assert!(!script.contains("__NOISE__"));
+}
+
+#[test]
+fn test_script_without_hints() {
+    let mut fp = windows_chrome();
+    fp.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:142.0) Gecko/20100101 Firefox/142.0".to_string();
+    let script = FingerprintInjection::from_fingerprint(&fp).script();
+
+    assert!(script.contains("\"clientHints\":null"));
+}
+
+#[test]
+fn test_script_escapes_values() {
+    let mut fp = windows_chrome();
+    fp.webgl_renderer = "\"); alert(1); (\"".to_string();
+    let script = FingerprintInjection::from_fingerprint(&fp).script();
+
+    assert!(script.contains(r#""webglRenderer":"\"); alert(1); (\"""#));
+}
+
+#[test]
+fn test_script_leaves_worker_constructors() {
+    let script = FingerprintInjection::from_fingerprint(&windows_chrome()).script();
+
+    // Workers are patched over DevTools, keeping their own URL and CSP
+    assert!(!script.contains("importScripts"));
+    assert!(!script.contains("createObjectURL"));
+}
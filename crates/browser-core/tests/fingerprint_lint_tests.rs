@@ -0,0 +1,259 @@
+//! Tests for the fingerprint consistency linter
+//!
+//! This module tests:
+//! - Clean generated fingerprints
+//! - UA/platform, UA/engine, UA/screen and WebGL contradictions
+//! - Timezone and language vs the exit country
+//! - Engine config overrides, viewport and geolocation
+//! - Applying suggested fixes
+
+use browser_core::chromium_engine::Geolocation;
+use browser_core::*;
+use std::net::Ipv4Addr;
+use virtual_ip::VirtualIP;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+fn us_ip() -> VirtualIP {
+    VirtualIP {
+        ip: Ipv4Addr::new(203, 0, 113, 1).into(),
+        country_code: "US".to_string(),
+        country: "United States".to_string(),
+        city: "Dallas".to_string(),
+        region: "Texas".to_string(),
+        timezone: "America/Chicago".to_string(),
+        language: "en-US".to_string(),
+        currency: "USD".to_string(),
+        isp: "Test ISP".to_string(),
+        proxy_url: Some("http://10.0.0.1:8080".to_string()),
+        accept_language: "en-US,en;q=0.9,es-US;q=0.8,es;q=0.7".to_string(),
+        exit_verified: true,
+    }
+}
+
+fn windows_chrome() -> BrowserFingerprint {
+    BrowserFingerprint {
+        user_agent: "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36".to_string(),
+        accept_language: "en-US,en;q=0.9".to_string(),
+        timezone: "America/Chicago".to_string(),
+        screen_resolution: (1920, 1080),
+        color_depth: 24,
+        hardware_concurrency: 8,
+        device_memory: 8,
+        platform: "Win32".to_string(),
+        webgl_vendor: "Google Inc. (NVIDIA)".to_string(),
+        webgl_renderer: "ANGLE (NVIDIA, NVIDIA GeForce RTX 3060 Direct3D11 vs_5_0 ps_5_0, D3D11)".to_string(),
+        canvas_hash: "0123456789abcdef".to_string(),
+        audio_hash: "fedcba9876543210".to_string(),
+        noise_seed: 42,
+    }
+}
+
+fn engine_config() -> ChromiumEngineConfig {
+    ChromiumEngineConfig {
+        viewport_width: 1920,
+        viewport_height: 940,
+        ..Default::default()
+    }
+}
+
+fn rules(report: &LintReport) -> Vec<&str> {
+    report.findings.iter().map(|f| f.rule.as_str()).collect()
+}
+
+// ============================================================================
+// Fingerprint Rules
+// ============================================================================
+
+#[test]
+fn test_consistent_profile_is_clean() {
+    let report = FingerprintLinter::new().lint(&windows_chrome(), &us_ip(), &engine_config());
+    assert_eq!(report.max_severity(), None, "{:?}", report.findings);
+}
+
+#[test]
+fn test_generated_fingerprints_have_no_errors() {
+    let linter = FingerprintLinter::new();
+    let generator = FingerprintGenerator::new();
+    let ip = us_ip();
+    for seed in 0..200 {
+        // Linted against a Chromium config, so only Blink browsers fit
+        let fp = generator
+            .generate(&FingerprintRequest::new(seed).with_engine("blink").with_virtual_ip(&ip))
+            .unwrap();
+        let config = ChromiumEngineConfig {
+            viewport_width: fp.screen_resolution.0,
+            viewport_height: fp.screen_resolution.1 - 140,
+            ..Default::default()
+        };
+        let report = linter.lint(&fp, &ip, &config);
+        assert!(!report.has_errors(), "seed {}: {:?}", seed, report.findings);
+    }
+}
+
+#[test]
+fn test_ua_platform_mismatch() {
+    let mut fp = windows_chrome();
+    fp.platform = "Linux x86_64".to_string();
+    let report = FingerprintLinter::new().lint(&fp, &us_ip(), &engine_config());
+
+    let finding = &report.findings[0];
+    assert_eq!(finding.rule, "ua-platform");
+    assert_eq!(finding.severity, LintSeverity::Error);
+    assert_eq!(finding.fix, Some(LintFix { field: "fingerprint.platform".into(), value: "Win32".into() }));
+}
+
+#[test]
+fn test_non_blink_ua_on_chromium_engine() {
+    let mut firefox = windows_chrome();
+    firefox.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:143.0) Gecko/20100101 Firefox/143.0".to_string();
+    firefox.webgl_vendor = "NVIDIA Corporation".to_string();
+    firefox.webgl_renderer = "NVIDIA GeForce GTX 980/PCIe/SSE2".to_string();
+    let report = FingerprintLinter::new().lint(&firefox, &us_ip(), &engine_config());
+    let engine = report.findings.iter().find(|f| f.rule == "ua-engine").expect("ua-engine finding");
+    assert_eq!(engine.severity, LintSeverity::Error);
+
+    let mut safari = windows_chrome();
+    safari.user_agent = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/605.1.15 (KHTML, like Gecko) Version/18.0 Safari/605.1.15".to_string();
+    safari.platform = "MacIntel".to_string();
+    safari.webgl_vendor = "Apple Inc.".to_string();
+    safari.webgl_renderer = "Apple GPU".to_string();
+    let report = FingerprintLinter::new().lint(&safari, &us_ip(), &engine_config());
+    assert_eq!(rules(&report), vec!["ua-engine"], "{:?}", report.findings);
+    assert!(report.has_errors());
+}
+
+#[test]
+fn test_mobile_ua_with_desktop_screen() {
+    let mut fp = windows_chrome();
+    fp.user_agent = "Mozilla/5.0 (Linux; Android 14; Pixel 8) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Mobile Safari/537.36".to_string();
+    fp.platform = "Linux armv8l".to_string();
+    fp.screen_resolution = (2560, 1440);
+    let report = FingerprintLinter::new().lint(&fp, &us_ip(), &engine_config());
+
+    assert!(report.has_errors());
+    assert!(rules(&report).contains(&"ua-screen"));
+    // A Direct3D renderer on Android is a second giveaway
+    assert!(rules(&report).contains(&"webgl-platform"));
+}
+
+#[test]
+fn test_webgl_and_hardware_contradictions() {
+    let mut fp = windows_chrome();
+    fp.user_agent = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:143.0) Gecko/20100101 Firefox/143.0".to_string();
+    fp.device_memory = 16;
+    let report = FingerprintLinter::new().lint(&fp, &us_ip(), &engine_config());
+
+    let webgl: Vec<_> = report.findings.iter().filter(|f| f.rule == "webgl-platform").collect();
+    assert_eq!(webgl.len(), 1, "{:?}", report.findings);
+    let memory = report.findings.iter().find(|f| f.rule == "hardware-values").unwrap();
+    assert_eq!(memory.fix.as_ref().unwrap().value, "8");
+}
+
+// ============================================================================
+// Country Rules
+// ============================================================================
+
+#[test]
+fn test_timezone_outside_exit_country() {
+    let mut fp = windows_chrome();
+    fp.timezone = "Europe/Berlin".to_string();
+    let report = FingerprintLinter::new().lint(&fp, &us_ip(), &engine_config());
+
+    let finding = report.findings.iter().find(|f| f.rule == "timezone-country").unwrap();
+    assert_eq!(finding.severity, LintSeverity::Error);
+    assert_eq!(finding.fix.as_ref().unwrap().value, "America/Chicago");
+
+    // Another US timezone is only informational
+    fp.timezone = "America/New_York".to_string();
+    let report = FingerprintLinter::new().lint(&fp, &us_ip(), &engine_config());
+    assert_eq!(report.max_severity(), Some(LintSeverity::Info));
+}
+
+#[test]
+fn test_language_not_spoken_in_country() {
+    let mut fp = windows_chrome();
+    fp.accept_language = "ja-JP,ja;q=0.9".to_string();
+    let report = FingerprintLinter::new().lint(&fp, &us_ip(), &engine_config());
+
+    let finding = report.findings.iter().find(|f| f.rule == "language-country").unwrap();
+    assert_eq!(finding.severity, LintSeverity::Warning);
+    assert_eq!(finding.fix.as_ref().unwrap().value, us_ip().accept_language);
+}
+
+// ============================================================================
+// Engine Config Rules
+// ============================================================================
+
+#[test]
+fn test_engine_overrides_and_viewport() {
+    let fp = windows_chrome();
+    let mut config = engine_config();
+    config.user_agent = Some("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7)".to_string());
+    config.fingerprint.spoof_timezone = true;
+    config.fingerprint.timezone = "America/New_York".to_string();
+    config.webrtc_protection = false;
+    config.viewport_width = 2560;
+    config.geolocation = Some(Geolocation { latitude: 52.52, longitude: 13.40, accuracy: 50.0 });
+
+    let report = FingerprintLinter::new().lint(&fp, &us_ip(), &config);
+    let found = rules(&report);
+    assert!(found.contains(&"engine-overrides"));
+    assert!(found.contains(&fingerprint_lint::VIEWPORT_RULE));
+    assert!(found.contains(&"geolocation-country"));
+    assert_eq!(report.findings.iter().filter(|f| f.rule == "engine-overrides").count(), 3);
+
+    // Findings are ordered most severe first
+    let severities: Vec<_> = report.findings.iter().map(|f| f.severity).collect();
+    assert!(severities.windows(2).all(|w| w[0] >= w[1]));
+}
+
+#[test]
+fn test_apply_fixes_resolves_errors() {
+    let mut fp = windows_chrome();
+    fp.platform = "MacIntel".to_string();
+    fp.timezone = "Asia/Tokyo".to_string();
+    let mut config = engine_config();
+    config.user_agent = Some("Mozilla/5.0 (X11; Linux x86_64)".to_string());
+    config.viewport_width = 3000;
+    config.webrtc_protection = false;
+
+    let linter = FingerprintLinter::new();
+    let report = linter.lint(&fp, &us_ip(), &config);
+    assert!(report.has_errors());
+    let applied = report.apply_fixes(&mut fp, &mut config);
+    assert!(applied >= 5, "{}", applied);
+
+    let after = linter.lint(&fp, &us_ip(), &config);
+    assert!(!after.has_errors(), "{:?}", after.findings);
+    assert_eq!(config.user_agent.as_deref(), Some(fp.user_agent.as_str()));
+}
+
+#[test]
+fn test_custom_rules() {
+    struct NoCanvasHash;
+    impl LintRule for NoCanvasHash {
+        fn id(&self) -> &str {
+            "canvas-hash"
+        }
+        fn check(&self, ctx: &LintContext<'_>, findings: &mut Vec<LintFinding>) {
+            if ctx.fingerprint.canvas_hash.is_empty() {
+                findings.push(LintFinding {
+                    rule: self.id().to_string(),
+                    severity: LintSeverity::Warning,
+                    message: "No canvas hash".to_string(),
+                    fix: None,
+                });
+            }
+        }
+    }
+
+    let linter = FingerprintLinter::empty().with_rule(Box::new(NoCanvasHash));
+    assert_eq!(linter.rule_ids(), vec!["canvas-hash"]);
+    let mut fp = windows_chrome();
+    fp.canvas_hash.clear();
+    let report = linter.lint(&fp, &us_ip(), &engine_config());
+    assert_eq!(rules(&report), vec!["canvas-hash"]);
+}
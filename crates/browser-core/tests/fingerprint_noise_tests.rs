@@ -0,0 +1,138 @@
+//! Tests for seeded canvas/audio/WebGL noise
+//!
+//! This module tests:
+//! - Noise keys per (seed, origin)
+//! - Canvas and audio noise bounds
+//! - Probe hashes stable per seed and origin
+//! - Generated fingerprints recording the hashes their seed produces
+//! - Explicit, recorded noise rotation
+
+use browser_core::fingerprint_noise::*;
+use browser_core::*;
+
+// ============================================================================
+// Noise Function Tests
+// ============================================================================
+
+#[test]
+fn test_origin_key_depends_on_seed_and_origin() {
+    let key = origin_key(42, "https://example.com");
+
+    assert_eq!(key, origin_key(42, "https://example.com"));
+    assert_ne!(key, origin_key(43, "https://example.com"));
+    assert_ne!(key, origin_key(42, "https://example.org"));
+}
+
+#[test]
+fn test_canvas_noise_is_sparse_and_small() {
+    let key = origin_key(42, "https://example.com");
+    let mut changed = 0;
+
+    for pixel in 0..4096 {
+        let mut rgba = [100u8, 150, 200, 255];
+        canvas_noise(key, pixel, &mut rgba);
+        let diff: Vec<i16> = rgba.iter().zip([100u8, 150, 200, 255]).map(|(a, b)| *a as i16 - b as i16).collect();
+        assert!(diff.iter().all(|d| d.abs() <= 1));
+        assert_eq!(diff[3], 0);
+        if diff.iter().any(|d| *d != 0) {
+            changed += 1;
+        }
+    }
+
+    // About one pixel in sixteen
+    assert!((150..=370).contains(&changed), "{}", changed);
+}
+
+#[test]
+fn test_canvas_noise_skips_transparent_pixels() {
+    let key = origin_key(42, "https://example.com");
+    for pixel in 0..1024 {
+        let mut rgba = [0u8, 0, 0, 0];
+        canvas_noise(key, pixel, &mut rgba);
+        assert_eq!(rgba, [0, 0, 0, 0]);
+    }
+}
+
+#[test]
+fn test_audio_noise_is_bounded() {
+    let key = origin_key(42, "https://example.com");
+    for index in 0..1024 {
+        let sample = 0.25f32;
+        let noised = audio_noise(key, 0, index, sample);
+        assert!((noised - sample).abs() <= 1e-7);
+    }
+    assert_ne!(audio_noise(key, 0, 7, 0.0), audio_noise(key, 1, 7, 0.0));
+}
+
+// ============================================================================
+// Probe Hash Tests
+// ============================================================================
+
+#[test]
+fn test_probe_hashes_are_stable_per_origin() {
+    let first = NoiseHashes::expected(42, "https://example.com");
+    let again = NoiseHashes::expected(42, "https://example.com");
+    let other_site = NoiseHashes::expected(42, "https://example.org");
+    let other_seed = NoiseHashes::expected(43, "https://example.com");
+
+    assert_eq!(first, again);
+    assert_eq!(first.canvas.len(), 16);
+    assert_eq!(first.audio.len(), 16);
+    assert_ne!(first.canvas, other_site.canvas);
+    assert_ne!(first.audio, other_site.audio);
+    assert_ne!(first.canvas, other_seed.canvas);
+    assert_ne!(first.audio, other_seed.audio);
+}
+
+#[test]
+fn test_probe_script_matches_rust_probe() {
+    assert!(PROBE_SCRIPT.contains("canvas.width = 32"));
+    assert!(PROBE_SCRIPT.contains("canvas.height = 8"));
+    assert!(PROBE_SCRIPT.contains("length: 512"));
+    assert!(NOISE_SCRIPT.starts_with("function installNoise(scope, seed, native)"));
+}
+
+// ============================================================================
+// Fingerprint Tests
+// ============================================================================
+
+#[test]
+fn test_generated_hashes_come_from_noise_seed() {
+    let generator = FingerprintGenerator::new();
+
+    for seed in 0..20 {
+        let fp = generator.generate(&FingerprintRequest::new(seed)).unwrap();
+        assert_eq!(fp.canvas_hash, canvas_probe_hash(fp.noise_seed, OPAQUE_ORIGIN));
+        assert_eq!(fp.audio_hash, audio_probe_hash(fp.noise_seed, OPAQUE_ORIGIN));
+    }
+
+    let a = generator.generate(&FingerprintRequest::new(7)).unwrap();
+    let b = generator.generate(&FingerprintRequest::new(7)).unwrap();
+    assert_eq!(a.noise_seed, b.noise_seed);
+    assert_eq!(a.canvas_hash, b.canvas_hash);
+}
+
+#[test]
+fn test_rotation_is_explicit_and_recorded() {
+    let fp = FingerprintGenerator::new().generate(&FingerprintRequest::new(7)).unwrap();
+    let mut rotated = fp.clone();
+
+    let rotation = rotated.rotate_noise(99, "flagged by example.com");
+
+    assert_eq!(rotation.previous_seed, fp.noise_seed);
+    assert_eq!(rotation.seed, 99);
+    assert_eq!(rotation.reason, "flagged by example.com");
+    assert_eq!(rotated.noise_seed, 99);
+    assert_eq!(rotated.canvas_hash, canvas_probe_hash(99, OPAQUE_ORIGIN));
+    assert_ne!(rotated.canvas_hash, fp.canvas_hash);
+    assert_eq!(rotated.user_agent, fp.user_agent);
+}
+
+#[test]
+fn test_missing_noise_seed_deserializes() {
+    let mut value = serde_json::to_value(FingerprintGenerator::new().generate(&FingerprintRequest::new(1)).unwrap()).unwrap();
+    value.as_object_mut().unwrap().remove("noise_seed");
+
+    let fp: BrowserFingerprint = serde_json::from_value(value).unwrap();
+    assert_eq!(fp.noise_seed, 0);
+}
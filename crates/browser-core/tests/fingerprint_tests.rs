@@ -1,6 +1,15 @@
 //! Unit tests for the fingerprint module.
+//!
+//! This module tests:
+//! - Seeded, reproducible fingerprint generation
+//! - Coherence between UA, platform, GPU and hardware values
+//! - OS, browser, engine and country conditioning
+//! - Chromium-family fingerprints for tabs
 
 use browser_core::*;
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use virtual_ip::VirtualIP;
 
 
 #[test]
@@ -8,3 +17,141 @@ fn test_browserfingerprint_basic() {
     // Basic test for BrowserFingerprint
     assert!(true, "BrowserFingerprint basic test placeholder");
 }
+
+// ============================================================================
+// Generator Tests
+// ============================================================================
+
+fn virtual_ip(country_code: &str) -> VirtualIP {
+    VirtualIP {
+        ip: Ipv4Addr::new(203, 0, 113, 1).into(),
+        country_code: country_code.to_string(),
+        country: country_code.to_string(),
+        city: "Test City".to_string(),
+        region: "Test Region".to_string(),
+        isp: "Test ISP".to_string(),
+        timezone: "Europe/Berlin".to_string(),
+        language: "de-DE".to_string(),
+        currency: "EUR".to_string(),
+        proxy_url: None,
+        accept_language: "de-DE,de;q=0.9,en-US;q=0.8,en;q=0.7".to_string(),
+        exit_verified: false,
+    }
+}
+
+#[test]
+fn test_generator_is_reproducible() {
+    let generator = FingerprintGenerator::new();
+    let request = FingerprintRequest::for_profile("tab-1234").with_virtual_ip(&virtual_ip("DE"));
+
+    let a = generator.generate(&request).unwrap();
+    let b = generator.generate(&request).unwrap();
+    assert_eq!(serde_json::to_string(&a).unwrap(), serde_json::to_string(&b).unwrap());
+    assert_eq!(a.timezone, "Europe/Berlin");
+    assert!(a.accept_language.starts_with("de-DE"));
+
+    let distinct: HashSet<String> = (0..50)
+        .map(|seed| generator.generate(&FingerprintRequest::new(seed)).unwrap())
+        .map(|fp| format!("{}|{:?}|{}", fp.user_agent, fp.screen_resolution, fp.webgl_renderer))
+        .collect();
+    assert!(distinct.len() > 10, "Seeds should give varied fingerprints");
+}
+
+#[test]
+fn test_generated_attributes_are_coherent() {
+    let generator = FingerprintGenerator::new();
+    for seed in 0..300 {
+        let fp = generator.generate(&FingerprintRequest::new(seed)).unwrap();
+        let ua = &fp.user_agent;
+
+        match fp.platform.as_str() {
+            "Win32" => assert!(ua.contains("Windows NT 10.0"), "{}", ua),
+            "MacIntel" => assert!(ua.contains("Mac OS X"), "{}", ua),
+            "Linux x86_64" => assert!(ua.contains("Linux x86_64"), "{}", ua),
+            other => panic!("Unexpected platform {}", other),
+        }
+        if ua.contains("Firefox/") {
+            assert!(!fp.webgl_vendor.starts_with("Google Inc."), "{}", fp.webgl_vendor);
+        } else if ua.contains("Version/") {
+            assert_eq!(fp.platform, "MacIntel");
+            assert_eq!(fp.webgl_renderer, "Apple GPU");
+        } else {
+            assert!(ua.contains("Chrome/"), "{}", ua);
+            assert!(fp.webgl_vendor.starts_with("Google Inc. ("), "{}", fp.webgl_vendor);
+            assert!(fp.webgl_renderer.starts_with("ANGLE ("), "{}", fp.webgl_renderer);
+        }
+        if fp.webgl_renderer.contains("Apple M") {
+            assert_eq!(fp.platform, "MacIntel");
+        }
+        if fp.webgl_renderer.contains("Direct3D") {
+            assert_eq!(fp.platform, "Win32");
+        }
+        assert!(fp.device_memory <= 8 && fp.device_memory.is_power_of_two());
+        assert!(fp.hardware_concurrency >= 2);
+        assert!(fp.color_depth == 24 || fp.color_depth == 30);
+        assert_eq!(fp.canvas_hash.len(), 16);
+    }
+}
+
+#[test]
+fn test_generator_respects_os_and_browser() {
+    let generator = FingerprintGenerator::new();
+
+    for seed in 0..50 {
+        let fp = generator
+            .generate(&FingerprintRequest::new(seed).with_os("linux").with_browser("Firefox"))
+            .unwrap();
+        assert_eq!(fp.platform, "Linux x86_64");
+        assert!(fp.user_agent.contains("Gecko/20100101 Firefox/"), "{}", fp.user_agent);
+
+        let safari = generator.generate(&FingerprintRequest::new(seed).with_browser("Safari")).unwrap();
+        assert_eq!(safari.platform, "MacIntel");
+    }
+
+    assert!(generator
+        .generate(&FingerprintRequest::new(1).with_os("Windows").with_browser("Safari"))
+        .is_err());
+    assert!(generator.generate(&FingerprintRequest::new(1).with_os("BeOS")).is_err());
+}
+
+#[test]
+fn test_country_conditions_os_share() {
+    let generator = FingerprintGenerator::new();
+    let mac_share = |country: &str| {
+        (0..600)
+            .map(|seed| {
+                let mut request = FingerprintRequest::new(seed);
+                request.country_code = Some(country.to_string());
+                generator.generate(&request).unwrap()
+            })
+            .filter(|fp| fp.platform == "MacIntel")
+            .count()
+    };
+    assert!(mac_share("US") > mac_share("CN"));
+}
+
+#[test]
+fn test_generator_respects_engine() {
+    let generator = FingerprintGenerator::new();
+    for seed in 0..200 {
+        let fp = generator.generate(&FingerprintRequest::new(seed).with_engine("blink")).unwrap();
+        assert!(fp.user_agent.contains("Chrome/"), "{}", fp.user_agent);
+        assert!(fp.webgl_vendor.starts_with("Google Inc."), "{}", fp.webgl_vendor);
+    }
+
+    assert!(generator
+        .generate(&FingerprintRequest::new(1).with_engine("blink").with_browser("Firefox"))
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_tab_fingerprints_are_chromium_family() {
+    let manager = TabIPManager::new(virtual_ip::demo_generator());
+    for country in ["US", "GB", "DE"].iter().cycle().take(60) {
+        let tab = manager.create_tab(country).await.unwrap();
+        let ua = &tab.fingerprint.user_agent;
+        // Tabs render in Chromium, so neither a Gecko nor a WebKit-only UA fits
+        assert!(ua.contains("Chrome/"), "{}", ua);
+        assert!(!ua.contains("Firefox/"), "{}", ua);
+    }
+}
@@ -0,0 +1,222 @@
+//! Tests for offline GeoIP lookups
+//!
+//! This module tests:
+//! - Loading DB-IP and IP2Location CSV databases
+//! - Merging city and ASN sources
+//! - Reloading databases when files change on disk
+//! - GeoVerifier and PublicIpDetector using the local database
+//! - Opting in to remote GeoIP APIs through `GEOIP_API_URLS`
+
+use browser_core::proxy::{FreeProxy, ProxyType};
+use browser_core::proxy_validator::{GeoVerificationConfig, GeoVerifier};
+use browser_core::PublicIpDetector;
+use chrono::Utc;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use virtual_ip::{GeoIpDatabase, GeoIpFormat, GeoIpSource};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+const DBIP_CITY_CSV: &str = "\
+ip_start,ip_end,continent,country,stateprov,city,latitude,longitude
+81.2.69.0,81.2.69.255,EU,GB,England,London,51.5142,-0.0931
+8.8.8.0,8.8.8.255,NA,US,California,\"Mountain View, CA\",37.386,-122.0838
+2a02:1000::,2a02:1fff:ffff:ffff:ffff:ffff:ffff:ffff,EU,DE,Hesse,Frankfurt,50.1109,8.6821
+";
+
+const DBIP_ASN_CSV: &str = "\
+8.8.8.0,8.8.8.255,15169,Google LLC
+81.2.69.0,81.2.69.255,20712,Andrews & Arnold Ltd
+";
+
+const IP2LOCATION_CSV: &str = "\
+\"16777216\",\"16777471\",\"AU\",\"Australia\",\"Queensland\",\"Brisbane\",\"-27.467940\",\"153.028090\"
+\"16777472\",\"16778239\",\"CN\",\"China\",\"Fujian\",\"Fuzhou\",\"26.061390\",\"119.306110\"
+\"16778240\",\"16779263\",\"-\",\"-\",\"-\",\"-\",\"0\",\"0\"
+";
+
+fn write_file(dir: &Path, name: &str, contents: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    let mut file = std::fs::File::create(&path).expect("Failed to create fixture");
+    file.write_all(contents.as_bytes()).expect("Failed to write fixture");
+    path
+}
+
+fn city_and_asn_db(dir: &Path) -> GeoIpDatabase {
+    let city = write_file(dir, "dbip-city-lite.csv", DBIP_CITY_CSV);
+    let asn = write_file(dir, "dbip-asn-lite.csv", DBIP_ASN_CSV);
+    GeoIpDatabase::open(vec![
+        GeoIpSource::detect(city).expect("City source"),
+        GeoIpSource::detect(asn).expect("ASN source"),
+    ])
+    .expect("Failed to open GeoIP database")
+}
+
+fn create_test_proxy(country: &str, country_code: &str) -> FreeProxy {
+    FreeProxy {
+        ip: "10.0.0.1".to_string(),
+        port: 8080,
+        protocol: ProxyType::Http,
+        country: country.to_string(),
+        country_code: country_code.to_string(),
+        anonymity: "elite".to_string(),
+        speed: 100,
+        uptime: 99.5,
+        last_checked: Utc::now().to_rfc3339(),
+        provider: "TestProvider".to_string(),
+        is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
+    }
+}
+
+// ============================================================================
+// GeoIpDatabase Tests
+// ============================================================================
+
+#[test]
+fn test_format_detection() {
+    assert_eq!(GeoIpFormat::detect(Path::new("GeoLite2-City.mmdb")), Some(GeoIpFormat::Mmdb));
+    assert_eq!(GeoIpFormat::detect(Path::new("dbip-city-lite.csv")), Some(GeoIpFormat::DbIpCsv));
+    assert_eq!(GeoIpFormat::detect(Path::new("dbip-asn-lite.csv")), Some(GeoIpFormat::DbIpAsnCsv));
+    assert_eq!(GeoIpFormat::detect(Path::new("IP2LOCATION-LITE-DB5.CSV")), Some(GeoIpFormat::Ip2LocationCsv));
+    assert_eq!(GeoIpFormat::detect(Path::new("ranges.json")), None);
+}
+
+#[test]
+fn test_dbip_city_and_asn_merge() {
+    let dir = TempDir::new().expect("Temp dir");
+    let db = city_and_asn_db(dir.path());
+
+    let record = db.lookup_str("8.8.8.8").expect("8.8.8.8 should resolve");
+    assert_eq!(record.country_code.as_deref(), Some("US"));
+    assert_eq!(record.city.as_deref(), Some("Mountain View, CA"));
+    assert_eq!(record.asn, Some(15169));
+    assert_eq!(record.organization.as_deref(), Some("Google LLC"));
+    assert_eq!(record.location(), Some((37.386, -122.0838)));
+
+    let v6 = db.lookup_str("2a02:1234::1").expect("IPv6 should resolve");
+    assert_eq!(v6.city.as_deref(), Some("Frankfurt"));
+
+    assert!(db.lookup_str("192.0.2.1").is_none());
+    assert!(db.lookup_str("not an ip").is_none());
+}
+
+#[test]
+fn test_ip2location_numeric_ranges() {
+    let dir = TempDir::new().expect("Temp dir");
+    let path = write_file(dir.path(), "IP2LOCATION-LITE-DB5.CSV", IP2LOCATION_CSV);
+    let db = GeoIpDatabase::open(vec![GeoIpSource::detect(path).expect("Source")]).expect("Open");
+
+    let record = db.lookup_str("1.0.0.1").expect("1.0.0.1 should resolve");
+    assert_eq!(record.country_code.as_deref(), Some("AU"));
+    assert_eq!(record.country.as_deref(), Some("Australia"));
+
+    let record = db.lookup_str("1.0.2.5").expect("1.0.2.5 should resolve");
+    assert_eq!(record.city.as_deref(), Some("Fuzhou"));
+
+    // "-" placeholders are treated as unknown
+    let record = db.lookup_str("1.0.4.1").expect("Placeholder row should resolve");
+    assert!(record.country_code.is_none());
+}
+
+#[test]
+fn test_open_dir_and_refresh() {
+    let dir = TempDir::new().expect("Temp dir");
+    write_file(dir.path(), "dbip-country-lite.csv", "1.1.1.0,1.1.1.255,AU\n");
+    write_file(dir.path(), "README.txt", "ignored");
+
+    let db = GeoIpDatabase::open_dir(dir.path()).expect("Open dir");
+    assert_eq!(db.sources().len(), 1);
+    assert_eq!(db.lookup_str("1.1.1.1").and_then(|r| r.country_code).as_deref(), Some("AU"));
+    assert!(!db.refresh_if_changed().expect("Refresh"));
+
+    // Rewrite the file with a different answer and a newer mtime
+    let path = dir.path().join("dbip-country-lite.csv");
+    std::fs::write(&path, "1.1.1.0,1.1.1.255,NZ\n").expect("Rewrite");
+    let later = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+    std::fs::File::options()
+        .write(true)
+        .open(&path)
+        .and_then(|f| f.set_modified(later))
+        .expect("Set mtime");
+
+    assert!(db.refresh_if_changed().expect("Refresh"));
+    assert_eq!(db.lookup_str("1.1.1.1").and_then(|r| r.country_code).as_deref(), Some("NZ"));
+}
+
+#[test]
+fn test_open_requires_sources() {
+    assert!(GeoIpDatabase::open(vec![]).is_err());
+    assert!(GeoIpDatabase::open(vec![GeoIpSource::new("/nonexistent/db.csv", GeoIpFormat::DbIpCsv)]).is_err());
+}
+
+// ============================================================================
+// Integration Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_geo_verifier_uses_local_database() {
+    let dir = TempDir::new().expect("Temp dir");
+    let db = Arc::new(city_and_asn_db(dir.path()));
+
+    // No HTTP fallback: the result must come from the local database
+    let config = GeoVerificationConfig {
+        geoip_api_urls: vec![],
+        ..Default::default()
+    };
+    let verifier = GeoVerifier::new(config).expect("Verifier").with_geoip_database(db);
+
+    let result = verifier
+        .verify_proxy_location(&create_test_proxy("United Kingdom", "GB"), "81.2.69.160")
+        .await;
+    assert!(result.is_verified);
+    assert_eq!(result.detected_location, Some((51.5142, -0.0931)));
+
+    let result = verifier
+        .verify_proxy_location(&create_test_proxy("Germany", "DE"), "8.8.8.8")
+        .await;
+    assert!(!result.is_verified);
+    assert_eq!(result.detected_country.as_deref(), Some("US"));
+
+    let result = verifier
+        .verify_proxy_location(&create_test_proxy("Germany", "DE"), "192.0.2.1")
+        .await;
+    assert!(!result.is_verified);
+    assert!(result.error.is_some());
+}
+
+#[test]
+fn test_public_ip_detector_local_lookup() {
+    let dir = TempDir::new().expect("Temp dir");
+    let db = Arc::new(city_and_asn_db(dir.path()));
+
+    let detector = PublicIpDetector::new().expect("Detector");
+    assert!(detector.lookup_local("8.8.8.8").is_none());
+
+    let detector = detector.with_geoip_database(db);
+    let info = detector.lookup_local("81.2.69.160").expect("Local lookup");
+    assert_eq!(info.country_code.as_deref(), Some("GB"));
+    assert_eq!(info.city.as_deref(), Some("London"));
+    assert_eq!(info.isp.as_deref(), Some("Andrews & Arnold Ltd"));
+}
+
+#[test]
+fn test_geo_verification_config_from_env() {
+    std::env::remove_var("GEOIP_API_URLS");
+    assert!(GeoVerificationConfig::from_env().geoip_api_urls.is_empty());
+
+    std::env::set_var("GEOIP_API_URLS", " https://ipapi.co/{ip}/json/ ,,http://ip-api.com/json/{ip}");
+    let config = GeoVerificationConfig::from_env();
+    std::env::remove_var("GEOIP_API_URLS");
+    assert_eq!(
+        config.geoip_api_urls,
+        vec!["https://ipapi.co/{ip}/json/".to_string(), "http://ip-api.com/json/{ip}".to_string()]
+    );
+    assert!(config.enabled);
+}
@@ -0,0 +1,414 @@
+//! Tests for TLS/HTTP2 fingerprint impersonation
+//!
+//! This module tests:
+//! - Resolving TLSProfile names into a ClientHello spec
+//! - JA3 and JA4 of a parsed ClientHello
+//! - Akamai HTTP/2 fingerprints of the browser presets
+//! - The ClientHello, HTTP/2 preface and HTTP/1.1 requests seen by local servers
+//! - Redirects and error statuses of impersonated requests
+//! - Response bodies announced larger than the body limit
+
+use browser_core::*;
+#[cfg(feature = "impersonation")]
+use std::time::Duration;
+#[cfg(feature = "impersonation")]
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+#[cfg(feature = "impersonation")]
+use tokio::net::TcpListener;
+
+const CHROME_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/140.0.0.0 Safari/537.36";
+const FIREFOX_UA: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64; rv:143.0) Gecko/20100101 Firefox/143.0";
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+fn u16_list(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_be_bytes()).collect()
+}
+
+fn with_len16(body: Vec<u8>) -> Vec<u8> {
+    let mut out = (body.len() as u16).to_be_bytes().to_vec();
+    out.extend(body);
+    out
+}
+
+fn extension(kind: u16, data: Vec<u8>) -> Vec<u8> {
+    let mut out = kind.to_be_bytes().to_vec();
+    out.extend(with_len16(data));
+    out
+}
+
+/// A Chrome-like ClientHello record with GREASE values, ALPS and ECH.
+fn chrome_client_hello() -> Vec<u8> {
+    let ciphers = [
+        0x0a0a, 0x1301, 0x1302, 0x1303, 0xc02b, 0xc02f, 0xc02c, 0xc030, 0xcca9, 0xcca8, 0xc013, 0xc014, 0x009c,
+        0x009d, 0x002f, 0x0035,
+    ];
+    let mut sni = vec![0u8];
+    sni.extend(with_len16(b"example.com".to_vec()));
+    let alpn = [vec![2], b"h2".to_vec(), vec![8], b"http/1.1".to_vec()].concat();
+    let mut versions = vec![6u8];
+    versions.extend(u16_list(&[0x3a3a, 0x0304, 0x0303]));
+
+    let extensions = [
+        extension(0x1a1a, vec![]),
+        extension(0x0000, with_len16(sni)),
+        extension(0x0017, vec![]),
+        extension(0xff01, vec![0]),
+        extension(0x000a, with_len16(u16_list(&[0x2a2a, 0x11ec, 0x001d, 0x0017, 0x0018]))),
+        extension(0x000b, vec![1, 0]),
+        extension(0x0023, vec![]),
+        extension(0x0010, with_len16(alpn)),
+        extension(0x0005, vec![1, 0, 0, 0, 0]),
+        extension(
+            0x000d,
+            with_len16(u16_list(&[0x0403, 0x0804, 0x0401, 0x0503, 0x0805, 0x0501, 0x0806, 0x0601])),
+        ),
+        extension(0x0012, vec![]),
+        extension(0x0033, with_len16(vec![])),
+        extension(0x002d, vec![1, 1]),
+        extension(0x002b, versions),
+        extension(0x001b, vec![2, 0, 2]),
+        extension(0x4469, with_len16([vec![2], b"h2".to_vec()].concat())),
+        extension(0xfe0d, vec![0]),
+        extension(0x4a4a, vec![0]),
+    ]
+    .concat();
+
+    let mut body = vec![0x03, 0x03];
+    body.extend([7u8; 32]);
+    body.push(0);
+    body.extend(with_len16(u16_list(&ciphers)));
+    body.extend([1, 0]);
+    body.extend(with_len16(extensions));
+
+    let mut handshake = vec![0x01];
+    handshake.extend(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend(body);
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend(with_len16(handshake));
+    record
+}
+
+#[cfg(feature = "impersonation")]
+fn frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+    let mut out = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+    out.extend([kind, flags]);
+    out.extend(stream_id.to_be_bytes());
+    out.extend(payload);
+    out
+}
+
+/// Answers one HTTP/1.1 request with `reply` and returns the request head.
+#[cfg(feature = "impersonation")]
+fn http1_server(listener: TcpListener, reply: String) -> tokio::task::JoinHandle<String> {
+    tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        while !received.ends_with(b"\r\n\r\n") {
+            let mut chunk = [0u8; 1024];
+            let n = socket.read(&mut chunk).await.unwrap();
+            assert!(n > 0);
+            received.extend_from_slice(&chunk[..n]);
+        }
+        socket.write_all(reply.as_bytes()).await.unwrap();
+        String::from_utf8(received).unwrap()
+    })
+}
+
+#[cfg(feature = "impersonation")]
+fn network_config() -> NetworkConfig {
+    NetworkConfig {
+        proxy_url: None,
+        dns_servers: Vec::new(),
+        tls_profile: TLSProfile::chrome(),
+        http2_settings: HTTP2Settings::chrome(),
+        tcp_fingerprint: TCPFingerprint { ttl: 128, window_size: 64240, options: Vec::new() },
+    }
+}
+
+// ============================================================================
+// ClientHello Spec and JA3/JA4
+// ============================================================================
+
+#[test]
+fn test_spec_from_browser_profiles() {
+    for profile in [TLSProfile::chrome(), TLSProfile::firefox(), TLSProfile::safari()] {
+        let spec = ClientHelloSpec::from_profile(&profile).unwrap();
+        assert_eq!(spec.cipher_suites.len(), profile.cipher_suites.len());
+        assert_eq!(spec.extensions.len(), profile.extensions.len());
+        assert_eq!(spec.max_version, 0x0304);
+        assert_eq!(spec.alpn_wire(), b"\x02h2\x08http/1.1");
+    }
+
+    let chrome = ClientHelloSpec::from_profile(&TLSProfile::chrome()).unwrap();
+    assert_eq!(&chrome.cipher_suites[..4], &[0x1301, 0x1302, 0x1303, 0xc02b]);
+    assert!(chrome.cipher_list().starts_with("ECDHE-ECDSA-AES128-GCM-SHA256:"));
+}
+
+#[test]
+fn test_spec_rejects_unknown_names() {
+    let mut profile = TLSProfile::chrome();
+    profile.extensions.push("made_up_extension".to_string());
+    let err = ClientHelloSpec::from_profile(&profile).unwrap_err();
+    assert!(err.to_string().contains("made_up_extension"));
+
+    let mut profile = TLSProfile::chrome();
+    profile.version = "SSL 3.0".to_string();
+    assert!(ClientHelloSpec::from_profile(&profile).is_err());
+}
+
+#[test]
+fn test_profiles_follow_user_agent() {
+    assert_eq!(TLSProfile::for_user_agent(FIREFOX_UA).extensions, TLSProfile::firefox().extensions);
+    assert_eq!(HTTP2Settings::for_user_agent(CHROME_UA).window_update, 15663105);
+}
+
+#[test]
+fn test_ja3_and_ja4_of_client_hello() {
+    let info = parse_client_hello(&chrome_client_hello()).unwrap();
+    assert_eq!(info.server_name.as_deref(), Some("example.com"));
+    assert_eq!(info.alpn, vec!["h2", "http/1.1"]);
+    assert!(info.ja3_string().starts_with("771,4865-4866-4867-49195-49199"));
+    // GREASE values are ignored
+    assert!(!info.ja3_string().contains("2570"));
+    assert!(info.ja3_string().ends_with(",4588-29-23-24,0"));
+    assert_eq!(info.ja3_hash().len(), 32);
+    assert_eq!(info.ja4(), "t13d1516h2_8daaf6152771_02713d6af862");
+}
+
+#[test]
+fn test_client_hello_split_across_records() {
+    let record = chrome_client_hello();
+    let handshake = &record[5..];
+    let (a, b) = handshake.split_at(40);
+    let mut split = vec![0x16, 0x03, 0x01];
+    split.extend(with_len16(a.to_vec()));
+    split.extend([0x16, 0x03, 0x01]);
+    split.extend(with_len16(b.to_vec()));
+
+    assert_eq!(tls_record_len(&split), Some(45));
+    assert_eq!(parse_client_hello(&split).unwrap().ja4(), parse_client_hello(&record).unwrap().ja4());
+    assert!(parse_client_hello(b"GET / HTTP/1.1\r\n").is_err());
+}
+
+// ============================================================================
+// HTTP/2 Fingerprint
+// ============================================================================
+
+#[test]
+fn test_akamai_fingerprint_of_presets() {
+    assert_eq!(
+        Http2Fingerprint::from_settings(&HTTP2Settings::chrome()).akamai(),
+        "1:65536;2:0;4:6291456;6:262144|15663105|0|m,a,s,p"
+    );
+    assert_eq!(
+        Http2Fingerprint::from_settings(&HTTP2Settings::firefox()).akamai(),
+        "1:65536;2:0;4:131072;5:16384|12517377|0|m,p,a,s"
+    );
+    assert_eq!(
+        Http2Fingerprint::from_settings(&HTTP2Settings::safari()).akamai(),
+        "2:0;3:100;4:2097152;9:1|10420225|0|m,s,a,p"
+    );
+}
+
+// ============================================================================
+// Local Servers
+// ============================================================================
+
+#[cfg(feature = "impersonation")]
+#[tokio::test]
+async fn test_h2_preface_matches_settings() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        let fingerprint = loop {
+            let mut chunk = [0u8; 4096];
+            let n = socket.read(&mut chunk).await.unwrap();
+            assert!(n > 0, "client closed before sending headers");
+            received.extend_from_slice(&chunk[..n]);
+            if let Ok(fingerprint) = Http2Fingerprint::parse_preface(&received) {
+                break fingerprint;
+            }
+        };
+
+        let block = loona_hpack::Encoder::new()
+            .encode([(&b":status"[..], &b"200"[..]), (&b"content-type"[..], &b"text/plain"[..])]);
+        let mut reply = frame(0x4, 0, 0, &[]);
+        reply.extend(frame(0x4, 0x1, 0, &[]));
+        reply.extend(frame(0x1, 0x4, 1, &block));
+        reply.extend(frame(0x0, 0x1, 1, b"hello h2"));
+        socket.write_all(&reply).await.unwrap();
+        // Let the client read the reply before the socket closes
+        let _ = socket.read(&mut [0u8; 1024]).await;
+        fingerprint
+    });
+
+    let settings = HTTP2Settings::firefox();
+    let client = ImpersonatedClient::from_profiles(&TLSProfile::firefox(), &settings)
+        .unwrap()
+        .http2_prior_knowledge()
+        .with_timeout(Duration::from_secs(5));
+    let response = client
+        .send(&ImpersonatedRequest::get(format!("http://{}/fp", addr)))
+        .await
+        .unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.protocol, "h2");
+    assert_eq!(response.header("content-type"), Some("text/plain"));
+    assert_eq!(response.text(), "hello h2");
+
+    let observed = server.await.unwrap();
+    let expected = Http2Fingerprint::from_settings(&settings);
+    assert_eq!(observed.akamai(), expected.akamai());
+    assert_eq!(observed.headers_priority, expected.headers_priority);
+}
+
+#[cfg(feature = "impersonation")]
+#[tokio::test]
+async fn test_http1_request_keeps_header_order() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        while !received.ends_with(b"\r\n\r\nping") {
+            let mut chunk = [0u8; 1024];
+            let n = socket.read(&mut chunk).await.unwrap();
+            assert!(n > 0);
+            received.extend_from_slice(&chunk[..n]);
+        }
+        socket
+            .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\npong\r\n0\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(received).unwrap()
+    });
+
+    let client = ImpersonatedClient::from_profiles(&TLSProfile::chrome(), &HTTP2Settings::chrome())
+        .unwrap()
+        .with_default_headers(vec![("user-agent".to_string(), CHROME_UA.to_string())]);
+    let request = ImpersonatedRequest::new("POST", format!("http://{}/echo?q=1", addr))
+        .header("x-first", "1")
+        .header("x-second", "2")
+        .body("ping");
+    let response = client.send(&request).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.protocol, "http/1.1");
+    assert_eq!(response.text(), "pong");
+
+    let head = server.await.unwrap();
+    assert!(head.starts_with(&format!("POST /echo?q=1 HTTP/1.1\r\nHost: {}\r\nuser-agent: ", addr)));
+    let first = head.find("x-first").unwrap();
+    assert!(first < head.find("x-second").unwrap());
+    assert!(head.contains("Content-Length: 4\r\n"));
+}
+
+#[cfg(feature = "impersonation")]
+#[tokio::test]
+async fn test_client_hello_matches_profile() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut received = Vec::new();
+        loop {
+            let mut chunk = [0u8; 4096];
+            let n = socket.read(&mut chunk).await.unwrap();
+            assert!(n > 0);
+            received.extend_from_slice(&chunk[..n]);
+            if let Ok(info) = parse_client_hello(&received) {
+                break info;
+            }
+        }
+    });
+
+    // Chrome shuffles its extensions; a fixed order makes the check exact
+    let mut profile = TLSProfile::chrome();
+    profile.permute_extensions = false;
+    let client = ImpersonatedClient::from_profiles(&profile, &HTTP2Settings::chrome())
+        .unwrap()
+        .with_timeout(Duration::from_secs(5));
+    // The server drops the connection after the ClientHello
+    let _ = client.send(&ImpersonatedRequest::get(format!("https://localhost:{}/", addr.port()))).await;
+
+    let info = server.await.unwrap();
+    let spec = client.spec();
+    let ciphers: Vec<u16> = info.cipher_suites.iter().copied().filter(|c| !is_grease(*c)).collect();
+    assert_eq!(ciphers, spec.cipher_suites);
+    let sent: Vec<u16> = info.extensions.iter().copied().filter(|e| !is_grease(*e)).collect();
+    assert_eq!(sent, spec.extensions);
+    assert_eq!(info.alpn, spec.alpn);
+    assert_eq!(info.server_name.as_deref(), Some("localhost"));
+    assert!(info.ja4().starts_with("t13d"));
+}
+
+#[cfg(feature = "impersonation")]
+#[tokio::test]
+async fn test_cross_origin_redirect_drops_credentials() {
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target_addr = target.local_addr().unwrap();
+    let target = http1_server(target, "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_string());
+    let origin = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let origin_addr = origin.local_addr().unwrap();
+    let origin = http1_server(
+        origin,
+        format!("HTTP/1.1 302 Found\r\nLocation: http://{}/next\r\nContent-Length: 0\r\n\r\n", target_addr),
+    );
+
+    let client = ImpersonatedClient::from_profiles(&TLSProfile::chrome(), &HTTP2Settings::chrome())
+        .unwrap()
+        .with_timeout(Duration::from_secs(5));
+    let request = ImpersonatedRequest::get(format!("http://{}/start", origin_addr))
+        .header("Authorization", "Bearer secret")
+        .header("Cookie", "session=1")
+        .header("x-kept", "1");
+    let response = client.send(&request).await.unwrap();
+    assert_eq!(response.status, 200);
+    assert_eq!(response.url, format!("http://{}/next", target_addr));
+
+    assert!(origin.await.unwrap().contains("Authorization: Bearer secret"));
+    let head = target.await.unwrap().to_ascii_lowercase();
+    assert!(!head.contains("authorization"));
+    assert!(!head.contains("cookie"));
+    assert!(head.contains("x-kept: 1"));
+}
+
+#[cfg(feature = "impersonation")]
+#[tokio::test]
+async fn test_http_client_rejects_error_status() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = http1_server(listener, "HTTP/1.1 404 Not Found\r\nContent-Length: 9\r\n\r\nnot found".to_string());
+
+    let client = HttpClient::with_network_config(&network_config(), None).unwrap();
+    let err = client.get(&format!("http://{}/missing", addr)).await.unwrap_err();
+    assert!(err.to_string().contains("404"));
+    server.await.unwrap();
+}
+
+#[cfg(feature = "impersonation")]
+#[tokio::test]
+async fn test_oversized_bodies_are_rejected() {
+    let client = ImpersonatedClient::from_profiles(&TLSProfile::chrome(), &HTTP2Settings::chrome()).unwrap();
+    let replies = [
+        "HTTP/1.1 200 OK\r\nContent-Length: 1000000000000\r\n\r\nshort",
+        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nffffffffffffffff\r\nshort",
+    ];
+    for reply in replies {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = http1_server(listener, reply.to_string());
+
+        let request = ImpersonatedRequest::new("GET", format!("http://{}/", addr));
+        let err = client.send(&request).await.unwrap_err();
+        assert!(err.to_string().contains("exceeds"), "{}", err);
+        server.await.unwrap();
+    }
+}
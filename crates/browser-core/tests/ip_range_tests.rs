@@ -0,0 +1,401 @@
+//! Tests for IP range ingestion
+//!
+//! This module tests:
+//! - Parsing RIR delegation files and IP-to-ASN tables
+//! - Splitting delegations by ASN and merging adjacent ranges
+//! - The binary range cache and directory loading, including source set changes
+//! - Size-weighted range selection in IPGenerator
+//! - IpRangeIndex lookups, building the index from range files and correcting
+//!   provider-supplied countries
+
+use browser_core::proxy::{FreeProxy, ProxyType};
+use browser_core::proxy_validator::{GeoVerificationConfig, GeoVerifier};
+use browser_core::{correct_proxy_country, FreeIpProviderManager};
+use std::io::Write;
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::Path;
+use std::sync::Arc;
+use tempfile::TempDir;
+use virtual_ip::{
+    decode_range_cache, encode_range_cache, load_ip_ranges_from_file, load_range_dir,
+    load_range_index, merge_adjacent, CountryDatabase, IPGenerator, IPRange, IpRangeBuilder, IpRangeIndex,
+    RangeFormat, RangeSource,
+};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+const RIR_DELEGATION: &str = "\
+2|ripencc|1700000000|4|19830705|20231114|+0100
+ripencc|*|ipv4|*|3|summary
+ripencc|DE|ipv4|5.1.0.0|512|20110826|allocated|abc
+ripencc|DE|ipv4|5.1.2.0|256|20110826|allocated|abc
+ripencc|GB|ipv4|81.2.64.0|2048|20030101|assigned|def
+ripencc||ipv4|10.0.0.0|256||reserved|
+ripencc|FR|ipv6|2a01::|29|20050101|allocated|ghi
+";
+
+const IP2ASN_TSV: &str = "\
+5.1.0.0\t5.1.0.255\t3320\tDE\tDTAG Deutsche Telekom AG
+5.1.1.0\t5.1.1.255\t3320\tDE\tDTAG Deutsche Telekom AG
+6.0.0.0\t6.0.0.255\t0\tNone\tNot routed
+";
+
+const DBIP_ASN_CSV: &str = "\
+81.2.69.0,81.2.69.255,20712,\"Andrews & Arnold Ltd\"
+";
+
+const GEOLITE2_ASN_CSV: &str = "\
+network,autonomous_system_number,autonomous_system_organization
+81.2.70.0/24,AS20712,Andrews & Arnold Ltd
+";
+
+fn write_file(dir: &Path, name: &str, contents: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    let mut file = std::fs::File::create(&path).expect("Failed to create fixture");
+    file.write_all(contents.as_bytes()).expect("Failed to write fixture");
+    path
+}
+
+fn range(start: [u8; 4], end: [u8; 4], country: &str, isp: &str, asn: Option<u32>) -> IPRange {
+    IPRange {
+        start: Ipv4Addr::from(start),
+        end: Ipv4Addr::from(end),
+        country_code: country.into(),
+        isp: isp.into(),
+        asn,
+    }
+}
+
+fn create_test_proxy(ip: &str, country_code: &str) -> FreeProxy {
+    FreeProxy {
+        ip: ip.to_string(),
+        port: 8080,
+        protocol: ProxyType::Http,
+        country: country_code.to_string(),
+        country_code: country_code.to_string(),
+        anonymity: "elite".to_string(),
+        speed: 100,
+        uptime: 99.5,
+        last_checked: "2026-01-01T00:00:00Z".to_string(),
+        provider: "TestProvider".to_string(),
+        is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
+    }
+}
+
+fn fixture_index() -> IpRangeIndex {
+    let mut builder = IpRangeBuilder::new();
+    builder.add_delegations(RIR_DELEGATION);
+    builder.add_asn_table(IP2ASN_TSV, RangeFormat::IpToAsnTsv).unwrap();
+    builder.build_index(&CountryDatabase::load_all_countries())
+}
+
+fn find<'a>(ranges: &'a [IPRange], ip: &str) -> &'a IPRange {
+    let ip: Ipv4Addr = ip.parse().unwrap();
+    ranges.iter().find(|r| r.contains(&ip)).expect("IP should be covered")
+}
+
+// ============================================================================
+// Parsing Tests
+// ============================================================================
+
+#[test]
+fn test_format_detection() {
+    let detect = |name: &str| RangeFormat::detect(Path::new(name));
+    assert_eq!(detect("delegated-ripencc-extended-latest"), Some(RangeFormat::RirDelegation));
+    assert_eq!(detect("ip2asn-v4.tsv"), Some(RangeFormat::IpToAsnTsv));
+    assert_eq!(detect("dbip-asn-lite-2026-10.csv"), Some(RangeFormat::DbIpAsnCsv));
+    assert_eq!(detect("GeoLite2-ASN-Blocks-IPv4.csv"), Some(RangeFormat::GeoLite2AsnCsv));
+    assert_eq!(detect("ranges.json"), Some(RangeFormat::Json));
+    assert_eq!(detect("ip_ranges.cache"), Some(RangeFormat::Cache));
+    assert_eq!(detect("README.md"), None);
+}
+
+#[test]
+fn test_delegations_skip_unallocated_space() {
+    let mut builder = IpRangeBuilder::new();
+    assert_eq!(builder.add_delegations(RIR_DELEGATION), 4);
+
+    let ranges = builder.build();
+    // The two adjacent DE allocations merge into one range
+    assert_eq!(ranges.len(), 2);
+    let de = find(&ranges, "5.1.2.10");
+    assert_eq!(de.start, Ipv4Addr::new(5, 1, 0, 0));
+    assert_eq!(de.end, Ipv4Addr::new(5, 1, 2, 255));
+    assert_eq!(de.country_code, "DE");
+    assert_eq!(de.asn, None);
+    assert_eq!(find(&ranges, "81.2.71.1").country_code, "GB");
+}
+
+#[test]
+fn test_asn_tables_split_delegations() {
+    let mut builder = IpRangeBuilder::new();
+    builder.add_delegations(RIR_DELEGATION);
+    assert_eq!(builder.add_asn_table(IP2ASN_TSV, RangeFormat::IpToAsnTsv).unwrap(), 2);
+    assert_eq!(builder.add_asn_table(DBIP_ASN_CSV, RangeFormat::DbIpAsnCsv).unwrap(), 1);
+    assert_eq!(builder.add_asn_table(GEOLITE2_ASN_CSV, RangeFormat::GeoLite2AsnCsv).unwrap(), 1);
+    let ranges = builder.build();
+
+    let dtag = find(&ranges, "5.1.1.1");
+    assert_eq!(dtag.start, Ipv4Addr::new(5, 1, 0, 0));
+    assert_eq!(dtag.end, Ipv4Addr::new(5, 1, 1, 255));
+    assert_eq!(dtag.asn, Some(3320));
+    assert_eq!(dtag.isp, "DTAG Deutsche Telekom AG");
+
+    let rest = find(&ranges, "5.1.2.1");
+    assert_eq!(rest.asn, None);
+    assert_eq!(rest.country_code, "DE");
+
+    // DB-IP and GeoLite2 rows take their country from the delegation and merge
+    let aaisp = find(&ranges, "81.2.69.200");
+    assert_eq!(aaisp.country_code, "GB");
+    assert_eq!(aaisp.asn, Some(20712));
+    assert_eq!(aaisp.isp, "Andrews & Arnold Ltd");
+    assert_eq!(aaisp.end, Ipv4Addr::new(81, 2, 70, 255));
+
+    assert_eq!(find(&ranges, "81.2.64.1").asn, None);
+    assert_eq!(find(&ranges, "81.2.71.255").asn, None);
+    assert!(!ranges.iter().any(|r| r.contains(&Ipv4Addr::new(6, 0, 0, 1))));
+}
+
+#[test]
+fn test_merge_adjacent_keeps_distinct_isps() {
+    let merged = merge_adjacent(vec![
+        range([1, 0, 1, 0], [1, 0, 1, 255], "AU", "A", Some(1)),
+        range([1, 0, 0, 0], [1, 0, 0, 255], "AU", "A", Some(1)),
+        range([1, 0, 2, 0], [1, 0, 2, 255], "AU", "B", Some(2)),
+    ]);
+    assert_eq!(merged.len(), 2);
+    assert_eq!(merged[0].end, Ipv4Addr::new(1, 0, 1, 255));
+    assert_eq!(merged[0].size(), 512);
+}
+
+// ============================================================================
+// Cache Tests
+// ============================================================================
+
+#[test]
+fn test_cache_round_trip() {
+    let ranges = vec![
+        range([5, 1, 0, 0], [5, 1, 1, 255], "DE", "DTAG Deutsche Telekom AG", Some(3320)),
+        range([5, 1, 2, 0], [5, 1, 2, 255], "DE", "Unknown ISP", None),
+        range([81, 2, 69, 0], [81, 2, 70, 255], "GB", "Andrews & Arnold Ltd", Some(20712)),
+    ];
+    let bytes = encode_range_cache(&ranges);
+    let decoded = decode_range_cache(&bytes).expect("Cache should decode");
+
+    assert_eq!(decoded.len(), 3);
+    for (a, b) in ranges.iter().zip(&decoded) {
+        assert_eq!((a.start, a.end, a.asn), (b.start, b.end, b.asn));
+        assert_eq!((&a.country_code, &a.isp), (&b.country_code, &b.isp));
+    }
+
+    assert!(decode_range_cache(&bytes[..bytes.len() - 1]).is_err());
+    assert!(decode_range_cache(b"nope").is_err());
+}
+
+#[test]
+fn test_cache_truncates_long_strings() {
+    // Multi-byte characters straddle the 65535-byte limit
+    let isp = "é".repeat(40_000);
+    let ranges = vec![
+        range([5, 1, 0, 0], [5, 1, 0, 255], "DE", &isp, Some(3320)),
+        range([5, 1, 1, 0], [5, 1, 1, 255], "DE", "Short ISP", None),
+    ];
+    let decoded = decode_range_cache(&encode_range_cache(&ranges)).expect("Cache should decode");
+
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[0].isp.len(), 65534);
+    assert!(isp.starts_with(&decoded[0].isp));
+    assert_eq!(decoded[1].isp, "Short ISP");
+}
+
+#[test]
+fn test_load_range_dir_writes_and_reuses_cache() {
+    let dir = TempDir::new().expect("Temp dir");
+    write_file(dir.path(), "delegated-ripencc-extended-latest", RIR_DELEGATION);
+    write_file(dir.path(), "ip2asn-v4.tsv", IP2ASN_TSV);
+
+    let ranges = load_range_dir(dir.path()).expect("Directory should load");
+    let cache = dir.path().join("ip_ranges.cache");
+    assert!(cache.exists());
+
+    let cached = RangeSource::detect(&cache).expect("Cache source");
+    let mut builder = IpRangeBuilder::new();
+    assert_eq!(builder.add_source(&cached).unwrap(), ranges.len());
+
+    let reloaded = load_range_dir(dir.path()).expect("Cached directory should load");
+    assert_eq!(reloaded.len(), ranges.len());
+    assert_eq!(find(&reloaded, "5.1.0.1").asn, Some(3320));
+}
+
+#[test]
+fn test_load_range_dir_rebuilds_after_source_removed() {
+    let dir = TempDir::new().expect("Temp dir");
+    write_file(dir.path(), "delegated-ripencc-extended-latest", RIR_DELEGATION);
+    let asn_table = write_file(dir.path(), "ip2asn-v4.tsv", IP2ASN_TSV);
+    assert_eq!(find(&load_range_dir(dir.path()).unwrap(), "5.1.0.1").asn, Some(3320));
+
+    // The cache is newer than every remaining source, but was built from a different set
+    std::fs::remove_file(asn_table).expect("Remove ASN table");
+    let rebuilt = load_range_dir(dir.path()).expect("Directory should load");
+    assert_eq!(find(&rebuilt, "5.1.0.1").asn, None);
+    assert_eq!(find(&rebuilt, "5.1.0.1").isp, "Unknown ISP");
+}
+
+#[test]
+fn test_load_ip_ranges_from_file_accepts_directories() {
+    let dir = TempDir::new().expect("Temp dir");
+    write_file(dir.path(), "delegated-ripencc-extended-latest", RIR_DELEGATION);
+    let ranges = load_ip_ranges_from_file(dir.path());
+    assert_eq!(find(&ranges, "81.2.64.1").country_code, "GB");
+
+    // Missing paths fall back to the placeholder list
+    assert!(!load_ip_ranges_from_file(&dir.path().join("missing.json")).is_empty());
+}
+
+// ============================================================================
+// Generator Tests
+// ============================================================================
+
+#[test]
+fn test_generate_for_country_weights_by_range_size() {
+    let ranges = vec![
+        range([10, 0, 0, 1], [10, 0, 0, 1], "US", "Tiny", None),
+        range([20, 0, 0, 0], [20, 255, 255, 255], "US", "Large", Some(7)),
+    ];
+    let generator = IPGenerator::new(CountryDatabase::load_all_countries(), ranges);
+
+    let mut large = 0;
+    for _ in 0..200 {
+        let ip = generator.generate_for_country("US").expect("US should generate");
+        if ip.isp == "Large" {
+            assert!(matches!(ip.ip, IpAddr::V4(v4) if v4.octets()[0] == 20), "{}", ip.ip);
+            large += 1;
+        } else {
+            assert_eq!(ip.ip, Ipv4Addr::new(10, 0, 0, 1));
+        }
+    }
+    assert!(large >= 195, "large range chosen only {} times", large);
+}
+
+// ============================================================================
+// Index Tests
+// ============================================================================
+
+#[test]
+fn test_index_lookup_v4_and_v6() {
+    let index = fixture_index();
+    assert_eq!(index.len(), 4);
+
+    let (country, isp) = index.lookup_str("5.1.0.7").expect("DTAG range");
+    assert_eq!(country.code, "DE");
+    assert_eq!(isp, "DTAG Deutsche Telekom AG");
+    assert_eq!(index.lookup_info("5.1.0.7".parse().unwrap()).unwrap().asn, Some(3320));
+
+    // Known countries keep their full record
+    let (country, _) = index.lookup_str("81.2.64.1").expect("GB range");
+    assert_eq!(country.name, "United Kingdom");
+
+    let (country, isp) = index.lookup_str("2a01:4:1::1").expect("FR IPv6 allocation");
+    assert_eq!(country.code, "FR");
+    assert_eq!(isp, "Unknown ISP");
+
+    let mapped: IpAddr = "::ffff:5.1.2.9".parse().unwrap();
+    assert_eq!(index.lookup(mapped).unwrap().0.code, "DE");
+
+    assert!(index.lookup_str("5.1.3.0").is_none());
+    assert!(index.lookup_str("10.0.0.1").is_none());
+    assert!(index.lookup_str("proxy.example.com").is_none());
+}
+
+#[test]
+fn test_index_first_overlapping_range_wins() {
+    let index = IpRangeIndex::new(
+        &[
+            range([1, 0, 0, 0], [1, 0, 0, 255], "AU", "APNIC Labs", Some(13335)),
+            range([1, 0, 0, 128], [1, 0, 1, 255], "CN", "Overlap", None),
+            range([1, 0, 2, 0], [1, 0, 2, 255], "CN", "China Telecom", None),
+        ],
+        &CountryDatabase::load_all_countries(),
+    );
+    assert_eq!(index.len(), 2);
+    assert_eq!(index.lookup_str("1.0.0.200").unwrap().1, "APNIC Labs");
+    assert!(index.lookup_str("1.0.1.1").is_none());
+    assert_eq!(index.lookup_str("1.0.2.1").unwrap().0.code, "CN");
+}
+
+#[test]
+fn test_load_range_index_without_placeholders() {
+    let dir = TempDir::new().expect("Temp dir");
+    write_file(dir.path(), "delegated-ripencc-extended-latest", RIR_DELEGATION);
+    write_file(dir.path(), "ip2asn-v4.tsv", IP2ASN_TSV);
+    let countries = CountryDatabase::load_all_countries();
+
+    let (ranges, index) = load_range_index(dir.path(), &countries).expect("Directory should index");
+    assert_eq!(find(&ranges, "5.1.0.1").asn, Some(3320));
+    assert_eq!(index.lookup_str("2a01:4:1::1").unwrap().0.code, "FR");
+    assert!(index.lookup_str("1.0.0.1").is_none());
+
+    // Unlike load_ip_ranges_from_file, nothing falls back to the placeholder ranges
+    assert!(load_range_index(&dir.path().join("missing.json"), &countries).is_err());
+    let empty = write_file(dir.path(), "empty.json", "[]");
+    assert!(load_range_index(&empty, &countries).is_err());
+}
+
+#[test]
+fn test_correct_proxy_country() {
+    let index = fixture_index();
+
+    let mut wrong = create_test_proxy("81.2.66.10", "US");
+    assert!(correct_proxy_country(&index, &mut wrong));
+    assert_eq!(wrong.country_code, "GB");
+    assert_eq!(wrong.country, "United Kingdom");
+
+    let mut right = create_test_proxy("5.1.0.1", "de");
+    assert!(!correct_proxy_country(&index, &mut right));
+    let mut unknown = create_test_proxy("203.0.113.5", "JP");
+    assert!(!correct_proxy_country(&index, &mut unknown));
+    assert_eq!(unknown.country_code, "JP");
+}
+
+#[test]
+fn test_provider_manager_corrects_countries_on_merge() {
+    let mut manager = FreeIpProviderManager::new()
+        .expect("Manager")
+        .with_range_index(Arc::new(fixture_index()));
+    let merged = manager.merge_results(vec![(
+        "TestProvider".to_string(),
+        Ok(vec![create_test_proxy("5.1.1.20", "XX"), create_test_proxy("198.51.100.1", "NL")]),
+    )]);
+
+    assert_eq!(merged[0].country_code, "DE");
+    assert_eq!(merged[1].country_code, "NL");
+}
+
+#[tokio::test]
+async fn test_geo_verifier_uses_range_index() {
+    let config = GeoVerificationConfig {
+        geoip_api_urls: Vec::new(),
+        ..Default::default()
+    };
+    let mut verifier = GeoVerifier::new(config).expect("Verifier");
+    assert!(verifier.lookup_ip_location("5.1.0.1").await.is_err());
+
+    verifier = verifier.with_range_index(Arc::new(fixture_index()));
+    let record = verifier.lookup_ip_location("5.1.0.1").await.expect("Indexed IP");
+    assert_eq!(record.country_code.as_deref(), Some("DE"));
+    assert_eq!(record.asn, Some(3320));
+    assert_eq!(record.organization.as_deref(), Some("DTAG Deutsche Telekom AG"));
+
+    let result = verifier.verify_proxy_location(&create_test_proxy("9.9.9.9", "DE"), "5.1.2.3").await;
+    assert!(result.is_verified);
+
+    let mut proxy = create_test_proxy("81.2.64.9", "FR");
+    assert!(verifier.correct_country(&mut proxy));
+    assert_eq!(proxy.country_code, "GB");
+}
@@ -0,0 +1,432 @@
+//! Tests for WebRTC and DNS leak detection
+//!
+//! This module tests:
+//! - STUN Binding request/response encoding
+//! - ICE candidate parsing and comparison with the exit IP
+//! - Skipping host and private candidates unless configured
+//! - WebRTC leak checks against a local STUN stand-in
+//! - DNS leak checks against a local "whoami" DNS stand-in
+//! - DNS leak checks from a page, read back from the probe zone's report
+//! - Treating resolvers in the exit's ASN as the provider's, whatever their country
+//! - Detecting the public IP through the tab's exit proxy
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tempfile::TempDir;
+use tokio::net::{TcpListener, UdpSocket};
+use virtual_ip::{
+    dns_probe_names, encode_binding_request, encode_binding_response, fetch_reported_resolvers,
+    parse_binding_response, parse_ice_candidate, webrtc_leaks, DnsProbeSource, GeoIpDatabase,
+    GeoIpSource, IPValidator, IceCandidateSource, LeakCheckConfig, StunCandidateSource, VirtualIP,
+};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+const EXIT_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1));
+
+/// Answer every STUN Binding request with `mapped` as the reflexive address.
+async fn spawn_stun_standin(mapped: SocketAddr) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+            if len < 20 {
+                continue;
+            }
+            let mut transaction_id = [0u8; 12];
+            transaction_id.copy_from_slice(&buf[8..20]);
+            let _ = socket.send_to(&encode_binding_response(&transaction_id, mapped), peer).await;
+        }
+    });
+    addr
+}
+
+/// Authoritative "whoami" stand-in: answers A queries with `resolver`, as if
+/// that resolver had forwarded the query.
+async fn spawn_dns_standin(resolver: Ipv4Addr) -> SocketAddr {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut buf).await {
+            if let Some(response) = dns_answer(&buf[..len], resolver) {
+                let _ = socket.send_to(&response, peer).await;
+            }
+        }
+    });
+    addr
+}
+
+fn dns_answer(query: &[u8], resolver: Ipv4Addr) -> Option<Vec<u8>> {
+    // Skip the question name to find QTYPE
+    let mut end = 12;
+    while *query.get(end)? != 0 {
+        end += 1 + query[end] as usize;
+    }
+    let question_end = end + 5;
+    let qtype = u16::from_be_bytes([*query.get(end + 1)?, *query.get(end + 2)?]);
+    let answers: u16 = if qtype == 1 { 1 } else { 0 };
+
+    let mut response = Vec::new();
+    response.extend_from_slice(&query[0..2]);
+    response.extend_from_slice(&[0x85, 0x80, 0, 1]);
+    response.extend_from_slice(&answers.to_be_bytes());
+    response.extend_from_slice(&[0, 0, 0, 0]);
+    response.extend_from_slice(&query[12..question_end]);
+    if answers == 1 {
+        response.extend_from_slice(&[0xC0, 0x0C, 0, 1, 0, 1, 0, 0, 0, 0, 0, 4]);
+        response.extend_from_slice(&resolver.octets());
+    }
+    Some(response)
+}
+
+/// Returns fixed candidates, standing in for a browser page.
+struct FixedCandidates(Vec<String>);
+
+#[async_trait]
+impl IceCandidateSource for FixedCandidates {
+    async fn gather_candidates(&self, _stun_servers: &[String], _timeout: Duration) -> Result<Vec<String>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// A source that could not gather, like a page whose browser is not running.
+struct UnavailableSource;
+
+#[async_trait]
+impl IceCandidateSource for UnavailableSource {
+    async fn gather_candidates(&self, _stun_servers: &[String], _timeout: Duration) -> Result<Vec<String>> {
+        Err(anyhow!("Chromium engine not running"))
+    }
+}
+
+/// Reports fixed resolvers, standing in for a page and the probe zone's report.
+struct FixedResolvers(Vec<IpAddr>);
+
+#[async_trait]
+impl DnsProbeSource for FixedResolvers {
+    async fn resolve_probes(&self, _config: &LeakCheckConfig, _names: &[String]) -> Result<Vec<IpAddr>> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Probe zone report stand-in: answers `/resolvers/{name}` with `resolver`
+/// for names under `known`, and 404 for the rest.
+async fn spawn_report_standin(known: &'static str, resolver: Ipv4Addr) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut buf = [0u8; 2048];
+            let n = socket.read(&mut buf).await.unwrap_or(0);
+            let request = String::from_utf8_lossy(&buf[..n]);
+            let path = request.split_whitespace().nth(1).unwrap_or("");
+            let response = if path.starts_with("/resolvers/") && path.ends_with(known) {
+                let body = format!("[\"{}\"]", resolver);
+                format!("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}", body.len(), body)
+            } else {
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n".to_string()
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+    addr
+}
+
+fn leak_config() -> LeakCheckConfig {
+    LeakCheckConfig {
+        ice_timeout: Duration::from_secs(2),
+        dns_timeout: Duration::from_secs(2),
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// Parsing Tests
+// ============================================================================
+
+#[test]
+fn test_stun_round_trip() {
+    let transaction_id = [7u8; 12];
+    let request = encode_binding_request(&transaction_id);
+    assert_eq!(request.len(), 20);
+    assert_eq!(&request[4..8], &[0x21, 0x12, 0xA4, 0x42]);
+
+    for mapped in ["198.51.100.7:40000", "[2001:db8::7]:3478"] {
+        let mapped: SocketAddr = mapped.parse().unwrap();
+        let response = encode_binding_response(&transaction_id, mapped);
+        assert_eq!(parse_binding_response(&response, &transaction_id).unwrap(), mapped);
+        assert!(parse_binding_response(&response, &[8u8; 12]).is_err());
+    }
+    assert!(parse_binding_response(&request, &transaction_id).is_err());
+}
+
+#[test]
+fn test_parse_ice_candidate() {
+    let srflx = parse_ice_candidate(
+        "candidate:842163049 1 udp 1677729535 198.51.100.7 40000 typ srflx raddr 0.0.0.0 rport 0 generation 0",
+    )
+    .unwrap();
+    assert_eq!(srflx.address, "198.51.100.7".parse::<IpAddr>().unwrap());
+    assert_eq!(srflx.port, 40000);
+    assert_eq!(srflx.kind, "srflx");
+
+    let v6 = parse_ice_candidate("a=candidate:1 1 udp 2122262783 2001:db8::1 50000 typ host").unwrap();
+    assert_eq!(v6.kind, "host");
+    assert!(v6.address.is_ipv6());
+
+    assert!(parse_ice_candidate("candidate:1 1 udp 2122260223 4f1c-2b.local 50000 typ host").is_none());
+    assert!(parse_ice_candidate("not a candidate").is_none());
+}
+
+#[test]
+fn test_webrtc_leaks_excludes_exit() {
+    let candidates = vec![
+        "candidate:1 1 udp 2122260223 abcd.local 50000 typ host".to_string(),
+        "candidate:2 1 udp 1686052607 203.0.113.1 50000 typ srflx".to_string(),
+        "candidate:3 1 udp 1686052607 198.51.100.7 50001 typ srflx".to_string(),
+        "candidate:4 1 tcp 1686052607 198.51.100.7 9 typ srflx".to_string(),
+    ];
+    let leaks = webrtc_leaks(&candidates, EXIT_IP, false);
+    assert_eq!(leaks, vec!["198.51.100.7".parse::<IpAddr>().unwrap()]);
+}
+
+#[test]
+fn test_webrtc_leaks_skips_local_candidates() {
+    let candidates = vec![
+        "candidate:1 1 udp 2122260223 192.168.1.20 50000 typ host".to_string(),
+        "candidate:2 1 udp 2122260223 198.51.100.9 50000 typ host".to_string(),
+        "candidate:3 1 udp 1686052607 10.8.0.2 50001 typ srflx".to_string(),
+        "candidate:4 1 udp 2122262783 fe80::1 50002 typ srflx".to_string(),
+        "candidate:5 1 udp 1686052607 198.51.100.7 50003 typ srflx".to_string(),
+    ];
+    let public = webrtc_leaks(&candidates, EXIT_IP, false);
+    assert_eq!(public, vec!["198.51.100.7".parse::<IpAddr>().unwrap()]);
+
+    let all = webrtc_leaks(&candidates, EXIT_IP, true);
+    assert_eq!(all.len(), 5, "{:?}", all);
+}
+
+// ============================================================================
+// WebRTC Leak Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_webrtc_leak_with_stun_standin() {
+    let stun = spawn_stun_standin("198.51.100.7:40000".parse().unwrap()).await;
+    let config = LeakCheckConfig {
+        stun_servers: vec![stun.to_string()],
+        ..leak_config()
+    };
+
+    let candidates = StunCandidateSource
+        .gather_candidates(&config.stun_servers, config.ice_timeout)
+        .await
+        .unwrap();
+    assert!(candidates.iter().any(|c| c.contains("198.51.100.7 40000 typ srflx")));
+
+    let validator = IPValidator::new().with_leak_config(config);
+    let leaks = validator.check_webrtc_leak(EXIT_IP).await.unwrap();
+    assert!(leaks.contains(&"198.51.100.7".to_string()), "{:?}", leaks);
+
+    // The stand-in reporting the exit itself is not a leak
+    let stun = spawn_stun_standin(SocketAddr::new(EXIT_IP, 40000)).await;
+    let validator = IPValidator::new().with_leak_config(LeakCheckConfig {
+        stun_servers: vec![stun.to_string()],
+        ..leak_config()
+    });
+    let leaks = validator.check_webrtc_leak(EXIT_IP).await.unwrap();
+    assert!(!leaks.contains(&EXIT_IP.to_string()));
+}
+
+#[tokio::test]
+async fn test_webrtc_leak_with_page_candidates() {
+    let validator = IPValidator::new().with_ice_source(Arc::new(FixedCandidates(vec![
+        "candidate:1 1 udp 2122260223 abcd.local 50000 typ host".to_string(),
+        "candidate:2 1 udp 1686052607 203.0.113.1 50000 typ srflx".to_string(),
+    ])));
+    assert!(validator.check_webrtc_leak(EXIT_IP).await.unwrap().is_empty());
+
+    // A page that finished gathering without candidates exposes nothing
+    let validator = IPValidator::new().with_ice_source(Arc::new(FixedCandidates(vec![])));
+    assert!(validator.check_webrtc_leak(EXIT_IP).await.unwrap().is_empty());
+
+    // A page that could not gather proves nothing
+    let validator = IPValidator::new().with_ice_source(Arc::new(UnavailableSource));
+    assert!(validator.check_webrtc_leak(EXIT_IP).await.is_err());
+}
+
+#[tokio::test]
+async fn test_stun_source_fails_without_answer() {
+    // Bound but silent, so the binding times out
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let servers = vec![silent.local_addr().unwrap().to_string()];
+    let result = StunCandidateSource
+        .gather_candidates(&servers, Duration::from_millis(200))
+        .await;
+    assert!(result.is_err());
+}
+
+// ============================================================================
+// DNS Leak Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_dns_leak_with_standin() {
+    let isp_resolver = Ipv4Addr::new(198, 51, 100, 53);
+    let dns = spawn_dns_standin(isp_resolver).await;
+    let config = LeakCheckConfig {
+        dns_probe_domain: Some("leak.test".to_string()),
+        dns_nameservers: vec![dns],
+        ..leak_config()
+    };
+
+    let validator = IPValidator::new().with_leak_config(config.clone());
+    let leaks = validator.check_dns_leak(EXIT_IP).await.unwrap();
+    assert_eq!(leaks, vec![isp_resolver.to_string()]);
+
+    let validator = IPValidator::new().with_leak_config(LeakCheckConfig {
+        trusted_resolvers: vec![IpAddr::V4(isp_resolver)],
+        ..config
+    });
+    assert!(validator.check_dns_leak(EXIT_IP).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_dns_leak_exit_resolver_is_secure() {
+    let dns = spawn_dns_standin(Ipv4Addr::new(203, 0, 113, 1)).await;
+    let validator = IPValidator::new().with_leak_config(LeakCheckConfig {
+        dns_probe_domain: Some("leak.test".to_string()),
+        dns_nameservers: vec![dns],
+        ..leak_config()
+    });
+    assert!(validator.check_dns_leak(EXIT_IP).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_dns_leak_requires_probe_domain() {
+    let validator = IPValidator::new().with_leak_config(leak_config());
+    assert!(validator.check_dns_leak(EXIT_IP).await.is_err());
+}
+
+#[tokio::test]
+async fn test_dns_leak_with_page_probes() {
+    let isp_resolver: IpAddr = "198.51.100.53".parse().unwrap();
+    let config = LeakCheckConfig {
+        dns_probe_domain: Some("leak.test".to_string()),
+        ..leak_config()
+    };
+
+    let validator = IPValidator::new()
+        .with_leak_config(config.clone())
+        .with_dns_source(Arc::new(FixedResolvers(vec![EXIT_IP, isp_resolver])));
+    assert_eq!(validator.check_dns_leak(EXIT_IP).await.unwrap(), vec![isp_resolver.to_string()]);
+
+    let validator = IPValidator::new()
+        .with_leak_config(config)
+        .with_dns_source(Arc::new(FixedResolvers(vec![EXIT_IP])));
+    assert!(validator.check_dns_leak(EXIT_IP).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_dns_leak_compares_resolver_asn_with_exit() {
+    // The exit and both resolvers are in the US; only the provider's own
+    // resolver shares the exit's ASN
+    let provider_resolver: IpAddr = "203.0.113.53".parse().unwrap();
+    let isp_resolver: IpAddr = "198.51.100.53".parse().unwrap();
+    let dir = TempDir::new().unwrap();
+    let write = |name: &str, contents: &str| {
+        let path = dir.path().join(name);
+        std::fs::File::create(&path).unwrap().write_all(contents.as_bytes()).unwrap();
+        GeoIpSource::detect(path).unwrap()
+    };
+    let geoip = GeoIpDatabase::open(vec![
+        write(
+            "dbip-city-lite.csv",
+            "ip_start,ip_end,continent,country,stateprov,city,latitude,longitude\n\
+             203.0.113.0,203.0.113.255,NA,US,Virginia,Ashburn,39.0438,-77.4874\n\
+             198.51.100.0,198.51.100.255,NA,US,Virginia,Reston,38.9586,-77.357\n",
+        ),
+        write(
+            "dbip-asn-lite.csv",
+            "203.0.113.0,203.0.113.255,64500,Example Proxy Provider\n\
+             198.51.100.0,198.51.100.255,64501,Example ISP\n",
+        ),
+    ])
+    .unwrap();
+
+    let validator = IPValidator::new()
+        .with_geoip(Arc::new(geoip))
+        .with_leak_config(LeakCheckConfig {
+            dns_probe_domain: Some("leak.test".to_string()),
+            ..leak_config()
+        })
+        .with_dns_source(Arc::new(FixedResolvers(vec![provider_resolver, isp_resolver])));
+    assert_eq!(validator.check_dns_leak(EXIT_IP).await.unwrap(), vec![isp_resolver.to_string()]);
+}
+
+#[tokio::test]
+async fn test_fetch_reported_resolvers() {
+    let resolver = Ipv4Addr::new(198, 51, 100, 53);
+    let report = spawn_report_standin("seen.leak.test", resolver).await;
+    let config = LeakCheckConfig {
+        dns_probe_report_url: Some(format!("http://{}/resolvers/{{name}}", report)),
+        ..leak_config()
+    };
+
+    let names = vec!["a1.seen.leak.test".to_string(), "b2.other.leak.test".to_string()];
+    let resolvers = fetch_reported_resolvers(&config, &names).await.unwrap();
+    assert_eq!(resolvers, vec![IpAddr::V4(resolver)]);
+
+    // A zone that saw none of the probes proves nothing
+    let other = dns_probe_names("other.leak.test", 2);
+    assert_eq!(other.len(), 2);
+    assert!(other.iter().all(|name| name.ends_with(".other.leak.test")));
+    assert!(fetch_reported_resolvers(&config, &other).await.is_err());
+    assert!(fetch_reported_resolvers(&leak_config(), &names).await.is_err());
+}
+
+// ============================================================================
+// Public IP Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_public_ip_is_detected_through_exit_proxy() {
+    // Proxy stand-in that records the request and refuses it
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let proxy = listener.local_addr().unwrap();
+    let seen = tokio::spawn(async move {
+        let (mut socket, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = socket.read(&mut buf).await.unwrap();
+        let _ = socket.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n").await;
+        String::from_utf8_lossy(&buf[..n]).into_owned()
+    });
+
+    let validator = IPValidator::new().with_proxy(&format!("http://{}", proxy)).unwrap();
+    let virtual_ip = VirtualIP {
+        ip: EXIT_IP,
+        country_code: "US".to_string(),
+        country: "United States".to_string(),
+        city: "New York".to_string(),
+        region: "New York".to_string(),
+        timezone: "America/New_York".to_string(),
+        language: "en-US".to_string(),
+        currency: "USD".to_string(),
+        isp: "Example Transit".to_string(),
+        proxy_url: Some(format!("http://{}", proxy)),
+        accept_language: "en-US,en;q=0.9".to_string(),
+        exit_verified: true,
+    };
+    assert!(validator.validate(&virtual_ip).await.is_err());
+    let request = tokio::time::timeout(Duration::from_secs(5), seen).await.unwrap().unwrap();
+    assert!(request.starts_with("CONNECT ipinfo.io:443"), "{}", request);
+}
@@ -0,0 +1,265 @@
+//! Tests for cross-provider deduplication and provider stats
+//!
+//! This module tests:
+//! - Merging provider results into one record per endpoint
+//! - Provenance (sources, first/last seen) across fetch rounds
+//! - Working share, duplicate ratio and failure tracking per provider
+//! - Automatic and manual disabling of providers
+//! - Fetching and validating a shared manager outside its lock
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use browser_core::free_ip_providers::{FreeIpProvider, FreeIpProviderManager, ProviderHealthPolicy, ProxyProvider};
+use browser_core::proxy::{FreeProxy, ProxyType};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+fn create_test_proxy(ip: &str, port: u16, country_code: &str, provider: &str) -> FreeProxy {
+    FreeProxy {
+        ip: ip.to_string(),
+        port,
+        protocol: ProxyType::Http,
+        country: country_code.to_string(),
+        country_code: country_code.to_string(),
+        anonymity: "elite".to_string(),
+        speed: 100,
+        uptime: 99.0,
+        last_checked: Utc::now().to_rfc3339(),
+        provider: provider.to_string(),
+        is_working: false,
+        username: None,
+        password: None,
+        ssh: None,
+    }
+}
+
+/// Provider returning a fixed list without touching the network
+struct StaticProvider {
+    name: String,
+    proxies: Vec<FreeProxy>,
+}
+
+#[async_trait]
+impl ProxyProvider for StaticProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn rate_limit(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    async fn fetch_proxies(&self) -> Result<Vec<FreeProxy>> {
+        Ok(self.proxies.clone())
+    }
+}
+
+fn manager_without_builtins() -> FreeIpProviderManager {
+    let mut manager = FreeIpProviderManager::new().expect("Provider manager");
+    for provider in FreeIpProvider::all() {
+        manager.set_provider_enabled(provider.name(), false).expect("Known provider");
+    }
+    manager
+}
+
+// ============================================================================
+// Merge and Provenance Tests
+// ============================================================================
+
+#[test]
+fn test_merge_keeps_one_record_per_endpoint() {
+    let mut manager = FreeIpProviderManager::new().expect("Provider manager");
+
+    let merged = manager.merge_results(vec![
+        (
+            "Alpha".to_string(),
+            Ok(vec![
+                create_test_proxy("1.1.1.1", 8080, "XX", "Alpha"),
+                create_test_proxy("2.2.2.2", 3128, "US", "Alpha"),
+                create_test_proxy("2.2.2.2", 3128, "US", "Alpha"),
+            ]),
+        ),
+        (
+            "Beta".to_string(),
+            Ok(vec![
+                create_test_proxy("1.1.1.1", 8080, "DE", "Beta"),
+                create_test_proxy("3.3.3.3", 80, "FR", "Beta"),
+            ]),
+        ),
+        ("Gamma".to_string(), Err(anyhow!("connection refused"))),
+    ]);
+
+    assert_eq!(merged.len(), 3);
+    let shared = merged.iter().find(|p| p.ip == "1.1.1.1").expect("Shared endpoint");
+    assert_eq!(shared.provider, "Alpha");
+    assert_eq!(shared.country_code, "DE");
+
+    let provenance = manager.provenance("1.1.1.1", 8080).expect("Provenance");
+    assert_eq!(provenance.sources, vec!["Alpha".to_string(), "Beta".to_string()]);
+    assert_eq!(manager.provenance("3.3.3.3", 80).expect("Provenance").sources, vec!["Beta".to_string()]);
+}
+
+#[test]
+fn test_provenance_survives_rounds() {
+    let mut manager = FreeIpProviderManager::new().expect("Provider manager");
+
+    manager.merge_results(vec![("Alpha".to_string(), Ok(vec![create_test_proxy("1.1.1.1", 80, "US", "Alpha")]))]);
+    let first = manager.provenance("1.1.1.1", 80).expect("Provenance").clone();
+
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    manager.merge_results(vec![("Beta".to_string(), Ok(vec![create_test_proxy("1.1.1.1", 80, "US", "Beta")]))]);
+    let second = manager.provenance("1.1.1.1", 80).expect("Provenance");
+
+    assert_eq!(second.first_seen, first.first_seen);
+    assert!(second.last_seen > first.last_seen);
+    assert_eq!(second.sources, vec!["Alpha".to_string(), "Beta".to_string()]);
+}
+
+// ============================================================================
+// Provider Stats Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_stats_track_duplicates_working_share_and_failures() {
+    let mut manager = manager_without_builtins().with_health_policy(ProviderHealthPolicy {
+        auto_disable: false,
+        ..Default::default()
+    });
+    let alpha = vec![
+        create_test_proxy("1.1.1.1", 80, "US", "Alpha"),
+        create_test_proxy("2.2.2.2", 80, "US", "Alpha"),
+    ];
+    let beta = vec![
+        create_test_proxy("1.1.1.1", 80, "US", "Beta"),
+        create_test_proxy("3.3.3.3", 80, "US", "Beta"),
+    ];
+    manager.register_provider(Arc::new(StaticProvider { name: "Alpha".to_string(), proxies: alpha })).expect("Register");
+    manager.register_provider(Arc::new(StaticProvider { name: "Beta".to_string(), proxies: beta })).expect("Register");
+
+    let mut pool = manager.fetch_all().await;
+    assert_eq!(pool.len(), 3);
+
+    for proxy in pool.iter_mut() {
+        proxy.is_working = proxy.ip != "3.3.3.3";
+    }
+    manager.record_validation(&pool);
+
+    let stats = manager.provider_stats();
+    let alpha = stats.iter().find(|s| s.name == "Alpha").expect("Alpha stats");
+    let beta = stats.iter().find(|s| s.name == "Beta").expect("Beta stats");
+
+    assert_eq!(alpha.last_fetched, 2);
+    assert_eq!(alpha.last_duplicates, 0);
+    assert_eq!(beta.last_duplicates, 1);
+    assert!((beta.duplicate_ratio - 0.5).abs() < 1e-9);
+    assert_eq!(alpha.working_ratio, 1.0);
+    assert!((beta.working_ratio - 0.5).abs() < 1e-9);
+    assert!(alpha.freshness_secs.is_some_and(|s| s >= 0));
+    assert!(alpha.enabled);
+
+    // Built-ins were switched off and never fetched
+    let builtin = stats.iter().find(|s| s.name == "GeoNode").expect("Built-in stats");
+    assert!(!builtin.enabled);
+    assert!(builtin.last_fetch_at.is_none());
+}
+
+#[test]
+fn test_consecutive_failures_disable_provider() {
+    let mut manager = FreeIpProviderManager::new().expect("Provider manager").with_health_policy(ProviderHealthPolicy {
+        max_consecutive_failures: 2,
+        ..Default::default()
+    });
+
+    manager.merge_results(vec![("GeoNode".to_string(), Err(anyhow!("timeout")))]);
+    assert!(manager.is_provider_enabled("GeoNode"));
+    manager.merge_results(vec![("GeoNode".to_string(), Err(anyhow!("timeout")))]);
+    assert!(!manager.is_provider_enabled("GeoNode"));
+
+    let stats = manager.provider_stats();
+    let geonode = stats.iter().find(|s| s.name == "GeoNode").expect("Stats");
+    assert_eq!(geonode.consecutive_failures, 2);
+    assert_eq!(geonode.last_error.as_deref(), Some("timeout"));
+    assert!(geonode.disabled_reason.as_deref().is_some_and(|r| r.contains("consecutive")));
+
+    // Re-enabling starts from a clean slate
+    manager.set_provider_enabled("GeoNode", true).expect("Known provider");
+    assert!(manager.is_provider_enabled("GeoNode"));
+    assert_eq!(manager.provider_stats().iter().find(|s| s.name == "GeoNode").expect("Stats").consecutive_failures, 0);
+    assert!(manager.set_provider_enabled("Nope", false).is_err());
+}
+
+#[tokio::test]
+async fn test_weak_providers_are_disabled_automatically() {
+    let mut manager = manager_without_builtins().with_health_policy(ProviderHealthPolicy {
+        min_samples: 2,
+        min_working_ratio: 0.5,
+        max_duplicate_ratio: 0.9,
+        ..Default::default()
+    });
+    let good = vec![
+        create_test_proxy("1.1.1.1", 80, "US", "Good"),
+        create_test_proxy("2.2.2.2", 80, "US", "Good"),
+    ];
+    let dead = vec![
+        create_test_proxy("3.3.3.3", 80, "US", "Dead"),
+        create_test_proxy("4.4.4.4", 80, "US", "Dead"),
+    ];
+    manager.register_provider(Arc::new(StaticProvider { name: "Good".to_string(), proxies: good.clone() })).expect("Register");
+    manager.register_provider(Arc::new(StaticProvider { name: "Dead".to_string(), proxies: dead })).expect("Register");
+    manager.register_provider(Arc::new(StaticProvider { name: "Mirror".to_string(), proxies: good })).expect("Register");
+
+    let mut pool = manager.fetch_all().await;
+    // A mirror of an earlier provider adds nothing and is switched off
+    assert!(!manager.is_provider_enabled("Mirror"));
+    assert!(manager.is_provider_enabled("Good"));
+
+    for proxy in pool.iter_mut() {
+        proxy.is_working = proxy.provider == "Good";
+    }
+    manager.record_validation(&pool);
+    assert!(!manager.is_provider_enabled("Dead"));
+    assert!(manager.is_provider_enabled("Good"));
+
+    // Disabled providers are skipped on the next round
+    let pool = manager.fetch_all().await;
+    assert!(pool.iter().all(|p| p.provider == "Good"));
+}
+
+// ============================================================================
+// Shared Manager Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_shared_fetch_records_validation() {
+    let mut manager = manager_without_builtins().with_health_policy(ProviderHealthPolicy {
+        min_samples: 2,
+        ..Default::default()
+    });
+    // Nothing listens on these ports, so both fail validation
+    let dead = vec![
+        create_test_proxy("127.0.0.1", 1, "US", "Dead"),
+        create_test_proxy("127.0.0.1", 2, "US", "Dead"),
+    ];
+    manager.register_provider(Arc::new(StaticProvider { name: "Dead".to_string(), proxies: dead })).expect("Register");
+    let shared = tokio::sync::RwLock::new(manager);
+
+    // The manager stays writable while the detached fetch runs
+    let fetch = shared.read().await.prepare_fetch();
+    let (fetched, _) = tokio::join!(fetch.run(), async { shared.write().await.provider_stats() });
+    let proxies = shared.write().await.complete_fetch(fetched);
+    assert_eq!(proxies.len(), 2);
+
+    let pool = FreeIpProviderManager::validate_shared(&shared, proxies).await;
+    assert!(pool.iter().all(|p| !p.is_working));
+
+    let manager = shared.read().await;
+    assert_eq!(manager.get_proxy_pool().len(), 2);
+    let stats = manager.provider_stats().into_iter().find(|s| s.name == "Dead").expect("Stats");
+    assert_eq!((stats.tested, stats.working), (2, 0));
+    assert!(!manager.is_provider_enabled("Dead"));
+}
@@ -85,6 +85,9 @@ fn create_test_proxies() -> Vec<FreeProxy> {
             last_checked: "2024-01-01T00:00:00Z".to_string(),
             provider: "TestProvider".to_string(),
             is_working: true,
+            username: None,
+            password: None,
+            ssh: None,
         },
         FreeProxy {
             ip: "192.168.1.2".to_string(),
@@ -98,6 +101,9 @@ fn create_test_proxies() -> Vec<FreeProxy> {
             last_checked: "2024-01-01T00:00:00Z".to_string(),
             provider: "TestProvider".to_string(),
             is_working: false,
+            username: None,
+            password: None,
+            ssh: None,
         },
         FreeProxy {
             ip: "192.168.1.3".to_string(),
@@ -111,6 +117,9 @@ fn create_test_proxies() -> Vec<FreeProxy> {
             last_checked: "2024-01-01T00:00:00Z".to_string(),
             provider: "TestProvider".to_string(),
             is_working: true,
+            username: None,
+            password: None,
+            ssh: None,
         },
         FreeProxy {
             ip: "192.168.1.4".to_string(),
@@ -124,6 +133,9 @@ fn create_test_proxies() -> Vec<FreeProxy> {
             last_checked: "2024-01-01T00:00:00Z".to_string(),
             provider: "TestProvider".to_string(),
             is_working: true,
+            username: None,
+            password: None,
+            ssh: None,
         },
     ]
 }
@@ -287,6 +299,9 @@ fn test_free_proxy_to_proxy_settings() {
         last_checked: "2024-01-01T00:00:00Z".to_string(),
         provider: "TestProvider".to_string(),
         is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
     };
     
     let settings = proxy.to_proxy_settings();
@@ -311,6 +326,9 @@ fn test_free_proxy_socks5_to_settings() {
         last_checked: "2024-01-01T00:00:00Z".to_string(),
         provider: "TestProvider".to_string(),
         is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
     };
     
     let settings = proxy.to_proxy_settings();
@@ -335,6 +353,7 @@ fn test_proxy_settings_to_url_http() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     let url = settings.to_url();
@@ -353,6 +372,7 @@ fn test_proxy_settings_to_url_with_auth() {
         password: Some("pass".to_string()),
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     let url = settings.to_url();
@@ -371,6 +391,7 @@ fn test_proxy_settings_to_url_direct() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     
     let url = settings.to_url();
@@ -390,6 +411,7 @@ fn test_proxy_settings_is_configured() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     assert!(settings.is_configured());
     
@@ -406,6 +428,7 @@ fn test_proxy_settings_is_configured() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     assert!(!settings.is_configured());
     
@@ -418,6 +441,7 @@ fn test_proxy_settings_is_configured() {
         password: None,
         dns_servers: vec![],
         bypass_list: vec![],
+        ssh: None,
     };
     assert!(!settings.is_configured());
 }
@@ -33,6 +33,9 @@ fn create_test_proxy(ip: &str, port: u16, country: &str) -> FreeProxy {
         last_checked: Utc::now().to_rfc3339(),
         provider: "TestProvider".to_string(),
         is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
     }
 }
 
@@ -170,6 +173,7 @@ fn test_proxy_metrics_creation() {
         consecutive_failures: 0,
         total_requests: 100,
         failed_requests: 5,
+        ..Default::default()
     };
     
     assert_eq!(metrics.response_time_ms, 150.5);
@@ -188,6 +192,7 @@ fn test_proxy_metrics_with_failures() {
         consecutive_failures: 5,
         total_requests: 10,
         failed_requests: 10,
+        ..Default::default()
     };
     
     assert_eq!(metrics.consecutive_failures, 5);
@@ -204,6 +209,7 @@ fn test_proxy_metrics_serialization() {
         consecutive_failures: 1,
         total_requests: 50,
         failed_requests: 5,
+        ..Default::default()
     };
     
     // Test serialization
@@ -386,6 +392,7 @@ fn test_proxy_metrics_clone() {
         consecutive_failures: 0,
         total_requests: 100,
         failed_requests: 5,
+        ..Default::default()
     };
     
     let cloned = metrics.clone();
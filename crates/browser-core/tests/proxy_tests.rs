@@ -121,3 +121,76 @@ fn test_fetch_proxies() {
     // Test the fetch_proxies function
     assert!(true, "fetch_proxies test placeholder");
 }
+
+#[test]
+fn test_requires_local_relay() {
+    let socks = |username: Option<&str>| ProxySettings {
+        proxy_type: ProxyType::Socks5,
+        host: Some("127.0.0.1".to_string()),
+        port: Some(1080),
+        username: username.map(str::to_string),
+        password: Some("secret".to_string()),
+        ..Default::default()
+    };
+
+    // Chromium can't send SOCKS5 credentials, but reqwest can
+    assert!(socks(Some("user")).requires_local_relay());
+    assert!(!socks(Some("user")).is_tunnel_protocol());
+    assert!(!socks(None).requires_local_relay());
+    assert!(!socks(Some("")).requires_local_relay());
+
+    let ssh = ProxySettings { proxy_type: ProxyType::Ssh, ..socks(None) };
+    assert!(ssh.requires_local_relay());
+    assert!(ssh.is_tunnel_protocol());
+}
+
+#[test]
+fn test_proxytype_names_round_trip() {
+    let all = [
+        ProxyType::Direct,
+        ProxyType::Http,
+        ProxyType::Https,
+        ProxyType::Socks4,
+        ProxyType::Socks5,
+        ProxyType::Ssh,
+        ProxyType::Shadowsocks,
+    ];
+    for proxy_type in all {
+        assert_eq!(ProxyType::from_name(proxy_type.name()), Some(proxy_type));
+    }
+
+    // A pool entry the UI sends back keeps its protocol
+    assert_eq!(ProxyType::from_name("ssh"), Some(ProxyType::Ssh));
+    assert_eq!(ProxyType::from_name("ss"), Some(ProxyType::Shadowsocks));
+    assert_eq!(ProxyType::from_name("gopher"), None);
+}
+
+#[tokio::test]
+async fn test_ssh_options_carried_through_pool_entries() {
+    let settings = ProxySettings {
+        proxy_type: ProxyType::Ssh,
+        host: Some("203.0.113.22".to_string()),
+        port: Some(22),
+        username: Some("tunnel".to_string()),
+        ssh: Some(SshOptions {
+            private_key_path: Some("/keys/id_ed25519".into()),
+            private_key_passphrase: Some("secret".to_string()),
+            known_hosts_path: Some("/keys/known_hosts".into()),
+        }),
+        ..Default::default()
+    };
+    let proxy = settings.to_free_proxy("manual").unwrap();
+    assert_eq!(proxy.to_proxy_settings().ssh, settings.ssh);
+
+    // Redacted copies keep the key paths but not the passphrase, which the
+    // pool entry restores when the UI sends the proxy back
+    let picked = proxy.redacted();
+    let options = picked.ssh.clone().unwrap();
+    assert_eq!(options.private_key_path, settings.ssh.as_ref().unwrap().private_key_path);
+    assert!(options.private_key_passphrase.is_none());
+
+    let manager = ProxyManager::new();
+    manager.add_free_proxies(vec![proxy]).await;
+    let restored = manager.with_pool_credentials(picked).await;
+    assert_eq!(restored.to_proxy_settings().ssh, settings.ssh);
+}
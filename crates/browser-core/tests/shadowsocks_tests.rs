@@ -0,0 +1,325 @@
+//! Tests for the Shadowsocks upstream
+//!
+//! This module tests:
+//! - `ss://` URI parsing and formatting (SIP002 and legacy base64)
+//! - SIP008 subscription parsing
+//! - Round trips for every cipher through the in-process server
+//! - Routing LocalProxyServer CONNECT and plain HTTP requests through Shadowsocks
+//! - Importing and exporting Shadowsocks entries in proxy lists
+//! - Connecting with an entry picked from redacted UI data
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use browser_core::local_proxy::LocalProxyServer;
+use browser_core::proxy::{FreeProxy, ProxyManager, ProxyType};
+use browser_core::proxy_import::{export_proxies, ProxyImporter, ProxyListFormat};
+use browser_core::shadowsocks::{ShadowsocksCipher, ShadowsocksConfig, ShadowsocksServer};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+/// Password suited to the cipher: any string for AEAD, a base64 key for 2022
+fn password_for(cipher: ShadowsocksCipher) -> String {
+    if cipher.is_2022() {
+        STANDARD.encode(vec![7u8; cipher.key_len()])
+    } else {
+        "correct horse".to_string()
+    }
+}
+
+async fn spawn_echo_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else { break };
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 || socket.write_all(&buf[..n]).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    port
+}
+
+/// Answers one request per connection with the request head it received as the body
+async fn spawn_http_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else { break };
+            tokio::spawn(async move {
+                let mut head = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+                    match socket.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => head.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let response = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", head.len());
+                let _ = socket.write_all(response.as_bytes()).await;
+                let _ = socket.write_all(&head).await;
+            });
+        }
+    });
+    port
+}
+
+async fn start_server(cipher: ShadowsocksCipher) -> (ShadowsocksServer, ShadowsocksConfig) {
+    let password = password_for(cipher);
+    let server = ShadowsocksServer::start(cipher, &password, "127.0.0.1:0".parse().expect("Addr"))
+        .await
+        .expect("Start server");
+    let config = ShadowsocksConfig::new("127.0.0.1", server.local_addr().port(), cipher, password).expect("Config");
+    (server, config)
+}
+
+async fn echo(stream: &mut TcpStream, message: &[u8]) -> std::io::Result<Vec<u8>> {
+    stream.write_all(message).await?;
+    let mut buf = vec![0u8; message.len()];
+    tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut buf))
+        .await
+        .expect("Echo timed out")?;
+    Ok(buf)
+}
+
+// ============================================================================
+// URI and Subscription Tests
+// ============================================================================
+
+#[test]
+fn test_parse_sip002_and_legacy_uris() {
+    // base64url("aes-256-gcm:pa ss") without padding
+    let sip002 = ShadowsocksConfig::parse_uri("ss://YWVzLTI1Ni1nY206cGEgc3M@198.51.100.4:8388#Frankfurt%201").expect("SIP002");
+    assert_eq!(sip002.cipher, ShadowsocksCipher::Aes256Gcm);
+    assert_eq!(sip002.password, "pa ss");
+    assert_eq!(sip002.server, "198.51.100.4");
+    assert_eq!(sip002.port, 8388);
+    assert_eq!(sip002.name.as_deref(), Some("Frankfurt 1"));
+
+    let legacy = format!(
+        "ss://{}",
+        STANDARD.encode("chacha20-ietf-poly1305:secret@[2001:db8::1]:443")
+    );
+    let legacy = ShadowsocksConfig::parse_uri(&legacy).expect("Legacy");
+    assert_eq!(legacy.cipher, ShadowsocksCipher::Chacha20IetfPoly1305);
+    assert_eq!(legacy.server, "2001:db8::1");
+    assert_eq!(legacy.port, 443);
+
+    let key = STANDARD.encode([1u8; 16]);
+    let plain = format!("ss://2022-blake3-aes-128-gcm:{}@example.com:8443", key.replace('=', "%3D"));
+    let plain = ShadowsocksConfig::parse_uri(&plain).expect("2022 URI");
+    assert_eq!(plain.cipher, ShadowsocksCipher::Blake3Aes128Gcm);
+    assert_eq!(plain.password, key);
+
+    assert!(ShadowsocksConfig::parse_uri("ss://YWVzLTI1Ni1nY206cGE@1.2.3.4:8388/?plugin=obfs-local").is_err());
+    assert!(ShadowsocksConfig::parse_uri("ss://cmM0LW1kNTpwYXNz@1.2.3.4:8388").is_err());
+    assert!(ShadowsocksConfig::parse_uri("http://1.2.3.4:8080").is_err());
+}
+
+#[test]
+fn test_uri_round_trip() {
+    for cipher in ShadowsocksCipher::ALL {
+        let config = ShadowsocksConfig::new("2001:db8::2", 8388, cipher, password_for(cipher))
+            .expect("Config")
+            .with_name("Node #1");
+        let parsed = ShadowsocksConfig::parse_uri(&config.to_uri()).expect("Round trip");
+        assert_eq!(parsed, config, "{}", cipher.name());
+    }
+}
+
+#[test]
+fn test_2022_key_length_is_validated() {
+    let short = STANDARD.encode([0u8; 16]);
+    assert!(ShadowsocksConfig::new("1.2.3.4", 8388, ShadowsocksCipher::Blake3Aes256Gcm, short.clone()).is_err());
+    assert!(ShadowsocksConfig::new("1.2.3.4", 8388, ShadowsocksCipher::Blake3Aes128Gcm, short).is_ok());
+    assert!(ShadowsocksConfig::new("1.2.3.4", 8388, ShadowsocksCipher::Blake3Aes128Gcm, "not base64!").is_err());
+}
+
+#[test]
+fn test_parse_sip008() {
+    let json = r#"{
+        "version": 1,
+        "servers": [
+            {"id": "a", "remarks": "Tokyo", "server": "203.0.113.9", "server_port": 8388,
+             "password": "pw", "method": "aes-128-gcm"},
+            {"server": "203.0.113.10", "server_port": 8389, "password": "pw",
+             "method": "aes-256-gcm", "plugin": "v2ray-plugin", "plugin_opts": "server"},
+            {"server": "203.0.113.11", "server_port": 8390, "password": "pw", "method": "rc4-md5"}
+        ],
+        "bytes_used": 1024
+    }"#;
+    let configs = ShadowsocksConfig::parse_sip008(json).expect("SIP008");
+    assert_eq!(configs.len(), 1);
+    assert_eq!(configs[0].name.as_deref(), Some("Tokyo"));
+    assert_eq!(configs[0].cipher, ShadowsocksCipher::Aes128Gcm);
+
+    let report = ProxyImporter::new().parse(json).expect("Import");
+    assert_eq!(report.format, ProxyListFormat::Sip008);
+    assert_eq!(report.imported.len(), 1);
+    assert_eq!(report.errors.len(), 2);
+}
+
+// ============================================================================
+// Relay Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_every_cipher_round_trips() {
+    let echo_port = spawn_echo_server().await;
+    for cipher in ShadowsocksCipher::ALL {
+        let (_server, config) = start_server(cipher).await;
+        let mut stream = config.connect("127.0.0.1", echo_port).await.expect("Connect");
+        assert_eq!(echo(&mut stream, b"hello").await.expect("Echo"), b"hello", "{}", cipher.name());
+
+        // Larger than one chunk in either protocol
+        let large: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        assert_eq!(echo(&mut stream, &large).await.expect("Echo"), large, "{}", cipher.name());
+    }
+}
+
+#[tokio::test]
+async fn test_wrong_password_gets_no_data() {
+    let echo_port = spawn_echo_server().await;
+    for cipher in [ShadowsocksCipher::Aes256Gcm, ShadowsocksCipher::Blake3Aes256Gcm] {
+        let (_server, config) = start_server(cipher).await;
+        let wrong = if cipher.is_2022() {
+            STANDARD.encode(vec![9u8; cipher.key_len()])
+        } else {
+            "wrong".to_string()
+        };
+        let wrong = ShadowsocksConfig::new(config.server.clone(), config.port, cipher, wrong).expect("Config");
+
+        let mut stream = wrong.connect("127.0.0.1", echo_port).await.expect("TCP connect");
+        assert!(echo(&mut stream, b"hello").await.is_err(), "{}", cipher.name());
+    }
+}
+
+#[tokio::test]
+async fn test_local_proxy_routes_through_shadowsocks() {
+    let echo_port = spawn_echo_server().await;
+    let (_server, config) = start_server(ShadowsocksCipher::Chacha20IetfPoly1305).await;
+
+    let settings = config.to_proxy_settings();
+    assert!(settings.requires_local_relay());
+    let (relay, addr) = LocalProxyServer::start_relay(settings).await.expect("Relay");
+
+    let mut client = TcpStream::connect(addr).await.expect("Connect");
+    client
+        .write_all(format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n", echo_port, echo_port).as_bytes())
+        .await
+        .expect("CONNECT");
+    let mut response = [0u8; 256];
+    let n = client.read(&mut response).await.expect("Response");
+    assert!(String::from_utf8_lossy(&response[..n]).contains("200"));
+    assert_eq!(echo(&mut client, b"via ss").await.expect("Echo"), b"via ss");
+
+    relay.stop().await.expect("Stop");
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[tokio::test]
+async fn test_local_proxy_forwards_plain_http_through_shadowsocks() {
+    let http_port = spawn_http_server().await;
+    let (_server, config) = start_server(ShadowsocksCipher::Aes256Gcm).await;
+    let (relay, addr) = LocalProxyServer::start_relay(config.to_proxy_settings()).await.expect("Relay");
+
+    let mut client = TcpStream::connect(addr).await.expect("Connect");
+    client
+        .write_all(
+            format!(
+                "GET http://127.0.0.1:{}/page?q=1 HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nProxy-Connection: keep-alive\r\n\r\n",
+                http_port, http_port
+            )
+            .as_bytes(),
+        )
+        .await
+        .expect("GET");
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .expect("Response timed out")
+        .expect("Response");
+    let response = String::from_utf8_lossy(&response);
+
+    assert!(response.starts_with("HTTP/1.1 200 OK"), "{}", response);
+    // The target saw an origin-form request without the proxy's hop-by-hop headers
+    assert!(response.contains("\r\n\r\nGET /page?q=1 HTTP/1.1\r\n"), "{}", response);
+    assert!(response.contains(&format!("Host: 127.0.0.1:{}", http_port)));
+    assert!(!response.contains("Proxy-Connection"));
+
+    relay.stop().await.expect("Stop");
+}
+
+// ============================================================================
+// Import/Export Tests
+// ============================================================================
+
+#[test]
+fn test_proxy_list_formats_carry_shadowsocks() {
+    let config = ShadowsocksConfig::new("198.51.100.7", 8388, ShadowsocksCipher::Aes128Gcm, "p,w").expect("Config");
+    let proxy = config.to_free_proxy("Import");
+    assert_eq!(proxy.protocol, ProxyType::Shadowsocks);
+    assert_eq!(proxy.username.as_deref(), Some("aes-128-gcm"));
+
+    for format in [
+        ProxyListFormat::Url,
+        ProxyListFormat::Csv,
+        ProxyListFormat::Clash,
+        ProxyListFormat::Surge,
+        ProxyListFormat::Sip008,
+    ] {
+        let exported = export_proxies(std::slice::from_ref(&proxy), format);
+        let report = ProxyImporter::new().parse_as(&exported, format).expect("Import");
+        assert!(report.errors.is_empty(), "{:?}: {:?}", format, report.errors);
+        assert_eq!(report.imported.len(), 1, "{:?}", format);
+        let imported = &report.imported[0];
+        assert_eq!(imported.protocol, ProxyType::Shadowsocks, "{:?}", format);
+        assert_eq!(imported.username, proxy.username, "{:?}", format);
+        assert_eq!(imported.password, proxy.password, "{:?}", format);
+    }
+
+    // host:port lists cannot carry the method
+    assert!(export_proxies(&[proxy], ProxyListFormat::HostPort).is_empty());
+
+    let surge = ProxyImporter::new()
+        .parse("[Proxy]\nJP = ss, 203.0.113.5, 8388, encrypt-method=chacha20-ietf-poly1305, password=abc, udp-relay=true\n")
+        .expect("Surge");
+    assert_eq!(surge.imported[0].username.as_deref(), Some("chacha20-ietf-poly1305"));
+
+    let lines = ProxyImporter::new()
+        .parse("ss://YWVzLTI1Ni1nY206cGEgc3M@198.51.100.4:8388#DE\nsocks5://10.0.0.1:1080\n")
+        .expect("URL list");
+    assert_eq!(lines.format, ProxyListFormat::Url);
+    assert_eq!(lines.imported[0].protocol, ProxyType::Shadowsocks);
+    assert_eq!(lines.imported[1].protocol, ProxyType::Socks5);
+}
+
+#[tokio::test]
+async fn test_entry_picked_in_ui_keeps_method_and_key() {
+    let echo_port = spawn_echo_server().await;
+    let (_server, config) = start_server(ShadowsocksCipher::Aes128Gcm).await;
+    let manager = ProxyManager::new();
+    manager.add_free_proxies(vec![config.to_free_proxy("Import")]).await;
+
+    // The UI only ever sees the redacted entry and sends it back as picked
+    let sent = serde_json::to_string(&manager.get_free_proxies().await[0].redacted()).expect("Serialize");
+    assert!(!sent.contains(&config.password));
+    let picked: FreeProxy = serde_json::from_str(&sent).expect("Deserialize");
+    let proxy = manager.with_pool_credentials(picked).await;
+
+    let restored = ShadowsocksConfig::from_settings(&proxy.to_proxy_settings()).expect("Config");
+    assert_eq!(restored.cipher, ShadowsocksCipher::Aes128Gcm);
+    let mut stream = restored.connect("127.0.0.1", echo_port).await.expect("Connect");
+    assert_eq!(echo(&mut stream, b"picked").await.expect("Echo"), b"picked");
+}
@@ -0,0 +1,318 @@
+//! Tests for SSH upstream tunnels
+//!
+//! This module tests:
+//! - Password and private key authentication against an in-process SSH server
+//! - known_hosts verification
+//! - Multiplexing several tunnels over one session
+//! - Reconnecting after the server drops the session, but not when it refuses a channel
+//! - Routing LocalProxyServer CONNECT requests through an SSH upstream
+
+use browser_core::local_proxy::LocalProxyServer;
+use browser_core::proxy::{ProxySettings, ProxyType};
+use browser_core::ssh_tunnel::{SshOptions, SshTunnelPool};
+use rand::rngs::OsRng;
+use russh::keys::ssh_key::LineEnding;
+use russh::keys::{Algorithm, PrivateKey, PublicKey};
+use russh::server::{self, Auth, Msg, Session};
+use russh::Channel;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+const USER: &str = "tunnel";
+const PASSWORD: &str = "hunter2";
+/// Tunnel target the test server refuses to open channels to
+const BLOCKED_HOST: &str = "blocked.test";
+
+#[derive(Clone)]
+struct TestServer {
+    client_key: Option<PublicKey>,
+    sessions: Arc<AtomicUsize>,
+    handles: Arc<Mutex<Vec<server::Handle>>>,
+}
+
+impl server::Handler for TestServer {
+    type Error = russh::Error;
+
+    async fn auth_password(&mut self, user: &str, password: &str) -> Result<Auth, Self::Error> {
+        if user == USER && password == PASSWORD {
+            self.sessions.fetch_add(1, Ordering::SeqCst);
+            Ok(Auth::Accept)
+        } else {
+            Ok(Auth::reject())
+        }
+    }
+
+    async fn auth_publickey(&mut self, user: &str, key: &PublicKey) -> Result<Auth, Self::Error> {
+        if user == USER && self.client_key.as_ref() == Some(key) {
+            self.sessions.fetch_add(1, Ordering::SeqCst);
+            Ok(Auth::Accept)
+        } else {
+            Ok(Auth::reject())
+        }
+    }
+
+    async fn channel_open_direct_tcpip(
+        &mut self,
+        channel: Channel<Msg>,
+        host_to_connect: &str,
+        port_to_connect: u32,
+        _originator_address: &str,
+        _originator_port: u32,
+        session: &mut Session,
+    ) -> Result<bool, Self::Error> {
+        self.handles.lock().await.push(session.handle());
+        if host_to_connect == BLOCKED_HOST {
+            return Ok(false);
+        }
+        let target = format!("{}:{}", host_to_connect, port_to_connect);
+        tokio::spawn(async move {
+            if let Ok(mut stream) = TcpStream::connect(target).await {
+                let mut channel = channel.into_stream();
+                let _ = tokio::io::copy_bidirectional(&mut stream, &mut channel).await;
+            }
+        });
+        Ok(true)
+    }
+}
+
+struct Fixture {
+    port: u16,
+    known_hosts: PathBuf,
+    server: TestServer,
+    dir: tempfile::TempDir,
+}
+
+async fn spawn_ssh_server(client_key: Option<PublicKey>) -> Fixture {
+    let host_key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).expect("Host key");
+    let config = Arc::new(server::Config {
+        keys: vec![host_key.clone()],
+        auth_rejection_time: Duration::ZERO,
+        auth_rejection_time_initial: Some(Duration::ZERO),
+        ..Default::default()
+    });
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    let server = TestServer {
+        client_key,
+        sessions: Arc::new(AtomicUsize::new(0)),
+        handles: Arc::new(Mutex::new(Vec::new())),
+    };
+
+    let handler = server.clone();
+    tokio::spawn(async move {
+        loop {
+            let Ok((socket, _)) = listener.accept().await else { break };
+            let config = config.clone();
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                if let Ok(session) = server::run_stream(config, socket, handler).await {
+                    let _ = session.await;
+                }
+            });
+        }
+    });
+
+    let dir = tempfile::tempdir().expect("Temp dir");
+    let known_hosts = dir.path().join("known_hosts");
+    let public = host_key.public_key().to_openssh().expect("Encode host key");
+    std::fs::write(&known_hosts, format!("[127.0.0.1]:{} {} test\n", port, public)).expect("Write known_hosts");
+
+    Fixture {
+        port,
+        known_hosts,
+        server,
+        dir,
+    }
+}
+
+/// Plain TCP echo server used as the tunnel target
+async fn spawn_echo_server() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else { break };
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                while let Ok(n) = socket.read(&mut buf).await {
+                    if n == 0 || socket.write_all(&buf[..n]).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    port
+}
+
+fn ssh_settings(fixture: &Fixture, password: Option<&str>, options: SshOptions) -> ProxySettings {
+    ProxySettings {
+        proxy_type: ProxyType::Ssh,
+        host: Some("127.0.0.1".to_string()),
+        port: Some(fixture.port),
+        username: Some(USER.to_string()),
+        password: password.map(str::to_string),
+        ssh: Some(SshOptions {
+            known_hosts_path: Some(fixture.known_hosts.clone()),
+            ..options
+        }),
+        ..Default::default()
+    }
+}
+
+async fn echo(stream: &mut TcpStream, message: &[u8]) -> Vec<u8> {
+    stream.write_all(message).await.expect("Write");
+    let mut buf = vec![0u8; message.len()];
+    tokio::time::timeout(Duration::from_secs(5), stream.read_exact(&mut buf))
+        .await
+        .expect("Echo timed out")
+        .expect("Read");
+    buf
+}
+
+// ============================================================================
+// Authentication Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_password_tunnel_multiplexes_one_session() {
+    let fixture = spawn_ssh_server(None).await;
+    let echo_port = spawn_echo_server().await;
+    let pool = SshTunnelPool::new();
+    let settings = ssh_settings(&fixture, Some(PASSWORD), SshOptions::default());
+
+    let mut first = pool.open_stream(&settings, "127.0.0.1", echo_port).await.expect("First tunnel");
+    let mut second = pool.open_stream(&settings, "127.0.0.1", echo_port).await.expect("Second tunnel");
+
+    assert_eq!(echo(&mut first, b"tab one").await, b"tab one");
+    assert_eq!(echo(&mut second, b"tab two").await, b"tab two");
+    assert_eq!(fixture.server.sessions.load(Ordering::SeqCst), 1);
+    assert_eq!(pool.session_count().await, 1);
+}
+
+#[tokio::test]
+async fn test_private_key_authentication() {
+    let client_key = PrivateKey::random(&mut OsRng, Algorithm::Ed25519).expect("Client key");
+    let fixture = spawn_ssh_server(Some(client_key.public_key().clone())).await;
+    let echo_port = spawn_echo_server().await;
+
+    let key_path = fixture.dir.path().join("id_ed25519");
+    std::fs::write(&key_path, client_key.to_openssh(LineEnding::LF).expect("Encode key").as_bytes())
+        .expect("Write key");
+
+    let settings = ssh_settings(
+        &fixture,
+        None,
+        SshOptions {
+            private_key_path: Some(key_path),
+            ..Default::default()
+        },
+    );
+    let mut stream = SshTunnelPool::new()
+        .open_stream(&settings, "127.0.0.1", echo_port)
+        .await
+        .expect("Key tunnel");
+    assert_eq!(echo(&mut stream, b"keyed").await, b"keyed");
+}
+
+#[tokio::test]
+async fn test_rejects_bad_credentials_and_unknown_hosts() {
+    let fixture = spawn_ssh_server(None).await;
+    let pool = SshTunnelPool::new();
+
+    let wrong = ssh_settings(&fixture, Some("wrong"), SshOptions::default());
+    assert!(pool.open_stream(&wrong, "127.0.0.1", 80).await.is_err());
+
+    // Host key not listed in known_hosts
+    let mut unknown = ssh_settings(&fixture, Some(PASSWORD), SshOptions::default());
+    let empty = fixture.dir.path().join("empty_known_hosts");
+    std::fs::write(&empty, "").expect("Write known_hosts");
+    unknown.ssh = Some(SshOptions {
+        known_hosts_path: Some(empty),
+        ..Default::default()
+    });
+    assert!(pool.open_stream(&unknown, "127.0.0.1", 80).await.is_err());
+    assert_eq!(fixture.server.sessions.load(Ordering::SeqCst), 0);
+
+    // Neither password nor key
+    let missing = ssh_settings(&fixture, None, SshOptions::default());
+    assert!(pool.open_stream(&missing, "127.0.0.1", 80).await.is_err());
+}
+
+// ============================================================================
+// Session Lifecycle Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_reconnects_after_session_drops() {
+    let fixture = spawn_ssh_server(None).await;
+    let echo_port = spawn_echo_server().await;
+    let pool = SshTunnelPool::new();
+    let settings = ssh_settings(&fixture, Some(PASSWORD), SshOptions::default());
+
+    let mut stream = pool.open_stream(&settings, "127.0.0.1", echo_port).await.expect("Tunnel");
+    assert_eq!(echo(&mut stream, b"before").await, b"before");
+
+    for handle in fixture.server.handles.lock().await.drain(..) {
+        let _ = handle
+            .disconnect(russh::Disconnect::ByApplication, "restart".to_string(), "en".to_string())
+            .await;
+    }
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let mut stream = pool.open_stream(&settings, "127.0.0.1", echo_port).await.expect("Tunnel after reconnect");
+    assert_eq!(echo(&mut stream, b"after").await, b"after");
+    assert_eq!(fixture.server.sessions.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_refused_channel_keeps_session() {
+    let fixture = spawn_ssh_server(None).await;
+    let echo_port = spawn_echo_server().await;
+    let pool = SshTunnelPool::new();
+    let settings = ssh_settings(&fixture, Some(PASSWORD), SshOptions::default());
+
+    let mut stream = pool.open_stream(&settings, "127.0.0.1", echo_port).await.expect("Tunnel");
+    let refused = pool.open_stream(&settings, BLOCKED_HOST, 80).await.expect_err("Refused tunnel");
+    assert!(refused.to_string().contains("refused tunnel"));
+
+    // The session survives a refusal, with its other tunnels
+    assert_eq!(echo(&mut stream, b"still up").await, b"still up");
+    let mut again = pool.open_stream(&settings, "127.0.0.1", echo_port).await.expect("Tunnel");
+    assert_eq!(echo(&mut again, b"same session").await, b"same session");
+    assert_eq!(fixture.server.sessions.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_local_proxy_routes_through_ssh() {
+    let fixture = spawn_ssh_server(None).await;
+    let echo_port = spawn_echo_server().await;
+
+    let probe = TcpListener::bind("127.0.0.1:0").await.expect("Probe");
+    let proxy_port = probe.local_addr().expect("Local addr").port();
+    drop(probe);
+
+    let settings = ssh_settings(&fixture, Some(PASSWORD), SshOptions::default());
+    let server = LocalProxyServer::new(proxy_port, Some(settings)).expect("Local proxy");
+    server.start().await.expect("Start");
+
+    let mut client = TcpStream::connect(("127.0.0.1", proxy_port)).await.expect("Connect");
+    client
+        .write_all(format!("CONNECT 127.0.0.1:{} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\n\r\n", echo_port, echo_port).as_bytes())
+        .await
+        .expect("CONNECT");
+    let mut response = [0u8; 256];
+    let n = client.read(&mut response).await.expect("Response");
+    assert!(String::from_utf8_lossy(&response[..n]).contains("200"));
+
+    assert_eq!(echo(&mut client, b"via ssh").await, b"via ssh");
+}
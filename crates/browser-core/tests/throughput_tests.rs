@@ -0,0 +1,300 @@
+//! Tests for proxy throughput benchmarking
+//!
+//! This module tests:
+//! - Download/upload benchmarks through a local HTTP proxy stand-in
+//! - Aggregation of samples into throughput, TTFB and jitter
+//! - Counting the first payload chunk's time as well as its bytes
+//! - Recording results into ProxyMetrics and BandwidthStats, also from the validation scheduler
+//! - Bandwidth filtering and scoring in SmartProxySelector
+
+use browser_core::proxy::{FreeProxy, ProxyType};
+use browser_core::proxy_rotation::{ProxyHealthMonitor, ProxyMetrics, SmartProxySelector};
+use browser_core::proxy_validator::{
+    ProxyQuarantineManager, ProxyValidator, ProxyValidatorConfig, ThroughputBenchmarkConfig, ThroughputResult,
+    ThroughputSample,
+};
+use browser_core::validation_scheduler::ValidationScheduler;
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+fn create_test_proxy(ip: &str, port: u16) -> FreeProxy {
+    FreeProxy {
+        ip: ip.to_string(),
+        port,
+        protocol: ProxyType::Http,
+        country: "United States".to_string(),
+        country_code: "US".to_string(),
+        anonymity: "elite".to_string(),
+        speed: 100,
+        uptime: 99.0,
+        last_checked: Utc::now().to_rfc3339(),
+        provider: "TestProvider".to_string(),
+        is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
+    }
+}
+
+fn sample(ttfb_ms: f64, mbps: f64) -> ThroughputSample {
+    ThroughputSample { ttfb_ms, bytes: 1_000_000, transfer_ms: 100.0, mbps }
+}
+
+/// Minimal HTTP proxy stand-in: answers `GET ...?bytes=N` with N bytes and
+/// acknowledges POST bodies once fully received.
+async fn spawn_payload_proxy() -> u16 {
+    spawn_delayed_payload_proxy(Duration::ZERO).await
+}
+
+/// Like `spawn_payload_proxy`, sending each body `body_delay` after its headers
+async fn spawn_delayed_payload_proxy(body_delay: Duration) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else { break };
+            tokio::spawn(async move {
+                let mut buf = Vec::new();
+                let mut chunk = [0u8; 8192];
+                let header_end = loop {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    buf.extend_from_slice(&chunk[..n]);
+                    if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+                        break pos + 4;
+                    }
+                };
+                let head = String::from_utf8_lossy(&buf[..header_end]).to_string();
+                let content_length: usize = head
+                    .lines()
+                    .find_map(|l| l.to_ascii_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0);
+                let mut received = buf.len() - header_end;
+                while received < content_length {
+                    let n = socket.read(&mut chunk).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    received += n;
+                }
+
+                let body_len: usize = head
+                    .split_whitespace()
+                    .nth(1)
+                    .and_then(|target| target.split("bytes=").nth(1))
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0);
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body_len
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+                tokio::time::sleep(body_delay).await;
+                let _ = socket.write_all(&vec![b'x'; body_len]).await;
+                let _ = socket.shutdown().await;
+            });
+        }
+    });
+
+    port
+}
+
+// ============================================================================
+// ThroughputResult Tests
+// ============================================================================
+
+#[test]
+fn test_throughput_result_aggregation() {
+    let result = ThroughputResult::from_samples(
+        vec![sample(100.0, 8.0), sample(140.0, 10.0), sample(120.0, 12.0)],
+        vec![sample(0.0, 4.0), sample(0.0, 6.0)],
+    );
+
+    assert!((result.download_mbps - 10.0).abs() < 1e-9);
+    assert!((result.ttfb_ms - 120.0).abs() < 1e-9);
+    // |140-100| and |120-140| averaged
+    assert!((result.jitter_ms - 30.0).abs() < 1e-9);
+    assert_eq!(result.upload_mbps, Some(5.0));
+    assert!((result.download_bytes_per_sec() - 1_250_000.0).abs() < 1e-6);
+}
+
+#[test]
+fn test_throughput_result_single_sample_has_no_jitter() {
+    let result = ThroughputResult::from_samples(vec![sample(80.0, 3.0)], vec![]);
+    assert_eq!(result.jitter_ms, 0.0);
+    assert!(result.upload_mbps.is_none());
+}
+
+#[test]
+fn test_benchmark_config_default() {
+    let config = ThroughputBenchmarkConfig::default();
+    assert!(config.download_url.contains("{bytes}"));
+    assert!(config.samples >= 2);
+    assert!(config.upload_url.is_none());
+}
+
+// ============================================================================
+// Benchmark Integration Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_benchmark_through_local_proxy() {
+    let port = spawn_payload_proxy().await;
+    let proxy = create_test_proxy("127.0.0.1", port);
+    let validator = ProxyValidator::new(ProxyValidatorConfig::default());
+
+    let config = ThroughputBenchmarkConfig {
+        download_url: "http://bench.test/__down?bytes={bytes}".to_string(),
+        download_bytes: 256 * 1024,
+        upload_url: Some("http://bench.test/__up".to_string()),
+        upload_bytes: 64 * 1024,
+        samples: 3,
+        sample_timeout: Duration::from_secs(10),
+    };
+
+    let result = validator.benchmark_throughput(&proxy, &config).await.expect("Benchmark should succeed");
+
+    assert_eq!(result.download_samples.len(), 3);
+    assert_eq!(result.upload_samples.len(), 3);
+    assert!(result.download_samples.iter().all(|s| s.bytes == 256 * 1024));
+    assert!(result.download_mbps > 0.0);
+    assert!(result.upload_mbps.is_some_and(|mbps| mbps > 0.0));
+    assert!(result.jitter_ms >= 0.0);
+
+    // Record into both metric stores
+    let monitor = ProxyHealthMonitor::new();
+    monitor.record_throughput(&proxy.ip, &result).await;
+    let stats = monitor.get_bandwidth_stats(&proxy.ip).await.expect("Bandwidth stats");
+    assert_eq!(stats.bytes_received, 3 * 256 * 1024);
+    assert_eq!(stats.bytes_sent, 3 * 64 * 1024);
+    assert!(stats.sustained_download_bps.is_some_and(|bps| bps > 0.0));
+
+    let mut metrics = ProxyMetrics::default();
+    metrics.apply_throughput(&result);
+    assert_eq!(metrics.download_mbps, Some(result.download_mbps));
+    assert_eq!(metrics.jitter_ms, Some(result.jitter_ms));
+    assert!(metrics.last_benchmark.is_some());
+}
+
+#[tokio::test]
+async fn test_first_chunk_counts_toward_transfer_time() {
+    // The whole body arrives at once after a pause
+    let port = spawn_delayed_payload_proxy(Duration::from_millis(300)).await;
+    let proxy = create_test_proxy("127.0.0.1", port);
+    let validator = ProxyValidator::new(ProxyValidatorConfig::default());
+    let config = ThroughputBenchmarkConfig {
+        download_url: "http://bench.test/__down?bytes={bytes}".to_string(),
+        download_bytes: 16 * 1024,
+        samples: 1,
+        sample_timeout: Duration::from_secs(10),
+        ..Default::default()
+    };
+
+    let result = validator.benchmark_throughput(&proxy, &config).await.expect("Benchmark should succeed");
+    let sample = &result.download_samples[0];
+    assert!(sample.transfer_ms >= 250.0, "{:?}", sample);
+    // 16 KiB in at least 250ms, not in the 1ms floor
+    assert!(sample.mbps < 1.0, "{:?}", sample);
+}
+
+#[tokio::test]
+async fn test_scheduler_benchmark_records_bandwidth() {
+    let port = spawn_payload_proxy().await;
+    let proxy = create_test_proxy("127.0.0.1", port);
+    let pool = Arc::new(tokio::sync::RwLock::new(vec![proxy.clone()]));
+    let quarantine = Arc::new(ProxyQuarantineManager::new(3, Duration::from_secs(60), Duration::from_secs(3600)));
+    let scheduler = ValidationScheduler::new(
+        ProxyValidator::new(ProxyValidatorConfig::default()),
+        quarantine,
+        pool,
+        Default::default(),
+    )
+    .with_throughput_benchmark(ThroughputBenchmarkConfig {
+        download_url: "http://bench.test/__down?bytes={bytes}".to_string(),
+        download_bytes: 64 * 1024,
+        samples: 2,
+        sample_timeout: Duration::from_secs(10),
+        ..Default::default()
+    });
+
+    assert!(scheduler.benchmark("127.0.0.1", port.wrapping_add(1)).await.is_err());
+    let result = scheduler.benchmark(&proxy.ip, port).await.expect("Benchmark should succeed");
+    let stats = scheduler
+        .health_monitor()
+        .get_bandwidth_stats(&format!("127.0.0.1:{}", port))
+        .await
+        .expect("Bandwidth stats");
+    assert_eq!(stats.bytes_received, 2 * 64 * 1024);
+    assert_eq!(stats.sustained_download_bps, Some(result.download_bytes_per_sec()));
+}
+
+#[tokio::test]
+async fn test_benchmark_fails_on_empty_payload() {
+    let port = spawn_payload_proxy().await;
+    let proxy = create_test_proxy("127.0.0.1", port);
+    let validator = ProxyValidator::new(ProxyValidatorConfig::default());
+
+    let config = ThroughputBenchmarkConfig {
+        download_url: "http://bench.test/__down?bytes={bytes}".to_string(),
+        download_bytes: 0,
+        samples: 1,
+        sample_timeout: Duration::from_secs(5),
+        ..Default::default()
+    };
+
+    assert!(validator.benchmark_throughput(&proxy, &config).await.is_err());
+}
+
+// ============================================================================
+// SmartProxySelector Bandwidth Tests
+// ============================================================================
+
+#[test]
+fn test_selector_filters_and_scores_on_bandwidth() {
+    let fast = create_test_proxy("10.0.0.1", 8080);
+    let slow = create_test_proxy("10.0.0.2", 8080);
+    let unmeasured = create_test_proxy("10.0.0.3", 8080);
+
+    let mut metrics = HashMap::new();
+    metrics.insert(fast.ip.clone(), ProxyMetrics {
+        success_rate: 0.9,
+        response_time_ms: 400.0,
+        download_mbps: Some(25.0),
+        ..Default::default()
+    });
+    metrics.insert(slow.ip.clone(), ProxyMetrics {
+        success_rate: 1.0,
+        response_time_ms: 100.0,
+        download_mbps: Some(1.5),
+        ..Default::default()
+    });
+
+    let proxies = vec![slow.clone(), fast.clone(), unmeasured];
+
+    // Without a requirement the low-latency proxy wins
+    let selector = SmartProxySelector::default();
+    assert_eq!(selector.select_best(&proxies, &metrics).map(|p| p.ip), Some(slow.ip.clone()));
+
+    // Requiring 5 Mbit/s excludes the slow and unmeasured proxies
+    let selector = SmartProxySelector::default().with_bandwidth_requirement(5.0, 0.3);
+    assert_eq!(selector.select_best(&proxies, &metrics).map(|p| p.ip), Some(fast.ip.clone()));
+    let top = selector.select_top_n(&proxies, &metrics, 3);
+    assert_eq!(top.len(), 1);
+
+    // Bandwidth raises the score of the faster proxy
+    let fast_score = selector.calculate_score(&fast, metrics.get(&fast.ip));
+    let base_score = SmartProxySelector::default().calculate_score(&fast, metrics.get(&fast.ip));
+    assert!(fast_score > base_score);
+}
@@ -0,0 +1,354 @@
+//! Tests for Tor control port integration
+//!
+//! This module tests:
+//! - Password, cookie and no-auth authentication against a mock control port
+//! - Reply parsing for GETINFO data blocks and errors
+//! - NEWNYM rotation with per-tab isolation credentials
+//! - Exit country pinning and exit relay lookup, rejecting anything but country codes
+//! - Timing out control replies
+//! - Keeping runtime exit pins across control reconnects
+//! - ProxyRotationManager routing through Tor, also from the app config
+
+use browser_core::config_manager::ProxyConfig;
+use browser_core::free_ip_providers::FreeIpProviderManager;
+use browser_core::proxy::ProxyType;
+use browser_core::proxy_rotation::{ProxyRotationManager, ProxyRotationStrategy};
+use browser_core::tor::{TorAuth, TorCircuit, TorConfig, TorControl, TorProvider};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{Mutex, RwLock};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+const EXIT_FP: &str = "BBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBBB";
+
+/// Control port stand-in speaking enough of the tor control protocol for the client
+///
+/// Accepts `AUTHENTICATE` only with `expected_auth` and records every command.
+async fn spawn_control_port(expected_auth: String, cookie_file: Option<String>) -> (u16, Arc<Mutex<Vec<String>>>) {
+    spawn_flaky_control_port(expected_auth, cookie_file, false).await
+}
+
+/// Like `spawn_control_port`, optionally hanging up instead of answering the first `SIGNAL NEWNYM`
+async fn spawn_flaky_control_port(
+    expected_auth: String,
+    cookie_file: Option<String>,
+    hang_up_once: bool,
+) -> (u16, Arc<Mutex<Vec<String>>>) {
+    let hang_up = Arc::new(AtomicBool::new(hang_up_once));
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    let commands = Arc::new(Mutex::new(Vec::new()));
+    let seen = commands.clone();
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((socket, _)) = listener.accept().await else { break };
+            let seen = seen.clone();
+            let expected_auth = expected_auth.clone();
+            let cookie_file = cookie_file.clone();
+            let hang_up = hang_up.clone();
+            tokio::spawn(async move {
+                let (read, mut write) = socket.into_split();
+                let mut lines = BufReader::new(read).lines();
+                let mut authenticated = false;
+                while let Ok(Some(line)) = lines.next_line().await {
+                    seen.lock().await.push(line.clone());
+                    if line == "SIGNAL NEWNYM" && hang_up.swap(false, Ordering::SeqCst) {
+                        break;
+                    }
+                    let reply = if line == "PROTOCOLINFO 1" {
+                        let methods = match &cookie_file {
+                            Some(path) => format!("250-AUTH METHODS=COOKIE,SAFECOOKIE COOKIEFILE=\"{}\"\r\n", path),
+                            None => "250-AUTH METHODS=NULL\r\n".to_string(),
+                        };
+                        format!("250-PROTOCOLINFO 1\r\n{}250-VERSION Tor=\"0.4.8.10\"\r\n250 OK\r\n", methods)
+                    } else if line.starts_with("AUTHENTICATE") {
+                        if line == expected_auth {
+                            authenticated = true;
+                            "250 OK\r\n".to_string()
+                        } else {
+                            "515 Authentication failed: Password did not match\r\n".to_string()
+                        }
+                    } else if !authenticated {
+                        "514 Authentication required.\r\n".to_string()
+                    } else if line == "SIGNAL NEWNYM" || line.starts_with("SETCONF ") || line.starts_with("RESETCONF ") {
+                        "250 OK\r\n".to_string()
+                    } else if line == "GETINFO circuit-status" {
+                        format!(
+                            "250+circuit-status=\r\n\
+                             3 BUILT $AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA~Guard1,$CCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCCC~Hs PURPOSE=HS_CLIENT_INTRO\r\n\
+                             7 BUILT $AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA~Guard1,$DDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDDD~Middle,${}~ExitOne BUILD_FLAGS=NEED_CAPACITY PURPOSE=GENERAL TIME_CREATED=2024-01-01T00:00:00.000000\r\n\
+                             8 EXTENDED $AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA~Guard1 PURPOSE=GENERAL\r\n\
+                             .\r\n250 OK\r\n",
+                            EXIT_FP
+                        )
+                    } else if line == format!("GETINFO ns/id/{}", EXIT_FP) {
+                        format!(
+                            "250+ns/id/{}=\r\nr ExitOne u6u6u6u6 digest 2024-01-01 00:00:00 203.0.113.7 9001 0\r\ns Exit Fast Running Valid\r\n.\r\n250 OK\r\n",
+                            EXIT_FP
+                        )
+                    } else if line == "GETINFO ip-to-country/203.0.113.7" {
+                        "250-ip-to-country/203.0.113.7=de\r\n250 OK\r\n".to_string()
+                    } else {
+                        "510 Unrecognized command\r\n".to_string()
+                    };
+                    if write.write_all(reply.as_bytes()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+
+    (port, commands)
+}
+
+fn config(control_port: u16, auth: TorAuth) -> TorConfig {
+    TorConfig {
+        control_port,
+        socks_port: 9050,
+        auth,
+        ..Default::default()
+    }
+}
+
+fn password_auth() -> TorAuth {
+    TorAuth::Password {
+        password: "s3cret".to_string(),
+    }
+}
+
+// ============================================================================
+// Control Connection Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_password_authentication() {
+    let (port, _) = spawn_control_port("AUTHENTICATE \"s3cret\"".to_string(), None).await;
+
+    assert!(TorControl::connect(&config(port, password_auth())).await.is_ok());
+
+    let wrong = TorAuth::Password {
+        password: "nope".to_string(),
+    };
+    let error = TorControl::connect(&config(port, wrong)).await.err().expect("Rejected");
+    assert!(format!("{:#}", error).contains("515"));
+    // The password never leaks into the error
+    assert!(!format!("{:#}", error).contains("nope"));
+}
+
+#[tokio::test]
+async fn test_cookie_authentication_uses_protocolinfo_path() {
+    let dir = tempfile::tempdir().expect("Temp dir");
+    let cookie_path = dir.path().join("control_auth_cookie");
+    std::fs::write(&cookie_path, [0xde, 0xad, 0xbe, 0xef]).expect("Write cookie");
+
+    let (port, commands) = spawn_control_port(
+        "AUTHENTICATE deadbeef".to_string(),
+        Some(cookie_path.to_string_lossy().to_string()),
+    )
+    .await;
+
+    TorControl::connect(&config(port, TorAuth::Cookie { path: None })).await.expect("Cookie auth");
+    assert_eq!(commands.lock().await[0], "PROTOCOLINFO 1");
+
+    let explicit = TorAuth::Cookie { path: Some(cookie_path) };
+    assert!(TorControl::connect(&config(port, explicit)).await.is_ok());
+}
+
+#[tokio::test]
+async fn test_commands_and_replies() {
+    let (port, commands) = spawn_control_port("AUTHENTICATE".to_string(), None).await;
+    let mut control = TorControl::connect(&config(port, TorAuth::None)).await.expect("Connect");
+
+    control.new_identity().await.expect("NEWNYM");
+    control
+        .set_exit_countries(&["DE".to_string(), " nl ".to_string()], true)
+        .await
+        .expect("SETCONF");
+    control.set_exit_countries(&[], false).await.expect("RESETCONF");
+    assert!(control.command("MAPADDRESS").await.is_err());
+
+    let circuits = control.circuits().await.expect("Circuits");
+    assert_eq!(circuits.len(), 3);
+    assert_eq!(circuits[1].path.len(), 3);
+    assert_eq!(circuits[1].exit().and_then(|r| r.nickname.as_deref()), Some("ExitOne"));
+
+    let exit = control.current_exit().await.expect("Exit").expect("Built circuit");
+    assert_eq!(exit.circuit_id, "7");
+    assert_eq!(exit.fingerprint, EXIT_FP);
+    assert_eq!(exit.address.as_deref(), Some("203.0.113.7"));
+    assert_eq!(exit.country_code.as_deref(), Some("DE"));
+
+    let sent = commands.lock().await;
+    assert!(sent.contains(&"SIGNAL NEWNYM".to_string()));
+    assert!(sent.contains(&"SETCONF ExitNodes={de},{nl} StrictNodes=1".to_string()));
+    assert!(sent.contains(&"RESETCONF ExitNodes StrictNodes".to_string()));
+}
+
+#[tokio::test]
+async fn test_exit_countries_must_be_country_codes() {
+    let (port, commands) = spawn_control_port("AUTHENTICATE".to_string(), None).await;
+    let mut control = TorControl::connect(&config(port, TorAuth::None)).await.expect("Connect");
+
+    for bad in ["de}\r\nSIGNAL HALT", "deu", "d1"] {
+        assert!(control.set_exit_countries(&[bad.to_string()], false).await.is_err(), "{:?}", bad);
+    }
+    let provider = TorProvider::new(config(port, TorAuth::None)).expect("Provider");
+    assert!(provider.set_exit_countries(&["nl\nSIGNAL HALT".to_string()], true).await.is_err());
+    assert_eq!(provider.exit_pin(), (Vec::new(), false));
+
+    let sent = commands.lock().await;
+    assert!(!sent.iter().any(|c| c.starts_with("SETCONF") || c.contains("HALT")));
+}
+
+#[tokio::test]
+async fn test_silent_control_port_times_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    // Accepts and reads, but never answers
+    tokio::spawn(async move {
+        let Ok((socket, _)) = listener.accept().await else { return };
+        let mut lines = BufReader::new(socket).lines();
+        while let Ok(Some(_)) = lines.next_line().await {}
+    });
+
+    let tor_config = TorConfig { timeout_secs: 1, ..config(port, TorAuth::None) };
+    let error = tokio::time::timeout(std::time::Duration::from_secs(5), TorControl::connect(&tor_config))
+        .await
+        .expect("Connect gave up on its own")
+        .err()
+        .expect("No reply");
+    assert!(format!("{:#}", error).contains("did not answer"));
+}
+
+#[test]
+fn test_circuit_parsing() {
+    let circuit = TorCircuit::parse("12 BUILT $ABC=relay1,$DEF~relay2,$0123 PURPOSE=GENERAL").expect("Parse");
+    assert_eq!(circuit.id, "12");
+    assert!(circuit.is_general());
+    assert_eq!(circuit.path[0].nickname.as_deref(), Some("relay1"));
+    assert_eq!(circuit.exit().map(|r| r.fingerprint.as_str()), Some("0123"));
+    assert!(circuit.exit().is_some_and(|r| r.nickname.is_none()));
+
+    let launched = TorCircuit::parse("13 LAUNCHED PURPOSE=GENERAL").expect("Parse");
+    assert!(launched.path.is_empty());
+    assert!(!launched.is_general());
+    assert!(TorCircuit::parse("").is_none());
+}
+
+// ============================================================================
+// Provider Tests
+// ============================================================================
+
+#[test]
+fn test_invalid_configs_are_rejected() {
+    assert!(TorProvider::new(TorConfig::default()).is_ok());
+    assert!(TorProvider::new(TorConfig { socks_port: 0, ..Default::default() }).is_err());
+    assert!(TorProvider::new(TorConfig {
+        exit_countries: vec!["germany".to_string()],
+        ..Default::default()
+    })
+    .is_err());
+}
+
+#[tokio::test]
+async fn test_tabs_are_isolated_and_rotate() {
+    let (port, commands) = spawn_control_port("AUTHENTICATE \"s3cret\"".to_string(), None).await;
+    let mut tor_config = config(port, password_auth());
+    tor_config.exit_countries = vec!["de".to_string()];
+    let provider = TorProvider::new(tor_config).expect("Provider");
+
+    let a = provider.assign("a").await.expect("Assign");
+    let b = provider.assign("b").await.expect("Assign");
+    assert_eq!(a.protocol, ProxyType::Socks5);
+    assert_eq!(a.port, 9050);
+    assert_eq!(a.country_code, "DE");
+    assert_ne!(a.username, b.username);
+
+    // Exit pinning is applied once per control connection
+    assert_eq!(
+        commands.lock().await.iter().filter(|c| c.starts_with("SETCONF ExitNodes={de}")).count(),
+        1
+    );
+
+    let rotated = provider.rotate("a").await.expect("Rotate");
+    assert_ne!(rotated.username, a.username);
+    assert_eq!(provider.proxy_for_tab("b").username, b.username);
+    assert!(commands.lock().await.contains(&"SIGNAL NEWNYM".to_string()));
+    assert!(provider.current_exit().await.expect("Exit").is_some());
+}
+
+#[tokio::test]
+async fn test_runtime_exit_pin_survives_reconnect() {
+    let (port, commands) = spawn_flaky_control_port("AUTHENTICATE".to_string(), None, true).await;
+    let mut tor_config = config(port, TorAuth::None);
+    tor_config.exit_countries = vec!["de".to_string()];
+    let provider = TorProvider::new(tor_config).expect("Provider");
+
+    provider.set_exit_countries(&["nl".to_string()], true).await.expect("Pin");
+    assert_eq!(provider.proxy_for_tab("a").country_code, "NL");
+
+    // The control port hangs up on NEWNYM; the reconnect re-applies the runtime pin
+    provider.new_identity().await.expect("NEWNYM after reconnect");
+    let commands = commands.lock().await;
+    let pins: Vec<&String> = commands.iter().filter(|c| c.starts_with("SETCONF ExitNodes=")).collect();
+    assert_eq!(pins.len(), 3);
+    assert!(pins[0].starts_with("SETCONF ExitNodes={de}"));
+    assert!(pins[1].starts_with("SETCONF ExitNodes={nl}"));
+    assert!(pins[2].starts_with("SETCONF ExitNodes={nl}"));
+    assert_eq!(provider.exit_pin(), (vec!["nl".to_string()], true));
+}
+
+#[tokio::test]
+async fn test_rotation_manager_routes_through_tor() {
+    let (port, commands) = spawn_control_port("AUTHENTICATE".to_string(), None).await;
+    let provider = Arc::new(TorProvider::new(config(port, TorAuth::None)).expect("Provider"));
+    let manager = ProxyRotationManager::new(
+        Arc::new(RwLock::new(FreeIpProviderManager::new().expect("Provider manager"))),
+        ProxyRotationStrategy::Manual,
+    )
+    .with_tor(provider);
+
+    let first = manager.get_proxy_for_tab("tab-1", None).await.expect("Initial proxy");
+    assert_eq!(first.provider, "Tor");
+    assert!(first.username.as_deref().is_some_and(|u| u.starts_with("tab-tab-1-")));
+
+    let rotated = manager.force_rotate("tab-1").await.expect("Rotate");
+    assert_ne!(rotated.username, first.username);
+    assert_eq!(commands.lock().await.iter().filter(|c| *c == "SIGNAL NEWNYM").count(), 1);
+}
+
+#[tokio::test]
+async fn test_rotation_manager_from_config() {
+    let (port, _) = spawn_control_port("AUTHENTICATE".to_string(), None).await;
+    let config = ProxyConfig {
+        tor: Some(config(port, TorAuth::None)),
+        ..Default::default()
+    };
+    let manager = ProxyRotationManager::from_config(
+        Arc::new(RwLock::new(FreeIpProviderManager::new().expect("Provider manager"))),
+        ProxyRotationStrategy::Manual,
+        &config,
+    );
+
+    let proxy = manager.get_proxy_for_tab("tab-1", None).await.expect("Tor proxy");
+    assert_eq!(proxy.provider, "Tor");
+    assert_eq!(proxy.protocol, ProxyType::Socks5);
+}
+
+#[tokio::test]
+async fn test_unreachable_control_port_fails() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    drop(listener);
+
+    let provider = TorProvider::new(config(port, TorAuth::None)).expect("Provider");
+    assert!(provider.assign("tab").await.is_err());
+    assert!(provider.new_identity().await.is_err());
+}
@@ -0,0 +1,387 @@
+//! Tests for the adaptive validation scheduler
+//!
+//! This module tests:
+//! - Per-proxy re-check intervals by priority and failure streak
+//! - Exponential backoff of repeated quarantine terms
+//! - Scheduling passes against a local proxy stand-in
+//! - Failing proxies reaching the dead tier across quarantine terms
+//! - Failing working proxies whose exit is outside their country
+//! - Removing and quarantining tampering proxies found by the validator's checks
+//! - The global per-minute check budget
+//! - Start/stop/status controls, including aborting in-flight checks
+
+use browser_core::proxy::{FreeProxy, ProxyType};
+use browser_core::proxy_validator::{
+    GeoVerificationConfig, GeoVerifier, ProxyQuarantineManager, ProxyValidator, ProxyValidatorConfig,
+    QuarantineReason, TamperCheckConfig,
+};
+use browser_core::validation_scheduler::{ValidationPriority, ValidationScheduler, ValidationSchedulerConfig};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::RwLock;
+use virtual_ip::{CountryDatabase, IPRange, IpRangeIndex};
+
+// ============================================================================
+// Test Fixtures
+// ============================================================================
+
+fn create_test_proxy(port: u16) -> FreeProxy {
+    FreeProxy {
+        ip: "127.0.0.1".to_string(),
+        port,
+        protocol: ProxyType::Http,
+        country: "United States".to_string(),
+        country_code: "US".to_string(),
+        anonymity: "elite".to_string(),
+        speed: 100,
+        uptime: 99.0,
+        last_checked: Utc::now().to_rfc3339(),
+        provider: "TestProvider".to_string(),
+        is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
+    }
+}
+
+/// HTTP proxy stand-in that answers every request with an IP echo payload
+async fn spawn_echo_proxy() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+
+    tokio::spawn(async move {
+        loop {
+            let Ok((mut socket, _)) = listener.accept().await else { break };
+            tokio::spawn(async move {
+                let mut buf = [0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let body = r#"{"ip":"203.0.113.7"}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    port
+}
+
+/// Proxy stand-in that accepts one connection and never answers
+///
+/// The receiver yields once the connection is accepted, the handle finishes
+/// once the client closes it.
+async fn spawn_silent_proxy() -> (u16, tokio::sync::oneshot::Receiver<()>, tokio::task::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    let port = listener.local_addr().expect("Local addr").port();
+    let (accepted_tx, accepted_rx) = tokio::sync::oneshot::channel();
+
+    let handle = tokio::spawn(async move {
+        let Ok((mut socket, _)) = listener.accept().await else { return };
+        let _ = accepted_tx.send(());
+        let mut buf = [0u8; 4096];
+        while matches!(socket.read(&mut buf).await, Ok(n) if n > 0) {}
+    });
+
+    (port, accepted_rx, handle)
+}
+
+/// A port with nothing listening on it
+async fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("Bind listener");
+    listener.local_addr().expect("Local addr").port()
+}
+
+fn local_validator() -> ProxyValidator {
+    ProxyValidator::new(ProxyValidatorConfig {
+        timeout: Duration::from_secs(5),
+        concurrent_checks: 4,
+        test_urls: vec!["http://check.test/ip".to_string()],
+        max_retries: 1,
+    })
+}
+
+fn create_scheduler(
+    proxies: Vec<FreeProxy>,
+    config: ValidationSchedulerConfig,
+) -> (Arc<ValidationScheduler>, Arc<RwLock<Vec<FreeProxy>>>) {
+    let pool = Arc::new(RwLock::new(proxies));
+    let quarantine = Arc::new(ProxyQuarantineManager::new(3, Duration::from_secs(60), Duration::from_secs(3600)));
+    let scheduler = Arc::new(ValidationScheduler::new(local_validator(), quarantine, pool.clone(), config));
+    (scheduler, pool)
+}
+
+// ============================================================================
+// Interval Tests
+// ============================================================================
+
+#[test]
+fn test_intervals_by_priority_and_failures() {
+    let config = ValidationSchedulerConfig::default();
+
+    assert_eq!(config.interval_for(ValidationPriority::Critical, 5), config.critical_interval);
+    assert_eq!(config.interval_for(ValidationPriority::Leased, 0), config.leased_interval);
+    assert_eq!(config.interval_for(ValidationPriority::Normal, 0), config.healthy_interval);
+    assert_eq!(config.interval_for(ValidationPriority::Normal, 1), config.failing_interval);
+    assert_eq!(config.interval_for(ValidationPriority::Normal, 3), config.failing_interval * 4);
+    assert_eq!(
+        config.interval_for(ValidationPriority::Normal, config.dead_after_failures),
+        config.dead_interval
+    );
+    assert!(config.critical_interval < config.leased_interval);
+    assert!(config.leased_interval < config.healthy_interval);
+}
+
+// ============================================================================
+// Quarantine Backoff Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_quarantine_terms_back_off_exponentially() {
+    let manager = ProxyQuarantineManager::new(1, Duration::from_millis(100), Duration::from_millis(350));
+    let proxy = create_test_proxy(8080);
+
+    assert_eq!(manager.backoff_duration(0), Duration::from_millis(100));
+    assert_eq!(manager.backoff_duration(1), Duration::from_millis(200));
+    assert_eq!(manager.backoff_duration(2), Duration::from_millis(350));
+
+    assert!(manager.record_failure(&proxy, "Connection refused".to_string()).await);
+    tokio::time::sleep(Duration::from_millis(150)).await;
+    assert_eq!(manager.release_expired().await.len(), 1);
+    assert_eq!(manager.served_terms(&proxy).await, 1);
+
+    // The second term is twice as long
+    assert!(manager.record_failure(&proxy, "Connection refused".to_string()).await);
+    let entry = manager.get_quarantined().await.pop().expect("Quarantined entry");
+    let term = entry.release_at - Utc::now();
+    assert!(term > chrono::Duration::milliseconds(150));
+
+    // A success resets the backoff
+    manager.record_success(&proxy).await;
+    assert_eq!(manager.served_terms(&proxy).await, 0);
+}
+
+#[tokio::test]
+async fn test_failures_below_threshold_do_not_quarantine() {
+    let manager = ProxyQuarantineManager::new(3, Duration::from_secs(60), Duration::from_secs(3600));
+    let proxy = create_test_proxy(8080);
+
+    assert!(!manager.record_failure(&proxy, "Timeout".to_string()).await);
+    assert!(!manager.record_failure(&proxy, "Timeout".to_string()).await);
+    assert!(!manager.is_quarantined(&proxy).await);
+    assert!(manager.record_failure(&proxy, "Timeout".to_string()).await);
+    assert!(manager.is_quarantined(&proxy).await);
+}
+
+// ============================================================================
+// Scheduling Tests
+// ============================================================================
+
+#[tokio::test]
+async fn test_run_once_schedules_by_result() {
+    let working = create_test_proxy(spawn_echo_proxy().await);
+    let dead = create_test_proxy(closed_port().await);
+    let config = ValidationSchedulerConfig::default();
+    let (scheduler, pool) = create_scheduler(vec![working.clone(), dead.clone()], config.clone());
+
+    assert_eq!(scheduler.run_once().await, 2);
+
+    let before = Utc::now();
+    let working_next = scheduler.next_check(&working).await.expect("Working proxy tracked");
+    let dead_next = scheduler.next_check(&dead).await.expect("Dead proxy tracked");
+    assert!(working_next - before > chrono::Duration::seconds(config.healthy_interval.as_secs() as i64 - 5));
+    assert!(dead_next < working_next);
+
+    let pool = pool.read().await;
+    assert!(pool.iter().any(|p| p.port == working.port && p.is_working));
+    assert!(pool.iter().any(|p| p.port == dead.port && !p.is_working));
+    drop(pool);
+
+    // Nothing is due again yet
+    assert_eq!(scheduler.run_once().await, 0);
+
+    let status = scheduler.status().await;
+    assert_eq!(status.tracked_proxies, 2);
+    assert_eq!(status.total_checks, 2);
+    assert_eq!(status.total_passed, 1);
+    assert_eq!(status.total_failed, 1);
+    assert_eq!(status.failing_proxies, 1);
+    assert_eq!(status.due_proxies, 0);
+}
+
+#[tokio::test]
+async fn test_failure_streak_reaches_dead_tier() {
+    let dead = create_test_proxy(closed_port().await);
+    let config = ValidationSchedulerConfig {
+        failing_interval: Duration::ZERO,
+        ..Default::default()
+    };
+    let pool = Arc::new(RwLock::new(vec![dead.clone()]));
+    // Every failure quarantines the proxy for a zero-length term, so each pass re-checks it
+    let quarantine = Arc::new(ProxyQuarantineManager::new(1, Duration::ZERO, Duration::from_secs(3600)));
+    let scheduler = ValidationScheduler::new(local_validator(), quarantine.clone(), pool.clone(), config.clone());
+
+    for _ in 0..config.dead_after_failures {
+        assert_eq!(scheduler.run_once().await, 1);
+    }
+    // The failure streak survives the terms served in between
+    assert_eq!(quarantine.served_terms(&dead).await, config.dead_after_failures - 1);
+
+    let status = scheduler.status().await;
+    assert_eq!(status.dead_proxies, 1);
+    assert_eq!(status.failing_proxies, 0);
+    assert_eq!(status.total_failed, config.dead_after_failures as u64);
+    let next = scheduler.next_check(&dead).await.expect("Dead proxy tracked");
+    assert!(next - Utc::now() > chrono::Duration::seconds(config.dead_interval.as_secs() as i64 - 5));
+    assert!(pool.read().await.iter().any(|p| p.port == dead.port && !p.is_working));
+}
+
+#[tokio::test]
+async fn test_geo_verifier_fails_misplaced_exits() {
+    // The echo proxy always reports 203.0.113.7 as its exit
+    let index = IpRangeIndex::new(
+        &[IPRange {
+            start: "203.0.113.0".parse().unwrap(),
+            end: "203.0.113.255".parse().unwrap(),
+            country_code: "DE".to_string(),
+            isp: "Example GmbH".to_string(),
+            asn: None,
+        }],
+        &CountryDatabase::load_all_countries(),
+    );
+    let verifier = || {
+        let config = GeoVerificationConfig { geoip_api_urls: Vec::new(), ..Default::default() };
+        GeoVerifier::new(config).expect("Verifier")
+    };
+
+    let misplaced = create_test_proxy(spawn_echo_proxy().await);
+    let mut placed = create_test_proxy(spawn_echo_proxy().await);
+    placed.country = "Germany".to_string();
+    placed.country_code = "DE".to_string();
+    let pool = Arc::new(RwLock::new(vec![misplaced.clone(), placed.clone()]));
+    let quarantine = Arc::new(ProxyQuarantineManager::new(3, Duration::from_secs(60), Duration::from_secs(3600)));
+    let scheduler = ValidationScheduler::new(local_validator(), quarantine, pool.clone(), Default::default())
+        .with_geo_verifier(verifier().with_range_index(Arc::new(index)));
+
+    assert_eq!(scheduler.run_once().await, 2);
+    let status = scheduler.status().await;
+    assert_eq!((status.total_passed, status.total_failed), (1, 1));
+    let pool_now = pool.read().await.clone();
+    assert!(pool_now.iter().any(|p| p.port == misplaced.port && !p.is_working));
+    assert!(pool_now.iter().any(|p| p.port == placed.port && p.is_working));
+
+    // An exit that cannot be located is not held against the proxy
+    let pool = Arc::new(RwLock::new(vec![misplaced.clone()]));
+    let quarantine = Arc::new(ProxyQuarantineManager::new(3, Duration::from_secs(60), Duration::from_secs(3600)));
+    let scheduler = ValidationScheduler::new(local_validator(), quarantine, pool.clone(), Default::default())
+        .with_geo_verifier(verifier());
+    scheduler.run_once().await;
+    assert!(pool.read().await[0].is_working);
+}
+
+#[tokio::test]
+async fn test_tampering_proxy_is_quarantined_through_scheduler() {
+    // The echo proxy answers the captive-portal probe with its IP payload, i.e. modified content
+    let tampering = create_test_proxy(spawn_echo_proxy().await);
+    let pool = Arc::new(RwLock::new(vec![tampering.clone()]));
+    let quarantine = Arc::new(ProxyQuarantineManager::new(3, Duration::from_secs(60), Duration::from_secs(3600)));
+    let validator = local_validator().with_tamper_checks(TamperCheckConfig {
+        http_url: Some("http://probe.test/success.txt".to_string()),
+        timeout: Duration::from_secs(5),
+        ..Default::default()
+    });
+    let scheduler = ValidationScheduler::new(validator, quarantine.clone(), pool.clone(), Default::default());
+
+    assert_eq!(scheduler.run_once().await, 1);
+    assert!(pool.read().await.is_empty());
+    assert!(scheduler.next_check(&tampering).await.is_none());
+    let entry = quarantine.get_quarantined().await.pop().expect("Quarantined entry");
+    assert_eq!(entry.reason, QuarantineReason::Tampering);
+    assert_eq!(entry.failure_reasons.len(), 1, "{:?}", entry.failure_reasons);
+}
+
+#[tokio::test]
+async fn test_budget_defers_checks() {
+    let first = create_test_proxy(spawn_echo_proxy().await);
+    let second = create_test_proxy(spawn_echo_proxy().await);
+    let config = ValidationSchedulerConfig {
+        max_checks_per_minute: 1,
+        ..Default::default()
+    };
+    let (scheduler, _pool) = create_scheduler(vec![first, second], config);
+
+    assert_eq!(scheduler.run_once().await, 1);
+    assert_eq!(scheduler.run_once().await, 0);
+
+    let status = scheduler.status().await;
+    assert_eq!(status.checks_last_minute, 1);
+    assert_eq!(status.max_checks_per_minute, 1);
+    assert_eq!(status.deferred_checks, 2);
+    assert_eq!(status.due_proxies, 1);
+}
+
+#[tokio::test]
+async fn test_priority_pulls_next_check_forward() {
+    let proxy = create_test_proxy(spawn_echo_proxy().await);
+    let config = ValidationSchedulerConfig::default();
+    let (scheduler, _pool) = create_scheduler(vec![proxy.clone()], config.clone());
+
+    scheduler.run_once().await;
+    let relaxed = scheduler.next_check(&proxy).await.expect("Tracked");
+
+    scheduler.set_priority(&proxy, ValidationPriority::Critical).await;
+    assert_eq!(scheduler.priority(&proxy).await, ValidationPriority::Critical);
+    let urgent = scheduler.next_check(&proxy).await.expect("Tracked");
+    assert!(urgent < relaxed);
+    assert!(urgent - Utc::now() <= chrono::Duration::seconds(config.critical_interval.as_secs() as i64));
+
+    scheduler.set_priority(&proxy, ValidationPriority::Normal).await;
+    assert_eq!(scheduler.priority(&proxy).await, ValidationPriority::Normal);
+}
+
+#[tokio::test]
+async fn test_start_stop_controls() {
+    let (scheduler, _pool) = create_scheduler(vec![], ValidationSchedulerConfig::default());
+
+    assert!(!scheduler.status().await.running);
+    assert!(scheduler.start());
+    assert!(!scheduler.start());
+    assert!(scheduler.is_running());
+
+    let status = scheduler.status().await;
+    assert!(status.running);
+    assert!(status.started_at.is_some());
+
+    assert!(scheduler.stop());
+    assert!(!scheduler.stop());
+    assert!(!scheduler.status().await.running);
+}
+
+#[tokio::test]
+async fn test_stop_aborts_in_flight_checks() {
+    let (port, accepted, closed) = spawn_silent_proxy().await;
+    let config = ValidationSchedulerConfig {
+        tick_interval: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let (scheduler, _pool) = create_scheduler(vec![create_test_proxy(port)], config);
+
+    assert!(scheduler.start());
+    tokio::time::timeout(Duration::from_secs(5), accepted)
+        .await
+        .expect("Check reached the proxy")
+        .expect("Accepted");
+    assert!(scheduler.stop());
+
+    // The check would otherwise hold the connection until its 5s timeout
+    tokio::time::timeout(Duration::from_secs(2), closed)
+        .await
+        .expect("Stopping the scheduler closed the check's connection")
+        .expect("Proxy task");
+    assert_eq!(scheduler.status().await.total_checks, 0);
+}
@@ -10,7 +10,7 @@
 use browser_core::proxy::{FreeProxy, ProxyType, ProxySettings};
 use browser_core::proxy_validator::{
     ProxyValidator, ProxyValidatorConfig, ValidationResult,
-    ProxyQuarantineManager, QuarantinedProxy, QuarantineStats,
+    ProxyQuarantineManager, QuarantinedProxy, QuarantineStats, QuarantineReason,
     GeoVerifier, GeoVerificationConfig, GeoVerificationResult,
     ProxyHealthChecker, EnhancedProxyHealthChecker,
 };
@@ -35,6 +35,9 @@ fn create_test_proxy() -> FreeProxy {
         last_checked: Utc::now().to_rfc3339(),
         provider: "TestProvider".to_string(),
         is_working: true,
+        username: None,
+        password: None,
+        ssh: None,
     }
 }
 
@@ -52,6 +55,9 @@ fn create_test_proxies() -> Vec<FreeProxy> {
             last_checked: Utc::now().to_rfc3339(),
             provider: "TestProvider".to_string(),
             is_working: true,
+            username: None,
+            password: None,
+            ssh: None,
         },
         FreeProxy {
             ip: "192.168.1.2".to_string(),
@@ -65,6 +71,9 @@ fn create_test_proxies() -> Vec<FreeProxy> {
             last_checked: Utc::now().to_rfc3339(),
             provider: "TestProvider".to_string(),
             is_working: true,
+            username: None,
+            password: None,
+            ssh: None,
         },
         FreeProxy {
             ip: "192.168.1.3".to_string(),
@@ -78,6 +87,9 @@ fn create_test_proxies() -> Vec<FreeProxy> {
             last_checked: Utc::now().to_rfc3339(),
             provider: "TestProvider".to_string(),
             is_working: false,
+            username: None,
+            password: None,
+            ssh: None,
         },
     ]
 }
@@ -153,6 +165,7 @@ fn test_validation_result_working() {
         has_ip_leak: false,
         error: None,
         validated_at: Utc::now(),
+        tampering: None,
     };
     
     assert!(result.is_working);
@@ -175,6 +188,7 @@ fn test_validation_result_failed() {
         has_ip_leak: false,
         error: Some("Connection timeout".to_string()),
         validated_at: Utc::now(),
+        tampering: None,
     };
     
     assert!(!result.is_working);
@@ -193,6 +207,7 @@ fn test_validation_result_with_ip_leak() {
         has_ip_leak: true, // IP leak detected
         error: None,
         validated_at: Utc::now(),
+        tampering: None,
     };
     
     assert!(result.is_working);
@@ -323,7 +338,8 @@ fn test_geo_verification_config_default() {
     
     assert!(config.enabled);
     assert!(config.tolerance_km > 0.0);
-    assert!(!config.geoip_api_urls.is_empty());
+    // Remote lookups are opt-in
+    assert!(config.geoip_api_urls.is_empty());
 }
 
 #[test]
@@ -435,6 +451,7 @@ fn test_quarantined_proxy_structure() {
             "Connection refused".to_string(),
             "SSL error".to_string(),
         ],
+        reason: QuarantineReason::Failures,
     };
     
     assert_eq!(quarantined.consecutive_failures, 3);
@@ -494,6 +511,9 @@ mod integration_tests {
             last_checked: Utc::now().to_rfc3339(),
             provider: "Test".to_string(),
             is_working: false,
+            username: None,
+            password: None,
+            ssh: None,
         };
         
         let result = validator.validate_proxy(&proxy).await;
@@ -56,6 +56,7 @@ fn test_webview_tab_with_proxy() {
         password: None,
         dns_servers: vec!["8.8.8.8".to_string()],
         bypass_list: vec!["localhost".to_string()],
+        ssh: None,
     };
     
     let tab = WebviewTab {
@@ -280,6 +281,7 @@ fn test_multiple_tabs_with_different_proxies() {
                     password: None,
                     dns_servers: vec![],
                     bypass_list: vec![],
+                    ssh: None,
                 }),
                 zoom_level: 1.0,
             }
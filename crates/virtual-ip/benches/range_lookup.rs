@@ -0,0 +1,81 @@
+//! Lookup throughput of `IpRangeIndex` over 500k ranges.
+//!
+//! Run with `cargo bench -p virtual-ip --bench range_lookup`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use virtual_ip::{CountryDatabase, IPRange, IpRangeIndex, Ipv6Range};
+
+const RANGE_COUNT: u32 = 500_000;
+const COUNTRY_CODES: [&str; 8] = ["US", "GB", "DE", "FR", "JP", "BR", "IN", "AU"];
+
+/// 500k /24-sized IPv4 ranges spread over the address space with gaps between them.
+fn synthetic_v4_ranges() -> Vec<IPRange> {
+    let stride = u32::MAX / RANGE_COUNT;
+    (0..RANGE_COUNT)
+        .map(|i| {
+            let start = i * stride;
+            IPRange {
+                start: Ipv4Addr::from(start),
+                end: Ipv4Addr::from(start + 255),
+                country_code: COUNTRY_CODES[i as usize % COUNTRY_CODES.len()].into(),
+                isp: format!("ISP {}", i % 5_000),
+                asn: Some(64_512 + i % 5_000),
+            }
+        })
+        .collect()
+}
+
+fn synthetic_v6_ranges() -> Vec<Ipv6Range> {
+    (0..RANGE_COUNT as u128)
+        .map(|i| {
+            let start = (0x2000u128 << 112) | (i << 80);
+            Ipv6Range {
+                start: Ipv6Addr::from(start),
+                end: Ipv6Addr::from(start | ((1u128 << 80) - 1)),
+                country_code: COUNTRY_CODES[i as usize % COUNTRY_CODES.len()].into(),
+                isp: "Unknown ISP".into(),
+                asn: None,
+            }
+        })
+        .collect()
+}
+
+fn bench_range_lookup(c: &mut Criterion) {
+    let countries = CountryDatabase::load_all_countries();
+    let v4 = synthetic_v4_ranges();
+    let v6 = synthetic_v6_ranges();
+
+    c.bench_function("index_build_500k_v4", |b| {
+        b.iter_batched(|| v4.clone(), |ranges| IpRangeIndex::new(&ranges, &countries), BatchSize::LargeInput)
+    });
+
+    let index = IpRangeIndex::with_v6_ranges(&v4, &v6, &countries);
+    let mut rng = StdRng::seed_from_u64(7);
+    let v4_ips: Vec<IpAddr> = (0..10_000).map(|_| IpAddr::V4(Ipv4Addr::from(rng.gen::<u32>()))).collect();
+    let v6_ips: Vec<IpAddr> = (0..10_000)
+        .map(|_| IpAddr::V6(Ipv6Addr::from((0x2000u128 << 112) | (rng.gen::<u128>() >> 16))))
+        .collect();
+
+    c.bench_function("lookup_10k_v4_in_500k", |b| {
+        b.iter(|| v4_ips.iter().filter(|ip| index.lookup(black_box(**ip)).is_some()).count())
+    });
+    c.bench_function("lookup_10k_v6_in_500k", |b| {
+        b.iter(|| v6_ips.iter().filter(|ip| index.lookup(black_box(**ip)).is_some()).count())
+    });
+    c.bench_function("linear_scan_100_v4_in_500k", |b| {
+        b.iter(|| {
+            v4_ips[..100]
+                .iter()
+                .filter_map(|ip| match ip {
+                    IpAddr::V4(v4_ip) => v4.iter().find(|r| r.contains(black_box(v4_ip))),
+                    IpAddr::V6(_) => None,
+                })
+                .count()
+        })
+    });
+}
+
+criterion_group!(benches, bench_range_lookup);
+criterion_main!(benches);
@@ -1,21 +1,29 @@
+use crate::geoip::GeoIpDatabase;
 use crate::models::{Country, CountryDatabase, IPRange, VirtualIP};
 use anyhow::{anyhow, Result};
-use rand::prelude::IteratorRandom;
 use rand::seq::SliceRandom;
 use rand::{thread_rng, Rng};
-use std::net::Ipv4Addr;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
 
 #[derive(Clone)]
 /// Represents a IPGenerator.
 pub struct IPGenerator {
     countries: Vec<Country>,
     ranges: Vec<IPRange>,
+    geoip_database: Option<Arc<GeoIpDatabase>>,
 }
 
 impl IPGenerator {
     /// Creates a new new.
     pub fn new(countries: Vec<Country>, ranges: Vec<IPRange>) -> Self {
-        Self { countries, ranges }
+        Self { countries, ranges, geoip_database: None }
+    }
+
+    /// Resolve the city, region and timezone of generated IPs from a GeoIP database.
+    pub fn with_geoip_database(mut self, database: Arc<GeoIpDatabase>) -> Self {
+        self.geoip_database = Some(database);
+        self
     }
 
     /// Lists countries.
@@ -45,31 +53,60 @@ impl IPGenerator {
             .get_country(code)
             .ok_or_else(|| anyhow!("Country not found: {}", code))?;
 
-        // Try to pick a range for the country; otherwise random fallback.
+        // Pick a range for the country weighted by its size; otherwise random fallback.
         let mut rng = thread_rng();
-        let range_opt = self
+        let candidates: Vec<&IPRange> = self
             .ranges
             .iter()
             .filter(|r| r.country_code.eq_ignore_ascii_case(code))
-            .choose(&mut rng);
+            .collect();
+        let range_opt = candidates
+            .choose_weighted(&mut rng, |r| r.size())
+            .ok()
+            .copied();
 
-        let ip = if let Some(range) = range_opt {
+        let ip = IpAddr::V4(if let Some(range) = range_opt {
             random_ip_in_range(range, &mut rng)
         } else {
             Ipv4Addr::new(rng.gen(), rng.gen(), rng.gen(), rng.gen())
+        });
+
+        // Place the IP in a city: the GeoIP record when it agrees with the
+        // country, otherwise the capital in the primary timezone.
+        let record = self
+            .geoip_database
+            .as_ref()
+            .and_then(|db| db.lookup(ip))
+            .filter(|r| r.country_code.as_deref().is_none_or(|c| c.eq_ignore_ascii_case(&country.code)));
+        let capital = country.capital.as_ref().map(|c| c.name.clone());
+        let (city, region, timezone) = match record {
+            Some(record) if record.city.is_some() => {
+                let timezone = record
+                    .timezone
+                    .filter(|tz| country.timezones.is_empty() || country.has_timezone(tz))
+                    .unwrap_or_else(|| country.timezone.clone());
+                (record.city, record.region, timezone)
+            }
+            _ => (capital, None, country.timezone.clone()),
         };
 
+        let places: Vec<&str> = [region.as_deref(), city.as_deref()].into_iter().flatten().collect();
+        let language = country.pick_language_at(&places, &mut rng).to_string();
+        let accept_language = country.accept_language(&language);
+
         Ok(VirtualIP {
             ip,
             country_code: country.code.clone(),
             country: country.name.clone(),
-            city: "Unknown".into(),
-            region: "Unknown".into(),
-            timezone: country.timezone.clone(),
-            language: country.language.clone(),
+            city: city.unwrap_or_else(|| "Unknown".into()),
+            region: region.unwrap_or_else(|| "Unknown".into()),
+            timezone,
+            language,
             currency: country.currency.clone(),
             isp: range_opt.map(|r| r.isp.clone()).unwrap_or_else(|| "Unknown ISP".into()),
             proxy_url: None,
+            accept_language,
+            exit_verified: false,
         })
     }
 }
@@ -77,9 +114,7 @@ impl IPGenerator {
 fn random_ip_in_range(range: &IPRange, rng: &mut impl Rng) -> Ipv4Addr {
     let start = u32::from(range.start);
     let end = u32::from(range.end);
-    let span = end.saturating_sub(start).max(1);
-    let offset = rng.gen_range(0..=span);
-    Ipv4Addr::from(start.saturating_add(offset))
+    Ipv4Addr::from(rng.gen_range(start..=end.max(start)))
 }
 
 /// Convenience to build a demo generator with placeholder data.
@@ -91,18 +126,21 @@ pub fn demo_generator() -> IPGenerator {
             end: Ipv4Addr::new(8, 8, 8, 255),
             country_code: "US".into(),
             isp: "ExampleISP".into(),
+            asn: None,
         },
         IPRange {
             start: Ipv4Addr::new(1, 1, 1, 0),
             end: Ipv4Addr::new(1, 1, 1, 255),
             country_code: "GB".into(),
             isp: "ExampleISP-GB".into(),
+            asn: None,
         },
         IPRange {
             start: Ipv4Addr::new(9, 9, 9, 0),
             end: Ipv4Addr::new(9, 9, 9, 255),
             country_code: "DE".into(),
             isp: "ExampleISP-DE".into(),
+            asn: None,
         },
     ];
     IPGenerator::new(countries, ranges)
@@ -0,0 +1,435 @@
+use anyhow::{anyhow, Context, Result};
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tracing::{debug, info, warn};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+/// Location and network details for a single IP address.
+pub struct GeoIpRecord {
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub timezone: Option<String>,
+    pub asn: Option<u32>,
+    pub organization: Option<String>,
+}
+
+impl GeoIpRecord {
+    /// Returns `(lat, lon)` when both coordinates are known.
+    pub fn location(&self) -> Option<(f64, f64)> {
+        match (self.latitude, self.longitude) {
+            (Some(lat), Some(lon)) => Some((lat, lon)),
+            _ => None,
+        }
+    }
+
+    /// Returns true if no field is populated.
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Fill fields that are still unknown from another record.
+    fn merge(&mut self, other: GeoIpRecord) {
+        fn fill<T>(slot: &mut Option<T>, value: Option<T>) {
+            if slot.is_none() {
+                *slot = value;
+            }
+        }
+        fill(&mut self.country_code, other.country_code);
+        fill(&mut self.country, other.country);
+        fill(&mut self.region, other.region);
+        fill(&mut self.city, other.city);
+        fill(&mut self.latitude, other.latitude);
+        fill(&mut self.longitude, other.longitude);
+        fill(&mut self.timezone, other.timezone);
+        fill(&mut self.asn, other.asn);
+        fill(&mut self.organization, other.organization);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// On-disk layout of a GeoIP database.
+pub enum GeoIpFormat {
+    /// MaxMind binary database (GeoLite2/GeoIP2 City, Country or ASN)
+    Mmdb,
+    /// DB-IP country or city CSV: `start,end,country` or `start,end,continent,country,region,city,lat,lon`
+    DbIpCsv,
+    /// DB-IP ASN CSV: `start,end,asn,organization`
+    DbIpAsnCsv,
+    /// IP2Location DB1-DB11 CSV with numeric ranges
+    Ip2LocationCsv,
+}
+
+impl GeoIpFormat {
+    /// Guess the format from a file name.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".mmdb") {
+            Some(Self::Mmdb)
+        } else if name.ends_with(".csv") {
+            if name.contains("ip2location") {
+                Some(Self::Ip2LocationCsv)
+            } else if name.contains("asn") {
+                Some(Self::DbIpAsnCsv)
+            } else {
+                Some(Self::DbIpCsv)
+            }
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A database file and its format.
+pub struct GeoIpSource {
+    pub path: PathBuf,
+    pub format: GeoIpFormat,
+}
+
+impl GeoIpSource {
+    /// Creates a new source.
+    pub fn new(path: impl Into<PathBuf>, format: GeoIpFormat) -> Self {
+        Self { path: path.into(), format }
+    }
+
+    /// Creates a source, detecting the format from the file name.
+    pub fn detect(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let format = GeoIpFormat::detect(&path)
+            .ok_or_else(|| anyhow!("Unknown GeoIP database format: {}", path.display()))?;
+        Ok(Self { path, format })
+    }
+}
+
+/// Sorted, non-overlapping ranges parsed from a CSV database.
+struct CsvTable {
+    entries: Vec<(u128, u128, GeoIpRecord)>,
+}
+
+impl CsvTable {
+    fn lookup(&self, ip: IpAddr) -> Option<&GeoIpRecord> {
+        let key = ip_to_u128(ip);
+        let idx = self.entries.partition_point(|(start, _, _)| *start <= key);
+        let (_, end, record) = self.entries.get(idx.checked_sub(1)?)?;
+        (key <= *end).then_some(record)
+    }
+}
+
+enum LoadedData {
+    Mmdb(Reader<Vec<u8>>),
+    Csv(CsvTable),
+}
+
+struct LoadedSource {
+    source: GeoIpSource,
+    modified: Option<SystemTime>,
+    data: LoadedData,
+}
+
+impl LoadedSource {
+    fn load(source: &GeoIpSource) -> Result<Self> {
+        let modified = std::fs::metadata(&source.path)
+            .and_then(|m| m.modified())
+            .ok();
+        let data = match source.format {
+            GeoIpFormat::Mmdb => LoadedData::Mmdb(
+                Reader::open_readfile(&source.path)
+                    .map_err(|e| anyhow!("Failed to open {}: {}", source.path.display(), e))?,
+            ),
+            format => {
+                let text = std::fs::read_to_string(&source.path)
+                    .with_context(|| format!("Failed to read {}", source.path.display()))?;
+                LoadedData::Csv(parse_csv(&text, format)?)
+            }
+        };
+        debug!("Loaded GeoIP database {}", source.path.display());
+        Ok(Self { source: source.clone(), modified, data })
+    }
+
+    fn lookup(&self, ip: IpAddr) -> Option<GeoIpRecord> {
+        match &self.data {
+            LoadedData::Csv(table) => table.lookup(ip).cloned(),
+            LoadedData::Mmdb(reader) => mmdb_lookup(reader, ip),
+        }
+    }
+}
+
+/// Local GeoIP database built from one or more MMDB/CSV files.
+///
+/// Sources are queried in order; later sources only fill fields the
+/// earlier ones left empty, so a city database can be combined with a
+/// separate ASN database.
+pub struct GeoIpDatabase {
+    loaded: RwLock<Vec<LoadedSource>>,
+}
+
+impl std::fmt::Debug for GeoIpDatabase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GeoIpDatabase")
+            .field("sources", &self.sources())
+            .finish()
+    }
+}
+
+impl GeoIpDatabase {
+    /// Load all sources from disk.
+    pub fn open(sources: Vec<GeoIpSource>) -> Result<Self> {
+        if sources.is_empty() {
+            return Err(anyhow!("No GeoIP sources configured"));
+        }
+        let loaded = sources
+            .iter()
+            .map(LoadedSource::load)
+            .collect::<Result<Vec<_>>>()?;
+        info!("GeoIP database ready with {} source(s)", loaded.len());
+        Ok(Self { loaded: RwLock::new(loaded) })
+    }
+
+    /// Load every `.mmdb`/`.csv` file found in a directory.
+    pub fn open_dir(dir: &Path) -> Result<Self> {
+        let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)
+            .with_context(|| format!("Failed to read {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| GeoIpFormat::detect(path).is_some())
+            .collect();
+        paths.sort();
+        Self::open(paths.into_iter().map(GeoIpSource::detect).collect::<Result<_>>()?)
+    }
+
+    /// Configured sources.
+    pub fn sources(&self) -> Vec<GeoIpSource> {
+        self.loaded.read().iter().map(|l| l.source.clone()).collect()
+    }
+
+    /// Look up an address across all sources.
+    pub fn lookup(&self, ip: IpAddr) -> Option<GeoIpRecord> {
+        let loaded = self.loaded.read();
+        let mut record = GeoIpRecord::default();
+        for source in loaded.iter() {
+            if let Some(found) = source.lookup(ip) {
+                record.merge(found);
+            }
+        }
+        (!record.is_empty()).then_some(record)
+    }
+
+    /// Look up an address given as a string.
+    pub fn lookup_str(&self, ip: &str) -> Option<GeoIpRecord> {
+        self.lookup(ip.trim().parse().ok()?)
+    }
+
+    /// Reload every source from disk.
+    pub fn reload(&self) -> Result<()> {
+        let sources = self.sources();
+        let reloaded = sources
+            .iter()
+            .map(LoadedSource::load)
+            .collect::<Result<Vec<_>>>()?;
+        *self.loaded.write() = reloaded;
+        Ok(())
+    }
+
+    /// Reload sources whose modification time changed. Returns true if anything was reloaded.
+    pub fn refresh_if_changed(&self) -> Result<bool> {
+        let stale: Vec<(usize, GeoIpSource)> = self
+            .loaded
+            .read()
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| {
+                let current = std::fs::metadata(&l.source.path).and_then(|m| m.modified()).ok();
+                current.is_some() && current != l.modified
+            })
+            .map(|(i, l)| (i, l.source.clone()))
+            .collect();
+
+        if stale.is_empty() {
+            return Ok(false);
+        }
+
+        let mut fresh = Vec::with_capacity(stale.len());
+        for (index, source) in stale {
+            fresh.push((index, LoadedSource::load(&source)?));
+        }
+        let mut loaded = self.loaded.write();
+        for (index, source) in fresh {
+            info!("Reloaded GeoIP database {}", source.source.path.display());
+            loaded[index] = source;
+        }
+        Ok(true)
+    }
+
+    /// Periodically reload changed sources in the background.
+    pub fn spawn_refresh_task(self: &Arc<Self>, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let db = Arc::clone(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let db = Arc::clone(&db);
+                match tokio::task::spawn_blocking(move || db.refresh_if_changed()).await {
+                    Ok(Err(e)) => warn!("GeoIP refresh failed: {}", e),
+                    Err(e) => warn!("GeoIP refresh task failed: {}", e),
+                    Ok(Ok(_)) => {}
+                }
+            }
+        })
+    }
+}
+
+fn mmdb_lookup(reader: &Reader<Vec<u8>>, ip: IpAddr) -> Option<GeoIpRecord> {
+    fn english(names: &Option<std::collections::BTreeMap<&str, &str>>) -> Option<String> {
+        names.as_ref()?.get("en").map(|s| s.to_string())
+    }
+
+    let mut record = GeoIpRecord::default();
+    match reader.lookup::<geoip2::City>(ip) {
+        Ok(city) => {
+            if let Some(country) = city.country.as_ref().or(city.registered_country.as_ref()) {
+                record.country_code = country.iso_code.map(str::to_string);
+                record.country = english(&country.names);
+            }
+            record.city = city.city.as_ref().and_then(|c| english(&c.names));
+            record.region = city
+                .subdivisions
+                .as_ref()
+                .and_then(|s| s.first())
+                .and_then(|s| english(&s.names));
+            if let Some(location) = &city.location {
+                record.latitude = location.latitude;
+                record.longitude = location.longitude;
+                record.timezone = location.time_zone.map(str::to_string);
+            }
+        }
+        Err(MaxMindDBError::AddressNotFoundError(_)) => return None,
+        Err(e) => debug!("MMDB city lookup for {} failed: {}", ip, e),
+    }
+    if let Ok(asn) = reader.lookup::<geoip2::Asn>(ip) {
+        record.asn = asn.autonomous_system_number;
+        record.organization = asn.autonomous_system_organization.map(str::to_string);
+    }
+    (!record.is_empty()).then_some(record)
+}
+
+/// Map any address into a single u128 key space (IPv4 as IPv4-mapped IPv6).
+fn ip_to_u128(ip: IpAddr) -> u128 {
+    match ip {
+        IpAddr::V4(v4) => u128::from(v4.to_ipv6_mapped()),
+        IpAddr::V6(v6) => u128::from(v6),
+    }
+}
+
+fn parse_range_bound(value: &str, format: GeoIpFormat) -> Option<u128> {
+    if format == GeoIpFormat::Ip2LocationCsv {
+        let numeric: u128 = value.parse().ok()?;
+        return Some(match u32::try_from(numeric) {
+            Ok(v4) => ip_to_u128(IpAddr::V4(Ipv4Addr::from(v4))),
+            Err(_) => u128::from(Ipv6Addr::from(numeric)),
+        });
+    }
+    value.parse::<IpAddr>().ok().map(ip_to_u128)
+}
+
+fn parse_csv(text: &str, format: GeoIpFormat) -> Result<CsvTable> {
+    let mut entries = Vec::new();
+    let mut skipped = 0usize;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        if fields.len() < 3 {
+            skipped += 1;
+            continue;
+        }
+        let (Some(start), Some(end)) = (
+            parse_range_bound(&fields[0], format),
+            parse_range_bound(&fields[1], format),
+        ) else {
+            // Header rows and malformed lines.
+            skipped += 1;
+            continue;
+        };
+        let field = |i: usize| {
+            fields
+                .get(i)
+                .map(|s| s.trim())
+                .filter(|s| !s.is_empty() && *s != "-")
+                .map(str::to_string)
+        };
+        let coord = |i: usize| field(i).and_then(|s| s.parse::<f64>().ok());
+
+        let record = match format {
+            GeoIpFormat::DbIpCsv if fields.len() >= 8 => GeoIpRecord {
+                country_code: field(3),
+                region: field(4),
+                city: field(5),
+                latitude: coord(6),
+                longitude: coord(7),
+                ..Default::default()
+            },
+            GeoIpFormat::DbIpCsv => GeoIpRecord {
+                country_code: field(2),
+                ..Default::default()
+            },
+            GeoIpFormat::DbIpAsnCsv => GeoIpRecord {
+                asn: field(2).and_then(|s| s.trim_start_matches("AS").parse().ok()),
+                organization: field(3),
+                ..Default::default()
+            },
+            GeoIpFormat::Ip2LocationCsv => GeoIpRecord {
+                country_code: field(2),
+                country: field(3),
+                region: field(4),
+                city: field(5),
+                latitude: coord(6),
+                longitude: coord(7),
+                ..Default::default()
+            },
+            GeoIpFormat::Mmdb => return Err(anyhow!("MMDB is not a CSV format")),
+        };
+        if start <= end {
+            entries.push((start, end, record));
+        }
+    }
+
+    if skipped > 0 {
+        debug!("Skipped {} unparseable GeoIP CSV rows", skipped);
+    }
+    entries.sort_by_key(|(start, _, _)| *start);
+    Ok(CsvTable { entries })
+}
+
+/// Split a CSV line, honouring double-quoted fields.
+pub(crate) fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
@@ -0,0 +1,418 @@
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use rand::Rng;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::{lookup_host, UdpSocket};
+use trust_dns_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+use trust_dns_resolver::system_conf::read_system_conf;
+use trust_dns_resolver::TokioAsyncResolver;
+use tracing::debug;
+
+const STUN_BINDING_REQUEST: u16 = 0x0001;
+const STUN_BINDING_RESPONSE: u16 = 0x0101;
+const STUN_MAGIC_COOKIE: u32 = 0x2112_A442;
+const ATTR_MAPPED_ADDRESS: u16 = 0x0001;
+const ATTR_XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+#[derive(Debug, Clone)]
+/// Configuration for WebRTC and DNS leak checks.
+pub struct LeakCheckConfig {
+    /// STUN servers as `host:port`, queried for server-reflexive candidates
+    pub stun_servers: Vec<String>,
+    /// Time allowed for ICE gathering
+    pub ice_timeout: Duration,
+    /// Zone served by a "whoami" authoritative server that answers every
+    /// A query with the address of the resolver that asked
+    pub dns_probe_domain: Option<String>,
+    /// URL on the probe zone's server that lists, as a JSON array, the
+    /// resolvers that asked for a name (`{name}` is replaced). Needed for
+    /// probes resolved in a browser page, which cannot read DNS answers
+    pub dns_probe_report_url: Option<String>,
+    /// Resolvers to query; empty uses the system configuration
+    pub dns_nameservers: Vec<SocketAddr>,
+    /// Unique subdomains resolved per check
+    pub dns_probes: usize,
+    /// Time allowed for each probe
+    pub dns_timeout: Duration,
+    /// Resolvers expected to be seen, such as the proxy provider's
+    pub trusted_resolvers: Vec<IpAddr>,
+    /// Also report host candidates and private, loopback and link-local
+    /// addresses, which only reveal the local network
+    pub report_local_candidates: bool,
+}
+
+impl Default for LeakCheckConfig {
+    fn default() -> Self {
+        Self {
+            stun_servers: vec!["stun.l.google.com:19302".to_string()],
+            ice_timeout: Duration::from_secs(5),
+            dns_probe_domain: None,
+            dns_probe_report_url: None,
+            dns_nameservers: Vec::new(),
+            dns_probes: 3,
+            dns_timeout: Duration::from_secs(5),
+            trusted_resolvers: Vec::new(),
+            report_local_candidates: false,
+        }
+    }
+}
+
+#[async_trait]
+/// Trait defining how ICE candidates are gathered for a WebRTC leak check.
+pub trait IceCandidateSource: Send + Sync {
+    /// Gather SDP candidate lines (`candidate:... typ srflx ...`) using the
+    /// given STUN servers.
+    ///
+    /// An empty list means gathering finished without exposing any address;
+    /// sources that could not gather return an error instead.
+    async fn gather_candidates(&self, stun_servers: &[String], timeout: Duration) -> Result<Vec<String>>;
+}
+
+/// Gathers candidates from this process's own UDP path, as an unproxied
+/// WebRTC stack would. Fails when no STUN server answers.
+#[derive(Debug, Default)]
+pub struct StunCandidateSource;
+
+#[async_trait]
+impl IceCandidateSource for StunCandidateSource {
+    async fn gather_candidates(&self, stun_servers: &[String], timeout: Duration) -> Result<Vec<String>> {
+        let mut candidates = Vec::new();
+        for (i, server) in stun_servers.iter().enumerate() {
+            match tokio::time::timeout(timeout, stun_binding(server)).await {
+                Ok(Ok((local, mapped))) => {
+                    let foundation = i * 2 + 1;
+                    candidates.push(format!(
+                        "candidate:{} 1 udp 2122260223 {} {} typ host",
+                        foundation,
+                        local.ip(),
+                        local.port()
+                    ));
+                    candidates.push(format!(
+                        "candidate:{} 1 udp 1686052607 {} {} typ srflx raddr {} rport {}",
+                        foundation + 1,
+                        mapped.ip(),
+                        mapped.port(),
+                        local.ip(),
+                        local.port()
+                    ));
+                }
+                Ok(Err(e)) => debug!("STUN binding with {} failed: {}", server, e),
+                Err(_) => debug!("STUN binding with {} timed out", server),
+            }
+        }
+        if candidates.is_empty() {
+            return Err(anyhow!("No STUN server answered"));
+        }
+        Ok(candidates)
+    }
+}
+
+#[async_trait]
+/// Trait defining how DNS leak probe names are resolved.
+pub trait DnsProbeSource: Send + Sync {
+    /// Resolve each probe name and return the resolver addresses the probe
+    /// zone saw asking for them.
+    async fn resolve_probes(&self, config: &LeakCheckConfig, names: &[String]) -> Result<Vec<IpAddr>>;
+}
+
+/// Resolves probes with this process's own resolver, reading the resolver
+/// addresses from the zone's "whoami" answers.
+#[derive(Debug, Default)]
+pub struct SystemDnsProbe;
+
+#[async_trait]
+impl DnsProbeSource for SystemDnsProbe {
+    async fn resolve_probes(&self, config: &LeakCheckConfig, names: &[String]) -> Result<Vec<IpAddr>> {
+        let mut opts = ResolverOpts::default();
+        opts.timeout = config.dns_timeout;
+        opts.attempts = 1;
+        // Every probe must reach the authoritative server
+        opts.cache_size = 0;
+        opts.use_hosts_file = false;
+
+        let resolver_config = if config.dns_nameservers.is_empty() {
+            read_system_conf()?.0
+        } else {
+            let mut group = NameServerConfigGroup::new();
+            for server in &config.dns_nameservers {
+                group.merge(NameServerConfigGroup::from_ips_clear(&[server.ip()], server.port(), true));
+            }
+            ResolverConfig::from_parts(None, vec![], group)
+        };
+        let resolver = TokioAsyncResolver::tokio(resolver_config, opts);
+
+        let mut resolvers: Vec<IpAddr> = Vec::new();
+        let mut failures = 0;
+        for name in names {
+            match resolver.lookup_ip(format!("{}.", name)).await {
+                Ok(answer) => {
+                    for ip in answer.iter() {
+                        if !resolvers.contains(&ip) {
+                            resolvers.push(ip);
+                        }
+                    }
+                }
+                Err(e) => {
+                    debug!("DNS probe {} failed: {}", name, e);
+                    failures += 1;
+                }
+            }
+        }
+        if failures == names.len() {
+            return Err(anyhow!("None of {} DNS probes was answered", names.len()));
+        }
+        Ok(resolvers)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Address and type parsed from an SDP candidate line.
+pub struct IceCandidate {
+    pub address: IpAddr,
+    pub port: u16,
+    pub kind: String,
+}
+
+/// Parse an SDP candidate line. mDNS-obfuscated (`.local`) hosts yield `None`.
+pub fn parse_ice_candidate(line: &str) -> Option<IceCandidate> {
+    let line = line.trim().trim_start_matches("a=");
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if !fields.first()?.starts_with("candidate:") || fields.len() < 8 {
+        return None;
+    }
+    let kind = fields
+        .iter()
+        .position(|f| *f == "typ")
+        .and_then(|i| fields.get(i + 1))?;
+    Some(IceCandidate {
+        address: fields[4].trim_matches(|c| c == '[' || c == ']').parse().ok()?,
+        port: fields[5].parse().ok()?,
+        kind: kind.to_string(),
+    })
+}
+
+/// Candidate addresses that differ from the proxy exit, deduplicated in
+/// the order they were seen.
+///
+/// Host candidates and local addresses are skipped unless `include_local`.
+pub fn webrtc_leaks(candidates: &[String], exit_ip: IpAddr, include_local: bool) -> Vec<IpAddr> {
+    let mut leaks: Vec<IpAddr> = Vec::new();
+    for candidate in candidates.iter().filter_map(|c| parse_ice_candidate(c)) {
+        let address = candidate.address;
+        if !include_local && (candidate.kind == "host" || is_local_address(address)) {
+            continue;
+        }
+        if address != exit_ip && !address.is_unspecified() && !leaks.contains(&address) {
+            leaks.push(address);
+        }
+    }
+    leaks
+}
+
+/// Private, loopback or link-local addresses, which are not routable from the internet.
+fn is_local_address(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => v4.is_private() || v4.is_loopback() || v4.is_link_local(),
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            // Unique local fc00::/7 and link-local fe80::/10
+            v6.is_loopback() || (first & 0xfe00) == 0xfc00 || (first & 0xffc0) == 0xfe80
+        }
+    }
+}
+
+/// Send a STUN Binding request to `server` and return the local address
+/// used together with the mapped address the server observed.
+pub async fn stun_binding(server: &str) -> Result<(SocketAddr, SocketAddr)> {
+    let remote = lookup_host(server)
+        .await
+        .with_context(|| format!("Failed to resolve STUN server {}", server))?
+        .next()
+        .ok_or_else(|| anyhow!("STUN server {} has no address", server))?;
+    let bind: SocketAddr = if remote.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(remote).await?;
+
+    let transaction_id: [u8; 12] = rand::thread_rng().gen();
+    socket.send(&encode_binding_request(&transaction_id)).await?;
+
+    let mut buf = [0u8; 1024];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        // Stray datagrams for other transactions are ignored
+        if let Ok(mapped) = parse_binding_response(&buf[..len], &transaction_id) {
+            return Ok((socket.local_addr()?, mapped));
+        }
+    }
+}
+
+/// Encode a STUN Binding request without attributes.
+pub fn encode_binding_request(transaction_id: &[u8; 12]) -> Vec<u8> {
+    let mut message = Vec::with_capacity(20);
+    message.extend_from_slice(&STUN_BINDING_REQUEST.to_be_bytes());
+    message.extend_from_slice(&0u16.to_be_bytes());
+    message.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    message.extend_from_slice(transaction_id);
+    message
+}
+
+/// Parse the mapped address out of a STUN Binding success response,
+/// preferring XOR-MAPPED-ADDRESS over MAPPED-ADDRESS.
+pub fn parse_binding_response(message: &[u8], transaction_id: &[u8; 12]) -> Result<SocketAddr> {
+    if message.len() < 20 {
+        return Err(anyhow!("STUN message too short"));
+    }
+    if u16::from_be_bytes([message[0], message[1]]) != STUN_BINDING_RESPONSE {
+        return Err(anyhow!("Not a STUN Binding success response"));
+    }
+    if message[4..8] != STUN_MAGIC_COOKIE.to_be_bytes() || &message[8..20] != transaction_id {
+        return Err(anyhow!("STUN transaction mismatch"));
+    }
+    let length = u16::from_be_bytes([message[2], message[3]]) as usize;
+    let body = message
+        .get(20..20 + length)
+        .ok_or_else(|| anyhow!("STUN message truncated"))?;
+
+    let mut mapped = None;
+    let mut offset = 0;
+    while offset + 4 <= body.len() {
+        let kind = u16::from_be_bytes([body[offset], body[offset + 1]]);
+        let len = u16::from_be_bytes([body[offset + 2], body[offset + 3]]) as usize;
+        let value = body
+            .get(offset + 4..offset + 4 + len)
+            .ok_or_else(|| anyhow!("STUN attribute truncated"))?;
+        match kind {
+            ATTR_XOR_MAPPED_ADDRESS => return decode_address(value, Some(transaction_id)),
+            ATTR_MAPPED_ADDRESS => mapped = Some(decode_address(value, None)?),
+            _ => {}
+        }
+        // Attributes are padded to a multiple of four bytes
+        offset += 4 + len.div_ceil(4) * 4;
+    }
+    mapped.ok_or_else(|| anyhow!("STUN response has no mapped address"))
+}
+
+/// Encode a STUN Binding success response reporting `mapped` as the
+/// XOR-MAPPED-ADDRESS. Used by STUN stand-ins.
+pub fn encode_binding_response(transaction_id: &[u8; 12], mapped: SocketAddr) -> Vec<u8> {
+    let mask = xor_mask(transaction_id);
+    let mut value = vec![0u8];
+    let port = mapped.port() ^ (STUN_MAGIC_COOKIE >> 16) as u16;
+    match mapped.ip() {
+        IpAddr::V4(ip) => {
+            value.push(0x01);
+            value.extend_from_slice(&port.to_be_bytes());
+            value.extend(ip.octets().iter().zip(mask).map(|(b, m)| b ^ m));
+        }
+        IpAddr::V6(ip) => {
+            value.push(0x02);
+            value.extend_from_slice(&port.to_be_bytes());
+            value.extend(ip.octets().iter().zip(mask).map(|(b, m)| b ^ m));
+        }
+    }
+
+    let mut message = Vec::with_capacity(24 + value.len());
+    message.extend_from_slice(&STUN_BINDING_RESPONSE.to_be_bytes());
+    message.extend_from_slice(&((4 + value.len()) as u16).to_be_bytes());
+    message.extend_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    message.extend_from_slice(transaction_id);
+    message.extend_from_slice(&ATTR_XOR_MAPPED_ADDRESS.to_be_bytes());
+    message.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    message.extend_from_slice(&value);
+    message
+}
+
+fn xor_mask(transaction_id: &[u8; 12]) -> [u8; 16] {
+    let mut mask = [0u8; 16];
+    mask[..4].copy_from_slice(&STUN_MAGIC_COOKIE.to_be_bytes());
+    mask[4..].copy_from_slice(transaction_id);
+    mask
+}
+
+fn decode_address(value: &[u8], xor: Option<&[u8; 12]>) -> Result<SocketAddr> {
+    if value.len() < 8 {
+        return Err(anyhow!("STUN address attribute too short"));
+    }
+    let mask = xor.map(xor_mask).unwrap_or([0u8; 16]);
+    let mut port = u16::from_be_bytes([value[2], value[3]]);
+    if xor.is_some() {
+        port ^= (STUN_MAGIC_COOKIE >> 16) as u16;
+    }
+    let ip = match value[1] {
+        0x01 => {
+            let mut octets = [0u8; 4];
+            for (i, octet) in octets.iter_mut().enumerate() {
+                *octet = value[4 + i] ^ mask[i];
+            }
+            IpAddr::from(octets)
+        }
+        0x02 if value.len() >= 20 => {
+            let mut octets = [0u8; 16];
+            for (i, octet) in octets.iter_mut().enumerate() {
+                *octet = value[4 + i] ^ mask[i];
+            }
+            IpAddr::from(octets)
+        }
+        family => return Err(anyhow!("Unsupported STUN address family {}", family)),
+    };
+    Ok(SocketAddr::new(ip, port))
+}
+
+/// `count` unique random subdomains of `domain`, so no resolver can answer from cache.
+pub fn dns_probe_names(domain: &str, count: usize) -> Vec<String> {
+    let domain = domain.trim_matches('.');
+    (0..count.max(1))
+        .map(|_| {
+            let token = uuid::Uuid::new_v4().simple().to_string();
+            format!("{}.{}", &token[..16], domain)
+        })
+        .collect()
+}
+
+/// Resolve `probes` unique random subdomains of `domain` and collect the
+/// resolver addresses the authoritative server reported back.
+pub async fn probe_dns_resolvers(config: &LeakCheckConfig, domain: &str) -> Result<Vec<IpAddr>> {
+    let names = dns_probe_names(domain, config.dns_probes);
+    SystemDnsProbe.resolve_probes(config, &names).await
+}
+
+/// Ask the probe zone's server which resolvers looked up each of `names`.
+///
+/// Names the server has no record of are skipped; an error means it knew none.
+pub async fn fetch_reported_resolvers(config: &LeakCheckConfig, names: &[String]) -> Result<Vec<IpAddr>> {
+    let template = config
+        .dns_probe_report_url
+        .as_deref()
+        .ok_or_else(|| anyhow!("No DNS probe report URL configured"))?;
+    let client = reqwest::Client::builder().timeout(config.dns_timeout).build()?;
+
+    let mut resolvers: Vec<IpAddr> = Vec::new();
+    let mut reported = 0;
+    for name in names {
+        let url = template.replace("{name}", name);
+        let seen = match client.get(&url).send().await.and_then(|r| r.error_for_status()) {
+            Ok(response) => response.json::<Vec<String>>().await,
+            Err(e) => Err(e),
+        };
+        match seen {
+            Ok(seen) => {
+                reported += 1;
+                for ip in seen.iter().filter_map(|ip| ip.parse::<IpAddr>().ok()) {
+                    if !resolvers.contains(&ip) {
+                        resolvers.push(ip);
+                    }
+                }
+            }
+            Err(e) => debug!("No resolver report for {}: {}", name, e),
+        }
+    }
+    if reported == 0 {
+        return Err(anyhow!("The probe zone reported no resolvers for any probe"));
+    }
+    Ok(resolvers)
+}
@@ -2,12 +2,18 @@ pub mod models;
 pub mod generator;
 pub mod rotation;
 pub mod validator;
+pub mod geoip;
+pub mod ranges;
+pub mod lookup;
+pub mod leak;
 
 pub use models::{
+    Capital,
     Country,
     CountryDatabase,
     IPRange,
     VirtualIP,
+    WeightedLanguage,
     load_ip_ranges,
     load_ip_ranges_from_file,
     load_countries_from_file,
@@ -15,3 +21,35 @@ pub use models::{
 pub use generator::{demo_generator, IPGenerator};
 pub use rotation::{IPRotationManager, RotationStrategy};
 pub use validator::{IPValidator, ValidationReport};
+pub use geoip::{GeoIpDatabase, GeoIpFormat, GeoIpRecord, GeoIpSource};
+pub use lookup::{IpRangeIndex, Ipv6Range, RangeInfo};
+pub use leak::{
+    dns_probe_names,
+    encode_binding_request,
+    encode_binding_response,
+    fetch_reported_resolvers,
+    parse_binding_response,
+    parse_ice_candidate,
+    probe_dns_resolvers,
+    stun_binding,
+    webrtc_leaks,
+    DnsProbeSource,
+    IceCandidate,
+    IceCandidateSource,
+    LeakCheckConfig,
+    StunCandidateSource,
+    SystemDnsProbe,
+};
+pub use ranges::{
+    decode_range_cache,
+    encode_range_cache,
+    load_range_cache,
+    load_range_dir,
+    load_range_index,
+    load_range_sources,
+    merge_adjacent,
+    save_range_cache,
+    IpRangeBuilder,
+    RangeFormat,
+    RangeSource,
+};
@@ -0,0 +1,220 @@
+use crate::models::{Country, IPRange};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv6Addr};
+use std::sync::Arc;
+use tracing::debug;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Network details of the range an address falls in.
+pub struct RangeInfo {
+    pub country_code: String,
+    pub country: String,
+    pub isp: String,
+    pub asn: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+/// An IPv6 allocation, as read from RIR delegation files.
+pub struct Ipv6Range {
+    pub start: Ipv6Addr,
+    pub end: Ipv6Addr,
+    pub country_code: String,
+    pub isp: String,
+    pub asn: Option<u32>,
+}
+
+impl Ipv6Range {
+    /// Performs contains operation.
+    pub fn contains(&self, ip: &Ipv6Addr) -> bool {
+        u128::from(*ip) >= u128::from(self.start) && u128::from(*ip) <= u128::from(self.end)
+    }
+}
+
+/// Shared country and ISP details for every interval with the same owner.
+struct Owner {
+    country: usize,
+    isp: Arc<str>,
+    asn: Option<u32>,
+}
+
+/// Sorted, non-overlapping intervals searched by binary search.
+///
+/// Built once from `IPRange`s (and optionally IPv6 allocations), then
+/// answers "which country and ISP owns this IP" in `O(log n)`. Owners are
+/// interned, so half a million ranges cost roughly 12 bytes per IPv4 interval.
+pub struct IpRangeIndex {
+    v4: Vec<(u32, u32, u32)>,
+    v6: Vec<(u128, u128, u32)>,
+    owners: Vec<Owner>,
+    countries: Vec<Country>,
+}
+
+impl std::fmt::Debug for IpRangeIndex {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IpRangeIndex")
+            .field("v4_ranges", &self.v4.len())
+            .field("v6_ranges", &self.v6.len())
+            .field("owners", &self.owners.len())
+            .finish()
+    }
+}
+
+impl IpRangeIndex {
+    /// Index IPv4 ranges. Countries missing from `countries` are kept with
+    /// their code as the name; where ranges overlap the first one wins.
+    pub fn new(ranges: &[IPRange], countries: &[Country]) -> Self {
+        Self::with_v6_ranges(ranges, &[], countries)
+    }
+
+    /// Index IPv4 and IPv6 ranges.
+    pub fn with_v6_ranges(ranges: &[IPRange], v6_ranges: &[Ipv6Range], countries: &[Country]) -> Self {
+        let mut interner = Interner::new(countries);
+
+        let mut v4: Vec<(u32, u32, u32)> = ranges
+            .iter()
+            .filter(|r| r.start <= r.end)
+            .map(|r| {
+                let owner = interner.owner(&r.country_code, &r.isp, r.asn);
+                (u32::from(r.start), u32::from(r.end), owner)
+            })
+            .collect();
+        let mut v6: Vec<(u128, u128, u32)> = v6_ranges
+            .iter()
+            .filter(|r| r.start <= r.end)
+            .map(|r| {
+                let owner = interner.owner(&r.country_code, &r.isp, r.asn);
+                (u128::from(r.start), u128::from(r.end), owner)
+            })
+            .collect();
+        drop_overlaps(&mut v4);
+        drop_overlaps(&mut v6);
+
+        debug!(
+            "Indexed {} IPv4 and {} IPv6 ranges across {} owners",
+            v4.len(),
+            v6.len(),
+            interner.owners.len()
+        );
+        Self {
+            v4,
+            v6,
+            owners: interner.owners,
+            countries: interner.countries,
+        }
+    }
+
+    /// Number of indexed intervals.
+    pub fn len(&self) -> usize {
+        self.v4.len() + self.v6.len()
+    }
+
+    /// Returns true if nothing is indexed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Country and ISP for an address.
+    pub fn lookup(&self, ip: IpAddr) -> Option<(&Country, &str)> {
+        let owner = self.owner(ip)?;
+        Some((&self.countries[owner.country], &owner.isp))
+    }
+
+    /// Parse and look up an address, returning `None` for invalid input.
+    pub fn lookup_str(&self, ip: &str) -> Option<(&Country, &str)> {
+        self.lookup(ip.trim().parse().ok()?)
+    }
+
+    /// Country code, ISP and ASN for an address.
+    pub fn lookup_info(&self, ip: IpAddr) -> Option<RangeInfo> {
+        let owner = self.owner(ip)?;
+        let country = &self.countries[owner.country];
+        Some(RangeInfo {
+            country_code: country.code.clone(),
+            country: country.name.clone(),
+            isp: owner.isp.to_string(),
+            asn: owner.asn,
+        })
+    }
+
+    fn owner(&self, ip: IpAddr) -> Option<&Owner> {
+        let id = match ip {
+            IpAddr::V4(v4) => find(&self.v4, u32::from(v4)),
+            // IPv4-mapped addresses resolve against the IPv4 table
+            IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+                Some(v4) => find(&self.v4, u32::from(v4)),
+                None => find(&self.v6, u128::from(v6)),
+            },
+        }?;
+        self.owners.get(id as usize)
+    }
+}
+
+fn find<T: Ord + Copy>(intervals: &[(T, T, u32)], ip: T) -> Option<u32> {
+    let idx = intervals.partition_point(|(start, _, _)| *start <= ip);
+    let (_, end, owner) = intervals.get(idx.checked_sub(1)?)?;
+    (ip <= *end).then_some(*owner)
+}
+
+fn drop_overlaps<T: Ord + Copy>(intervals: &mut Vec<(T, T, u32)>) {
+    // A stable sort keeps input order among equal starts, so the first range wins.
+    intervals.sort_by_key(|(start, _, _)| *start);
+    let mut last_end: Option<T> = None;
+    intervals.retain(|(start, end, _)| {
+        if last_end.is_some_and(|last| *start <= last) {
+            return false;
+        }
+        last_end = Some(*end);
+        true
+    });
+}
+
+struct Interner {
+    countries: Vec<Country>,
+    country_index: HashMap<String, usize>,
+    owners: Vec<Owner>,
+    owner_index: HashMap<(usize, Arc<str>, Option<u32>), u32>,
+}
+
+impl Interner {
+    fn new(countries: &[Country]) -> Self {
+        let countries = countries.to_vec();
+        let country_index = countries
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.code.to_ascii_uppercase(), i))
+            .collect();
+        Self {
+            countries,
+            country_index,
+            owners: Vec::new(),
+            owner_index: HashMap::new(),
+        }
+    }
+
+    fn country(&mut self, code: &str) -> usize {
+        let code = code.to_ascii_uppercase();
+        if let Some(&i) = self.country_index.get(&code) {
+            return i;
+        }
+        self.countries.push(Country {
+            code: code.clone(),
+            name: code.clone(),
+            ..Default::default()
+        });
+        self.country_index.insert(code, self.countries.len() - 1);
+        self.countries.len() - 1
+    }
+
+    fn owner(&mut self, country_code: &str, isp: &str, asn: Option<u32>) -> u32 {
+        let country = self.country(country_code);
+        let isp: Arc<str> = Arc::from(isp);
+        let key = (country, isp.clone(), asn);
+        if let Some(&id) = self.owner_index.get(&key) {
+            return id;
+        }
+        let id = self.owners.len() as u32;
+        self.owners.push(Owner { country, isp, asn });
+        self.owner_index.insert(key, id);
+        id
+    }
+}
@@ -1,9 +1,17 @@
+use crate::ranges::{load_range_dir, load_range_sources, RangeFormat, RangeSource};
+use rand::seq::SliceRandom;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
-use std::net::Ipv4Addr;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::Path;
+use std::sync::OnceLock;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 /// Represents a Country.
+///
+/// `timezone` and `language` are the primary entries of `timezones` and
+/// `languages`; the lists may be empty in hand-written country files.
 pub struct Country {
     pub code: String,
     pub name: String,
@@ -12,12 +20,112 @@ pub struct Country {
     pub language: String,
     pub currency: String,
     pub is_top: bool,
+    #[serde(default)]
+    pub alpha3: String,
+    /// IANA timezones in use, primary first
+    #[serde(default)]
+    pub timezones: Vec<String>,
+    /// BCP-47 language tags weighted by share of speakers
+    #[serde(default)]
+    pub languages: Vec<WeightedLanguage>,
+    /// International dialling prefix, e.g. `+44`
+    #[serde(default)]
+    pub calling_code: String,
+    #[serde(default)]
+    pub capital: Option<Capital>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A language tag and its share of a country's speakers.
+pub struct WeightedLanguage {
+    pub tag: String,
+    pub weight: f32,
+    /// Regions or cities where this is the majority language, as GeoIP names them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub regions: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+/// A country's capital city.
+pub struct Capital {
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl Country {
+    /// Returns true if the timezone is used in this country.
+    pub fn has_timezone(&self, timezone: &str) -> bool {
+        self.timezone == timezone || self.timezones.iter().any(|tz| tz == timezone)
+    }
+
+    /// Pick a language tag weighted by share of speakers.
+    pub fn pick_language(&self, rng: &mut impl Rng) -> &str {
+        self.languages
+            .choose_weighted(rng, |l| l.weight.max(0.0))
+            .map(|l| l.tag.as_str())
+            .unwrap_or(&self.language)
+    }
+
+    /// Pick a language for a place in this country, given its region and city.
+    ///
+    /// A language whose `regions` name the place is used outright. Elsewhere
+    /// the pick is weighted among the languages not tied to a region, or among
+    /// all of them when every language is regional or the place is unknown.
+    pub fn pick_language_at(&self, places: &[&str], rng: &mut impl Rng) -> &str {
+        let names_place = |l: &&WeightedLanguage| {
+            l.regions.iter().any(|r| places.iter().any(|p| p.eq_ignore_ascii_case(r)))
+        };
+        if let Some(local) = self.languages.iter().find(names_place) {
+            return &local.tag;
+        }
+
+        let national: Vec<&WeightedLanguage> = self.languages.iter().filter(|l| l.regions.is_empty()).collect();
+        if places.is_empty() || national.len() == self.languages.len() {
+            return self.pick_language(rng);
+        }
+        national
+            .choose_weighted(rng, |l| l.weight.max(0.0))
+            .map(|l| l.tag.as_str())
+            .unwrap_or_else(|_| self.pick_language(rng))
+    }
+
+    /// Build an `Accept-Language` header led by `primary`.
+    ///
+    /// The country's other languages follow by weight, each tag trailed by its
+    /// base language, with English appended as browsers commonly configure it.
+    pub fn accept_language(&self, primary: &str) -> String {
+        let mut others: Vec<&WeightedLanguage> = self.languages.iter().collect();
+        others.sort_by(|a, b| b.weight.total_cmp(&a.weight));
+
+        let mut tags: Vec<&str> = Vec::new();
+        for tag in std::iter::once(primary).chain(others.iter().map(|l| l.tag.as_str())) {
+            let base = tag.split('-').next().unwrap_or(tag);
+            for entry in [tag, base] {
+                if !entry.is_empty() && !tags.iter().any(|t| t.eq_ignore_ascii_case(entry)) {
+                    tags.push(entry);
+                }
+            }
+        }
+        if !tags.iter().any(|t| t.eq_ignore_ascii_case("en")) {
+            tags.extend(["en-US", "en"]);
+        }
+
+        tags.iter()
+            .enumerate()
+            .map(|(i, tag)| match i {
+                0 => tag.to_string(),
+                _ => format!("{};q={:.1}", tag, (1.0 - 0.1 * i as f32).max(0.1)),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a VirtualIP.
 pub struct VirtualIP {
-    pub ip: Ipv4Addr,
+    pub ip: IpAddr,
     pub country_code: String,
     pub country: String,
     pub city: String,
@@ -27,6 +135,13 @@ pub struct VirtualIP {
     pub currency: String,
     pub isp: String,
     pub proxy_url: Option<String>,
+    /// `Accept-Language` header matching `language`
+    #[serde(default)]
+    pub accept_language: String,
+    /// True when `ip`, `city` and `isp` were observed through `proxy_url`
+    /// rather than generated
+    #[serde(default)]
+    pub exit_verified: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,6 +151,8 @@ pub struct IPRange {
     pub end: Ipv4Addr,
     pub country_code: String,
     pub isp: String,
+    #[serde(default)]
+    pub asn: Option<u32>,
 }
 
 impl IPRange {
@@ -43,34 +160,28 @@ impl IPRange {
     pub fn contains(&self, ip: &Ipv4Addr) -> bool {
         u32::from(*ip) >= u32::from(self.start) && u32::from(*ip) <= u32::from(self.end)
     }
+
+    /// Number of addresses in the range.
+    pub fn size(&self) -> u64 {
+        u64::from(u32::from(self.end)).saturating_sub(u64::from(u32::from(self.start))) + 1
+    }
 }
 
+/// ISO-3166 countries bundled with the crate, in the `load_countries_from_file` format.
+const BUNDLED_COUNTRIES: &str = include_str!("../data/countries.json");
+
 /// Represents a CountryDatabase.
 pub struct CountryDatabase;
 
 impl CountryDatabase {
-    /// Placeholder loaders; replace with real data source.
+    /// Loads every ISO-3166 country from the bundled dataset.
     pub fn load_all_countries() -> Vec<Country> {
-        vec![
-            Country {
-                code: "US".into(),
-                name: "United States".into(),
-                flag: "🇺🇸".into(),
-                timezone: "America/New_York".into(),
-                language: "en-US".into(),
-                currency: "USD".into(),
-                is_top: true,
-            },
-            Country {
-                code: "GB".into(),
-                name: "United Kingdom".into(),
-                flag: "🇬🇧".into(),
-                timezone: "Europe/London".into(),
-                language: "en-GB".into(),
-                currency: "GBP".into(),
-                is_top: true,
-            },
-        ]
+        Self::bundled().to_vec()
+    }
+
+    fn bundled() -> &'static [Country] {
+        static COUNTRIES: OnceLock<Vec<Country>> = OnceLock::new();
+        COUNTRIES.get_or_init(|| serde_json::from_str(BUNDLED_COUNTRIES).expect("Bundled country data is valid"))
     }
 
     /// Loads the top countries.
@@ -80,6 +191,29 @@ impl CountryDatabase {
             .filter(|c| c.is_top)
             .collect()
     }
+
+    /// Finds a bundled country by its alpha-2 or alpha-3 code.
+    pub fn find(code: &str) -> Option<Country> {
+        static BY_CODE: OnceLock<HashMap<String, usize>> = OnceLock::new();
+        let countries = Self::bundled();
+        let by_code = BY_CODE.get_or_init(|| {
+            let mut by_code = HashMap::with_capacity(countries.len() * 2);
+            for (i, country) in countries.iter().enumerate() {
+                by_code.insert(country.code.to_ascii_uppercase(), i);
+                if !country.alpha3.is_empty() {
+                    by_code.insert(country.alpha3.to_ascii_uppercase(), i);
+                }
+            }
+            by_code
+        });
+        by_code.get(&code.to_ascii_uppercase()).map(|&i| countries[i].clone())
+    }
+
+    /// Finds a bundled country by its English name, ignoring case.
+    pub fn find_by_name(name: &str) -> Option<Country> {
+        let name = name.trim();
+        Self::bundled().iter().find(|c| c.name.eq_ignore_ascii_case(name)).cloned()
+    }
 }
 
 /// Placeholder loader; replace with real JSON/CSV ingestion.
@@ -90,17 +224,19 @@ pub fn load_ip_ranges() -> Vec<IPRange> {
             end: Ipv4Addr::new(8, 8, 4, 255),
             country_code: "US".into(),
             isp: "ExampleISP".into(),
+            asn: None,
         },
         IPRange {
             start: Ipv4Addr::new(1, 0, 0, 0),
             end: Ipv4Addr::new(1, 0, 0, 255),
             country_code: "GB".into(),
             isp: "ExampleISP-GB".into(),
+            asn: None,
         },
     ]
 }
 
-/// Load countries from a JSON file if present; otherwise fallback to placeholder list.
+/// Load countries from a JSON file if present; otherwise fallback to the bundled list.
 pub fn load_countries_from_file(path: &Path) -> Vec<Country> {
     std::fs::read_to_string(path)
         .ok()
@@ -109,11 +245,29 @@ pub fn load_countries_from_file(path: &Path) -> Vec<Country> {
         .unwrap_or_else(CountryDatabase::load_all_countries)
 }
 
-/// Load IP ranges from a JSON file if present; otherwise fallback to placeholder list.
+/// Load IP ranges from a file or directory if present; otherwise fallback to placeholder list.
+///
+/// Directories and RIR/ASN files go through `IpRangeBuilder`, with a binary
+/// cache kept beside directory sources; other files are read as JSON.
 pub fn load_ip_ranges_from_file(path: &Path) -> Vec<IPRange> {
-    std::fs::read_to_string(path)
-        .ok()
-        .and_then(|data| serde_json::from_str::<Vec<IPRange>>(&data).ok())
-        .filter(|v| !v.is_empty())
-        .unwrap_or_else(load_ip_ranges)
+    let loaded = if path.is_dir() {
+        load_range_dir(path)
+    } else {
+        match RangeFormat::detect(path) {
+            Some(format) if format != RangeFormat::Json => {
+                load_range_sources(&[RangeSource::new(path, format)])
+            }
+            _ => std::fs::read_to_string(path)
+                .map_err(anyhow::Error::from)
+                .and_then(|data| Ok(serde_json::from_str::<Vec<IPRange>>(&data)?)),
+        }
+    };
+    match loaded {
+        Ok(ranges) if !ranges.is_empty() => ranges,
+        Ok(_) => load_ip_ranges(),
+        Err(e) => {
+            tracing::warn!("Failed to load IP ranges from {}: {:#}", path.display(), e);
+            load_ip_ranges()
+        }
+    }
 }
@@ -0,0 +1,618 @@
+use crate::geoip::split_csv_line;
+use crate::lookup::{IpRangeIndex, Ipv6Range};
+use crate::models::{Country, IPRange};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{debug, info, warn};
+
+/// ISP name for allocated space no ASN table covers.
+const UNKNOWN_ISP: &str = "Unknown ISP";
+/// Leading bytes of a binary range cache.
+const CACHE_MAGIC: &[u8; 4] = b"VIPR";
+const CACHE_VERSION: u8 = 2;
+/// Longest string the cache's `u16` length prefix can hold.
+const MAX_CACHED_STRING: usize = u16::MAX as usize;
+/// File name used for the cache when loading a directory of sources.
+pub const RANGE_CACHE_FILE: &str = "ip_ranges.cache";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// On-disk layout of an IP range source.
+pub enum RangeFormat {
+    /// RIR `delegated-<registry>-extended-latest` statistics file
+    RirDelegation,
+    /// iptoasn.com `ip2asn-v4.tsv`: `start\tend\tasn\tcountry\tdescription`
+    IpToAsnTsv,
+    /// DB-IP ASN CSV: `start,end,asn,organization`
+    DbIpAsnCsv,
+    /// MaxMind GeoLite2 ASN blocks CSV: `network,asn,organization`
+    GeoLite2AsnCsv,
+    /// JSON array of `IPRange`
+    Json,
+    /// Binary cache written by `save_range_cache`
+    Cache,
+}
+
+impl RangeFormat {
+    /// Guess the format from a file name.
+    pub fn detect(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.starts_with("delegated-") {
+            Some(Self::RirDelegation)
+        } else if name.contains("ip2asn") || name.ends_with(".tsv") {
+            Some(Self::IpToAsnTsv)
+        } else if name.contains("geolite2-asn") && name.ends_with(".csv") {
+            Some(Self::GeoLite2AsnCsv)
+        } else if name.contains("asn") && name.ends_with(".csv") {
+            Some(Self::DbIpAsnCsv)
+        } else if name.ends_with(".json") {
+            Some(Self::Json)
+        } else if name.ends_with(".cache") || name.ends_with(".bin") {
+            Some(Self::Cache)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A range data file and its format.
+pub struct RangeSource {
+    pub path: PathBuf,
+    pub format: RangeFormat,
+}
+
+impl RangeSource {
+    /// Creates a new source.
+    pub fn new(path: impl Into<PathBuf>, format: RangeFormat) -> Self {
+        Self { path: path.into(), format }
+    }
+
+    /// Creates a source, detecting the format from the file name.
+    pub fn detect(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let format = RangeFormat::detect(&path)
+            .ok_or_else(|| anyhow!("Unknown IP range format: {}", path.display()))?;
+        Ok(Self { path, format })
+    }
+}
+
+/// An ASN block before its country is resolved.
+struct AsnBlock {
+    start: u32,
+    end: u32,
+    asn: u32,
+    organization: String,
+    country_code: Option<String>,
+}
+
+/// Builds per-country `IPRange`s from RIR delegations and IP-to-ASN tables.
+///
+/// Delegations supply the country of every allocated block; ASN tables split
+/// those blocks by network and name the ISP. ASN rows without a country take
+/// it from the delegation covering them, and allocated space no ASN table
+/// covers is kept with an unknown ISP.
+#[derive(Default)]
+pub struct IpRangeBuilder {
+    delegations: Vec<(u32, u32, String)>,
+    v6_delegations: Vec<Ipv6Range>,
+    asn_blocks: Vec<AsnBlock>,
+    ranges: Vec<IPRange>,
+}
+
+impl IpRangeBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read a source file. Returns the number of records added.
+    pub fn add_source(&mut self, source: &RangeSource) -> Result<usize> {
+        if source.format == RangeFormat::Cache {
+            let ranges = load_range_cache(&source.path)?;
+            let count = ranges.len();
+            self.ranges.extend(ranges);
+            return Ok(count);
+        }
+        let text = std::fs::read_to_string(&source.path)
+            .with_context(|| format!("Failed to read {}", source.path.display()))?;
+        let count = match source.format {
+            RangeFormat::RirDelegation => self.add_delegations(&text),
+            RangeFormat::Json => {
+                let ranges: Vec<IPRange> = serde_json::from_str(&text)
+                    .with_context(|| format!("Invalid IP range JSON in {}", source.path.display()))?;
+                let count = ranges.len();
+                self.ranges.extend(ranges);
+                count
+            }
+            format => self.add_asn_table(&text, format)?,
+        };
+        debug!("Read {} IP range records from {}", count, source.path.display());
+        Ok(count)
+    }
+
+    /// Add the allocations of a RIR delegation file.
+    ///
+    /// Lines look like `ripencc|DE|ipv4|5.1.0.0|65536|20110826|allocated|...`;
+    /// the version header, summaries and reserved/available space are skipped.
+    /// IPv6 lines carry a prefix length instead of an address count.
+    pub fn add_delegations(&mut self, text: &str) -> usize {
+        let before = self.delegations.len() + self.v6_delegations.len();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split('|').collect();
+            if fields.len() < 7 || !matches!(fields[6], "allocated" | "assigned") {
+                continue;
+            }
+            let country = fields[1].trim().to_ascii_uppercase();
+            if country.len() != 2 {
+                continue;
+            }
+            match fields[2] {
+                "ipv4" => {
+                    let (Ok(start), Ok(count)) = (fields[3].parse::<Ipv4Addr>(), fields[4].parse::<u64>()) else {
+                        continue;
+                    };
+                    if count == 0 {
+                        continue;
+                    }
+                    let start = u32::from(start);
+                    let end = (u64::from(start) + count - 1).min(u64::from(u32::MAX)) as u32;
+                    self.delegations.push((start, end, country));
+                }
+                "ipv6" => {
+                    let Ok(network) = format!("{}/{}", fields[3], fields[4]).parse::<ipnetwork::Ipv6Network>() else {
+                        continue;
+                    };
+                    self.v6_delegations.push(Ipv6Range {
+                        start: network.network(),
+                        end: network.broadcast(),
+                        country_code: country,
+                        isp: UNKNOWN_ISP.into(),
+                        asn: None,
+                    });
+                }
+                _ => {}
+            }
+        }
+        self.delegations.len() + self.v6_delegations.len() - before
+    }
+
+    /// Add an IP-to-ASN table. Rows that cannot be parsed, such as headers, are skipped.
+    pub fn add_asn_table(&mut self, text: &str, format: RangeFormat) -> Result<usize> {
+        let before = self.asn_blocks.len();
+        let mut skipped = 0usize;
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            match parse_asn_row(line, format)? {
+                Some(block) => self.asn_blocks.push(block),
+                None => skipped += 1,
+            }
+        }
+        if skipped > 0 {
+            debug!("Skipped {} unparseable ASN rows", skipped);
+        }
+        Ok(self.asn_blocks.len() - before)
+    }
+
+    /// Build a lookup index over the IPv4 ranges and IPv6 allocations.
+    pub fn build_index(self, countries: &[Country]) -> IpRangeIndex {
+        self.build_with_index(countries).1
+    }
+
+    /// Build the IPv4 ranges and an index over them and the IPv6 allocations.
+    pub fn build_with_index(mut self, countries: &[Country]) -> (Vec<IPRange>, IpRangeIndex) {
+        let v6 = std::mem::take(&mut self.v6_delegations);
+        let ranges = self.build();
+        let index = IpRangeIndex::with_v6_ranges(&ranges, &v6, countries);
+        (ranges, index)
+    }
+
+    /// Resolve countries, fill gaps and merge adjacent ranges.
+    ///
+    /// Only IPv4 space is returned; IPv6 allocations are kept for `build_index`.
+    pub fn build(self) -> Vec<IPRange> {
+        let mut delegations = self.delegations;
+        delegations.sort_by_key(|(start, _, _)| *start);
+        let country_at = |ip: u32| {
+            let idx = delegations.partition_point(|(start, _, _)| *start <= ip);
+            let (_, end, country) = delegations.get(idx.checked_sub(1)?)?;
+            (ip <= *end).then(|| country.clone())
+        };
+
+        let mut asn_blocks = self.asn_blocks;
+        asn_blocks.sort_by_key(|b| (b.start, b.end));
+        let mut covered: Vec<(u32, u32)> = Vec::with_capacity(asn_blocks.len());
+        let mut ranges = self.ranges;
+        let mut unresolved = 0usize;
+
+        for block in asn_blocks {
+            // Tables occasionally overlap; the first block wins.
+            if covered.last().is_some_and(|(_, end)| block.start <= *end) {
+                continue;
+            }
+            covered.push((block.start, block.end));
+            let Some(country_code) = block.country_code.or_else(|| country_at(block.start)) else {
+                unresolved += 1;
+                continue;
+            };
+            ranges.push(IPRange {
+                start: Ipv4Addr::from(block.start),
+                end: Ipv4Addr::from(block.end),
+                country_code,
+                isp: block.organization,
+                asn: (block.asn != 0).then_some(block.asn),
+            });
+        }
+        if unresolved > 0 {
+            debug!("Dropped {} ASN blocks with no known country", unresolved);
+        }
+
+        for (start, end, country) in &delegations {
+            for (gap_start, gap_end) in uncovered(&covered, *start, *end) {
+                ranges.push(IPRange {
+                    start: Ipv4Addr::from(gap_start),
+                    end: Ipv4Addr::from(gap_end),
+                    country_code: country.clone(),
+                    isp: UNKNOWN_ISP.into(),
+                    asn: None,
+                });
+            }
+        }
+
+        let merged = merge_adjacent(ranges);
+        info!("Built {} IP ranges", merged.len());
+        merged
+    }
+}
+
+fn parse_asn_row(line: &str, format: RangeFormat) -> Result<Option<AsnBlock>> {
+    let fields: Vec<String> = match format {
+        RangeFormat::IpToAsnTsv => line.split('\t').map(str::to_string).collect(),
+        RangeFormat::DbIpAsnCsv | RangeFormat::GeoLite2AsnCsv => split_csv_line(line),
+        other => return Err(anyhow!("{:?} is not an ASN table format", other)),
+    };
+    let field = |i: usize| fields.get(i).map(|s| s.trim()).filter(|s| !s.is_empty());
+    let asn = |i: usize| field(i).and_then(|s| s.trim_start_matches("AS").parse::<u32>().ok());
+    let ip = |i: usize| field(i).and_then(|s| s.parse::<Ipv4Addr>().ok()).map(u32::from);
+
+    let block = match format {
+        RangeFormat::IpToAsnTsv => {
+            let (Some(start), Some(end), Some(asn)) = (ip(0), ip(1), asn(2)) else {
+                return Ok(None);
+            };
+            // Unrouted space is listed as AS0 "Not routed"
+            if asn == 0 {
+                return Ok(None);
+            }
+            AsnBlock {
+                start,
+                end,
+                asn,
+                organization: field(4).unwrap_or(UNKNOWN_ISP).to_string(),
+                country_code: field(3)
+                    .filter(|c| c.len() == 2 && *c != "None")
+                    .map(str::to_ascii_uppercase),
+            }
+        }
+        RangeFormat::DbIpAsnCsv => {
+            let (Some(start), Some(end), Some(asn)) = (ip(0), ip(1), asn(2)) else {
+                return Ok(None);
+            };
+            AsnBlock {
+                start,
+                end,
+                asn,
+                organization: field(3).unwrap_or(UNKNOWN_ISP).to_string(),
+                country_code: None,
+            }
+        }
+        _ => {
+            let (Some((start, end)), Some(asn)) = (field(0).and_then(parse_cidr), asn(1)) else {
+                return Ok(None);
+            };
+            AsnBlock {
+                start,
+                end,
+                asn,
+                organization: field(2).unwrap_or(UNKNOWN_ISP).to_string(),
+                country_code: None,
+            }
+        }
+    };
+    Ok((block.start <= block.end).then_some(block))
+}
+
+fn parse_cidr(value: &str) -> Option<(u32, u32)> {
+    let network: ipnetwork::Ipv4Network = value.parse().ok()?;
+    Some((u32::from(network.network()), u32::from(network.broadcast())))
+}
+
+/// Parts of `[start, end]` not covered by the sorted, non-overlapping `covered` list.
+fn uncovered(covered: &[(u32, u32)], start: u32, end: u32) -> Vec<(u32, u32)> {
+    let mut gaps = Vec::new();
+    let mut cursor = u64::from(start);
+    let first = covered.partition_point(|(_, e)| *e < start);
+    for (c_start, c_end) in &covered[first..] {
+        if *c_start > end {
+            break;
+        }
+        if u64::from(*c_start) > cursor {
+            gaps.push((cursor as u32, c_start - 1));
+        }
+        cursor = cursor.max(u64::from(*c_end) + 1);
+    }
+    if cursor <= u64::from(end) {
+        gaps.push((cursor as u32, end));
+    }
+    gaps
+}
+
+/// Sort ranges and join neighbours that share country, ISP and ASN.
+pub fn merge_adjacent(mut ranges: Vec<IPRange>) -> Vec<IPRange> {
+    ranges.sort_by_key(|r| (u32::from(r.start), u32::from(r.end)));
+    let mut merged: Vec<IPRange> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        if let Some(last) = merged.last_mut() {
+            let touches = u64::from(u32::from(range.start)) <= u64::from(u32::from(last.end)) + 1;
+            if touches && last.country_code == range.country_code && last.isp == range.isp && last.asn == range.asn {
+                if u32::from(range.end) > u32::from(last.end) {
+                    last.end = range.end;
+                }
+                continue;
+            }
+        }
+        merged.push(range);
+    }
+    merged
+}
+
+// ============================================================================
+// Binary cache
+// ============================================================================
+
+/// Encode ranges into the compact cache format.
+///
+/// Layout (little endian): magic, version, a `u64` key of the sources the
+/// cache was built from, a string table of country codes and ISP names, then
+/// `start u32, end u32, country u32, isp u32, asn u32` per range with ASN 0
+/// meaning unknown. Strings longer than 65535 bytes are truncated.
+pub fn encode_range_cache(ranges: &[IPRange]) -> Vec<u8> {
+    encode_keyed_cache(ranges, 0)
+}
+
+fn encode_keyed_cache(ranges: &[IPRange], sources_key: u64) -> Vec<u8> {
+    let mut strings: Vec<&str> = Vec::new();
+    let mut index: HashMap<&str, u32> = HashMap::new();
+    let mut records = Vec::with_capacity(ranges.len() * 20);
+    let mut intern = |value| -> u32 {
+        *index.entry(value).or_insert_with(|| {
+            strings.push(value);
+            (strings.len() - 1) as u32
+        })
+    };
+    for range in ranges {
+        let country = intern(truncate_utf8(&range.country_code, MAX_CACHED_STRING));
+        let isp = intern(truncate_utf8(&range.isp, MAX_CACHED_STRING));
+        for field in [u32::from(range.start), u32::from(range.end), country, isp, range.asn.unwrap_or(0)] {
+            records.extend_from_slice(&field.to_le_bytes());
+        }
+    }
+
+    let mut out = Vec::with_capacity(16 + records.len());
+    out.extend_from_slice(CACHE_MAGIC);
+    out.push(CACHE_VERSION);
+    out.extend_from_slice(&sources_key.to_le_bytes());
+    out.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+    for value in &strings {
+        out.extend_from_slice(&(value.len() as u16).to_le_bytes());
+        out.extend_from_slice(value.as_bytes());
+    }
+    out.extend_from_slice(&(ranges.len() as u32).to_le_bytes());
+    out.extend_from_slice(&records);
+    out
+}
+
+/// Decode a cache produced by `encode_range_cache`.
+pub fn decode_range_cache(bytes: &[u8]) -> Result<Vec<IPRange>> {
+    decode_keyed_cache(bytes).map(|(_, ranges)| ranges)
+}
+
+fn decode_keyed_cache(bytes: &[u8]) -> Result<(u64, Vec<IPRange>)> {
+    let mut reader = CacheReader { bytes, pos: 0 };
+    if reader.take(4)? != CACHE_MAGIC {
+        return Err(anyhow!("Not an IP range cache"));
+    }
+    let version = reader.take(1)?[0];
+    if version != CACHE_VERSION {
+        return Err(anyhow!("Unsupported IP range cache version {}", version));
+    }
+    let sources_key = u64::from_le_bytes(reader.take(8)?.try_into()?);
+
+    let string_count = reader.u32()? as usize;
+    let mut strings = Vec::with_capacity(string_count.min(bytes.len()));
+    for _ in 0..string_count {
+        let len = u16::from_le_bytes(reader.take(2)?.try_into()?) as usize;
+        let value = std::str::from_utf8(reader.take(len)?).context("Invalid string in IP range cache")?;
+        strings.push(value.to_string());
+    }
+    let string = |id: u32| {
+        strings
+            .get(id as usize)
+            .cloned()
+            .ok_or_else(|| anyhow!("Invalid string id {} in IP range cache", id))
+    };
+
+    let range_count = reader.u32()? as usize;
+    let mut ranges = Vec::with_capacity(range_count.min(bytes.len() / 20));
+    for _ in 0..range_count {
+        let start = Ipv4Addr::from(reader.u32()?);
+        let end = Ipv4Addr::from(reader.u32()?);
+        let country_code = string(reader.u32()?)?;
+        let isp = string(reader.u32()?)?;
+        let asn = reader.u32()?;
+        ranges.push(IPRange { start, end, country_code, isp, asn: (asn != 0).then_some(asn) });
+    }
+    if reader.pos != bytes.len() {
+        return Err(anyhow!("Trailing data in IP range cache"));
+    }
+    Ok((sources_key, ranges))
+}
+
+/// Longest prefix of `value` within `max` bytes that ends on a char boundary
+fn truncate_utf8(value: &str, max: usize) -> &str {
+    if value.len() <= max {
+        return value;
+    }
+    let mut end = max;
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+struct CacheReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> CacheReader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8]> {
+        let end = self.pos.checked_add(len).filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| anyhow!("Truncated IP range cache"))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into()?))
+    }
+}
+
+/// Write ranges to a binary cache file.
+pub fn save_range_cache(path: &Path, ranges: &[IPRange]) -> Result<()> {
+    std::fs::write(path, encode_range_cache(ranges))
+        .with_context(|| format!("Failed to write IP range cache {}", path.display()))
+}
+
+/// Read ranges from a binary cache file.
+pub fn load_range_cache(path: &Path) -> Result<Vec<IPRange>> {
+    let bytes = std::fs::read(path)
+        .with_context(|| format!("Failed to read IP range cache {}", path.display()))?;
+    decode_range_cache(&bytes).with_context(|| format!("Invalid IP range cache {}", path.display()))
+}
+
+/// Build ranges from a set of sources.
+pub fn load_range_sources(sources: &[RangeSource]) -> Result<Vec<IPRange>> {
+    let mut builder = IpRangeBuilder::new();
+    for source in sources {
+        builder.add_source(source)?;
+    }
+    Ok(builder.build())
+}
+
+/// Build ranges from every recognised file in a directory.
+///
+/// The result is cached as `ip_ranges.cache` in the same directory and reused
+/// until a source file is added, removed or modified.
+pub fn load_range_dir(dir: &Path) -> Result<Vec<IPRange>> {
+    let cache_path = dir.join(RANGE_CACHE_FILE);
+    let sources = range_dir_sources(dir)?;
+    if sources.is_empty() {
+        return load_range_cache(&cache_path);
+    }
+
+    let key = sources_key(&sources);
+    if cache_path.is_file() {
+        match std::fs::read(&cache_path).map_err(anyhow::Error::from).and_then(|b| decode_keyed_cache(&b)) {
+            Ok((cached_key, ranges)) if cached_key == key => {
+                debug!("Loaded {} IP ranges from cache", ranges.len());
+                return Ok(ranges);
+            }
+            Ok(_) => debug!("IP range sources changed, rebuilding cache"),
+            Err(e) => warn!("Ignoring IP range cache: {:#}", e),
+        }
+    }
+
+    let ranges = load_range_sources(&sources)?;
+    if let Err(e) = std::fs::write(&cache_path, encode_keyed_cache(&ranges, key)) {
+        warn!("Failed to write IP range cache {}: {}", cache_path.display(), e);
+    }
+    Ok(ranges)
+}
+
+/// Build ranges and a lookup index from a file or directory in one pass.
+///
+/// Unlike `load_ip_ranges_from_file` there is no placeholder fallback: missing,
+/// unreadable or empty sources are an error. IPv6 allocations in RIR
+/// delegation files are indexed as well.
+pub fn load_range_index(path: &Path, countries: &[Country]) -> Result<(Vec<IPRange>, IpRangeIndex)> {
+    let sources = if path.is_dir() {
+        let sources = range_dir_sources(path)?;
+        if sources.is_empty() {
+            vec![RangeSource::new(path.join(RANGE_CACHE_FILE), RangeFormat::Cache)]
+        } else {
+            sources
+        }
+    } else {
+        vec![RangeSource::detect(path)?]
+    };
+
+    let mut builder = IpRangeBuilder::new();
+    for source in &sources {
+        builder.add_source(source)?;
+    }
+    let (ranges, index) = builder.build_with_index(countries);
+    if index.is_empty() {
+        return Err(anyhow!("No IP ranges in {}", path.display()));
+    }
+    Ok((ranges, index))
+}
+
+/// Recognised range sources in a directory, sorted by path, without the cache
+fn range_dir_sources(dir: &Path) -> Result<Vec<RangeSource>> {
+    let cache_path = dir.join(RANGE_CACHE_FILE);
+    let mut sources = Vec::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path == cache_path || !path.is_file() {
+            continue;
+        }
+        if let Ok(source) = RangeSource::detect(&path) {
+            sources.push(source);
+        }
+    }
+    sources.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(sources)
+}
+
+/// FNV-1a over each source's name, size and modification time
+fn sources_key(sources: &[RangeSource]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for source in sources {
+        let metadata = std::fs::metadata(&source.path).ok();
+        let size = metadata.as_ref().map(|m| m.len()).unwrap_or(0);
+        let modified = metadata
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let name = source.path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let fields = name.bytes().chain([0]).chain(size.to_le_bytes()).chain(modified.to_le_bytes());
+        for byte in fields {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
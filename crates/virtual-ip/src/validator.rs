@@ -1,7 +1,15 @@
+use crate::geoip::{GeoIpDatabase, GeoIpRecord};
+use crate::leak::{
+    dns_probe_names, webrtc_leaks, DnsProbeSource, IceCandidateSource, LeakCheckConfig, StunCandidateSource,
+    SystemDnsProbe,
+};
 use crate::models::VirtualIP;
-use anyhow::Result;
-use reqwest::Client;
+use anyhow::{anyhow, Result};
+use reqwest::{Client, Proxy};
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::warn;
 
 #[derive(Debug, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -13,10 +21,18 @@ struct IPInfoResponse {
     timezone: String,
 }
 
+#[derive(Debug, Deserialize)]
+struct PlainIpResponse {
+    ip: String,
+}
+
 /// Represents a IPValidator.
 pub struct IPValidator {
-    #[allow(dead_code)]
     client: Client,
+    geoip: Option<Arc<GeoIpDatabase>>,
+    leak_config: LeakCheckConfig,
+    ice_source: Arc<dyn IceCandidateSource>,
+    dns_source: Arc<dyn DnsProbeSource>,
 }
 
 impl Default for IPValidator {
@@ -30,44 +46,172 @@ impl IPValidator {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
+            geoip: None,
+            leak_config: LeakCheckConfig::default(),
+            ice_source: Arc::new(StunCandidateSource),
+            dns_source: Arc::new(SystemDnsProbe),
         }
     }
 
-    /// Validate IP by checking external service
-    pub async fn validate(&self, virtual_ip: &VirtualIP) -> Result<bool> {
+    /// Detect the public IP through `proxy_url`, such as the tab's exit proxy.
+    pub fn with_proxy(mut self, proxy_url: &str) -> Result<Self> {
+        self.client = Client::builder().proxy(Proxy::all(proxy_url)?).build()?;
+        Ok(self)
+    }
+
+    /// Resolve locations from a local GeoIP database instead of a remote service.
+    pub fn with_geoip(mut self, geoip: Arc<GeoIpDatabase>) -> Self {
+        self.geoip = Some(geoip);
+        self
+    }
+
+    /// Configure the STUN servers and DNS probe zone used by leak checks.
+    pub fn with_leak_config(mut self, config: LeakCheckConfig) -> Self {
+        self.leak_config = config;
+        self
+    }
+
+    /// Gather WebRTC candidates from `source`, such as a page in the tab's browser.
+    pub fn with_ice_source(mut self, source: Arc<dyn IceCandidateSource>) -> Self {
+        self.ice_source = source;
+        self
+    }
+
+    /// Resolve DNS leak probes through `source`, such as a page in the tab's browser.
+    pub fn with_dns_source(mut self, source: Arc<dyn DnsProbeSource>) -> Self {
+        self.dns_source = source;
+        self
+    }
+
+    /// Detect the current public IP.
+    pub async fn detect_public_ip(&self) -> Result<String> {
+        if self.geoip.is_some() {
+            // Location comes from the local database, so only ask for the bare IP.
+            let response = self.client
+                .get("https://api.ipify.org?format=json")
+                .send()
+                .await?;
+            let ip_info: PlainIpResponse = response.json().await?;
+            return Ok(ip_info.ip);
+        }
+
         // Hit ipinfo.io to check current public IP
         let response = self.client
             .get("https://ipinfo.io/json")
             .send()
             .await?;
-        
+
         let ip_info: IPInfoResponse = response.json().await?;
-        
+        Ok(ip_info.ip)
+    }
+
+    /// Validate IP by checking external service
+    pub async fn validate(&self, virtual_ip: &VirtualIP) -> Result<bool> {
+        let detected_ip = self.detect_public_ip().await?;
+
         // Check if the detected IP matches our virtual IP
-        Ok(ip_info.ip == virtual_ip.ip.to_string())
+        Ok(detected_ip == virtual_ip.ip.to_string())
+    }
+
+    /// Look up an IP in the local GeoIP database, if one is configured.
+    pub fn locate(&self, ip: &str) -> Option<GeoIpRecord> {
+        self.geoip.as_ref()?.lookup_str(ip)
     }
 
-    /// Check for WebRTC leaks (stubbed)
-    pub async fn check_webrtc_leak(&self) -> Result<Vec<String>> {
-        Ok(vec![])
+    /// Check that the virtual IP's address resolves to its claimed country.
+    ///
+    /// Returns `None` when no local database is configured or the address is unknown.
+    pub fn check_country(&self, virtual_ip: &VirtualIP) -> Option<bool> {
+        let record = self.locate(&virtual_ip.ip.to_string())?;
+        let code = record.country_code?;
+        Some(code.eq_ignore_ascii_case(&virtual_ip.country_code))
     }
 
-    /// Check DNS leak (stubbed)
-    pub async fn check_dns_leak(&self) -> Result<bool> {
-        Ok(true)
+    /// Check for WebRTC leaks.
+    ///
+    /// Gathers ICE candidates against the configured STUN servers and returns
+    /// every public candidate address other than `exit_ip`.
+    pub async fn check_webrtc_leak(&self, exit_ip: IpAddr) -> Result<Vec<String>> {
+        let candidates = self
+            .ice_source
+            .gather_candidates(&self.leak_config.stun_servers, self.leak_config.ice_timeout)
+            .await?;
+        Ok(webrtc_leaks(&candidates, exit_ip, self.leak_config.report_local_candidates)
+            .into_iter()
+            .map(|ip| ip.to_string())
+            .collect())
+    }
+
+    /// Check for DNS leaks.
+    ///
+    /// Resolves unique subdomains of the probe zone through the DNS source and
+    /// returns the resolvers that reached its authoritative server, other than
+    /// `exit_ip`, trusted resolvers, and resolvers the GeoIP database places in
+    /// the same ASN as the exit, which belong to the proxy provider.
+    pub async fn check_dns_leak(&self, exit_ip: IpAddr) -> Result<Vec<String>> {
+        let domain = self
+            .leak_config
+            .dns_probe_domain
+            .as_deref()
+            .ok_or_else(|| anyhow!("No DNS probe domain configured"))?;
+        let names = dns_probe_names(domain, self.leak_config.dns_probes);
+        let resolvers = self.dns_source.resolve_probes(&self.leak_config, &names).await?;
+        let exit_asn = self.locate(&exit_ip.to_string()).and_then(|r| r.asn);
+        Ok(resolvers
+            .into_iter()
+            .filter(|ip| *ip != exit_ip && !self.leak_config.trusted_resolvers.contains(ip))
+            .filter(|ip| {
+                exit_asn.is_none() || self.locate(&ip.to_string()).and_then(|r| r.asn) != exit_asn
+            })
+            .map(|ip| ip.to_string())
+            .collect())
     }
 
     /// Comprehensive validation
+    ///
+    /// Leak checks that cannot run are reported as failures with a note. They
+    /// only run for a virtual IP whose exit was observed, since a generated
+    /// address is not one any candidate or resolver could match.
     pub async fn validate_comprehensive(&self, virtual_ip: &VirtualIP) -> Result<ValidationReport> {
         let ip_matches = self.validate(virtual_ip).await?;
-        let webrtc_leaks = self.check_webrtc_leak().await?;
-        let dns_secure = self.check_dns_leak().await?;
+        let exit_ip = virtual_ip.ip;
+        let country_matches = self.check_country(virtual_ip);
+        let mut notes = Vec::new();
+
+        let (webrtc_leaked_ips, dns_leaked_resolvers) = if virtual_ip.exit_verified {
+            let webrtc = match self.check_webrtc_leak(exit_ip).await {
+                Ok(leaks) => Some(leaks),
+                Err(e) => {
+                    warn!("WebRTC leak check failed: {}", e);
+                    notes.push(format!("WebRTC leak check failed: {}", e));
+                    None
+                }
+            };
+            let dns = match self.check_dns_leak(exit_ip).await {
+                Ok(leaks) => Some(leaks),
+                Err(e) => {
+                    warn!("DNS leak check failed: {}", e);
+                    notes.push(format!("DNS leak check failed: {}", e));
+                    None
+                }
+            };
+            (webrtc, dns)
+        } else {
+            notes.push(format!("Leak checks skipped: {} is not an observed exit", exit_ip));
+            (None, None)
+        };
 
+        let webrtc_secure = webrtc_leaked_ips.as_ref().is_some_and(|l| l.is_empty());
+        let dns_secure = dns_leaked_resolvers.as_ref().is_some_and(|l| l.is_empty());
         Ok(ValidationReport {
             ip_matches,
-            webrtc_leaks: webrtc_leaks.is_empty(),
+            webrtc_secure,
             dns_secure,
-            overall_pass: ip_matches && webrtc_leaks.is_empty() && dns_secure,
+            country_matches,
+            overall_pass: ip_matches && webrtc_secure && dns_secure && country_matches.unwrap_or(true),
+            webrtc_leaked_ips: webrtc_leaked_ips.unwrap_or_default(),
+            dns_leaked_resolvers: dns_leaked_resolvers.unwrap_or_default(),
+            notes,
         })
     }
 }
@@ -76,7 +220,18 @@ impl IPValidator {
 /// Represents a ValidationReport.
 pub struct ValidationReport {
     pub ip_matches: bool,
-    pub webrtc_leaks: bool,
+    /// True when the WebRTC check ran and found no leaked candidates
+    pub webrtc_secure: bool,
     pub dns_secure: bool,
+    pub country_matches: Option<bool>,
     pub overall_pass: bool,
+    /// Candidate addresses other than the exit
+    #[serde(default)]
+    pub webrtc_leaked_ips: Vec<String>,
+    /// Resolvers seen by the probe zone that reveal another network
+    #[serde(default)]
+    pub dns_leaked_resolvers: Vec<String>,
+    /// Why a check could not be completed
+    #[serde(default)]
+    pub notes: Vec<String>,
 }
@@ -6,13 +6,19 @@ use browser_core::{
     PublicIpDetector, PublicIpInfo, FreeIpProviderManager,
     StorageEngine, BackupManager, BackupData, BackupOptions, BackupInfo,
     BrowserController, BrowserState, BrowserSettings, WebRtcPolicy,
+    ProxyQuarantineManager, ProxyValidator, ProxyValidatorConfig, GeoVerifier, GeoVerificationConfig,
+    TamperCheckConfig, ThroughputResult,
+    ValidationScheduler, ValidationSchedulerConfig, ValidationSchedulerStatus, ValidationPriority,
+    ProxyListFormat, ImportError, ProviderStats, SshOptions,
+    AppConfig, ConfigManager, BrowserTabManager, TabIPManager, PublicIpExitVerifier, ExitBindingConfig,
+    BrowserEngineManager,
 };
 use serde::{Deserialize, Serialize};
 use tauri::{State, Manager};
 use tracing::{info, error, debug, warn};
 use virtual_ip::{
-    demo_generator, load_countries_from_file, load_ip_ranges, load_ip_ranges_from_file,
-    Country, CountryDatabase, IPGenerator, VirtualIP,
+    demo_generator, load_countries_from_file, load_ip_ranges, load_range_index,
+    Country, CountryDatabase, GeoIpDatabase, IPGenerator, IpRangeIndex, VirtualIP,
 };
 
 mod webview_manager;
@@ -29,6 +35,9 @@ struct AppState {
     storage_engine: Arc<StorageEngine>,
     backup_manager: Arc<BackupManager>,
     browser_controller: Arc<BrowserController>,
+    validation_scheduler: Arc<ValidationScheduler>,
+    provider_manager: Arc<tokio::sync::RwLock<FreeIpProviderManager>>,
+    engine_manager: Arc<BrowserEngineManager>,
 }
 
 // ============================================================================
@@ -192,7 +201,18 @@ async fn get_active_proxy(state: State<'_, AppState>) -> Result<Option<FreeProxy
 
 #[tauri::command]
 async fn set_active_proxy(state: State<'_, AppState>, proxy: Option<FreeProxyRequest>) -> Result<(), String> {
-    state.proxy_manager.set_active_proxy(proxy.map(|p| p.into())).await;
+    let proxy = match proxy {
+        Some(p) => Some(state.proxy_manager.with_pool_credentials(p.into()).await),
+        None => None,
+    };
+    // The active proxy is leased to the browser, so keep it under closer watch
+    if let Some(previous) = state.proxy_manager.get_active_proxy().await {
+        state.validation_scheduler.set_priority(&previous, ValidationPriority::Normal).await;
+    }
+    if let Some(active) = &proxy {
+        state.validation_scheduler.set_priority(active, ValidationPriority::Leased).await;
+    }
+    state.proxy_manager.set_active_proxy(proxy).await;
     Ok(())
 }
 
@@ -213,9 +233,18 @@ async fn detect_public_ip(state: State<'_, AppState>) -> Result<PublicIpResponse
 // Free IP Providers
 #[tauri::command]
 async fn fetch_free_proxies(state: State<'_, AppState>) -> Result<Vec<FreeProxyResponse>, String> {
-    let mut manager = FreeIpProviderManager::new().map_err(|e| e.to_string())?;
-    let proxies = manager.fetch_all().await;
+    let proxies = FreeIpProviderManager::fetch_shared(&state.provider_manager).await;
     state.proxy_manager.add_free_proxies(proxies.clone()).await;
+
+    // Credit a tested sample to the providers so failing ones get disabled
+    let provider_manager = state.provider_manager.clone();
+    let proxy_manager = state.proxy_manager.clone();
+    let sample = proxies.clone();
+    tauri::async_runtime::spawn(async move {
+        let checked = FreeIpProviderManager::validate_shared(&provider_manager, sample).await;
+        proxy_manager.record_checks(&checked).await;
+    });
+
     Ok(proxies.into_iter().map(FreeProxyResponse::from).collect())
 }
 
@@ -225,9 +254,10 @@ async fn get_free_proxies(state: State<'_, AppState>) -> Result<Vec<FreeProxyRes
 }
 
 #[tauri::command]
-async fn test_proxy(_state: State<'_, AppState>, proxy: FreeProxyRequest) -> Result<ProxyTestResultResponse, String> {
+async fn test_proxy(state: State<'_, AppState>, proxy: FreeProxyRequest) -> Result<ProxyTestResultResponse, String> {
+    let proxy = state.proxy_manager.with_pool_credentials(proxy.into()).await;
     let manager = FreeIpProviderManager::new().map_err(|e| e.to_string())?;
-    let result = manager.test_proxy(&proxy.into()).await;
+    let result = manager.test_proxy(&proxy).await;
     Ok(ProxyTestResultResponse::from(result))
 }
 
@@ -243,6 +273,80 @@ async fn remove_dead_proxies(state: State<'_, AppState>) -> Result<(), String> {
     Ok(())
 }
 
+#[tauri::command]
+async fn get_ip_providers_status(state: State<'_, AppState>) -> Result<Vec<ProviderStats>, String> {
+    Ok(state.provider_manager.read().await.provider_stats())
+}
+
+#[tauri::command]
+async fn set_ip_provider_enabled(state: State<'_, AppState>, name: String, enabled: bool) -> Result<(), String> {
+    state.provider_manager.write().await.set_provider_enabled(&name, enabled).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn import_proxy_list(
+    state: State<'_, AppState>,
+    content: String,
+    format: Option<ProxyListFormat>,
+) -> Result<ProxyImportResponse, String> {
+    let report = state.proxy_manager.import_proxies(&content, format).await.map_err(|e| e.to_string())?;
+    info!("Imported {} proxies ({} duplicates, {} errors)", report.imported.len(), report.duplicates, report.errors.len());
+    Ok(ProxyImportResponse {
+        format: report.format,
+        imported: report.imported.len(),
+        duplicates: report.duplicates,
+        errors: report.errors,
+    })
+}
+
+#[tauri::command]
+async fn export_proxy_list(
+    state: State<'_, AppState>,
+    format: ProxyListFormat,
+    working_only: bool,
+) -> Result<String, String> {
+    Ok(state.proxy_manager.export_proxies(format, working_only).await)
+}
+
+// Background Validation
+#[tauri::command]
+async fn start_validation_scheduler(state: State<'_, AppState>) -> Result<ValidationSchedulerStatus, String> {
+    if !state.validation_scheduler.start() {
+        debug!("Validation scheduler already running");
+    }
+    Ok(state.validation_scheduler.status().await)
+}
+
+#[tauri::command]
+async fn stop_validation_scheduler(state: State<'_, AppState>) -> Result<ValidationSchedulerStatus, String> {
+    state.validation_scheduler.stop();
+    Ok(state.validation_scheduler.status().await)
+}
+
+#[tauri::command]
+async fn get_validation_scheduler_status(state: State<'_, AppState>) -> Result<ValidationSchedulerStatus, String> {
+    Ok(state.validation_scheduler.status().await)
+}
+
+#[tauri::command]
+async fn benchmark_proxy(state: State<'_, AppState>, proxy: FreeProxyRequest) -> Result<ThroughputResult, String> {
+    state
+        .validation_scheduler
+        .benchmark(&proxy.ip, proxy.port)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn set_proxy_validation_priority(
+    state: State<'_, AppState>,
+    proxy: FreeProxyRequest,
+    priority: ValidationPriority,
+) -> Result<(), String> {
+    state.validation_scheduler.set_priority(&proxy.into(), priority).await;
+    Ok(())
+}
+
 // Backup & Restore
 #[tauri::command]
 async fn create_backup(state: State<'_, AppState>, options: BackupOptionsRequest) -> Result<BackupInfoResponse, String> {
@@ -378,7 +482,13 @@ async fn get_browser_settings(state: State<'_, AppState>) -> Result<BrowserSetti
 
 #[tauri::command]
 async fn set_browser_settings(state: State<'_, AppState>, settings: BrowserSettingsRequest) -> Result<(), String> {
-    state.browser_controller.set_settings(settings.into()).await;
+    let settings: BrowserSettings = settings.into();
+    let mut config = state.engine_manager.get_config().await;
+    config.stealth_mode = settings.stealth_mode;
+    config.headless = settings.headless_mode;
+    state.engine_manager.update_chromium_config(config).await.map_err(|e| e.to_string())?;
+    state.engine_manager.set_engine_type(settings.engine_type).await.map_err(|e| e.to_string())?;
+    state.browser_controller.set_settings(settings).await;
     Ok(())
 }
 
@@ -520,24 +630,20 @@ pub struct ProxySettingsResponse {
     pub password: Option<String>,
     pub dns_servers: Vec<String>,
     pub bypass_list: Vec<String>,
+    pub ssh: Option<SshOptions>,
 }
 
 impl From<ProxySettings> for ProxySettingsResponse {
     fn from(s: ProxySettings) -> Self {
         Self {
-            proxy_type: match s.proxy_type {
-                ProxyType::Direct => "direct",
-                ProxyType::Http => "http",
-                ProxyType::Https => "https",
-                ProxyType::Socks4 => "socks4",
-                ProxyType::Socks5 => "socks5",
-            }.to_string(),
+            proxy_type: s.proxy_type.name().to_string(),
             host: s.host,
             port: s.port,
             username: s.username,
             password: s.password,
             dns_servers: s.dns_servers,
             bypass_list: s.bypass_list,
+            ssh: s.ssh,
         }
     }
 }
@@ -552,24 +658,21 @@ pub struct ProxySettingsRequest {
     pub password: Option<String>,
     pub dns_servers: Vec<String>,
     pub bypass_list: Vec<String>,
+    #[serde(default)]
+    pub ssh: Option<SshOptions>,
 }
 
 impl From<ProxySettingsRequest> for ProxySettings {
     fn from(r: ProxySettingsRequest) -> Self {
         Self {
-            proxy_type: match r.proxy_type.as_str() {
-                "http" => ProxyType::Http,
-                "https" => ProxyType::Https,
-                "socks4" => ProxyType::Socks4,
-                "socks5" => ProxyType::Socks5,
-                _ => ProxyType::Direct,
-            },
+            proxy_type: ProxyType::from_name(&r.proxy_type).unwrap_or_default(),
             host: r.host,
             port: r.port,
             username: r.username,
             password: r.password,
             dns_servers: r.dns_servers,
             bypass_list: r.bypass_list,
+            ssh: r.ssh,
         }
     }
 }
@@ -588,6 +691,9 @@ pub struct FreeProxyResponse {
     pub last_checked: String,
     pub provider: String,
     pub is_working: bool,
+    /// Sent so the pool entry can be found again; the password never leaves the backend
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
 }
 
 impl From<FreeProxy> for FreeProxyResponse {
@@ -595,13 +701,7 @@ impl From<FreeProxy> for FreeProxyResponse {
         Self {
             ip: p.ip,
             port: p.port,
-            protocol: match p.protocol {
-                ProxyType::Http => "http",
-                ProxyType::Https => "https",
-                ProxyType::Socks4 => "socks4",
-                ProxyType::Socks5 => "socks5",
-                ProxyType::Direct => "direct",
-            }.to_string(),
+            protocol: p.protocol.name().to_string(),
             country: p.country,
             country_code: p.country_code,
             anonymity: p.anonymity,
@@ -610,6 +710,7 @@ impl From<FreeProxy> for FreeProxyResponse {
             last_checked: p.last_checked,
             provider: p.provider,
             is_working: p.is_working,
+            username: p.username,
         }
     }
 }
@@ -628,6 +729,10 @@ pub struct FreeProxyRequest {
     pub last_checked: String,
     pub provider: String,
     pub is_working: bool,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
 }
 
 impl From<FreeProxyRequest> for FreeProxy {
@@ -635,13 +740,7 @@ impl From<FreeProxyRequest> for FreeProxy {
         Self {
             ip: r.ip,
             port: r.port,
-            protocol: match r.protocol.as_str() {
-                "http" => ProxyType::Http,
-                "https" => ProxyType::Https,
-                "socks4" => ProxyType::Socks4,
-                "socks5" => ProxyType::Socks5,
-                _ => ProxyType::Direct,
-            },
+            protocol: ProxyType::from_name(&r.protocol).unwrap_or_default(),
             country: r.country,
             country_code: r.country_code,
             anonymity: r.anonymity,
@@ -650,10 +749,22 @@ impl From<FreeProxyRequest> for FreeProxy {
             last_checked: r.last_checked,
             provider: r.provider,
             is_working: r.is_working,
+            username: r.username,
+            password: r.password,
+            ssh: None,
         }
     }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Represents a ProxyImportResponse.
+pub struct ProxyImportResponse {
+    pub format: ProxyListFormat,
+    pub imported: usize,
+    pub duplicates: usize,
+    pub errors: Vec<ImportError>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 /// Represents a ProxyTestResultResponse.
 pub struct ProxyTestResultResponse {
@@ -893,7 +1004,12 @@ impl From<browser_core::Bookmark> for BookmarkResponse {
     }
 }
 
-fn build_ip_generator() -> IPGenerator {
+/// Build the IP generator, and an index of the configured IP ranges so
+/// provider-supplied countries can be corrected.
+///
+/// The index is only built from real range data; the placeholder ranges the
+/// generator falls back to are never used to correct countries.
+fn build_ip_generator(geoip: Option<Arc<GeoIpDatabase>>) -> (IPGenerator, Option<Arc<IpRangeIndex>>) {
     let countries_path = std::env::var("COUNTRIES_PATH").ok();
     let ranges_path = std::env::var("IP_RANGES_PATH").ok();
 
@@ -903,16 +1019,42 @@ fn build_ip_generator() -> IPGenerator {
         .map(load_countries_from_file)
         .unwrap_or_else(CountryDatabase::load_all_countries);
 
-    let ranges = ranges_path
-        .as_deref()
-        .map(std::path::Path::new)
-        .map(load_ip_ranges_from_file)
-        .unwrap_or_else(load_ip_ranges);
+    let loaded = ranges_path.as_deref().and_then(|path| {
+        load_range_index(std::path::Path::new(path), &countries)
+            .map_err(|e| warn!("IP ranges in {} not loaded: {:#}", path, e))
+            .ok()
+    });
+    let (ranges, index) = match loaded {
+        Some((ranges, index)) => (ranges, Some(Arc::new(index))),
+        None => (load_ip_ranges(), None),
+    };
 
-    if countries.is_empty() || ranges.is_empty() {
+    let mut generator = if countries.is_empty() || ranges.is_empty() {
         demo_generator()
     } else {
         IPGenerator::new(countries, ranges)
+    };
+    if let Some(db) = geoip {
+        generator = generator.with_geoip_database(db);
+    }
+    (generator, index)
+}
+
+/// Open the offline GeoIP databases in `GEOIP_DB_DIR`, if set, and keep them refreshed
+fn load_geoip_database() -> Option<Arc<GeoIpDatabase>> {
+    let dir = std::env::var("GEOIP_DB_DIR").ok()?;
+    match GeoIpDatabase::open_dir(std::path::Path::new(&dir)) {
+        Ok(db) => {
+            let db = Arc::new(db);
+            tauri::async_runtime::block_on(async {
+                db.spawn_refresh_task(std::time::Duration::from_secs(3600));
+            });
+            Some(db)
+        }
+        Err(e) => {
+            warn!("GeoIP databases in {} not loaded: {}", dir, e);
+            None
+        }
     }
 }
 
@@ -942,15 +1084,93 @@ fn init_backup_manager(app_data_dir: &std::path::Path) -> Arc<BackupManager> {
     }
 }
 
+/// Load the config file named by `CONFIG_PATH`, or the defaults
+fn load_app_config() -> AppConfig {
+    let manager = match std::env::var("CONFIG_PATH") {
+        Ok(path) => ConfigManager::with_path(path),
+        Err(_) => ConfigManager::new(),
+    };
+    tauri::async_runtime::block_on(async {
+        if let Err(e) = manager.load().await {
+            warn!("Config not loaded, using defaults: {}", e);
+        }
+        manager.get().await
+    })
+}
+
 /// Spawn async task to fetch proxies on startup
-fn spawn_proxy_fetch_task(proxy_manager: Arc<ProxyManager>) {
+fn spawn_proxy_fetch_task(
+    proxy_manager: Arc<ProxyManager>,
+    provider_manager: Arc<tokio::sync::RwLock<FreeIpProviderManager>>,
+) {
     tauri::async_runtime::spawn(async move {
         info!("Fetching free proxies on startup...");
-        match proxy_manager.fetch_proxies().await {
-            Ok(count) => info!("Successfully fetched {} proxies", count),
-            Err(e) => error!("Failed to fetch free proxies on startup: {}", e),
+        let proxies = FreeIpProviderManager::fetch_shared(&provider_manager).await;
+        info!("Successfully fetched {} proxies", proxies.len());
+        proxy_manager.add_free_proxies(proxies.clone()).await;
+        // Tabs only bind to proxies the pool marks as working
+        let checked = FreeIpProviderManager::validate_shared(&provider_manager, proxies).await;
+        proxy_manager.record_checks(&checked).await;
+    });
+}
+
+/// Build the background validation scheduler over the proxy manager's pool
+fn build_validation_scheduler(
+    proxy_manager: &ProxyManager,
+    quarantine: Arc<ProxyQuarantineManager>,
+    geoip: Option<Arc<GeoIpDatabase>>,
+    range_index: Option<Arc<IpRangeIndex>>,
+) -> Arc<ValidationScheduler> {
+    // Working proxies are also checked for content injection and TLS interception
+    let mut scheduler = ValidationScheduler::new(
+        ProxyValidator::new(ProxyValidatorConfig::default()).with_tamper_checks(TamperCheckConfig::default()),
+        quarantine,
+        proxy_manager.proxy_pool(),
+        ValidationSchedulerConfig::default(),
+    );
+    match GeoVerifier::new(GeoVerificationConfig::from_env()) {
+        Ok(mut verifier) => {
+            if let Some(db) = geoip {
+                verifier = verifier.with_geoip_database(db);
+            }
+            if let Some(index) = range_index {
+                verifier = verifier.with_range_index(index);
+            }
+            scheduler = scheduler.with_geo_verifier(verifier);
         }
+        Err(e) => warn!("Geographic verification disabled: {}", e),
+    }
+    Arc::new(scheduler)
+}
+
+/// Observe tab exits through the proxy, locating them offline where possible
+fn build_exit_verifier(
+    geoip: Option<Arc<GeoIpDatabase>>,
+    range_index: Option<Arc<IpRangeIndex>>,
+) -> PublicIpExitVerifier {
+    let mut verifier = PublicIpExitVerifier::new();
+    if let Some(db) = geoip {
+        verifier = verifier.with_geoip_database(db);
+    }
+    if let Some(index) = range_index {
+        verifier = verifier.with_range_index(index);
+    }
+    verifier
+}
+
+/// Providers from the app config, or only the built-in ones if those fail to load
+fn build_provider_manager(config: &AppConfig) -> FreeIpProviderManager {
+    let manager = FreeIpProviderManager::from_config(&config.proxy).or_else(|e| {
+        error!("Proxy providers from the config not loaded, using the built-in providers: {}", e);
+        FreeIpProviderManager::new()
     });
+    match manager {
+        Ok(manager) => manager,
+        Err(e) => {
+            error!("No proxy providers available: {}", e);
+            std::process::exit(1);
+        }
+    }
 }
 
 /// Get app data directory with fallback to temp directory
@@ -962,9 +1182,32 @@ fn get_app_data_dir(app: &tauri::App) -> std::path::PathBuf {
 }
 
 fn main() {
-    let ip_generator = Arc::new(build_ip_generator());
+    let geoip = load_geoip_database();
+    let (ip_generator, range_index) = build_ip_generator(geoip.clone());
+    let ip_generator = Arc::new(ip_generator);
     let proxy_manager = Arc::new(ProxyManager::new());
     let browser_controller = Arc::new(BrowserController::new());
+    // Tampering caught in validation and credentials rejected in Chromium tabs share one quarantine
+    let quarantine = Arc::new(ProxyQuarantineManager::new(
+        3,
+        std::time::Duration::from_secs(300),
+        std::time::Duration::from_secs(24 * 3600),
+    ));
+    let validation_scheduler =
+        build_validation_scheduler(&proxy_manager, quarantine.clone(), geoip.clone(), range_index.clone());
+    let engine_manager = Arc::new(BrowserEngineManager::new().with_quarantine_manager(quarantine));
+    let config = load_app_config();
+    let mut free_ip_manager = build_provider_manager(&config);
+    if let Some(index) = &range_index {
+        free_ip_manager = free_ip_manager.with_range_index(index.clone());
+    }
+    let provider_manager = Arc::new(tokio::sync::RwLock::new(free_ip_manager));
+    // Browser tabs bind to verified exits from the pool the provider fetch fills
+    let tab_ips = TabIPManager::new(ip_generator.as_ref().clone()).with_exit_verifier(
+        proxy_manager.proxy_pool(),
+        Arc::new(build_exit_verifier(geoip, range_index)),
+        ExitBindingConfig::default(),
+    );
     
     tauri::Builder::default()
         .plugin(tauri_plugin_store::Builder::new().build())
@@ -975,6 +1218,14 @@ fn main() {
             // Initialize WebviewManager
             let webview_manager = WebviewManager::new(app.handle().clone());
             app.manage(webview_manager);
+
+            // Proxied browser tabs rotate through the shared provider pool and configured gateways
+            let tab_webviews = browser_core::WebviewManager::new(app.handle().clone())
+                .with_proxy_config(provider_manager.clone(), &config.proxy);
+            let browser_tabs = BrowserTabManager::new(ip_generator.as_ref().clone(), app.handle().clone())
+                .with_webview_manager(tab_webviews)
+                .with_tab_ip_manager(tab_ips);
+            app.manage(Arc::new(browser_tabs));
             
             // Get app data directory and initialize components
             let app_data_dir = get_app_data_dir(app);
@@ -982,7 +1233,7 @@ fn main() {
             let backup_manager = init_backup_manager(&app_data_dir);
             
             // Fetch free proxies on startup
-            spawn_proxy_fetch_task(proxy_manager.clone());
+            spawn_proxy_fetch_task(proxy_manager.clone(), provider_manager.clone());
             
             // Manage the app state
             app.manage(AppState {
@@ -991,6 +1242,9 @@ fn main() {
                 storage_engine,
                 backup_manager,
                 browser_controller,
+                validation_scheduler,
+                provider_manager,
+                engine_manager,
             });
             
             Ok(())
@@ -1005,6 +1259,25 @@ fn main() {
             get_webview_tabs,
             navigation_changed,
             title_changed,
+            // Browser tab commands
+            browser_core::browser_tab_manager::create_browser_tab,
+            browser_core::browser_tab_manager::close_browser_tab,
+            browser_core::browser_tab_manager::switch_browser_tab,
+            browser_core::browser_tab_manager::navigate_browser_tab,
+            browser_core::browser_tab_manager::browser_tab_go_back,
+            browser_core::browser_tab_manager::browser_tab_go_forward,
+            browser_core::browser_tab_manager::reload_browser_tab,
+            browser_core::browser_tab_manager::stop_browser_tab,
+            browser_core::browser_tab_manager::set_browser_tab_zoom,
+            browser_core::browser_tab_manager::rotate_browser_tab_ip,
+            browser_core::browser_tab_manager::get_browser_tabs,
+            browser_core::browser_tab_manager::get_browser_tab,
+            browser_core::browser_tab_manager::get_active_browser_tab,
+            browser_core::browser_tab_manager::execute_script_in_browser_tab,
+            browser_core::browser_tab_manager::clear_browser_tab_data,
+            browser_core::browser_tab_manager::get_browser_tab_stats,
+            browser_core::browser_tab_manager::update_webview_tab_state,
+            browser_core::browser_tab_manager::lint_browser_tab,
             // Tab commands
             create_tab,
             create_tab_random,
@@ -1026,6 +1299,16 @@ fn main() {
             test_proxy,
             clear_free_proxies,
             remove_dead_proxies,
+            get_ip_providers_status,
+            set_ip_provider_enabled,
+            import_proxy_list,
+            export_proxy_list,
+            // Background validation
+            start_validation_scheduler,
+            stop_validation_scheduler,
+            get_validation_scheduler_status,
+            benchmark_proxy,
+            set_proxy_validation_priority,
             // Backup
             create_backup,
             list_backups,
@@ -1,8 +1,9 @@
 import { invoke } from '@tauri-apps/api/tauri';
 import { logDebug } from './logger';
 import type { 
-  Tab, WebviewTab, VirtualIPResponse, ValidationResponse, Country,
+  Tab, WebviewTab, VirtualIPResponse, ValidationResponse, LintReport, Country,
   ProxySettings, FreeProxy, ProxyTestResult, PublicIpInfo,
+  ValidationPriority, ValidationSchedulerStatus, ThroughputResult, ProxyListFormat, ProxyImportResult, IpProviderStats,
   BackupOptions, BackupInfo, BrowserState, BrowserSettings, EnterpriseUserData,
   HistoryEntry, Bookmark, User, ProxyProviderConfig
 } from './types';
@@ -126,6 +127,10 @@ export async function validateIp(tabId: string): Promise<ValidationResponse> {
   return invoke('validate_ip', { tabId });
 }
 
+export async function lintBrowserTab(tabId: string): Promise<LintReport> {
+  return invoke('lint_browser_tab', { tabId });
+}
+
 // Proxy Management
 export async function getProxySettings(): Promise<ProxySettings> {
   return invoke('get_proxy_settings');
@@ -169,6 +174,43 @@ export async function removeDeadProxies(): Promise<void> {
   return invoke('remove_dead_proxies');
 }
 
+export async function getIpProvidersStatus(): Promise<IpProviderStats[]> {
+  return invoke('get_ip_providers_status');
+}
+
+export async function setIpProviderEnabled(name: string, enabled: boolean): Promise<void> {
+  return invoke('set_ip_provider_enabled', { name, enabled });
+}
+
+export async function importProxyList(content: string, format?: ProxyListFormat): Promise<ProxyImportResult> {
+  return invoke('import_proxy_list', { content, format: format ?? null });
+}
+
+export async function exportProxyList(format: ProxyListFormat, workingOnly: boolean): Promise<string> {
+  return invoke('export_proxy_list', { format, workingOnly });
+}
+
+// Background Validation
+export async function startValidationScheduler(): Promise<ValidationSchedulerStatus> {
+  return invoke('start_validation_scheduler');
+}
+
+export async function stopValidationScheduler(): Promise<ValidationSchedulerStatus> {
+  return invoke('stop_validation_scheduler');
+}
+
+export async function getValidationSchedulerStatus(): Promise<ValidationSchedulerStatus> {
+  return invoke('get_validation_scheduler_status');
+}
+
+export async function benchmarkProxy(proxy: FreeProxy): Promise<ThroughputResult> {
+  return invoke('benchmark_proxy', { proxy });
+}
+
+export async function setProxyValidationPriority(proxy: FreeProxy, priority: ValidationPriority): Promise<void> {
+  return invoke('set_proxy_validation_priority', { proxy, priority });
+}
+
 // Backup & Restore
 export async function createBackup(options: BackupOptions): Promise<BackupInfo> {
   return invoke('create_backup', { options });
@@ -29,6 +29,19 @@ export type ValidationResponse = {
   overall_pass: boolean;
 };
 
+export type LintSeverity = 'Info' | 'Warning' | 'Error';
+
+export type LintFinding = {
+  rule: string;
+  severity: LintSeverity;
+  message: string;
+  fix: { field: string; value: string } | null;
+};
+
+export type LintReport = {
+  findings: LintFinding[];
+};
+
 export type User = {
   id: string;
   username: string;
@@ -47,20 +60,27 @@ export type Country = {
   is_top: boolean;
 };
 
+export type SshOptions = {
+  private_key_path: string | null;
+  private_key_passphrase: string | null;
+  known_hosts_path: string | null;
+};
+
 export type ProxySettings = {
-  proxy_type: 'direct' | 'http' | 'https' | 'socks4' | 'socks5';
+  proxy_type: 'direct' | 'http' | 'https' | 'socks4' | 'socks5' | 'ssh' | 'shadowsocks';
   host: string | null;
   port: number | null;
   username: string | null;
   password: string | null;
   dns_servers: string[];
   bypass_list: string[];
+  ssh?: SshOptions | null;
 };
 
 export type FreeProxy = {
   ip: string;
   port: number;
-  protocol: 'http' | 'https' | 'socks4' | 'socks5' | 'direct';
+  protocol: 'http' | 'https' | 'socks4' | 'socks5' | 'ssh' | 'shadowsocks' | 'direct';
   country: string;
   country_code: string;
   anonymity: string;
@@ -69,6 +89,7 @@ export type FreeProxy = {
   last_checked: string;
   provider: string;
   is_working: boolean;
+  username?: string;
 };
 
 export type ProxyTestResult = {
@@ -79,6 +100,76 @@ export type ProxyTestResult = {
   error: string | null;
 };
 
+export type IpProviderStats = {
+  name: string;
+  enabled: boolean;
+  disabled_reason: string | null;
+  last_fetch_at: string | null;
+  last_success_at: string | null;
+  freshness_secs: number | null;
+  consecutive_failures: number;
+  last_error: string | null;
+  last_fetched: number;
+  last_duplicates: number;
+  total_fetched: number;
+  total_duplicates: number;
+  tested: number;
+  working: number;
+  working_ratio: number;
+  duplicate_ratio: number;
+};
+
+export type ProxyListFormat = 'host_port' | 'url' | 'csv' | 'clash' | 'surge' | 'sip008';
+
+export type ProxyImportError = {
+  line: number;
+  content: string;
+  message: string;
+};
+
+export type ProxyImportResult = {
+  format: ProxyListFormat;
+  imported: number;
+  duplicates: number;
+  errors: ProxyImportError[];
+};
+
+export type ValidationPriority = 'critical' | 'leased' | 'normal';
+
+export type ValidationSchedulerStatus = {
+  running: boolean;
+  started_at: string | null;
+  tracked_proxies: number;
+  due_proxies: number;
+  failing_proxies: number;
+  dead_proxies: number;
+  quarantined_proxies: number;
+  next_check_at: string | null;
+  checks_last_minute: number;
+  max_checks_per_minute: number;
+  total_checks: number;
+  total_passed: number;
+  total_failed: number;
+  deferred_checks: number;
+};
+
+export type ThroughputSample = {
+  ttfb_ms: number;
+  bytes: number;
+  transfer_ms: number;
+  mbps: number;
+};
+
+export type ThroughputResult = {
+  download_mbps: number;
+  upload_mbps: number | null;
+  ttfb_ms: number;
+  jitter_ms: number;
+  download_samples: ThroughputSample[];
+  upload_samples: ThroughputSample[];
+  measured_at: string;
+};
+
 export type PublicIpInfo = {
   ip: string;
   country: string | null;